@@ -0,0 +1,67 @@
+//! `/tick` integration: the scalar loop against `wide` lane blocks.
+//!
+//! Setup stays outside the timed closures; each iteration is one step of
+//! an already-built population.
+
+use api::physics::{step_scalar, EntityBatch};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use spec::{InputState, Vec3};
+
+/// Every input combination, with spread-out starting poses.
+fn entities(count: usize) -> Vec<(Vec3, Vec3, InputState)> {
+    (0..count)
+        .map(|i| {
+            let bits = i % 64;
+            let input = InputState {
+                forward: bits & 1 != 0,
+                back: bits & 2 != 0,
+                right: bits & 4 != 0,
+                left: bits & 8 != 0,
+                jump: bits & 16 != 0,
+                sneak: bits & 32 != 0,
+            };
+            let f = i as f64;
+            let pos = Vec3 {
+                x: f * 0.37,
+                y: 64.0 + f * 0.01,
+                z: -f * 1.3,
+            };
+            let vel = Vec3 {
+                x: (f * 0.11).sin(),
+                y: -0.2,
+                z: (f * 0.07).cos(),
+            };
+            (pos, vel, input)
+        })
+        .collect()
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick_step");
+    for count in [64, 1024, 4096, 16384] {
+        let mut scalar = entities(count);
+        group.bench_function(BenchmarkId::new("scalar", count), |b| {
+            b.iter(|| {
+                for (pos, vel, input) in &mut scalar {
+                    step_scalar(pos, vel, input);
+                }
+                black_box(&scalar);
+            })
+        });
+
+        let mut batch = EntityBatch::with_capacity(count);
+        for (pos, vel, input) in &entities(count) {
+            batch.push(*pos, *vel, input);
+        }
+        group.bench_function(BenchmarkId::new("lane_blocks", count), |b| {
+            b.iter(|| {
+                batch.step();
+                black_box(&batch);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
@@ -0,0 +1,3 @@
+//! Library half of the API server, so benches can reach its internals.
+
+pub mod physics;
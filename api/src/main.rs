@@ -1,3 +1,9 @@
+mod planets;
+mod shed;
+mod signing;
+mod view_cache;
+
+use api::physics::EntityBatch;
 use axum::{
     extract::State,
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
@@ -6,11 +12,15 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use dlog_error::StorageError;
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use shed::{limited, ShedStats, TickLimit};
+use signing::{signed, ResponseSigner};
 use spec::{
-    Anchor, Barrier, InputState, MonetarySpec, PlanetGravityProfile, Pose, RenderEntity, SimTickRequest,
-    SimTickResponse, SimView, UiOverlay, Vec3, PLANET_PROFILES, PHI,
+    Anchor, Barrier, ClockSyncRequest, ClockSyncResponse, InputState, MonetarySpec,
+    PlanetGravityProfile, PlanetId, Pose, RenderEntity, ResponseKeySet, SimTickRequest,
+    SimTickResponse, SimView, UiOverlay, Vec3, ViewMask, PLANET_PROFILES, PHI,
 };
 use std::{
     net::SocketAddr,
@@ -24,11 +34,17 @@ use tokio::{
     time::timeout,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use view_cache::{pose_moved, ViewCache, ViewCacheStats, ViewKey};
 
 #[derive(Clone)]
 struct AppState {
     paper_addr: SocketAddr,
     sim_state_path: Arc<PathBuf>,
+    view_cache: Arc<ViewCache>,
+    sim_tick_limit: Arc<TickLimit>,
+    tick_limit: Arc<TickLimit>,
+    /// Signs `/v1/sim/tick` responses; `None` leaves them unsigned.
+    signer: Option<Arc<ResponseSigner>>,
 }
 
 impl AppState {
@@ -50,7 +66,31 @@ impl AppState {
         Self {
             paper_addr,
             sim_state_path: Arc::new(sim_state_path),
+            view_cache: Arc::new(ViewCache::default()),
+            sim_tick_limit: TickLimit::from_env("/v1/sim/tick", "SIM_TICK_MAX_CONCURRENCY", 64),
+            tick_limit: TickLimit::from_env("/tick", "TICK_MAX_CONCURRENCY", 32),
+            signer: ResponseSigner::from_env()
+                .expect("valid OMEGA_RESPONSE_SIGNING_KEY")
+                .map(Arc::new),
+        }
+    }
+
+    /// Sim state file for `planet`: Earth keeps `SIM_STATE_PATH`, other planets
+    /// get a sibling `<stem>-<planet>.<ext>` file.
+    fn sim_state_path_for(&self, planet: PlanetId) -> PathBuf {
+        let base = self.sim_state_path.as_ref();
+        if planet == PlanetId::Earth {
+            return base.clone();
         }
+        let stem = base
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "omega-sim-state".to_string());
+        let name = match base.extension() {
+            Some(ext) => format!("{stem}-{planet}.{}", ext.to_string_lossy()),
+            None => format!("{stem}-{planet}"),
+        };
+        base.with_file_name(name)
     }
 }
 
@@ -66,11 +106,22 @@ async fn main() {
         .route("/v1/hypercube/summary", get(hypercube))
         .route("/v1/spec/monetary", get(monetary))
         .route("/v1/spec/planets", get(planets))
+        .route("/v1/planets/:key/gravity-field", get(gravity_field))
         .route("/v1/paper/status", get(paper_status))
         .route("/ws/paper", get(ws_paper))
-        .route("/v1/sim/tick", post(sim_tick))
+        .route(
+            "/v1/sim/tick",
+            signed(
+                limited(post(sim_tick), state.sim_tick_limit.clone()),
+                state.signer.clone(),
+            ),
+        )
+        .route("/v1/sim/keys", get(sim_keys))
+        .route("/v1/sim/clock", post(sim_clock))
+        .route("/v1/sim/view-cache", get(view_cache_stats))
+        .route("/v1/metrics", get(metrics))
         // Bridge for the Minecraft plugin → Rust control loop.
-        .route("/tick", post(tick))
+        .route("/tick", limited(post(tick), state.tick_limit.clone()))
         .with_state(state.clone());
 
     // 8888 here is just a human-friendly port; underneath it's all bits anyway.
@@ -105,8 +156,12 @@ async fn root(State(state): State<AppState>) -> Json<serde_json::Value> {
             "/v1/hypercube/summary",
             "/v1/spec/monetary",
             "/v1/spec/planets",
+            "/v1/planets/{key}/gravity-field",
             "/v1/paper/status",
             "/v1/sim/tick",
+            "/v1/sim/keys",
+            "/v1/sim/view-cache",
+            "/v1/metrics",
             "/ws/paper",
             "/tick"
         ]
@@ -145,6 +200,74 @@ async fn planets() -> Json<PlanetsResponse> {
     })
 }
 
+#[derive(Debug, Deserialize)]
+struct GravityFieldQuery {
+    /// Number of radial samples (clamped to 2..=1024).
+    #[serde(default = "default_gravity_samples")]
+    samples: usize,
+    /// Sample out to this many shell radii.
+    #[serde(default = "default_gravity_extent")]
+    extent: f64,
+}
+
+fn default_gravity_samples() -> usize {
+    64
+}
+
+fn default_gravity_extent() -> f64 {
+    2.0
+}
+
+#[derive(Debug, Serialize)]
+struct GravitySample {
+    radius_m: f64,
+    gravity_mps2: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct GravityFieldResponse {
+    key: &'static str,
+    phi_exp: f64,
+    surface_gravity_mps2: f64,
+    shell_radius_m: f64,
+    core_radius_m: f64,
+    samples: Vec<GravitySample>,
+}
+
+/// Radial gravity profile for one planet, from the center out to `extent` shell radii.
+async fn gravity_field(
+    axum::extract::Path(key): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<GravityFieldQuery>,
+) -> Result<Json<GravityFieldResponse>, StatusCode> {
+    let profile = PlanetGravityProfile::by_key(&key).ok_or(StatusCode::NOT_FOUND)?;
+    let count = query.samples.clamp(2, 1024);
+    let extent = if query.extent.is_finite() {
+        query.extent.clamp(0.1, 64.0)
+    } else {
+        default_gravity_extent()
+    };
+    let max_radius = profile.shell_radius_m * extent;
+
+    let samples = (0..count)
+        .map(|i| {
+            let radius_m = max_radius * i as f64 / (count - 1) as f64;
+            GravitySample {
+                radius_m,
+                gravity_mps2: profile.gravity_at(radius_m),
+            }
+        })
+        .collect();
+
+    Ok(Json(GravityFieldResponse {
+        key: profile.key,
+        phi_exp: profile.phi_exp,
+        surface_gravity_mps2: profile.surface_gravity_mps2,
+        shell_radius_m: profile.shell_radius_m,
+        core_radius_m: profile.core_radius_m,
+        samples,
+    }))
+}
+
 // === Hypercube summary ===
 
 #[derive(Serialize)]
@@ -285,185 +408,246 @@ async fn tick(headers: HeaderMap, axum::extract::Json(req): axum::extract::Json<
         }
     }
 
-    // Simple physics step tuned for the Ω bridge. Replace with richer φ-based logic later.
-    const DT: f64 = 0.05; // 20 ticks/sec
-    const ACCEL: f64 = 0.08;
-    const JUMP_SPEED: f64 = 0.32;
-    const GRAVITY: f64 = 0.08;
-
-    let mut updates = Vec::with_capacity(req.entities.len());
-
-    for mut e in req.entities {
-        // Apply input -> velocity
-        if e.input.forward {
-            e.vel.z += ACCEL;
-        }
-        if e.input.back {
-            e.vel.z -= ACCEL;
-        }
-        if e.input.right {
-            e.vel.x += ACCEL;
-        }
-        if e.input.left {
-            e.vel.x -= ACCEL;
-        }
-        if e.input.jump {
-            // naive jump; real impl should check ground contact
-            e.vel.y = JUMP_SPEED;
-        }
-        if e.input.sneak {
-            e.vel.y -= ACCEL * 0.5;
-        }
-
-        // Gravity
-        e.vel.y -= GRAVITY * DT;
-
-        // Integrate
-        e.pos.x += e.vel.x * DT;
-        e.pos.y += e.vel.y * DT;
-        e.pos.z += e.vel.z * DT;
-
-        updates.push(EntityUpdate {
-            armor_stand_id: e.armor_stand_id,
-            pos: e.pos,
-            vel: e.vel,
-        });
+    // Simple physics step tuned for the Ω bridge, integrated in lane blocks.
+    let mut batch = EntityBatch::with_capacity(req.entities.len());
+    for e in &req.entities {
+        batch.push(e.pos, e.vel, &e.input);
     }
+    batch.step();
+    let updates = req
+        .entities
+        .into_iter()
+        .zip(batch.iter())
+        .map(|(e, (pos, vel))| EntityUpdate {
+            armor_stand_id: e.armor_stand_id,
+            pos,
+            vel,
+        })
+        .collect();
 
     Ok(Json(TickResponse { updates }))
 }
 
 // === Ω sim tick (Cloud Run + GCS bucket state) ===
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 struct SimState {
     tick: u64,
+    /// Bumped whenever any player's pose materially changes; keys the view cache.
+    #[serde(default)]
+    world_version: u64,
     players: Vec<PlayerSnapshot>,
 }
 
-impl Default for SimState {
-    fn default() -> Self {
-        Self {
-            tick: 0,
-            players: Vec::new(),
-        }
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 struct PlayerSnapshot {
     player_id: String,
     pose: Pose,
     last_inputs: InputState,
+    /// Pose at the last world-version bump. Movement is measured from here,
+    /// so sub-epsilon steps can't add up unseen.
+    #[serde(default)]
+    versioned_pose: Option<Pose>,
+    /// Server time the last applied inputs happened at; older ticks that
+    /// arrive late don't overwrite them.
+    #[serde(default)]
+    input_time_ms: u64,
+}
+
+/// How far back a client-stamped tick may be placed. Anything older is
+/// treated as arriving this late, so a bad offset can't rewind a player.
+const MAX_LAG_COMPENSATION_MS: u64 = 250;
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Place a tick's inputs on the server clock: the client's send time shifted
+/// by its clock offset, clamped to the lag-compensation window. Without both
+/// it's the time the tick was received.
+fn input_time_ms(req: &SimTickRequest, received_ms: u64) -> u64 {
+    match (req.client_time_ms, req.clock_offset_ms) {
+        (Some(client_ms), Some(offset_ms)) => client_ms.saturating_add_signed(offset_ms).clamp(
+            received_ms.saturating_sub(MAX_LAG_COMPENSATION_MS),
+            received_ms,
+        ),
+        _ => received_ms,
+    }
+}
+
+/// `POST /v1/sim/clock`: one φ-NTP exchange, see [`spec::ClockSync`].
+async fn sim_clock(Json(req): Json<ClockSyncRequest>) -> Json<ClockSyncResponse> {
+    let server_receive_ms = now_ms();
+    Json(ClockSyncResponse {
+        client_send_ms: req.client_send_ms,
+        server_receive_ms,
+        server_transmit_ms: now_ms(),
+    })
+}
+
+/// `GET /v1/sim/keys`: the keys tick responses are signed with.
+async fn sim_keys(State(state): State<AppState>) -> Json<ResponseKeySet> {
+    Json(
+        state
+            .signer
+            .as_ref()
+            .map(|signer| signer.key_set().clone())
+            .unwrap_or_default(),
+    )
 }
 
 async fn sim_tick(
     State(state): State<AppState>,
     Json(req): Json<SimTickRequest>,
 ) -> Result<Json<SimTickResponse>, StatusCode> {
-    let mut sim = read_sim_state(&state.sim_state_path)
+    let input_time_ms = input_time_ms(&req, now_ms());
+    let sim_state_path = state.sim_state_path_for(req.planet);
+    let mut sim = read_sim_state(&sim_state_path)
         .await
         .map_err(|err| {
             tracing::warn!("[sim] failed to read state: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
+            StatusCode::from(err)
         })?;
 
     sim.tick = sim.tick.wrapping_add(1);
-    upsert_player(&mut sim, &req);
+    upsert_player(&mut sim, &req, input_time_ms);
 
-    write_sim_state(&state.sim_state_path, &sim)
+    write_sim_state(&sim_state_path, &sim)
         .await
         .map_err(|err| {
             tracing::warn!("[sim] failed to write state: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
+            StatusCode::from(err)
         })?;
 
-    let view = build_view(&sim, &req);
-    let server_time_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .unwrap_or(0);
+    let key = ViewKey::new(req.planet, sim.tick, sim.world_version, req.view_mask);
+    let view = state
+        .view_cache
+        .get_or_build(key, &req, || build_view(&sim, &req));
 
     Ok(Json(SimTickResponse {
         tick: sim.tick,
         state_version: format!("tick-{}", sim.tick),
-        server_time_ms,
+        server_time_ms: now_ms(),
+        input_time_ms,
         view,
     }))
 }
 
-fn upsert_player(sim: &mut SimState, req: &SimTickRequest) {
+async fn view_cache_stats(State(state): State<AppState>) -> Json<ViewCacheStats> {
+    Json(state.view_cache.stats())
+}
+
+#[derive(Serialize)]
+struct MetricsResponse {
+    view_cache: ViewCacheStats,
+    load_shed: Vec<ShedStats>,
+}
+
+async fn metrics(State(state): State<AppState>) -> Json<MetricsResponse> {
+    Json(MetricsResponse {
+        view_cache: state.view_cache.stats(),
+        load_shed: vec![state.sim_tick_limit.stats(), state.tick_limit.stats()],
+    })
+}
+
+fn upsert_player(sim: &mut SimState, req: &SimTickRequest, input_time_ms: u64) {
     if let Some(existing) = sim
         .players
         .iter_mut()
         .find(|p| p.player_id == req.player_id)
     {
+        if input_time_ms < existing.input_time_ms {
+            return;
+        }
+        existing.input_time_ms = input_time_ms;
+        if pose_moved(&existing.versioned_pose.unwrap_or(existing.pose), &req.pose) {
+            sim.world_version = sim.world_version.wrapping_add(1);
+            existing.versioned_pose = Some(req.pose);
+        }
         existing.pose = req.pose;
         existing.last_inputs = req.inputs.clone();
         return;
     }
 
+    sim.world_version = sim.world_version.wrapping_add(1);
     sim.players.push(PlayerSnapshot {
         player_id: req.player_id.clone(),
         pose: req.pose,
         last_inputs: req.inputs.clone(),
+        versioned_pose: Some(req.pose),
+        input_time_ms,
     });
 }
 
 fn build_view(sim: &SimState, req: &SimTickRequest) -> SimView {
     let mut view = SimView::default();
+    let mask = req.view_mask;
 
-    view.anchors.push(Anchor {
-        id: "omega-root".to_string(),
-        kind: "origin".to_string(),
-        pos: Vec3 { x: 0.0, y: 64.0, z: 0.0 },
-    });
-
-    for player in &sim.players {
-        view.entities.push(RenderEntity {
-            id: format!("player-{}", player.player_id),
-            kind: "player-shadow".to_string(),
-            pos: player.pose.pos,
-            yaw: player.pose.yaw,
-            pitch: player.pose.pitch,
+    if mask.contains(ViewMask::ANCHORS) {
+        view.anchors.push(Anchor {
+            id: "omega-root".to_string(),
+            kind: "origin".to_string(),
+            pos: Vec3 { x: 0.0, y: 64.0, z: 0.0 },
         });
+        view.anchors.extend(planets::planet_anchors(req.pose.pos));
+    }
+
+    if mask.contains(ViewMask::ENTITIES) {
+        for player in &sim.players {
+            view.entities.push(RenderEntity {
+                id: format!("player-{}", player.player_id),
+                kind: "player-shadow".to_string(),
+                pos: player.pose.pos,
+                yaw: player.pose.yaw,
+                pitch: player.pose.pitch,
+            });
+        }
     }
 
     // Minimal barrier hint at spawn platform; clients can render a 3x3 pad.
-    view.barriers.push(Barrier {
-        min: Vec3 { x: -1.0, y: 64.0, z: -1.0 },
-        max: Vec3 { x: 1.0, y: 64.0, z: 1.0 },
-    });
+    if mask.contains(ViewMask::BARRIERS) {
+        view.barriers.push(Barrier {
+            min: Vec3 { x: -1.0, y: 64.0, z: -1.0 },
+            max: Vec3 { x: 1.0, y: 64.0, z: 1.0 },
+        });
+    }
 
-    view.ui = UiOverlay {
-        title: "Ω void terminal".to_string(),
-        hotbar: vec![
-            "You are in the shared Ω simulation".to_string(),
-            format!("Tick {}", sim.tick),
-            format!("You reported: ({:.2},{:.2},{:.2})", req.pose.pos.x, req.pose.pos.y, req.pose.pos.z),
-        ],
-    };
+    if mask.contains(ViewMask::UI) {
+        view.ui = UiOverlay {
+            title: "Ω void terminal".to_string(),
+            hotbar: vec![
+                "You are in the shared Ω simulation".to_string(),
+                format!("Tick {}", sim.tick),
+                format!("You reported: ({:.2},{:.2},{:.2})", req.pose.pos.x, req.pose.pos.y, req.pose.pos.z),
+            ],
+            ..Default::default()
+        };
+    }
 
     view
 }
 
-async fn read_sim_state(path: &PathBuf) -> Result<SimState, std::io::Error> {
+async fn read_sim_state(path: &PathBuf) -> Result<SimState, StorageError> {
     match tokio::fs::read(path).await {
         Ok(bytes) => {
             let parsed = serde_json::from_slice::<SimState>(&bytes).unwrap_or_default();
             Ok(parsed)
         }
         Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(SimState::default()),
-        Err(err) => Err(err),
+        Err(err) => Err(err.into()),
     }
 }
 
-async fn write_sim_state(path: &PathBuf, sim: &SimState) -> Result<(), std::io::Error> {
+async fn write_sim_state(path: &PathBuf, sim: &SimState) -> Result<(), StorageError> {
     if let Some(parent) = path.parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
-    let data = serde_json::to_vec_pretty(sim).unwrap();
-    tokio::fs::write(path, data).await
+    let data = serde_json::to_vec_pretty(sim)?;
+    tokio::fs::write(path, data).await?;
+    Ok(())
 }
 
 // === Paper shim (HTTP/WS bridge) ===
@@ -563,6 +747,10 @@ mod tests {
         AppState {
             paper_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 25565)),
             sim_state_path: Arc::new(path),
+            view_cache: Arc::new(ViewCache::default()),
+            sim_tick_limit: TickLimit::new("/v1/sim/tick", 64),
+            tick_limit: TickLimit::new("/tick", 32),
+            signer: None,
         }
     }
 
@@ -603,4 +791,234 @@ mod tests {
         assert_eq!(disk_state.tick, 2);
         assert_eq!(disk_state.players.len(), 1);
     }
+
+    #[tokio::test]
+    async fn view_mask_skips_unwanted_sections() {
+        let dir = tempdir().unwrap();
+        let state = test_state(dir.path().join("sim.json"));
+        let tick = |view_mask| SimTickRequest {
+            player_id: "player-1".to_string(),
+            view_mask,
+            ..Default::default()
+        };
+
+        let ui_only = sim_tick(State(state.clone()), Json(tick(ViewMask::UI)))
+            .await
+            .expect("ok")
+            .0
+            .view;
+        assert!(ui_only.anchors.is_empty() && ui_only.entities.is_empty());
+        assert!(ui_only.barriers.is_empty());
+        assert!(!ui_only.ui.hotbar.is_empty());
+
+        // Same tick bucket, different mask: the cached UI-only view is not reused.
+        let mask = ViewMask::ANCHORS | ViewMask::ENTITIES;
+        let world = sim_tick(State(state.clone()), Json(tick(mask)))
+            .await
+            .expect("ok")
+            .0
+            .view;
+        assert!(!world.anchors.is_empty() && world.entities.len() == 1);
+        assert!(world.barriers.is_empty() && world.ui.hotbar.is_empty());
+
+        let legacy: SimTickRequest = serde_json::from_str(r#"{"player_id":"p"}"#).unwrap();
+        assert_eq!(legacy.view_mask, ViewMask::ALL);
+    }
+
+    /// Drive a scripted session through the cache and check every served view
+    /// against a fresh build: entities must agree within the pose epsilon.
+    #[tokio::test]
+    async fn cached_views_match_fresh_builds() {
+        let dir = tempdir().unwrap();
+        let state = test_state(dir.path().join("sim.json"));
+
+        let steps: &[(&str, f64, bool)] = &[
+            ("a", 0.0, false),
+            ("a", 0.001, false), // jitter below epsilon -> hit
+            ("a", 0.002, false),
+            ("b", 5.0, false), // new player bumps world version -> miss
+            ("a", 0.003, false),
+            ("a", 0.003, true), // inputs changed -> miss
+            ("a", 2.0, true),   // moved -> miss
+            ("a", 2.0, true), // tick 8 opens a new bucket -> miss
+        ];
+
+        for (player, x, jump) in steps {
+            let req = SimTickRequest {
+                player_id: player.to_string(),
+                pose: Pose {
+                    pos: Vec3 { x: *x, y: 64.0, z: 0.0 },
+                    ..Default::default()
+                },
+                inputs: InputState {
+                    jump: *jump,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let served = sim_tick(State(state.clone()), Json(req.clone()))
+                .await
+                .expect("ok")
+                .0;
+            let sim = read_sim_state(&state.sim_state_path).await.unwrap();
+            let fresh = build_view(&sim, &req);
+
+            assert_eq!(served.view.entities.len(), fresh.entities.len());
+            for (a, b) in served.view.entities.iter().zip(&fresh.entities) {
+                assert_eq!(a.id, b.id);
+                assert!((a.pos.x - b.pos.x).abs() <= view_cache::POSE_EPSILON);
+            }
+        }
+
+        let stats = state.view_cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 6);
+    }
+
+    #[test]
+    fn sub_epsilon_drift_still_bumps_world_version() {
+        let mut sim = SimState::default();
+        let step = |sim: &mut SimState, x: f64| {
+            let req = SimTickRequest {
+                player_id: "a".into(),
+                pose: Pose {
+                    pos: Vec3 { x, y: 64.0, z: 0.0 },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            upsert_player(sim, &req, 0);
+            sim.world_version
+        };
+        let joined = step(&mut sim, 0.0);
+        assert_eq!(step(&mut sim, 0.006), joined);
+        // Each step is below epsilon, but the total since the last bump is not.
+        assert_eq!(step(&mut sim, 0.012), joined + 1);
+        assert_eq!(step(&mut sim, 0.018), joined + 1);
+    }
+
+    #[tokio::test]
+    async fn clock_offset_places_inputs_and_drops_late_ticks() {
+        // A client whose clock runs 5 s behind, 40 ms from the server each way.
+        let skew = 5_000;
+        let mut clock = spec::ClockSync::default();
+        for _ in 0..3 {
+            let sent = now_ms() - skew - 40;
+            let reply = sim_clock(Json(ClockSyncRequest {
+                client_send_ms: sent,
+            }))
+            .await
+            .0;
+            let sample = clock.record(&reply, reply.server_transmit_ms - skew + 40);
+            assert!((sample.offset_ms - skew as i64).abs() <= 2);
+        }
+        let offset = clock.offset_ms().unwrap();
+        assert!((offset - skew as i64).abs() <= 2);
+        assert!(clock.rtt_ms().unwrap() >= 80);
+
+        let dir = tempdir().unwrap();
+        let state = test_state(dir.path().join("sim.json"));
+        let tick = |x: f64, client_ms: u64| SimTickRequest {
+            player_id: "p".into(),
+            pose: Pose {
+                pos: Vec3 { x, y: 64.0, z: 0.0 },
+                ..Default::default()
+            },
+            client_time_ms: Some(client_ms),
+            clock_offset_ms: Some(offset),
+            ..Default::default()
+        };
+
+        let now_client = now_ms() - skew;
+        let fresh = sim_tick(State(state.clone()), Json(tick(1.0, now_client - 100)))
+            .await
+            .unwrap()
+            .0;
+        assert!(fresh.server_time_ms - fresh.input_time_ms >= 90);
+        assert!(fresh.server_time_ms - fresh.input_time_ms <= MAX_LAG_COMPENSATION_MS);
+
+        // Sent before the tick above but delivered after it: the view still
+        // comes back, the player's pose doesn't rewind.
+        let late = sim_tick(State(state.clone()), Json(tick(9.0, now_client - 200)))
+            .await
+            .unwrap()
+            .0;
+        assert!(late.input_time_ms < fresh.input_time_ms);
+        let sim = read_sim_state(&state.sim_state_path).await.unwrap();
+        assert_eq!(sim.players[0].pose.pos.x, 1.0);
+
+        // A wildly stale stamp is held to the compensation window.
+        let req = tick(2.0, now_client - 60_000);
+        let received = now_ms();
+        assert_eq!(
+            input_time_ms(&req, received),
+            received - MAX_LAG_COMPENSATION_MS
+        );
+    }
+
+    #[tokio::test]
+    async fn gravity_field_follows_hollow_shell_profile() {
+        let query = GravityFieldQuery {
+            samples: 5,
+            extent: 2.0,
+        };
+        let field = gravity_field(
+            axum::extract::Path("Earth".to_string()),
+            axum::extract::Query(query),
+        )
+        .await
+        .expect("earth exists")
+        .0;
+
+        assert_eq!(field.phi_exp, 1.0);
+        let g: Vec<f64> = field.samples.iter().map(|s| s.gravity_mps2).collect();
+        // center (cavity), 0.5R (cavity), R (surface), 1.5R, 2R
+        assert_eq!(g[0], 0.0);
+        assert_eq!(g[1], 0.0);
+        assert!((g[2] - 9.806_65).abs() < 1e-9);
+        assert!((g[4] - 9.806_65 / 4.0).abs() < 1e-9);
+
+        let missing = gravity_field(
+            axum::extract::Path("pluto".to_string()),
+            axum::extract::Query(GravityFieldQuery {
+                samples: 8,
+                extent: 1.0,
+            }),
+        )
+        .await;
+        assert_eq!(missing.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn saturated_tick_route_sheds_with_retry_after() {
+        use axum::{body::Body, http::Request};
+        use tower::ServiceExt;
+
+        let limit = TickLimit::new("/slow", 1);
+        let app: Router = Router::new().route(
+            "/slow",
+            limited(
+                post(|| async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    "done"
+                }),
+                limit.clone(),
+            ),
+        );
+        let request = || Request::post("/slow").body(Body::empty()).unwrap();
+
+        let first = tokio::spawn(app.clone().oneshot(request()));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let shed = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()["retry-after"], "1");
+
+        assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            app.oneshot(request()).await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(limit.stats().shed, 1);
+    }
 }
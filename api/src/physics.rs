@@ -0,0 +1,240 @@
+//! Entity integration for `/tick` in struct-of-arrays lane blocks.
+//!
+//! Entities are packed [`LANES`] to a block, one array per component, and
+//! each block steps as `wide::f64x4` vectors on the stable toolchain (no
+//! `std::simd`). Input flags become per-lane blend masks instead of
+//! branches. The vectors are `f64x4` rather than `f32x8`: positions are
+//! `spec::Vec3` (`f64`), and each lane runs the exact operation sequence
+//! of [`step_scalar`], so results are bit-for-bit the same. Two `f64x4`
+//! halves keep the block at eight entities, the same 256-bit-per-half
+//! width an `f32x8` would have. `cargo bench -p api --bench physics`
+//! times both paths.
+
+use spec::{InputState, Vec3};
+use wide::f64x4;
+
+/// Seconds per `/tick` (20 ticks/sec).
+pub const DT: f64 = 0.05;
+pub const ACCEL: f64 = 0.08;
+pub const JUMP_SPEED: f64 = 0.32;
+pub const GRAVITY: f64 = 0.08;
+/// Entities per lane block: two `f64x4` vectors per component.
+pub const LANES: usize = 8;
+const WIDTH: usize = 4;
+
+type Lane = [f64x4; LANES / WIDTH];
+
+fn get(lane: &Lane, i: usize) -> f64 {
+    lane[i / WIDTH].as_array_ref()[i % WIDTH]
+}
+
+fn set(lane: &mut Lane, i: usize, value: f64) {
+    lane[i / WIDTH].as_array_mut()[i % WIDTH] = value;
+}
+
+/// All bits set where the flag is held, for `blend`.
+fn mask(held: bool) -> f64 {
+    if held {
+        f64::from_bits(u64::MAX)
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LaneBlock {
+    px: Lane,
+    py: Lane,
+    pz: Lane,
+    vx: Lane,
+    vy: Lane,
+    vz: Lane,
+    forward: Lane,
+    back: Lane,
+    right: Lane,
+    left: Lane,
+    jump: Lane,
+    sneak: Lane,
+}
+
+impl LaneBlock {
+    /// One tick for every lane; padding lanes integrate too and are ignored.
+    fn step(&mut self) {
+        let accel = f64x4::splat(ACCEL);
+        let jump_speed = f64x4::splat(JUMP_SPEED);
+        let sneak_drop = f64x4::splat(ACCEL * 0.5);
+        let fall = f64x4::splat(GRAVITY * DT);
+        let dt = f64x4::splat(DT);
+        for h in 0..LANES / WIDTH {
+            let vz = self.vz[h];
+            let vz = self.forward[h].blend(vz + accel, vz);
+            self.vz[h] = self.back[h].blend(vz - accel, vz);
+
+            let vx = self.vx[h];
+            let vx = self.right[h].blend(vx + accel, vx);
+            self.vx[h] = self.left[h].blend(vx - accel, vx);
+
+            let vy = self.jump[h].blend(jump_speed, self.vy[h]);
+            let vy = self.sneak[h].blend(vy - sneak_drop, vy);
+            self.vy[h] = vy - fall;
+
+            self.px[h] += self.vx[h] * dt;
+            self.py[h] += self.vy[h] * dt;
+            self.pz[h] += self.vz[h] * dt;
+        }
+    }
+}
+
+/// The one-entity-at-a-time step lane blocks must reproduce exactly.
+pub fn step_scalar(pos: &mut Vec3, vel: &mut Vec3, input: &InputState) {
+    if input.forward {
+        vel.z += ACCEL;
+    }
+    if input.back {
+        vel.z -= ACCEL;
+    }
+    if input.right {
+        vel.x += ACCEL;
+    }
+    if input.left {
+        vel.x -= ACCEL;
+    }
+    if input.jump {
+        vel.y = JUMP_SPEED;
+    }
+    if input.sneak {
+        vel.y -= ACCEL * 0.5;
+    }
+    vel.y -= GRAVITY * DT;
+    pos.x += vel.x * DT;
+    pos.y += vel.y * DT;
+    pos.z += vel.z * DT;
+}
+
+/// Positions, velocities and inputs of one `/tick` request's entities.
+#[derive(Debug, Default)]
+pub struct EntityBatch {
+    blocks: Vec<LaneBlock>,
+    len: usize,
+}
+
+impl EntityBatch {
+    pub fn with_capacity(entities: usize) -> Self {
+        Self {
+            blocks: Vec::with_capacity(entities.div_ceil(LANES)),
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, pos: Vec3, vel: Vec3, input: &InputState) {
+        let lane = self.len % LANES;
+        if lane == 0 {
+            self.blocks.push(LaneBlock::default());
+        }
+        let block = self.blocks.last_mut().expect("block pushed above");
+        set(&mut block.px, lane, pos.x);
+        set(&mut block.py, lane, pos.y);
+        set(&mut block.pz, lane, pos.z);
+        set(&mut block.vx, lane, vel.x);
+        set(&mut block.vy, lane, vel.y);
+        set(&mut block.vz, lane, vel.z);
+        set(&mut block.forward, lane, mask(input.forward));
+        set(&mut block.back, lane, mask(input.back));
+        set(&mut block.right, lane, mask(input.right));
+        set(&mut block.left, lane, mask(input.left));
+        set(&mut block.jump, lane, mask(input.jump));
+        set(&mut block.sneak, lane, mask(input.sneak));
+        self.len += 1;
+    }
+
+    /// Apply input, gravity and one `DT` of motion to every entity.
+    pub fn step(&mut self) {
+        for block in &mut self.blocks {
+            block.step();
+        }
+    }
+
+    /// `(pos, vel)` per entity, in push order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.blocks
+            .iter()
+            .flat_map(|block| {
+                (0..LANES).map(move |lane| {
+                    (
+                        Vec3 {
+                            x: get(&block.px, lane),
+                            y: get(&block.py, lane),
+                            z: get(&block.pz, lane),
+                        },
+                        Vec3 {
+                            x: get(&block.vx, lane),
+                            y: get(&block.vy, lane),
+                            z: get(&block.vz, lane),
+                        },
+                    )
+                })
+            })
+            .take(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every input combination, with spread-out starting poses.
+    fn entities(count: usize) -> Vec<(Vec3, Vec3, InputState)> {
+        (0..count)
+            .map(|i| {
+                let bits = i % 64;
+                let input = InputState {
+                    forward: bits & 1 != 0,
+                    back: bits & 2 != 0,
+                    right: bits & 4 != 0,
+                    left: bits & 8 != 0,
+                    jump: bits & 16 != 0,
+                    sneak: bits & 32 != 0,
+                };
+                let f = i as f64;
+                let pos = Vec3 {
+                    x: f * 0.37,
+                    y: 64.0 + f * 0.01,
+                    z: -f * 1.3,
+                };
+                let vel = Vec3 {
+                    x: (f * 0.11).sin(),
+                    y: -0.2,
+                    z: (f * 0.07).cos(),
+                };
+                (pos, vel, input)
+            })
+            .collect()
+    }
+
+    fn batch_of(entities: &[(Vec3, Vec3, InputState)]) -> EntityBatch {
+        let mut batch = EntityBatch::with_capacity(entities.len());
+        for (pos, vel, input) in entities {
+            batch.push(*pos, *vel, input);
+        }
+        batch
+    }
+
+    #[test]
+    fn lane_blocks_match_scalar_bit_for_bit() {
+        // 133 leaves a partial last block.
+        let mut scalar = entities(133);
+        let mut batch = batch_of(&scalar);
+        for _ in 0..3 {
+            batch.step();
+            for (pos, vel, input) in &mut scalar {
+                step_scalar(pos, vel, input);
+            }
+        }
+        assert_eq!(batch.iter().count(), scalar.len());
+        let bits = |v: Vec3| [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()];
+        for ((pos, vel), (want_pos, want_vel, _)) in batch.iter().zip(&scalar) {
+            assert_eq!(bits(pos), bits(*want_pos));
+            assert_eq!(bits(vel), bits(*want_vel));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Hollow-planet render anchors derived from `PLANET_PROFILES`.
+//!
+//! Planets are scaled down into block space and laid out along +z away from
+//! the spawn origin. Every planet always contributes a shell and a core beacon
+//! anchor; inner-surface rings are added as the player gets closer.
+
+use spec::{Anchor, PlanetGravityProfile, Vec3, PLANET_PROFILES};
+
+/// Meters per block when mapping canon radii into the void world.
+pub const METERS_PER_BLOCK: f64 = 25_000.0;
+/// First planet starts this far down +z from the origin.
+const LAYOUT_START_Z: f64 = 1_024.0;
+/// Empty space between neighbouring shells.
+const LAYOUT_GAP: f64 = 512.0;
+/// Height of every planet center (matches the spawn platform).
+const LAYOUT_Y: f64 = 64.0;
+/// Beyond this many shell radii only the shell + core beacon are sent.
+const FAR_RADII: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanetLod {
+    /// Shell + core beacon.
+    Far,
+    /// Adds the equatorial belt of inner-surface rings, sparsely sampled.
+    Mid,
+    /// Inside the shell: full ring set, densely sampled.
+    Near,
+}
+
+impl PlanetLod {
+    /// Ring latitudes (degrees) and points per ring for this LOD.
+    fn rings(self) -> (&'static [f64], usize) {
+        match self {
+            PlanetLod::Far => (&[], 0),
+            PlanetLod::Mid => (&[-30.0, 0.0, 30.0], 8),
+            PlanetLod::Near => (&[-60.0, -30.0, 0.0, 30.0, 60.0], 32),
+        }
+    }
+}
+
+/// A planet placed in block space.
+#[derive(Clone, Copy, Debug)]
+pub struct PlacedPlanet {
+    pub profile: PlanetGravityProfile,
+    pub center: Vec3,
+    pub shell_radius: f64,
+    pub core_radius: f64,
+}
+
+impl PlacedPlanet {
+    pub fn lod_for(&self, pos: Vec3) -> PlanetLod {
+        let d = distance(self.center, pos);
+        if d <= self.shell_radius {
+            PlanetLod::Near
+        } else if d <= self.shell_radius * FAR_RADII {
+            PlanetLod::Mid
+        } else {
+            PlanetLod::Far
+        }
+    }
+}
+
+/// Deterministic block-space layout of every profile.
+pub fn planet_layout() -> Vec<PlacedPlanet> {
+    let mut z = LAYOUT_START_Z;
+    PLANET_PROFILES
+        .iter()
+        .map(|profile| {
+            let shell_radius = profile.shell_radius_m / METERS_PER_BLOCK;
+            let core_radius = profile.core_radius_m / METERS_PER_BLOCK;
+            let center = Vec3 {
+                x: 0.0,
+                y: LAYOUT_Y,
+                z: z + shell_radius,
+            };
+            z += shell_radius * 2.0 + LAYOUT_GAP;
+            PlacedPlanet {
+                profile: *profile,
+                center,
+                shell_radius,
+                core_radius,
+            }
+        })
+        .collect()
+}
+
+/// Shell, core and inner-ring anchors for every planet as seen from `pos`.
+pub fn planet_anchors(pos: Vec3) -> Vec<Anchor> {
+    let mut anchors = Vec::new();
+    for planet in planet_layout() {
+        let key = planet.profile.key;
+        anchors.push(Anchor {
+            id: format!("planet-{key}-shell"),
+            kind: "planet-shell".to_string(),
+            pos: planet.center,
+        });
+        anchors.push(Anchor {
+            id: format!("planet-{key}-core"),
+            kind: "core-beacon".to_string(),
+            pos: planet.center,
+        });
+
+        let (latitudes, points) = planet.lod_for(pos).rings();
+        for (ring, lat) in latitudes.iter().enumerate() {
+            let lat = lat.to_radians();
+            let ring_radius = planet.core_radius * lat.cos();
+            let ring_y = planet.center.y + planet.core_radius * lat.sin();
+            for i in 0..points {
+                let theta = std::f64::consts::TAU * i as f64 / points as f64;
+                anchors.push(Anchor {
+                    id: format!("planet-{key}-ring-{ring}-{i}"),
+                    kind: "inner-ring".to_string(),
+                    pos: Vec3 {
+                        x: planet.center.x + ring_radius * theta.cos(),
+                        y: ring_y,
+                        z: planet.center.z + ring_radius * theta.sin(),
+                    },
+                });
+            }
+        }
+    }
+    anchors
+}
+
+fn distance(a: Vec3, b: Vec3) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_detail_grows_as_player_approaches() {
+        let earth = planet_layout()
+            .into_iter()
+            .find(|p| p.profile.key == "earth")
+            .unwrap();
+        let rings = |pos: Vec3| {
+            planet_anchors(pos)
+                .into_iter()
+                .filter(|a| a.id.starts_with("planet-earth-ring"))
+                .count()
+        };
+
+        let spawn = Vec3 {
+            x: 0.0,
+            y: 64.0,
+            z: 0.0,
+        };
+        let outside = Vec3 {
+            z: earth.center.z - earth.shell_radius * 2.0,
+            ..earth.center
+        };
+        assert_eq!(earth.lod_for(spawn), PlanetLod::Far);
+        assert_eq!(rings(spawn), 0);
+        assert_eq!(rings(outside), 3 * 8);
+        assert_eq!(rings(earth.center), 5 * 32);
+
+        // Every ring point sits on the inner surface.
+        for anchor in planet_anchors(earth.center)
+            .iter()
+            .filter(|a| a.id.starts_with("planet-earth-ring"))
+        {
+            assert!((distance(anchor.pos, earth.center) - earth.core_radius).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Concurrency ceilings for the tick endpoints.
+//!
+//! Each limited route gets a fixed number of in-flight slots. When they're
+//! all taken the request is shed immediately with `503` + `Retry-After`
+//! instead of queueing behind a slow sim step; sheds are counted per route
+//! and reported by `/v1/metrics`.
+
+use axum::{
+    error_handling::HandleErrorLayer,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+    BoxError,
+};
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio::sync::Semaphore;
+use tower::{limit::GlobalConcurrencyLimitLayer, ServiceBuilder};
+
+/// Seconds a shed client is told to wait before retrying.
+pub const RETRY_AFTER_SECS: u64 = 1;
+
+#[derive(Debug)]
+pub struct TickLimit {
+    route: &'static str,
+    max_concurrency: usize,
+    /// Owned here rather than by the layer: axum may rebuild a route's layer
+    /// stack, and every copy must draw from the same slots.
+    slots: Arc<Semaphore>,
+    shed: AtomicU64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ShedStats {
+    pub route: &'static str,
+    pub max_concurrency: usize,
+    pub shed: u64,
+}
+
+impl TickLimit {
+    pub fn new(route: &'static str, max_concurrency: usize) -> Arc<Self> {
+        let max_concurrency = max_concurrency.max(1);
+        Arc::new(Self {
+            route,
+            max_concurrency,
+            slots: Arc::new(Semaphore::new(max_concurrency)),
+            shed: AtomicU64::new(0),
+        })
+    }
+
+    /// Ceiling from `env_key`, falling back to `default` when unset or invalid.
+    pub fn from_env(route: &'static str, env_key: &str, default: usize) -> Arc<Self> {
+        let max = std::env::var(env_key)
+            .ok()
+            .and_then(|raw| raw.parse::<usize>().ok())
+            .filter(|max| *max > 0)
+            .unwrap_or(default);
+        Self::new(route, max)
+    }
+
+    pub fn stats(&self) -> ShedStats {
+        ShedStats {
+            route: self.route,
+            max_concurrency: self.max_concurrency,
+            shed: self.shed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reject(&self, err: BoxError) -> Response {
+        if err.is::<tower::load_shed::error::Overloaded>() {
+            self.shed.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "[shed] {} at capacity ({})",
+                self.route,
+                self.max_concurrency
+            );
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+                "tick capacity reached, retry shortly",
+            )
+                .into_response()
+        } else {
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+/// Wrap `route` so at most `limit.max_concurrency` requests run at once.
+pub fn limited<S>(route: MethodRouter<S>, limit: Arc<TickLimit>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let slots = GlobalConcurrencyLimitLayer::with_semaphore(Arc::clone(&limit.slots));
+    route.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(move |err: BoxError| {
+                let limit = Arc::clone(&limit);
+                async move { limit.reject(err) }
+            }))
+            .load_shed()
+            .layer(slots),
+    )
+}
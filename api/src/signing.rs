@@ -0,0 +1,196 @@
+//! Ed25519 signatures on `/v1/sim/tick` responses.
+//!
+//! Paper plugins can't tell the Ω backend from a proxy pretending to be
+//! it, so with `OMEGA_RESPONSE_SIGNING_KEY` set (a 32-byte Ed25519 seed,
+//! base64url) every tick response carries `X-Omega-Signature: <kid>.<sig>`:
+//! the signature of the body's SHA-256 digest and the id of the key that made
+//! it. `GET /v1/sim/keys` publishes the key set clients check against. Keys
+//! retired by a rotation stay in it while their public halves are listed in
+//! `OMEGA_RESPONSE_RETIRED_KEYS` (comma-separated, base64url), so clients
+//! holding older responses or a cached set keep verifying.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use spec::{ResponseKey, ResponseKeySet, SIGNATURE_HEADER};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct ResponseSigner {
+    kid: String,
+    key_pair: Ed25519KeyPair,
+    keys: ResponseKeySet,
+}
+
+/// Id of an Ed25519 public key: hex of the first 8 bytes of its SHA-256.
+pub fn key_id(public_key: &[u8]) -> String {
+    digest(&SHA256, public_key).as_ref()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn decode(what: &str, raw: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(raw.trim().trim_end_matches('='))
+        .map_err(|err| format!("{what} is not base64url: {err}"))
+}
+
+impl ResponseSigner {
+    /// `None` unless `OMEGA_RESPONSE_SIGNING_KEY` is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(seed) = std::env::var("OMEGA_RESPONSE_SIGNING_KEY") else {
+            return Ok(None);
+        };
+        let retired = std::env::var("OMEGA_RESPONSE_RETIRED_KEYS").unwrap_or_default();
+        let retired = retired
+            .split(',')
+            .filter(|key| !key.trim().is_empty())
+            .map(|key| decode("OMEGA_RESPONSE_RETIRED_KEYS entry", key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(&decode("OMEGA_RESPONSE_SIGNING_KEY", &seed)?, &retired).map(Some)
+    }
+
+    pub fn new(seed: &[u8], retired: &[Vec<u8>]) -> Result<Self, String> {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(seed)
+            .map_err(|_| "response signing key must be a 32-byte Ed25519 seed".to_string())?;
+        let public = key_pair.public_key().as_ref();
+        let kid = key_id(public);
+        let mut keys = vec![ResponseKey {
+            kid: kid.clone(),
+            public_key: URL_SAFE_NO_PAD.encode(public),
+            active: true,
+        }];
+        for key in retired {
+            if key.len() != 32 {
+                return Err("retired response keys must be 32-byte Ed25519 public keys".into());
+            }
+            keys.push(ResponseKey {
+                kid: key_id(key),
+                public_key: URL_SAFE_NO_PAD.encode(key),
+                active: false,
+            });
+        }
+        Ok(Self {
+            kid,
+            key_pair,
+            keys: ResponseKeySet { keys },
+        })
+    }
+
+    pub fn key_set(&self) -> &ResponseKeySet {
+        &self.keys
+    }
+
+    /// `X-Omega-Signature` value for a response with `body`.
+    pub fn sign(&self, body: &[u8]) -> String {
+        let signature = self.key_pair.sign(digest(&SHA256, body).as_ref());
+        format!(
+            "{}.{}",
+            self.kid,
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        )
+    }
+
+    async fn sign_response(&self, response: Response) -> Response {
+        let (mut parts, body) = response.into_parts();
+        let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+        let signature = HeaderValue::from_str(&self.sign(&bytes))
+            .expect("kid and base64url are valid header text");
+        parts.headers.insert(SIGNATURE_HEADER, signature);
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+/// Sign every response of `route` when a signer is configured.
+pub fn signed<S>(route: MethodRouter<S>, signer: Option<Arc<ResponseSigner>>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let Some(signer) = signer else {
+        return route;
+    };
+    route.layer(middleware::from_fn(move |req: Request, next: Next| {
+        let signer = Arc::clone(&signer);
+        async move { signer.sign_response(next.run(req).await).await }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+    use ring::signature::{UnparsedPublicKey, ED25519};
+    use tower::ServiceExt;
+
+    /// What a client does with a response: find the key, check the digest.
+    fn verify(keys: &ResponseKeySet, header: &str, body: &[u8]) -> bool {
+        let Some((kid, sig)) = header.split_once('.') else {
+            return false;
+        };
+        let (Some(key), Ok(sig)) = (keys.get(kid), URL_SAFE_NO_PAD.decode(sig)) else {
+            return false;
+        };
+        let Ok(public) = URL_SAFE_NO_PAD.decode(&key.public_key) else {
+            return false;
+        };
+        UnparsedPublicKey::new(&ED25519, public)
+            .verify(digest(&SHA256, body).as_ref(), &sig)
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn responses_verify_against_the_published_keys() {
+        let old = ResponseSigner::new(&[1; 32], &[]).unwrap();
+        let old_public = URL_SAFE_NO_PAD
+            .decode(&old.key_set().keys[0].public_key)
+            .unwrap();
+        let signer = Arc::new(ResponseSigner::new(&[2; 32], &[old_public]).unwrap());
+        let keys = signer.key_set().clone();
+        assert_eq!(keys.keys.len(), 2);
+        assert!(keys.keys[0].active && !keys.keys[1].active);
+        assert_eq!(keys.keys[0].kid.len(), 16);
+
+        let app = Router::new().route(
+            "/v1/sim/tick",
+            signed(
+                post(|| async { Json(serde_json::json!({ "tick": 1 })) }),
+                Some(signer),
+            ),
+        );
+        let resp = app
+            .oneshot(
+                axum::http::Request::post("/v1/sim/tick")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let header = resp.headers()[SIGNATURE_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert!(verify(&keys, &header, &body));
+
+        // A proxy rewriting the body, or signing with its own key, fails.
+        assert!(!verify(&keys, &header, br#"{"tick":2}"#));
+        let spoofed = ResponseSigner::new(&[3; 32], &[]).unwrap();
+        assert!(!verify(&keys, &spoofed.sign(&body), &body));
+
+        // Responses signed before the rotation still check out.
+        assert!(verify(&keys, &old.sign(&body), &body));
+        assert!(ResponseSigner::new(&[0; 16], &[]).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Per-player cache of built `SimView`s.
+//!
+//! A view is reused while the player stays on the same planet and tick
+//! bucket, asks for the same view sections, the world version is unchanged,
+//! and their own pose/inputs haven't materially moved. Anything else
+//! rebuilds. Player ids come from clients, so
+//! entries idle past `VIEW_CACHE_TTL` are dropped and the cache never holds
+//! more than its capacity (least recently used goes first).
+
+use serde::Serialize;
+use spec::{InputState, PlanetId, Pose, SimTickRequest, SimView, ViewMask};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Ticks per cache bucket (base-8 rhythm).
+pub const VIEW_TICK_BUCKET: u64 = 8;
+/// Position drift (blocks) below which a pose counts as unchanged.
+pub const POSE_EPSILON: f64 = 0.01;
+/// Yaw/pitch drift (degrees) below which a pose counts as unchanged.
+pub const ANGLE_EPSILON: f32 = 0.5;
+/// Players cached at once.
+pub const VIEW_CACHE_CAPACITY: usize = 4096;
+/// Entries unused for this long are evicted.
+pub const VIEW_CACHE_TTL: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ViewKey {
+    pub planet: PlanetId,
+    pub tick_bucket: u64,
+    pub world_version: u64,
+    pub view_mask: ViewMask,
+}
+
+impl ViewKey {
+    pub fn new(planet: PlanetId, tick: u64, world_version: u64, view_mask: ViewMask) -> Self {
+        Self {
+            planet,
+            tick_bucket: tick / VIEW_TICK_BUCKET,
+            world_version,
+            view_mask,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CachedView {
+    key: ViewKey,
+    pose: Pose,
+    inputs: InputState,
+    view: SimView,
+    last_used: Instant,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ViewCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub hit_ratio: f64,
+}
+
+#[derive(Debug)]
+pub struct ViewCache {
+    entries: Mutex<HashMap<String, CachedView>>,
+    capacity: usize,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl Default for ViewCache {
+    fn default() -> Self {
+        Self::new(VIEW_CACHE_CAPACITY, VIEW_CACHE_TTL)
+    }
+}
+
+impl ViewCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Serve the cached view for `req.player_id` if still valid for `key`,
+    /// otherwise run `build` and remember the result.
+    pub fn get_or_build(
+        &self,
+        key: ViewKey,
+        req: &SimTickRequest,
+        build: impl FnOnce() -> SimView,
+    ) -> SimView {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("view cache mutex poisoned");
+        if let Some(cached) = entries.get_mut(&req.player_id) {
+            if cached.key == key
+                && cached.inputs == req.inputs
+                && !pose_moved(&cached.pose, &req.pose)
+            {
+                cached.last_used = now;
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return cached.view.clone();
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let view = build();
+        if !entries.contains_key(&req.player_id) && entries.len() >= self.capacity {
+            self.evict(&mut entries, now);
+        }
+        entries.insert(
+            req.player_id.clone(),
+            CachedView {
+                key,
+                pose: req.pose,
+                inputs: req.inputs.clone(),
+                view: view.clone(),
+                last_used: now,
+            },
+        );
+        view
+    }
+
+    /// Drop expired entries; if that frees nothing, drop the least recently used.
+    fn evict(&self, entries: &mut HashMap<String, CachedView>, now: Instant) {
+        let before = entries.len();
+        entries.retain(|_, cached| now.duration_since(cached.last_used) < self.ttl);
+        if entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(player, _)| player.clone());
+            if let Some(player) = oldest {
+                entries.remove(&player);
+            }
+        }
+        self.evictions
+            .fetch_add((before - entries.len()) as u64, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> ViewCacheStats {
+        let entries = self
+            .entries
+            .lock()
+            .expect("view cache mutex poisoned")
+            .len();
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + misses;
+        ViewCacheStats {
+            entries,
+            hits,
+            misses,
+            evictions: self.evictions.load(Ordering::Relaxed),
+            hit_ratio: if total == 0 {
+                0.0
+            } else {
+                hits as f64 / total as f64
+            },
+        }
+    }
+}
+
+/// True when `b` differs from `a` enough to change what the client sees.
+pub fn pose_moved(a: &Pose, b: &Pose) -> bool {
+    (a.pos.x - b.pos.x).abs() > POSE_EPSILON
+        || (a.pos.y - b.pos.y).abs() > POSE_EPSILON
+        || (a.pos.z - b.pos.z).abs() > POSE_EPSILON
+        || (a.yaw - b.yaw).abs() > ANGLE_EPSILON
+        || (a.pitch - b.pitch).abs() > ANGLE_EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_cache_evicts_least_recently_used_player() {
+        let cache = ViewCache::new(2, VIEW_CACHE_TTL);
+        let key = ViewKey::new(PlanetId::Earth, 0, 0, ViewMask::ALL);
+        let tick = |player: &str| {
+            let req = SimTickRequest {
+                player_id: player.into(),
+                ..Default::default()
+            };
+            cache.get_or_build(key, &req, SimView::default);
+        };
+        tick("a");
+        tick("b");
+        tick("a");
+        tick("c");
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.evictions), (2, 1));
+
+        // "a" survived, "b" was evicted and rebuilds.
+        tick("a");
+        tick("b");
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 4));
+    }
+}
@@ -0,0 +1,685 @@
+//! Canonical JSON for hash inputs.
+//!
+//! Roots, digests and MACs hash a value's JSON, so two nodes holding the same
+//! state must produce the same bytes. `serde_json` writes maps in iteration
+//! order (which for a `HashMap` differs between processes) and struct fields
+//! in declaration order. The canonical form is compact `serde_json` output
+//! with every map and struct sorted by key string, and with floats fixed:
+//! `-0.0` is written as `0.0` and non-finite floats as `null`. A value whose
+//! maps were already sorted encodes exactly as `serde_json` writes it, so
+//! roots taken before the canonical form existed still hold.
+//!
+//! Sequences stream straight to the writer; each map is held in memory while
+//! its entries are sorted.
+
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug)]
+pub struct CanonicalError(String);
+
+impl fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CanonicalError {}
+
+impl ser::Error for CanonicalError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl From<io::Error> for CanonicalError {
+    fn from(err: io::Error) -> Self {
+        Self(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CanonicalError {
+    fn from(err: serde_json::Error) -> Self {
+        Self(err.to_string())
+    }
+}
+
+/// Write `value`'s canonical JSON to `writer`.
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), CanonicalError> {
+    value.serialize(&mut Canonical { out: writer })
+}
+
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, CanonicalError> {
+    let mut out = Vec::new();
+    to_writer(&mut out, value)?;
+    Ok(out)
+}
+
+struct Canonical<W> {
+    out: W,
+}
+
+impl<W: Write> Canonical<W> {
+    fn float(&mut self, value: f64) -> Result<(), CanonicalError> {
+        if !value.is_finite() {
+            return Ok(self.out.write_all(b"null")?);
+        }
+        // `0.0 == -0.0`, so this folds the sign away.
+        let value = if value == 0.0 { 0.0 } else { value };
+        Ok(serde_json::to_writer(&mut self.out, &value)?)
+    }
+
+    /// `{"variant":` before a variant's content.
+    fn open_variant(&mut self, variant: &str) -> Result<(), CanonicalError> {
+        self.out.write_all(b"{")?;
+        serde_json::to_writer(&mut self.out, variant)?;
+        Ok(self.out.write_all(b":")?)
+    }
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut Canonical<W> {
+    type Ok = ();
+    type Error = CanonicalError;
+    type SerializeSeq = Seq<'a, W>;
+    type SerializeTuple = Seq<'a, W>;
+    type SerializeTupleStruct = Seq<'a, W>;
+    type SerializeTupleVariant = Seq<'a, W>;
+    type SerializeMap = Map<'a, W>;
+    type SerializeStruct = Map<'a, W>;
+    type SerializeStructVariant = Map<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), CanonicalError> {
+        Ok(self.out.write_all(if v { b"true" } else { b"false" })?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), CanonicalError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), CanonicalError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), CanonicalError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), CanonicalError> {
+        Ok(write!(self.out, "{v}")?)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), CanonicalError> {
+        Ok(write!(self.out, "{v}")?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), CanonicalError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), CanonicalError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), CanonicalError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), CanonicalError> {
+        Ok(write!(self.out, "{v}")?)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), CanonicalError> {
+        Ok(write!(self.out, "{v}")?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), CanonicalError> {
+        if !v.is_finite() || v == 0.0 {
+            return self.float(v.into());
+        }
+        Ok(serde_json::to_writer(&mut self.out, &v)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), CanonicalError> {
+        self.float(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), CanonicalError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), CanonicalError> {
+        Ok(serde_json::to_writer(&mut self.out, v)?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), CanonicalError> {
+        use ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+        for byte in v {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+
+    fn serialize_none(self) -> Result<(), CanonicalError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), CanonicalError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), CanonicalError> {
+        Ok(self.out.write_all(b"null")?)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), CanonicalError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), CanonicalError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        self.open_variant(variant)?;
+        value.serialize(&mut *self)?;
+        Ok(self.out.write_all(b"}")?)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Seq<'a, W>, CanonicalError> {
+        self.out.write_all(b"[")?;
+        Ok(Seq {
+            ser: self,
+            first: true,
+            variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq<'a, W>, CanonicalError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Seq<'a, W>, CanonicalError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Seq<'a, W>, CanonicalError> {
+        self.open_variant(variant)?;
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = true;
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Map<'a, W>, CanonicalError> {
+        Ok(Map {
+            ser: self,
+            entries: Vec::new(),
+            key: None,
+            variant: false,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Map<'a, W>, CanonicalError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Map<'a, W>, CanonicalError> {
+        self.open_variant(variant)?;
+        let mut map = self.serialize_map(Some(len))?;
+        map.variant = true;
+        Ok(map)
+    }
+}
+
+struct Seq<'a, W> {
+    ser: &'a mut Canonical<W>,
+    first: bool,
+    /// Close the `{"variant":` wrapper too.
+    variant: bool,
+}
+
+impl<W: Write> Seq<'_, W> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CanonicalError> {
+        if !self.first {
+            self.ser.out.write_all(b",")?;
+        }
+        self.first = false;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn close(self) -> Result<(), CanonicalError> {
+        self.ser
+            .out
+            .write_all(if self.variant { b"]}" } else { b"]" })?;
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Seq<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Seq<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Seq<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CanonicalError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Seq<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CanonicalError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+/// A map or struct: entries are encoded as they come and written sorted.
+struct Map<'a, W> {
+    ser: &'a mut Canonical<W>,
+    entries: Vec<(String, Vec<u8>)>,
+    key: Option<String>,
+    /// Close the `{"variant":` wrapper too.
+    variant: bool,
+}
+
+impl<W: Write> Map<'_, W> {
+    fn entry<T: Serialize + ?Sized>(
+        &mut self,
+        key: String,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        self.entries.push((key, to_vec(value)?));
+        Ok(())
+    }
+
+    fn close(mut self) -> Result<(), CanonicalError> {
+        // Stable, so repeated keys keep their order as serde_json would.
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let out = &mut self.ser.out;
+        out.write_all(b"{")?;
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut *out, key)?;
+            out.write_all(b":")?;
+            out.write_all(value)?;
+        }
+        out.write_all(if self.variant { b"}}" } else { b"}" })?;
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Map<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CanonicalError> {
+        self.key = Some(key.serialize(MapKey)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CanonicalError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| CanonicalError("map value without a key".into()))?;
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Map<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        self.entry(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Map<'_, W> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), CanonicalError> {
+        self.entry(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), CanonicalError> {
+        self.close()
+    }
+}
+
+/// Map keys become strings the way `serde_json` makes them: strings and
+/// unit variants as is, integers, chars and bools in their text form.
+struct MapKey;
+
+impl MapKey {
+    fn refuse() -> CanonicalError {
+        CanonicalError("map keys must be strings, integers, chars or bools".into())
+    }
+}
+
+impl ser::Serializer for MapKey {
+    type Ok = String;
+    type Error = CanonicalError;
+    type SerializeSeq = Impossible<String, CanonicalError>;
+    type SerializeTuple = Impossible<String, CanonicalError>;
+    type SerializeTupleStruct = Impossible<String, CanonicalError>;
+    type SerializeTupleVariant = Impossible<String, CanonicalError>;
+    type SerializeMap = Impossible<String, CanonicalError>;
+    type SerializeStruct = Impossible<String, CanonicalError>;
+    type SerializeStructVariant = Impossible<String, CanonicalError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, CanonicalError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_none(self) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_unit(self) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, CanonicalError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, CanonicalError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, CanonicalError> {
+        Err(Self::refuse())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, CanonicalError> {
+        Err(Self::refuse())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize)]
+    enum Shape {
+        Dot,
+        Scaled(f64),
+        Pair(u8, u8),
+        Box { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Unsorted {
+        zeta: Vec<Shape>,
+        alpha: Option<u128>,
+        mid: (char, bool),
+    }
+
+    #[test]
+    fn maps_and_structs_sort_and_floats_are_fixed() {
+        let hashed: HashMap<String, i32> = (0..64).map(|i| (format!("k{i}"), i)).collect();
+        let sorted: BTreeMap<String, i32> = hashed.clone().into_iter().collect();
+        assert_eq!(
+            to_vec(&hashed).unwrap(),
+            serde_json::to_vec(&sorted).unwrap()
+        );
+
+        let value = Unsorted {
+            zeta: vec![
+                Shape::Dot,
+                Shape::Scaled(-0.0),
+                Shape::Scaled(f64::NAN),
+                Shape::Pair(1, 2),
+                Shape::Box { w: 3, h: 4 },
+            ],
+            alpha: Some(u128::MAX),
+            mid: ('é', true),
+        };
+        assert_eq!(
+            String::from_utf8(to_vec(&value).unwrap()).unwrap(),
+            format!(
+                r#"{{"alpha":{},"mid":["é",true],"zeta":["Dot",{{"Scaled":0.0}},{{"Scaled":null}},{{"Pair":[1,2]}},{{"Box":{{"h":4,"w":3}}}}]}}"#,
+                u128::MAX
+            )
+        );
+
+        // Already-canonical values encode exactly as serde_json writes them.
+        let json = serde_json::json!({ "b": [1.5, "x\n\"y\""], "a": { "d": null, "c": -7 } });
+        assert_eq!(to_vec(&json).unwrap(), serde_json::to_vec(&json).unwrap());
+        let numbered: BTreeMap<u32, &str> = BTreeMap::from([(10, "ten"), (9, "nine")]);
+        assert_eq!(to_vec(&numbered).unwrap(), br#"{"10":"ten","9":"nine"}"#);
+        assert!(to_vec(&BTreeMap::from([((1, 2), 3)])).is_err());
+    }
+}
@@ -0,0 +1,605 @@
+//! Block production consensus for multi-node deployments.
+//!
+//! Every sealed block carries a [`BlockHeader`] whose root chains the
+//! previous root with the new height, producer, seal time and the
+//! producer's ledger state root. A
+//! [`Consensus`] policy decides which node may propose each height and
+//! validates proposals from peers; [`fork_choice`] picks between competing
+//! tips (highest valid height, then the lower root so every node agrees).
+//! Nodes exchange [`ConsensusMessage`]s; transport is the caller's problem.
+//! A producer name is not a credential: [`PeerSigned`] wraps a policy so
+//! only nodes holding the shared peer key can mint headers. The same
+//! [`KeyedSigner`] signs client frames under per-session keys
+//! ([`frame_signing_bytes`]).
+
+use crate::shaless::{chained_root, shaless_json_digest};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Parent root of height 1.
+pub const GENESIS_ROOT: &str = ";∞;genesis;";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub height: u64,
+    pub prev_root: String,
+    /// Chained master root over `prev_root`, height, producer, seal time
+    /// and `state_root`.
+    pub root: String,
+    /// Node id of the proposer.
+    pub producer: String,
+    pub sealed_ms: i64,
+    /// Producer's ledger master root at this height.
+    #[serde(default)]
+    pub state_root: String,
+    /// Set by signing policies ([`ExternalSigner`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl BlockHeader {
+    /// Unsigned header extending `parent` (genesis when `None`).
+    pub fn next(
+        parent: Option<&BlockHeader>,
+        producer: impl Into<String>,
+        sealed_ms: i64,
+        state_root: &str,
+    ) -> Self {
+        match parent {
+            Some(parent) => Self::at(
+                parent.height + 1,
+                &parent.root,
+                producer,
+                sealed_ms,
+                state_root,
+            ),
+            None => Self::at(1, GENESIS_ROOT, producer, sealed_ms, state_root),
+        }
+    }
+
+    /// Unsigned header at an explicit height and parent root.
+    pub fn at(
+        height: u64,
+        prev_root: &str,
+        producer: impl Into<String>,
+        sealed_ms: i64,
+        state_root: &str,
+    ) -> Self {
+        let producer = producer.into();
+        Self {
+            root: chained_root(prev_root, height, &producer, sealed_ms, state_root),
+            height,
+            prev_root: prev_root.to_string(),
+            producer,
+            sealed_ms,
+            state_root: state_root.to_string(),
+            signature: None,
+        }
+    }
+
+    /// Recompute the root from the header's own fields.
+    pub fn root_is_valid(&self) -> bool {
+        self.root
+            == chained_root(
+                &self.prev_root,
+                self.height,
+                &self.producer,
+                self.sealed_ms,
+                &self.state_root,
+            )
+    }
+
+    /// Direct child of `parent`?
+    pub fn extends(&self, parent: Option<&BlockHeader>) -> bool {
+        match parent {
+            Some(parent) => self.height == parent.height + 1 && self.prev_root == parent.root,
+            None => self.height == 1 && self.prev_root == GENESIS_ROOT,
+        }
+    }
+
+    /// Bytes covered by a signature: everything except the signature itself.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        format!(
+            "{};{};{};{};{};{}",
+            self.height, self.prev_root, self.root, self.producer, self.sealed_ms, self.state_root
+        )
+        .into_bytes()
+    }
+}
+
+/// Messages exchanged between nodes (carried in `BLOCK` frames by the gateway).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConsensusMessage {
+    /// A producer sealed `header` and asks peers to apply it.
+    Proposal { header: BlockHeader },
+    /// `node` applied the block with `root` at `height`.
+    Acceptance {
+        height: u64,
+        root: String,
+        node: String,
+    },
+}
+
+impl ConsensusMessage {
+    /// Bytes covered by a peer's frame MAC.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        match self {
+            ConsensusMessage::Proposal { header } => {
+                let mut bytes = b"proposal;".to_vec();
+                bytes.extend(header.signing_bytes());
+                bytes.extend(header.signature.as_deref().unwrap_or("").as_bytes());
+                bytes
+            }
+            ConsensusMessage::Acceptance { height, root, node } => {
+                format!("acceptance;{height};{root};{node}").into_bytes()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsensusError {
+    /// The policy doesn't allow `producer` to seal `height`.
+    NotProducer {
+        height: u64,
+        producer: String,
+    },
+    BadRoot {
+        height: u64,
+    },
+    /// Header doesn't extend the local tip.
+    UnknownParent {
+        height: u64,
+        prev_root: String,
+    },
+    MissingSignature {
+        height: u64,
+    },
+    BadSignature {
+        height: u64,
+    },
+}
+
+impl fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusError::NotProducer { height, producer } => {
+                write!(f, "{producer} may not produce height {height}")
+            }
+            ConsensusError::BadRoot { height } => write!(f, "root mismatch at height {height}"),
+            ConsensusError::UnknownParent { height, prev_root } => {
+                write!(f, "height {height} extends unknown root {prev_root}")
+            }
+            ConsensusError::MissingSignature { height } => {
+                write!(f, "height {height} is unsigned")
+            }
+            ConsensusError::BadSignature { height } => {
+                write!(f, "bad signature at height {height}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConsensusError {}
+
+/// Pluggable block production policy.
+pub trait Consensus: Send + Sync + fmt::Debug {
+    /// Short policy name for status output.
+    fn name(&self) -> &'static str;
+
+    /// May `node` propose the block at `height`?
+    fn may_propose(&self, node: &str, height: u64) -> bool;
+
+    /// Finish a locally produced header (e.g. attach a signature).
+    fn seal(&self, _header: &mut BlockHeader) {}
+
+    /// Check a header on its own: root, producer and any signature.
+    /// Parent linkage is checked by the caller against its tip.
+    fn validate(&self, header: &BlockHeader) -> Result<(), ConsensusError> {
+        if !header.root_is_valid() {
+            return Err(ConsensusError::BadRoot {
+                height: header.height,
+            });
+        }
+        if !self.may_propose(&header.producer, header.height) {
+            return Err(ConsensusError::NotProducer {
+                height: header.height,
+                producer: header.producer.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// One fixed leader seals every block; everyone else follows.
+#[derive(Clone, Debug)]
+pub struct SingleProducer {
+    pub leader: String,
+}
+
+impl Consensus for SingleProducer {
+    fn name(&self) -> &'static str {
+        "single"
+    }
+
+    fn may_propose(&self, node: &str, _height: u64) -> bool {
+        node == self.leader
+    }
+}
+
+/// Producers take turns by height: `nodes[height % nodes.len()]`.
+#[derive(Clone, Debug)]
+pub struct RoundRobin {
+    pub nodes: Vec<String>,
+}
+
+impl RoundRobin {
+    pub fn producer_for(&self, height: u64) -> Option<&str> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let slot = (height % self.nodes.len() as u64) as usize;
+        Some(&self.nodes[slot])
+    }
+}
+
+impl Consensus for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round_robin"
+    }
+
+    fn may_propose(&self, node: &str, height: u64) -> bool {
+        self.producer_for(height) == Some(node)
+    }
+}
+
+/// Hot standby: any listed node may seal (any node when the list is empty).
+/// Which one actually does is decided outside consensus by a producer lease,
+/// so followers accept a standby's blocks as soon as it takes over.
+#[derive(Clone, Debug)]
+pub struct LeasedProducer {
+    pub nodes: Vec<String>,
+}
+
+impl Consensus for LeasedProducer {
+    fn name(&self) -> &'static str {
+        "lease"
+    }
+
+    fn may_propose(&self, node: &str, _height: u64) -> bool {
+        self.nodes.is_empty() || self.nodes.iter().any(|n| n == node)
+    }
+}
+
+/// Signs and verifies header bytes with a key the policy doesn't own.
+pub trait BlockSigner: Send + Sync + fmt::Debug {
+    fn sign(&self, message: &[u8]) -> String;
+    fn verify(&self, message: &[u8], signature: &str) -> bool;
+}
+
+/// Any node may propose, but only blocks signed by the external key count.
+#[derive(Debug)]
+pub struct ExternalSigner<S> {
+    pub signer: S,
+}
+
+impl<S: BlockSigner> Consensus for ExternalSigner<S> {
+    fn name(&self) -> &'static str {
+        "external_signer"
+    }
+
+    fn may_propose(&self, _node: &str, _height: u64) -> bool {
+        true
+    }
+
+    fn seal(&self, header: &mut BlockHeader) {
+        header.signature = Some(self.signer.sign(&header.signing_bytes()));
+    }
+
+    fn validate(&self, header: &BlockHeader) -> Result<(), ConsensusError> {
+        if !header.root_is_valid() {
+            return Err(ConsensusError::BadRoot {
+                height: header.height,
+            });
+        }
+        let signature = header
+            .signature
+            .as_deref()
+            .ok_or(ConsensusError::MissingSignature {
+                height: header.height,
+            })?;
+        if !self.signer.verify(&header.signing_bytes(), signature) {
+            return Err(ConsensusError::BadSignature {
+                height: header.height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Keyed blake3 MAC over the header: every node holding the key can sign
+/// and verify. Stands in for an HSM or remote signer.
+#[derive(Clone)]
+pub struct KeyedSigner {
+    key: [u8; 32],
+}
+
+impl KeyedSigner {
+    /// Derive the MAC key from a shared secret of any length.
+    pub fn from_secret(secret: &str) -> Self {
+        Self {
+            key: blake3::derive_key("dlog omega block signer", secret.as_bytes()),
+        }
+    }
+}
+
+impl fmt::Debug for KeyedSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KeyedSigner(..)")
+    }
+}
+
+impl BlockSigner for KeyedSigner {
+    fn sign(&self, message: &[u8]) -> String {
+        blake3::keyed_hash(&self.key, message).to_hex().to_string()
+    }
+
+    fn verify(&self, message: &[u8], signature: &str) -> bool {
+        blake3::Hash::from_hex(signature)
+            .is_ok_and(|sig| sig == blake3::keyed_hash(&self.key, message))
+    }
+}
+
+/// What a client MACs to sign a frame under its session's key: the
+/// session id, seq and namespace, and the sha-less digest of the payload's
+/// canonical JSON, so gateway and client agree whatever the key order.
+pub fn frame_signing_bytes<P: Serialize + ?Sized>(
+    session_id: &str,
+    seq: u64,
+    namespace: &str,
+    payload: &P,
+) -> Vec<u8> {
+    format!(
+        "frame;{session_id};{seq};{namespace};{}",
+        shaless_json_digest(payload)
+    )
+    .into_bytes()
+}
+
+/// Any policy, plus a [`KeyedSigner`] MAC on every header from the shared
+/// peer key. The inner policy still decides who may produce; the key proves
+/// the header came from a node in the deployment.
+#[derive(Debug)]
+pub struct PeerSigned<C> {
+    pub policy: C,
+    pub signer: KeyedSigner,
+}
+
+impl<C: Consensus> Consensus for PeerSigned<C> {
+    fn name(&self) -> &'static str {
+        self.policy.name()
+    }
+
+    fn may_propose(&self, node: &str, height: u64) -> bool {
+        self.policy.may_propose(node, height)
+    }
+
+    fn seal(&self, header: &mut BlockHeader) {
+        self.policy.seal(header);
+        header.signature = Some(self.signer.sign(&header.signing_bytes()));
+    }
+
+    fn validate(&self, header: &BlockHeader) -> Result<(), ConsensusError> {
+        let signature = header
+            .signature
+            .as_deref()
+            .ok_or(ConsensusError::MissingSignature {
+                height: header.height,
+            })?;
+        if !self.signer.verify(&header.signing_bytes(), signature) {
+            return Err(ConsensusError::BadSignature {
+                height: header.height,
+            });
+        }
+        self.policy.validate(header)
+    }
+}
+
+/// Check that `chain` starts at genesis, links root to root, and that every
+/// header passes `consensus`.
+pub fn validate_chain(
+    chain: &[BlockHeader],
+    consensus: &dyn Consensus,
+) -> Result<(), ConsensusError> {
+    let mut parent = None;
+    for header in chain {
+        if !header.extends(parent) {
+            return Err(ConsensusError::UnknownParent {
+                height: header.height,
+                prev_root: header.prev_root.clone(),
+            });
+        }
+        consensus.validate(header)?;
+        parent = Some(header);
+    }
+    Ok(())
+}
+
+/// Preferred of two tips: higher height wins, ties go to the lower root.
+pub fn fork_choice<'a>(a: &'a BlockHeader, b: &'a BlockHeader) -> &'a BlockHeader {
+    match a.height.cmp(&b.height) {
+        std::cmp::Ordering::Greater => a,
+        std::cmp::Ordering::Less => b,
+        std::cmp::Ordering::Equal if b.root < a.root => b,
+        std::cmp::Ordering::Equal => a,
+    }
+}
+
+/// Best of several candidate chains: the longest that validates.
+pub fn choose_chain<'a>(
+    candidates: &'a [Vec<BlockHeader>],
+    consensus: &dyn Consensus,
+) -> Option<&'a [BlockHeader]> {
+    candidates
+        .iter()
+        .filter(|chain| !chain.is_empty() && validate_chain(chain, consensus).is_ok())
+        .reduce(|best, chain| {
+            let (best_tip, tip) = (best.last().unwrap(), chain.last().unwrap());
+            if std::ptr::eq(fork_choice(best_tip, tip), tip) {
+                chain
+            } else {
+                best
+            }
+        })
+        .map(Vec::as_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(producers: &[&str]) -> Vec<BlockHeader> {
+        let mut headers: Vec<BlockHeader> = Vec::new();
+        for (i, producer) in producers.iter().enumerate() {
+            headers.push(BlockHeader::next(
+                headers.last(),
+                *producer,
+                i as i64 * 8_000,
+                ";∞;state;",
+            ));
+        }
+        headers
+    }
+
+    #[test]
+    fn round_robin_rotates_and_rejects_out_of_turn_blocks() {
+        let rr = RoundRobin {
+            nodes: vec!["a".into(), "b".into()],
+        };
+        // height 1 → b, height 2 → a, ...
+        assert!(validate_chain(&chain(&["b", "a", "b"]), &rr).is_ok());
+        assert_eq!(
+            validate_chain(&chain(&["b", "b"]), &rr),
+            Err(ConsensusError::NotProducer {
+                height: 2,
+                producer: "b".into()
+            })
+        );
+
+        let mut tampered = chain(&["b", "a"]);
+        tampered[1].sealed_ms += 1;
+        assert_eq!(
+            validate_chain(&tampered, &rr),
+            Err(ConsensusError::BadRoot { height: 2 })
+        );
+    }
+
+    #[test]
+    fn leased_producer_accepts_any_listed_node() {
+        let lease = LeasedProducer {
+            nodes: vec!["a".into(), "b".into()],
+        };
+        assert!(lease.may_propose("a", 1) && lease.may_propose("b", 1));
+        assert!(!lease.may_propose("c", 1));
+        assert!(LeasedProducer { nodes: Vec::new() }.may_propose("c", 9));
+    }
+
+    #[test]
+    fn fork_choice_prefers_highest_valid_chain() {
+        let leader = SingleProducer { leader: "a".into() };
+        let short = chain(&["a", "a"]);
+        let long = chain(&["a", "a", "a"]);
+        let invalid = chain(&["a", "a", "x", "x"]);
+        let candidates = vec![short.clone(), invalid, long.clone()];
+        assert_eq!(choose_chain(&candidates, &leader), Some(long.as_slice()));
+
+        // Same height: the lower root wins regardless of argument order.
+        let sibling = BlockHeader::next(short.first(), "a", 99, ";∞;state;");
+        let (x, y) = (&short[1], &sibling);
+        assert_eq!(fork_choice(x, y), fork_choice(y, x));
+    }
+
+    #[test]
+    fn external_signer_requires_a_valid_signature() {
+        let consensus = ExternalSigner {
+            signer: KeyedSigner::from_secret("shared"),
+        };
+        let mut header = BlockHeader::next(None, "anyone", 0, ";∞;state;");
+        assert_eq!(
+            consensus.validate(&header),
+            Err(ConsensusError::MissingSignature { height: 1 })
+        );
+        consensus.seal(&mut header);
+        assert!(consensus.validate(&header).is_ok());
+
+        let imposter = ExternalSigner {
+            signer: KeyedSigner::from_secret("other"),
+        };
+        assert_eq!(
+            imposter.validate(&header),
+            Err(ConsensusError::BadSignature { height: 1 })
+        );
+    }
+
+    #[test]
+    fn frame_signatures_cover_every_signed_field() {
+        let signer = KeyedSigner::from_secret("session key");
+        let payload = serde_json::json!({"kind": "transfer", "amount": 5});
+        let signature = signer.sign(&frame_signing_bytes("s1", 7, ";∞;bank;", &payload));
+        let reordered: serde_json::Value =
+            serde_json::from_str(r#"{"amount": 5, "kind": "transfer"}"#).unwrap();
+        assert!(signer.verify(
+            &frame_signing_bytes("s1", 7, ";∞;bank;", &reordered),
+            &signature
+        ));
+        for bytes in [
+            frame_signing_bytes("s2", 7, ";∞;bank;", &payload),
+            frame_signing_bytes("s1", 8, ";∞;bank;", &payload),
+            frame_signing_bytes("s1", 7, ";∞;game;", &payload),
+            frame_signing_bytes("s1", 7, ";∞;bank;", &serde_json::json!({"amount": 6})),
+        ] {
+            assert!(!signer.verify(&bytes, &signature));
+        }
+        assert!(!KeyedSigner::from_secret("guess").verify(
+            &frame_signing_bytes("s1", 7, ";∞;bank;", &payload),
+            &signature
+        ));
+    }
+
+    #[test]
+    fn peer_signed_policy_rejects_headers_without_the_peer_key() {
+        let policy = |secret: &str| PeerSigned {
+            policy: SingleProducer {
+                leader: "leader".into(),
+            },
+            signer: KeyedSigner::from_secret(secret),
+        };
+        let (deployment, outsider) = (policy("peers"), policy("guess"));
+
+        // Naming the leader isn't enough without the key.
+        let mut forged = BlockHeader::next(None, "leader", 0, ";∞;state;");
+        assert_eq!(
+            deployment.validate(&forged),
+            Err(ConsensusError::MissingSignature { height: 1 })
+        );
+        outsider.seal(&mut forged);
+        assert_eq!(
+            deployment.validate(&forged),
+            Err(ConsensusError::BadSignature { height: 1 })
+        );
+
+        let mut sealed = BlockHeader::next(None, "leader", 0, ";∞;state;");
+        deployment.seal(&mut sealed);
+        assert!(deployment.validate(&sealed).is_ok());
+
+        // The root commits to the ledger state the producer reported.
+        let mut rewritten = sealed.clone();
+        rewritten.state_root = ";∞;other;".into();
+        assert_eq!(
+            deployment.validate(&rewritten),
+            Err(ConsensusError::BadSignature { height: 1 })
+        );
+        assert!(!rewritten.root_is_valid());
+    }
+}
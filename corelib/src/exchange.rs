@@ -0,0 +1,88 @@
+//! Conversion between planet currency zones.
+//!
+//! Rates are powers of φ set by each zone's `phi_rank`, kept in integer ppm
+//! so every node converts to the same amount. A conversion pays a fee of
+//! 0.382% (1/φ² percent) of what it sends; conversions within one zone are
+//! free and 1:1.
+
+use serde::Serialize;
+use spec::CurrencyZone;
+
+/// φ in parts per million.
+pub const PHI_PPM: u128 = 1_618_034;
+/// Fee on a conversion between zones, in parts per million of the amount sent.
+pub const FEE_PPM: u128 = 3_820;
+const ONE_PPM: u128 = 1_000_000;
+
+/// One conversion, worked out before any balance moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Quote {
+    /// Debited from the sender, in the source unit.
+    pub sent: u128,
+    /// Part of `sent` kept as the fee, in the source unit.
+    pub fee: u128,
+    /// Destination units per source unit, in ppm.
+    pub rate_ppm: u128,
+    /// Credited to the recipient, in the destination unit.
+    pub received: u128,
+}
+
+/// φ^n in ppm, compounded in integers.
+fn phi_pow_ppm(n: u32) -> u128 {
+    (0..n).fold(ONE_PPM, |acc, _| acc * PHI_PPM / ONE_PPM)
+}
+
+/// Destination units one source unit buys, in ppm.
+pub fn rate_ppm(from: &CurrencyZone, to: &CurrencyZone) -> u128 {
+    let steps = from.phi_rank - to.phi_rank;
+    if steps >= 0 {
+        phi_pow_ppm(steps.unsigned_abs())
+    } else {
+        ONE_PPM * ONE_PPM / phi_pow_ppm(steps.unsigned_abs())
+    }
+}
+
+/// Convert `amount` of `from`'s unit into `to`'s. `None` when nothing would
+/// arrive (zero, or too small to survive the fee and rate).
+pub fn quote(from: &CurrencyZone, to: &CurrencyZone, amount: u128) -> Option<Quote> {
+    let (fee, rate_ppm) = if from.planet == to.planet {
+        (0, ONE_PPM)
+    } else {
+        ((amount * FEE_PPM).div_ceil(ONE_PPM), rate_ppm(from, to))
+    };
+    let received = (amount - fee) * rate_ppm / ONE_PPM;
+    (received > 0).then_some(Quote {
+        sent: amount,
+        fee,
+        rate_ppm,
+        received,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec::PlanetId;
+
+    #[test]
+    fn rates_are_phi_powers_and_fees_only_cross_zones() {
+        let (earth, moon, sun) = (
+            PlanetId::Earth.zone(),
+            PlanetId::Moon.zone(),
+            PlanetId::Sun.zone(),
+        );
+        assert_eq!(rate_ppm(&earth, &moon), PHI_PPM);
+        assert_eq!(rate_ppm(&moon, &earth), 618_033);
+        assert_eq!(rate_ppm(&sun, &moon), 4_236_068);
+
+        let same = quote(&earth, &earth, 1_000).unwrap();
+        assert_eq!((same.fee, same.received), (0, 1_000));
+
+        let out = quote(&earth, &moon, 1_000_000).unwrap();
+        assert_eq!(out.fee, 3_820);
+        assert_eq!(out.received, 996_180 * PHI_PPM / 1_000_000);
+
+        assert!(quote(&earth, &moon, 0).is_none());
+        assert!(quote(&moon, &sun, 1).is_none());
+    }
+}
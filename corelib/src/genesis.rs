@@ -0,0 +1,235 @@
+//! Genesis wallet provisioning.
+//!
+//! A genesis file lists one wallet per line in label-file canon,
+//! `;phone;label;O1;O2;O3;O4;O5;O6;O7;O8;`, with the starting balances in
+//! `O1` (`earth:1000,moon:5`). Blank lines and `#` comments are skipped.
+//! [`GenesisPlan::parse`] checks every line and drops exact repeats of a
+//! wallet; a repeat with different content is an error. A clean plan folds
+//! into per-planet ledgers and [`GenesisPlan::seal`] chains block 1 over
+//! their master root, so every node provisioned from the same file starts
+//! from the same root.
+
+use serde::Serialize;
+use spec::PlanetId;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::consensus::BlockHeader;
+use crate::label_file::LabelFile;
+use crate::master_root_for;
+use crate::privacy::is_phone_key;
+
+/// Ledgers per planet, keyed by canon label `;phone;label;`.
+pub type Ledgers = BTreeMap<PlanetId, BTreeMap<String, u128>>;
+
+/// One wallet and the line it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenesisWallet {
+    pub line: usize,
+    pub file: LabelFile,
+}
+
+impl GenesisWallet {
+    /// Ledger key `;phone;label;`.
+    pub fn key(&self) -> String {
+        format!(";{};{};", self.file.phone, self.file.label)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GenesisIssue {
+    /// 1-based line in the genesis file.
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GenesisPlan {
+    /// In file order, without repeats.
+    pub wallets: Vec<GenesisWallet>,
+    /// Exact repeats that were dropped.
+    pub duplicates: Vec<GenesisIssue>,
+    pub errors: Vec<GenesisIssue>,
+}
+
+impl GenesisPlan {
+    pub fn parse(text: &str) -> Self {
+        let mut plan = Self::default();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let raw = raw.trim();
+            if raw.is_empty() || raw.starts_with('#') {
+                continue;
+            }
+            let file = match LabelFile::decode(raw) {
+                Ok(file) => file,
+                Err(err) => {
+                    plan.reject(line, err.to_string());
+                    continue;
+                }
+            };
+            if !looks_like_phone(&file.phone) {
+                plan.reject(line, format!("`{}` is not a phone number", file.phone));
+                continue;
+            }
+            let wallet = GenesisWallet { line, file };
+            match seen.get(&wallet.key()) {
+                Some(&at) => {
+                    let first = &plan.wallets[at];
+                    let issue = GenesisIssue {
+                        line,
+                        message: format!("{} repeats line {}", wallet.key(), first.line),
+                    };
+                    if first.file == wallet.file {
+                        plan.duplicates.push(issue);
+                    } else {
+                        plan.reject(
+                            line,
+                            format!("{} conflicts with line {}", wallet.key(), first.line),
+                        );
+                    }
+                }
+                None => {
+                    seen.insert(wallet.key(), plan.wallets.len());
+                    plan.wallets.push(wallet);
+                }
+            }
+        }
+        plan
+    }
+
+    /// Record a problem with `line`, e.g. from checks the caller adds.
+    pub fn reject(&mut self, line: usize, message: impl Into<String>) {
+        self.errors.push(GenesisIssue {
+            line,
+            message: message.into(),
+        });
+        self.errors.sort_by_key(|issue| issue.line);
+    }
+
+    pub fn ledgers(&self) -> Ledgers {
+        let mut ledgers = Ledgers::new();
+        for wallet in &self.wallets {
+            for (planet, amount) in &wallet.file.balances {
+                ledgers
+                    .entry(*planet)
+                    .or_default()
+                    .insert(wallet.key(), *amount);
+            }
+        }
+        ledgers
+    }
+
+    /// Block 1 over the plan's ledgers, sealed by `producer` at `sealed_ms`,
+    /// and the report to verify it by.
+    pub fn seal(&self, producer: &str, sealed_ms: i64) -> GenesisReport {
+        let ledgers = self.ledgers();
+        let master_root = master_root_for(1, &ledgers);
+        let block = BlockHeader::next(None, producer, sealed_ms, &master_root);
+        GenesisReport {
+            wallets: self.wallets.len(),
+            totals: ledgers
+                .iter()
+                .map(|(planet, ledger)| (*planet, ledger.values().sum()))
+                .collect(),
+            duplicates: self.duplicates.clone(),
+            errors: self.errors.clone(),
+            master_root,
+            block,
+        }
+    }
+}
+
+fn looks_like_phone(phone: &str) -> bool {
+    let digits = phone.strip_prefix('+').unwrap_or(phone);
+    is_phone_key(phone)
+        || ((7..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GenesisReport {
+    pub wallets: usize,
+    /// Sum of starting balances per planet.
+    pub totals: BTreeMap<PlanetId, u128>,
+    pub duplicates: Vec<GenesisIssue>,
+    pub errors: Vec<GenesisIssue>,
+    /// Ledger master root at height 1, the block's `state_root`.
+    pub master_root: String,
+    pub block: BlockHeader,
+}
+
+impl GenesisReport {
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for GenesisReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "genesis: {} wallets, {} duplicates dropped, {} errors",
+            self.wallets,
+            self.duplicates.len(),
+            self.errors.len()
+        )?;
+        for (planet, total) in &self.totals {
+            writeln!(f, "  {}: {total}", planet.key())?;
+        }
+        for issue in &self.duplicates {
+            writeln!(f, "  line {}: {}", issue.line, issue.message)?;
+        }
+        for issue in &self.errors {
+            writeln!(f, "  line {}: error: {}", issue.line, issue.message)?;
+        }
+        writeln!(f, "master root: {}", self.master_root)?;
+        writeln!(
+            f,
+            "block {} by {}: {}",
+            self.block.height, self.block.producer, self.block.root
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genesis_files_dedupe_validate_and_seal_block_one() {
+        let text = "\
+# genesis
+;9132077554;vortex;earth:8000;∞;∞;∞;∞;∞;∞;∞;
+;9132077554;comet;earth:800,moon:8;∞;∞;∞;∞;∞;∞;∞;
+
+;9132077554;vortex;earth:8000;∞;∞;∞;∞;∞;∞;∞;
+;9132077554;comet;earth:1;∞;∞;∞;∞;∞;∞;∞;
+;pluto;x;earth:1;∞;∞;∞;∞;∞;∞;∞;
+;5550001111;main;mars:oops;∞;∞;∞;∞;∞;∞;∞;
+;5550001111;main;∞;∞;∞;∞;∞;∞;∞;∞;
+";
+        let plan = GenesisPlan::parse(text);
+        assert_eq!(plan.wallets.len(), 3);
+        assert_eq!(plan.duplicates.len(), 1);
+        assert_eq!(plan.duplicates[0].line, 5);
+        let lines: Vec<usize> = plan.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [6, 7, 8]);
+
+        let report = plan.seal("genesis", 0);
+        assert!(!report.is_clean());
+        assert_eq!(report.totals[&PlanetId::Earth], 8_800);
+        assert_eq!(report.totals[&PlanetId::Moon], 8);
+        assert_eq!(report.block.height, 1);
+        assert_eq!(report.block.state_root, report.master_root);
+        assert!(report.block.extends(None) && report.block.root_is_valid());
+        assert_eq!(report.master_root, master_root_for(1, &plan.ledgers()));
+        // Order in the file doesn't move the root.
+        let clean: Vec<&str> = text.lines().skip(1).take(2).collect();
+        let swapped = format!("{}\n{}", clean[1], clean[0]);
+        let mut again = GenesisPlan::parse(&swapped);
+        again.wallets.extend(plan.wallets[2..].iter().cloned());
+        let again = again.seal("genesis", 0);
+        assert_eq!(again.master_root, report.master_root);
+    }
+}
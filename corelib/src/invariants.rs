@@ -0,0 +1,125 @@
+//! Invariant reports shared by the gateway's and the sim's checkers.
+//!
+//! Each service checks the state it owns (the gateway its ledgers, the sim
+//! its chunks) by stating what a value should be and what it is. A report
+//! keeps every mismatch as a [`Violation`] carrying both sides, so an alert
+//! is its own diff. An [`AlertGate`] decides which of them to raise.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub invariant: String,
+    /// What broke it: a planet, a chunk, a position.
+    pub subject: String,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InvariantReport {
+    pub checked_at_ms: i64,
+    /// Subjects checked per invariant, broken or not.
+    pub checked: BTreeMap<String, usize>,
+    pub violations: Vec<Violation>,
+}
+
+impl InvariantReport {
+    pub fn new(checked_at_ms: i64) -> Self {
+        Self {
+            checked_at_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Check that `subject` holds `expected` under `invariant`.
+    pub fn expect<T: Serialize + PartialEq>(
+        &mut self,
+        invariant: &str,
+        subject: impl Into<String>,
+        expected: T,
+        actual: T,
+    ) {
+        *self.checked.entry(invariant.to_string()).or_default() += 1;
+        if expected != actual {
+            self.violations.push(Violation {
+                invariant: invariant.to_string(),
+                subject: subject.into(),
+                expected: serde_json::to_value(expected).unwrap_or_default(),
+                actual: serde_json::to_value(actual).unwrap_or_default(),
+            });
+        }
+    }
+
+    pub fn ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Which violations to alert on. Checks run while the service keeps
+/// writing, so one can land between two writes of the same operation and
+/// see a break that isn't there: a violation alerts once two checks in a
+/// row report it, and not again until a check finds it gone (a changed
+/// `actual` counts as a new violation).
+#[derive(Debug, Default)]
+pub struct AlertGate {
+    previous: Vec<Violation>,
+    alerted: Vec<Violation>,
+}
+
+impl AlertGate {
+    /// Violations of `report` to alert on now.
+    pub fn admit(&mut self, report: &InvariantReport) -> Vec<Violation> {
+        let confirmed: Vec<Violation> = report
+            .violations
+            .iter()
+            .filter(|v| self.previous.contains(v))
+            .cloned()
+            .collect();
+        let fresh = confirmed
+            .iter()
+            .filter(|v| !self.alerted.contains(v))
+            .cloned()
+            .collect();
+        self.previous = report.violations.clone();
+        self.alerted = confirmed;
+        fresh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_diff_and_alert_once() {
+        let mut first = InvariantReport::new(1);
+        first.expect("supply", "earth", 100u128, 100);
+        first.expect("supply", "moon", 8u128, 7);
+        assert!(!first.ok());
+        assert_eq!(first.checked["supply"], 2);
+        assert_eq!(first.violations[0].expected, serde_json::json!(8));
+        assert_eq!(first.violations[0].actual, serde_json::json!(7));
+
+        let mut gate = AlertGate::default();
+        assert!(gate.admit(&first).is_empty());
+        let mut second = InvariantReport::new(2);
+        second.expect("supply", "moon", 8u128, 7);
+        second.expect("supply", "mars", 5u128, 6);
+        let fresh = gate.admit(&second);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].subject, "moon");
+        // Mars is confirmed now; Moon already alerted.
+        let fresh = gate.admit(&second);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].subject, "mars");
+        assert!(gate.admit(&second).is_empty());
+
+        // Once clear, a recurrence alerts again after two checks.
+        assert!(gate.admit(&InvariantReport::new(3)).is_empty());
+        assert!(gate.admit(&first).is_empty());
+        assert_eq!(gate.admit(&first).len(), 1);
+    }
+}
@@ -0,0 +1,321 @@
+//! Per-label universe files.
+//!
+//! Canon gives every `(phone, label)` its own semicolon file named
+//! `;phone;label;∞;∞;∞;∞;∞;∞;∞;∞;hash;` whose contents are
+//! `;phone;label;O1;O2;O3;O4;O5;O6;O7;O8;`. Here the Omega segments are:
+//!
+//! - `O1` balances per planet, `earth:1000,moon:5`
+//! - `O2` history refs, oldest first, `t12,t40`
+//! - `O3` key metadata, `device=ph01,sig=ed25519`
+//! - `O4`–`O8` reserved, written as `∞`
+//!
+//! An empty segment is written as `∞` too. No dots anywhere, and values may
+//! not contain the separators `;`, `,`, `:` or `=`, nor path slashes.
+
+use spec::PlanetId;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// History refs kept per file; the oldest are dropped first.
+pub const HISTORY_MAX: usize = 64;
+/// Longest label name, in bytes.
+pub const LABEL_MAX: usize = 32;
+const SEGMENTS: usize = 8;
+const EMPTY: &str = "∞";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelFile {
+    pub phone: String,
+    pub label: String,
+    pub balances: BTreeMap<PlanetId, u128>,
+    pub history: Vec<String>,
+    pub keys: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LabelFileError {
+    /// Not `;phone;label;O1;…;O8;`.
+    Shape(String),
+    /// A value that would break the canon encoding.
+    Invalid(String),
+    /// Merging files that belong to different labels.
+    Mismatch { expected: String, found: String },
+}
+
+impl fmt::Display for LabelFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelFileError::Shape(why) => write!(f, "malformed label file: {why}"),
+            LabelFileError::Invalid(why) => write!(f, "invalid label file value: {why}"),
+            LabelFileError::Mismatch { expected, found } => {
+                write!(f, "label file for {found} cannot merge into {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LabelFileError {}
+
+impl From<LabelFileError> for io::Error {
+    fn from(err: LabelFileError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl LabelFile {
+    pub fn new(phone: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            phone: phone.into(),
+            label: label.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Empty file for a canon label key `;phone;label;`.
+    pub fn for_label_key(key: &str) -> Option<Self> {
+        match key
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [phone, label] => Some(Self::new(*phone, *label)),
+            _ => None,
+        }
+    }
+
+    /// Canon label key `;phone;label;`.
+    pub fn key(&self) -> String {
+        format!(";{};{};", self.phone, self.label)
+    }
+
+    /// `;phone;label;∞;∞;∞;∞;∞;∞;∞;∞;hash;`
+    pub fn filename(&self) -> String {
+        format!(
+            ";{};{};{}hash;",
+            self.phone,
+            self.label,
+            format!("{EMPTY};").repeat(SEGMENTS)
+        )
+    }
+
+    /// Append a history ref unless it is already present.
+    pub fn push_history(&mut self, reference: impl Into<String>) {
+        let reference = reference.into();
+        if !self.history.contains(&reference) {
+            self.history.push(reference);
+        }
+        let excess = self.history.len().saturating_sub(HISTORY_MAX);
+        self.history.drain(..excess);
+    }
+
+    /// True when the file carries nothing worth keeping (no balance, no history).
+    pub fn is_empty(&self) -> bool {
+        self.balances.values().all(|b| *b == 0) && self.history.is_empty()
+    }
+
+    /// Check every value against the canon encoding rules.
+    pub fn validate(&self) -> Result<(), LabelFileError> {
+        check_token("phone", &self.phone)?;
+        check_token("label", &self.label)?;
+        for reference in &self.history {
+            check_token("history ref", reference)?;
+        }
+        for (name, value) in &self.keys {
+            check_token("key name", name)?;
+            check_token("key value", value)?;
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Result<String, LabelFileError> {
+        self.validate()?;
+        let balances = self
+            .balances
+            .iter()
+            .map(|(planet, amount)| format!("{}:{amount}", planet.key()))
+            .collect::<Vec<_>>()
+            .join(",");
+        let keys = self
+            .keys
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut segments = vec![balances, self.history.join(","), keys];
+        segments.resize(SEGMENTS, String::new());
+        let body = segments
+            .iter()
+            .map(|s| if s.is_empty() { EMPTY } else { s.as_str() })
+            .collect::<Vec<_>>()
+            .join(";");
+        Ok(format!(";{};{};{body};", self.phone, self.label))
+    }
+
+    pub fn decode(text: &str) -> Result<Self, LabelFileError> {
+        let text = text.trim();
+        let inner = text
+            .strip_prefix(';')
+            .and_then(|t| t.strip_suffix(';'))
+            .ok_or_else(|| LabelFileError::Shape("must start and end with ';'".into()))?;
+        let parts: Vec<&str> = inner.split(';').collect();
+        if parts.len() != SEGMENTS + 2 {
+            return Err(LabelFileError::Shape(format!(
+                "expected phone, label and {SEGMENTS} segments, found {} parts",
+                parts.len()
+            )));
+        }
+        let segment = |i: usize| Some(parts[i]).filter(|s| *s != EMPTY && !s.is_empty());
+        let mut file = Self::new(parts[0], parts[1]);
+        check_token("phone", &file.phone)?;
+        check_token("label", &file.label)?;
+
+        for entry in segment(2).into_iter().flat_map(|s| s.split(',')) {
+            let (planet, amount) = entry
+                .split_once(':')
+                .ok_or_else(|| LabelFileError::Shape(format!("balance `{entry}`")))?;
+            let planet = PlanetId::parse(planet)
+                .ok_or_else(|| LabelFileError::Invalid(format!("unknown planet `{planet}`")))?;
+            let amount = amount
+                .parse()
+                .map_err(|_| LabelFileError::Invalid(format!("balance `{amount}`")))?;
+            file.balances.insert(planet, amount);
+        }
+        for reference in segment(3).into_iter().flat_map(|s| s.split(',')) {
+            file.push_history(reference);
+        }
+        for entry in segment(4).into_iter().flat_map(|s| s.split(',')) {
+            let (name, value) = entry
+                .split_once('=')
+                .ok_or_else(|| LabelFileError::Shape(format!("key `{entry}`")))?;
+            file.keys.insert(name.into(), value.into());
+        }
+        Ok(file)
+    }
+
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Self::decode(&text)?)
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(self.encode()?.as_bytes())
+    }
+
+    /// Fold a newer snapshot of the same label into this one: balances are
+    /// replaced, history refs are appended without duplicates, and keys are
+    /// overwritten one by one.
+    pub fn merge(&mut self, newer: &LabelFile) -> Result<(), LabelFileError> {
+        if (&self.phone, &self.label) != (&newer.phone, &newer.label) {
+            return Err(LabelFileError::Mismatch {
+                expected: format!(";{};{};", self.phone, self.label),
+                found: format!(";{};{};", newer.phone, newer.label),
+            });
+        }
+        self.balances = newer.balances.clone();
+        for reference in &newer.history {
+            self.push_history(reference.clone());
+        }
+        self.keys
+            .extend(newer.keys.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(())
+    }
+}
+
+/// A label name as ledgers key it: `;` around it and surrounding space
+/// dropped, lowercased, 1-[`LABEL_MAX`] of `a-z`, `0-9`, `_` and `-`.
+pub fn canonical_label(raw: &str) -> Result<String, LabelFileError> {
+    let name = raw.trim().trim_matches(';').to_ascii_lowercase();
+    if name.is_empty() || name.len() > LABEL_MAX {
+        return Err(LabelFileError::Invalid(format!(
+            "label must be 1-{LABEL_MAX} characters"
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return Err(LabelFileError::Invalid(
+            "label may only use a-z, 0-9, '_' and '-'".into(),
+        ));
+    }
+    Ok(name)
+}
+
+fn check_token(what: &str, value: &str) -> Result<(), LabelFileError> {
+    if value.is_empty() || value == EMPTY {
+        return Err(LabelFileError::Invalid(format!("{what} is empty")));
+    }
+    if value.chars().any(|c| {
+        matches!(c, ';' | ',' | ':' | '=' | '.' | '/' | '\\') || c.is_whitespace() || c.is_control()
+    }) {
+        return Err(LabelFileError::Invalid(format!(
+            "{what} `{value}` contains a separator, dot, slash or whitespace"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> LabelFile {
+        let mut file = LabelFile::for_label_key(";9132077554;fun;").unwrap();
+        file.balances.insert(PlanetId::Earth, 80_000);
+        file.balances.insert(PlanetId::Moon, 5);
+        file.push_history("t12");
+        file.push_history("t40");
+        file.keys.insert("device".into(), "ph01".into());
+        file
+    }
+
+    #[test]
+    fn round_trips_through_canon_text() {
+        let file = sample();
+        assert_eq!(file.filename(), ";9132077554;fun;∞;∞;∞;∞;∞;∞;∞;∞;hash;");
+        let text = file.encode().unwrap();
+        assert_eq!(
+            text,
+            ";9132077554;fun;earth:80000,moon:5;t12,t40;device=ph01;∞;∞;∞;∞;∞;"
+        );
+        assert!(!text.contains('.'));
+        assert_eq!(LabelFile::decode(&text).unwrap(), file);
+
+        let mut buf = Vec::new();
+        file.write(&mut buf).unwrap();
+        assert_eq!(LabelFile::read(buf.as_slice()).unwrap(), file);
+
+        let empty = LabelFile::new("9132077554", "comet");
+        assert_eq!(LabelFile::decode(&empty.encode().unwrap()).unwrap(), empty);
+        assert_eq!(LabelFile::for_label_key(&empty.key()), Some(empty));
+        assert_eq!(canonical_label(" ;Comet; ").unwrap(), "comet");
+        assert!(canonical_label("has space").is_err());
+
+        assert!(LabelFile::decode(";9132077554;fun;earth:1;").is_err());
+        let mut dotted = sample();
+        dotted.keys.insert("host".into(), "dlog.gold".into());
+        assert!(matches!(dotted.encode(), Err(LabelFileError::Invalid(_))));
+    }
+
+    #[test]
+    fn merge_replaces_balances_and_unions_history() {
+        let mut on_disk = sample();
+        let mut newer = LabelFile::new("9132077554", "fun");
+        newer.balances.insert(PlanetId::Earth, 70_000);
+        newer.push_history("t40");
+        newer.push_history("t41");
+        newer.keys.insert("sig".into(), "ed25519".into());
+        on_disk.merge(&newer).unwrap();
+
+        assert_eq!(on_disk.balances, newer.balances);
+        assert_eq!(on_disk.history, ["t12", "t40", "t41"]);
+        assert_eq!(on_disk.keys.len(), 2);
+        assert!(matches!(
+            on_disk.merge(&LabelFile::new("9132077554", "comet")),
+            Err(LabelFileError::Mismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+//! Transaction log behind a [`UniverseSnapshot`].
+//!
+//! A [`Ledger`] owns the current snapshot and every transaction that built
+//! it, stamped with the height it landed at and the φ tick it was applied
+//! on. Mints and transfers land at the current height; interest accrual
+//! moves the height forward. Replaying the log from genesis (an empty
+//! universe) reproduces the snapshot at any recorded height, root included.
+
+use serde::{Deserialize, Serialize};
+use spec::{LabelId, MonetarySpec};
+use std::fmt;
+
+use crate::UniverseSnapshot;
+
+/// What a ledger entry did.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Transaction {
+    /// New supply credited to `to`.
+    Mint { to: LabelId, amount: f64 },
+    Transfer {
+        from: LabelId,
+        to: LabelId,
+        amount: f64,
+    },
+    /// Holder interest over `blocks`; advances the height by as much.
+    Interest { blocks: u64 },
+}
+
+/// One applied transaction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Height after the transaction.
+    pub height: u64,
+    pub tick: u64,
+    #[serde(flatten)]
+    pub transaction: Transaction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LedgerError {
+    /// Mints and transfers move a positive, finite amount.
+    InvalidAmount(f64),
+    InsufficientFunds {
+        label: LabelId,
+        balance: f64,
+        amount: f64,
+    },
+    /// A tick stamp below the last entry's.
+    TickRewound { tick: u64, last: u64 },
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::InvalidAmount(amount) => write!(f, "invalid amount {amount}"),
+            LedgerError::InsufficientFunds {
+                label,
+                balance,
+                amount,
+            } => write!(
+                f,
+                ";{};{}; insufficient: {balance} < {amount}",
+                label.phone, label.label
+            ),
+            LedgerError::TickRewound { tick, last } => {
+                write!(f, "tick {tick} is before the last entry's {last}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+/// A universe snapshot with the log of how it got there.
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    spec: MonetarySpec,
+    snapshot: UniverseSnapshot,
+    entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    /// Empty universe at height 0.
+    pub fn new(spec: MonetarySpec) -> Self {
+        Self {
+            spec,
+            snapshot: UniverseSnapshot::empty(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Rebuild a ledger by replaying stored `entries` from genesis. Fails on
+    /// the first entry that doesn't apply, so a tampered log won't load.
+    pub fn from_entries(
+        spec: MonetarySpec,
+        entries: Vec<LedgerEntry>,
+    ) -> Result<Self, LedgerError> {
+        let mut ledger = Self::new(spec);
+        for entry in entries {
+            ledger.apply_transaction(entry.tick, entry.transaction)?;
+        }
+        Ok(ledger)
+    }
+
+    pub fn snapshot(&self) -> &UniverseSnapshot {
+        &self.snapshot
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+
+    pub fn balance(&self, label: &LabelId) -> f64 {
+        self.snapshot
+            .balances
+            .get(label)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Apply `transaction` on `tick` and append it to the log. A rejected
+    /// transaction leaves both untouched.
+    pub fn apply_transaction(
+        &mut self,
+        tick: u64,
+        transaction: Transaction,
+    ) -> Result<&LedgerEntry, LedgerError> {
+        if let Some(last) = self.entries.last() {
+            if tick < last.tick {
+                return Err(LedgerError::TickRewound {
+                    tick,
+                    last: last.tick,
+                });
+            }
+        }
+        apply(&mut self.snapshot, &self.spec, &transaction)?;
+        self.entries.push(LedgerEntry {
+            height: self.snapshot.height,
+            tick,
+            transaction,
+        });
+        Ok(self.entries.last().expect("entry pushed above"))
+    }
+
+    /// Snapshot after every entry at or below `height`, replayed from
+    /// genesis. Interest that would carry past `height` is left out, so the
+    /// result may sit below `height`.
+    pub fn replay_to(&self, height: u64) -> Result<UniverseSnapshot, LedgerError> {
+        let mut snapshot = UniverseSnapshot::empty();
+        for entry in self.entries.iter().take_while(|e| e.height <= height) {
+            apply(&mut snapshot, &self.spec, &entry.transaction)?;
+        }
+        Ok(snapshot)
+    }
+}
+
+fn apply(
+    snapshot: &mut UniverseSnapshot,
+    spec: &MonetarySpec,
+    transaction: &Transaction,
+) -> Result<(), LedgerError> {
+    let check = |amount: f64| {
+        if amount.is_finite() && amount > 0.0 {
+            Ok(())
+        } else {
+            Err(LedgerError::InvalidAmount(amount))
+        }
+    };
+    match transaction {
+        Transaction::Mint { to, amount } => {
+            check(*amount)?;
+            *snapshot.balances.entry(to.clone()).or_default() += amount;
+        }
+        Transaction::Transfer { from, to, amount } => {
+            check(*amount)?;
+            let balance = snapshot.balances.get(from).copied().unwrap_or_default();
+            if balance < *amount {
+                return Err(LedgerError::InsufficientFunds {
+                    label: from.clone(),
+                    balance,
+                    amount: *amount,
+                });
+            }
+            snapshot.balances.insert(from.clone(), balance - amount);
+            *snapshot.balances.entry(to.clone()).or_default() += amount;
+        }
+        Transaction::Interest { blocks } => {
+            snapshot.apply_holder_interest(*blocks, spec);
+            return Ok(());
+        }
+    }
+    snapshot.refresh_master_root();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(name: &str) -> LabelId {
+        LabelId {
+            phone: "9132077554".into(),
+            label: name.into(),
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_every_recorded_height() {
+        let mut ledger = Ledger::new(MonetarySpec::default());
+        ledger
+            .apply_transaction(
+                1,
+                Transaction::Mint {
+                    to: label("comet"),
+                    amount: 1_000.0,
+                },
+            )
+            .unwrap();
+        ledger
+            .apply_transaction(2, Transaction::Interest { blocks: 8 })
+            .unwrap();
+        let at_eight = ledger.snapshot().clone();
+        ledger
+            .apply_transaction(
+                3,
+                Transaction::Transfer {
+                    from: label("comet"),
+                    to: label("fun"),
+                    amount: 400.0,
+                },
+            )
+            .unwrap();
+        ledger
+            .apply_transaction(4, Transaction::Interest { blocks: 8 })
+            .unwrap();
+        assert_eq!(ledger.snapshot().height, 16);
+
+        // A refused transfer leaves no entry behind.
+        let overdraw = Transaction::Transfer {
+            from: label("fun"),
+            to: label("comet"),
+            amount: 1e9,
+        };
+        assert!(matches!(
+            ledger.apply_transaction(5, overdraw),
+            Err(LedgerError::InsufficientFunds { .. })
+        ));
+        assert!(matches!(
+            ledger.apply_transaction(0, Transaction::Interest { blocks: 1 }),
+            Err(LedgerError::TickRewound { tick: 0, last: 4 })
+        ));
+        assert_eq!(ledger.entries().len(), 4);
+
+        // Height 8 holds the transfer too; interest to 16 is left out.
+        let replayed = ledger.replay_to(15).unwrap();
+        assert_eq!(replayed.height, 8);
+        assert_eq!(replayed.balances[&label("fun")], 400.0);
+        assert_eq!(
+            replayed.balances[&label("comet")],
+            at_eight.balances[&label("comet")] - 400.0
+        );
+        assert_eq!(
+            ledger.replay_to(0).unwrap().balances[&label("comet")],
+            1_000.0
+        );
+
+        let full = ledger.replay_to(u64::MAX).unwrap();
+        assert_eq!(
+            full.master_root_infinity,
+            ledger.snapshot().master_root_infinity
+        );
+
+        let json = serde_json::to_string(ledger.entries()).unwrap();
+        let stored: Vec<LedgerEntry> = serde_json::from_str(&json).unwrap();
+        let reloaded = Ledger::from_entries(MonetarySpec::default(), stored).unwrap();
+        assert_eq!(
+            reloaded.snapshot().master_root_infinity,
+            ledger.snapshot().master_root_infinity
+        );
+        assert!(reloaded.balance(&label("fun")) > 400.0);
+    }
+}
@@ -1,18 +1,49 @@
 //! Core DLOG Ω state machine utilities.
 //!
-//! This crate stays pure & deterministic: no IO, no sockets.
+//! This crate stays pure & deterministic: no IO, no sockets. The one
+//! exception is the opt-in `async` feature, which streams files and readers
+//! into sha-less digests.
 //! It knows how to:
 //! - Represent a universe snapshot (block height + balances)
+//! - Log typed transactions behind a snapshot and replay it to any height
 //! - Apply φ-based holder interest over N blocks
-//! - Render block height as base-8 text for UI/logs
+//! - Render block height, durations and factors as base-8 text for UI/logs
+//! - Queue height-keyed tasks for the block producer
+//! - Digest arbitrary state into sha-less roots for cross-node checks
+//! - Encode hash inputs as canonical JSON (sorted keys, fixed floats)
+//! - Chain block headers and decide who may produce them (consensus), and
+//!   MAC client frames under per-session keys
+//! - Key and redact phone numbers for privacy mode
+//! - Encode per-label `;phone;label;…;` universe files
+//! - Upgrade stored JSON objects across schema versions
+//! - Convert amounts between planet currency zones
+//! - Parse genesis wallet files and seal block 1 over their balances
+//! - Report broken invariants with what was expected and what was found
 
+pub mod canonical;
+pub mod consensus;
+pub mod exchange;
+pub mod genesis;
+pub mod invariants;
+pub mod label_file;
+pub mod ledger;
+pub mod migrate;
+pub mod octal;
+pub mod privacy;
+mod scheduler;
 mod shaless;
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use shaless::master_root_for;
-use spec::{LabelId, MonetarySpec};
+pub use ledger::{Ledger, LedgerEntry, LedgerError, Transaction};
+pub use scheduler::{HeightScheduler, ScheduledTask};
+pub use shaless::{
+    master_root_for, shaless_digest, shaless_json_digest, shaless_leading_zeros, ShalessHasher,
+};
+#[cfg(feature = "async")]
+pub use shaless::{shaless_digest_file, shaless_digest_reader};
+use spec::{CompoundingMode, LabelId, MonetarySpec};
 
 /// Snapshot of balances at a given block height.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -42,6 +73,10 @@ impl UniverseSnapshot {
     /// This directly mirrors the MonetarySpec:
     /// - holder_yearly_factor ≈ φ
     /// - blocks_per_attention_year ≈ 3.9M (octal literal in spec)
+    ///
+    /// The per-block rate is fixed by the APY; `compounding` decides how it
+    /// lands (see [`CompoundingMode`]). Per-8-block interest only lands on
+    /// heights divisible by 8, so it depends on where `height` starts.
     pub fn apply_holder_interest(&mut self, blocks_elapsed: u64, spec: &MonetarySpec) {
         if blocks_elapsed == 0 {
             return;
@@ -50,9 +85,18 @@ impl UniverseSnapshot {
         let yearly = 1.0 + spec.holder_interest_apy;
         let blocks_per_year = (365.0 * 24.0 * 60.0 * 60.0) / spec.target_block_seconds;
 
-        // per-block factor = yearly^(1 / blocks_per_year)
-        let per_block = yearly.powf(1.0 / blocks_per_year);
-        let total_factor = per_block.powf(blocks_elapsed as f64);
+        // per-block rate = yearly^(1 / blocks_per_year) - 1
+        let rate = yearly.powf(1.0 / blocks_per_year) - 1.0;
+        let total_factor = match spec.compounding {
+            CompoundingMode::PerBlock => (1.0 + rate).powf(blocks_elapsed as f64),
+            CompoundingMode::PerEightBlocks => {
+                let period = spec.compounding.period_blocks();
+                let end = self.height.saturating_add(blocks_elapsed);
+                let periods = end / period - self.height / period;
+                (1.0 + rate * period as f64).powf(periods as f64)
+            }
+            CompoundingMode::Continuous => (rate * blocks_elapsed as f64).exp(),
+        };
 
         for value in self.balances.values_mut() {
             *value *= total_factor;
@@ -63,7 +107,14 @@ impl UniverseSnapshot {
     }
 
     fn refresh_master_root(&mut self) {
-        self.master_root_infinity = master_root_for(self.height, &self.balances);
+        // Canon `;phone;label;` keys, sorted: JSON maps need string keys and
+        // HashMap order would differ between nodes.
+        let sorted: BTreeMap<String, f64> = self
+            .balances
+            .iter()
+            .map(|(id, value)| (format!(";{};{};", id.phone, id.label), *value))
+            .collect();
+        self.master_root_infinity = master_root_for(self.height, &sorted);
     }
 }
 
@@ -71,3 +122,49 @@ impl UniverseSnapshot {
 pub fn octal_height(height: u64) -> String {
     format!("{:o}", height)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compounding_modes_converge_over_a_year() {
+        let year = |compounding| {
+            let spec = MonetarySpec {
+                compounding,
+                ..MonetarySpec::default()
+            };
+            let blocks_per_year = (365.0 * 24.0 * 60.0 * 60.0 / spec.target_block_seconds) as u64;
+            let mut snapshot = UniverseSnapshot::empty();
+            let id = LabelId {
+                phone: "9132077554".into(),
+                label: "comet".into(),
+            };
+            snapshot.balances.insert(id.clone(), 1_000.0);
+            snapshot.apply_holder_interest(blocks_per_year, &spec);
+            snapshot.balances[&id]
+        };
+        let per_block = year(CompoundingMode::PerBlock);
+        let per_eight = year(CompoundingMode::PerEightBlocks);
+        let continuous = year(CompoundingMode::Continuous);
+        assert!((per_block - 1_618.0).abs() < 0.01);
+        assert!(per_eight < per_block && per_block < continuous);
+        assert!((continuous - per_eight) / per_block < 1e-6);
+
+        // Octave interest waits for a height divisible by 8.
+        let spec = MonetarySpec {
+            compounding: CompoundingMode::PerEightBlocks,
+            ..MonetarySpec::default()
+        };
+        let mut snapshot = UniverseSnapshot::empty();
+        let id = LabelId {
+            phone: "9132077554".into(),
+            label: "fun".into(),
+        };
+        snapshot.balances.insert(id.clone(), 1_000.0);
+        snapshot.apply_holder_interest(7, &spec);
+        assert_eq!(snapshot.balances[&id], 1_000.0);
+        snapshot.apply_holder_interest(1, &spec);
+        assert!(snapshot.balances[&id] > 1_000.0);
+    }
+}
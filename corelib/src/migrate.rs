@@ -0,0 +1,308 @@
+//! Schema versions for stored JSON objects.
+//!
+//! Every stored object kind has a [`Schema`]: an ordered list of
+//! [`Migration`]s, each lifting an object one version. Objects carry their
+//! version in a top-level `schema_version` field; objects written before the
+//! field existed count as version 0. [`Schema::upgrade`] runs the missing
+//! steps in order and stamps the result, so loaders can deserialize into the
+//! current type. Storage layers own the IO; a bulk `migrate` walks every key
+//! and fills a [`MigrationReport`], writing back only outside dry runs.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// One step from `to - 1` to `to`.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub to: u32,
+    /// What the step changes, for reports.
+    pub note: &'static str,
+    pub apply: fn(&mut Map<String, Value>) -> Result<(), String>,
+}
+
+/// Version history of one stored object kind.
+#[derive(Clone, Copy)]
+pub struct Schema {
+    pub kind: &'static str,
+    /// Steps ordered by `to`, starting at 1 with no gaps.
+    pub migrations: &'static [Migration],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// Only JSON objects can carry a version.
+    NotAnObject(&'static str),
+    /// Written by a newer binary; upgrading would drop what it added.
+    Newer {
+        kind: &'static str,
+        found: u32,
+        current: u32,
+    },
+    /// `schema_version` is not a non-negative integer.
+    BadVersion(&'static str),
+    Step {
+        kind: &'static str,
+        to: u32,
+        why: String,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NotAnObject(kind) => write!(f, "{kind} is not a JSON object"),
+            MigrationError::Newer {
+                kind,
+                found,
+                current,
+            } => write!(
+                f,
+                "{kind} schema v{found} is newer than this binary (v{current})"
+            ),
+            MigrationError::BadVersion(kind) => {
+                write!(f, "{kind} has an unreadable {SCHEMA_VERSION_FIELD}")
+            }
+            MigrationError::Step { kind, to, why } => {
+                write!(f, "{kind} migration to v{to} failed: {why}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// What [`Schema::upgrade`] did to one object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Upgrade {
+    pub from: u32,
+    pub to: u32,
+    pub applied: Vec<&'static str>,
+}
+
+impl Upgrade {
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+impl Schema {
+    pub fn current(&self) -> u32 {
+        self.migrations.last().map(|m| m.to).unwrap_or_default()
+    }
+
+    /// Version `value` was written at.
+    pub fn version_of(&self, value: &Value) -> Result<u32, MigrationError> {
+        let object = value
+            .as_object()
+            .ok_or(MigrationError::NotAnObject(self.kind))?;
+        match object.get(SCHEMA_VERSION_FIELD) {
+            None => Ok(0),
+            Some(version) => version
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or(MigrationError::BadVersion(self.kind)),
+        }
+    }
+
+    /// Run every step past the object's version, in order, and stamp it.
+    /// On error `value` may be half-migrated; callers drop it.
+    pub fn upgrade(&self, value: &mut Value) -> Result<Upgrade, MigrationError> {
+        let from = self.version_of(value)?;
+        let current = self.current();
+        if from > current {
+            return Err(MigrationError::Newer {
+                kind: self.kind,
+                found: from,
+                current,
+            });
+        }
+        let object = value
+            .as_object_mut()
+            .ok_or(MigrationError::NotAnObject(self.kind))?;
+        let mut applied = Vec::new();
+        for step in self.migrations.iter().filter(|m| m.to > from) {
+            (step.apply)(object).map_err(|why| MigrationError::Step {
+                kind: self.kind,
+                to: step.to,
+                why,
+            })?;
+            applied.push(step.note);
+        }
+        object.insert(SCHEMA_VERSION_FIELD.into(), current.into());
+        Ok(Upgrade {
+            from,
+            to: current,
+            applied,
+        })
+    }
+
+    /// Mark a freshly serialized object as written at the current version.
+    pub fn stamp(&self, value: &mut Value) {
+        if let Some(object) = value.as_object_mut() {
+            object.insert(SCHEMA_VERSION_FIELD.into(), self.current().into());
+        }
+    }
+}
+
+/// Per-kind outcome of a bulk migration.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct KindReport {
+    pub current: u32,
+    pub up_to_date: usize,
+    /// Keys that needed (or, in a dry run, would need) an upgrade, with the
+    /// version they were at.
+    pub upgraded: BTreeMap<String, u32>,
+    pub failed: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MigrationReport {
+    pub dry_run: bool,
+    pub kinds: BTreeMap<&'static str, KindReport>,
+}
+
+impl MigrationReport {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Upgrade one stored object and tally the outcome. Returns the upgraded
+    /// value when it must be written back.
+    pub fn check(&mut self, schema: &Schema, key: &str, mut value: Value) -> Option<Value> {
+        let kind = self.kinds.entry(schema.kind).or_default();
+        kind.current = schema.current();
+        match schema.upgrade(&mut value) {
+            Ok(upgrade) if upgrade.changed() => {
+                kind.upgraded.insert(key.to_string(), upgrade.from);
+                Some(value)
+            }
+            Ok(_) => {
+                kind.up_to_date += 1;
+                None
+            }
+            Err(err) => {
+                kind.failed.insert(key.to_string(), err.to_string());
+                None
+            }
+        }
+    }
+
+    /// A key that could not even be read as JSON.
+    pub fn unreadable(&mut self, schema: &Schema, key: &str, why: impl fmt::Display) {
+        let kind = self.kinds.entry(schema.kind).or_default();
+        kind.current = schema.current();
+        kind.failed.insert(key.to_string(), why.to_string());
+    }
+
+    pub fn failures(&self) -> usize {
+        self.kinds.values().map(|k| k.failed.len()).sum()
+    }
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.dry_run {
+            "would upgrade"
+        } else {
+            "upgraded"
+        };
+        for (name, kind) in &self.kinds {
+            writeln!(
+                f,
+                "{name} (v{}): {} up to date, {verb} {}, {} failed",
+                kind.current,
+                kind.up_to_date,
+                kind.upgraded.len(),
+                kind.failed.len()
+            )?;
+            for (key, from) in &kind.upgraded {
+                writeln!(f, "  {key}: v{from} -> v{}", kind.current)?;
+            }
+            for (key, why) in &kind.failed {
+                writeln!(f, "  {key}: {why}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_pos(object: &mut Map<String, Value>) -> Result<(), String> {
+        let pos = object.remove("pos").ok_or("missing pos")?;
+        object.insert("position".into(), pos);
+        Ok(())
+    }
+
+    fn default_tags(object: &mut Map<String, Value>) -> Result<(), String> {
+        object.entry("tags").or_insert_with(|| json!([]));
+        Ok(())
+    }
+
+    const THING: Schema = Schema {
+        kind: "thing",
+        migrations: &[
+            Migration {
+                to: 1,
+                note: "rename pos to position",
+                apply: rename_pos,
+            },
+            Migration {
+                to: 2,
+                note: "default tags",
+                apply: default_tags,
+            },
+        ],
+    };
+
+    #[test]
+    fn upgrades_run_in_order_from_the_stored_version() {
+        let mut legacy = json!({ "pos": 3 });
+        let upgrade = THING.upgrade(&mut legacy).unwrap();
+        assert_eq!((upgrade.from, upgrade.to, upgrade.applied.len()), (0, 2, 2));
+        assert_eq!(
+            legacy,
+            json!({ "position": 3, "tags": [], "schema_version": 2 })
+        );
+
+        let mut v1 = json!({ "position": 4, "schema_version": 1 });
+        assert_eq!(THING.upgrade(&mut v1).unwrap().applied, ["default tags"]);
+        assert!(!THING.upgrade(&mut v1).unwrap().changed());
+
+        let mut newer = json!({ "schema_version": 9 });
+        assert!(matches!(
+            THING.upgrade(&mut newer),
+            Err(MigrationError::Newer { found: 9, .. })
+        ));
+    }
+
+    #[test]
+    fn reports_tally_without_writing() {
+        let mut report = MigrationReport::new(true);
+        assert!(report.check(&THING, "a", json!({ "pos": 1 })).is_some());
+        assert!(report
+            .check(
+                &THING,
+                "b",
+                json!({ "position": 1, "tags": [], "schema_version": 2 })
+            )
+            .is_none());
+        assert!(report.check(&THING, "c", json!({})).is_none());
+        let thing = &report.kinds["thing"];
+        assert_eq!(thing.upgraded["a"], 0);
+        assert_eq!(thing.up_to_date, 1);
+        assert!(thing.failed["c"].contains("missing pos"));
+        assert_eq!(report.failures(), 1);
+        assert!(report.to_string().contains("would upgrade 1"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Base-8 renderings of durations, countdowns and factors.
+//!
+//! Everything here is integer or exact binary arithmetic, so every node
+//! prints the same digits for the same value. Fractions get at most
+//! [`FRACTION_DIGITS`] octal places with trailing zeros dropped; a value
+//! that doesn't end there is cut (not rounded) and marked with `…`.
+
+/// Octal places after the point before a fraction is cut.
+pub const FRACTION_DIGITS: usize = 16;
+
+/// `n` in base 8.
+pub fn octal_int(n: u128) -> String {
+    format!("{n:o}")
+}
+
+/// `num / den` in base 8, e.g. `1_000_000 / 999_999` → `1.0000001…`.
+///
+/// Panics if `den` is zero or above `u128::MAX / 8`.
+pub fn octal_ratio(num: u128, den: u128) -> String {
+    assert!(
+        den > 0 && den <= u128::MAX >> 3,
+        "octal_ratio denominator out of range"
+    );
+    let mut out = octal_int(num / den);
+    let mut rest = num % den;
+    let mut digits = String::new();
+    while rest > 0 && digits.len() < FRACTION_DIGITS {
+        rest *= 8;
+        digits.push(char::from(b'0' + (rest / den) as u8));
+        rest %= den;
+    }
+    push_fraction(&mut out, &digits, rest > 0);
+    out
+}
+
+/// `x` in base 8. A float is a binary fraction, so its expansion is exact
+/// until it is cut; NaN and infinities print as in decimal.
+pub fn octal_f64(x: f64) -> String {
+    if !x.is_finite() {
+        return x.to_string();
+    }
+    let sign = if x.is_sign_negative() && x != 0.0 { "-" } else { "" };
+    let whole = x.abs().trunc();
+    let mut rest = x.abs() - whole;
+    let mut digits = String::new();
+    while rest > 0.0 && digits.len() < FRACTION_DIGITS {
+        // Scaling by 8 and dropping the integer part are both exact.
+        rest *= 8.0;
+        let digit = rest.trunc();
+        digits.push(char::from(b'0' + digit as u8));
+        rest -= digit;
+    }
+    let mut out = format!("{sign}{}", octal_int(whole as u128));
+    push_fraction(&mut out, &digits, rest > 0.0);
+    out
+}
+
+fn push_fraction(out: &mut String, digits: &str, cut: bool) {
+    let digits = if cut {
+        digits
+    } else {
+        digits.trim_end_matches('0')
+    };
+    if !digits.is_empty() {
+        out.push('.');
+        out.push_str(digits);
+    }
+    if cut {
+        out.push('…');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratios_and_floats_print_the_same_exact_octal_digits() {
+        assert_eq!(octal_int(0), "0");
+        assert_eq!(octal_int(3_900_000), "16701140");
+        assert_eq!(octal_ratio(16, 8), "2");
+        assert_eq!(octal_ratio(9, 8), "1.1");
+        assert_eq!(octal_ratio(1, 3), "0.2525252525252525…");
+        assert_eq!(octal_ratio(1_020_000, 1_000_000), "1.0121727024365605…");
+
+        assert_eq!(octal_f64(0.0), "0");
+        assert_eq!(octal_f64(-2.625), "-2.5");
+        assert_eq!(octal_f64(8_888.0), "21270");
+        assert_eq!(octal_f64(0.1), "0.0631463146314631…");
+        assert_eq!(octal_f64(f64::INFINITY), "inf");
+        // Floats with the same value print the same digits as the ratio.
+        assert_eq!(octal_f64(1.0 / 3.0), octal_ratio(1, 3));
+    }
+}
@@ -0,0 +1,279 @@
+//! Phone privacy helpers: salted phone keys for storage and log redaction.
+//!
+//! With a salt configured, phones are stored as `ph<32 hex>` keys (keyed
+//! BLAKE3 over the phone digits). Keys are idempotent, so a value that is
+//! already hashed passes through unchanged and lookups work from either form.
+
+use blake3::Hasher;
+
+const KEY_PREFIX: &str = "ph";
+const KEY_HEX_LEN: usize = 32;
+const REDACT_MIN_DIGITS: usize = 7;
+const REDACT_MAX_DIGITS: usize = 15;
+/// Bare runs outside canon segments: a national number up to one with a
+/// country code, short of a 13-digit ms timestamp.
+const REDACT_BARE_DIGITS: usize = 10;
+const REDACT_BARE_MAX_DIGITS: usize = 12;
+
+#[derive(Clone, Debug, Default)]
+pub struct PhonePrivacy {
+    key: Option<[u8; 32]>,
+    /// Separate key for deletion receipts so they never equal a phone key.
+    subject: Option<[u8; 32]>,
+}
+
+impl PhonePrivacy {
+    /// Privacy mode is on when `OMEGA_PHONE_SALT` is set and non-empty.
+    pub fn from_env() -> Self {
+        match std::env::var("OMEGA_PHONE_SALT") {
+            Ok(salt) if !salt.trim().is_empty() => Self::with_salt(&salt),
+            _ => Self::disabled(),
+        }
+    }
+
+    pub fn with_salt(salt: &str) -> Self {
+        let key_material = format!("omega-phone|{salt}");
+        let subject_material = format!("omega-subject|{salt}");
+        Self {
+            key: Some(*blake3::hash(key_material.as_bytes()).as_bytes()),
+            subject: Some(*blake3::hash(subject_material.as_bytes()).as_bytes()),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            key: None,
+            subject: None,
+        }
+    }
+
+    /// Key for `subject_digest`, derived from the salt. `None` without a
+    /// salt; callers then need a secret of their own.
+    pub fn subject_key(&self) -> Option<[u8; 32]> {
+        self.subject
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Storage key for a phone: salted hash in privacy mode, the phone otherwise.
+    pub fn phone_key(&self, phone: &str) -> String {
+        let phone = phone.trim();
+        let Some(key) = &self.key else {
+            return phone.to_string();
+        };
+        if is_phone_key(phone) {
+            return phone.to_string();
+        }
+        let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+        let mut hasher = Hasher::new_keyed(key);
+        hasher.update(digits.as_bytes());
+        let digest = hasher.finalize();
+        let mut out = String::with_capacity(KEY_PREFIX.len() + KEY_HEX_LEN);
+        out.push_str(KEY_PREFIX);
+        for b in &digest.as_bytes()[..KEY_HEX_LEN / 2] {
+            out.push_str(&format!("{b:02x}"));
+        }
+        out
+    }
+
+    /// Rewrite the phone segment of a canon label `;phone;label;`.
+    pub fn label_key(&self, label: &str) -> String {
+        if !self.enabled() {
+            return label.to_string();
+        }
+        let parts: Vec<&str> = label.split(';').filter(|s| !s.is_empty()).collect();
+        match parts.as_slice() {
+            [phone, rest @ ..] if !rest.is_empty() => {
+                format!(";{};{};", self.phone_key(phone), rest.join(";"))
+            }
+            _ => label.to_string(),
+        }
+    }
+}
+
+/// True when `value` already has the `ph<hex>` key shape.
+pub fn is_phone_key(value: &str) -> bool {
+    value
+        .strip_prefix(KEY_PREFIX)
+        .map(|hex| hex.len() == KEY_HEX_LEN && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .unwrap_or(false)
+}
+
+/// Short keyed digest of a phone key, safe to keep in audit receipts. The
+/// key must stay secret: phones are a small space, so an unkeyed digest could
+/// be reversed by hashing every number.
+pub fn subject_digest(key: &[u8; 32], phone_key: &str) -> String {
+    blake3::keyed_hash(key, phone_key.as_bytes()).to_hex()[..16].to_string()
+}
+
+/// Mask phone-shaped digit runs anywhere in `text`: canon segments
+/// (`;9132077554;fun;` → `;********54;fun;`), free-form log text, and
+/// quoted or `Debug` values (`phone: "+15551234567"`). Digits inside a
+/// longer token (hex digests, `ph<hex>` keys) or dashed onto one (uuid
+/// groups, zero-padded heights in file names) are left alone.
+pub fn redact_phones(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_run = (chars[i].is_ascii_digit()
+            || (chars[i] == '+' && chars.get(i + 1).is_some_and(char::is_ascii_digit)))
+            && !(i > 0 && is_token_char(chars[i - 1]));
+        if !starts_run {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let end = phone_run_end(&chars, i);
+        let glued_after = match chars.get(end) {
+            Some('-') => chars.get(end + 1).is_some_and(|&c| is_token_char(c)),
+            Some(&c) => is_token_char(c),
+            None => false,
+        };
+        let glued_before = i >= 2 && chars[i - 1] == '-' && is_token_char(chars[i - 2]);
+        if glued_after || glued_before {
+            // Part of a longer token (a uuid group, `balances-000000001234`):
+            // copy it through untouched.
+            let token_end = (end..chars.len())
+                .find(|&j| !is_token_char(chars[j]) && chars[j] != '-')
+                .unwrap_or(chars.len());
+            out.extend(&chars[i..token_end]);
+            i = token_end;
+            continue;
+        }
+        let run: String = chars[i..end].iter().collect();
+        let in_segment = i > 0 && chars[i - 1] == ';' && chars.get(end) == Some(&';');
+        if looks_like_phone(&run, in_segment) {
+            out.push_str(&redact_phone(&run));
+        } else {
+            out.push_str(&run);
+        }
+        i = end;
+    }
+    out
+}
+
+/// Mask a phone, keeping the last two digits for support conversations.
+pub fn redact_phone(phone: &str) -> String {
+    let digits: Vec<char> = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    let keep = digits.len().saturating_sub(2);
+    let mut out: String = std::iter::repeat_n('*', keep).collect();
+    out.extend(&digits[keep..]);
+    out
+}
+
+/// End of the run starting at `start`: an optional `+`, then digit groups
+/// joined by single dashes (`913-207-7554`).
+fn phone_run_end(chars: &[char], start: usize) -> usize {
+    let mut end = start + usize::from(chars[start] == '+');
+    while end < chars.len()
+        && (chars[end].is_ascii_digit()
+            || (chars[end] == '-'
+                && chars[end - 1].is_ascii_digit()
+                && chars.get(end + 1).is_some_and(char::is_ascii_digit)))
+    {
+        end += 1;
+    }
+    end
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A whole canon segment only needs enough digits; elsewhere a bare run
+/// must be phone length (so amounts and ms timestamps survive), and a `+`
+/// run anything up to E.164's 15 digits.
+fn looks_like_phone(run: &str, in_segment: bool) -> bool {
+    let digits = run.chars().filter(char::is_ascii_digit).count();
+    if in_segment || run.starts_with('+') {
+        (REDACT_MIN_DIGITS..=REDACT_MAX_DIGITS).contains(&digits)
+    } else {
+        (REDACT_BARE_DIGITS..=REDACT_BARE_MAX_DIGITS).contains(&digits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phone_keys_are_salted_and_idempotent() {
+        let privacy = PhonePrivacy::with_salt("pepper");
+        let key = privacy.phone_key(" 913-207-7554 ");
+        assert!(is_phone_key(&key));
+        assert_eq!(privacy.phone_key("9132077554"), key);
+        assert_eq!(privacy.phone_key(&key), key);
+        assert_ne!(PhonePrivacy::with_salt("salt").phone_key("9132077554"), key);
+        assert_eq!(
+            privacy.label_key(";9132077554;comet;"),
+            format!(";{key};comet;")
+        );
+        assert_eq!(
+            PhonePrivacy::disabled().phone_key("9132077554"),
+            "9132077554"
+        );
+
+        let subject = privacy.subject_key().unwrap();
+        assert_ne!(
+            subject_digest(&subject, &key),
+            subject_digest(&[0; 32], &key)
+        );
+        assert!(PhonePrivacy::disabled().subject_key().is_none());
+    }
+
+    #[test]
+    fn redaction_masks_phone_segments() {
+        assert_eq!(
+            redact_phones("bank::balance ;9132077554;fun; = 80000"),
+            "bank::balance ;********54;fun; = 80000"
+        );
+        assert_eq!(
+            redact_phones("sealed at 1716400000000"),
+            "sealed at 1716400000000"
+        );
+        assert_eq!(redact_phone("+19132077554"), "*********54");
+        assert_eq!(redact_phones(";+19132077554;x;"), ";*********54;x;");
+    }
+
+    #[test]
+    fn redaction_masks_phones_embedded_in_free_text() {
+        assert_eq!(
+            redact_phones("handshake from 9132077554 ok, callback +1 913-207-7554"),
+            "handshake from ********54 ok, callback +1 ********54"
+        );
+        assert_eq!(
+            redact_phones("lookup(9132077554): 5000000 at height 42"),
+            "lookup(********54): 5000000 at height 42"
+        );
+        // Digits inside hex digests, uuids and phone keys aren't phones.
+        let untouched = "ph9132077554aa0f 069a79f4-1234567890-4726 v9132077554 9132077554x";
+        assert_eq!(redact_phones(untouched), untouched);
+        // All-digit uuid groups and padded block heights are dashed onto a token.
+        let untouched = "player 123e4567-e89b-12d3-a456-426614174000 joined, \
+                         wrote balances-000000001234.csv, 9132077554-ab12";
+        assert_eq!(redact_phones(untouched), untouched);
+        assert_eq!(
+            redact_phones("call 913-207-7554 - ok"),
+            "call ********54 - ok"
+        );
+    }
+
+    #[test]
+    fn redaction_masks_quoted_and_debug_phones() {
+        assert_eq!(
+            redact_phones(r#"Identity { phone: "+15551234567", label: "fun" }"#),
+            r#"Identity { phone: "*********67", label: "fun" }"#
+        );
+        assert_eq!(
+            redact_phones(r#"{"phone":"9132077554","amount":300}"#),
+            r#"{"phone":"********54","amount":300}"#
+        );
+        assert_eq!(
+            redact_phones("phone: Some(\"5550001111\")"),
+            "phone: Some(\"********11\")"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Height-keyed task scheduler ("do X at block height H").
+//!
+//! Pure bookkeeping only: callers persist the scheduler (it is serde-friendly)
+//! and drain due tasks whenever the block producer seals a new height.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One unit of deferred work, e.g. a gift unlock or an auction close.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    /// Monotonic id assigned by the scheduler.
+    pub id: u64,
+    /// Block height at (or after) which the task fires.
+    pub height: u64,
+    /// Free-form task kind, e.g. "gift_unlock", "auction_close".
+    pub kind: String,
+    /// Opaque task payload handed back when the task fires.
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
+
+/// Tasks ordered by target height, then by registration order.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HeightScheduler {
+    next_id: u64,
+    tasks: BTreeMap<u64, Vec<ScheduledTask>>,
+}
+
+impl HeightScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a task for `height` and return the stored copy.
+    pub fn schedule(
+        &mut self,
+        height: u64,
+        kind: impl Into<String>,
+        payload: serde_json::Value,
+    ) -> ScheduledTask {
+        self.next_id = self.next_id.wrapping_add(1);
+        let task = ScheduledTask {
+            id: self.next_id,
+            height,
+            kind: kind.into(),
+            payload,
+        };
+        self.tasks.entry(height).or_default().push(task.clone());
+        task
+    }
+
+    /// Remove a pending task by id.
+    pub fn cancel(&mut self, id: u64) -> Option<ScheduledTask> {
+        let height = self
+            .tasks
+            .iter()
+            .find(|(_, tasks)| tasks.iter().any(|t| t.id == id))
+            .map(|(height, _)| *height)?;
+        let bucket = self.tasks.get_mut(&height)?;
+        let idx = bucket.iter().position(|t| t.id == id)?;
+        let task = bucket.remove(idx);
+        if bucket.is_empty() {
+            self.tasks.remove(&height);
+        }
+        Some(task)
+    }
+
+    /// Drain every task whose target height is `<= height`, oldest height first.
+    pub fn take_due(&mut self, height: u64) -> Vec<ScheduledTask> {
+        let later = self.tasks.split_off(&height.saturating_add(1));
+        let due = std::mem::replace(&mut self.tasks, later);
+        due.into_values().flatten().collect()
+    }
+
+    /// Iterate pending tasks in firing order.
+    pub fn pending(&self) -> impl Iterator<Item = &ScheduledTask> {
+        self.tasks.values().flatten()
+    }
+
+    /// Lowest height that still has work queued.
+    pub fn next_height(&self) -> Option<u64> {
+        self.tasks.keys().next().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_due_drains_in_height_order() {
+        let mut scheduler = HeightScheduler::new();
+        scheduler.schedule(12, "auction_close", serde_json::Value::Null);
+        scheduler.schedule(10, "gift_unlock", serde_json::Value::Null);
+        scheduler.schedule(10, "gift_unlock", serde_json::Value::Null);
+
+        assert!(scheduler.take_due(9).is_empty());
+        let due = scheduler.take_due(11);
+        assert_eq!(due.len(), 2);
+        assert!(due.iter().all(|t| t.height == 10));
+        assert_eq!(scheduler.next_height(), Some(12));
+        assert_eq!(scheduler.len(), 1);
+    }
+
+    #[test]
+    fn survives_json_round_trip() {
+        let mut scheduler = HeightScheduler::new();
+        let task = scheduler.schedule(8, "gift_unlock", serde_json::json!({ "label": ";1;fun;" }));
+        let bytes = serde_json::to_vec(&scheduler).unwrap();
+        let mut restored: HeightScheduler = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(restored.take_due(8), vec![task]);
+        let next = restored.schedule(9, "auction_close", serde_json::Value::Null);
+        assert_eq!(next.id, 2, "ids keep counting after restore");
+    }
+
+    #[test]
+    fn cancel_removes_only_the_target() {
+        let mut scheduler = HeightScheduler::new();
+        let a = scheduler.schedule(5, "a", serde_json::Value::Null);
+        let b = scheduler.schedule(5, "b", serde_json::Value::Null);
+        assert_eq!(scheduler.cancel(a.id), Some(a));
+        assert_eq!(scheduler.pending().collect::<Vec<_>>(), vec![&b]);
+        assert!(scheduler.cancel(99).is_none());
+    }
+}
@@ -1,35 +1,159 @@
+use crate::canonical::{self, CanonicalError};
 use blake3::Hasher as Blake3Hasher;
 use num_bigint::BigUint;
+use serde::Serialize;
 use sha2::{Digest, Sha512};
-use spec::LabelId;
-use std::collections::HashMap;
+use std::io;
+#[cfg(feature = "async")]
+use std::path::Path;
 
-/// Compute the Ω master root string for a given height + balance map.
-pub fn master_root_for(height: u64, balances: &HashMap<LabelId, f64>) -> String {
-    let payload = serde_json::json!({
+/// Read size for the async digests.
+#[cfg(feature = "async")]
+const STREAM_CHUNK: usize = 64 * 1024;
+
+/// Incremental sha-less hashing: feed bytes in any number of chunks and get
+/// the digest [`shaless_digest`] gives over their concatenation. It is also
+/// an [`io::Write`], so values can be JSON-encoded straight into it.
+#[derive(Clone, Default)]
+pub struct ShalessHasher {
+    sha: Sha512,
+    blake: Blake3Hasher,
+}
+
+impl ShalessHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        self.sha.update(data);
+        self.blake.update(data);
+        self
+    }
+
+    /// Feed `value`'s canonical JSON ([`canonical`]). On error the hasher
+    /// holds whatever was encoded before it.
+    pub fn update_json<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<&mut Self, CanonicalError> {
+        canonical::to_writer(&mut *self, value)?;
+        Ok(self)
+    }
+
+    /// Digest in the `;∞;sha-less;…;` form. The hasher can keep taking data.
+    pub fn finalize(&self) -> String {
+        infinity_base(&self.finalize_bytes())
+    }
+
+    fn finalize_bytes(&self) -> [u8; 128] {
+        let mut combined = [0u8; 128];
+        combined[..64].copy_from_slice(&self.sha.clone().finalize());
+        self.blake.finalize_xof().fill(&mut combined[64..]);
+        combined
+    }
+}
+
+impl io::Write for ShalessHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Master root payload, the `{"balances", "height"}` object roots were
+/// first defined over.
+#[derive(Serialize)]
+struct RootPayload<'a, B: ?Sized> {
+    balances: &'a B,
+    height: u64,
+}
+
+/// Compute the Ω master root string for a given height + balance map,
+/// streaming its canonical encoding into the hasher. Equal ledgers give
+/// equal roots whatever map type holds them.
+pub fn master_root_for<B: Serialize + ?Sized>(height: u64, balances: &B) -> String {
+    shaless_json_digest(&RootPayload { balances, height })
+}
+
+/// sha-less digest of `value`'s canonical JSON, streamed; a value that can't
+/// be encoded digests as empty input.
+pub fn shaless_json_digest<T: Serialize + ?Sized>(value: &T) -> String {
+    let mut hasher = ShalessHasher::new();
+    match hasher.update_json(value) {
+        Ok(hasher) => hasher.finalize(),
+        Err(_) => shaless_digest(&[]),
+    }
+}
+
+/// Chain a block onto `prev_root`: same sha-less encoding as the master root.
+/// `state_root` is the producer's [`master_root_for`] at this height, so the
+/// block commits to ledger state as well as to its parent.
+pub fn chained_root(
+    prev_root: &str,
+    height: u64,
+    producer: &str,
+    sealed_ms: i64,
+    state_root: &str,
+) -> String {
+    shaless_json_digest(&serde_json::json!({
+        "prev_root": prev_root,
         "height": height,
-        "balances": balances,
-    });
-    let bytes = serde_json::to_vec(&payload).unwrap_or_default();
-    let digest = shaless_hash(&bytes);
-    infinity_base(&digest)
+        "producer": producer,
+        "sealed_ms": sealed_ms,
+        "state_root": state_root,
+    }))
 }
 
-fn shaless_hash(data: &[u8]) -> [u8; 128] {
-    let mut sha = Sha512::new();
-    sha.update(data);
-    let sha_out = sha.finalize();
+/// sha-less digest of arbitrary bytes, in the same `;∞;sha-less;…;` form as roots.
+pub fn shaless_digest(data: &[u8]) -> String {
+    infinity_base(&shaless_hash(data))
+}
 
-    let mut blake_out = [0u8; 64];
-    Blake3Hasher::new()
-        .update(data)
-        .finalize_xof()
-        .fill(&mut blake_out);
+/// Leading zero bits of `data`'s 1024-bit sha-less hash: the work it
+/// proves. Each extra bit halves the odds that a given input meets it.
+pub fn shaless_leading_zeros(data: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for byte in shaless_hash(data) {
+        if byte != 0 {
+            return zeros + byte.leading_zeros();
+        }
+        zeros += 8;
+    }
+    zeros
+}
 
-    let mut combined = [0u8; 128];
-    combined[..64].copy_from_slice(&sha_out);
-    combined[64..].copy_from_slice(&blake_out);
-    combined
+/// sha-less digest of everything `reader` yields, read a chunk at a time.
+#[cfg(feature = "async")]
+pub async fn shaless_digest_reader<R>(mut reader: R) -> io::Result<String>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut hasher = ShalessHasher::new();
+    let mut buf = vec![0u8; STREAM_CHUNK];
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..read]);
+    }
+}
+
+/// sha-less digest of the file at `path`, without reading it into memory.
+#[cfg(feature = "async")]
+pub async fn shaless_digest_file(path: impl AsRef<Path>) -> io::Result<String> {
+    shaless_digest_reader(tokio::fs::File::open(path).await?).await
+}
+
+fn shaless_hash(data: &[u8]) -> [u8; 128] {
+    ShalessHasher::new().update(data).finalize_bytes()
 }
 
 fn infinity_base(hash: &[u8]) -> String {
@@ -37,3 +161,111 @@ fn infinity_base(hash: &[u8]) -> String {
     let base8 = big.to_str_radix(8);
     format!(";∞;sha-less;{base8};")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn streamed_digests_match_whole_buffer_digests() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut hasher = ShalessHasher::new();
+        for chunk in data.chunks(4093) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), shaless_digest(&data));
+
+        // Roots keep the encoding they were defined over: a `json!` object.
+        let ledger: BTreeMap<&str, BTreeMap<&str, u128>> = BTreeMap::from([
+            ("moon", BTreeMap::from([(";1;a;", 5)])),
+            ("earth", BTreeMap::from([(";2;b;", 7), (";1;a;", 1)])),
+        ]);
+        let payload = serde_json::json!({ "height": 9, "balances": ledger });
+        assert_eq!(
+            master_root_for(9, &ledger),
+            shaless_digest(&serde_json::to_vec(&payload).unwrap())
+        );
+        assert_eq!(
+            shaless_json_digest(&ledger),
+            shaless_digest(&serde_json::to_vec(&ledger).unwrap())
+        );
+        // Non-string map keys can't be encoded and digest as empty input.
+        let keyed = BTreeMap::from([((1, 2), 3)]);
+        assert_eq!(shaless_json_digest(&keyed), shaless_digest(&[]));
+    }
+
+    #[test]
+    fn roots_are_pinned_and_independent_of_map_order() {
+        let ledger: BTreeMap<&str, BTreeMap<&str, u128>> = BTreeMap::from([
+            (
+                "earth",
+                BTreeMap::from([
+                    (";9132077554;comet;", 1_000_000),
+                    (";9132077554;fun;", 50_000),
+                ]),
+            ),
+            ("moon", BTreeMap::from([(";5550001111;main;", 500)])),
+        ]);
+        let root = master_root_for(7, &ledger);
+        assert_eq!(root, ";∞;sha-less;136225655001510637273461231655362747136104076377613671455664153500050705111157746165422760350756410023034772770176411373034774061436564523534755611704132433703254316046604564004654712213460076431137353401573345675271514405266631235271336177051317117642625302225545777742547751766434364506576072234210376610437016526623377505516637160431732423;");
+        assert_eq!(
+            chained_root(";∞;genesis;", 1, "node-a", 1_700_000_000_000, &root),
+            ";∞;sha-less;65304051237734411651167456321720631360461770153512407205715445646470417610745256363502652311622067047066534631104620216226007053705057107677060342350774214440004733467667011226741122765476534367355455362407755011625626604701443600163266222632040653230026327565262133422353075641244604347742745166344472074500733010714011200541023020442141667;"
+        );
+
+        // The same ledger in hash maps, built in either order.
+        for reversed in [false, true] {
+            let mut planets: Vec<_> = ledger.iter().collect();
+            if reversed {
+                planets.reverse();
+            }
+            let hashed: HashMap<String, HashMap<String, u128>> = planets
+                .into_iter()
+                .map(|(planet, labels)| {
+                    let labels = labels.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+                    (planet.to_string(), labels)
+                })
+                .collect();
+            assert_eq!(master_root_for(7, &hashed), root);
+        }
+
+        // Floats: shortest round-trip digits, and `-0.0` hashes as `0.0`.
+        let floats = BTreeMap::from([(";1;a;", 1.5), (";2;b;", 0.1), (";3;c;", 2.0)]);
+        assert_eq!(master_root_for(3, &floats), ";∞;sha-less;62243261440232076111457154304307044446625211341556573755500056213240126322106337257333725162524052506117250240070650640771615446270176067350057113446400621502556406207037607604753244104431022604440620470750466006637264033744155440313634153347526737535373041050365024111605725565566027563270314061703074557324073414441516736220010563332033741;");
+        assert_eq!(
+            shaless_json_digest(&[-0.0f64, 1.0]),
+            shaless_json_digest(&[0.0f64, 1.0])
+        );
+    }
+
+    #[test]
+    fn leading_zeros_match_the_digest_length() {
+        let found = (0u32..)
+            .map(|nonce| format!("challenge;{nonce}"))
+            .find(|input| shaless_leading_zeros(input.as_bytes()) >= 8)
+            .unwrap();
+        for input in [found.as_str(), "", "challenge;0"] {
+            let zeros = shaless_leading_zeros(input.as_bytes());
+            let digest = shaless_digest(input.as_bytes());
+            let digits = digest.trim_end_matches(';').rsplit(';').next().unwrap();
+            // 1024 bits, three per octal digit.
+            assert_eq!(digits.len() as u32, (1024 - zeros).div_ceil(3));
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn readers_and_files_stream_into_the_same_digest() {
+        let data = vec![7u8; STREAM_CHUNK * 2 + 11];
+        assert_eq!(
+            shaless_digest_reader(&data[..]).await.unwrap(),
+            shaless_digest(&data)
+        );
+        let path = std::env::temp_dir().join(format!("shaless-{}", std::process::id()));
+        tokio::fs::write(&path, &data).await.unwrap();
+        let digest = shaless_digest_file(&path).await;
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(digest.unwrap(), shaless_digest(&data));
+    }
+}
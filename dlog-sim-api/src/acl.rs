@@ -0,0 +1,292 @@
+//! Shared builds: who besides its owner may edit a claimed chunk.
+//!
+//! A label claims an unclaimed chunk by setting its access list, and owns
+//! it from then on. The owner grants other labels any of `place`, `break`
+//! and `container`, or revokes them with an empty set; emptying its own
+//! entry releases the claim. Only the owner may change a claimed chunk's
+//! list. In a claimed chunk, a tick's block updates need its builder (the
+//! tick's `label`, else its player) to own the chunk or hold `place` or
+//! `break` for the action, plus `container` for blocks that hold items.
+//! Unclaimed chunks stay open to every builder. Each list lives at
+//! `world;acl;<cx>;<cz>.json` with its last [`CHANGES_KEPT`] changes, the
+//! chunk's audit trail, which outlives a released claim.
+
+use dlog_error::StorageError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::model::{BlockAction, BlockUpdate};
+use crate::rules::{self, Rejection};
+
+/// Changes a chunk's audit trail keeps, newest last.
+pub const CHANGES_KEPT: usize = 64;
+const LABEL_MAX: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Place,
+    Break,
+    /// Place or break blocks that hold items, on top of `place`/`break`.
+    Container,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AclChange {
+    pub at_ms: u64,
+    /// Who changed the list.
+    pub actor: String,
+    pub label: String,
+    /// What `label` holds afterwards; empty once revoked or released.
+    pub permissions: BTreeSet<Permission>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkAcl {
+    pub cx: i64,
+    pub cz: i64,
+    /// `None` while the chunk is unclaimed.
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub grants: BTreeMap<String, BTreeSet<Permission>>,
+    #[serde(default)]
+    pub changes: Vec<AclChange>,
+}
+
+#[derive(Debug)]
+pub enum AclError {
+    Invalid(String),
+    /// Someone other than the owner tried to change a claimed chunk.
+    NotOwner,
+    Storage(StorageError),
+}
+
+impl AclError {
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Invalid(_) => 400,
+            Self::NotOwner => 403,
+            Self::Storage(err) => err.status(),
+        }
+    }
+}
+
+impl fmt::Display for AclError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(why) => f.write_str(why),
+            Self::NotOwner => f.write_str("only the chunk's owner may change its access list"),
+            Self::Storage(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<StorageError> for AclError {
+    fn from(err: StorageError) -> Self {
+        Self::Storage(err)
+    }
+}
+
+impl ChunkAcl {
+    pub fn new(cx: i64, cz: i64) -> Self {
+        Self {
+            cx,
+            cz,
+            ..Self::default()
+        }
+    }
+
+    /// Apply `change` as its actor asked, recording it on the audit trail.
+    pub fn apply(&mut self, change: AclChange) -> Result<(), AclError> {
+        for name in [&change.actor, &change.label] {
+            if name.trim().is_empty() || name.len() > LABEL_MAX {
+                return Err(AclError::Invalid(format!(
+                    "`{name}` is not a label (1 to {LABEL_MAX} characters)"
+                )));
+            }
+        }
+        match &self.owner {
+            None if change.permissions.is_empty() => {
+                return Err(AclError::Invalid(
+                    "the chunk is unclaimed; there is nothing to revoke".into(),
+                ))
+            }
+            None => self.owner = Some(change.actor.clone()),
+            Some(owner) if *owner != change.actor => return Err(AclError::NotOwner),
+            Some(owner) if *owner == change.label => {
+                if !change.permissions.is_empty() {
+                    return Err(AclError::Invalid(
+                        "the owner already holds every permission".into(),
+                    ));
+                }
+                self.owner = None;
+                self.grants.clear();
+            }
+            Some(_) => {}
+        }
+        if self.owner.as_ref() != Some(&change.label) {
+            if change.permissions.is_empty() {
+                self.grants.remove(&change.label);
+            } else {
+                self.grants
+                    .insert(change.label.clone(), change.permissions.clone());
+            }
+        }
+        self.changes.push(change);
+        if self.changes.len() > CHANGES_KEPT {
+            let drop = self.changes.len() - CHANGES_KEPT;
+            self.changes.drain(..drop);
+        }
+        Ok(())
+    }
+
+    /// Whether `builder` may make `update` here.
+    pub fn check(&self, builder: &str, update: &BlockUpdate) -> Result<(), Rejection> {
+        let Some(owner) = &self.owner else {
+            return Ok(());
+        };
+        if owner == builder {
+            return Ok(());
+        }
+        let held = self.grants.get(builder);
+        let mut needed = vec![match update.action {
+            BlockAction::Place => Permission::Place,
+            BlockAction::Break => Permission::Break,
+        }];
+        if rules::is_container(&update.block) {
+            needed.push(Permission::Container);
+        }
+        match needed
+            .into_iter()
+            .find(|permission| !held.is_some_and(|held| held.contains(permission)))
+        {
+            Some(permission) => Err(Rejection::NotPermitted { permission }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::OmegaStorage;
+    use crate::store::MemoryStore;
+    use std::sync::Arc;
+
+    fn change(actor: &str, label: &str, permissions: &[Permission]) -> AclChange {
+        AclChange {
+            at_ms: 1,
+            actor: actor.into(),
+            label: label.into(),
+            permissions: permissions.iter().copied().collect(),
+        }
+    }
+
+    fn update(block: &str, action: BlockAction) -> BlockUpdate {
+        BlockUpdate {
+            x: 0,
+            y: 64,
+            z: 0,
+            block: block.into(),
+            action,
+        }
+    }
+
+    #[test]
+    fn owners_grant_friends_the_edits_they_may_make() {
+        let mut acl = ChunkAcl::new(0, 0);
+        // Unclaimed chunks are open to everyone.
+        assert_eq!(
+            acl.check("eve", &update("chest", BlockAction::Place)),
+            Ok(())
+        );
+        assert!(matches!(
+            acl.apply(change("ann", "bob", &[])),
+            Err(AclError::Invalid(_))
+        ));
+
+        acl.apply(change("ann", "bob", &[Permission::Place]))
+            .unwrap();
+        assert_eq!(acl.owner.as_deref(), Some("ann"));
+        assert!(matches!(
+            acl.apply(change("bob", "bob", &[Permission::Break])),
+            Err(AclError::NotOwner)
+        ));
+        assert_eq!(
+            acl.check("ann", &update("chest", BlockAction::Break)),
+            Ok(())
+        );
+        assert_eq!(
+            acl.check("bob", &update("stone", BlockAction::Place)),
+            Ok(())
+        );
+        assert_eq!(
+            acl.check("bob", &update("stone", BlockAction::Break)),
+            Err(Rejection::NotPermitted {
+                permission: Permission::Break
+            })
+        );
+        assert_eq!(
+            acl.check("bob", &update("minecraft:barrel", BlockAction::Place)),
+            Err(Rejection::NotPermitted {
+                permission: Permission::Container
+            })
+        );
+        assert_eq!(
+            acl.check("eve", &update("stone", BlockAction::Place)),
+            Err(Rejection::NotPermitted {
+                permission: Permission::Place
+            })
+        );
+
+        acl.apply(change(
+            "ann",
+            "bob",
+            &[Permission::Place, Permission::Container],
+        ))
+        .unwrap();
+        assert_eq!(
+            acl.check("bob", &update("barrel", BlockAction::Place)),
+            Ok(())
+        );
+        acl.apply(change("ann", "bob", &[])).unwrap();
+        assert!(acl.grants.is_empty());
+
+        // The owner releasing the chunk opens it again; the trail stays.
+        acl.apply(change("ann", "ann", &[])).unwrap();
+        assert_eq!(acl.owner, None);
+        assert_eq!(
+            acl.check("eve", &update("stone", BlockAction::Break)),
+            Ok(())
+        );
+        assert_eq!(acl.changes.len(), 4);
+        for n in 0..CHANGES_KEPT {
+            acl.apply(change("cat", &format!("friend{n}"), &[Permission::Place]))
+                .unwrap();
+        }
+        assert_eq!(acl.changes.len(), CHANGES_KEPT);
+        assert_eq!(acl.changes[0].label, "friend0");
+    }
+
+    #[tokio::test]
+    async fn refused_changes_leave_the_stored_list_alone() {
+        let storage = OmegaStorage::local(Arc::new(MemoryStore::default()));
+        let update_as = |actor: &'static str| {
+            let storage = storage.clone();
+            async move {
+                storage
+                    .update_chunk_acl(3, -1, |acl| {
+                        acl.apply(change(actor, "bob", &[Permission::Break]))
+                    })
+                    .await
+            }
+        };
+        let claimed = update_as("ann").await.unwrap();
+        assert_eq!((claimed.cx, claimed.cz), (3, -1));
+        assert!(matches!(update_as("eve").await, Err(AclError::NotOwner)));
+        assert_eq!(storage.load_chunk_acl(3, -1).await.unwrap(), claimed);
+        assert_eq!(storage.load_chunk_acl(0, 0).await.unwrap().owner, None);
+    }
+}
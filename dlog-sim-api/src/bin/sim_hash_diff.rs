@@ -0,0 +1,122 @@
+//! Compare the world hashes of two sim instances and localize divergence.
+//!
+//! `sim_hash_diff <base_url_a> <base_url_b> [scope]`
+//!
+//! Roots are compared first; for each scope whose digest differs, the chunk
+//! and player scopes are fetched again to list the exact entries that differ.
+//! `OMEGA_ADMIN_TOKEN` is sent as `X-Admin-Token` when set. Exits 1 on divergence.
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize)]
+struct WorldHash {
+    root: String,
+    scopes: BTreeMap<String, String>,
+    #[serde(default)]
+    entries: BTreeMap<String, String>,
+}
+
+struct Instance {
+    base: String,
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl Instance {
+    async fn hash(&self, scope: &str) -> anyhow::Result<WorldHash> {
+        let mut req = self
+            .client
+            .get(format!("{}/v1/sim/hash", self.base.trim_end_matches('/')))
+            .query(&[("scope", scope)]);
+        if let Some(token) = &self.token {
+            req = req.header("x-admin-token", token);
+        }
+        let resp = req
+            .send()
+            .await
+            .with_context(|| format!("GET {} scope={scope}", self.base))?;
+        let resp = resp
+            .error_for_status()
+            .with_context(|| format!("{} rejected scope={scope}", self.base))?;
+        Ok(resp.json().await?)
+    }
+}
+
+fn diff_entries(a: &BTreeMap<String, String>, b: &BTreeMap<String, String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (key, digest) in a {
+        match b.get(key) {
+            None => lines.push(format!("  {key}: only on a")),
+            Some(other) if other != digest => lines.push(format!("  {key}: differs")),
+            Some(_) => {}
+        }
+    }
+    for key in b.keys().filter(|key| !a.contains_key(*key)) {
+        lines.push(format!("  {key}: only on b"));
+    }
+    lines
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(a), Some(b)) = (args.next(), args.next()) else {
+        bail!("usage: sim_hash_diff <base_url_a> <base_url_b> [all|chunks|players|bank]");
+    };
+    let scope = args.next().unwrap_or_else(|| "all".into());
+    let client = reqwest::Client::new();
+    let token = std::env::var("OMEGA_ADMIN_TOKEN").ok();
+    let a = Instance {
+        base: a,
+        client: client.clone(),
+        token: token.clone(),
+    };
+    let b = Instance {
+        base: b,
+        client,
+        token,
+    };
+
+    let (hash_a, hash_b) = tokio::try_join!(a.hash(&scope), b.hash(&scope))?;
+    if hash_a.root == hash_b.root {
+        println!("in agreement ({scope}): {}", hash_a.root);
+        return Ok(());
+    }
+
+    println!("diverged ({scope})");
+    let names: Vec<&String> = hash_a
+        .scopes
+        .keys()
+        .chain(
+            hash_b
+                .scopes
+                .keys()
+                .filter(|s| !hash_a.scopes.contains_key(*s)),
+        )
+        .collect();
+    for name in names {
+        match (hash_a.scopes.get(name), hash_b.scopes.get(name)) {
+            (Some(x), Some(y)) if x == y => println!("{name}: same"),
+            (Some(_), Some(_)) => {
+                println!("{name}: differs");
+                let (entries_a, entries_b) = if scope == "all" {
+                    if name == "bank" {
+                        continue;
+                    }
+                    let (x, y) = tokio::try_join!(a.hash(name), b.hash(name))?;
+                    (x.entries, y.entries)
+                } else {
+                    (hash_a.entries.clone(), hash_b.entries.clone())
+                };
+                for line in diff_entries(&entries_a, &entries_b) {
+                    println!("{line}");
+                }
+            }
+            (Some(_), None) => println!("{name}: only on a"),
+            (None, _) => println!("{name}: only on b"),
+        }
+    }
+    std::process::exit(1);
+}
@@ -0,0 +1,147 @@
+//! Import a world into a sim instance through its resumable import API.
+//!
+//! `sim_import <base_url> <chunks.json> [import_id]`
+//!
+//! The file is a JSON array of chunks as the explorer serves them. It is
+//! sent in parts of `OMEGA_IMPORT_PART_CHUNKS` chunks (default 256); with an
+//! `import_id` the import is resumed and only the parts the instance is
+//! missing are sent. Progress is printed while the import is applied.
+//! `OMEGA_ADMIN_TOKEN` is sent as `X-Admin-Token` when set.
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+struct WorldImport {
+    id: String,
+    state: String,
+    #[serde(default)]
+    parts: Vec<u32>,
+    #[serde(default)]
+    chunks: usize,
+    #[serde(default)]
+    applied: usize,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+struct Instance {
+    base: String,
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl Instance {
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/v1/sim/imports{path}", self.base.trim_end_matches('/'));
+        let req = self.client.request(method, url);
+        match &self.token {
+            Some(token) => req.header("x-admin-token", token),
+            None => req,
+        }
+    }
+
+    async fn send(&self, req: reqwest::RequestBuilder) -> anyhow::Result<WorldImport> {
+        let resp = req.send().await.with_context(|| self.base.clone())?;
+        let status = resp.status();
+        if !status.is_success() {
+            bail!("{status}: {}", resp.text().await.unwrap_or_default());
+        }
+        Ok(resp.json().await?)
+    }
+
+    async fn status(&self, id: &str) -> anyhow::Result<WorldImport> {
+        self.send(self.request(reqwest::Method::GET, &format!("/{id}")))
+            .await
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(base), Some(path)) = (args.next(), args.next()) else {
+        bail!("usage: sim_import <base_url> <chunks.json> [import_id]");
+    };
+    let per_part: usize = std::env::var("OMEGA_IMPORT_PART_CHUNKS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(256);
+    let bytes = std::fs::read(&path).with_context(|| format!("reading {path}"))?;
+    let chunks: Vec<Value> =
+        serde_json::from_slice(&bytes).with_context(|| format!("{path} is not a chunk array"))?;
+    let parts: Vec<&[Value]> = chunks.chunks(per_part).collect();
+    let sim = Instance {
+        base,
+        client: reqwest::Client::new(),
+        token: std::env::var("OMEGA_ADMIN_TOKEN").ok(),
+    };
+
+    let import = match args.next() {
+        Some(id) => sim.status(&id).await?,
+        None => sim.send(sim.request(reqwest::Method::POST, "")).await?,
+    };
+    println!(
+        "import {} ({} chunks in {} parts)",
+        import.id,
+        chunks.len(),
+        parts.len()
+    );
+    let received: BTreeSet<u32> = import.parts.iter().copied().collect();
+    for (part, batch) in (0u32..).zip(&parts) {
+        if received.contains(&part) {
+            continue;
+        }
+        let resp = sim
+            .request(
+                reqwest::Method::PUT,
+                &format!("/{}/parts/{part}", import.id),
+            )
+            .json(batch)
+            .send()
+            .await
+            .with_context(|| format!("sending part {part}"))?;
+        let status = resp.status();
+        if !status.is_success() {
+            bail!(
+                "part {part}: {status}: {}; rerun with {} to resume",
+                resp.text().await.unwrap_or_default(),
+                import.id
+            );
+        }
+        println!("sent part {}/{}", part + 1, parts.len());
+    }
+
+    let complete = sim.send(
+        sim.request(reqwest::Method::POST, &format!("/{}/complete", import.id))
+            .json(&serde_json::json!({ "parts": parts.len() })),
+    );
+    tokio::pin!(complete);
+    let mut progress = tokio::time::interval(Duration::from_secs(2));
+    let done = loop {
+        tokio::select! {
+            done = &mut complete => break done,
+            _ = progress.tick() => {
+                if let Ok(status) = sim.status(&import.id).await {
+                    println!("{}: {}/{} chunks", status.state, status.applied, status.chunks);
+                }
+            }
+        }
+    };
+    match done {
+        Ok(import) => println!("{}: {} chunks", import.state, import.chunks),
+        Err(err) => {
+            let status = sim.status(&import.id).await?;
+            bail!(
+                "{err}\nimport {} is {}{}",
+                status.id,
+                status.state,
+                status.error.map(|e| format!(": {e}")).unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
@@ -1,12 +1,11 @@
 //! Physics cannon: phi-shaped Ω integrator for 8×8 rails.
-//! Self-contained Rust module; drop into a crate and wire to your rail loop.
+//! The sim steps each player's body through it (see `physics`).
 //! - 1000 Hz tick (DT = 1 ms)
 //! - Phi-based acceleration per planet
 //! - Simple gravity and jump
 //! - Speed clamp to keep clients honest
 
-pub const PHI: f64 = 1.618_033_988_749_894_8;
-pub const BLOCKS_PER_ATTENTION_YEAR: f64 = 3_900_000.0; // stored as octal in canon
+pub const PHI: f64 = 1.618_033_988_749_895;
 pub const TICK_HZ: f64 = 1000.0;
 pub const DT: f64 = 1.0 / TICK_HZ;
 
@@ -118,8 +117,6 @@ impl Default for Body {
 pub struct TickResult {
     pub pos: Vec3,
     pub vel: Vec3,
-    /// Optional: energy change in this tick (for rail accounting).
-    pub energy_delta: f64,
 }
 
 /// Step one tick of Ω physics for a body.
@@ -151,11 +148,7 @@ pub fn step(body: Body, input: Input, profile: PlanetProfile) -> TickResult {
 
     let pos = body.pos.add(vel.scale(DT));
 
-    let old_ke = 0.5 * (body.vel.length().powi(2));
-    let new_ke = 0.5 * (vel.length().powi(2));
-    let energy_delta = new_ke - old_ke;
-
-    TickResult { pos, vel, energy_delta }
+    TickResult { pos, vel }
 }
 
 #[cfg(test)]
@@ -166,7 +159,11 @@ mod tests {
     fn accelerates_with_phi() {
         let body = Body::default();
         let input = Input {
-            wish_dir: Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+            wish_dir: Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
             jump: false,
         };
         let out = step(body, input, planet_earth());
@@ -176,9 +173,16 @@ mod tests {
 
     #[test]
     fn jump_adds_upward_velocity() {
-        let body = Body { on_ground: true, ..Default::default() };
+        let body = Body {
+            on_ground: true,
+            ..Default::default()
+        };
         let input = Input {
-            wish_dir: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            wish_dir: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
             jump: true,
         };
         let out = step(body, input, planet_earth());
@@ -0,0 +1,129 @@
+//! Named, separately versioned pieces of player state.
+//!
+//! A player is its universe tick plus a map of components, so subsystems
+//! (physics today; inventory, locks and effects as they arrive) each attach
+//! their own data instead of widening one struct. A component type names its
+//! key and carries its own [`Schema`]. Stored components keep their
+//! `schema_version` and are upgraded as they are read, so one component's
+//! shape changes with a migration step of its own, without touching the
+//! others or the player schema. Components this binary doesn't know ride
+//! along untouched.
+
+use corelib::migrate::Schema;
+use dlog_error::StorageError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Data a subsystem keeps per player. Components serialize as JSON objects,
+/// which is where their version is stamped.
+pub trait Component: Serialize + DeserializeOwned + Default {
+    /// Key in the player's component map.
+    const NAME: &'static str;
+    const SCHEMA: Schema;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Components(BTreeMap<String, Value>);
+
+impl Components {
+    /// `C` as stored, upgraded to its current schema.
+    pub fn get<C: Component>(&self) -> Result<Option<C>, StorageError> {
+        let Some(stored) = self.0.get(C::NAME) else {
+            return Ok(None);
+        };
+        let mut value = stored.clone();
+        C::SCHEMA
+            .upgrade(&mut value)
+            .map_err(|err| StorageError::Schema(err.to_string()))?;
+        Ok(Some(serde_json::from_value(value)?))
+    }
+
+    /// `C`, or its default for a player that never had one.
+    pub fn get_or_default<C: Component>(&self) -> Result<C, StorageError> {
+        Ok(self.get()?.unwrap_or_default())
+    }
+
+    pub fn insert<C: Component>(&mut self, component: &C) -> Result<(), StorageError> {
+        let mut value = serde_json::to_value(component)?;
+        C::SCHEMA.stamp(&mut value);
+        self.0.insert(C::NAME.to_string(), value);
+        Ok(())
+    }
+
+    /// Stored components other than `names`, as saved.
+    pub fn others(&self, names: &[&str]) -> BTreeMap<&str, &Value> {
+        self.0
+            .iter()
+            .filter(|(name, _)| !names.contains(&name.as_str()))
+            .map(|(name, value)| (name.as_str(), value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use corelib::migrate::Migration;
+    use serde_json::{json, Map};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Inventory {
+        slots: u32,
+        items: BTreeMap<String, u32>,
+    }
+
+    fn inventory_v2(object: &mut Map<String, Value>) -> Result<(), String> {
+        object.entry("slots").or_insert(json!(36));
+        Ok(())
+    }
+
+    impl Component for Inventory {
+        const NAME: &'static str = "inventory";
+        const SCHEMA: Schema = Schema {
+            kind: "inventory",
+            migrations: &[
+                Migration {
+                    to: 1,
+                    note: "items by id",
+                    apply: |_| Ok(()),
+                },
+                Migration {
+                    to: 2,
+                    note: "fill the slot count",
+                    apply: inventory_v2,
+                },
+            ],
+        };
+    }
+
+    #[test]
+    fn components_upgrade_on_their_own_versions() {
+        let mut components: Components = serde_json::from_value(json!({
+            "inventory": { "schema_version": 1, "items": { "stone": 3 } },
+            "effects": { "schema_version": 9, "haste": 40 },
+        }))
+        .unwrap();
+        let inventory: Inventory = components.get().unwrap().unwrap();
+        assert_eq!((inventory.slots, inventory.items["stone"]), (36, 3));
+
+        components.insert(&inventory).unwrap();
+        let saved = serde_json::to_value(&components).unwrap();
+        assert_eq!(saved["inventory"]["schema_version"], 2);
+        // Unknown components survive a round trip unread.
+        assert_eq!(saved["effects"]["haste"], 40);
+        assert_eq!(components.others(&["inventory"]).len(), 1);
+
+        let newer: Components = serde_json::from_value(json!({
+            "inventory": { "schema_version": 3, "items": {} },
+        }))
+        .unwrap();
+        assert!(newer.get::<Inventory>().is_err());
+        assert_eq!(
+            Components::default().get_or_default::<Inventory>().unwrap(),
+            Inventory::default()
+        );
+    }
+}
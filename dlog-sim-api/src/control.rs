@@ -0,0 +1,133 @@
+//! Pause, resume and single-step for debugging physics.
+//!
+//! While paused, tick requests leave player state and chunks untouched and
+//! answer with a frozen-world title; each step lets one more tick through.
+//! The controls only exist when `OMEGA_SIM_DEBUG=1`, so a production sim
+//! can't be frozen by a stray admin call.
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// Most ticks one step request may queue.
+pub const MAX_STEP: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ControlStatus {
+    pub enabled: bool,
+    pub paused: bool,
+    /// Ticks still allowed through while paused.
+    pub steps_left: u64,
+}
+
+/// Whether a tick request may advance the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Run,
+    /// Paused, but a queued step lets this tick through.
+    Step,
+    Frozen,
+}
+
+#[derive(Debug, Default)]
+pub struct SimControl {
+    enabled: bool,
+    state: Mutex<ControlStatus>,
+}
+
+impl SimControl {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            state: Mutex::new(ControlStatus {
+                enabled,
+                ..ControlStatus::default()
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("OMEGA_SIM_DEBUG").is_ok_and(|v| v.trim() == "1"))
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn status(&self) -> ControlStatus {
+        *self.state.lock().expect("sim control mutex poisoned")
+    }
+
+    pub fn pause(&self) -> ControlStatus {
+        self.update(|state| state.paused = true)
+    }
+
+    /// Resume normal ticking and drop any queued steps.
+    pub fn resume(&self) -> ControlStatus {
+        self.update(|state| {
+            state.paused = false;
+            state.steps_left = 0;
+        })
+    }
+
+    /// Pause (if running) and let `ticks` more ticks through.
+    pub fn step(&self, ticks: u64) -> ControlStatus {
+        self.update(|state| {
+            state.paused = true;
+            state.steps_left = state.steps_left.saturating_add(ticks).min(MAX_STEP);
+        })
+    }
+
+    /// Decide one tick request, spending a queued step if it runs on one.
+    pub fn admit(&self) -> Admission {
+        if !self.enabled {
+            return Admission::Run;
+        }
+        let mut state = self.state.lock().expect("sim control mutex poisoned");
+        match (state.paused, state.steps_left) {
+            (false, _) => Admission::Run,
+            (true, 0) => Admission::Frozen,
+            (true, _) => {
+                state.steps_left -= 1;
+                Admission::Step
+            }
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut ControlStatus)) -> ControlStatus {
+        let mut state = self.state.lock().expect("sim control mutex poisoned");
+        if self.enabled {
+            change(&mut state);
+        }
+        *state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_let_exactly_n_ticks_through_a_pause() {
+        let control = SimControl::new(true);
+        assert_eq!(control.admit(), Admission::Run);
+        control.pause();
+        assert_eq!(control.admit(), Admission::Frozen);
+
+        assert_eq!(control.step(2).steps_left, 2);
+        assert_eq!(control.admit(), Admission::Step);
+        assert_eq!(control.admit(), Admission::Step);
+        assert_eq!(control.admit(), Admission::Frozen);
+
+        control.step(5);
+        let resumed = control.resume();
+        assert!(!resumed.paused && resumed.steps_left == 0);
+        assert_eq!(control.admit(), Admission::Run);
+    }
+
+    #[test]
+    fn disabled_controls_never_freeze() {
+        let control = SimControl::new(false);
+        assert!(!control.pause().paused);
+        assert_eq!(control.admit(), Admission::Run);
+    }
+}
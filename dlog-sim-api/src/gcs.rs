@@ -1,136 +1,311 @@
-use crate::model::{BlockEvent, BlockLedger, ChunkSnapshot};
+//! The GCS [`ObjectStore`]: one bucket, behind its own breaker.
+
+use crate::store::{ObjectStore, StoreFuture, Stored};
+use corelib::shaless_digest_reader;
+use dlog_error::StorageError;
+use dlog_resilience::{BreakerConfig, BreakerSnapshot, CallError, RetryPolicy, Upstream};
+use futures_util::TryStreamExt;
 use google_cloud_storage::client::{Client, ClientConfig};
+use google_cloud_storage::http::objects::delete::DeleteObjectRequest;
 use google_cloud_storage::http::objects::download::Range;
 use google_cloud_storage::http::objects::get::GetObjectRequest;
+use google_cloud_storage::http::objects::list::ListObjectsRequest;
 use google_cloud_storage::http::objects::upload::{Media, UploadObjectRequest, UploadType};
 use google_cloud_storage::http::Error as GcsError;
 use hyper::http::StatusCode;
-use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use std::io;
 use std::sync::Arc;
+use tokio_util::io::StreamReader;
 
-#[derive(Clone)]
-pub struct OmegaStorage {
+pub struct GcsStore {
     client: Arc<Client>,
     bucket: String,
+    /// Retry + circuit breaker for this bucket's calls.
+    upstream: Upstream,
 }
 
-impl OmegaStorage {
-    pub async fn new_from_env() -> anyhow::Result<Self> {
-        let bucket = std::env::var("OMEGA_BUCKET")?;
-        let config = ClientConfig::default().with_auth().await?;
-        let client = Client::new(config);
-        Ok(Self {
-            client: Arc::new(client),
-            bucket,
-        })
+impl fmt::Debug for GcsStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcsStore")
+            .field("bucket", &self.bucket)
+            .field("upstream", &self.upstream.name())
+            .finish()
+    }
+}
+
+/// A client with Application Default Credentials, shared by every bucket.
+pub async fn client_from_env() -> Result<Arc<Client>, StorageError> {
+    let config = ClientConfig::default()
+        .with_auth()
+        .await
+        .map_err(|e| StorageError::Config(e.to_string()))?;
+    Ok(Arc::new(Client::new(config)))
+}
+
+fn not_found(err: &GcsError) -> bool {
+    match err {
+        GcsError::Response(err) => err.code == 404,
+        GcsError::HttpClient(err) => err.status() == Some(StatusCode::NOT_FOUND),
+        _ => false,
     }
+}
 
-    fn key_for_player(player_uuid: &str) -> String {
-        format!("sim;players;{};state.json", player_uuid)
+/// A generation precondition failed: the object changed since it was read.
+fn precondition_failed(err: &GcsError) -> bool {
+    match err {
+        GcsError::Response(err) => err.code == 412,
+        GcsError::HttpClient(err) => err.status() == Some(StatusCode::PRECONDITION_FAILED),
+        _ => false,
     }
+}
+
+/// Throttling, server errors and dropped connections are retried; 4xx are not.
+fn gcs_transient(err: &GcsError) -> bool {
+    match err {
+        GcsError::Response(err) => err.code >= 500 || err.code == 429 || err.code == 408,
+        GcsError::HttpClient(err) => {
+            err.is_connect()
+                || err.is_timeout()
+                || err.status().is_some_and(|s| s.is_server_error())
+        }
+        GcsError::HttpMiddleware(_) => true,
+        GcsError::TokenSource(_) => false,
+    }
+}
+
+/// Where a streamed download failed: starting it, or reading its body.
+#[derive(Debug)]
+enum StreamError {
+    Gcs(GcsError),
+    Read(io::Error),
+}
 
-    fn key_for_chunk(cx: i64, cz: i64) -> String {
-        format!("world;chunks;{};{}.json", cx, cz)
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Gcs(err) => err.fmt(f),
+            StreamError::Read(err) => write!(f, "reading object: {err}"),
+        }
     }
+}
+
+fn backend(err: CallError<GcsError>) -> StorageError {
+    StorageError::Backend(err.to_string())
+}
 
-    fn key_for_block_ledger(cx: i64, cz: i64) -> String {
-        format!("ledger;blocks;{};{}.json", cx, cz)
+impl GcsStore {
+    /// `bucket` through `client`, its calls guarded by an upstream named
+    /// `upstream` (what /health and chaos plans call it).
+    pub fn new(client: Arc<Client>, bucket: String, upstream: String) -> Self {
+        Self {
+            client,
+            bucket,
+            upstream: Upstream::new(upstream, RetryPolicy::default(), BreakerConfig::default()),
+        }
     }
 
-    pub async fn load_json<T: DeserializeOwned>(&self, key: &str) -> anyhow::Result<Option<T>> {
-        let req = GetObjectRequest {
+    fn object(&self, key: &str) -> GetObjectRequest {
+        GetObjectRequest {
             bucket: self.bucket.clone(),
             object: key.to_string(),
             ..Default::default()
-        };
+        }
+    }
 
-        let bytes = match self
-            .client
-            .download_object(&req, &Range::default())
+    async fn download(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let req = self.object(key);
+        let download = || async {
+            match self.client.download_object(&req, &Range::default()).await {
+                Ok(data) => Ok(Some(data)),
+                Err(err) if not_found(&err) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+        self.upstream
+            .call_if(download, gcs_transient)
             .await
-        {
-            Ok(data) => data,
-            Err(GcsError::Response(err)) if err.code == 404 => return Ok(None),
-            Err(GcsError::HttpClient(err)) if err.status() == Some(StatusCode::NOT_FOUND) => {
-                return Ok(None)
+            .map_err(backend)
+    }
+
+    /// The current copy of `key` and its generation. The body is read at
+    /// the generation the metadata named, so the two match even when the
+    /// object is replaced in between.
+    async fn download_current(&self, key: &str) -> Result<Option<Stored>, StorageError> {
+        let req = self.object(key);
+        let download = || async {
+            loop {
+                let object = match self.client.get_object(&req).await {
+                    Ok(object) => object,
+                    Err(err) if not_found(&err) => return Ok(None),
+                    Err(e) => return Err(e),
+                };
+                let pinned = GetObjectRequest {
+                    generation: Some(object.generation),
+                    ..self.object(key)
+                };
+                match self
+                    .client
+                    .download_object(&pinned, &Range::default())
+                    .await
+                {
+                    Ok(bytes) => {
+                        return Ok(Some(Stored {
+                            bytes,
+                            generation: object.generation,
+                        }))
+                    }
+                    // Replaced since the metadata was read; read the new one.
+                    Err(err) if not_found(&err) => continue,
+                    Err(e) => return Err(e),
+                }
             }
-            Err(e) => return Err(e.into()),
         };
+        self.upstream
+            .call_if(download, gcs_transient)
+            .await
+            .map_err(backend)
+    }
 
-        let value = serde_json::from_slice(&bytes)?;
-        Ok(Some(value))
+    /// The digest is streamed from the bucket instead of downloaded whole.
+    async fn digest_in(&self, key: &str) -> Result<Option<String>, StorageError> {
+        let req = self.object(key);
+        let digest = || async {
+            let stream = match self
+                .client
+                .download_streamed_object(&req, &Range::default())
+                .await
+            {
+                Ok(stream) => stream,
+                Err(err) if not_found(&err) => return Ok(None),
+                Err(e) => return Err(StreamError::Gcs(e)),
+            };
+            let reader = StreamReader::new(stream.map_err(io::Error::other));
+            shaless_digest_reader(reader)
+                .await
+                .map(Some)
+                .map_err(StreamError::Read)
+        };
+        self.upstream
+            .call_if(digest, |err| match err {
+                StreamError::Gcs(err) => gcs_transient(err),
+                // The body broke off mid-download.
+                StreamError::Read(_) => true,
+            })
+            .await
+            .map_err(|err| StorageError::Backend(err.to_string()))
     }
 
-    pub async fn save_json<T: Serialize>(&self, key: &str, value: &T) -> anyhow::Result<()> {
-        let bytes = serde_json::to_vec(value)?;
+    /// Upload `bytes` to `key`; with `if_generation_match` only over that
+    /// generation of it (0: only if there is none).
+    async fn upload(
+        &self,
+        key: &str,
+        bytes: Vec<u8>,
+        if_generation_match: Option<i64>,
+    ) -> Result<(), StorageError> {
         let mut media = Media::new(key.to_string());
         media.content_type = "application/json".into();
         media.content_length = Some(bytes.len() as u64);
         let upload_type = UploadType::Simple(media);
         let req = UploadObjectRequest {
             bucket: self.bucket.clone(),
+            if_generation_match,
             ..Default::default()
         };
-        self.client.upload_object(&req, bytes, &upload_type).await?;
+        self.upstream
+            .call_if(
+                || self.client.upload_object(&req, bytes.clone(), &upload_type),
+                gcs_transient,
+            )
+            .await
+            .map_err(|err| match err {
+                CallError::Failed(err) if precondition_failed(&err) => {
+                    StorageError::Conflict(key.to_string())
+                }
+                err => backend(err),
+            })?;
         Ok(())
     }
 
-    pub async fn load_player_state<T: DeserializeOwned>(
-        &self,
-        player_uuid: &str,
-    ) -> anyhow::Result<Option<T>> {
-        let key = Self::key_for_player(player_uuid);
-        self.load_json(&key).await
+    async fn remove(&self, key: &str) -> Result<(), StorageError> {
+        let req = DeleteObjectRequest {
+            bucket: self.bucket.clone(),
+            object: key.to_string(),
+            ..Default::default()
+        };
+        let delete = || async {
+            match self.client.delete_object(&req).await {
+                Err(err) if not_found(&err) => Ok(()),
+                other => other,
+            }
+        };
+        self.upstream
+            .call_if(delete, gcs_transient)
+            .await
+            .map_err(backend)
     }
 
-    pub async fn save_player_state<T: Serialize>(
-        &self,
-        player_uuid: &str,
-        state: &T,
-    ) -> anyhow::Result<()> {
-        let key = Self::key_for_player(player_uuid);
-        self.save_json(&key, state).await
+    /// Following pagination.
+    async fn list_in(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let mut keys = Vec::new();
+        let mut page_token = None;
+        loop {
+            let req = ListObjectsRequest {
+                bucket: self.bucket.clone(),
+                prefix: Some(prefix.to_string()),
+                page_token: page_token.clone(),
+                ..Default::default()
+            };
+            let page = self
+                .upstream
+                .call_if(|| self.client.list_objects(&req), gcs_transient)
+                .await
+                .map_err(backend)?;
+            keys.extend(page.items.unwrap_or_default().into_iter().map(|o| o.name));
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                return Ok(keys);
+            }
+        }
     }
+}
 
-    pub async fn load_chunk(&self, cx: i64, cz: i64) -> anyhow::Result<ChunkSnapshot> {
-        let key = Self::key_for_chunk(cx, cz);
-        let chunk = self
-            .load_json::<ChunkSnapshot>(&key)
-            .await?
-            .unwrap_or_else(|| ChunkSnapshot {
-                cx,
-                cz,
-                ..ChunkSnapshot::default()
-            });
-        Ok(chunk)
+impl ObjectStore for GcsStore {
+    fn name(&self) -> &'static str {
+        "gcs"
     }
 
-    pub async fn save_chunk(&self, chunk: &ChunkSnapshot) -> anyhow::Result<()> {
-        let key = Self::key_for_chunk(chunk.cx, chunk.cz);
-        self.save_json(&key, chunk).await
+    fn load<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(self.download(key))
     }
 
-    pub async fn append_block_events(
-        &self,
-        cx: i64,
-        cz: i64,
-        events: &[BlockEvent],
-    ) -> anyhow::Result<()> {
-        if events.is_empty() {
-            return Ok(());
-        }
-        let key = Self::key_for_block_ledger(cx, cz);
-        let mut ledger = self
-            .load_json::<BlockLedger>(&key)
-            .await?
-            .unwrap_or_default();
-        ledger.events.extend_from_slice(events);
-        // Keep a modest window so the ledger does not grow unbounded.
-        const MAX_EVENTS: usize = 512;
-        if ledger.events.len() > MAX_EVENTS {
-            let drop = ledger.events.len() - MAX_EVENTS;
-            ledger.events.drain(0..drop);
-        }
-        self.save_json(&key, &ledger).await
+    fn load_versioned<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Stored>> {
+        Box::pin(self.download_current(key))
+    }
+
+    fn save<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Vec<u8>,
+        if_generation: Option<i64>,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(self.upload(key, bytes, if_generation))
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(self.remove(key))
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(self.list_in(prefix))
+    }
+
+    fn digest<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<String>> {
+        Box::pin(self.digest_in(key))
+    }
+
+    fn breaker(&self) -> Option<BreakerSnapshot> {
+        Some(self.upstream.snapshot())
     }
 }
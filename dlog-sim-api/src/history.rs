@@ -0,0 +1,123 @@
+//! Recent entity positions, for lag-compensated interactions.
+//!
+//! Every admitted tick records the reporting player's position at the time
+//! the client sent it (its `client_time_ms` moved onto the server clock by
+//! `clock_offset_ms`, see `docs/sim-api.md`). An `Interact` then resolves its
+//! target where the target was at the interacting client's compensated time,
+//! not where it is by the time the request lands. Rewinds are capped at
+//! [`MAX_REWIND_MS`], and only the last [`HISTORY_LEN`] samples per entity are
+//! kept, in memory: a restart simply starts with no history.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Furthest back an interaction may be resolved.
+pub const MAX_REWIND_MS: u64 = 250;
+/// Samples kept per entity, enough to span the rewind window at 200+ Hz.
+pub const HISTORY_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Sample {
+    /// Server time the entity was at this position.
+    pub at_ms: u64,
+    pub universe_tick: u64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct EntityHistory {
+    entities: Mutex<HashMap<String, VecDeque<Sample>>>,
+}
+
+impl EntityHistory {
+    pub fn record(&self, entity_id: &str, sample: Sample) {
+        let mut entities = self.entities.lock().expect("entity history mutex poisoned");
+        let ring = entities.entry(entity_id.to_string()).or_default();
+        // Ticks can arrive out of order; keep the ring sorted by time.
+        let at = ring.partition_point(|s| s.at_ms <= sample.at_ms);
+        ring.insert(at, sample);
+        if ring.len() > HISTORY_LEN {
+            ring.pop_front();
+        }
+    }
+
+    /// Where `entity_id` was at `at_ms`: the latest sample no later than
+    /// that, or the oldest one held if the entity only appeared afterwards.
+    pub fn position_at(&self, entity_id: &str, at_ms: u64) -> Option<Sample> {
+        let entities = self.entities.lock().expect("entity history mutex poisoned");
+        let ring = entities.get(entity_id)?;
+        ring.iter()
+            .rev()
+            .find(|s| s.at_ms <= at_ms)
+            .or_else(|| ring.front())
+            .copied()
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Server time a tick's inputs happened at: the client's send time shifted by
+/// its clock offset, no earlier than [`MAX_REWIND_MS`] before `received_ms`
+/// and never in the future. Without both fields it's `received_ms`.
+pub fn compensated_ms(
+    client_time_ms: Option<u64>,
+    clock_offset_ms: Option<i64>,
+    received_ms: u64,
+) -> u64 {
+    match (client_time_ms, clock_offset_ms) {
+        (Some(client_ms), Some(offset_ms)) => client_ms
+            .saturating_add_signed(offset_ms)
+            .clamp(received_ms.saturating_sub(MAX_REWIND_MS), received_ms),
+        _ => received_ms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(at_ms: u64, x: f64) -> Sample {
+        Sample {
+            at_ms,
+            universe_tick: at_ms / 100,
+            x,
+            y: 64.0,
+            z: 0.0,
+        }
+    }
+
+    #[test]
+    fn rewinds_to_the_sample_in_effect_and_forgets_old_ones() {
+        let history = EntityHistory::default();
+        history.record("player-b", at(1_000, 1.0));
+        history.record("player-b", at(1_200, 3.0));
+        history.record("player-b", at(1_100, 2.0)); // late delivery
+
+        assert_eq!(history.position_at("player-b", 1_150).unwrap().x, 2.0);
+        assert_eq!(history.position_at("player-b", 5_000).unwrap().x, 3.0);
+        assert_eq!(history.position_at("player-b", 10).unwrap().x, 1.0);
+        assert!(history.position_at("player-c", 1_000).is_none());
+
+        for i in 0..HISTORY_LEN as u64 {
+            history.record("player-b", at(2_000 + i, 9.0));
+        }
+        assert_eq!(history.position_at("player-b", 1_150).unwrap().at_ms, 2_000);
+
+        assert_eq!(compensated_ms(Some(900), Some(50), 1_000), 950);
+        assert_eq!(
+            compensated_ms(Some(100), Some(50), 1_000),
+            1_000 - MAX_REWIND_MS
+        );
+        assert_eq!(compensated_ms(Some(2_000), Some(0), 1_000), 1_000);
+        assert_eq!(compensated_ms(Some(900), None, 1_000), 1_000);
+    }
+}
@@ -0,0 +1,404 @@
+//! Resumable world imports, for worlds too big for one request.
+//!
+//! An import is started, then fed numbered parts, each a JSON array of
+//! chunks as the explorer serves them, in any order and as often as
+//! needed: a part sent again replaces the last copy, so an importer that
+//! lost its connection asks which parts arrived and sends the rest. Parts
+//! wait in `imports;<id>;parts;` until the import is completed.
+//!
+//! Completing copies every chunk the import touches into `undo;<id>;`
+//! before writing any, then writes the parts in order (a chunk in two parts
+//! ends up as the later one), saving its progress to the import after each
+//! part. If a write fails, the copied chunks go back and the import is
+//! `rolled_back`, ready to be completed again; if putting them back fails
+//! too it is `failed` and the copies stay for a person to look at. Like a
+//! restore, every chunk written gets a version past both its own and the
+//! live one. Ticks that build in the imported chunks meanwhile can be lost,
+//! so pause the sim first.
+
+use dlog_error::StorageError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::model::ChunkSnapshot;
+use crate::savepoint::restored_version;
+use crate::storage::OmegaStorage;
+
+const IMPORTS_PREFIX: &str = "imports;";
+/// Parts one import can have.
+pub const PARTS_MAX: u32 = 10_000;
+/// Largest part body taken, under Cloud Run's 32 MiB request cap.
+pub const PART_BYTES_MAX: usize = 30 * 1024 * 1024;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportState {
+    /// Taking parts.
+    Open,
+    Applying,
+    Applied,
+    /// A write failed and the world was put back; parts are kept.
+    RolledBack,
+    /// Putting the world back failed as well; see `undo;<id>;`.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldImport {
+    pub id: String,
+    pub created_ms: u64,
+    pub state: ImportState,
+    /// Parts received, listed when the import is read.
+    #[serde(default)]
+    pub parts: Vec<u32>,
+    /// Chunks in all parts, counted when the import is completed.
+    #[serde(default)]
+    pub chunks: usize,
+    /// Of those, how many have been written to the world.
+    #[serde(default)]
+    pub applied: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PartReceipt {
+    pub part: u32,
+    pub chunks: usize,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Invalid(String),
+    NotFound(String),
+    /// The import isn't in a state that allows the call.
+    Conflict(String),
+    /// Completed before every part arrived.
+    MissingParts(Vec<u32>),
+    Storage(StorageError),
+}
+
+impl ImportError {
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Invalid(_) => 400,
+            Self::NotFound(_) => 404,
+            Self::Conflict(_) | Self::MissingParts(_) => 409,
+            Self::Storage(err) => err.status(),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(why) | Self::Conflict(why) => f.write_str(why),
+            Self::NotFound(id) => write!(f, "import {id} not found"),
+            Self::MissingParts(parts) => {
+                let parts: Vec<String> = parts.iter().map(u32::to_string).collect();
+                write!(f, "missing parts {}", parts.join(", "))
+            }
+            Self::Storage(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<StorageError> for ImportError {
+    fn from(err: StorageError) -> Self {
+        Self::Storage(err)
+    }
+}
+
+pub fn undo_scope(id: &str) -> String {
+    format!("undo;{id};")
+}
+
+fn manifest_key(id: &str) -> String {
+    format!("{IMPORTS_PREFIX}{id}.json")
+}
+
+fn parts_prefix(id: &str) -> String {
+    format!("{IMPORTS_PREFIX}{id};parts;")
+}
+
+/// Ids are `a-z0-9-` as [`start`] makes them, so one never splits a key.
+fn check_id(id: &str) -> Result<(), ImportError> {
+    let ok = !id.is_empty()
+        && id.len() <= 32
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if ok {
+        Ok(())
+    } else {
+        Err(ImportError::NotFound(id.to_string()))
+    }
+}
+
+/// Parts of `0..parts` not in `received`.
+fn missing_parts(received: &[u32], parts: u32) -> Vec<u32> {
+    let received: BTreeSet<u32> = received.iter().copied().collect();
+    (0..parts).filter(|part| !received.contains(part)).collect()
+}
+
+fn parse_part(body: &[u8]) -> Result<Vec<ChunkSnapshot>, ImportError> {
+    serde_json::from_slice(body)
+        .map_err(|err| ImportError::Invalid(format!("a part is a JSON array of chunks: {err}")))
+}
+
+pub async fn start(storage: &OmegaStorage, created_ms: u64) -> Result<WorldImport, ImportError> {
+    loop {
+        let id = format!(
+            "{created_ms:x}-{:x}",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        if storage.load_bytes(&manifest_key(&id)).await?.is_some() {
+            continue;
+        }
+        let import = WorldImport {
+            id,
+            created_ms,
+            state: ImportState::Open,
+            parts: Vec::new(),
+            chunks: 0,
+            applied: 0,
+            error: None,
+        };
+        storage
+            .save_json(&manifest_key(&import.id), &import)
+            .await?;
+        return Ok(import);
+    }
+}
+
+/// The import with the parts received so far.
+pub async fn status(storage: &OmegaStorage, id: &str) -> Result<WorldImport, ImportError> {
+    check_id(id)?;
+    let mut import: WorldImport = storage
+        .load_json(&manifest_key(id))
+        .await?
+        .ok_or_else(|| ImportError::NotFound(id.to_string()))?;
+    let prefix = parts_prefix(id);
+    import.parts = storage
+        .list_keys(&prefix)
+        .await?
+        .iter()
+        .filter_map(|key| {
+            key.strip_prefix(&prefix)?
+                .strip_suffix(".json")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    import.parts.sort_unstable();
+    Ok(import)
+}
+
+/// Store part `part`, replacing any earlier copy.
+pub async fn put_part(
+    storage: &OmegaStorage,
+    id: &str,
+    part: u32,
+    body: Vec<u8>,
+) -> Result<PartReceipt, ImportError> {
+    let import = status(storage, id).await?;
+    if !matches!(import.state, ImportState::Open | ImportState::RolledBack) {
+        return Err(ImportError::Conflict(format!(
+            "import {id} is {:?} and takes no more parts",
+            import.state
+        )));
+    }
+    if part >= PARTS_MAX {
+        return Err(ImportError::Invalid(format!(
+            "parts are numbered 0-{}",
+            PARTS_MAX - 1
+        )));
+    }
+    let chunks = parse_part(&body)?.len();
+    storage
+        .save_bytes(&format!("{}{part}.json", parts_prefix(id)), body)
+        .await?;
+    Ok(PartReceipt { part, chunks })
+}
+
+async fn load_part(
+    storage: &OmegaStorage,
+    id: &str,
+    part: u32,
+) -> Result<Vec<ChunkSnapshot>, ImportError> {
+    let body = storage
+        .load_bytes(&format!("{}{part}.json", parts_prefix(id)))
+        .await?
+        .ok_or_else(|| ImportError::MissingParts(vec![part]))?;
+    parse_part(&body)
+}
+
+/// Write parts `0..parts` to the live world, all or nothing.
+pub async fn complete(
+    storage: &OmegaStorage,
+    id: &str,
+    parts: u32,
+) -> Result<WorldImport, ImportError> {
+    let mut import = status(storage, id).await?;
+    if !matches!(import.state, ImportState::Open | ImportState::RolledBack) {
+        return Err(ImportError::Conflict(format!(
+            "import {id} is {:?}",
+            import.state
+        )));
+    }
+    if parts == 0 {
+        return Err(ImportError::Invalid("an import needs a part".into()));
+    }
+    let missing = missing_parts(&import.parts, parts);
+    if !missing.is_empty() {
+        return Err(ImportError::MissingParts(missing));
+    }
+    import.state = ImportState::Applying;
+    import.applied = 0;
+    import.error = None;
+    storage.save_json(&manifest_key(id), &import).await?;
+
+    let undo = storage.scoped(undo_scope(id));
+    let mut touched = BTreeSet::new();
+    let backed_up = async {
+        import.chunks = 0;
+        for part in 0..parts {
+            for chunk in load_part(storage, id, part).await? {
+                import.chunks += 1;
+                if touched.insert((chunk.cx, chunk.cz)) {
+                    let live = storage.load_chunk(chunk.cx, chunk.cz).await?;
+                    undo.update_chunk(chunk.cx, chunk.cz, |backup| *backup = live.clone())
+                        .await?;
+                }
+            }
+        }
+        Ok::<_, ImportError>(())
+    }
+    .await;
+    if let Err(err) = backed_up {
+        // Nothing was written yet.
+        return Err(settle(storage, &mut import, ImportState::RolledBack, err).await);
+    }
+
+    let written = async {
+        for part in 0..parts {
+            for chunk in load_part(storage, id, part).await? {
+                storage
+                    .update_chunk(chunk.cx, chunk.cz, |live| {
+                        let version = restored_version(live.version, chunk.version);
+                        *live = chunk.clone();
+                        live.version = version;
+                    })
+                    .await?;
+                import.applied += 1;
+            }
+            storage.save_json(&manifest_key(id), &import).await?;
+        }
+        Ok::<_, ImportError>(())
+    }
+    .await;
+    if let Err(err) = written {
+        let state = match put_back(storage, &undo, &touched).await {
+            Ok(()) => ImportState::RolledBack,
+            Err(undo_err) => {
+                import.error = Some(format!("rollback failed: {undo_err}"));
+                ImportState::Failed
+            }
+        };
+        return Err(settle(storage, &mut import, state, err).await);
+    }
+
+    import.state = ImportState::Applied;
+    storage.save_json(&manifest_key(id), &import).await?;
+    delete_prefix(storage, &parts_prefix(id)).await?;
+    delete_prefix(storage, &undo_scope(id)).await?;
+    import.parts.clear();
+    Ok(import)
+}
+
+/// Record how a failed completion ended and hand back its error.
+async fn settle(
+    storage: &OmegaStorage,
+    import: &mut WorldImport,
+    state: ImportState,
+    err: ImportError,
+) -> ImportError {
+    import.state = state;
+    import.applied = 0;
+    import.error = Some(match import.error.take() {
+        Some(undo_err) => format!("{err}; {undo_err}"),
+        None => err.to_string(),
+    });
+    if let Err(save_err) = storage.save_json(&manifest_key(&import.id), import).await {
+        return save_err.into();
+    }
+    err
+}
+
+async fn put_back(
+    storage: &OmegaStorage,
+    undo: &OmegaStorage,
+    touched: &BTreeSet<(i64, i64)>,
+) -> Result<(), StorageError> {
+    for &(cx, cz) in touched {
+        let chunk = undo.load_chunk(cx, cz).await?;
+        storage
+            .update_chunk(cx, cz, |live| {
+                let version = restored_version(live.version, chunk.version);
+                *live = chunk.clone();
+                live.version = version;
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Drop an import that isn't being applied, with its parts.
+pub async fn abort(storage: &OmegaStorage, id: &str) -> Result<(), ImportError> {
+    let import = status(storage, id).await?;
+    if matches!(import.state, ImportState::Applying | ImportState::Failed) {
+        return Err(ImportError::Conflict(format!(
+            "import {id} is {:?}",
+            import.state
+        )));
+    }
+    delete_prefix(storage, &parts_prefix(id)).await?;
+    storage.delete(&manifest_key(id)).await?;
+    Ok(())
+}
+
+async fn delete_prefix(storage: &OmegaStorage, prefix: &str) -> Result<(), StorageError> {
+    for key in storage.list_keys(prefix).await? {
+        storage.delete(&key).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_parts_and_errors() {
+        assert!(check_id("18f2a3b4c5d-0").is_ok());
+        for bad in ["", "A", "a;b", "../x", &"x".repeat(33)] {
+            assert_eq!(check_id(bad).unwrap_err().status(), 404);
+        }
+
+        assert_eq!(missing_parts(&[0, 2, 3], 5), [1, 4]);
+        assert!(missing_parts(&[1, 0], 2).is_empty());
+        let err = ImportError::MissingParts(missing_parts(&[], 2));
+        assert_eq!(
+            (err.status(), err.to_string()),
+            (409, "missing parts 0, 1".into())
+        );
+
+        let part = br#"[{"cx": 1, "cz": 2}, {"cx": 1, "cz": 3, "version": 4, "blocks": []}]"#;
+        assert_eq!(parse_part(part).unwrap().len(), 2);
+        assert_eq!(parse_part(b"{}").unwrap_err().status(), 400);
+    }
+}
@@ -0,0 +1,217 @@
+//! Continuous world checks behind `GET /admin/invariants`.
+//!
+//! Every `OMEGA_INVARIANTS_EVERY_SECS` (default 30; `0` turns the loop off)
+//! the sim reads each live chunk with its block ledger and checks that:
+//!
+//! - `chunk_version`: a chunk's version never goes back between checks;
+//! - `ledger_backed`: every block placed inside the ledger's window matches
+//!   the `place` event that put it there (same tick, same block).
+//!
+//! Restoring a savepoint rewinds chunks but not ledgers; restored blocks keep
+//! the tick they were placed at, so their events still back them. Blocks
+//! older than the ledger's window aren't checked. Violations that last two
+//! checks are logged and, with `OMEGA_INVARIANT_WEBHOOK` set, POSTed there as
+//! JSON carrying each one's expected and actual value.
+
+use crate::history::now_ms;
+use crate::model::{BlockAction, BlockLedger, ChunkSnapshot};
+use crate::storage::OmegaStorage;
+use corelib::invariants::{AlertGate, InvariantReport, Violation};
+use dlog_error::StorageError;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_EVERY_SECS: u64 = 30;
+
+/// Check one chunk against its ledger and the version seen last time.
+pub fn check_chunk(
+    report: &mut InvariantReport,
+    chunk: &ChunkSnapshot,
+    ledger: &BlockLedger,
+    last_version: Option<u64>,
+) {
+    let at = format!("{};{}", chunk.cx, chunk.cz);
+    if let Some(last) = last_version {
+        report.expect(
+            "chunk_version",
+            at.as_str(),
+            chunk.version.max(last),
+            chunk.version,
+        );
+    }
+    // Events of the oldest tick may have been cut from the window in part.
+    let Some(oldest) = ledger.events.first().map(|event| event.tick) else {
+        return;
+    };
+    for block in chunk.blocks().filter(|b| b.last_tick > oldest) {
+        let placed = ledger
+            .events
+            .iter()
+            .rev()
+            .find(|event| {
+                event.tick == block.last_tick
+                    && matches!(event.action, BlockAction::Place)
+                    && (event.x, event.y, event.z) == (block.x, block.y, block.z)
+            })
+            .map(|event| event.block.as_str());
+        report.expect(
+            "ledger_backed",
+            format!("{at}@{};{};{}", block.x, block.y, block.z),
+            Some(block.block),
+            placed,
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct InvariantMonitor {
+    versions: Mutex<HashMap<(i64, i64), u64>>,
+    latest: Mutex<Option<InvariantReport>>,
+    gate: Mutex<AlertGate>,
+    webhook: Option<String>,
+    http: Client,
+}
+
+impl InvariantMonitor {
+    pub fn from_env() -> Self {
+        Self {
+            versions: Mutex::default(),
+            latest: Mutex::new(None),
+            gate: Mutex::default(),
+            webhook: env::var("OMEGA_INVARIANT_WEBHOOK")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+            http: Client::new(),
+        }
+    }
+
+    /// Time between background checks; `None` when they are off.
+    pub fn interval_from_env() -> Option<Duration> {
+        let secs = env::var("OMEGA_INVARIANTS_EVERY_SECS")
+            .ok()
+            .and_then(|raw| raw.trim().parse().ok())
+            .unwrap_or(DEFAULT_EVERY_SECS);
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    pub fn latest(&self) -> Option<InvariantReport> {
+        self.latest
+            .lock()
+            .expect("invariants mutex poisoned")
+            .clone()
+    }
+
+    /// Check every live chunk, keep the report and raise its alerts.
+    pub async fn check(&self, storage: &OmegaStorage) -> Result<InvariantReport, StorageError> {
+        let mut report = InvariantReport::new(now_ms() as i64);
+        let mut seen = HashMap::new();
+        for (cx, cz) in storage.list_chunks().await? {
+            let chunk = storage.load_chunk(cx, cz).await?;
+            let ledger = storage.load_block_ledger(cx, cz).await?;
+            let last = self
+                .versions
+                .lock()
+                .expect("invariants mutex poisoned")
+                .get(&(cx, cz))
+                .copied();
+            check_chunk(&mut report, &chunk, &ledger, last);
+            seen.insert((cx, cz), chunk.version.max(last.unwrap_or(0)));
+        }
+        *self.versions.lock().expect("invariants mutex poisoned") = seen;
+        let fresh = self
+            .gate
+            .lock()
+            .expect("invariant gate mutex poisoned")
+            .admit(&report);
+        *self.latest.lock().expect("invariants mutex poisoned") = Some(report.clone());
+        if !fresh.is_empty() {
+            self.alert(&fresh).await;
+        }
+        Ok(report)
+    }
+
+    async fn alert(&self, violations: &[Violation]) {
+        for violation in violations {
+            warn!(
+                "[invariants] {} broken for {}: expected {}, found {}",
+                violation.invariant, violation.subject, violation.expected, violation.actual
+            );
+        }
+        let Some(url) = &self.webhook else {
+            return;
+        };
+        let body = serde_json::json!({
+            "service": "dlog-sim-api",
+            "violations": violations,
+        });
+        let sent = self
+            .http
+            .post(url)
+            .timeout(Duration::from_secs(5))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        if let Err(err) = sent {
+            warn!("[invariants] webhook failed: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::BlockEvent;
+
+    fn event(tick: u64, x: i64, block: &str, action: BlockAction) -> BlockEvent {
+        BlockEvent {
+            tick,
+            x,
+            y: 64,
+            z: 0,
+            block: block.to_string(),
+            action,
+            builder: None,
+        }
+    }
+
+    #[test]
+    fn chunks_must_match_their_ledger_and_keep_their_version() {
+        let ledger = BlockLedger {
+            events: vec![
+                event(1, 0, "minecraft:dirt", BlockAction::Place),
+                event(2, 1, "minecraft:stone", BlockAction::Place),
+                event(3, 2, "minecraft:oak_log", BlockAction::Place),
+                event(4, 2, "minecraft:oak_log", BlockAction::Break),
+            ],
+        };
+        let mut chunk = ChunkSnapshot::new(0, 0);
+        chunk.version = 3;
+        // Tick 1 is the window's edge, so the dirt isn't checked.
+        chunk.set_block(0, 64, 0, "minecraft:dirt", 1);
+        chunk.set_block(1, 64, 0, "minecraft:stone", 2);
+        let mut report = InvariantReport::new(0);
+        check_chunk(&mut report, &chunk, &ledger, Some(3));
+        assert!(report.ok());
+        assert_eq!(report.checked["ledger_backed"], 1);
+
+        chunk.version = 2;
+        chunk.set_block(5, 64, 0, "minecraft:tnt", 4);
+        let mut report = InvariantReport::new(0);
+        check_chunk(&mut report, &chunk, &ledger, Some(3));
+        let broken: Vec<_> = report
+            .violations
+            .iter()
+            .map(|v| (v.invariant.as_str(), v.subject.as_str()))
+            .collect();
+        assert_eq!(
+            broken,
+            [("chunk_version", "0;0"), ("ledger_backed", "0;0@5;64;0")]
+        );
+        assert_eq!(report.violations[1].actual, serde_json::Value::Null);
+    }
+}
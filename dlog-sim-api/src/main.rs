@@ -1,20 +1,81 @@
+mod acl;
+mod cannon;
+mod components;
+mod control;
 mod gcs;
+mod history;
+mod import;
+mod invariants;
 mod model;
+mod palette;
+mod physics;
+mod region;
+mod rules;
+mod savepoint;
+mod schema;
 mod sim;
+mod stamina;
+mod stats;
+mod storage;
+mod store;
+mod warm;
+mod world_hash;
 
-use axum::extract::State;
-use axum::http::StatusCode;
-use axum::routing::{get, post};
+use acl::{AclChange, AclError, ChunkAcl, Permission};
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, FromRef, Path, Query, State};
+use axum::handler::Handler;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::routing::{delete, get, post, put, MethodRouter};
 use axum::{Json, Router};
-use gcs::OmegaStorage;
+use control::{Admission, ControlStatus, SimControl};
+use corelib::invariants::InvariantReport;
+use dlog_command::{command, CommandReply, Registry, HELP};
+use dlog_error::StorageError;
+use dlog_http_cache::{cached, CachePolicy, Tagged};
+use dlog_profiling::{AllocStats, CountingAlloc, ProfileParams};
+#[cfg(feature = "chaos")]
+use dlog_resilience::chaos::{self, FaultPlan};
+use dlog_resilience::BreakerState;
+use history::EntityHistory;
+use import::{ImportError, PartReceipt, WorldImport};
+use invariants::InvariantMonitor;
 use model::{
-    BlockAction, BlockEvent, BlockState, BlockUpdate, ChunkSnapshot, TickRequest, TickResponse,
+    BlockAction, BlockEvent, BlockUpdate, ChunkSnapshot, RejectedUpdate, TickRequest, TickResponse,
 };
+use rules::{BlockGate, BlockRules};
+use savepoint::{Branch, RestoreReport, Savepoint, SavepointError};
 use sim::PlayerState;
-use std::collections::HashMap;
+use stats::ChunkReport;
+use std::collections::{BTreeMap, BTreeSet};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use storage::OmegaStorage;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
+use warm::{Warmer, WarmupReport};
+use world_hash::{HashScope, WorldHash, WorldHasher};
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+#[derive(Clone)]
+struct AppState {
+    storage: OmegaStorage,
+    control: Arc<SimControl>,
+    history: Arc<EntityHistory>,
+    rules: Arc<BlockRules>,
+    invariants: Arc<InvariantMonitor>,
+    warmer: Arc<Warmer>,
+}
+
+impl FromRef<AppState> for OmegaStorage {
+    fn from_ref(state: &AppState) -> Self {
+        state.storage.clone()
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -29,10 +90,99 @@ async fn main() -> anyhow::Result<()> {
 
     let storage = OmegaStorage::new_from_env().await?;
 
+    // `dlog-sim-api migrate [--dry-run]` upgrades stored objects and exits.
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        let dry_run = std::env::args().skip(2).any(|arg| arg == "--dry-run");
+        let report = storage.migrate(dry_run).await?;
+        storage.flush_replication().await;
+        print!("{report}");
+        if report.failures() > 0 {
+            anyhow::bail!("{} objects could not be migrated", report.failures());
+        }
+        return Ok(());
+    }
+
+    let control = SimControl::from_env();
+    let rules = BlockRules::from_env()?;
+    if std::env::var("OMEGA_SIM_BLOCK_RULES").is_ok() {
+        info!("[sim] block rules for {} worlds", rules.worlds.len());
+    }
+    if control.enabled() {
+        warn!("[sim] OMEGA_SIM_DEBUG=1: pause and step controls are live");
+    }
+    #[cfg(feature = "chaos")]
+    match chaos::load_env() {
+        Ok(0) => {}
+        Ok(count) => warn!("[chaos] OMEGA_CHAOS: faults injected into {count} upstreams"),
+        Err(err) => warn!("[chaos] OMEGA_CHAOS ignored: {err}"),
+    }
     let app = Router::new()
         .route("/health", get(health))
+        .route("/warmup", post(warmup))
         .route("/v1/sim/tick", post(sim_tick))
-        .with_state(storage);
+        .route("/v1/sim/hash", get(sim_hash))
+        .route("/v1/sim/digest", get(sim_digest))
+        .route("/v1/sim/control", get(control_status))
+        .route("/v1/sim/pause", post(control_pause))
+        .route("/v1/sim/resume", post(control_resume))
+        .route("/v1/sim/step", post(control_step))
+        .route("/v1/sim/command", post(sim_command))
+        .route(
+            "/v1/sim/savepoints",
+            get(list_savepoints).post(create_savepoint),
+        )
+        .route("/v1/sim/savepoints/:name", delete(discard_savepoint))
+        .route("/v1/sim/savepoints/:name/restore", post(restore_savepoint))
+        .route("/v1/sim/branches", get(list_branches).post(fork_branch))
+        .route("/v1/sim/branches/:name", delete(discard_branch))
+        .route("/v1/sim/imports", post(start_import))
+        .route(
+            "/v1/sim/imports/:id",
+            get(import_status).delete(abort_import),
+        )
+        .route(
+            "/v1/sim/imports/:id/parts/:part",
+            put(import_part).layer(DefaultBodyLimit::max(import::PART_BYTES_MAX)),
+        )
+        .route("/v1/sim/imports/:id/complete", post(complete_import))
+        .route("/v1/chunks/:cx/:cz/stats", get(chunk_stats))
+        .route("/v1/chunks/:cx/:cz/acl", get(chunk_acl))
+        .route(
+            "/v1/chunks/:cx/:cz/acl/:label",
+            put(grant_chunk_acl).delete(revoke_chunk_acl),
+        )
+        .route("/admin/invariants", get(admin_invariants))
+        .route("/debug/pprof/profile", get(pprof_profile))
+        .route("/debug/pprof/allocs", get(pprof_allocs))
+        .nest(
+            "/explorer",
+            Router::new().route("/chunks/:cx/:cz", chunk_route(chunk)),
+        );
+    #[cfg(feature = "chaos")]
+    let app = app.route(
+        "/v1/sim/chaos",
+        get(chaos_plans).put(chaos_update).delete(chaos_clear),
+    );
+    let invariants = Arc::new(InvariantMonitor::from_env());
+    match InvariantMonitor::interval_from_env() {
+        Some(every) => spawn_invariant_checks(storage.clone(), invariants.clone(), every),
+        None => info!("[invariants] OMEGA_INVARIANTS_EVERY_SECS=0; background checks are off"),
+    }
+    // Cloud Run holds traffic until the port opens, so warm up first.
+    let warmer = Arc::new(Warmer::from_env());
+    warm::warm_up_at_boot(&storage, &warmer).await;
+    match Warmer::save_interval_from_env() {
+        Some(every) => warm::spawn_saves(storage.clone(), warmer.clone(), every),
+        None => info!("[warm] OMEGA_WARM_SAVE_SECS=0; recent activity is not saved"),
+    }
+    let app = app.with_state(AppState {
+        storage,
+        control: Arc::new(control),
+        history: Arc::default(),
+        rules: Arc::new(rules),
+        invariants,
+        warmer,
+    });
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     info!("listening on {}", addr);
@@ -43,81 +193,914 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn health() -> &'static str {
-    "ok"
+/// Liveness plus each bucket's breaker, so a tripped bucket shows up before
+/// ticks fail, how far behind cross-region copies are, and the last warmup.
+async fn health(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let storage = &state.storage;
+    let upstreams = storage.breakers();
+    let status = if upstreams
+        .iter()
+        .all(|gcs| gcs.state == BreakerState::Closed)
+    {
+        "ok"
+    } else {
+        "degraded"
+    };
+    Json(serde_json::json!({
+        "status": status,
+        "store": storage.store_name(),
+        "upstreams": upstreams,
+        "replication": storage.replication(),
+        "warmup": state.warmer.latest(),
+        "preloaded": storage.preloaded(),
+    }))
 }
 
-async fn sim_tick(
+/// Preload the recently active players and chunks again (admin token).
+async fn warmup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<WarmupReport>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let report = state.warmer.warm_up(&state.storage).await.map_err(|err| {
+        warn!("[warm] warmup failed: {}", err);
+        (StatusCode::BAD_GATEWAY, "failed to warm up".to_string())
+    })?;
+    Ok(Json(report))
+}
+
+/// Same guard as the gateway: X-Admin-Token must match OMEGA_ADMIN_TOKEN, and
+/// without a configured token every admin route is refused.
+fn require_admin_token(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let denied = || (StatusCode::UNAUTHORIZED, "admin token required".to_string());
+    let expected = std::env::var("OMEGA_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+        .ok_or_else(denied)?;
+    let ok = headers
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == expected);
+    if !ok {
+        return Err(denied());
+    }
+    Ok(())
+}
+
+/// Check the live world's invariants every `every`.
+fn spawn_invariant_checks(storage: OmegaStorage, monitor: Arc<InvariantMonitor>, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = monitor.check(&storage).await {
+                warn!("[invariants] check failed: {}", err);
+            }
+        }
+    });
+}
+
+/// The latest invariant report, checking now when the background loop
+/// hasn't produced one (admin token).
+async fn admin_invariants(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<InvariantReport>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    if let Some(report) = state.invariants.latest() {
+        return Ok(Json(report));
+    }
+    let report = state
+        .invariants
+        .check(&state.storage)
+        .await
+        .map_err(|err| {
+            warn!("[invariants] check failed: {}", err);
+            (
+                StatusCode::BAD_GATEWAY,
+                "failed to check invariants".to_string(),
+            )
+        })?;
+    Ok(Json(report))
+}
+
+/// CPU profile of the sim as pprof protobuf.
+async fn pprof_profile(
+    headers: HeaderMap,
+    Query(params): Query<ProfileParams>,
+) -> Result<Response, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    Ok(dlog_profiling::profile_response(params).await)
+}
+
+async fn pprof_allocs(headers: HeaderMap) -> Result<Json<AllocStats>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    Ok(Json(dlog_profiling::alloc_stats()))
+}
+
+/// Chunks change only through block updates, each of which bumps `version`.
+const CHUNK_CACHE: CachePolicy = CachePolicy {
+    max_age: 8,
+    stale_while_revalidate: 60,
+};
+
+/// `/chunks/:cx/:cz` behind the shared ETag layer.
+fn chunk_route<S, H, T>(handler: H) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    H: Handler<T, S>,
+    T: 'static,
+{
+    cached(get(handler), CHUNK_CACHE)
+}
+
+/// The ETag is the chunk version, so a CDN revalidation costs one storage
+/// read and no body.
+fn tagged_chunk(chunk: ChunkSnapshot) -> Tagged<ChunkSnapshot> {
+    let etag = format!("\"chunk;{};{};v{}\"", chunk.cx, chunk.cz, chunk.version);
+    Tagged(etag, chunk)
+}
+
+/// Public read of one chunk.
+async fn chunk(
     State(storage): State<OmegaStorage>,
-    Json(req): Json<TickRequest>,
-) -> Result<Json<TickResponse>, (StatusCode, String)> {
-    let player_uuid = req.player_uuid.clone();
+    Path((cx, cz)): Path<(i64, i64)>,
+) -> Result<Tagged<ChunkSnapshot>, (StatusCode, String)> {
+    let chunk = storage.load_chunk(cx, cz).await.map_err(|err| {
+        warn!("[explorer] failed to load chunk {};{}: {}", cx, cz, err);
+        (StatusCode::BAD_GATEWAY, "failed to load chunk".to_string())
+    })?;
+    Ok(tagged_chunk(chunk))
+}
+
+/// Public read of one chunk's block statistics and who may build there.
+async fn chunk_stats(
+    State(storage): State<OmegaStorage>,
+    Path((cx, cz)): Path<(i64, i64)>,
+) -> Result<Json<ChunkReport>, (StatusCode, String)> {
+    let loaded = async {
+        let stats = storage.load_chunk_stats(cx, cz).await?;
+        Ok::<_, StorageError>((stats, storage.load_chunk_acl(cx, cz).await?))
+    };
+    let (stats, acl) = loaded.await.map_err(|err| {
+        warn!(
+            "[explorer] failed to load stats of chunk {};{}: {}",
+            cx, cz, err
+        );
+        (
+            StatusCode::BAD_GATEWAY,
+            "failed to load chunk stats".to_string(),
+        )
+    })?;
+    Ok(Json(ChunkReport::new(stats, acl)))
+}
+
+/// Public read of one chunk's access list and its audit trail.
+async fn chunk_acl(
+    State(storage): State<OmegaStorage>,
+    Path((cx, cz)): Path<(i64, i64)>,
+) -> Result<Json<ChunkAcl>, (StatusCode, String)> {
+    let acl = storage
+        .load_chunk_acl(cx, cz)
+        .await
+        .map_err(|err| acl_failure(err.into()))?;
+    Ok(Json(acl))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AclGrant {
+    /// Label making the change, vouched for by the admin token's holder.
+    actor: String,
+    permissions: BTreeSet<Permission>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AclActor {
+    actor: String,
+}
+
+/// Set what `label` may do in a chunk (admin token, on behalf of `actor`).
+async fn grant_chunk_acl(
+    State(storage): State<OmegaStorage>,
+    Path((cx, cz, label)): Path<(i64, i64, String)>,
+    headers: HeaderMap,
+    Json(req): Json<AclGrant>,
+) -> Result<Json<ChunkAcl>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    change_chunk_acl(&storage, cx, cz, req.actor, label, req.permissions).await
+}
 
-    let current_state: PlayerState = match storage.load_player_state(&player_uuid).await {
-        Ok(Some(state)) => state,
-        Ok(None) => PlayerState::default(),
+/// Take everything `label` may do in a chunk away (admin token, on behalf
+/// of `actor`); the owner revoking itself releases the claim.
+async fn revoke_chunk_acl(
+    State(storage): State<OmegaStorage>,
+    Path((cx, cz, label)): Path<(i64, i64, String)>,
+    headers: HeaderMap,
+    Query(req): Query<AclActor>,
+) -> Result<Json<ChunkAcl>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    change_chunk_acl(&storage, cx, cz, req.actor, label, BTreeSet::new()).await
+}
+
+async fn change_chunk_acl(
+    storage: &OmegaStorage,
+    cx: i64,
+    cz: i64,
+    actor: String,
+    label: String,
+    permissions: BTreeSet<Permission>,
+) -> Result<Json<ChunkAcl>, (StatusCode, String)> {
+    let change = AclChange {
+        at_ms: history::now_ms(),
+        actor,
+        label,
+        permissions,
+    };
+    let acl = storage
+        .update_chunk_acl(cx, cz, |acl| acl.apply(change.clone()))
+        .await
+        .map_err(acl_failure)?;
+    info!(
+        "[acl] {} set {} to {:?} in chunk {};{}",
+        change.actor, change.label, change.permissions, cx, cz
+    );
+    Ok(Json(acl))
+}
+
+fn acl_failure(err: AclError) -> (StatusCode, String) {
+    if let AclError::Storage(err) = &err {
+        warn!("[acl] {}", err);
+    }
+    let status = StatusCode::from_u16(err.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, err.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct HashQuery {
+    #[serde(default)]
+    scope: HashScope,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DigestQuery {
+    key: String,
+}
+
+/// sha-less digest of one stored object, streamed from the bucket (admin
+/// token); compares large objects across buckets without downloading them.
+async fn sim_digest(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Query(query): Query<DigestQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    match storage.digest_object(&query.key).await {
+        Ok(Some(digest)) => Ok(Json(
+            serde_json::json!({ "key": query.key, "digest": digest }),
+        )),
+        Ok(None) => Err((StatusCode::NOT_FOUND, format!("no object {}", query.key))),
         Err(err) => {
-            warn!("[sim] failed to load state for {}: {}", player_uuid, err);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "failed to load player state".to_string(),
-            ));
+            warn!("[hash] failed to digest {}: {}", query.key, err);
+            Err((
+                StatusCode::BAD_GATEWAY,
+                format!("failed to digest {}", query.key),
+            ))
         }
+    }
+}
+
+/// World hash over the requested scope (admin token; reads every stored chunk).
+async fn sim_hash(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Query(query): Query<HashQuery>,
+) -> Result<Json<WorldHash>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let failed = |what: &str, err: StorageError| {
+        warn!("[hash] failed to load {}: {}", what, err);
+        (StatusCode::BAD_GATEWAY, format!("failed to load {what}"))
     };
+    let scope = query.scope;
+    let mut hasher = WorldHasher::default();
+    if matches!(scope, HashScope::All | HashScope::Chunks) {
+        let coords = storage
+            .list_chunks()
+            .await
+            .map_err(|e| failed("chunks", e))?;
+        for (cx, cz) in coords {
+            let chunk = storage
+                .load_chunk(cx, cz)
+                .await
+                .map_err(|e| failed("chunks", e))?;
+            hasher.chunk(&chunk);
+        }
+    }
+    if matches!(scope, HashScope::All | HashScope::Players) {
+        let uuids = storage
+            .list_players()
+            .await
+            .map_err(|e| failed("players", e))?;
+        for uuid in uuids {
+            let state: PlayerState = storage
+                .load_player_state(&uuid)
+                .await
+                .map_err(|e| failed("players", e))?
+                .unwrap_or_default();
+            hasher
+                .player(&uuid, &state)
+                .map_err(|e| failed("players", e))?;
+        }
+    }
+    let gateway = bank_gateway(scope, std::env::var("OMEGA_GATEWAY_URL").ok())?;
+    if matches!(scope, HashScope::All | HashScope::Bank) {
+        if let Some(gateway) = gateway {
+            let roots = gateway_bank_roots(&gateway).await.map_err(|err| {
+                warn!("[hash] gateway bank roots unavailable: {}", err);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    "gateway bank roots unavailable".to_string(),
+                )
+            })?;
+            hasher.bank(roots);
+        }
+    }
+    Ok(Json(hasher.finish(scope)))
+}
 
-    let (next_state, mut response) = sim::advance(current_state, &req);
+/// Gateway to read bank roots from. `all` leaves the bank out without one,
+/// but an explicit `bank` request has nothing to hash and is refused rather
+/// than answered with an empty root.
+fn bank_gateway(
+    scope: HashScope,
+    configured: Option<String>,
+) -> Result<Option<String>, (StatusCode, String)> {
+    let gateway = configured.filter(|url| !url.trim().is_empty());
+    if scope == HashScope::Bank && gateway.is_none() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "bank scope needs OMEGA_GATEWAY_URL".to_string(),
+        ));
+    }
+    Ok(gateway)
+}
 
-    if let Err(err) = persist_block_updates(&storage, &req, next_state.universe_tick, &mut response).await
-    {
-        warn!("[sim] block persistence failed for {}: {}", player_uuid, err);
+/// Planet → `bank_root` from the gateway's `/omega/status`.
+async fn gateway_bank_roots(gateway: &str) -> Result<BTreeMap<String, String>, reqwest::Error> {
+    #[derive(serde::Deserialize)]
+    struct Status {
+        planets: Vec<Planet>,
+    }
+    #[derive(serde::Deserialize)]
+    struct Planet {
+        planet: String,
+        bank_root: String,
+    }
+    let status: Status = reqwest::Client::new()
+        .get(format!("{}/omega/status", gateway.trim_end_matches('/')))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(status
+        .planets
+        .into_iter()
+        .map(|p| (p.planet, p.bank_root))
+        .collect())
+}
+
+/// Debug controls need the admin token and `OMEGA_SIM_DEBUG=1`.
+fn require_debug_controls(
+    headers: &HeaderMap,
+    control: &SimControl,
+) -> Result<(), (StatusCode, String)> {
+    require_admin_token(headers)?;
+    if !control.enabled() {
         return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "failed to persist block updates".to_string(),
+            StatusCode::FORBIDDEN,
+            "debug controls are off (set OMEGA_SIM_DEBUG=1)".to_string(),
         ));
     }
+    Ok(())
+}
+
+#[cfg(feature = "chaos")]
+type FaultPlans = BTreeMap<String, FaultPlan>;
+
+/// Fault plans injected into GCS calls (`chaos` builds only).
+#[cfg(feature = "chaos")]
+async fn chaos_plans(headers: HeaderMap) -> Result<Json<FaultPlans>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    Ok(Json(chaos::plans()))
+}
+
+/// Replace the plans of the named upstreams; an all-zero plan clears one.
+#[cfg(feature = "chaos")]
+async fn chaos_update(
+    headers: HeaderMap,
+    Json(plans): Json<FaultPlans>,
+) -> Result<Json<FaultPlans>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    for (upstream, plan) in plans {
+        warn!("[chaos] {upstream}: {plan:?}");
+        chaos::set(&upstream, plan);
+    }
+    Ok(Json(chaos::plans()))
+}
+
+#[cfg(feature = "chaos")]
+async fn chaos_clear(headers: HeaderMap) -> Result<Json<FaultPlans>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    chaos::clear();
+    info!("[chaos] faults cleared");
+    Ok(Json(chaos::plans()))
+}
+
+async fn control_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ControlStatus>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    Ok(Json(state.control.status()))
+}
+
+async fn control_pause(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ControlStatus>, (StatusCode, String)> {
+    require_debug_controls(&headers, &state.control)?;
+    info!("[sim] paused");
+    Ok(Json(state.control.pause()))
+}
+
+async fn control_resume(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ControlStatus>, (StatusCode, String)> {
+    require_debug_controls(&headers, &state.control)?;
+    info!("[sim] resumed");
+    Ok(Json(state.control.resume()))
+}
 
-    if let Err(err) = storage.save_player_state(&player_uuid, &next_state).await {
-        warn!("[sim] failed to write state for {}: {}", player_uuid, err);
+#[derive(Debug, serde::Deserialize)]
+struct StepRequest {
+    #[serde(default = "one")]
+    ticks: u64,
+}
+
+fn one() -> u64 {
+    1
+}
+
+async fn control_step(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<StepRequest>,
+) -> Result<Json<ControlStatus>, (StatusCode, String)> {
+    require_debug_controls(&headers, &state.control)?;
+    if req.ticks == 0 || req.ticks > control::MAX_STEP {
         return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "failed to persist state".to_string(),
+            StatusCode::BAD_REQUEST,
+            format!("ticks must be 1..={}", control::MAX_STEP),
         ));
     }
+    info!("[sim] stepping {} ticks", req.ticks);
+    Ok(Json(state.control.step(req.ticks)))
+}
+
+/// Debug controls as typed commands; `debug` commands need
+/// `OMEGA_SIM_DEBUG=1`, like their routes.
+const SIM_COMMANDS: Registry = Registry::new(&[
+    HELP,
+    command! {
+        "status" => "Whether the sim is paused",
+    },
+    command! {
+        "pause" => "Freeze the world",
+        perm: "debug",
+    },
+    command! {
+        "resume" => "Let ticks run again",
+        perm: "debug",
+    },
+    command! {
+        "step" => "Let ticks through while paused",
+        perm: "debug",
+        optional: [ticks: Uint],
+    },
+]);
+
+#[derive(Debug, serde::Deserialize)]
+struct CommandRequest {
+    line: String,
+}
+
+async fn sim_command(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CommandRequest>,
+) -> Result<Json<CommandReply>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    Ok(Json(run_command(&state.control, &req.line)))
+}
+
+fn run_command(control: &SimControl, line: &str) -> CommandReply {
+    let granted = |tag: &str| tag == "debug" && control.enabled();
+    let invocation = match SIM_COMMANDS.parse(line, granted) {
+        Ok(invocation) => invocation,
+        Err(err) => return SIM_COMMANDS.reject(&err),
+    };
+    let command = invocation.command;
+    let status = match command {
+        "help" => {
+            return match SIM_COMMANDS.help(invocation.text("command"), granted) {
+                Ok(lines) => CommandReply::done(command, lines),
+                Err(err) => SIM_COMMANDS.reject(&err),
+            }
+        }
+        "status" => control.status(),
+        "pause" => {
+            info!("[sim] paused");
+            control.pause()
+        }
+        "resume" => {
+            info!("[sim] resumed");
+            control.resume()
+        }
+        "step" => {
+            let ticks = invocation.uint("ticks").unwrap_or(1);
+            if ticks == 0 || ticks > control::MAX_STEP {
+                return CommandReply::failed(
+                    command,
+                    format!("ticks must be 1..={}", control::MAX_STEP),
+                );
+            }
+            info!("[sim] stepping {ticks} ticks");
+            control.step(ticks)
+        }
+        _ => unreachable!("registered command /{command} has no handler"),
+    };
+    let state = if status.paused { "paused" } else { "running" };
+    CommandReply::done(
+        command,
+        vec![
+            format!("sim {state}"),
+            format!("{} steps left", status.steps_left),
+        ],
+    )
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SavepointRequest {
+    name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ForkRequest {
+    name: String,
+    /// Savepoint to fork from.
+    from: String,
+}
+
+fn savepoint_failure(err: SavepointError) -> (StatusCode, String) {
+    if let SavepointError::Storage(err) = &err {
+        warn!("[savepoint] {}", err);
+    }
+    let status = StatusCode::from_u16(err.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, err.to_string())
+}
+
+async fn list_savepoints(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Savepoint>>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let savepoints = savepoint::list(&storage).await.map_err(savepoint_failure)?;
+    Ok(Json(savepoints))
+}
+
+async fn create_savepoint(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Json(req): Json<SavepointRequest>,
+) -> Result<Json<Savepoint>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let savepoint = savepoint::create(&storage, &req.name, history::now_ms())
+        .await
+        .map_err(savepoint_failure)?;
+    info!(
+        "[savepoint] {} saved ({} chunks, {} players)",
+        savepoint.name,
+        savepoint.chunks.len(),
+        savepoint.players.len()
+    );
+    Ok(Json(savepoint))
+}
+
+async fn restore_savepoint(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<RestoreReport>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let report = savepoint::restore(&storage, &name)
+        .await
+        .map_err(savepoint_failure)?;
+    warn!(
+        "[savepoint] live world restored to {} ({} chunks, {} emptied)",
+        name, report.chunks, report.emptied
+    );
+    Ok(Json(report))
+}
+
+async fn discard_savepoint(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    savepoint::discard(&storage, &name)
+        .await
+        .map_err(savepoint_failure)?;
+    info!("[savepoint] {} discarded", name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_branches(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Branch>>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let branches = savepoint::branches(&storage)
+        .await
+        .map_err(savepoint_failure)?;
+    Ok(Json(branches))
+}
+
+async fn fork_branch(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Json(req): Json<ForkRequest>,
+) -> Result<Json<Branch>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let branch = savepoint::fork(&storage, &req.name, &req.from, history::now_ms())
+        .await
+        .map_err(savepoint_failure)?;
+    info!(
+        "[savepoint] branch {} forked from {}",
+        branch.name, branch.from
+    );
+    Ok(Json(branch))
+}
+
+async fn discard_branch(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    savepoint::discard_branch(&storage, &name)
+        .await
+        .map_err(savepoint_failure)?;
+    info!("[savepoint] branch {} discarded", name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CompleteImportRequest {
+    /// Parts sent, numbered from 0.
+    parts: u32,
+}
+
+fn import_failure(err: ImportError) -> (StatusCode, String) {
+    if let ImportError::Storage(err) = &err {
+        warn!("[import] {}", err);
+    }
+    let status = StatusCode::from_u16(err.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, err.to_string())
+}
+
+async fn start_import(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+) -> Result<Json<WorldImport>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let import = import::start(&storage, history::now_ms())
+        .await
+        .map_err(import_failure)?;
+    info!("[import] {} started", import.id);
+    Ok(Json(import))
+}
+
+async fn import_status(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<WorldImport>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let import = import::status(&storage, &id)
+        .await
+        .map_err(import_failure)?;
+    Ok(Json(import))
+}
+
+async fn import_part(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path((id, part)): Path<(String, u32)>,
+    body: Bytes,
+) -> Result<Json<PartReceipt>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let receipt = import::put_part(&storage, &id, part, body.to_vec())
+        .await
+        .map_err(import_failure)?;
+    Ok(Json(receipt))
+}
+
+async fn complete_import(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(req): Json<CompleteImportRequest>,
+) -> Result<Json<WorldImport>, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    let import = import::complete(&storage, &id, req.parts)
+        .await
+        .map_err(import_failure)?;
+    warn!(
+        "[import] {} applied to the live world ({} chunks)",
+        import.id, import.chunks
+    );
+    Ok(Json(import))
+}
+
+async fn abort_import(
+    State(storage): State<OmegaStorage>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin_token(&headers)?;
+    import::abort(&storage, &id).await.map_err(import_failure)?;
+    info!("[import] {} aborted", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn sim_tick(
+    State(AppState {
+        storage,
+        control,
+        history,
+        rules,
+        warmer,
+        ..
+    }): State<AppState>,
+    Json(req): Json<TickRequest>,
+) -> Result<Json<TickResponse>, (StatusCode, String)> {
+    let received_ms = history::now_ms();
+    let player_uuid = req.player_uuid.clone();
+    if physics::profile(&req.world).is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("world `{}` is on no known planet", req.world),
+        ));
+    }
+    let storage = match &req.branch {
+        Some(name) => savepoint::branch(&storage, name)
+            .await
+            .map_err(savepoint_failure)?,
+        None => storage,
+    };
+
+    // Frozen ticks touch nothing, including the block updates they carry.
+    let admission = control.admit();
+    if admission == Admission::Frozen {
+        let current_state: PlayerState = match storage.load_player_state(&player_uuid).await {
+            Ok(state) => state.unwrap_or_default(),
+            Err(err) => {
+                warn!("[sim] failed to load state for {}: {}", player_uuid, err);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "failed to load player state".to_string(),
+                ));
+            }
+        };
+        return Ok(Json(sim::frozen(&current_state)));
+    }
+
+    // Blocks land before the player's state, so a tick whose blocks fail
+    // hasn't happened and the client can retry it whole. They carry the
+    // tick the player is on its way to.
+    let mut gate = rules.gate(&req.world, req.lock_tier);
+    let mut blocks = (Vec::new(), Vec::new());
+    if !req.block_updates.is_empty() {
+        let persisted = async {
+            let state: Option<PlayerState> = storage.load_player_state(&player_uuid).await?;
+            let tick = state.unwrap_or_default().universe_tick.wrapping_add(1);
+            persist_block_updates(&storage, &req, tick, &mut gate).await
+        };
+        blocks = persisted.await.map_err(|err| {
+            warn!(
+                "[sim] block persistence failed for {}: {}",
+                player_uuid, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to persist block updates".to_string(),
+            )
+        })?;
+    }
+
+    // A tick that raced another for the same player re-runs on its result.
+    let advanced = storage
+        .update_player_state(&player_uuid, |state: Option<PlayerState>| {
+            sim::advance(state.unwrap_or_default(), &req, &history, received_ms)
+        })
+        .await;
+    let (_, mut response) = match advanced {
+        Ok(advanced) => advanced,
+        Err(StorageError::Conflict(_)) => {
+            warn!(
+                "[sim] state for {} kept changing under the tick",
+                player_uuid
+            );
+            return Err((
+                StatusCode::CONFLICT,
+                "player state is busy, retry the tick".to_string(),
+            ));
+        }
+        Err(err) => {
+            warn!("[sim] failed to advance state for {}: {}", player_uuid, err);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to persist state".to_string(),
+            ));
+        }
+    };
+    response.paused = admission == Admission::Step;
+    (response.chunks, response.rejected) = blocks;
+    if req.branch.is_none() {
+        let chunks = req.block_updates.iter().map(|u| chunk_coords(u.x, u.z));
+        warmer.touch(&player_uuid, chunks, received_ms);
+    }
 
     Ok(Json(response))
 }
 
+/// Apply a tick's block updates chunk by chunk; returns the chunks as saved
+/// and the updates the rules refused.
 async fn persist_block_updates(
     storage: &OmegaStorage,
     req: &TickRequest,
     tick: u64,
-    response: &mut TickResponse,
-) -> anyhow::Result<()> {
-    if req.block_updates.is_empty() {
-        return Ok(());
-    }
+    gate: &mut BlockGate<'_>,
+) -> Result<(Vec<ChunkSnapshot>, Vec<RejectedUpdate>), StorageError> {
+    let (mut chunks, mut rejected) = (Vec::new(), Vec::new());
+    let builder = req.label.as_deref().unwrap_or(&req.player_uuid);
 
-    let mut per_chunk: HashMap<(i64, i64), Vec<BlockUpdate>> = HashMap::new();
+    // Chunks go in coordinate order so the placement cap always cuts the
+    // same updates.
+    let mut per_chunk: BTreeMap<(i64, i64), Vec<BlockUpdate>> = BTreeMap::new();
     for update in &req.block_updates {
         let (cx, cz) = chunk_coords(update.x, update.z);
         per_chunk.entry((cx, cz)).or_default().push(update.clone());
     }
 
     for ((cx, cz), updates) in per_chunk {
-        let mut chunk = storage.load_chunk(cx, cz).await?;
-        let events = apply_updates_to_chunk(&mut chunk, &updates, tick);
-        storage.save_chunk(&chunk).await?;
+        let acl = storage.load_chunk_acl(cx, cz).await?;
+        // A retry after a concurrent write starts from this chunk's gate and
+        // rejections again.
+        let (start, refused) = (gate.clone(), rejected.len());
+        let (chunk, (events, counted)) = storage
+            .update_chunk(cx, cz, |chunk| {
+                let mut counted = start.clone();
+                rejected.truncate(refused);
+                let events = apply_updates_to_chunk(
+                    chunk,
+                    &updates,
+                    tick,
+                    (builder, &acl),
+                    &mut counted,
+                    &mut rejected,
+                );
+                (events, counted)
+            })
+            .await?;
+        *gate = counted;
         storage.append_block_events(cx, cz, &events).await?;
-        response.chunks.push(chunk);
+        chunks.push(chunk);
     }
 
-    Ok(())
+    Ok((chunks, rejected))
 }
 
 fn apply_updates_to_chunk(
     chunk: &mut ChunkSnapshot,
     updates: &[BlockUpdate],
     tick: u64,
+    (builder, acl): (&str, &ChunkAcl),
+    gate: &mut BlockGate<'_>,
+    rejected: &mut Vec<RejectedUpdate>,
 ) -> Vec<BlockEvent> {
     let mut events = Vec::new();
     if updates.is_empty() {
@@ -126,6 +1109,18 @@ fn apply_updates_to_chunk(
 
     let mut touched = false;
     for update in updates {
+        // Who may build here comes before what may be built.
+        if let Err(rejection) = acl.check(builder, update).and_then(|()| gate.check(update)) {
+            rejected.push(RejectedUpdate {
+                x: update.x,
+                y: update.y,
+                z: update.z,
+                block: update.block.clone(),
+                action: update.action,
+                rejection,
+            });
+            continue;
+        }
         touched = true;
         match update.action {
             BlockAction::Place => {
@@ -143,6 +1138,7 @@ fn apply_updates_to_chunk(
             z: update.z,
             block: update.block.clone(),
             action: update.action,
+            builder: Some(builder.to_string()),
         });
     }
 
@@ -154,31 +1150,89 @@ fn apply_updates_to_chunk(
 }
 
 fn upsert_block(chunk: &mut ChunkSnapshot, update: &BlockUpdate, tick: u64) {
-    if let Some(existing) = chunk
-        .blocks
-        .iter_mut()
-        .find(|b| b.x == update.x && b.y == update.y && b.z == update.z)
-    {
-        existing.block = update.block.clone();
-        existing.last_tick = tick;
-        return;
-    }
-
-    chunk.blocks.push(BlockState {
-        x: update.x,
-        y: update.y,
-        z: update.z,
-        block: update.block.clone(),
-        last_tick: tick,
-    });
+    chunk.set_block(update.x, update.y, update.z, &update.block, tick);
 }
 
 fn remove_block(chunk: &mut ChunkSnapshot, update: &BlockUpdate) {
-    chunk
-        .blocks
-        .retain(|b| !(b.x == update.x && b.y == update.y && b.z == update.z));
+    chunk.remove_block(update.x, update.y, update.z);
 }
 
 fn chunk_coords(x: i64, z: i64) -> (i64, i64) {
     (x.div_euclid(16), z.div_euclid(16))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::http::header;
+    use tower::ServiceExt;
+
+    #[test]
+    fn bank_scope_without_a_gateway_is_refused() {
+        let err = bank_gateway(HashScope::Bank, None).unwrap_err();
+        assert_eq!(err.0, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(bank_gateway(HashScope::Bank, Some(" ".into())).is_err());
+        assert_eq!(bank_gateway(HashScope::All, None).unwrap(), None);
+        assert_eq!(
+            bank_gateway(HashScope::Bank, Some("http://gw".into())).unwrap(),
+            Some("http://gw".to_string())
+        );
+    }
+
+    #[test]
+    fn debug_commands_need_debug_mode() {
+        let off = SimControl::new(false);
+        let reply = run_command(&off, "/pause");
+        assert!(!reply.ok);
+        assert_eq!(reply.ui.title, "/pause needs the debug permission");
+        assert_eq!(run_command(&off, "/status").ui.title, "sim running");
+
+        let on = SimControl::new(true);
+        assert_eq!(run_command(&on, "pause").ui.title, "sim paused");
+        let step = run_command(&on, "/step 3");
+        assert_eq!(step.ui.hotbar, ["3 steps left"]);
+        assert!(!run_command(&on, "/step 0").ok);
+        assert_eq!(run_command(&on, "/step x").ui.hotbar, ["/step [ticks]"]);
+    }
+
+    #[tokio::test]
+    async fn chunk_reads_revalidate_until_the_version_moves() {
+        let version = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(3));
+        let current = version.clone();
+        let app: Router = Router::new().route(
+            "/chunks/:cx/:cz",
+            chunk_route(move |Path((cx, cz)): Path<(i64, i64)>| async move {
+                let mut chunk = ChunkSnapshot::new(cx, cz);
+                chunk.version = current.load(std::sync::atomic::Ordering::Relaxed);
+                tagged_chunk(chunk)
+            }),
+        );
+        let get = |tag: Option<&header::HeaderValue>| {
+            let mut req = Request::get("/chunks/1/-2").body(Body::empty()).unwrap();
+            if let Some(tag) = tag {
+                req.headers_mut().insert(header::IF_NONE_MATCH, tag.clone());
+            }
+            req
+        };
+
+        let first = app.clone().oneshot(get(None)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(
+            first.headers()[header::CACHE_CONTROL],
+            "public, max-age=8, stale-while-revalidate=60"
+        );
+        let tag = first.headers()[header::ETAG].clone();
+        assert_eq!(tag, "\"chunk;1;-2;v3\"");
+
+        let again = app.clone().oneshot(get(Some(&tag))).await.unwrap();
+        assert_eq!(again.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(again.headers()[header::ETAG], tag);
+
+        version.store(4, std::sync::atomic::Ordering::Relaxed);
+        let moved = app.oneshot(get(Some(&tag))).await.unwrap();
+        assert_eq!(moved.status(), StatusCode::OK);
+        assert_eq!(moved.headers()[header::ETAG], "\"chunk;1;-2;v4\"");
+    }
+}
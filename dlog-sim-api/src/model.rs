@@ -1,4 +1,9 @@
-use serde::{Deserialize, Serialize};
+use crate::history::Sample;
+use crate::palette::{BlockRef, ChunkBlocks, SECTION};
+use crate::physics::PhysicsReport;
+use crate::rules::{LockTier, Rejection};
+use crate::stamina::StaminaReport;
+use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Debug, Deserialize)]
 pub struct TickRequest {
@@ -9,14 +14,75 @@ pub struct TickRequest {
     pub inputs: Vec<InputEvent>,
     #[serde(default)]
     pub block_updates: Vec<BlockUpdate>,
+    /// World the player is in, as the Paper bridge names it.
+    #[serde(default = "default_world")]
+    pub world: String,
+    /// Tier of the lock on the land the player builds on, if any.
+    #[serde(default)]
+    pub lock_tier: Option<LockTier>,
+    /// Client clock when the tick was sent; with `clock_offset_ms` (from a
+    /// clock sync) it places the tick on the server clock.
+    #[serde(default)]
+    pub client_time_ms: Option<u64>,
+    #[serde(default)]
+    pub clock_offset_ms: Option<i64>,
+    /// Branch to run the tick on instead of the live world.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Label the player builds as; block statistics credit it instead of
+    /// the player uuid.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+fn default_world() -> String {
+    "earth".to_string()
 }
 
 #[derive(Debug, Serialize)]
 pub struct TickResponse {
     pub universe_tick: u64,
+    /// The world is paused for debugging (set even on a single-stepped tick).
+    pub paused: bool,
     pub render: Vec<RenderCommand>,
     #[serde(default)]
     pub chunks: Vec<ChunkSnapshot>,
+    /// Interact targets, resolved where they were when the client acted.
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+    /// Block updates the world's rules refused, with the reason.
+    #[serde(default)]
+    pub rejected: Vec<RejectedUpdate>,
+    /// The player's stamina after the tick, and the sprint and jump it
+    /// allows next; absent on frozen ticks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stamina: Option<StaminaReport>,
+    /// The player's body after the tick, where the position was accepted;
+    /// absent on frozen ticks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physics: Option<PhysicsReport>,
+}
+
+/// A block update the rules turned away; nothing of it was applied.
+#[derive(Debug, Serialize)]
+pub struct RejectedUpdate {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+    pub block: String,
+    pub action: BlockAction,
+    #[serde(flatten)]
+    pub rejection: Rejection,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Interaction {
+    pub target_id: String,
+    /// Target position at the compensated time; `None` if the sim has never
+    /// seen the target.
+    pub target: Option<Sample>,
+    /// How far before the tick's arrival the target was looked up.
+    pub rewound_ms: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,9 +97,15 @@ pub struct Position {
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum InputEvent {
-    Move { dx: f64, dy: f64, dz: f64 },
+    /// Only the x/z direction steers the body; a `dy` sent along is ignored.
+    Move {
+        dx: f64,
+        dz: f64,
+    },
     Jump,
-    Interact { target_id: Option<String> },
+    Interact {
+        target_id: Option<String>,
+    },
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -46,19 +118,14 @@ pub struct BlockUpdate {
     pub action: BlockAction,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum BlockAction {
+    #[default]
     Place,
     Break,
 }
 
-impl Default for BlockAction {
-    fn default() -> Self {
-        BlockAction::Place
-    }
-}
-
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
 pub enum RenderCommand {
@@ -85,16 +152,127 @@ pub enum RenderCommand {
     Title {
         text: String,
     },
+    /// Line over the hotbar; carries the stamina bar.
+    ActionBar {
+        text: String,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// One chunk's blocks, kept packed (see [`ChunkBlocks`]) and stored and
+/// served as the `{cx, cz, version, blocks: [BlockState]}` it always was.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "StoredChunk")]
 pub struct ChunkSnapshot {
     pub cx: i64,
     pub cz: i64,
-    #[serde(default)]
     pub version: u64,
+    blocks: ChunkBlocks,
+}
+
+#[derive(Deserialize)]
+struct StoredChunk {
+    cx: i64,
+    cz: i64,
     #[serde(default)]
-    pub blocks: Vec<BlockState>,
+    version: u64,
+    #[serde(default)]
+    blocks: Vec<BlockState>,
+}
+
+#[derive(Serialize)]
+struct StoredChunkRef<'a> {
+    cx: i64,
+    cz: i64,
+    version: u64,
+    blocks: Vec<BlockRef<'a>>,
+}
+
+impl From<StoredChunk> for ChunkSnapshot {
+    fn from(stored: StoredChunk) -> Self {
+        let mut chunk = Self::new(stored.cx, stored.cz);
+        chunk.version = stored.version;
+        for block in &stored.blocks {
+            chunk.set_block(block.x, block.y, block.z, &block.block, block.last_tick);
+        }
+        chunk
+    }
+}
+
+impl Serialize for ChunkSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StoredChunkRef {
+            cx: self.cx,
+            cz: self.cz,
+            version: self.version,
+            blocks: self.blocks().collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl ChunkSnapshot {
+    pub fn new(cx: i64, cz: i64) -> Self {
+        Self {
+            cx,
+            cz,
+            ..Self::default()
+        }
+    }
+
+    /// Local column of a world `x`/`z`, which must be inside this chunk.
+    fn local(&self, x: i64, z: i64) -> (i64, i64) {
+        debug_assert_eq!(
+            (x.div_euclid(SECTION), z.div_euclid(SECTION)),
+            (self.cx, self.cz)
+        );
+        (x.rem_euclid(SECTION), z.rem_euclid(SECTION))
+    }
+
+    /// The block at a world position, if any.
+    #[allow(dead_code)]
+    pub fn block(&self, x: i64, y: i64, z: i64) -> Option<BlockRef<'_>> {
+        let (lx, lz) = self.local(x, z);
+        let (block, last_tick) = self.blocks.get(lx, y, lz)?;
+        Some(BlockRef {
+            x,
+            y,
+            z,
+            block,
+            last_tick,
+        })
+    }
+
+    pub fn set_block(&mut self, x: i64, y: i64, z: i64, block: &str, tick: u64) {
+        let (lx, lz) = self.local(x, z);
+        self.blocks.set(lx, y, lz, block, tick);
+    }
+
+    pub fn remove_block(&mut self, x: i64, y: i64, z: i64) -> bool {
+        let (lx, lz) = self.local(x, z);
+        self.blocks.remove(lx, y, lz)
+    }
+
+    /// Every block, by world coordinates, bottom section first.
+    pub fn blocks(&self) -> impl Iterator<Item = BlockRef<'_>> + '_ {
+        let (ox, oz) = (self.cx * SECTION, self.cz * SECTION);
+        self.blocks
+            .iter()
+            .map(move |((x, y, z), block, last_tick)| BlockRef {
+                x: ox + x,
+                y,
+                z: oz + z,
+                block,
+                last_tick,
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn clear_blocks(&mut self) {
+        self.blocks.clear();
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -120,4 +298,7 @@ pub struct BlockEvent {
     pub z: i64,
     pub block: String,
     pub action: BlockAction,
+    /// Who made the change: the tick's label, else its player.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builder: Option<String>,
 }
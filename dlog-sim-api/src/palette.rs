@@ -0,0 +1,224 @@
+//! Packed block storage for one chunk.
+//!
+//! The chunk's 16×16 columns are cut into 16-high sections, allocated as
+//! blocks land in them. Each cell holds an index into the chunk's palette
+//! of block ids (0 for no block) and the tick it last changed, so reading
+//! or setting a block is a little arithmetic instead of a scan. The palette
+//! only grows while the chunk is in memory; it is rebuilt from the blocks
+//! whenever the chunk is loaded.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Width, depth and height of a section.
+pub const SECTION: i64 = 16;
+const CELLS: usize = (SECTION * SECTION * SECTION) as usize;
+
+/// One block, borrowed from a chunk; serializes like a `BlockState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BlockRef<'a> {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+    pub block: &'a str,
+    pub last_tick: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    /// Palette index plus one per cell, 0 where there is no block.
+    cells: Box<[u32]>,
+    ticks: Box<[u64]>,
+    filled: usize,
+}
+
+impl Section {
+    fn new() -> Self {
+        Self {
+            cells: vec![0; CELLS].into_boxed_slice(),
+            ticks: vec![0; CELLS].into_boxed_slice(),
+            filled: 0,
+        }
+    }
+}
+
+/// Blocks of one chunk by local `x` and `z` (`0..16`) and world `y`.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkBlocks {
+    palette: Vec<String>,
+    lookup: HashMap<String, u32>,
+    /// Section (`y / 16`, rounded down) of `sections[0]`.
+    base: i64,
+    sections: Vec<Option<Section>>,
+    len: usize,
+}
+
+fn cell(x: i64, y: i64, z: i64) -> usize {
+    debug_assert!((0..SECTION).contains(&x) && (0..SECTION).contains(&z));
+    ((y.rem_euclid(SECTION) * SECTION + z) * SECTION + x) as usize
+}
+
+impl ChunkBlocks {
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn section(&self, y: i64) -> Option<&Section> {
+        let slot = usize::try_from(y.div_euclid(SECTION) - self.base).ok()?;
+        self.sections.get(slot)?.as_ref()
+    }
+
+    /// The block at a cell and the tick it was set.
+    pub fn get(&self, x: i64, y: i64, z: i64) -> Option<(&str, u64)> {
+        let section = self.section(y)?;
+        let at = cell(x, y, z);
+        let index = section.cells[at].checked_sub(1)?;
+        Some((&self.palette[index as usize], section.ticks[at]))
+    }
+
+    /// Put `block` at a cell, replacing what was there.
+    pub fn set(&mut self, x: i64, y: i64, z: i64, block: &str, tick: u64) {
+        let index = match self.lookup.get(block) {
+            Some(&index) => index,
+            None => {
+                self.palette.push(block.to_string());
+                let index = self.palette.len() as u32;
+                self.lookup.insert(block.to_string(), index);
+                index
+            }
+        };
+        let y_section = y.div_euclid(SECTION);
+        if self.sections.is_empty() {
+            self.base = y_section;
+        }
+        if y_section < self.base {
+            let mut sections = Vec::new();
+            sections.resize_with((self.base - y_section) as usize, || None);
+            sections.append(&mut self.sections);
+            self.sections = sections;
+            self.base = y_section;
+        }
+        let slot = (y_section - self.base) as usize;
+        if slot >= self.sections.len() {
+            self.sections.resize_with(slot + 1, || None);
+        }
+        let section = self.sections[slot].get_or_insert_with(Section::new);
+        let at = cell(x, y, z);
+        if section.cells[at] == 0 {
+            section.filled += 1;
+            self.len += 1;
+        }
+        section.cells[at] = index;
+        section.ticks[at] = tick;
+    }
+
+    /// Take the block out of a cell; false if it was empty.
+    pub fn remove(&mut self, x: i64, y: i64, z: i64) -> bool {
+        let Ok(slot) = usize::try_from(y.div_euclid(SECTION) - self.base) else {
+            return false;
+        };
+        let Some(Some(section)) = self.sections.get_mut(slot) else {
+            return false;
+        };
+        let at = cell(x, y, z);
+        if section.cells[at] == 0 {
+            return false;
+        }
+        section.cells[at] = 0;
+        section.ticks[at] = 0;
+        section.filled -= 1;
+        self.len -= 1;
+        if section.filled == 0 {
+            self.sections[slot] = None;
+        }
+        true
+    }
+
+    /// Every block as local `(x, y, z)`, bottom section first.
+    pub fn iter(&self) -> impl Iterator<Item = ((i64, i64, i64), &str, u64)> + '_ {
+        self.sections
+            .iter()
+            .zip(self.base..)
+            .filter_map(|(section, y_section)| Some((section.as_ref()?, y_section)))
+            .flat_map(move |(section, y_section)| {
+                section
+                    .cells
+                    .iter()
+                    .zip(section.ticks.iter())
+                    .enumerate()
+                    .filter(|(_, (index, _))| **index != 0)
+                    .map(move |(at, (&index, &tick))| {
+                        let at = at as i64;
+                        let x = at % SECTION;
+                        let z = at / SECTION % SECTION;
+                        let y = y_section * SECTION + at / (SECTION * SECTION);
+                        ((x, y, z), self.palette[index as usize - 1].as_str(), tick)
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ChunkSnapshot;
+    use serde_json::json;
+
+    #[test]
+    fn sets_reads_and_removes_cells_across_sections() {
+        let mut blocks = ChunkBlocks::default();
+        blocks.set(3, 64, 15, "minecraft:stone", 1);
+        blocks.set(0, -1, 0, "minecraft:dirt", 2);
+        blocks.set(15, 200, 7, "minecraft:stone", 3);
+        blocks.set(3, 64, 15, "minecraft:glass", 4);
+        assert_eq!(blocks.len, 3);
+        assert_eq!(blocks.get(3, 64, 15), Some(("minecraft:glass", 4)));
+        assert_eq!(blocks.get(0, -1, 0), Some(("minecraft:dirt", 2)));
+        assert_eq!(blocks.get(0, -2, 0), None);
+        assert_eq!(blocks.get(0, -100, 0), None);
+        assert_eq!(blocks.palette.len(), 3);
+
+        let all: Vec<_> = blocks.iter().collect();
+        assert_eq!(
+            all,
+            [
+                ((0, -1, 0), "minecraft:dirt", 2),
+                ((3, 64, 15), "minecraft:glass", 4),
+                ((15, 200, 7), "minecraft:stone", 3),
+            ]
+        );
+
+        assert!(blocks.remove(0, -1, 0));
+        assert!(!blocks.remove(0, -1, 0));
+        assert!(!blocks.remove(0, 1000, 0));
+        assert_eq!(blocks.len, 2);
+        assert!(blocks.sections[0].is_none());
+        blocks.clear();
+        assert!(blocks.is_empty() && blocks.iter().next().is_none());
+    }
+
+    #[test]
+    fn chunks_keep_their_stored_json() {
+        let stored = json!({
+            "cx": -1,
+            "cz": 2,
+            "version": 5,
+            "blocks": [
+                { "x": -1, "y": 3, "z": 32, "block": "minecraft:dirt", "last_tick": 4 },
+                { "x": -16, "y": 64, "z": 47, "block": "minecraft:stone", "last_tick": 9 },
+            ],
+        });
+        let chunk: ChunkSnapshot = serde_json::from_value(stored.clone()).unwrap();
+        assert_eq!(chunk.block(-1, 3, 32).unwrap().block, "minecraft:dirt");
+        assert!(chunk.block(-2, 3, 32).is_none());
+        // Blocks come back bottom section first rather than as placed.
+        assert_eq!(serde_json::to_value(&chunk).unwrap(), stored);
+
+        let old: ChunkSnapshot = serde_json::from_value(json!({ "cx": 0, "cz": 0 })).unwrap();
+        assert!(old.is_empty());
+    }
+}
@@ -0,0 +1,322 @@
+//! Server-side movement.
+//!
+//! Each player has a body stepped through the [`cannon`] integrator, one
+//! step per millisecond the tick spans. `Move` inputs only say which way
+//! the player wants to go (their sum, flattened onto the ground), `Jump`
+//! launches from the ground, and thrust, gravity and the top speed come
+//! from the planet profile of the tick's world, which must name a known
+//! planet. Stamina narrows the top
+//! speed past a walk and weakens jumps, as it scaled strides before.
+//!
+//! The sim knows no terrain, so the client still says where the player
+//! ended up, but only within reach: no further from where the tick started
+//! than the simulated body got, plus [`SLACK`] per tick for step-ups and
+//! rounding. A position past that is pulled back along the same line and
+//! the tick answers `corrected`. The body carries on from the accepted
+//! position, never faster than the client actually moved, and the client's
+//! height decides ground contact: dropping below the body walks off an
+//! edge, and staying above a falling body lands it. Only a player's first
+//! tick is placed where the client says; moving to another world is held
+//! to the same reach, from rest.
+
+use corelib::migrate::{Migration, Schema};
+use serde::{Deserialize, Serialize};
+
+use crate::cannon::{self, Body, Input, PlanetProfile, Vec3};
+use crate::components::Component;
+use crate::stamina::{MovementScales, TICK_MS};
+
+/// Blocks a client may be past the simulated body, per tick.
+pub const SLACK: f64 = 0.6;
+/// Height difference that counts as leaving or reaching the ground.
+const GROUND_EPS: f64 = 1e-3;
+
+/// The physics subsystem's body: the Ω position last accepted for the
+/// player and how it is moving.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Physics {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Blocks per second.
+    #[serde(default)]
+    pub velocity: [f64; 3],
+    #[serde(default)]
+    pub airborne: bool,
+    /// World the body is in; `None` until a tick places it.
+    #[serde(default)]
+    pub world: Option<String>,
+    /// Server time of the last tick, which the next one's span runs from.
+    #[serde(default)]
+    pub stepped_ms: Option<u64>,
+}
+
+impl Component for Physics {
+    const NAME: &'static str = "physics";
+    const SCHEMA: Schema = Schema {
+        kind: "physics",
+        migrations: &[
+            Migration {
+                to: 1,
+                note: "Ω position split out of the player",
+                apply: |_| Ok(()),
+            },
+            Migration {
+                to: 2,
+                note: "velocity and ground contact; placed afresh on the next tick",
+                apply: |_| Ok(()),
+            },
+        ],
+    };
+}
+
+/// What a tick's inputs ask of the body.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Intent {
+    /// Summed `Move` deltas along x and z.
+    pub wish: [f64; 2],
+    pub jump: bool,
+}
+
+/// The body after a tick, for the bridge.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PhysicsReport {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Blocks per second.
+    pub velocity: [f64; 3],
+    pub on_ground: bool,
+    /// The client's position was out of reach and was pulled back here;
+    /// the bridge should move the player to match.
+    pub corrected: bool,
+}
+
+/// Planet profile of a world: `moon_shell` is on the Moon, `earth` and
+/// `earth_nether` on Earth, and so on. `None` for a world of no known
+/// planet, which the sim doesn't step.
+pub fn profile(world: &str) -> Option<PlanetProfile> {
+    match world.split(['_', '-', ';']).next().unwrap_or_default() {
+        "earth" => Some(cannon::planet_earth()),
+        "moon" => Some(cannon::planet_moon()),
+        "mars" => Some(cannon::planet_mars()),
+        "sun" => Some(cannon::planet_sun()),
+        _ => None,
+    }
+}
+
+fn vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3 { x, y, z }
+}
+
+fn offset(from: Vec3, to: Vec3) -> Vec3 {
+    to.add(from.scale(-1.0))
+}
+
+impl Physics {
+    fn position(&self) -> Vec3 {
+        vec3([self.x, self.y, self.z])
+    }
+
+    /// Run `span` ticks (see `stamina::span`) of `intent` in `world` and
+    /// accept what the client `reported` as far as the result allows. The
+    /// first tick puts the body where the client is, at rest; the first in
+    /// another world starts from rest where the body was. A world of no
+    /// known planet leaves the body where it is.
+    pub fn step(
+        &mut self,
+        world: &str,
+        intent: Intent,
+        span: f64,
+        scales: MovementScales,
+        reported: [f64; 3],
+    ) -> PhysicsReport {
+        let Some(mut profile) = profile(world) else {
+            return self.report(true);
+        };
+        match self.world.as_deref() {
+            None => {
+                *self = Physics {
+                    x: reported[0],
+                    y: reported[1],
+                    z: reported[2],
+                    world: Some(world.to_string()),
+                    stepped_ms: self.stepped_ms,
+                    ..Physics::default()
+                };
+                return self.report(false);
+            }
+            Some(current) if current != world => {
+                self.world = Some(world.to_string());
+                self.velocity = [0.0; 3];
+                self.airborne = false;
+            }
+            Some(_) => {}
+        }
+
+        let tick_s = TICK_MS / 1000.0;
+        let (top, _) = scales.stride(profile.max_speed * tick_s, 0.0);
+        profile.max_speed = top / tick_s;
+
+        let start = self.position();
+        let mut body = Body {
+            pos: start,
+            vel: vec3(self.velocity),
+            on_ground: !self.airborne,
+        };
+        let wish_dir = vec3([intent.wish[0], 0.0, intent.wish[1]]);
+        let steps = (span * tick_s * cannon::TICK_HZ).round().max(1.0) as u32;
+        let mut jump = intent.jump;
+        for _ in 0..steps {
+            let out = cannon::step(body, Input { wish_dir, jump }, profile);
+            (body.pos, body.vel) = (out.pos, out.vel);
+            if jump && body.on_ground {
+                body.vel.y *= scales.jump;
+                body.on_ground = false;
+            }
+            jump = false;
+        }
+
+        let reach = offset(start, body.pos).length() + SLACK * span.max(1.0);
+        let moved = offset(start, vec3(reported));
+        let corrected = moved.length() > reach;
+        let accepted = if corrected {
+            start.add(moved.normalize().scale(reach))
+        } else {
+            vec3(reported)
+        };
+
+        // Never faster than the client went.
+        let seconds = steps as f64 * cannon::DT;
+        let walked = (accepted.x - start.x).hypot(accepted.z - start.z) / seconds;
+        let speed = body.vel.x.hypot(body.vel.z);
+        if walked < speed {
+            body.vel.x *= walked / speed;
+            body.vel.z *= walked / speed;
+        }
+        if body.on_ground && accepted.y < body.pos.y - GROUND_EPS {
+            body.on_ground = false;
+        } else if !body.on_ground && body.vel.y <= 0.0 && accepted.y > body.pos.y + GROUND_EPS {
+            body.on_ground = true;
+            body.vel.y = 0.0;
+        }
+
+        (self.x, self.y, self.z) = (accepted.x, accepted.y, accepted.z);
+        self.velocity = [body.vel.x, body.vel.y, body.vel.z];
+        self.airborne = !body.on_ground;
+        self.report(corrected)
+    }
+
+    /// Velocity in blocks per tick, as stamina counts it; falling is free.
+    pub fn effort(&self) -> [f64; 3] {
+        let tick_s = TICK_MS / 1000.0;
+        let [vx, vy, vz] = self.velocity;
+        [vx * tick_s, vy.max(0.0) * tick_s, vz * tick_s]
+    }
+
+    fn report(&self, corrected: bool) -> PhysicsReport {
+        PhysicsReport {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            velocity: self.velocity,
+            on_ground: !self.airborne,
+            corrected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stamina::Stamina;
+
+    const FULL: MovementScales = MovementScales {
+        sprint: 1.0,
+        jump: 1.0,
+    };
+
+    #[test]
+    fn bodies_move_by_physics_and_clamp_teleports() {
+        let mut body = Physics::default();
+        let placed = body.step("earth", Intent::default(), 1.0, FULL, [10.0, 64.0, 10.0]);
+        assert_eq!((placed.x, placed.y, placed.on_ground), (10.0, 64.0, true));
+
+        // Walking a stride is within reach and builds speed slowly.
+        let forward = Intent {
+            wish: [0.2, 0.0],
+            jump: false,
+        };
+        let walk = body.step("earth", forward, 1.0, FULL, [10.2, 64.0, 10.0]);
+        assert!(!walk.corrected && walk.x == 10.2);
+        assert!(walk.velocity[0] > 0.0 && walk.velocity[0] < 1.0);
+
+        // A teleport is pulled back to the body's reach.
+        let teleport = body.step("earth", forward, 1.0, FULL, [110.2, 64.0, 10.0]);
+        assert!(teleport.corrected);
+        assert!(teleport.x > 10.2 && teleport.x < 10.2 + 2.0 * SLACK);
+        assert_eq!((teleport.y, teleport.z), (64.0, 10.0));
+
+        // A jump leaves the ground; the client standing still lands it.
+        let jumped = body.step(
+            "earth",
+            Intent {
+                jump: true,
+                ..Intent::default()
+            },
+            1.0,
+            FULL,
+            [teleport.x, 64.2, 10.0],
+        );
+        assert!(!jumped.on_ground && jumped.velocity[1] > 0.0);
+        assert!(body.effort()[1] > 0.0);
+        let mut landed = jumped;
+        for _ in 0..40 {
+            landed = body.step(
+                "earth",
+                Intent::default(),
+                1.0,
+                FULL,
+                [teleport.x, 64.0, 10.0],
+            );
+            if landed.on_ground {
+                break;
+            }
+        }
+        assert!(landed.on_ground && landed.velocity[1] == 0.0);
+
+        // Another world starts from rest, still within reach; an unknown
+        // one doesn't move the body at all.
+        let moved = body.step("earth_x", Intent::default(), 1.0, FULL, [0.0, 80.0, 0.0]);
+        assert!(moved.corrected && moved.velocity == [0.0; 3]);
+        assert!((moved.x - teleport.x).abs() <= 2.0 * SLACK && moved.y < 65.0);
+        let lost = body.step("lobby", Intent::default(), 1.0, FULL, [0.0, 80.0, 0.0]);
+        assert_eq!((lost.x, lost.y, lost.z), (moved.x, moved.y, moved.z));
+        assert_eq!(body.world.as_deref(), Some("earth_x"));
+    }
+
+    #[test]
+    fn planets_and_stamina_shape_jumps() {
+        let jump = |world: &str, scales: MovementScales| {
+            let mut body = Physics::default();
+            body.step(world, Intent::default(), 1.0, scales, [0.0; 3]);
+            let intent = Intent {
+                jump: true,
+                ..Intent::default()
+            };
+            body.step(world, intent, 1.0, scales, [0.0; 3]).velocity[1]
+        };
+        assert!(jump("moon_shell", FULL) < jump("earth", FULL));
+        assert!(jump("sun", FULL) > jump("earth", FULL));
+        let spent = Stamina {
+            level: 0.0,
+            ..Stamina::default()
+        };
+        assert!(jump("earth", spent.scales()) < jump("earth", FULL));
+        assert_eq!(
+            profile("mars_base").map(|p| p.gravity),
+            Some(cannon::planet_mars().gravity)
+        );
+        assert!(profile("lobby").is_none() && profile("earthly").is_none());
+    }
+}
@@ -0,0 +1,211 @@
+//! Regional buckets: which one to use first and how far behind the rest are.
+//!
+//! `OMEGA_BUCKETS=us-east1=omega-us,europe-west1=omega-eu` lists one bucket
+//! per region; `OMEGA_REGION` says where this instance runs. Buckets are
+//! tried nearest first: the same region, then the one sharing the longest
+//! name prefix (`europe-west4` is nearer `europe-west1` than `us-east1`),
+//! then the listed order. Without `OMEGA_BUCKETS` the single `OMEGA_BUCKET`
+//! is used as before.
+//!
+//! Every write lands in one bucket and is queued for the others; a worker
+//! per region copies it over in write order. [`ReplicationLag`] counts what
+//! each region has pending, copied, failed, conflicting (a conditional
+//! write whose base the region no longer has) and dropped (queue full), and
+//! how long the copies took from write to landing.
+
+use dlog_error::StorageError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Region name used when only `OMEGA_BUCKET` is set.
+pub const DEFAULT_REGION: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionBucketConfig {
+    pub region: String,
+    pub bucket: String,
+}
+
+/// Buckets from the environment, nearest to `OMEGA_REGION` first.
+pub fn from_env() -> Result<Vec<RegionBucketConfig>, StorageError> {
+    let here = std::env::var("OMEGA_REGION").unwrap_or_default();
+    let buckets = match std::env::var("OMEGA_BUCKETS") {
+        Ok(raw) if !raw.trim().is_empty() => parse(&raw)?,
+        _ => {
+            let bucket = std::env::var("OMEGA_BUCKET").map_err(|_| {
+                StorageError::Config("OMEGA_BUCKET or OMEGA_BUCKETS must be set".into())
+            })?;
+            vec![RegionBucketConfig {
+                region: DEFAULT_REGION.into(),
+                bucket,
+            }]
+        }
+    };
+    Ok(nearest_first(buckets, here.trim()))
+}
+
+/// Comma-separated `region=bucket` pairs.
+pub fn parse(raw: &str) -> Result<Vec<RegionBucketConfig>, StorageError> {
+    let mut buckets: Vec<RegionBucketConfig> = Vec::new();
+    for pair in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (region, bucket) = pair
+            .split_once('=')
+            .map(|(r, b)| (r.trim(), b.trim()))
+            .filter(|(r, b)| !r.is_empty() && !b.is_empty())
+            .ok_or_else(|| {
+                StorageError::Config(format!("OMEGA_BUCKETS entry `{pair}` is not region=bucket"))
+            })?;
+        if buckets.iter().any(|b| b.region == region) {
+            return Err(StorageError::Config(format!(
+                "OMEGA_BUCKETS names region {region} twice"
+            )));
+        }
+        buckets.push(RegionBucketConfig {
+            region: region.to_string(),
+            bucket: bucket.to_string(),
+        });
+    }
+    if buckets.is_empty() {
+        return Err(StorageError::Config(
+            "OMEGA_BUCKETS lists no buckets".into(),
+        ));
+    }
+    Ok(buckets)
+}
+
+/// Order `buckets` by how much of their region name they share with `here`;
+/// ties keep the listed order.
+pub fn nearest_first(mut buckets: Vec<RegionBucketConfig>, here: &str) -> Vec<RegionBucketConfig> {
+    let shared = |region: &str| {
+        if region == here {
+            usize::MAX
+        } else {
+            region
+                .chars()
+                .zip(here.chars())
+                .take_while(|(a, b)| a == b)
+                .count()
+        }
+    };
+    buckets.sort_by_key(|b| std::cmp::Reverse(shared(&b.region)));
+    buckets
+}
+
+/// Copies into one region.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RegionLag {
+    /// Queued and not yet landed.
+    pub pending: u64,
+    pub replicated: u64,
+    pub failed: u64,
+    /// Conditional writes not copied because the region's copy had changed
+    /// since the one they replaced.
+    pub conflicts: u64,
+    /// Not queued because the region's queue was full.
+    pub dropped: u64,
+    /// Write-to-landing time of the latest copy, and the worst seen.
+    pub last_lag_ms: Option<u64>,
+    pub max_lag_ms: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ReplicationLag {
+    regions: Mutex<BTreeMap<String, RegionLag>>,
+}
+
+impl ReplicationLag {
+    fn update(&self, region: &str, f: impl FnOnce(&mut RegionLag)) {
+        let mut regions = self.regions.lock().expect("replication mutex poisoned");
+        f(regions.entry(region.to_string()).or_default());
+    }
+
+    pub fn queued(&self, region: &str) {
+        self.update(region, |lag| lag.pending += 1);
+    }
+
+    pub fn dropped(&self, region: &str) {
+        self.update(region, |lag| lag.dropped += 1);
+    }
+
+    /// A copy landed `lag` after its write.
+    pub fn replicated(&self, region: &str, lag: Duration) {
+        let ms = lag.as_millis() as u64;
+        self.update(region, |entry| {
+            entry.pending = entry.pending.saturating_sub(1);
+            entry.replicated += 1;
+            entry.last_lag_ms = Some(ms);
+            entry.max_lag_ms = entry.max_lag_ms.max(ms);
+        });
+    }
+
+    pub fn failed(&self, region: &str) {
+        self.update(region, |lag| {
+            lag.pending = lag.pending.saturating_sub(1);
+            lag.failed += 1;
+        });
+    }
+
+    pub fn conflicted(&self, region: &str) {
+        self.update(region, |lag| {
+            lag.pending = lag.pending.saturating_sub(1);
+            lag.conflicts += 1;
+        });
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, RegionLag> {
+        self.regions
+            .lock()
+            .expect("replication mutex poisoned")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_parse_order_nearest_first_and_count_lag() {
+        let buckets =
+            parse("us-east1=omega-us, europe-west1=omega-eu,asia-east1=omega-asia").unwrap();
+        let order = |here: &str| -> Vec<String> {
+            nearest_first(buckets.clone(), here)
+                .into_iter()
+                .map(|b| b.region)
+                .collect()
+        };
+        assert_eq!(order("europe-west1")[0], "europe-west1");
+        assert_eq!(
+            order("europe-west4"),
+            ["europe-west1", "us-east1", "asia-east1"]
+        );
+        assert_eq!(
+            order("us-east4"),
+            ["us-east1", "europe-west1", "asia-east1"]
+        );
+        assert_eq!(order(""), ["us-east1", "europe-west1", "asia-east1"]);
+        for bad in ["", "us-east1", "us-east1=", "a=x,a=y"] {
+            assert!(parse(bad).is_err(), "{bad}");
+        }
+
+        let lag = ReplicationLag::default();
+        lag.queued("eu");
+        lag.queued("eu");
+        lag.replicated("eu", Duration::from_millis(40));
+        lag.dropped("asia");
+        let regions = lag.snapshot();
+        let eu = &regions["eu"];
+        assert_eq!(
+            (eu.pending, eu.replicated, eu.last_lag_ms),
+            (1, 1, Some(40))
+        );
+        lag.failed("eu");
+        lag.queued("eu");
+        lag.replicated("eu", Duration::from_millis(10));
+        let eu = &lag.snapshot()["eu"];
+        assert_eq!((eu.pending, eu.failed, eu.max_lag_ms), (0, 1, 40));
+        assert_eq!(regions["asia"].dropped, 1);
+    }
+}
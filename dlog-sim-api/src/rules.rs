@@ -0,0 +1,279 @@
+//! Which blocks a tick may place or break.
+//!
+//! Rules come from the JSON file named by `OMEGA_SIM_BLOCK_RULES`; without
+//! it every world gets [`WorldRules::default`]. Each world has allow and deny
+//! lists (empty `allow` allows everything not denied) and a cap on
+//! placements per tick. `lock_tiers` names blocks that need at least a given
+//! lock tier where the player builds, so by default containers need a
+//! diamond lock. Patterns are block ids without the `minecraft:` namespace,
+//! with an optional leading or trailing `*`.
+//!
+//! ```json
+//! {
+//!   "default": { "deny": ["bedrock", "barrier"], "max_placements_per_tick": 64 },
+//!   "worlds": { "moon_shell": { "allow": ["stone", "*_slab"], "max_placements_per_tick": 16 } },
+//!   "lock_tiers": { "chest": "diamond", "beacon": "emerald" }
+//! }
+//! ```
+
+use crate::acl::Permission;
+use crate::model::{BlockAction, BlockUpdate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Lock tiers in the order they unlock more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockTier {
+    Iron,
+    Gold,
+    Diamond,
+    Emerald,
+}
+
+/// Blocks that hold items; they need a diamond lock unless configured.
+const CONTAINERS: [&str; 11] = [
+    "chest",
+    "trapped_chest",
+    "barrel",
+    "*shulker_box",
+    "hopper",
+    "dispenser",
+    "dropper",
+    "furnace",
+    "blast_furnace",
+    "smoker",
+    "brewing_stand",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldRules {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Placements one player tick may make; 0 lifts the cap.
+    #[serde(default = "default_max_placements")]
+    pub max_placements_per_tick: u32,
+}
+
+fn default_max_placements() -> u32 {
+    64
+}
+
+impl Default for WorldRules {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            max_placements_per_tick: default_max_placements(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockRules {
+    /// Worlds not listed in `worlds`.
+    #[serde(default)]
+    pub default: WorldRules,
+    #[serde(default)]
+    pub worlds: BTreeMap<String, WorldRules>,
+    #[serde(default = "default_lock_tiers")]
+    pub lock_tiers: BTreeMap<String, LockTier>,
+}
+
+fn default_lock_tiers() -> BTreeMap<String, LockTier> {
+    CONTAINERS
+        .iter()
+        .map(|block| (block.to_string(), LockTier::Diamond))
+        .collect()
+}
+
+impl Default for BlockRules {
+    fn default() -> Self {
+        Self {
+            default: WorldRules::default(),
+            worlds: BTreeMap::new(),
+            lock_tiers: default_lock_tiers(),
+        }
+    }
+}
+
+/// Why a block update was not applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Rejection {
+    /// On the world's deny list.
+    Denied,
+    /// The world has an allow list and the block is not on it.
+    NotAllowed,
+    NeedsLock {
+        tier: LockTier,
+    },
+    /// The tick already placed `limit` blocks.
+    RateLimited {
+        limit: u32,
+    },
+    /// The chunk is claimed and the builder lacks `permission` there.
+    NotPermitted {
+        permission: Permission,
+    },
+}
+
+impl BlockRules {
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("OMEGA_SIM_BLOCK_RULES") {
+            Ok(path) if !path.trim().is_empty() => {
+                let raw = std::fs::read_to_string(path.trim())?;
+                Ok(serde_json::from_str(&raw)?)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn world(&self, world: &str) -> &WorldRules {
+        self.worlds.get(world).unwrap_or(&self.default)
+    }
+
+    /// Checks for one tick of a player building in `world` under a lock of
+    /// `lock_tier` (none outside locked land).
+    pub fn gate<'a>(&'a self, world: &str, lock_tier: Option<LockTier>) -> BlockGate<'a> {
+        BlockGate {
+            rules: self,
+            world: self.world(world),
+            lock_tier,
+            placed: 0,
+        }
+    }
+
+    fn required_tier(&self, block: &str) -> Option<LockTier> {
+        self.lock_tiers
+            .iter()
+            .filter(|(pattern, _)| matches(pattern, block))
+            .map(|(_, tier)| *tier)
+            .max()
+    }
+}
+
+/// Rule checks for one tick, counting its placements.
+#[derive(Debug, Clone)]
+pub struct BlockGate<'a> {
+    rules: &'a BlockRules,
+    world: &'a WorldRules,
+    lock_tier: Option<LockTier>,
+    placed: u32,
+}
+
+impl BlockGate<'_> {
+    /// Admit `update`, counting it if it places a block.
+    pub fn check(&mut self, update: &BlockUpdate) -> Result<(), Rejection> {
+        let block = update.block.as_str();
+        if self
+            .world
+            .deny
+            .iter()
+            .any(|pattern| matches(pattern, block))
+        {
+            return Err(Rejection::Denied);
+        }
+        if !self.world.allow.is_empty()
+            && !self
+                .world
+                .allow
+                .iter()
+                .any(|pattern| matches(pattern, block))
+        {
+            return Err(Rejection::NotAllowed);
+        }
+        if let Some(tier) = self.rules.required_tier(block) {
+            if self.lock_tier < Some(tier) {
+                return Err(Rejection::NeedsLock { tier });
+            }
+        }
+        if let BlockAction::Place = update.action {
+            let limit = self.world.max_placements_per_tick;
+            if limit > 0 && self.placed >= limit {
+                return Err(Rejection::RateLimited { limit });
+            }
+            self.placed += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `block` holds items (chests, barrels, furnaces and the like).
+pub fn is_container(block: &str) -> bool {
+    CONTAINERS.iter().any(|pattern| matches(pattern, block))
+}
+
+fn matches(pattern: &str, block: &str) -> bool {
+    let id = |raw: &str| {
+        raw.trim()
+            .trim_start_matches("minecraft:")
+            .to_ascii_lowercase()
+    };
+    let (pattern, block) = (id(pattern), id(block));
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        block.ends_with(suffix)
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        block.starts_with(prefix)
+    } else {
+        pattern == block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(block: &str, action: BlockAction) -> BlockUpdate {
+        BlockUpdate {
+            x: 0,
+            y: 64,
+            z: 0,
+            block: block.into(),
+            action,
+        }
+    }
+
+    #[test]
+    fn worlds_tiers_and_rate_limits_gate_updates() {
+        let rules: BlockRules = serde_json::from_value(serde_json::json!({
+            "default": { "deny": ["bedrock"], "max_placements_per_tick": 2 },
+            "worlds": { "moon_shell": { "allow": ["*_slab"] } },
+        }))
+        .unwrap();
+        let mut earth = rules.gate("earth", Some(LockTier::Gold));
+        assert_eq!(
+            earth.check(&update("minecraft:bedrock", BlockAction::Break)),
+            Err(Rejection::Denied)
+        );
+        assert_eq!(
+            earth.check(&update("minecraft:red_shulker_box", BlockAction::Place)),
+            Err(Rejection::NeedsLock {
+                tier: LockTier::Diamond
+            })
+        );
+        assert_eq!(earth.check(&update("stone", BlockAction::Place)), Ok(()));
+        assert_eq!(earth.check(&update("dirt", BlockAction::Break)), Ok(()));
+        assert_eq!(earth.check(&update("dirt", BlockAction::Place)), Ok(()));
+        assert_eq!(
+            earth.check(&update("dirt", BlockAction::Place)),
+            Err(Rejection::RateLimited { limit: 2 })
+        );
+
+        let mut moon = rules.gate("moon_shell", Some(LockTier::Emerald));
+        assert_eq!(
+            moon.check(&update("stone_slab", BlockAction::Place)),
+            Ok(())
+        );
+        assert_eq!(
+            moon.check(&update("chest", BlockAction::Place)),
+            Err(Rejection::NotAllowed)
+        );
+        // The moon's own rules replace the default, deny list and cap included.
+        assert_eq!(moon.world.max_placements_per_tick, 64);
+        let mut vault = rules.gate("mars", Some(LockTier::Diamond));
+        assert_eq!(vault.check(&update("chest", BlockAction::Place)), Ok(()));
+    }
+}
@@ -0,0 +1,370 @@
+//! Named savepoints and scratch branches for creative experiments.
+//!
+//! A savepoint copies every live chunk and player state under
+//! `saved;<name>;` and lists the chunk versions it caught in
+//! `savepoints;<name>.json`. Ticks keep running while it copies, so the
+//! chunks are read again afterwards and the copy is redone if any version
+//! moved; after [`CAPTURE_ATTEMPTS`] tries the savepoint is refused.
+//! Restoring writes the saved chunks and players back over the live world
+//! and empties chunks built since. Every chunk it writes gets a version past
+//! both the saved and the live one, so cached chunk reads move on. Block
+//! ledgers are history and are left alone.
+//!
+//! A branch forks a savepoint into `branch;<name>;`. Ticks that name the
+//! branch read and write there and never touch the live world. Discarding
+//! a savepoint or a branch deletes its objects.
+
+use dlog_error::StorageError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::sim::PlayerState;
+use crate::storage::OmegaStorage;
+
+/// Copies tried before a savepoint of a busy world is refused.
+pub const CAPTURE_ATTEMPTS: usize = 3;
+const NAME_MAX: usize = 32;
+const SAVEPOINTS_PREFIX: &str = "savepoints;";
+const BRANCHES_PREFIX: &str = "branches;";
+/// Roots of scoped copies of the world; `undo;` holds an import's backups.
+const SCOPE_ROOTS: [&str; 3] = ["saved;", "branch;", "undo;"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkVersion {
+    pub cx: i64,
+    pub cz: i64,
+    pub version: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Savepoint {
+    pub name: String,
+    pub created_ms: u64,
+    pub chunks: Vec<ChunkVersion>,
+    pub players: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Branch {
+    pub name: String,
+    /// Savepoint the branch was forked from.
+    pub from: String,
+    pub created_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RestoreReport {
+    pub savepoint: String,
+    pub chunks: usize,
+    /// Chunks built after the savepoint, now empty.
+    pub emptied: usize,
+    pub players: usize,
+}
+
+#[derive(Debug)]
+pub enum SavepointError {
+    Invalid(String),
+    NotFound(String),
+    Exists(String),
+    /// Chunks kept changing while the savepoint copied them.
+    Busy,
+    Storage(StorageError),
+}
+
+impl SavepointError {
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Invalid(_) => 400,
+            Self::NotFound(_) => 404,
+            Self::Exists(_) | Self::Busy => 409,
+            Self::Storage(err) => err.status(),
+        }
+    }
+}
+
+impl fmt::Display for SavepointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(why) => f.write_str(why),
+            Self::NotFound(what) => write!(f, "{what} not found"),
+            Self::Exists(what) => write!(f, "{what} already exists"),
+            Self::Busy => write!(
+                f,
+                "chunks kept changing over {CAPTURE_ATTEMPTS} tries; pause the sim and retry"
+            ),
+            Self::Storage(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<StorageError> for SavepointError {
+    fn from(err: StorageError) -> Self {
+        Self::Storage(err)
+    }
+}
+
+/// 1-32 of `a-z`, `0-9`, `_` and `-`, so a name never splits a key.
+pub fn check_name(name: &str) -> Result<(), SavepointError> {
+    let ok = !name.is_empty()
+        && name.len() <= NAME_MAX
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if ok {
+        Ok(())
+    } else {
+        Err(SavepointError::Invalid(format!(
+            "names are 1-{NAME_MAX} of a-z, 0-9, '_' and '-'"
+        )))
+    }
+}
+
+pub fn saved_scope(name: &str) -> String {
+    format!("saved;{name};")
+}
+
+pub fn branch_scope(name: &str) -> String {
+    format!("branch;{name};")
+}
+
+/// `key` without a savepoint or branch scope, i.e. as the live world names it.
+pub fn unscoped(key: &str) -> &str {
+    SCOPE_ROOTS
+        .iter()
+        .find_map(|root| Some(key.strip_prefix(root)?.split_once(';')?.1))
+        .unwrap_or(key)
+}
+
+/// Version for a chunk written back by a restore: past both, so readers
+/// holding either one see it change.
+pub fn restored_version(live: u64, saved: u64) -> u64 {
+    live.max(saved) + 1
+}
+
+pub async fn create(
+    storage: &OmegaStorage,
+    name: &str,
+    created_ms: u64,
+) -> Result<Savepoint, SavepointError> {
+    check_name(name)?;
+    let key = format!("{SAVEPOINTS_PREFIX}{name}.json");
+    if storage.load_bytes(&key).await?.is_some() {
+        return Err(SavepointError::Exists(format!("savepoint {name}")));
+    }
+    let saved = storage.scoped(saved_scope(name));
+    for _ in 0..CAPTURE_ATTEMPTS {
+        let chunks = copy_world(storage, &saved).await?;
+        if chunk_versions(storage).await? != chunks {
+            continue;
+        }
+        let savepoint = Savepoint {
+            name: name.to_string(),
+            created_ms,
+            chunks,
+            players: saved.list_players().await?,
+        };
+        storage.save_json(&key, &savepoint).await?;
+        return Ok(savepoint);
+    }
+    delete_scope(storage, &saved_scope(name)).await?;
+    Err(SavepointError::Busy)
+}
+
+pub async fn list(storage: &OmegaStorage) -> Result<Vec<Savepoint>, SavepointError> {
+    let mut savepoints = Vec::new();
+    for key in storage.list_keys(SAVEPOINTS_PREFIX).await? {
+        savepoints.extend(storage.load_json::<Savepoint>(&key).await?);
+    }
+    Ok(savepoints)
+}
+
+async fn load(storage: &OmegaStorage, name: &str) -> Result<Savepoint, SavepointError> {
+    check_name(name)?;
+    storage
+        .load_json(&format!("{SAVEPOINTS_PREFIX}{name}.json"))
+        .await?
+        .ok_or_else(|| SavepointError::NotFound(format!("savepoint {name}")))
+}
+
+/// Put the live world back the way savepoint `name` caught it.
+pub async fn restore(storage: &OmegaStorage, name: &str) -> Result<RestoreReport, SavepointError> {
+    let savepoint = load(storage, name).await?;
+    let saved = storage.scoped(saved_scope(name));
+    let mut report = RestoreReport {
+        savepoint: savepoint.name.clone(),
+        ..RestoreReport::default()
+    };
+    let kept: BTreeSet<(i64, i64)> = savepoint.chunks.iter().map(|c| (c.cx, c.cz)).collect();
+    for (cx, cz) in storage.list_chunks().await? {
+        if kept.contains(&(cx, cz)) {
+            continue;
+        }
+        let (_, emptied) = storage
+            .update_chunk(cx, cz, |live| {
+                let emptied = !live.is_empty();
+                if emptied {
+                    live.clear_blocks();
+                    live.version += 1;
+                }
+                emptied
+            })
+            .await?;
+        if emptied {
+            report.emptied += 1;
+        }
+    }
+    for &(cx, cz) in &kept {
+        let chunk = saved.load_chunk(cx, cz).await?;
+        storage
+            .update_chunk(cx, cz, |live| {
+                let version = restored_version(live.version, chunk.version);
+                *live = chunk.clone();
+                live.version = version;
+            })
+            .await?;
+        report.chunks += 1;
+    }
+    for uuid in &savepoint.players {
+        let state: PlayerState = saved.load_player_state(uuid).await?.unwrap_or_default();
+        storage
+            .update_player_state(uuid, |_: Option<PlayerState>| Ok((state.clone(), ())))
+            .await?;
+        report.players += 1;
+    }
+    Ok(report)
+}
+
+pub async fn discard(storage: &OmegaStorage, name: &str) -> Result<(), SavepointError> {
+    load(storage, name).await?;
+    delete_scope(storage, &saved_scope(name)).await?;
+    storage
+        .delete(&format!("{SAVEPOINTS_PREFIX}{name}.json"))
+        .await?;
+    Ok(())
+}
+
+/// Start branch `name` as a copy of savepoint `from`.
+pub async fn fork(
+    storage: &OmegaStorage,
+    name: &str,
+    from: &str,
+    created_ms: u64,
+) -> Result<Branch, SavepointError> {
+    check_name(name)?;
+    let key = format!("{BRANCHES_PREFIX}{name}.json");
+    if storage.load_bytes(&key).await?.is_some() {
+        return Err(SavepointError::Exists(format!("branch {name}")));
+    }
+    let savepoint = load(storage, from).await?;
+    copy_world(
+        &storage.scoped(saved_scope(from)),
+        &storage.scoped(branch_scope(name)),
+    )
+    .await?;
+    let branch = Branch {
+        name: name.to_string(),
+        from: savepoint.name,
+        created_ms,
+    };
+    storage.save_json(&key, &branch).await?;
+    Ok(branch)
+}
+
+pub async fn branches(storage: &OmegaStorage) -> Result<Vec<Branch>, SavepointError> {
+    let mut branches = Vec::new();
+    for key in storage.list_keys(BRANCHES_PREFIX).await? {
+        branches.extend(storage.load_json::<Branch>(&key).await?);
+    }
+    Ok(branches)
+}
+
+/// Storage for ticks on branch `name`.
+pub async fn branch(storage: &OmegaStorage, name: &str) -> Result<OmegaStorage, SavepointError> {
+    check_name(name)?;
+    let key = format!("{BRANCHES_PREFIX}{name}.json");
+    if storage.load_bytes(&key).await?.is_none() {
+        return Err(SavepointError::NotFound(format!("branch {name}")));
+    }
+    Ok(storage.scoped(branch_scope(name)))
+}
+
+pub async fn discard_branch(storage: &OmegaStorage, name: &str) -> Result<(), SavepointError> {
+    branch(storage, name).await?;
+    delete_scope(storage, &branch_scope(name)).await?;
+    storage
+        .delete(&format!("{BRANCHES_PREFIX}{name}.json"))
+        .await?;
+    Ok(())
+}
+
+/// Copy every chunk and player state from `from` to `to`, returning the
+/// chunk versions copied.
+async fn copy_world(
+    from: &OmegaStorage,
+    to: &OmegaStorage,
+) -> Result<Vec<ChunkVersion>, StorageError> {
+    let mut versions = Vec::new();
+    for (cx, cz) in from.list_chunks().await? {
+        let chunk = from.load_chunk(cx, cz).await?;
+        to.update_chunk(cx, cz, |copy| *copy = chunk.clone())
+            .await?;
+        versions.push(ChunkVersion {
+            cx,
+            cz,
+            version: chunk.version,
+        });
+    }
+    for uuid in from.list_players().await? {
+        if let Some(state) = from.load_player_state::<PlayerState>(&uuid).await? {
+            to.update_player_state(&uuid, |_: Option<PlayerState>| Ok((state.clone(), ())))
+                .await?;
+        }
+    }
+    versions.sort_by_key(|c| (c.cx, c.cz));
+    Ok(versions)
+}
+
+async fn chunk_versions(storage: &OmegaStorage) -> Result<Vec<ChunkVersion>, StorageError> {
+    let mut versions = Vec::new();
+    for (cx, cz) in storage.list_chunks().await? {
+        let chunk = storage.load_chunk(cx, cz).await?;
+        versions.push(ChunkVersion {
+            cx,
+            cz,
+            version: chunk.version,
+        });
+    }
+    versions.sort_by_key(|c| (c.cx, c.cz));
+    Ok(versions)
+}
+
+async fn delete_scope(storage: &OmegaStorage, scope: &str) -> Result<(), StorageError> {
+    for key in storage.list_keys(scope).await? {
+        storage.delete(&key).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_scopes_and_restored_versions() {
+        assert!(check_name("try-arches_2").is_ok());
+        for bad in ["", "Arches", "a;b", "a/b", &"x".repeat(33)] {
+            assert_eq!(check_name(bad).unwrap_err().status(), 400);
+        }
+
+        let chunk = "world;chunks;1;-2.json";
+        assert_eq!(unscoped(chunk), chunk);
+        assert_eq!(unscoped(&format!("{}{chunk}", saved_scope("s"))), chunk);
+        assert_eq!(unscoped(&format!("{}{chunk}", branch_scope("b"))), chunk);
+        assert_eq!(unscoped("savepoints;s.json"), "savepoints;s.json");
+
+        // A chunk rebuilt past its saved version, and one never touched since.
+        assert_eq!(restored_version(9, 4), 10);
+        assert_eq!(restored_version(4, 4), 5);
+    }
+}
@@ -0,0 +1,133 @@
+//! Schema history of the objects the sim keeps in its bucket.
+//!
+//! `gcs` upgrades every object of these kinds as it loads it and stamps the
+//! current version as it saves, so a type only changes together with a new
+//! step here. `dlog-sim-api migrate` rewrites whole prefixes in bulk.
+
+use corelib::migrate::{Migration, Schema};
+use serde_json::{json, Map, Value};
+
+/// Fill `fields` that objects written before they existed lack.
+fn fill(object: &mut Map<String, Value>, fields: &[(&str, Value)]) {
+    for (name, default) in fields {
+        object
+            .entry(name.to_string())
+            .or_insert_with(|| default.clone());
+    }
+}
+
+fn chunk_v1(object: &mut Map<String, Value>) -> Result<(), String> {
+    for axis in ["cx", "cz"] {
+        if !object.get(axis).is_some_and(Value::is_i64) {
+            return Err(format!("chunk without an integer {axis}"));
+        }
+    }
+    fill(object, &[("version", json!(0)), ("blocks", json!([]))]);
+    Ok(())
+}
+
+fn player_v1(object: &mut Map<String, Value>) -> Result<(), String> {
+    fill(
+        object,
+        &[
+            ("universe_tick", json!(0)),
+            ("omega_x", json!(0.0)),
+            ("omega_y", json!(0.0)),
+            ("omega_z", json!(0.0)),
+        ],
+    );
+    Ok(())
+}
+
+/// Move the Ω position into the `physics` component.
+fn player_v2(object: &mut Map<String, Value>) -> Result<(), String> {
+    let mut physics = Map::new();
+    for (field, axis) in [("omega_x", "x"), ("omega_y", "y"), ("omega_z", "z")] {
+        physics.insert(axis.into(), object.remove(field).unwrap_or(json!(0.0)));
+    }
+    physics.insert("schema_version".into(), json!(1));
+    let components = object
+        .entry("components")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or("player components are not an object")?;
+    components.insert("physics".into(), Value::Object(physics));
+    Ok(())
+}
+
+fn block_ledger_v1(object: &mut Map<String, Value>) -> Result<(), String> {
+    fill(object, &[("events", json!([]))]);
+    Ok(())
+}
+
+pub const CHUNK: Schema = Schema {
+    kind: "chunk",
+    migrations: &[Migration {
+        to: 1,
+        note: "fill version and blocks on chunks saved before block updates",
+        apply: chunk_v1,
+    }],
+};
+
+pub const PLAYER: Schema = Schema {
+    kind: "player",
+    migrations: &[
+        Migration {
+            to: 1,
+            note: "fill the universe tick and Ω position",
+            apply: player_v1,
+        },
+        Migration {
+            to: 2,
+            note: "move the Ω position into the physics component",
+            apply: player_v2,
+        },
+    ],
+};
+
+pub const BLOCK_LEDGER: Schema = Schema {
+    kind: "block_ledger",
+    migrations: &[Migration {
+        to: 1,
+        note: "fill an empty event list",
+        apply: block_ledger_v1,
+    }],
+};
+
+/// Written since block statistics were introduced, so nothing to upgrade.
+pub const CHUNK_STATS: Schema = Schema {
+    kind: "chunk_stats",
+    migrations: &[],
+};
+
+/// Written since chunk access lists were introduced, so nothing to upgrade.
+pub const CHUNK_ACL: Schema = Schema {
+    kind: "chunk_acl",
+    migrations: &[],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ChunkSnapshot;
+    use crate::physics::Physics;
+    use crate::sim::PlayerState;
+
+    #[test]
+    fn legacy_objects_load_as_current_types() {
+        let mut chunk = json!({ "cx": 2, "cz": -1 });
+        assert!(CHUNK.upgrade(&mut chunk).unwrap().changed());
+        assert_eq!(chunk["schema_version"], 1);
+        let chunk: ChunkSnapshot = serde_json::from_value(chunk).unwrap();
+        assert_eq!((chunk.cx, chunk.cz, chunk.version), (2, -1, 0));
+
+        let mut player = json!({ "universe_tick": 40, "omega_y": 64.5 });
+        PLAYER.upgrade(&mut player).unwrap();
+        let player: PlayerState = serde_json::from_value(player).unwrap();
+        let position: Physics = player.components.get().unwrap().unwrap();
+        assert_eq!((player.universe_tick, position.y), (40, 64.5));
+
+        let mut broken = json!({ "cx": "2" });
+        assert!(CHUNK.upgrade(&mut broken).is_err());
+    }
+}
@@ -1,35 +1,76 @@
-use crate::model::{InputEvent, RenderCommand, TickRequest, TickResponse};
+use crate::components::Components;
+use crate::history::{self, EntityHistory, Sample};
+use crate::model::{InputEvent, Interaction, RenderCommand, TickRequest, TickResponse};
+use crate::physics::{Intent, Physics};
+use crate::stamina::{self, Stamina, StaminaReport};
+use dlog_error::StorageError;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PlayerState {
     pub universe_tick: u64,
-    pub omega_x: f64,
-    pub omega_y: f64,
-    pub omega_z: f64,
+    /// Per-subsystem data, keyed by `Component::NAME`.
+    #[serde(default)]
+    pub components: Components,
 }
 
-pub fn advance(mut state: PlayerState, req: &TickRequest) -> (PlayerState, TickResponse) {
+/// Run one tick received at `received_ms` (server clock), spanning the
+/// time since the player's last one. The tick's moves and jump steer the
+/// player's body, within what its stamina allows, and the reported
+/// position is accepted only as far as the body could have got; stamina
+/// then pays for the kinetic energy the body gained. `req.world` must be
+/// one [`physics::profile`](crate::physics::profile) knows.
+/// Interactions are resolved against `history` at the tick's compensated
+/// time, and the accepted position is then recorded there. Fails only on a
+/// stored component this binary can't read.
+pub fn advance(
+    mut state: PlayerState,
+    req: &TickRequest,
+    history: &EntityHistory,
+    received_ms: u64,
+) -> Result<(PlayerState, TickResponse), StorageError> {
+    let mut body: Physics = state.components.get_or_default()?;
+    let mut stamina: Stamina = state.components.get_or_default()?;
+    let scales = stamina.scales();
     state.universe_tick = state.universe_tick.wrapping_add(1);
+    let input_ms = history::compensated_ms(req.client_time_ms, req.clock_offset_ms, received_ms);
 
     let mut interact_title = None;
+    let mut interactions = Vec::new();
+    let mut intent = Intent::default();
 
     for event in &req.inputs {
         match event {
-            InputEvent::Move { dx, dy, dz } => {
-                state.omega_x += dx;
-                state.omega_y += dy;
-                state.omega_z += dz;
+            InputEvent::Move { dx, dz } => {
+                intent.wish[0] += dx;
+                intent.wish[1] += dz;
             }
-            InputEvent::Jump => {}
+            InputEvent::Jump => intent.jump = true,
             InputEvent::Interact { target_id } => {
                 if let Some(id) = target_id {
-                    interact_title = Some(format!("Interacted with {id}"));
+                    let target = history.position_at(id, input_ms);
+                    interact_title = Some(match &target {
+                        Some(t) => {
+                            format!("Interacted with {id} at ({:.2},{:.2},{:.2})", t.x, t.y, t.z)
+                        }
+                        None => format!("Interacted with {id}"),
+                    });
+                    interactions.push(Interaction {
+                        target_id: id.clone(),
+                        target,
+                        rewound_ms: received_ms - input_ms,
+                    });
                 }
             }
         }
     }
 
+    let span = stamina::span(body.stepped_ms.map(|last| received_ms.saturating_sub(last)));
+    body.stepped_ms = Some(received_ms);
+    let reported = [req.position.x, req.position.y, req.position.z];
+    let physics = body.step(&req.world, intent, span, scales, reported);
+    let energy_delta = stamina.step(body.effort(), span);
+
     let mut render = vec![
         RenderCommand::PlaceArmorStand {
             id: "as-origin".into(),
@@ -41,29 +82,69 @@ pub fn advance(mut state: PlayerState, req: &TickRequest) -> (PlayerState, TickR
         },
         RenderCommand::MoveArmorStand {
             id: format!("player-{}", req.player_uuid),
-            x: req.position.x,
-            y: req.position.y,
-            z: req.position.z,
+            x: physics.x,
+            y: physics.y,
+            z: physics.z,
             yaw: req.position.yaw,
             pitch: req.position.pitch,
         },
         RenderCommand::Title {
-            text: format!(
-                "Ω tick {} (local {})",
-                state.universe_tick, req.local_tick
-            ),
+            text: format!("Ω tick {} (local {})", state.universe_tick, req.local_tick),
         },
     ];
 
     if let Some(text) = interact_title {
         render.push(RenderCommand::Title { text });
     }
+    render.push(RenderCommand::ActionBar {
+        text: stamina.hud(),
+    });
+
+    history.record(
+        &format!("player-{}", req.player_uuid),
+        Sample {
+            at_ms: input_ms,
+            universe_tick: state.universe_tick,
+            x: physics.x,
+            y: physics.y,
+            z: physics.z,
+        },
+    );
+
+    state.components.insert(&body)?;
+    state.components.insert(&stamina)?;
 
     let resp = TickResponse {
         universe_tick: state.universe_tick,
+        paused: false,
         render,
         chunks: Vec::new(),
+        interactions,
+        rejected: Vec::new(),
+        stamina: Some(StaminaReport {
+            level: stamina.level,
+            energy_delta,
+            scales: stamina.scales(),
+        }),
+        physics: Some(physics),
     };
 
-    (state, resp)
+    Ok((state, resp))
+}
+
+/// Answer for a tick that arrives while the world is paused: nothing moves,
+/// and the client shows a frozen-world title.
+pub fn frozen(state: &PlayerState) -> TickResponse {
+    TickResponse {
+        universe_tick: state.universe_tick,
+        paused: true,
+        render: vec![RenderCommand::Title {
+            text: format!("Ω frozen at tick {}", state.universe_tick),
+        }],
+        chunks: Vec::new(),
+        interactions: Vec::new(),
+        rejected: Vec::new(),
+        stamina: None,
+        physics: None,
+    }
 }
@@ -0,0 +1,201 @@
+//! Stamina, paid for in kinetic energy.
+//!
+//! Each tick's velocity is the player's body after the physics step, in
+//! blocks per tick and without falling speed. The step compares its
+//! kinetic energy (per unit mass, ½|v|²) with the previous tick's: energy
+//! gained by speeding up or jumping drains stamina, [`CAPACITY`] of it
+//! emptying a full bar. A tick that gains nothing and isn't faster than a
+//! walk rests, and rest shrinks the missing stamina by a factor of φ every
+//! [`REGEN_TICKS`] ticks, quickly at first and tapering off near full.
+//!
+//! Stamina gates intensity rather than movement: below 1/φ the speed past a
+//! walk that the body may reach is scaled down until, empty, the player can
+//! only walk, and jumps weaken towards 1/φ² of their full launch.
+//!
+//! Clients on slow links tick less often. A request covers the server time
+//! since the player's last tick, so the body is stepped that long and rest
+//! counts for every tick the request spans. The client's word on how long
+//! it was away is not taken: a longer span widens how far it may move.
+
+use corelib::migrate::{Migration, Schema};
+use serde::{Deserialize, Serialize};
+
+use crate::components::Component;
+
+const PHI: f64 = 1.618_033_988_749_894;
+/// Walking pace, blocks per tick; faster strides are sprinting.
+pub const WALK_SPEED: f64 = 0.216;
+/// Kinetic energy a full bar pays for.
+pub const CAPACITY: f64 = 0.5;
+/// Resting ticks over which missing stamina shrinks by a factor of φ.
+pub const REGEN_TICKS: f64 = 20.0;
+/// A tick as the speeds above count it: one Paper tick.
+pub const TICK_MS: f64 = 50.0;
+/// Most ticks one request counts for, however long the client was away.
+pub const MAX_SPAN: f64 = 20.0;
+const HUD_SEGMENTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stamina {
+    /// 0 (spent) to 1 (rested).
+    pub level: f64,
+    /// Last tick's velocity, blocks per tick.
+    pub velocity: [f64; 3],
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            velocity: [0.0; 3],
+        }
+    }
+}
+
+impl Component for Stamina {
+    const NAME: &'static str = "stamina";
+    const SCHEMA: Schema = Schema {
+        kind: "stamina",
+        migrations: &[Migration {
+            to: 1,
+            note: "stamina level and last velocity",
+            apply: |_| Ok(()),
+        }],
+    };
+}
+
+/// How much of a sprint and a jump the current stamina allows.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MovementScales {
+    /// Share of the speed past a walk that a stride keeps.
+    pub sprint: f64,
+    /// Share of the planet's full launch a jump gets.
+    pub jump: f64,
+}
+
+impl MovementScales {
+    /// A horizontal stride with its speed past [`WALK_SPEED`] scaled down.
+    pub fn stride(&self, dx: f64, dz: f64) -> (f64, f64) {
+        let speed = dx.hypot(dz);
+        if speed <= WALK_SPEED {
+            return (dx, dz);
+        }
+        let kept = (WALK_SPEED + (speed - WALK_SPEED) * self.sprint) / speed;
+        (dx * kept, dz * kept)
+    }
+}
+
+/// Stamina as a tick response reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StaminaReport {
+    pub level: f64,
+    /// Kinetic energy gained (or, negative, lost) this tick.
+    pub energy_delta: f64,
+    pub scales: MovementScales,
+}
+
+/// Ticks `elapsed_ms` of server time since the last tick spans; one for a
+/// player's first tick.
+pub fn span(elapsed_ms: Option<u64>) -> f64 {
+    elapsed_ms.map_or(1.0, |ms| (ms as f64 / TICK_MS).clamp(0.1, MAX_SPAN))
+}
+
+pub fn kinetic_energy(velocity: [f64; 3]) -> f64 {
+    0.5 * velocity.iter().map(|v| v * v).sum::<f64>()
+}
+
+impl Stamina {
+    pub fn scales(&self) -> MovementScales {
+        let strength = (self.level * PHI).min(1.0);
+        let floor = 1.0 / (PHI * PHI);
+        MovementScales {
+            sprint: strength,
+            jump: floor + (1.0 - floor) * strength,
+        }
+    }
+
+    /// Move on to `ticks` ticks at `velocity`, paying for the energy it
+    /// gained or resting through them if it gained none at a walk. Returns
+    /// the energy delta.
+    pub fn step(&mut self, velocity: [f64; 3], ticks: f64) -> f64 {
+        let energy_delta = kinetic_energy(velocity) - kinetic_energy(self.velocity);
+        if energy_delta > 0.0 {
+            self.level = (self.level - energy_delta / CAPACITY).max(0.0);
+        } else if velocity[0].hypot(velocity[2]) <= WALK_SPEED {
+            self.level = 1.0 - (1.0 - self.level) * PHI.powf(-ticks / REGEN_TICKS);
+        }
+        self.velocity = velocity;
+        energy_delta
+    }
+
+    /// `Stamina ▮▮▮▮▮▮▯▯▯▯ 62%`, for the action bar.
+    pub fn hud(&self) -> String {
+        let filled = (self.level * HUD_SEGMENTS as f64).round() as usize;
+        format!(
+            "Stamina {}{} {:.0}%",
+            "▮".repeat(filled),
+            "▯".repeat(HUD_SEGMENTS - filled),
+            self.level * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effort_drains_rest_refills_on_phi_and_scales_follow() {
+        let mut stamina = Stamina::default();
+        assert_eq!(
+            stamina.scales(),
+            MovementScales {
+                sprint: 1.0,
+                jump: 1.0
+            }
+        );
+        assert_eq!(stamina.hud(), "Stamina ▮▮▮▮▮▮▮▮▮▮ 100%");
+
+        // Jumping costs its launch energy; stopping rests.
+        let jump = stamina.step([0.0, 0.42, 0.0], 1.0);
+        assert!((jump - kinetic_energy([0.0, 0.42, 0.0])).abs() < 1e-12);
+        assert!((stamina.level - (1.0 - jump / CAPACITY)).abs() < 1e-12);
+        let spent = stamina.level;
+        assert!(stamina.step([0.0; 3], 1.0) < 0.0);
+        assert!(stamina.level > spent);
+
+        // Holding a sprint costs nothing more but doesn't rest either.
+        stamina.step([0.28, 0.0, 0.0], 1.0);
+        let sprinting = stamina.level;
+        stamina.step([0.28, 0.0, 0.0], 1.0);
+        assert_eq!(stamina.level, sprinting);
+
+        // REGEN_TICKS of rest leave 1/φ of what was missing.
+        stamina.level = 0.0;
+        stamina.velocity = [0.1, 0.0, 0.0];
+        for _ in 0..REGEN_TICKS as usize {
+            stamina.step([0.1, 0.0, 0.0], 1.0);
+        }
+        assert!((1.0 - stamina.level - 1.0 / PHI).abs() < 1e-9);
+
+        // One request spanning those ticks rests as much.
+        stamina.level = 0.0;
+        stamina.step([0.1, 0.0, 0.0], span(Some(20 * TICK_MS as u64)));
+        assert!((1.0 - stamina.level - 1.0 / PHI).abs() < 1e-9);
+        assert_eq!(span(None), 1.0);
+        assert_eq!(span(Some(0)), 0.1);
+        assert_eq!(span(Some(60_000)), MAX_SPAN);
+
+        // Spent: strides fall back to a walk and jumps to 1/φ² height.
+        let empty = Stamina {
+            level: 0.0,
+            ..Stamina::default()
+        };
+        let scales = empty.scales();
+        let (dx, dz) = scales.stride(0.3, 0.4);
+        assert!((dx.hypot(dz) - WALK_SPEED).abs() < 1e-12);
+        assert_eq!(scales.stride(0.1, 0.0), (0.1, 0.0));
+        assert!((scales.jump - 1.0 / (PHI * PHI)).abs() < 1e-12);
+        assert_eq!(empty.hud(), "Stamina ▯▯▯▯▯▯▯▯▯▯ 0%");
+    }
+}
@@ -0,0 +1,165 @@
+//! Per-chunk block statistics, kept up to date as events are appended.
+//!
+//! The block ledger only holds a window of raw events. Next to it every
+//! chunk keeps running totals at `world;stats;<cx>;<cz>.json`: places and
+//! breaks by block type, the most active builders and the tick of the last
+//! change. They are folded in on append, so readers such as the land and
+//! lock system never replay the ledger. They are served with who may build
+//! in the chunk ([`ChunkReport`]).
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::acl::{ChunkAcl, Permission};
+use crate::model::{BlockAction, BlockEvent};
+
+/// Builders a chunk remembers. Past this the least active is forgotten, so
+/// a newcomer's count starts over once it drops out.
+pub const BUILDERS_KEPT: usize = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCounts {
+    pub placed: u64,
+    pub broken: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuilderCount {
+    pub builder: String,
+    /// Places and breaks together.
+    pub events: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkStats {
+    pub cx: i64,
+    pub cz: i64,
+    /// Every event folded in, including ones the ledger window has dropped.
+    #[serde(default)]
+    pub events: u64,
+    #[serde(default)]
+    pub blocks: BTreeMap<String, BlockCounts>,
+    /// Most active first; ties go to the builder seen first.
+    #[serde(default)]
+    pub top_builders: Vec<BuilderCount>,
+    /// Tick of the latest event; absent until the chunk is first built in.
+    #[serde(default)]
+    pub last_tick: Option<u64>,
+}
+
+impl ChunkStats {
+    pub fn new(cx: i64, cz: i64) -> Self {
+        Self {
+            cx,
+            cz,
+            ..Self::default()
+        }
+    }
+
+    pub fn record(&mut self, event: &BlockEvent) {
+        self.events += 1;
+        let counts = self.blocks.entry(event.block.clone()).or_default();
+        match event.action {
+            BlockAction::Place => counts.placed += 1,
+            BlockAction::Break => counts.broken += 1,
+        }
+        self.last_tick = Some(self.last_tick.map_or(event.tick, |t| t.max(event.tick)));
+
+        let Some(builder) = &event.builder else {
+            return;
+        };
+        let at = match self.top_builders.iter().position(|b| &b.builder == builder) {
+            Some(at) => at,
+            None => {
+                if self.top_builders.len() >= BUILDERS_KEPT {
+                    self.top_builders.pop();
+                }
+                self.top_builders.push(BuilderCount {
+                    builder: builder.clone(),
+                    events: 0,
+                });
+                self.top_builders.len() - 1
+            }
+        };
+        self.top_builders[at].events += 1;
+        // One bump moves a builder up past those it now strictly beats.
+        let mut at = at;
+        while at > 0 && self.top_builders[at - 1].events < self.top_builders[at].events {
+            self.top_builders.swap(at - 1, at);
+            at -= 1;
+        }
+    }
+}
+
+/// What `GET /v1/chunks/<cx>/<cz>/stats` serves.
+#[derive(Debug, Serialize)]
+pub struct ChunkReport {
+    #[serde(flatten)]
+    pub stats: ChunkStats,
+    /// The claim's owner; `None` while anyone may build.
+    pub owner: Option<String>,
+    pub grants: BTreeMap<String, BTreeSet<Permission>>,
+}
+
+impl ChunkReport {
+    pub fn new(stats: ChunkStats, acl: ChunkAcl) -> Self {
+        Self {
+            stats,
+            owner: acl.owner,
+            grants: acl.grants,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tick: u64, block: &str, action: BlockAction, builder: Option<&str>) -> BlockEvent {
+        BlockEvent {
+            tick,
+            x: 0,
+            y: 64,
+            z: 0,
+            block: block.into(),
+            action,
+            builder: builder.map(String::from),
+        }
+    }
+
+    #[test]
+    fn events_fold_into_histograms_and_a_bounded_builder_ranking() {
+        let mut stats = ChunkStats::new(1, -2);
+        stats.record(&event(7, "stone", BlockAction::Place, Some("ann")));
+        stats.record(&event(9, "stone", BlockAction::Break, Some("bob")));
+        stats.record(&event(8, "dirt", BlockAction::Place, Some("bob")));
+        stats.record(&event(8, "dirt", BlockAction::Place, None));
+
+        assert_eq!(stats.events, 4);
+        assert_eq!(
+            stats.blocks["stone"],
+            BlockCounts {
+                placed: 1,
+                broken: 1
+            }
+        );
+        assert_eq!(stats.blocks["dirt"].placed, 2);
+        // A late-arriving event never moves the last activity back.
+        assert_eq!(stats.last_tick, Some(9));
+        let ranking: Vec<_> = stats
+            .top_builders
+            .iter()
+            .map(|b| (b.builder.as_str(), b.events))
+            .collect();
+        assert_eq!(ranking, [("bob", 2), ("ann", 1)]);
+
+        for n in 0..BUILDERS_KEPT {
+            let builder = format!("new{n}");
+            stats.record(&event(10, "sand", BlockAction::Place, Some(&builder)));
+        }
+        assert_eq!(stats.top_builders.len(), BUILDERS_KEPT);
+        assert_eq!(stats.top_builders[0].builder, "bob");
+        assert_eq!(stats.top_builders[1].builder, "ann");
+        assert_eq!(stats.top_builders[BUILDERS_KEPT - 1].builder, "new15");
+    }
+}
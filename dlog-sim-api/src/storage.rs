@@ -0,0 +1,835 @@
+use crate::acl::ChunkAcl;
+use crate::gcs::{self, GcsStore};
+use crate::model::{BlockEvent, BlockLedger, ChunkSnapshot};
+use crate::region::{self, RegionLag, ReplicationLag};
+use crate::savepoint;
+use crate::schema;
+use crate::stats::ChunkStats;
+use crate::store::{
+    content_generation, FsStore, MemoryStore, ObjectStore, Stored, DEFAULT_STORE_DIR,
+};
+use corelib::migrate::{MigrationReport, Schema};
+use dlog_error::StorageError;
+use dlog_resilience::BreakerSnapshot;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+pub const PLAYERS_PREFIX: &str = "sim;players;";
+pub const CHUNKS_PREFIX: &str = "world;chunks;";
+const BLOCK_LEDGERS_PREFIX: &str = "ledger;blocks;";
+const CHUNK_STATS_PREFIX: &str = "world;stats;";
+const CHUNK_ACLS_PREFIX: &str = "world;acl;";
+/// Writes waiting to be copied into one region before new ones are dropped.
+pub const REPLICATION_QUEUE: usize = 4096;
+/// Times an update reloads and re-applies after losing a race before it
+/// gives up with `StorageError::Conflict`.
+pub const CONFLICT_RETRIES: usize = 4;
+
+/// Versioned object kinds by key prefix.
+const SCHEMAS: [(&str, &Schema); 5] = [
+    (CHUNKS_PREFIX, &schema::CHUNK),
+    (PLAYERS_PREFIX, &schema::PLAYER),
+    (BLOCK_LEDGERS_PREFIX, &schema::BLOCK_LEDGER),
+    (CHUNK_STATS_PREFIX, &schema::CHUNK_STATS),
+    (CHUNK_ACLS_PREFIX, &schema::CHUNK_ACL),
+];
+
+fn schema_for(key: &str) -> Option<&'static Schema> {
+    let key = savepoint::unscoped(key);
+    SCHEMAS
+        .iter()
+        .find(|(prefix, _)| key.starts_with(prefix))
+        .map(|(_, schema)| *schema)
+}
+
+#[derive(Clone)]
+pub struct OmegaStorage {
+    /// One bucket per region, nearest first; calls fall back down the list.
+    buckets: Arc<[RegionBucket]>,
+    lag: Arc<ReplicationLag>,
+    /// Prepended to player, chunk and ledger keys; empty for the live world.
+    scope: String,
+    preloaded: Arc<Mutex<Preloaded>>,
+}
+
+/// Copies loaded ahead of the update that needs them, by key, with the
+/// bucket they came from; `None` when there was no object.
+type Preloaded = HashMap<String, (usize, Option<Stored>)>;
+
+struct RegionBucket {
+    region: String,
+    store: Arc<dyn ObjectStore>,
+    /// Copies of writes made in other regions; `None` with a single bucket.
+    replicas: Option<mpsc::Sender<Replica>>,
+}
+
+/// A write to copy into another region.
+struct Replica {
+    key: String,
+    /// `None` copies a delete.
+    bytes: Option<Vec<u8>>,
+    /// For a conditional save, the contents it replaced (see
+    /// [`Generation::content`]): the copy only lands over the same ones.
+    base: Option<i64>,
+    written: Instant,
+}
+
+/// The copy of an object a load saw. A save with it only lands while that
+/// copy is still the current one (GCS `ifGenerationMatch`, or the same
+/// check in the other stores).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generation {
+    /// Bucket the copy came from; the save goes back to it.
+    bucket: usize,
+    /// The store's generation of the object, 0 when there was none.
+    number: i64,
+    /// [`content_generation`] of the copy, 0 when there was none. Other
+    /// regions number their copies differently, so replicas of the save
+    /// are checked against this instead.
+    content: i64,
+}
+
+/// Copy a conditional save into `bucket`, but only over the contents the
+/// save replaced; a region that has moved on since keeps its copy
+/// (`StorageError::Conflict`).
+async fn copy_if(
+    bucket: &RegionBucket,
+    key: &str,
+    bytes: Vec<u8>,
+    base: i64,
+) -> Result<(), StorageError> {
+    let (number, content) = match bucket.store.load_versioned(key).await? {
+        Some(stored) => (stored.generation, content_generation(&stored.bytes)),
+        None => (0, 0),
+    };
+    if content == content_generation(&bytes) {
+        return Ok(());
+    }
+    if content != base {
+        return Err(StorageError::Conflict(key.to_string()));
+    }
+    bucket.store.save(key, bytes, Some(number)).await
+}
+
+/// `bytes` stored under `key`, upgraded to its kind's current schema.
+fn decode<T: DeserializeOwned>(key: &str, bytes: &[u8]) -> Result<T, StorageError> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    if let Some(schema) = schema_for(key) {
+        schema
+            .upgrade(&mut value)
+            .map_err(|err| StorageError::Schema(format!("{key}: {err}")))?;
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// `value` as stored under `key`, stamped with its kind's schema version.
+fn encode<T: Serialize>(key: &str, value: &T) -> Result<Vec<u8>, StorageError> {
+    let mut value = serde_json::to_value(value)?;
+    if let Some(schema) = schema_for(key) {
+        schema.stamp(&mut value);
+    }
+    Ok(serde_json::to_vec(&value)?)
+}
+
+impl OmegaStorage {
+    /// Open the store `OMEGA_STORE` names: `gcs` (the default), `fs` or
+    /// `memory` (see [`store`](crate::store)).
+    pub async fn new_from_env() -> Result<Self, StorageError> {
+        let kind = std::env::var("OMEGA_STORE").unwrap_or_default();
+        match kind.trim() {
+            "" | "gcs" => Self::gcs_from_env().await,
+            "fs" => {
+                let dir =
+                    std::env::var("OMEGA_STORE_DIR").unwrap_or_else(|_| DEFAULT_STORE_DIR.into());
+                Ok(Self::local(Arc::new(FsStore::open(dir).await?)))
+            }
+            "memory" => Ok(Self::local(Arc::new(MemoryStore::default()))),
+            other => Err(StorageError::Config(format!(
+                "OMEGA_STORE `{other}` is not gcs, fs or memory"
+            ))),
+        }
+    }
+
+    /// Connect to the buckets from `OMEGA_BUCKETS`/`OMEGA_REGION` (or the
+    /// single `OMEGA_BUCKET`) and start a replication worker per region.
+    async fn gcs_from_env() -> Result<Self, StorageError> {
+        let configs = region::from_env()?;
+        let client = gcs::client_from_env().await?;
+        let regional = configs.len() > 1;
+        let buckets = configs
+            .into_iter()
+            .map(|config| {
+                // A lone bucket keeps the plain `gcs` name for /health and chaos plans.
+                let upstream = if regional {
+                    format!("gcs:{}", config.region)
+                } else {
+                    "gcs".into()
+                };
+                let store: Arc<dyn ObjectStore> =
+                    Arc::new(GcsStore::new(Arc::clone(&client), config.bucket, upstream));
+                (config.region, store)
+            })
+            .collect();
+        Ok(Self::regional(buckets))
+    }
+
+    /// `buckets` by region, nearest first, with a replication worker per
+    /// region when there is more than one.
+    fn regional(buckets: Vec<(String, Arc<dyn ObjectStore>)>) -> Self {
+        let regional = buckets.len() > 1;
+        let mut receivers = Vec::new();
+        let buckets: Arc<[RegionBucket]> = buckets
+            .into_iter()
+            .map(|(region, store)| {
+                let replicas = regional.then(|| {
+                    let (tx, rx) = mpsc::channel(REPLICATION_QUEUE);
+                    receivers.push(rx);
+                    tx
+                });
+                RegionBucket {
+                    region,
+                    store,
+                    replicas,
+                }
+            })
+            .collect();
+        let storage = Self {
+            buckets,
+            lag: Arc::new(ReplicationLag::default()),
+            scope: String::new(),
+            preloaded: Arc::default(),
+        };
+        for (index, rx) in receivers.into_iter().enumerate() {
+            tokio::spawn(storage.clone().replicate(index, rx));
+        }
+        storage
+    }
+
+    /// Everything in `store`, as a single region with nothing to replicate.
+    pub fn local(store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            buckets: Arc::new([RegionBucket {
+                region: region::DEFAULT_REGION.into(),
+                store,
+                replicas: None,
+            }]),
+            lag: Arc::new(ReplicationLag::default()),
+            scope: String::new(),
+            preloaded: Arc::default(),
+        }
+    }
+
+    /// The same buckets with player, chunk and ledger keys under `scope`
+    /// (a branch's `branch;<name>;`).
+    pub fn scoped(&self, scope: String) -> Self {
+        Self {
+            scope,
+            ..self.clone()
+        }
+    }
+
+    /// Which kind of store the objects are in.
+    pub fn store_name(&self) -> &'static str {
+        self.buckets[0].store.name()
+    }
+
+    /// Breaker per bucket, nearest first; none for local stores.
+    pub fn breakers(&self) -> Vec<BreakerSnapshot> {
+        self.buckets
+            .iter()
+            .filter_map(|b| b.store.breaker())
+            .collect()
+    }
+
+    /// Copies into each region: pending, landed, failed, and their lag.
+    pub fn replication(&self) -> BTreeMap<String, RegionLag> {
+        self.lag.snapshot()
+    }
+
+    /// Wait until every queued copy has landed or failed, e.g. before a
+    /// one-shot command exits.
+    pub async fn flush_replication(&self) {
+        while self.lag.snapshot().values().any(|lag| lag.pending > 0) {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Run `op` against each bucket (by index) nearest first until one
+    /// answers, returning which one did.
+    async fn nearest<T, F, Fut>(&self, mut op: F) -> Result<(usize, T), StorageError>
+    where
+        F: FnMut(usize) -> Fut,
+        Fut: Future<Output = Result<T, StorageError>>,
+    {
+        let mut last = None;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            match op(index).await {
+                Ok(value) => return Ok((index, value)),
+                // Another region's copy won't be any newer.
+                Err(err @ StorageError::Conflict(_)) => return Err(err),
+                Err(err) => {
+                    if index + 1 < self.buckets.len() {
+                        warn!(
+                            "[{}] {} failed ({err}); trying the next region",
+                            bucket.store.name(),
+                            bucket.region
+                        );
+                    }
+                    last = Some(err);
+                }
+            }
+        }
+        Err(last.expect("storage has at least one bucket"))
+    }
+
+    /// Queue a write made in bucket `from` for every other region; `base`
+    /// makes the copies conditional like the write was.
+    fn fan_out(&self, from: usize, key: &str, bytes: Option<&[u8]>, base: Option<i64>) {
+        let written = Instant::now();
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            let Some(replicas) = bucket.replicas.as_ref().filter(|_| index != from) else {
+                continue;
+            };
+            let replica = Replica {
+                key: key.to_string(),
+                bytes: bytes.map(<[u8]>::to_vec),
+                base,
+                written,
+            };
+            match replicas.try_send(replica) {
+                Ok(()) => self.lag.queued(&bucket.region),
+                Err(_) => self.lag.dropped(&bucket.region),
+            }
+        }
+    }
+
+    /// Copy writes into bucket `index` in the order they were made.
+    async fn replicate(self, index: usize, mut rx: mpsc::Receiver<Replica>) {
+        let bucket = &self.buckets[index];
+        while let Some(replica) = rx.recv().await {
+            let copied = match (replica.bytes, replica.base) {
+                (Some(bytes), Some(base)) => copy_if(bucket, &replica.key, bytes, base).await,
+                (Some(bytes), None) => bucket.store.save(&replica.key, bytes, None).await,
+                (None, _) => bucket.store.delete(&replica.key).await,
+            };
+            match copied {
+                Ok(()) => self
+                    .lag
+                    .replicated(&bucket.region, replica.written.elapsed()),
+                Err(StorageError::Conflict(_)) => {
+                    warn!(
+                        "[{}] {} changed in {} since the write it would copy; keeping that copy",
+                        bucket.store.name(),
+                        replica.key,
+                        bucket.region
+                    );
+                    self.lag.conflicted(&bucket.region);
+                }
+                Err(err) => {
+                    warn!(
+                        "[{}] copying {} to {}: {err}",
+                        bucket.store.name(),
+                        replica.key,
+                        bucket.region
+                    );
+                    self.lag.failed(&bucket.region);
+                }
+            }
+        }
+    }
+
+    fn key_for_player(&self, player_uuid: &str) -> String {
+        format!("{}{PLAYERS_PREFIX}{};state.json", self.scope, player_uuid)
+    }
+
+    fn key_for_chunk(&self, cx: i64, cz: i64) -> String {
+        format!("{}{CHUNKS_PREFIX}{};{}.json", self.scope, cx, cz)
+    }
+
+    fn key_for_block_ledger(&self, cx: i64, cz: i64) -> String {
+        format!("{}{BLOCK_LEDGERS_PREFIX}{};{}.json", self.scope, cx, cz)
+    }
+
+    fn key_for_chunk_stats(&self, cx: i64, cz: i64) -> String {
+        format!("{}{CHUNK_STATS_PREFIX}{};{}.json", self.scope, cx, cz)
+    }
+
+    fn key_for_chunk_acl(&self, cx: i64, cz: i64) -> String {
+        format!("{}{CHUNK_ACLS_PREFIX}{};{}.json", self.scope, cx, cz)
+    }
+
+    /// Load `key`, upgraded to its kind's current schema when it has one.
+    pub async fn load_json<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, StorageError> {
+        let Some(bytes) = self.load_bytes(key).await? else {
+            return Ok(None);
+        };
+        decode(key, &bytes).map(Some)
+    }
+
+    /// [`load_json`](Self::load_json), along with the generation a
+    /// conditional save of `key` has to match.
+    pub async fn load_json_versioned<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<(Option<T>, Generation), StorageError> {
+        let preloaded = self.preloaded.lock().unwrap().remove(key);
+        let (bucket, stored) = match preloaded {
+            Some(preloaded) => preloaded,
+            None => self.load_stored(key).await?,
+        };
+        let (number, content) = stored.as_ref().map_or((0, 0), |stored| {
+            (stored.generation, content_generation(&stored.bytes))
+        });
+        let value = stored
+            .map(|stored| decode(key, &stored.bytes))
+            .transpose()?;
+        Ok((
+            value,
+            Generation {
+                bucket,
+                number,
+                content,
+            },
+        ))
+    }
+
+    async fn load_stored(&self, key: &str) -> Result<(usize, Option<Stored>), StorageError> {
+        self.nearest(|index| self.buckets[index].store.load_versioned(key))
+            .await
+    }
+
+    /// Load `key` now so the next [`update_json`](Self::update_json) of it
+    /// starts without a round trip. Only that update takes the copy, and
+    /// only once: if it went stale meanwhile, the save conflicts and the
+    /// update reloads, so nothing is lost. Returns whether `key` exists.
+    async fn preload(&self, key: &str) -> Result<bool, StorageError> {
+        let (bucket, stored) = self.load_stored(key).await?;
+        let found = stored.is_some();
+        self.preloaded
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (bucket, stored));
+        Ok(found)
+    }
+
+    /// Drop the preloaded copy of `key`, which is being written.
+    fn forget_preloaded(&self, key: &str) {
+        self.preloaded.lock().unwrap().remove(key);
+    }
+
+    /// Copies preloaded and not yet taken by an update.
+    pub fn preloaded(&self) -> usize {
+        self.preloaded.lock().unwrap().len()
+    }
+
+    pub async fn load_bytes(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let (_, bytes) = self
+            .nearest(|index| self.buckets[index].store.load(key))
+            .await?;
+        Ok(bytes)
+    }
+
+    /// sha-less digest of the object at `key`, streamed from the store
+    /// where it can be; `None` if there is no such object.
+    pub async fn digest_object(&self, key: &str) -> Result<Option<String>, StorageError> {
+        let (_, digest) = self
+            .nearest(|index| self.buckets[index].store.digest(key))
+            .await?;
+        Ok(digest)
+    }
+
+    /// Save `value` under `key`, stamped with its kind's schema version.
+    pub async fn save_json<T: Serialize>(&self, key: &str, value: &T) -> Result<(), StorageError> {
+        self.save_bytes(key, encode(key, value)?).await
+    }
+
+    /// [`save_json`](Self::save_json), but only over the copy `generation`
+    /// saw; anything newer fails with `StorageError::Conflict`.
+    pub async fn save_json_if<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        generation: Generation,
+    ) -> Result<(), StorageError> {
+        let bytes = encode(key, value)?;
+        self.forget_preloaded(key);
+        self.buckets[generation.bucket]
+            .store
+            .save(key, bytes.clone(), Some(generation.number))
+            .await?;
+        self.fan_out(
+            generation.bucket,
+            key,
+            Some(&bytes),
+            Some(generation.content),
+        );
+        Ok(())
+    }
+
+    /// Load `key`, let `apply` turn it into its next value, and save that
+    /// only if nothing else wrote `key` in between. A lost race starts over
+    /// from the fresh copy, up to [`CONFLICT_RETRIES`] times, so `apply` may
+    /// run more than once; an error from it is returned without saving.
+    pub async fn update_json<T, R, E, F>(&self, key: &str, mut apply: F) -> Result<(T, R), E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: From<StorageError>,
+        F: FnMut(Option<T>) -> Result<(T, R), E>,
+    {
+        let mut retries = 0;
+        loop {
+            let (current, generation) = self.load_json_versioned(key).await?;
+            let (next, out) = apply(current)?;
+            match self.save_json_if(key, &next, generation).await {
+                Err(StorageError::Conflict(_)) if retries < CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err.into()),
+                Ok(()) => return Ok((next, out)),
+            }
+        }
+    }
+
+    async fn save_value(&self, key: &str, value: &Value) -> Result<(), StorageError> {
+        self.save_bytes(key, serde_json::to_vec(value)?).await
+    }
+
+    /// Store `bytes` under `key` as they are, e.g. a copy of another object.
+    /// The nearest bucket that takes it passes it on to the other regions.
+    pub async fn save_bytes(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        self.forget_preloaded(key);
+        let (from, ()) = self
+            .nearest(|index| self.buckets[index].store.save(key, bytes.clone(), None))
+            .await?;
+        self.fan_out(from, key, Some(&bytes), None);
+        Ok(())
+    }
+
+    /// Delete `key` everywhere; a missing object is not an error.
+    pub async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.forget_preloaded(key);
+        let (from, ()) = self
+            .nearest(|index| self.buckets[index].store.delete(key))
+            .await?;
+        self.fan_out(from, key, None, None);
+        Ok(())
+    }
+
+    /// Every object key under `prefix`.
+    pub async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let (_, keys) = self
+            .nearest(|index| self.buckets[index].store.list(prefix))
+            .await?;
+        Ok(keys)
+    }
+
+    /// Upgrade every versioned object in the store to its current schema.
+    /// A dry run reads and reports without writing anything back.
+    pub async fn migrate(&self, dry_run: bool) -> Result<MigrationReport, StorageError> {
+        let mut report = MigrationReport::new(dry_run);
+        for (prefix, schema) in SCHEMAS {
+            for key in self.list_keys(prefix).await? {
+                let Some(bytes) = self.load_bytes(&key).await? else {
+                    continue;
+                };
+                let value = match serde_json::from_slice::<Value>(&bytes) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        report.unreadable(schema, &key, err);
+                        continue;
+                    }
+                };
+                if let Some(upgraded) = report.check(schema, &key, value) {
+                    if !dry_run {
+                        self.save_value(&key, &upgraded).await?;
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Uuids of every player with saved state.
+    pub async fn list_players(&self) -> Result<Vec<String>, StorageError> {
+        let prefix = format!("{}{PLAYERS_PREFIX}", self.scope);
+        let keys = self.list_keys(&prefix).await?;
+        Ok(keys
+            .iter()
+            .filter_map(|key| key.strip_prefix(&prefix)?.strip_suffix(";state.json"))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Coordinates of every stored chunk.
+    pub async fn list_chunks(&self) -> Result<Vec<(i64, i64)>, StorageError> {
+        let prefix = format!("{}{CHUNKS_PREFIX}", self.scope);
+        let keys = self.list_keys(&prefix).await?;
+        Ok(keys
+            .iter()
+            .filter_map(|key| {
+                let coords = key.strip_prefix(&prefix)?.strip_suffix(".json")?;
+                let (cx, cz) = coords.split_once(';')?;
+                Some((cx.parse().ok()?, cz.parse().ok()?))
+            })
+            .collect())
+    }
+
+    pub async fn load_player_state<T: DeserializeOwned>(
+        &self,
+        player_uuid: &str,
+    ) -> Result<Option<T>, StorageError> {
+        let key = self.key_for_player(player_uuid);
+        self.load_json(&key).await
+    }
+
+    /// Preload a player's state for its next tick (see `preload`).
+    pub async fn preload_player_state(&self, player_uuid: &str) -> Result<bool, StorageError> {
+        self.preload(&self.key_for_player(player_uuid)).await
+    }
+
+    /// Replace a player's state with what `apply` makes of the current one,
+    /// without losing a concurrent write (see [`update_json`](Self::update_json)).
+    pub async fn update_player_state<T, R, F>(
+        &self,
+        player_uuid: &str,
+        apply: F,
+    ) -> Result<(T, R), StorageError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnMut(Option<T>) -> Result<(T, R), StorageError>,
+    {
+        let key = self.key_for_player(player_uuid);
+        self.update_json(&key, apply).await
+    }
+
+    pub async fn load_chunk(&self, cx: i64, cz: i64) -> Result<ChunkSnapshot, StorageError> {
+        let key = self.key_for_chunk(cx, cz);
+        let chunk = self
+            .load_json::<ChunkSnapshot>(&key)
+            .await?
+            .unwrap_or_else(|| ChunkSnapshot::new(cx, cz));
+        Ok(chunk)
+    }
+
+    /// Preload a chunk for the next block update in it (see `preload`).
+    pub async fn preload_chunk(&self, cx: i64, cz: i64) -> Result<bool, StorageError> {
+        self.preload(&self.key_for_chunk(cx, cz)).await
+    }
+
+    /// Change a chunk in place (an empty one if it was never stored) and
+    /// save it, re-applying `apply` to the fresh copy when another write got
+    /// there first.
+    pub async fn update_chunk<R>(
+        &self,
+        cx: i64,
+        cz: i64,
+        mut apply: impl FnMut(&mut ChunkSnapshot) -> R,
+    ) -> Result<(ChunkSnapshot, R), StorageError> {
+        let key = self.key_for_chunk(cx, cz);
+        self.update_json(&key, |chunk| {
+            let mut chunk = chunk.unwrap_or_else(|| ChunkSnapshot::new(cx, cz));
+            let out = apply(&mut chunk);
+            Ok((chunk, out))
+        })
+        .await
+    }
+
+    pub async fn append_block_events(
+        &self,
+        cx: i64,
+        cz: i64,
+        events: &[BlockEvent],
+    ) -> Result<(), StorageError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let key = self.key_for_block_ledger(cx, cz);
+        let mut ledger = self
+            .load_json::<BlockLedger>(&key)
+            .await?
+            .unwrap_or_default();
+        ledger.events.extend_from_slice(events);
+        // Keep a modest window so the ledger does not grow unbounded.
+        const MAX_EVENTS: usize = 512;
+        if ledger.events.len() > MAX_EVENTS {
+            let drop = ledger.events.len() - MAX_EVENTS;
+            ledger.events.drain(0..drop);
+        }
+        self.save_json(&key, &ledger).await?;
+
+        let mut stats = self.load_chunk_stats(cx, cz).await?;
+        for event in events {
+            stats.record(event);
+        }
+        self.save_json(&self.key_for_chunk_stats(cx, cz), &stats)
+            .await
+    }
+
+    /// The recent block events of one chunk; empty if nothing was built.
+    pub async fn load_block_ledger(&self, cx: i64, cz: i64) -> Result<BlockLedger, StorageError> {
+        let key = self.key_for_block_ledger(cx, cz);
+        Ok(self
+            .load_json::<BlockLedger>(&key)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Running block statistics of one chunk; empty if nothing was built.
+    pub async fn load_chunk_stats(&self, cx: i64, cz: i64) -> Result<ChunkStats, StorageError> {
+        let key = self.key_for_chunk_stats(cx, cz);
+        Ok(self
+            .load_json::<ChunkStats>(&key)
+            .await?
+            .unwrap_or_else(|| ChunkStats::new(cx, cz)))
+    }
+
+    /// Who may build in one chunk; an unclaimed list if none was ever set.
+    pub async fn load_chunk_acl(&self, cx: i64, cz: i64) -> Result<ChunkAcl, StorageError> {
+        let key = self.key_for_chunk_acl(cx, cz);
+        Ok(self
+            .load_json::<ChunkAcl>(&key)
+            .await?
+            .unwrap_or_else(|| ChunkAcl::new(cx, cz)))
+    }
+
+    /// Change a chunk's access list like [`update_json`](Self::update_json)
+    /// changes any object, except that an error from `apply` leaves the
+    /// stored list as it was.
+    pub async fn update_chunk_acl<E: From<StorageError>>(
+        &self,
+        cx: i64,
+        cz: i64,
+        mut apply: impl FnMut(&mut ChunkAcl) -> Result<(), E>,
+    ) -> Result<ChunkAcl, E> {
+        let key = self.key_for_chunk_acl(cx, cz);
+        let (acl, ()) = self
+            .update_json(&key, |acl| {
+                let mut acl = acl.unwrap_or_else(|| ChunkAcl::new(cx, cz));
+                apply(&mut acl)?;
+                Ok::<_, E>((acl, ()))
+            })
+            .await?;
+        Ok(acl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[tokio::test]
+    async fn updates_reapply_after_losing_a_race() {
+        let store = Arc::new(MemoryStore::default());
+        let storage = OmegaStorage::local(store.clone());
+        assert_eq!(storage.store_name(), "memory");
+        assert!(storage.breakers().is_empty());
+
+        let key = "sim;counter.json";
+        storage.save_json(key, &1u32).await.unwrap();
+        let mut calls = 0;
+        let (value, ()) = storage
+            .update_json(key, |n: Option<u32>| {
+                calls += 1;
+                if calls == 1 {
+                    // Another tick saves between this load and its save.
+                    let raced = store.save(key, b"10".to_vec(), None);
+                    raced.now_or_never().unwrap().unwrap();
+                }
+                Ok::<_, StorageError>((n.unwrap_or_default() + 1, ()))
+            })
+            .await
+            .unwrap();
+        assert_eq!((value, calls), (11, 2));
+        assert_eq!(storage.load_json::<u32>(key).await.unwrap(), Some(11));
+    }
+
+    #[tokio::test]
+    async fn stale_saves_conflict_and_updates_give_up_after_their_retries() {
+        let store = Arc::new(MemoryStore::default());
+        let storage = OmegaStorage::local(store.clone());
+        let key = "sim;counter.json";
+        let (_, none_yet) = storage.load_json_versioned::<u32>(key).await.unwrap();
+        storage.save_json_if(key, &1u32, none_yet).await.unwrap();
+        assert!(matches!(
+            storage.save_json_if(key, &2u32, none_yet).await,
+            Err(StorageError::Conflict(_))
+        ));
+        assert_eq!(storage.load_json::<u32>(key).await.unwrap(), Some(1));
+
+        // Another tick wins every race on the chunk.
+        let chunk_key = storage.key_for_chunk(0, 0);
+        let theirs = encode(&chunk_key, &ChunkSnapshot::new(0, 0)).unwrap();
+        let mut calls = 0;
+        let lost = storage
+            .update_chunk(0, 0, |_| {
+                calls += 1;
+                let raced = store.save(&chunk_key, theirs.clone(), None);
+                raced.now_or_never().unwrap().unwrap();
+            })
+            .await;
+        assert!(matches!(lost, Err(StorageError::Conflict(_))));
+        assert_eq!(calls, CONFLICT_RETRIES + 1);
+    }
+
+    #[tokio::test]
+    async fn copies_only_land_over_what_their_write_replaced() {
+        let near = Arc::new(MemoryStore::default());
+        let far = Arc::new(MemoryStore::default());
+        let storage = OmegaStorage::regional(vec![
+            ("near".into(), near.clone() as Arc<dyn ObjectStore>),
+            ("far".into(), far.clone() as Arc<dyn ObjectStore>),
+        ]);
+        let key = "sim;counter.json";
+        let bump = |n: Option<u32>| Ok::<_, StorageError>((n.unwrap_or_default() + 1, ()));
+        storage.update_json(key, bump).await.unwrap();
+        storage.flush_replication().await;
+        assert_eq!(far.load(key).await.unwrap().unwrap(), b"1");
+
+        // An instance in the far region writes there; the near one's next
+        // write was based on the old copy and must not replace it.
+        far.save(key, b"7".to_vec(), None).await.unwrap();
+        storage.update_json(key, bump).await.unwrap();
+        storage.flush_replication().await;
+        assert_eq!(near.load(key).await.unwrap().unwrap(), b"2");
+        assert_eq!(far.load(key).await.unwrap().unwrap(), b"7");
+        let lag = &storage.replication()["far"];
+        assert_eq!((lag.replicated, lag.conflicts), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn preloaded_copies_are_taken_once_and_never_lose_writes() {
+        let store = Arc::new(MemoryStore::default());
+        let storage = OmegaStorage::local(store.clone());
+        let key = "sim;counter.json";
+        storage.save_json(key, &1u32).await.unwrap();
+        assert!(storage.preload(key).await.unwrap());
+        assert_eq!(storage.preloaded(), 1);
+
+        // Another instance writes after the preload: the stale copy costs a
+        // reload, not the write.
+        store.save(key, b"5".to_vec(), None).await.unwrap();
+        let mut seen = Vec::new();
+        let (value, ()) = storage
+            .update_json(key, |n: Option<u32>| {
+                seen.push(n);
+                Ok::<_, StorageError>((n.unwrap_or_default() + 1, ()))
+            })
+            .await
+            .unwrap();
+        assert_eq!((value, seen), (6, vec![Some(1), Some(5)]));
+        assert_eq!(storage.preloaded(), 0);
+
+        // Writing an object drops its preloaded copy.
+        assert!(!storage.preload("sim;absent.json").await.unwrap());
+        storage.save_json("sim;absent.json", &2u32).await.unwrap();
+        assert_eq!(storage.preloaded(), 0);
+    }
+}
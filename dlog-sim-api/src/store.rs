@@ -0,0 +1,367 @@
+//! Where the sim's objects live.
+//!
+//! [`OmegaStorage`](crate::storage::OmegaStorage) keys, versions and
+//! replicates JSON objects; an [`ObjectStore`] only keeps their bytes. Three
+//! stores exist, picked by `OMEGA_STORE`:
+//!
+//! - `gcs` (the default): a GCS bucket per region, see `gcs`.
+//! - `fs`: one file per object under `OMEGA_STORE_DIR` (default
+//!   `omega-store`), so a sim can run offline and keep its world.
+//! - `memory`: a map that is gone when the process exits, for tests and
+//!   throwaway runs.
+//!
+//! Every store keeps a generation per object and honours the preconditions
+//! conditional saves send, so ticks racing on one chunk behave the same on
+//! all three.
+
+use corelib::{shaless_digest, shaless_digest_file};
+use dlog_error::StorageError;
+use dlog_resilience::BreakerSnapshot;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, StorageError>> + Send + 'a>>;
+
+/// Directory of the `fs` store unless `OMEGA_STORE_DIR` says otherwise.
+pub const DEFAULT_STORE_DIR: &str = "omega-store";
+
+/// An object's bytes as one generation of it had them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stored {
+    pub bytes: Vec<u8>,
+    /// Never 0, which stands for "no object" in preconditions.
+    pub generation: i64,
+}
+
+/// One bucket's worth of objects.
+pub trait ObjectStore: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+    /// The object at `key`, if there is one.
+    fn load<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>>;
+    /// [`load`](Self::load) with the generation the bytes belong to.
+    fn load_versioned<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Stored>>;
+    /// Write `bytes` to `key`. With `if_generation` only over that
+    /// generation (0: only where there is no object); anything else fails
+    /// with `StorageError::Conflict`.
+    fn save<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Vec<u8>,
+        if_generation: Option<i64>,
+    ) -> StoreFuture<'a, ()>;
+    /// Delete `key`; a missing object is not an error.
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()>;
+    /// Every key starting with `prefix`.
+    fn list<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<String>>;
+    /// sha-less digest of the object at `key`, streamed where the store can.
+    fn digest<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<String>>;
+    /// Breaker the store's calls go through, if any.
+    fn breaker(&self) -> Option<BreakerSnapshot> {
+        None
+    }
+}
+
+fn check_generation(key: &str, current: i64, wanted: Option<i64>) -> Result<(), StorageError> {
+    match wanted {
+        Some(wanted) if wanted != current => Err(StorageError::Conflict(key.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Objects in a map, lost on exit.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<BTreeMap<String, Stored>>,
+    /// Last generation handed out.
+    generations: Mutex<i64>,
+}
+
+impl MemoryStore {
+    fn get(&self, key: &str) -> Option<Stored> {
+        self.objects.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(
+        &self,
+        key: &str,
+        bytes: Vec<u8>,
+        if_generation: Option<i64>,
+    ) -> Result<(), StorageError> {
+        let mut objects = self.objects.lock().unwrap();
+        let current = objects.get(key).map_or(0, |stored| stored.generation);
+        check_generation(key, current, if_generation)?;
+        let mut last = self.generations.lock().unwrap();
+        *last += 1;
+        let generation = *last;
+        objects.insert(key.to_string(), Stored { bytes, generation });
+        Ok(())
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn load<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move { Ok(self.get(key).map(|stored| stored.bytes)) })
+    }
+
+    fn load_versioned<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Stored>> {
+        Box::pin(async move { Ok(self.get(key)) })
+    }
+
+    fn save<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Vec<u8>,
+        if_generation: Option<i64>,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move { self.put(key, bytes, if_generation) })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            self.objects.lock().unwrap().remove(key);
+            Ok(())
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let objects = self.objects.lock().unwrap();
+            Ok(objects
+                .range(prefix.to_string()..)
+                .map(|(key, _)| key)
+                .take_while(|key| key.starts_with(prefix))
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn digest<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<String>> {
+        Box::pin(async move { Ok(self.get(key).map(|stored| shaless_digest(&stored.bytes))) })
+    }
+}
+
+/// One file per object in a directory. Keys become file names with `/`,
+/// `\` and `%` escaped as `%XX`, so no key reaches outside the directory.
+/// A file's generation is derived from its contents; writes go through a
+/// temporary file and a rename, one at a time per process.
+#[derive(Debug)]
+pub struct FsStore {
+    dir: PathBuf,
+    writes: tokio::sync::Mutex<()>,
+}
+
+/// File name for `key`.
+fn file_name(key: &str) -> String {
+    let mut name = String::with_capacity(key.len());
+    for c in key.chars() {
+        match c {
+            '/' | '\\' | '%' | '\0' => name.push_str(&format!("%{:02X}", c as u32)),
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// Key of a file name written by [`file_name`].
+fn key_of(name: &str) -> Option<String> {
+    let mut key = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            let hex: String = chars.by_ref().take(2).collect();
+            key.push(char::from(u8::from_str_radix(&hex, 16).ok()?));
+        } else {
+            key.push(c);
+        }
+    }
+    Some(key)
+}
+
+/// Generation of a file holding `bytes`. Equal contents share one, which
+/// is all a precondition needs: a save over the bytes it loaded loses
+/// nothing, however often they changed in between.
+pub(crate) fn content_generation(bytes: &[u8]) -> i64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    (hasher.finish() >> 1).max(1) as i64
+}
+
+fn io_error(key: &str, err: io::Error) -> StorageError {
+    StorageError::Io(io::Error::new(err.kind(), format!("{key}: {err}")))
+}
+
+impl FsStore {
+    /// Keep objects in `dir`, creating it if needed.
+    pub async fn open(dir: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(&dir).await?;
+        Ok(Self {
+            dir,
+            writes: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(file_name(key))
+    }
+
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match tokio::fs::read(self.path(key)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(io_error(key, err)),
+        }
+    }
+}
+
+impl ObjectStore for FsStore {
+    fn name(&self) -> &'static str {
+        "fs"
+    }
+
+    fn load<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(self.read(key))
+    }
+
+    fn load_versioned<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Stored>> {
+        Box::pin(async move {
+            Ok(self.read(key).await?.map(|bytes| Stored {
+                generation: content_generation(&bytes),
+                bytes,
+            }))
+        })
+    }
+
+    fn save<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Vec<u8>,
+        if_generation: Option<i64>,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let _one_at_a_time = self.writes.lock().await;
+            if if_generation.is_some() {
+                let current = self
+                    .read(key)
+                    .await?
+                    .map_or(0, |bytes| content_generation(&bytes));
+                check_generation(key, current, if_generation)?;
+            }
+            let path = self.path(key);
+            let tmp = path.with_file_name(format!(".{}.tmp", file_name(key)));
+            tokio::fs::write(&tmp, &bytes)
+                .await
+                .map_err(|err| io_error(key, err))?;
+            tokio::fs::rename(&tmp, &path)
+                .await
+                .map_err(|err| io_error(key, err))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let _one_at_a_time = self.writes.lock().await;
+            match tokio::fs::remove_file(self.path(key)).await {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(io_error(key, err)),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let mut keys = Vec::new();
+            let mut entries = tokio::fs::read_dir(&self.dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name();
+                let Some(key) = name
+                    .to_str()
+                    .filter(|n| !n.starts_with('.'))
+                    .and_then(key_of)
+                else {
+                    continue;
+                };
+                if key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+            keys.sort();
+            Ok(keys)
+        })
+    }
+
+    fn digest<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<String>> {
+        Box::pin(async move {
+            match shaless_digest_file(self.path(key)).await {
+                Ok(digest) => Ok(Some(digest)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(io_error(key, err)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn behaves_like_a_bucket(store: &dyn ObjectStore) {
+        let key = "sim;players;a/../b;state.json";
+        assert_eq!(store.load(key).await.unwrap(), None);
+        assert_eq!(store.digest(key).await.unwrap(), None);
+
+        // 0 only creates; a stale generation is refused.
+        store.save(key, b"1".to_vec(), Some(0)).await.unwrap();
+        let first = store.load_versioned(key).await.unwrap().unwrap();
+        assert!(first.generation != 0 && first.bytes == b"1");
+        assert!(matches!(
+            store.save(key, b"x".to_vec(), Some(0)).await,
+            Err(StorageError::Conflict(_))
+        ));
+        store
+            .save(key, b"2".to_vec(), Some(first.generation))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.save(key, b"3".to_vec(), Some(first.generation)).await,
+            Err(StorageError::Conflict(_))
+        ));
+        store.save(key, b"4".to_vec(), None).await.unwrap();
+        assert_eq!(store.load(key).await.unwrap().unwrap(), b"4");
+        assert_eq!(store.digest(key).await.unwrap(), Some(shaless_digest(b"4")));
+
+        store.save("sim;other", b"5".to_vec(), None).await.unwrap();
+        store.save("world;x", b"6".to_vec(), None).await.unwrap();
+        assert_eq!(
+            store.list("sim;").await.unwrap(),
+            ["sim;other", "sim;players;a/../b;state.json"]
+        );
+        store.delete(key).await.unwrap();
+        store.delete(key).await.unwrap();
+        assert_eq!(store.list("sim;").await.unwrap(), ["sim;other"]);
+    }
+
+    #[tokio::test]
+    async fn memory_and_files_keep_objects_alike() {
+        behaves_like_a_bucket(&MemoryStore::default()).await;
+
+        let dir = std::env::temp_dir().join(format!("omega-store-{}", std::process::id()));
+        let fs = FsStore::open(&dir).await.unwrap();
+        behaves_like_a_bucket(&fs).await;
+        // Keys stay inside the directory.
+        assert!(dir.join("sim;other").exists());
+        assert_eq!(key_of(&file_name("a/b%c")).as_deref(), Some("a/b%c"));
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
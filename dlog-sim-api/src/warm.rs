@@ -0,0 +1,312 @@
+//! Warm standby: preloading what the first ticks will read.
+//!
+//! A fresh instance reads each player's state and each chunk from storage
+//! on the first tick that needs it, so the first ticks after a cold start
+//! are slow. Ticks in the live world note their player and the chunks
+//! their block updates touch, and every `OMEGA_WARM_SAVE_SECS` (default
+//! 30, `0` off) that activity is merged into [`WARM_KEY`]: the most
+//! recently active [`WARM_PLAYERS`] players and [`WARM_CHUNKS`] chunks
+//! across all instances. At boot, before it listens, an instance preloads
+//! those (see `OmegaStorage::preload_player_state`), giving up after
+//! `OMEGA_WARMUP_SECS` (default 10, `0` skips it). `POST /warmup` does it
+//! again.
+
+use crate::storage::OmegaStorage;
+use dlog_error::StorageError;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Where the recently active players and chunks are kept.
+pub const WARM_KEY: &str = "sim;warm.json";
+/// Players a warmup preloads.
+pub const WARM_PLAYERS: usize = 256;
+/// Chunks a warmup preloads.
+pub const WARM_CHUNKS: usize = 1024;
+/// Objects loaded at once during a warmup.
+const CONCURRENCY: usize = 32;
+const DEFAULT_SAVE_SECS: u64 = 30;
+const DEFAULT_WARMUP_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmPlayer {
+    pub player_uuid: String,
+    pub last_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmChunk {
+    pub cx: i64,
+    pub cz: i64,
+    pub last_ms: u64,
+}
+
+/// What to preload, most recently active first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmSet {
+    pub players: Vec<WarmPlayer>,
+    pub chunks: Vec<WarmChunk>,
+}
+
+impl WarmSet {
+    /// Fold `activity` in, keeping each player's and chunk's latest time
+    /// and only the most recent of each.
+    fn merge(&mut self, activity: Activity) {
+        let mut players: HashMap<String, u64> = activity.players;
+        for player in self.players.drain(..) {
+            let last = players.entry(player.player_uuid).or_default();
+            *last = (*last).max(player.last_ms);
+        }
+        let mut chunks: HashMap<(i64, i64), u64> = activity.chunks;
+        for chunk in self.chunks.drain(..) {
+            let last = chunks.entry((chunk.cx, chunk.cz)).or_default();
+            *last = (*last).max(chunk.last_ms);
+        }
+        self.players = players
+            .into_iter()
+            .map(|(player_uuid, last_ms)| WarmPlayer {
+                player_uuid,
+                last_ms,
+            })
+            .collect();
+        // Ties by key, so every instance keeps the same ones.
+        self.players
+            .sort_by(|a, b| (b.last_ms, &a.player_uuid).cmp(&(a.last_ms, &b.player_uuid)));
+        self.players.truncate(WARM_PLAYERS);
+        self.chunks = chunks
+            .into_iter()
+            .map(|((cx, cz), last_ms)| WarmChunk { cx, cz, last_ms })
+            .collect();
+        self.chunks
+            .sort_by(|a, b| (b.last_ms, a.cx, a.cz).cmp(&(a.last_ms, b.cx, b.cz)));
+        self.chunks.truncate(WARM_CHUNKS);
+    }
+}
+
+/// Activity since the last save.
+#[derive(Debug, Clone, Default)]
+struct Activity {
+    players: HashMap<String, u64>,
+    chunks: HashMap<(i64, i64), u64>,
+}
+
+/// One object a warmup preloads.
+enum Target {
+    Player(String),
+    Chunk(i64, i64),
+}
+
+/// What a warmup preloaded.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WarmupReport {
+    pub players: usize,
+    pub chunks: usize,
+    /// Listed but never stored, preloaded as absent.
+    pub missing: usize,
+    pub failed: usize,
+    /// Left out when the deadline passed.
+    pub skipped: usize,
+    pub took_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct Warmer {
+    activity: Mutex<Activity>,
+    deadline: Option<Duration>,
+    latest: Mutex<Option<WarmupReport>>,
+}
+
+impl Warmer {
+    pub fn new(deadline: Option<Duration>) -> Self {
+        Self {
+            activity: Mutex::default(),
+            deadline,
+            latest: Mutex::default(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(secs_from_env("OMEGA_WARMUP_SECS", DEFAULT_WARMUP_SECS))
+    }
+
+    pub fn save_interval_from_env() -> Option<Duration> {
+        secs_from_env("OMEGA_WARM_SAVE_SECS", DEFAULT_SAVE_SECS)
+    }
+
+    /// `player_uuid` ticked at `at_ms`, building in `chunks`.
+    pub fn touch(
+        &self,
+        player_uuid: &str,
+        chunks: impl IntoIterator<Item = (i64, i64)>,
+        at_ms: u64,
+    ) {
+        let mut activity = self.activity.lock().expect("warm activity mutex poisoned");
+        activity.players.insert(player_uuid.to_string(), at_ms);
+        for chunk in chunks {
+            activity.chunks.insert(chunk, at_ms);
+        }
+    }
+
+    /// Merge the activity since the last save into [`WARM_KEY`]. Nothing
+    /// is written without any; on failure the activity is dropped.
+    pub async fn save(&self, storage: &OmegaStorage) -> Result<(), StorageError> {
+        let activity =
+            std::mem::take(&mut *self.activity.lock().expect("warm activity mutex poisoned"));
+        if activity.players.is_empty() && activity.chunks.is_empty() {
+            return Ok(());
+        }
+        storage
+            .update_json(WARM_KEY, |set: Option<WarmSet>| {
+                let mut set = set.unwrap_or_default();
+                set.merge(activity.clone());
+                Ok::<_, StorageError>((set, ()))
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Preload the players and chunks in [`WARM_KEY`], most recent first,
+    /// until the deadline.
+    pub async fn warm_up(&self, storage: &OmegaStorage) -> Result<WarmupReport, StorageError> {
+        let started = Instant::now();
+        let set: WarmSet = storage.load_json(WARM_KEY).await?.unwrap_or_default();
+        let mut report = WarmupReport {
+            skipped: set.players.len() + set.chunks.len(),
+            ..WarmupReport::default()
+        };
+        let targets = set
+            .players
+            .into_iter()
+            .map(|player| Target::Player(player.player_uuid))
+            .chain(
+                set.chunks
+                    .into_iter()
+                    .map(|chunk| Target::Chunk(chunk.cx, chunk.cz)),
+            );
+        let mut loads = stream::iter(targets)
+            .map(|target| async move {
+                let loaded = match &target {
+                    Target::Player(uuid) => storage.preload_player_state(uuid).await,
+                    Target::Chunk(cx, cz) => storage.preload_chunk(*cx, *cz).await,
+                };
+                (target, loaded)
+            })
+            .buffer_unordered(CONCURRENCY);
+        let deadline = self.deadline.map(|after| started + after);
+        loop {
+            let next = match deadline {
+                Some(at) => match tokio::time::timeout_at(at.into(), loads.next()).await {
+                    Ok(next) => next,
+                    Err(_) => break,
+                },
+                None => loads.next().await,
+            };
+            let Some((target, loaded)) = next else {
+                break;
+            };
+            report.skipped -= 1;
+            match (target, loaded) {
+                (Target::Player(_), Ok(true)) => report.players += 1,
+                (Target::Chunk(..), Ok(true)) => report.chunks += 1,
+                (_, Ok(false)) => report.missing += 1,
+                (_, Err(err)) => {
+                    warn!("[warm] preload failed: {err}");
+                    report.failed += 1;
+                }
+            }
+        }
+        report.took_ms = started.elapsed().as_millis() as u64;
+        *self.latest.lock().expect("warm report mutex poisoned") = Some(report.clone());
+        Ok(report)
+    }
+
+    pub fn latest(&self) -> Option<WarmupReport> {
+        self.latest
+            .lock()
+            .expect("warm report mutex poisoned")
+            .clone()
+    }
+}
+
+fn secs_from_env(name: &str, default: u64) -> Option<Duration> {
+    let secs = env::var(name)
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .unwrap_or(default);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Save the activity every `every`.
+pub fn spawn_saves(storage: OmegaStorage, warmer: Arc<Warmer>, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = warmer.save(&storage).await {
+                warn!("[warm] saving recent activity failed: {}", err);
+            }
+        }
+    });
+}
+
+/// Boot warmup, logged rather than fatal: a cold instance still works.
+pub async fn warm_up_at_boot(storage: &OmegaStorage, warmer: &Warmer) {
+    if warmer.deadline.is_none() {
+        info!("[warm] OMEGA_WARMUP_SECS=0; starting cold");
+        return;
+    }
+    match warmer.warm_up(storage).await {
+        Ok(report) => info!(
+            "[warm] preloaded {} players and {} chunks in {} ms ({} missing, {} failed, {} skipped)",
+            report.players, report.chunks, report.took_ms, report.missing, report.failed, report.skipped
+        ),
+        Err(err) => warn!("[warm] warmup failed, starting cold: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[tokio::test]
+    async fn activity_is_saved_and_preloaded_most_recent_first() {
+        let storage = OmegaStorage::local(Arc::new(MemoryStore::default()));
+        storage
+            .update_player_state("a", |_: Option<serde_json::Value>| {
+                Ok((serde_json::json!({ "universe_tick": 3 }), ()))
+            })
+            .await
+            .unwrap();
+
+        let warmer = Warmer::new(Some(Duration::from_secs(5)));
+        warmer.touch("a", [(0, 0), (1, 0)], 10);
+        warmer.touch("b", [], 20);
+        warmer.save(&storage).await.unwrap();
+        // Another instance's older activity merges in behind.
+        let other = Warmer::new(None);
+        other.touch("a", [(0, 0)], 5);
+        other.touch("c", [(2, 2)], 1);
+        other.save(&storage).await.unwrap();
+
+        let set: WarmSet = storage.load_json(WARM_KEY).await.unwrap().unwrap();
+        let players: Vec<_> = set
+            .players
+            .iter()
+            .map(|p| (p.player_uuid.as_str(), p.last_ms))
+            .collect();
+        assert_eq!(players, [("b", 20), ("a", 10), ("c", 1)]);
+        assert_eq!(set.chunks.len(), 3);
+        assert_eq!((set.chunks[2].cx, set.chunks[2].last_ms), (2, 1));
+
+        let report = warmer.warm_up(&storage).await.unwrap();
+        assert_eq!((report.players, report.missing, report.failed), (1, 5, 0));
+        assert_eq!(report.skipped, 0);
+        assert_eq!(storage.preloaded(), 6);
+        assert_eq!(warmer.latest(), Some(report));
+    }
+}
@@ -0,0 +1,143 @@
+//! Deterministic world hash for checking that sim instances agree.
+//!
+//! Chunks, player states and the gateway's bank roots are each reduced to a
+//! canonical form (sorted keys, sorted blocks, floats by bit pattern) and
+//! digested with the sha-less hash. The per-scope digests fold into one root,
+//! so two instances compare roots first and scopes next; a chunk or player
+//! scope also lists per-entry digests to pin down exactly what diverged.
+
+use crate::components::Component;
+use crate::model::ChunkSnapshot;
+use crate::physics::Physics;
+use crate::sim::PlayerState;
+use corelib::shaless_json_digest;
+use dlog_error::StorageError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashScope {
+    #[default]
+    All,
+    Chunks,
+    Players,
+    Bank,
+}
+
+impl HashScope {
+    fn includes(self, scope: HashScope) -> bool {
+        self == HashScope::All || self == scope
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorldHash {
+    pub scope: HashScope,
+    /// Digest of `scopes`.
+    pub root: String,
+    /// One digest per scope covered. `bank` is absent when no gateway is configured.
+    pub scopes: BTreeMap<&'static str, String>,
+    /// Per-chunk (`cx;cz`) or per-player digests; empty for `all` and `bank`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub entries: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct WorldHasher {
+    chunks: BTreeMap<String, String>,
+    players: BTreeMap<String, String>,
+    /// Planet → bank root as reported by the gateway.
+    bank: Option<BTreeMap<String, String>>,
+}
+
+impl WorldHasher {
+    pub fn chunk(&mut self, chunk: &ChunkSnapshot) {
+        let mut blocks: Vec<_> = chunk
+            .blocks()
+            .map(|b| (b.x, b.y, b.z, b.block, b.last_tick))
+            .collect();
+        blocks.sort_unstable();
+        let canonical = serde_json::json!({ "version": chunk.version, "blocks": blocks });
+        self.chunks
+            .insert(format!("{};{}", chunk.cx, chunk.cz), digest(&canonical));
+    }
+
+    /// Players with only a position hash as they did before components, so
+    /// nodes on either side of that change still agree.
+    pub fn player(&mut self, uuid: &str, state: &PlayerState) -> Result<(), StorageError> {
+        let position: Physics = state.components.get_or_default()?;
+        let mut canonical = vec![
+            serde_json::json!(state.universe_tick),
+            serde_json::json!(position.x.to_bits()),
+            serde_json::json!(position.y.to_bits()),
+            serde_json::json!(position.z.to_bits()),
+        ];
+        let others = state.components.others(&[Physics::NAME]);
+        if !others.is_empty() {
+            canonical.push(serde_json::json!(others));
+        }
+        self.players.insert(uuid.to_string(), digest(&canonical));
+        Ok(())
+    }
+
+    pub fn bank(&mut self, roots: BTreeMap<String, String>) {
+        self.bank = Some(roots);
+    }
+
+    pub fn finish(self, scope: HashScope) -> WorldHash {
+        let mut scopes = BTreeMap::new();
+        let mut entries = BTreeMap::new();
+        if scope.includes(HashScope::Chunks) {
+            scopes.insert("chunks", digest(&self.chunks));
+            if scope == HashScope::Chunks {
+                entries = self.chunks;
+            }
+        }
+        if scope.includes(HashScope::Players) {
+            scopes.insert("players", digest(&self.players));
+            if scope == HashScope::Players {
+                entries = self.players;
+            }
+        }
+        if let Some(bank) = self.bank.filter(|_| scope.includes(HashScope::Bank)) {
+            scopes.insert("bank", digest(&bank));
+        }
+        WorldHash {
+            scope,
+            root: digest(&scopes),
+            scopes,
+            entries,
+        }
+    }
+}
+
+fn digest<T: Serialize>(value: &T) -> String {
+    shaless_json_digest(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_order_does_not_change_the_hash() {
+        let hash = |blocks: &[(i64, &str)]| {
+            let mut chunk = ChunkSnapshot::new(0, 0);
+            chunk.version = 2;
+            for &(x, block) in blocks {
+                chunk.set_block(x, 64, 0, block, 7);
+            }
+            let mut hasher = WorldHasher::default();
+            hasher.chunk(&chunk);
+            hasher.finish(HashScope::Chunks)
+        };
+        let a = hash(&[(1, "stone"), (2, "dirt")]);
+        let b = hash(&[(2, "dirt"), (1, "stone")]);
+        assert_eq!(a.root, b.root);
+        assert_eq!(a.entries.keys().collect::<Vec<_>>(), ["0;0"]);
+
+        let c = hash(&[(1, "stone"), (2, "grass")]);
+        assert_ne!(a.root, c.root);
+    }
+}
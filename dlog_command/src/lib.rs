@@ -0,0 +1,694 @@
+//! Slash-command grammar shared by the gateway (`POST /omega/command`) and
+//! the sim (`POST /v1/sim/command`).
+//!
+//! A command is a name, typed positional arguments, `--flags` and an
+//! optional permission tag, declared with [`command!`] and collected in a
+//! [`Registry`]. Lines tokenize like a shell: whitespace splits, `"…"` and
+//! `'…'` quote, `\` escapes, a leading `/` is optional and `--` ends the
+//! flags. Flags are written `--name value` or `--name=value`; dashes and
+//! underscores in flag names are interchangeable. Each service maps
+//! permission tags onto its own notion of access (session capabilities on
+//! the gateway, debug mode on the sim).
+//!
+//! Errors are one short line meant for the HUD title, and
+//! [`Registry::reject`] adds the usage of the command involved to the
+//! hotbar, so a plugin can show a failed command without knowing the grammar.
+
+use serde::Serialize;
+use spec::UiOverlay;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Hotbar slots a reply fills at most.
+pub const HOTBAR_SLOTS: usize = 9;
+
+/// `/help [command]`, which every registry is expected to carry.
+pub const HELP: CommandSpec = command! {
+    "help" => "List the commands you can run",
+    optional: [command: Word],
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Word,
+    Int,
+    /// Whole number ≥ 0.
+    Uint,
+    Number,
+    Bool,
+    /// Everything left on the line; only valid as the last argument.
+    Rest,
+    /// A flag that takes no value.
+    Switch,
+}
+
+impl ArgKind {
+    fn placeholder(self) -> &'static str {
+        match self {
+            ArgKind::Word | ArgKind::Rest => "text",
+            ArgKind::Int => "int",
+            ArgKind::Uint => "n",
+            ArgKind::Number => "number",
+            ArgKind::Bool | ArgKind::Switch => "true|false",
+        }
+    }
+
+    fn expected(self) -> &'static str {
+        match self {
+            ArgKind::Word | ArgKind::Rest => "text",
+            ArgKind::Int => "a whole number",
+            ArgKind::Uint => "a whole number ≥ 0",
+            ArgKind::Number => "a number",
+            ArgKind::Bool | ArgKind::Switch => "true or false",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagSpec {
+    /// Written `--name`, with `_` shown as `-`.
+    pub name: &'static str,
+    pub kind: ArgKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub about: &'static str,
+    /// Tag the caller must hold; `None` lets anyone run it.
+    pub permission: Option<&'static str>,
+    /// Required arguments first, then optional ones.
+    pub args: &'static [ArgSpec],
+    pub flags: &'static [FlagSpec],
+}
+
+impl CommandSpec {
+    /// `/quote <amount> <to> [--from <text>]`
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in self.args {
+            let dots = if arg.kind == ArgKind::Rest { "…" } else { "" };
+            if arg.required {
+                usage.push_str(&format!(" <{}{dots}>", arg.name));
+            } else {
+                usage.push_str(&format!(" [{}{dots}]", arg.name));
+            }
+        }
+        for flag in self.flags {
+            let name = flag.name.replace('_', "-");
+            if flag.kind == ArgKind::Switch {
+                usage.push_str(&format!(" [--{name}]"));
+            } else {
+                usage.push_str(&format!(" [--{name} <{}>]", flag.kind.placeholder()));
+            }
+        }
+        usage
+    }
+}
+
+/// Declare a [`CommandSpec`] as a constant:
+///
+/// ```
+/// use dlog_command::{command, CommandSpec};
+///
+/// const STEP: CommandSpec = command! {
+///     "step" => "Let ticks through while paused",
+///     perm: "debug",
+///     optional: [ticks: Uint],
+///     flags: [quiet: Switch],
+/// };
+/// assert_eq!(STEP.usage(), "/step [ticks] [--quiet]");
+/// ```
+///
+/// Sections are optional but keep this order: `perm`, `args` (required),
+/// `optional`, `flags`. Kinds are [`ArgKind`] variant names.
+#[macro_export]
+macro_rules! command {
+    (@perm) => {
+        None
+    };
+    (@perm $perm:literal) => {
+        Some($perm)
+    };
+    (
+        $name:literal => $about:literal
+        $(, perm: $perm:literal)?
+        $(, args: [$($arg:ident: $arg_kind:ident),* $(,)?])?
+        $(, optional: [$($opt:ident: $opt_kind:ident),* $(,)?])?
+        $(, flags: [$($flag:ident: $flag_kind:ident),* $(,)?])?
+        $(,)?
+    ) => {
+        $crate::CommandSpec {
+            name: $name,
+            about: $about,
+            permission: $crate::command!(@perm $($perm)?),
+            args: &[
+                $($($crate::ArgSpec {
+                    name: stringify!($arg),
+                    kind: $crate::ArgKind::$arg_kind,
+                    required: true,
+                },)*)?
+                $($($crate::ArgSpec {
+                    name: stringify!($opt),
+                    kind: $crate::ArgKind::$opt_kind,
+                    required: false,
+                },)*)?
+            ],
+            flags: &[
+                $($($crate::FlagSpec {
+                    name: stringify!($flag),
+                    kind: $crate::ArgKind::$flag_kind,
+                },)*)?
+            ],
+        }
+    };
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum CommandError {
+    #[error("type a command, or /help")]
+    Empty,
+    #[error("unknown command /{name}{}", .suggestion.map(|s| format!(", did you mean /{s}?")).unwrap_or_default())]
+    Unknown {
+        name: String,
+        suggestion: Option<&'static str>,
+    },
+    #[error("/{command} needs the {permission} permission")]
+    Forbidden {
+        command: &'static str,
+        permission: &'static str,
+    },
+    #[error("/{command} needs <{arg}>")]
+    MissingArg {
+        command: &'static str,
+        arg: &'static str,
+    },
+    #[error("--{} needs a value", .flag.replace('_', "-"))]
+    MissingFlagValue {
+        command: &'static str,
+        flag: &'static str,
+    },
+    #[error("{name} must be {expected}, not `{got}`")]
+    BadValue {
+        command: &'static str,
+        name: &'static str,
+        expected: &'static str,
+        got: String,
+    },
+    #[error("/{command} has no --{flag}")]
+    UnknownFlag { command: &'static str, flag: String },
+    #[error("/{command} doesn't take `{extra}`")]
+    TooManyArgs {
+        command: &'static str,
+        extra: String,
+    },
+    #[error("unterminated quote")]
+    UnterminatedQuote,
+}
+
+impl CommandError {
+    /// Command the error is about, once the name resolved.
+    pub fn command(&self) -> Option<&'static str> {
+        match self {
+            CommandError::Empty
+            | CommandError::Unknown { .. }
+            | CommandError::UnterminatedQuote => None,
+            CommandError::Forbidden { command, .. }
+            | CommandError::MissingArg { command, .. }
+            | CommandError::MissingFlagValue { command, .. }
+            | CommandError::BadValue { command, .. }
+            | CommandError::UnknownFlag { command, .. }
+            | CommandError::TooManyArgs { command, .. } => Some(command),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Int(i64),
+    Uint(u64),
+    Number(f64),
+    Bool(bool),
+}
+
+/// A parsed line: the command and its arguments and flags by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub command: &'static str,
+    values: BTreeMap<&'static str, Value>,
+}
+
+impl Invocation {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn uint(&self, name: &str) -> Option<u64> {
+        match self.get(name)? {
+            Value::Uint(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Whether switch `name` was given (and not as `--name=false`).
+    pub fn switch(&self, name: &str) -> bool {
+        self.bool(name).unwrap_or(false)
+    }
+}
+
+/// What a command endpoint answers, success or not.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandReply {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<&'static str>,
+    pub output: Vec<String>,
+    pub ui: UiOverlay,
+}
+
+impl CommandReply {
+    /// The first line becomes the HUD title, the next ones the hotbar.
+    pub fn done(command: &'static str, output: Vec<String>) -> Self {
+        Self {
+            ok: true,
+            command: Some(command),
+            ui: overlay(&output),
+            output,
+        }
+    }
+
+    /// A parsed command that could not be carried out.
+    pub fn failed(command: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            ..Self::done(command, vec![message.into()])
+        }
+    }
+}
+
+fn overlay(lines: &[String]) -> UiOverlay {
+    UiOverlay {
+        title: lines.first().cloned().unwrap_or_default(),
+        hotbar: lines.iter().skip(1).take(HOTBAR_SLOTS).cloned().collect(),
+        ..UiOverlay::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Registry {
+    commands: &'static [CommandSpec],
+}
+
+impl Registry {
+    pub const fn new(commands: &'static [CommandSpec]) -> Self {
+        Self { commands }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'static CommandSpec> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
+    /// Parse `line` for a caller holding the permission tags `granted`
+    /// accepts. Permission is checked before arguments, so a caller can't
+    /// learn more than a command's name from one it may not run.
+    pub fn parse(
+        &self,
+        line: &str,
+        granted: impl Fn(&str) -> bool,
+    ) -> Result<Invocation, CommandError> {
+        let mut tokens = tokenize(line)?.into_iter();
+        let head = tokens.next().ok_or(CommandError::Empty)?;
+        let name = head.text.strip_prefix('/').unwrap_or(&head.text);
+        let name = name.to_ascii_lowercase();
+        if name.is_empty() {
+            return Err(CommandError::Empty);
+        }
+        let spec = self.get(&name).ok_or_else(|| CommandError::Unknown {
+            suggestion: self.suggest(&name),
+            name,
+        })?;
+        if let Some(permission) = spec.permission {
+            if !granted(permission) {
+                return Err(CommandError::Forbidden {
+                    command: spec.name,
+                    permission,
+                });
+            }
+        }
+
+        let mut values = BTreeMap::new();
+        let mut positional = Vec::new();
+        let mut flags_done = false;
+        while let Some(token) = tokens.next() {
+            if flags_done || token.quoted {
+                positional.push(token.text);
+                continue;
+            }
+            if token.text == "--" {
+                flags_done = true;
+                continue;
+            }
+            let Some(flag) = token.text.strip_prefix("--") else {
+                positional.push(token.text);
+                continue;
+            };
+            let (key, inline) = match flag.split_once('=') {
+                Some((key, value)) => (key, Some(value.to_string())),
+                None => (flag, None),
+            };
+            let key = key.replace('-', "_");
+            let Some(flag) = spec.flags.iter().find(|f| f.name == key) else {
+                return Err(CommandError::UnknownFlag {
+                    command: spec.name,
+                    flag: key.replace('_', "-"),
+                });
+            };
+            let raw = match (flag.kind, inline) {
+                (_, Some(raw)) => raw,
+                (ArgKind::Switch, None) => "true".to_string(),
+                (_, None) => {
+                    tokens
+                        .next()
+                        .ok_or(CommandError::MissingFlagValue {
+                            command: spec.name,
+                            flag: flag.name,
+                        })?
+                        .text
+                }
+            };
+            let value = parse_value(spec.name, flag.name, flag.kind, raw)?;
+            values.insert(flag.name, value);
+        }
+
+        let mut positional = positional.into_iter();
+        for arg in spec.args {
+            let raw = if arg.kind == ArgKind::Rest {
+                let rest: Vec<String> = positional.by_ref().collect();
+                (!rest.is_empty()).then(|| rest.join(" "))
+            } else {
+                positional.next()
+            };
+            match raw {
+                Some(raw) => {
+                    values.insert(arg.name, parse_value(spec.name, arg.name, arg.kind, raw)?);
+                }
+                None if arg.required => {
+                    return Err(CommandError::MissingArg {
+                        command: spec.name,
+                        arg: arg.name,
+                    })
+                }
+                None => {}
+            }
+        }
+        if let Some(extra) = positional.next() {
+            return Err(CommandError::TooManyArgs {
+                command: spec.name,
+                extra,
+            });
+        }
+        Ok(Invocation {
+            command: spec.name,
+            values,
+        })
+    }
+
+    /// `usage — about` for every command the caller may run, or just
+    /// `topic`'s when given.
+    pub fn help(
+        &self,
+        topic: Option<&str>,
+        granted: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>, CommandError> {
+        let visible = |c: &&CommandSpec| c.permission.is_none_or(&granted);
+        let lines = match topic {
+            Some(topic) => {
+                let name = topic
+                    .strip_prefix('/')
+                    .unwrap_or(topic)
+                    .to_ascii_lowercase();
+                let spec = self.get(&name).ok_or_else(|| CommandError::Unknown {
+                    suggestion: self.suggest(&name),
+                    name,
+                })?;
+                vec![format!("{} — {}", spec.usage(), spec.about)]
+            }
+            None => self
+                .commands
+                .iter()
+                .filter(visible)
+                .map(|c| format!("{} — {}", c.usage(), c.about))
+                .collect(),
+        };
+        Ok(lines)
+    }
+
+    /// Reply for a line that failed to parse: the error as the HUD title and
+    /// the usage of the command involved (or suggested) on the hotbar.
+    pub fn reject(&self, err: &CommandError) -> CommandReply {
+        let about = match err {
+            CommandError::Unknown { suggestion, .. } => *suggestion,
+            _ => err.command(),
+        };
+        let mut lines = vec![err.to_string()];
+        lines.extend(
+            about
+                .and_then(|name| self.get(name))
+                .map(CommandSpec::usage),
+        );
+        CommandReply {
+            ok: false,
+            command: err.command(),
+            ui: overlay(&lines),
+            output: lines,
+        }
+    }
+
+    /// Closest command to a mistyped `name`: one it prefixes, else one at
+    /// most two edits away.
+    fn suggest(&self, name: &str) -> Option<&'static str> {
+        if let Some(spec) = self.commands.iter().find(|c| c.name.starts_with(name)) {
+            return Some(spec.name);
+        }
+        self.commands
+            .iter()
+            .map(|c| (edit_distance(name, c.name), c.name))
+            .filter(|(distance, _)| *distance <= 2)
+            .min()
+            .map(|(_, name)| name)
+    }
+}
+
+fn parse_value(
+    command: &'static str,
+    name: &'static str,
+    kind: ArgKind,
+    raw: String,
+) -> Result<Value, CommandError> {
+    let value = match kind {
+        ArgKind::Word | ArgKind::Rest => Some(Value::Text(raw.clone())),
+        ArgKind::Int => raw.parse().ok().map(Value::Int),
+        ArgKind::Uint => raw.parse().ok().map(Value::Uint),
+        ArgKind::Number => raw
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(Value::Number),
+        ArgKind::Bool | ArgKind::Switch => match raw.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(Value::Bool(true)),
+            "false" | "no" | "off" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    };
+    value.ok_or(CommandError::BadValue {
+        command,
+        name,
+        expected: kind.expected(),
+        got: raw,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct Token {
+    text: String,
+    /// Any part was quoted, so it is never a flag.
+    quoted: bool,
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, CommandError> {
+    let mut tokens = Vec::new();
+    let mut current: Option<Token> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().ok_or(CommandError::UnterminatedQuote)?;
+                start(&mut current).text.push(escaped);
+            }
+            (Some(_), c) => start(&mut current).text.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                start(&mut current).quoted = true;
+            }
+            (None, c) if c.is_whitespace() => tokens.extend(current.take()),
+            (None, c) => start(&mut current).text.push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(CommandError::UnterminatedQuote);
+    }
+    tokens.extend(current);
+    Ok(tokens)
+}
+
+fn start(current: &mut Option<Token>) -> &mut Token {
+    current.get_or_insert_with(|| Token {
+        text: String::new(),
+        quoted: false,
+    })
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: Registry = Registry::new(&[
+        HELP,
+        command! {
+            "quote" => "Price a conversion",
+            args: [amount: Uint, to: Word],
+            flags: [from: Word, dry_run: Switch],
+        },
+        command! {
+            "say" => "Broadcast a line",
+            perm: "admin",
+            args: [message: Rest],
+        },
+    ]);
+
+    #[test]
+    fn lines_parse_into_typed_invocations_or_hud_errors() {
+        let all = |_: &str| true;
+        let quote = REGISTRY
+            .parse(r#"/Quote 250 "mars" --from=moon --dry-run"#, all)
+            .unwrap();
+        assert_eq!(quote.command, "quote");
+        assert_eq!(quote.uint("amount"), Some(250));
+        assert_eq!(quote.text("to"), Some("mars"));
+        assert_eq!(quote.text("from"), Some("moon"));
+        assert!(quote.switch("dry_run"));
+        let say = REGISTRY
+            .parse(r#"say it\'s "--not a flag" -- --raw"#, all)
+            .unwrap();
+        assert_eq!(say.text("message"), Some("it's --not a flag --raw"));
+
+        assert_eq!(REGISTRY.parse("  ", all), Err(CommandError::Empty));
+        assert_eq!(
+            REGISTRY
+                .parse("/qoute 1 mars", all)
+                .unwrap_err()
+                .to_string(),
+            "unknown command /qoute, did you mean /quote?"
+        );
+        assert_eq!(
+            REGISTRY
+                .parse("/say hi", |_| false)
+                .unwrap_err()
+                .to_string(),
+            "/say needs the admin permission"
+        );
+        let bad = REGISTRY.parse("/quote lots mars", all).unwrap_err();
+        assert_eq!(
+            bad.to_string(),
+            "amount must be a whole number ≥ 0, not `lots`"
+        );
+        let reply = REGISTRY.reject(&bad);
+        assert!(!reply.ok);
+        assert_eq!(
+            reply.ui.hotbar,
+            ["/quote <amount> <to> [--from <text>] [--dry-run]"]
+        );
+        assert_eq!(
+            REGISTRY.parse("/quote 1", all),
+            Err(CommandError::MissingArg {
+                command: "quote",
+                arg: "to"
+            })
+        );
+        assert!(matches!(
+            REGISTRY.parse("/quote 1 mars --from", all),
+            Err(CommandError::MissingFlagValue { .. })
+        ));
+        assert!(matches!(
+            REGISTRY.parse("/quote 1 mars --to sun", all),
+            Err(CommandError::UnknownFlag { .. })
+        ));
+        assert!(matches!(
+            REGISTRY.parse("/quote 1 mars sun", all),
+            Err(CommandError::TooManyArgs { .. })
+        ));
+        assert_eq!(
+            REGISTRY.parse("/say \"open", all),
+            Err(CommandError::UnterminatedQuote)
+        );
+
+        assert_eq!(REGISTRY.help(None, |_| false).unwrap().len(), 2);
+        assert_eq!(
+            REGISTRY.help(Some("/say"), all).unwrap(),
+            ["/say <message…> — Broadcast a line"]
+        );
+    }
+}
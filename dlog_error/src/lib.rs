@@ -0,0 +1,244 @@
+//! Typed domain errors shared across the DLOG Ω services.
+//!
+//! Each boundary gets its own enum (storage, bank, auth, frames) so callers
+//! can match on what went wrong instead of parsing strings. [`DlogError`]
+//! wraps them all for handlers that touch several domains. Every error knows
+//! its HTTP status; with the `axum` feature they also become responses
+//! (`status` + plain-text message) and convert into `StatusCode` /
+//! `(StatusCode, String)` so existing handler signatures keep using `?`.
+
+use thiserror::Error;
+
+/// Persistence failures: local `OMEGA_ROOT` files, GCS objects, sim state.
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("storage io: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("storage codec: {0}")]
+    Codec(#[from] serde_json::Error),
+    #[error("{0} not found")]
+    NotFound(String),
+    /// Remote object store rejected or failed the call.
+    #[error("storage backend: {0}")]
+    Backend(String),
+    #[error("storage config: {0}")]
+    Config(String),
+    /// A stored object could not be brought to the current schema.
+    #[error("storage schema: {0}")]
+    Schema(String),
+    /// Someone else wrote the object since it was loaded; reload and
+    /// re-apply the change.
+    #[error("{0} changed since it was loaded")]
+    Conflict(String),
+}
+
+impl StorageError {
+    pub fn status(&self) -> u16 {
+        match self {
+            StorageError::NotFound(_) => 404,
+            StorageError::Backend(_) => 502,
+            StorageError::Conflict(_) => 409,
+            StorageError::Io(_)
+            | StorageError::Codec(_)
+            | StorageError::Config(_)
+            | StorageError::Schema(_) => 500,
+        }
+    }
+}
+
+/// Infinity bank rejections.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BankError {
+    #[error("amount=0")]
+    ZeroAmount,
+    #[error("{label} insufficient: {balance} < {amount}")]
+    InsufficientFunds {
+        label: String,
+        balance: u128,
+        amount: u128,
+    },
+    #[error("unknown alias {0}")]
+    UnknownAlias(String),
+    /// The session's phone doesn't own the label it tried to spend from.
+    #[error("{0} is not owned by this session")]
+    NotOwner(String),
+    /// Memo, tag or client_ref breaks the canon rules.
+    #[error("invalid transfer {0}")]
+    InvalidMetadata(String),
+    /// A cross-zone amount too small to survive the fee and rate.
+    #[error("{0} is too small to convert")]
+    Unconvertible(u128),
+    /// Transfer to an archived label, or (in strict mode) one never created.
+    #[error("{0} is not an open label")]
+    UnknownLabel(String),
+    /// One entry of a batch transfer failed, so none of it was paid.
+    #[error("entry {index}: {reason}")]
+    BatchEntry { index: usize, reason: String },
+    /// A monetary change that can't be scheduled as given.
+    #[error("invalid monetary change: {0}")]
+    InvalidSchedule(String),
+}
+
+impl BankError {
+    pub fn status(&self) -> u16 {
+        match self {
+            BankError::ZeroAmount
+            | BankError::InvalidMetadata(_)
+            | BankError::Unconvertible(_)
+            | BankError::BatchEntry { .. }
+            | BankError::InvalidSchedule(_) => 400,
+            BankError::InsufficientFunds { .. } => 409,
+            BankError::UnknownAlias(_) | BankError::UnknownLabel(_) => 404,
+            BankError::NotOwner(_) => 403,
+        }
+    }
+}
+
+/// Session, phone and admin-token checks.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthError {
+    #[error("phone and session_token required")]
+    MissingCredentials,
+    #[error("verified session required")]
+    Unverified,
+    #[error("admin token required")]
+    AdminRequired,
+    /// Authenticated, but the resource belongs to someone else.
+    #[error("{0}")]
+    Forbidden(String),
+}
+
+impl AuthError {
+    pub fn status(&self) -> u16 {
+        match self {
+            AuthError::MissingCredentials | AuthError::Unverified | AuthError::AdminRequired => 401,
+            AuthError::Forbidden(_) => 403,
+        }
+    }
+}
+
+/// Problems with frames and gateway commands (schedules, DNS imports, aliases).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FrameError {
+    #[error("malformed: {0}")]
+    Malformed(String),
+    #[error("height {height} already sealed (current {current})")]
+    StaleHeight { height: u64, current: u64 },
+    /// Valid request that conflicts with current state.
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0} not found")]
+    NotFound(String),
+}
+
+impl FrameError {
+    pub fn status(&self) -> u16 {
+        match self {
+            FrameError::Malformed(_) => 400,
+            FrameError::StaleHeight { .. } | FrameError::Conflict(_) => 409,
+            FrameError::NotFound(_) => 404,
+        }
+    }
+}
+
+/// Any domain error, for handlers that cross several boundaries.
+#[derive(Debug, Error)]
+pub enum DlogError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error(transparent)]
+    Bank(#[from] BankError),
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    #[error(transparent)]
+    Frame(#[from] FrameError),
+    /// A peer service (presence, Paper) was unreachable or failed.
+    #[error("{0}")]
+    Upstream(String),
+}
+
+impl DlogError {
+    pub fn status(&self) -> u16 {
+        match self {
+            DlogError::Storage(err) => err.status(),
+            DlogError::Bank(err) => err.status(),
+            DlogError::Auth(err) => err.status(),
+            DlogError::Frame(err) => err.status(),
+            DlogError::Upstream(_) => 502,
+        }
+    }
+}
+
+#[cfg(feature = "axum")]
+mod http {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+
+    macro_rules! http_error {
+        ($($ty:ty),*) => {$(
+            impl From<$ty> for StatusCode {
+                fn from(err: $ty) -> Self {
+                    StatusCode::from_u16(err.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+                }
+            }
+
+            impl From<$ty> for (StatusCode, String) {
+                fn from(err: $ty) -> Self {
+                    let message = err.to_string();
+                    (err.into(), message)
+                }
+            }
+
+            impl IntoResponse for $ty {
+                fn into_response(self) -> Response {
+                    <(StatusCode, String)>::from(self).into_response()
+                }
+            }
+        )*};
+    }
+
+    http_error!(StorageError, BankError, AuthError, FrameError, DlogError);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_errors_keep_their_status_through_dlog_error() {
+        let missing: DlogError = StorageError::NotFound("sim;players;p".into()).into();
+        assert_eq!(missing.status(), 404);
+        assert_eq!(missing.to_string(), "sim;players;p not found");
+
+        let io: DlogError = StorageError::from(std::io::Error::other("disk")).into();
+        assert_eq!(io.status(), 500);
+
+        let raced = StorageError::Conflict("world;chunks;0;0.json".into());
+        assert_eq!(
+            (raced.status(), raced.to_string().as_str()),
+            (409, "world;chunks;0;0.json changed since it was loaded")
+        );
+
+        let broke = DlogError::from(BankError::InsufficientFunds {
+            label: ";1;a;".into(),
+            balance: 1,
+            amount: 2,
+        });
+        assert_eq!(
+            (broke.status(), broke.to_string().as_str()),
+            (409, ";1;a; insufficient: 1 < 2")
+        );
+
+        assert_eq!(DlogError::from(AuthError::AdminRequired).status(), 401);
+        assert_eq!(
+            DlogError::from(FrameError::StaleHeight {
+                height: 1,
+                current: 2
+            })
+            .status(),
+            409
+        );
+        assert_eq!(DlogError::Upstream("presence".into()).status(), 502);
+    }
+}
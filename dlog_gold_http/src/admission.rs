@@ -0,0 +1,250 @@
+//! Handshake admission: smoothing the reconnect wave after an edge restart.
+//!
+//! When an edge comes back, every client it dropped re-handshakes at once.
+//! Handshakes are admitted at `OMEGA_HANDSHAKE_RATE` per second (default
+//! 200) with bursts of up to `OMEGA_HANDSHAKE_BURST` (default the rate);
+//! `OMEGA_HANDSHAKE_RATE=off` admits everything. Past that a handshake is
+//! deferred with `503`, `Retry-After` and a `retry_after_ms` hint. Deferred
+//! clients are handed consecutive slots at the admission rate, so the wave
+//! comes back spread out instead of all at once again, each plus a jitter
+//! seeded by its `client_id` (the same client always lands on the same
+//! offset). Hints never exceed [`MAX_WAIT_MS`]. While handshakes are being
+//! deferred, admitted ones carry `retry_after_ms` too: how long to wait
+//! before re-handshaking should the session drop. Gateway peers are never
+//! deferred.
+
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// Longest a client is ever told to wait.
+pub const MAX_WAIT_MS: u64 = 30_000;
+const DEFAULT_RATE: u32 = 200;
+/// A client's jitter spans this many admission slots.
+const JITTER_SLOTS: f64 = 4.0;
+/// Handshakes count as overloaded for this long after the last deferral.
+const PRESSURE_MS: i64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AdmissionConfig {
+    pub per_second: u32,
+    pub burst: u32,
+}
+
+impl AdmissionConfig {
+    /// `None` when `OMEGA_HANDSHAKE_RATE` is `off`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let rate = env::var("OMEGA_HANDSHAKE_RATE").ok();
+        let burst = env::var("OMEGA_HANDSHAKE_BURST").ok();
+        Self::parse(rate.as_deref(), burst.as_deref())
+    }
+
+    pub fn parse(rate: Option<&str>, burst: Option<&str>) -> Result<Option<Self>, String> {
+        let count = |name: &str, raw: &str| match raw.trim().parse::<u32>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!("{name} `{raw}` is not a positive count")),
+        };
+        let per_second = match rate.map(str::trim) {
+            None | Some("") => DEFAULT_RATE,
+            Some("off") => return Ok(None),
+            Some(raw) => count("OMEGA_HANDSHAKE_RATE", raw)?,
+        };
+        let burst = match burst.map(str::trim) {
+            None | Some("") => per_second,
+            Some(raw) => count("OMEGA_HANDSHAKE_BURST", raw)?,
+        };
+        Ok(Some(Self { per_second, burst }))
+    }
+
+    /// Time between admission slots.
+    fn interval_ms(&self) -> f64 {
+        1000.0 / self.per_second as f64
+    }
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            per_second: DEFAULT_RATE,
+            burst: DEFAULT_RATE,
+        }
+    }
+}
+
+/// A handshake told to come back later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deferral {
+    pub retry_after_ms: u64,
+}
+
+impl IntoResponse for Deferral {
+    fn into_response(self) -> Response {
+        let retry_after_secs = self.retry_after_ms.div_ceil(1000).max(1);
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            Json(serde_json::json!({
+                "error": "gateway busy admitting handshakes; retry after retry_after_ms",
+                "retry_after_ms": self.retry_after_ms,
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// What `GET /admin/admission` shows.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AdmissionStats {
+    /// `None` when admission is off.
+    pub config: Option<AdmissionConfig>,
+    pub admitted: u64,
+    pub deferred: u64,
+    /// Until the last slot handed out is reached.
+    pub backlog_ms: u64,
+    pub overloaded: bool,
+}
+
+#[derive(Debug, Default)]
+struct Bucket {
+    tokens: f64,
+    refilled_ms: i64,
+    /// Latest slot handed to a deferred client.
+    next_slot_ms: f64,
+    last_deferred_ms: Option<i64>,
+    admitted: u64,
+    deferred: u64,
+}
+
+#[derive(Debug)]
+pub struct HandshakeAdmission {
+    config: Option<AdmissionConfig>,
+    bucket: Mutex<Bucket>,
+}
+
+impl HandshakeAdmission {
+    pub fn new(config: Option<AdmissionConfig>) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: config.map_or(0.0, |config| config.burst as f64),
+                ..Bucket::default()
+            }),
+            config,
+        }
+    }
+
+    /// Admit `client_id`'s handshake at `now_ms`, with the backoff hint it
+    /// should carry while the gateway is overloaded, or defer it.
+    pub fn admit(&self, client_id: &str, now_ms: i64) -> Result<Option<u64>, Deferral> {
+        let Some(config) = self.config else {
+            return Ok(None);
+        };
+        let interval = config.interval_ms();
+        let jitter = jitter_ms(client_id, interval * JITTER_SLOTS);
+        let mut bucket = self.bucket.lock().expect("admission mutex poisoned");
+        let elapsed = (now_ms - bucket.refilled_ms).max(0) as f64;
+        bucket.tokens = (bucket.tokens + elapsed / interval).min(config.burst as f64);
+        bucket.refilled_ms = now_ms;
+        let now = now_ms as f64;
+        let backlog = (bucket.next_slot_ms - now).max(0.0);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.admitted += 1;
+            let overloaded = bucket
+                .last_deferred_ms
+                .is_some_and(|at| now_ms - at < PRESSURE_MS);
+            return Ok(overloaded.then(|| capped(backlog + interval + jitter)));
+        }
+        // The next free slot, unless the queue already reaches the cap.
+        let slot = bucket.next_slot_ms.max(now) + interval;
+        if slot - now <= MAX_WAIT_MS as f64 {
+            bucket.next_slot_ms = slot;
+        }
+        bucket.last_deferred_ms = Some(now_ms);
+        bucket.deferred += 1;
+        Err(Deferral {
+            retry_after_ms: capped(slot - now + jitter),
+        })
+    }
+
+    pub fn stats(&self, now_ms: i64) -> AdmissionStats {
+        let bucket = self.bucket.lock().expect("admission mutex poisoned");
+        AdmissionStats {
+            config: self.config,
+            admitted: bucket.admitted,
+            deferred: bucket.deferred,
+            backlog_ms: capped(bucket.next_slot_ms - now_ms as f64),
+            overloaded: bucket
+                .last_deferred_ms
+                .is_some_and(|at| now_ms - at < PRESSURE_MS),
+        }
+    }
+}
+
+fn capped(wait_ms: f64) -> u64 {
+    (wait_ms.max(0.0).ceil() as u64).min(MAX_WAIT_MS)
+}
+
+/// A stable offset under `span_ms` for `client_id`.
+fn jitter_ms(client_id: &str, span_ms: f64) -> f64 {
+    let mut hasher = DefaultHasher::new();
+    client_id.hash(&mut hasher);
+    let span = (span_ms.ceil() as u64).max(1);
+    (hasher.finish() % span) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_reconnect_wave_is_spread_over_slots_with_stable_jitter() {
+        let config = AdmissionConfig::parse(Some("10"), Some("2"))
+            .unwrap()
+            .unwrap();
+        let admission = HandshakeAdmission::new(Some(config));
+        assert_eq!(admission.admit("a", 0), Ok(None));
+        assert_eq!(admission.admit("b", 0), Ok(None));
+
+        // Slots are 100 ms apart, each client offset by up to 400 ms.
+        let waits: Vec<u64> = ["c", "d", "e"]
+            .iter()
+            .map(|client| admission.admit(client, 0).unwrap_err().retry_after_ms)
+            .collect();
+        for (slot, wait) in waits.iter().enumerate() {
+            let earliest = 100 * (slot as u64 + 1);
+            assert!((earliest..earliest + 400).contains(wait), "{waits:?}");
+        }
+        let again = HandshakeAdmission::new(Some(config));
+        again.admit("x", 0).unwrap();
+        again.admit("y", 0).unwrap();
+        assert_eq!(again.admit("c", 0).unwrap_err().retry_after_ms, waits[0]);
+
+        // A token is back after one slot; admitted clients now carry a hint.
+        let hint = admission.admit("c", 100).unwrap().unwrap();
+        assert!((300..=MAX_WAIT_MS).contains(&hint));
+        let stats = admission.stats(100);
+        assert_eq!((stats.admitted, stats.deferred), (3, 3));
+        assert!(stats.overloaded);
+        assert!(!admission.stats(100 + PRESSURE_MS).overloaded);
+
+        // The queue never reaches past the cap.
+        for n in 0..1_000 {
+            let wait = admission.admit(&format!("wave-{n}"), 100).unwrap_err();
+            assert!(wait.retry_after_ms <= MAX_WAIT_MS);
+        }
+        assert!(admission.stats(100).backlog_ms <= MAX_WAIT_MS);
+
+        assert_eq!(AdmissionConfig::parse(Some("off"), None), Ok(None));
+        assert!(AdmissionConfig::parse(Some("0"), None).is_err());
+        assert_eq!(
+            AdmissionConfig::parse(None, None),
+            Ok(Some(AdmissionConfig::default()))
+        );
+        assert_eq!(HandshakeAdmission::new(None).admit("a", 0), Ok(None));
+    }
+}
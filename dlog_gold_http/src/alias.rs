@@ -0,0 +1,266 @@
+//! Vanity handles (`@comet`) mapped onto canon labels (`;9132077554;comet;`).
+//!
+//! Handles are unique (case-insensitive), one per label, and only reveal the
+//! underlying label on reverse/forward lookup when the owner marks them public.
+//! Bank frames resolve handles regardless of the privacy flag.
+
+use dlog_error::{AuthError, BankError, DlogError, FrameError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const HANDLE_MIN: usize = 3;
+const HANDLE_MAX: usize = 32;
+const RESERVED_HANDLES: &[&str] = &["admin", "comet", "omega", "root", "system", "vortex"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AliasRecord {
+    pub handle: String,
+    pub label: String,
+    pub owner_phone: String,
+    pub public: bool,
+    pub created_ms: i64,
+}
+
+/// What the outside world is allowed to see about a handle.
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasView {
+    pub handle: String,
+    pub label: Option<String>,
+    pub public: bool,
+}
+
+impl AliasRecord {
+    pub fn view(&self) -> AliasView {
+        AliasView {
+            handle: format!("@{}", self.handle),
+            label: self.public.then(|| self.label.clone()),
+            public: self.public,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AliasRegistry {
+    by_handle: Mutex<HashMap<String, AliasRecord>>,
+}
+
+impl AliasRegistry {
+    pub fn from_records(records: Vec<AliasRecord>) -> Self {
+        let by_handle = records.into_iter().map(|r| (r.handle.clone(), r)).collect();
+        Self {
+            by_handle: Mutex::new(by_handle),
+        }
+    }
+
+    /// Take another registry's records wholesale (read replicas).
+    pub fn replace(&self, records: Vec<AliasRecord>) {
+        *self.by_handle.lock().expect("alias mutex poisoned") =
+            records.into_iter().map(|r| (r.handle.clone(), r)).collect();
+    }
+
+    pub fn records(&self) -> Vec<AliasRecord> {
+        let guard = self.by_handle.lock().expect("alias mutex poisoned");
+        let mut records: Vec<_> = guard.values().cloned().collect();
+        records.sort_by(|a, b| a.handle.cmp(&b.handle));
+        records
+    }
+
+    /// Bind `handle` to `label`. The label must belong to `owner_phone`.
+    pub fn register(
+        &self,
+        handle: &str,
+        label: &str,
+        owner_phone: &str,
+        public: bool,
+    ) -> Result<AliasRecord, DlogError> {
+        let handle = canonical_handle(handle)?;
+        if RESERVED_HANDLES.contains(&handle.as_str()) {
+            return Err(FrameError::Conflict(format!("handle @{handle} is reserved")).into());
+        }
+        if label_phone(label) != Some(owner_phone) {
+            return Err(
+                AuthError::Forbidden(format!("label {label} is not owned by this phone")).into(),
+            );
+        }
+
+        let mut guard = self.by_handle.lock().expect("alias mutex poisoned");
+        if guard.contains_key(&handle) {
+            return Err(FrameError::Conflict(format!("handle @{handle} already taken")).into());
+        }
+        if let Some(existing) = guard.values().find(|r| r.label == label) {
+            return Err(FrameError::Conflict(format!(
+                "label already aliased as @{}",
+                existing.handle
+            ))
+            .into());
+        }
+
+        let record = AliasRecord {
+            handle: handle.clone(),
+            label: label.to_string(),
+            owner_phone: owner_phone.to_string(),
+            public,
+            created_ms: crate::omega::now_ms(),
+        };
+        guard.insert(handle, record.clone());
+        Ok(record)
+    }
+
+    /// Drop a handle owned by `owner_phone`.
+    pub fn release(&self, handle: &str, owner_phone: &str) -> Result<AliasRecord, DlogError> {
+        let handle = canonical_handle(handle)?;
+        let mut guard = self.by_handle.lock().expect("alias mutex poisoned");
+        match guard.get(&handle) {
+            Some(record) if record.owner_phone == owner_phone => {
+                Ok(guard.remove(&handle).expect("record present"))
+            }
+            Some(_) => Err(AuthError::Forbidden(format!(
+                "handle @{handle} is owned by another phone"
+            ))
+            .into()),
+            None => Err(FrameError::NotFound(format!("alias @{handle}")).into()),
+        }
+    }
+
+    /// Drop every handle owned by `owner_phone` (account deletion).
+    pub fn purge_owner(&self, owner_phone: &str) -> Vec<AliasRecord> {
+        let mut guard = self.by_handle.lock().expect("alias mutex poisoned");
+        let handles: Vec<String> = guard
+            .values()
+            .filter(|r| r.owner_phone == owner_phone)
+            .map(|r| r.handle.clone())
+            .collect();
+        let mut purged: Vec<AliasRecord> = handles
+            .iter()
+            .filter_map(|handle| guard.remove(handle))
+            .collect();
+        purged.sort_by(|a, b| a.handle.cmp(&b.handle));
+        purged
+    }
+
+    /// Point `old`'s handle at `new`, owned by `owner_phone` (label
+    /// recovery). Returns the handle moved, if any.
+    pub fn rebind(&self, old: &str, new: &str, owner_phone: &str) -> Option<String> {
+        let mut guard = self.by_handle.lock().expect("alias mutex poisoned");
+        let record = guard.values_mut().find(|r| r.label == old)?;
+        record.label = new.to_string();
+        record.owner_phone = owner_phone.to_string();
+        Some(record.handle.clone())
+    }
+
+    pub fn lookup(&self, handle: &str) -> Option<AliasRecord> {
+        let handle = canonical_handle(handle).ok()?;
+        let guard = self.by_handle.lock().expect("alias mutex poisoned");
+        guard.get(&handle).cloned()
+    }
+
+    /// Reverse lookup; private aliases stay hidden unless asked by their owner.
+    pub fn reverse(&self, label: &str, requester_phone: Option<&str>) -> Option<AliasView> {
+        let guard = self.by_handle.lock().expect("alias mutex poisoned");
+        let record = guard.values().find(|r| r.label == label)?;
+        if record.public || requester_phone == Some(record.owner_phone.as_str()) {
+            Some(record.view())
+        } else {
+            None
+        }
+    }
+
+    /// Accept either a canon label or an `@handle` and return the canon label.
+    pub fn resolve_label(&self, raw: &str) -> Result<String, BankError> {
+        if !raw.starts_with('@') {
+            return Ok(raw.to_string());
+        }
+        self.lookup(raw)
+            .map(|r| r.label)
+            .ok_or_else(|| BankError::UnknownAlias(raw.to_string()))
+    }
+}
+
+/// Lowercase, strip a leading `@`, and enforce the handle charset.
+pub fn canonical_handle(raw: &str) -> Result<String, FrameError> {
+    let handle = raw.trim().trim_start_matches('@').to_ascii_lowercase();
+    if !(HANDLE_MIN..=HANDLE_MAX).contains(&handle.len()) {
+        return Err(FrameError::Malformed(format!(
+            "handle must be {HANDLE_MIN}-{HANDLE_MAX} characters"
+        )));
+    }
+    if !handle
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return Err(FrameError::Malformed(
+            "handle may only use a-z, 0-9, '_' and '-'".into(),
+        ));
+    }
+    if handle.chars().all(|c| c.is_ascii_digit()) {
+        return Err(FrameError::Malformed("handle cannot be all digits".into()));
+    }
+    Ok(handle)
+}
+
+/// Phone segment of a canon label `;phone;label;`.
+pub fn label_phone(label: &str) -> Option<&str> {
+    let mut parts = label.split(';').filter(|s| !s.is_empty());
+    let phone = parts.next()?;
+    parts.next()?;
+    Some(phone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_are_unique_and_validated() {
+        let registry = AliasRegistry::default();
+        registry
+            .register("@Luke", ";9132077554;fun;", "9132077554", false)
+            .expect("fresh handle");
+
+        let taken = registry
+            .register("luke", ";5550000000;fun;", "5550000000", true)
+            .unwrap_err();
+        assert!(taken.to_string().contains("taken"));
+        assert_eq!(taken.status(), 409);
+        assert!(registry
+            .register("other", ";9132077554;fun;", "9132077554", true)
+            .unwrap_err()
+            .to_string()
+            .contains("already aliased"));
+        assert!(registry
+            .register("vortex", ";9132077554;vortex1;", "9132077554", true)
+            .is_err());
+        assert_eq!(
+            registry
+                .register("mine", ";5550000000;fun;", "9132077554", true)
+                .unwrap_err()
+                .status(),
+            403
+        );
+        assert!(canonical_handle("a.b").is_err());
+        assert!(canonical_handle("12345").is_err());
+
+        let status = |result: Result<AliasRecord, DlogError>| result.unwrap_err().status();
+        assert_eq!(status(registry.release("luke", "5550000000")), 403);
+        assert_eq!(status(registry.release("nobody", "9132077554")), 404);
+        assert_eq!(status(registry.release("a.b", "9132077554")), 400);
+        assert!(registry.release("luke", "9132077554").is_ok());
+    }
+
+    #[test]
+    fn private_aliases_resolve_but_hide_on_reverse() {
+        let registry = AliasRegistry::default();
+        registry
+            .register("luke", ";9132077554;fun;", "9132077554", false)
+            .unwrap();
+
+        assert_eq!(registry.resolve_label("@LUKE").unwrap(), ";9132077554;fun;");
+        assert_eq!(registry.resolve_label(";1;x;").unwrap(), ";1;x;");
+        assert!(registry.resolve_label("@nobody").is_err());
+        assert!(registry.reverse(";9132077554;fun;", None).is_none());
+        assert!(registry
+            .reverse(";9132077554;fun;", Some("9132077554"))
+            .is_some());
+    }
+}
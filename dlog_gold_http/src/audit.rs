@@ -0,0 +1,236 @@
+//! Append-only gateway audit log.
+//!
+//! Entries are appended as JSON lines to `$OMEGA_ROOT/∞/audit;log` (when a
+//! store root is configured) and the most recent ones stay in memory for the
+//! admin endpoints.
+//!
+//! Frames are too many for the file, so each session's recent frames are
+//! kept in memory only, as a [`FrameTrace`] per ack. A [`SessionTimeline`]
+//! summarizes them, with the session's audit entries, for support: counts
+//! per kind over time buckets, the last routes, and the last rejections.
+//! Notes and details pass through `redact_phones` on the way out.
+
+use corelib::privacy::redact_phones;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::store::GatewayStore;
+
+const AUDIT_PATH: &str = ";∞;audit;log;";
+const AUDIT_BACKLOG: usize = 512;
+/// Frames kept per session.
+const FRAME_TRAIL: usize = 256;
+/// Sessions with a frame trail; the one that went quiet longest goes first.
+const TRAIL_SESSIONS: usize = 1024;
+/// Routes and rejections a timeline lists.
+const TIMELINE_TAIL: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at_ms: i64,
+    /// Who triggered the action (session id, redacted phone, "admin", ...).
+    pub actor: String,
+    /// Snake-case action key, e.g. `identity_delete`.
+    pub action: String,
+    #[serde(default)]
+    pub detail: Value,
+}
+
+/// One acked frame.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameTrace {
+    pub at_ms: i64,
+    pub kind: String,
+    pub accepted: bool,
+    /// Targets the frame was routed to.
+    pub routes: Vec<String>,
+    /// Rejection notes, if any.
+    pub errors: Vec<String>,
+}
+
+/// Frame kinds seen in one time bucket.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineBucket {
+    pub start_ms: i64,
+    pub frames: BTreeMap<String, u32>,
+    pub rejected: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineNote {
+    pub at_ms: i64,
+    pub kind: String,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionTimeline {
+    pub session_id: String,
+    pub bucket_ms: i64,
+    /// Oldest first; buckets without frames are left out.
+    pub buckets: Vec<TimelineBucket>,
+    /// Distinct route targets, most recent first.
+    pub last_routes: Vec<String>,
+    /// Most recent first.
+    pub last_errors: Vec<TimelineNote>,
+    /// Audit entries naming the session, oldest first.
+    pub audit: Vec<AuditEntry>,
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    store: GatewayStore,
+    recent: Mutex<VecDeque<AuditEntry>>,
+    frames: Mutex<HashMap<String, VecDeque<FrameTrace>>>,
+}
+
+impl AuditLog {
+    pub fn new(store: GatewayStore) -> Self {
+        Self {
+            store,
+            recent: Mutex::new(VecDeque::new()),
+            frames: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(
+        &self,
+        actor: impl Into<String>,
+        action: impl Into<String>,
+        detail: Value,
+    ) -> AuditEntry {
+        let entry = AuditEntry {
+            at_ms: crate::omega::now_ms(),
+            actor: actor.into(),
+            action: action.into(),
+            detail,
+        };
+        if let Err(err) = self.store.append_json_line(AUDIT_PATH, &entry) {
+            warn!("[audit] failed to append {}: {err}", entry.action);
+        }
+        let mut recent = self.recent.lock().expect("audit mutex poisoned");
+        if recent.len() >= AUDIT_BACKLOG {
+            recent.pop_front();
+        }
+        recent.push_back(entry.clone());
+        entry
+    }
+
+    /// Most recent entries, newest last.
+    pub fn recent(&self, limit: usize) -> Vec<AuditEntry> {
+        let recent = self.recent.lock().expect("audit mutex poisoned");
+        let skip = recent.len().saturating_sub(limit);
+        recent.iter().skip(skip).cloned().collect()
+    }
+
+    /// Add a frame to `session_id`'s trail.
+    pub fn record_frame(&self, session_id: &str, trace: FrameTrace) {
+        let mut frames = self.frames.lock().expect("audit frames mutex poisoned");
+        if !frames.contains_key(session_id) && frames.len() >= TRAIL_SESSIONS {
+            let quietest = frames
+                .iter()
+                .min_by_key(|(_, trail)| trail.back().map(|t| t.at_ms))
+                .map(|(id, _)| id.clone());
+            if let Some(id) = quietest {
+                frames.remove(&id);
+            }
+        }
+        let trail = frames.entry(session_id.to_string()).or_default();
+        if trail.len() >= FRAME_TRAIL {
+            trail.pop_front();
+        }
+        trail.push_back(trace);
+    }
+
+    /// What `session_id` did, in `bucket_ms` buckets; `None` when it has
+    /// neither frames nor audit entries.
+    pub fn timeline(&self, session_id: &str, bucket_ms: i64) -> Option<SessionTimeline> {
+        let bucket_ms = bucket_ms.max(1);
+        let trail: Vec<FrameTrace> = self
+            .frames
+            .lock()
+            .expect("audit frames mutex poisoned")
+            .get(session_id)
+            .map(|trail| trail.iter().cloned().collect())
+            .unwrap_or_default();
+        let audit: Vec<AuditEntry> = self
+            .recent
+            .lock()
+            .expect("audit mutex poisoned")
+            .iter()
+            .filter(|entry| {
+                entry.actor == session_id
+                    || entry.detail.get("session_id").and_then(Value::as_str) == Some(session_id)
+            })
+            .map(|entry| AuditEntry {
+                actor: redact_phones(&entry.actor),
+                detail: redact_value(&entry.detail),
+                ..entry.clone()
+            })
+            .collect();
+        if trail.is_empty() && audit.is_empty() {
+            return None;
+        }
+
+        let mut buckets: Vec<TimelineBucket> = Vec::new();
+        for trace in &trail {
+            let start_ms = trace.at_ms.div_euclid(bucket_ms) * bucket_ms;
+            if buckets.last().is_none_or(|b| b.start_ms != start_ms) {
+                buckets.push(TimelineBucket {
+                    start_ms,
+                    frames: BTreeMap::new(),
+                    rejected: 0,
+                });
+            }
+            let bucket = buckets.last_mut().expect("bucket just pushed");
+            *bucket.frames.entry(trace.kind.clone()).or_default() += 1;
+            bucket.rejected += u32::from(!trace.accepted);
+        }
+        let mut last_routes: Vec<String> = Vec::new();
+        for route in trail.iter().rev().flat_map(|t| t.routes.iter()) {
+            if last_routes.len() == TIMELINE_TAIL {
+                break;
+            }
+            if !last_routes.contains(route) {
+                last_routes.push(route.clone());
+            }
+        }
+        let last_errors = trail
+            .iter()
+            .rev()
+            .flat_map(|t| {
+                t.errors.iter().map(|note| TimelineNote {
+                    at_ms: t.at_ms,
+                    kind: t.kind.clone(),
+                    note: redact_phones(note),
+                })
+            })
+            .take(TIMELINE_TAIL)
+            .collect();
+        Some(SessionTimeline {
+            session_id: session_id.to_string(),
+            bucket_ms,
+            buckets,
+            last_routes,
+            last_errors,
+            audit,
+        })
+    }
+}
+
+fn redact_value(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(redact_phones(text)),
+        Value::Array(items) => Value::Array(items.iter().map(redact_value).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), redact_value(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,222 @@
+//! Where phone auth sessions live between restarts.
+//!
+//! Auth sessions are kept in memory and written through a [`SessionStore`]
+//! after every change, so a cold start doesn't log everyone out. With
+//! `OMEGA_SESSION_BUCKET` set they go to the object `auth;sessions` in that
+//! GCS bucket, which outlives the instance; otherwise to
+//! `$OMEGA_ROOT/∞/auth;sessions` (nowhere without `OMEGA_ROOT`). Tokens are
+//! never written: sessions are keyed by the blake3 digest of theirs.
+
+use dlog_error::StorageError;
+use dlog_resilience::{BreakerConfig, CallError, RetryPolicy, Upstream};
+use google_cloud_storage::client::{Client, ClientConfig};
+use google_cloud_storage::http::objects::download::Range;
+use google_cloud_storage::http::objects::get::GetObjectRequest;
+use google_cloud_storage::http::objects::upload::{Media, UploadObjectRequest, UploadType};
+use google_cloud_storage::http::Error as GcsError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::store::GatewayStore;
+
+pub const SESSIONS_PATH: &str = ";∞;auth;sessions;";
+/// Object name of the sessions in `OMEGA_SESSION_BUCKET`.
+const SESSIONS_OBJECT: &str = "auth;sessions";
+
+pub type SessionFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, StorageError>> + Send + 'a>>;
+
+/// One phone auth session, as kept in memory and in the store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhoneAuthSession {
+    /// blake3 digest of the session token.
+    pub token_key: String,
+    /// Phone storage key (salted hash in privacy mode).
+    pub phone: String,
+    pub label: String,
+    pub display_name: String,
+    pub expires_at_ms: i64,
+    pub verified: bool,
+    pub providers: Vec<String>,
+}
+
+/// Key a session is stored and looked up under.
+pub fn token_key(token: &str) -> String {
+    blake3::hash(token.as_bytes()).to_hex().to_string()
+}
+
+pub trait SessionStore: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Every session saved last.
+    fn load(&self) -> SessionFuture<'_, Vec<PhoneAuthSession>>;
+    /// Replace what is saved with `sessions`.
+    fn save<'a>(&'a self, sessions: &'a [PhoneAuthSession]) -> SessionFuture<'a, ()>;
+}
+
+/// Sessions in a file under `OMEGA_ROOT`.
+#[derive(Debug)]
+pub struct FileSessions(pub GatewayStore);
+
+impl SessionStore for FileSessions {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn load(&self) -> SessionFuture<'_, Vec<PhoneAuthSession>> {
+        Box::pin(async move { Ok(self.0.load_json_strict(SESSIONS_PATH)?.unwrap_or_default()) })
+    }
+
+    fn save<'a>(&'a self, sessions: &'a [PhoneAuthSession]) -> SessionFuture<'a, ()> {
+        Box::pin(async move { self.0.save_json(SESSIONS_PATH, &sessions) })
+    }
+}
+
+/// Sessions in one object of a GCS bucket.
+pub struct GcsSessions {
+    client: Client,
+    bucket: String,
+    upstream: Upstream,
+}
+
+impl fmt::Debug for GcsSessions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcsSessions")
+            .field("bucket", &self.bucket)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GcsSessions {
+    pub async fn connect(bucket: String) -> Result<Self, StorageError> {
+        let config = ClientConfig::default()
+            .with_auth()
+            .await
+            .map_err(|e| StorageError::Config(e.to_string()))?;
+        Ok(Self {
+            client: Client::new(config),
+            bucket,
+            upstream: Upstream::new(
+                "gcs:sessions",
+                RetryPolicy::default(),
+                BreakerConfig::default(),
+            ),
+        })
+    }
+}
+
+/// Throttling, server errors and dropped connections are retried; 4xx are not.
+fn gcs_transient(err: &GcsError) -> bool {
+    match err {
+        GcsError::Response(err) => err.code >= 500 || err.code == 429 || err.code == 408,
+        GcsError::HttpClient(err) => {
+            err.is_connect()
+                || err.is_timeout()
+                || err.status().is_some_and(|s| s.is_server_error())
+        }
+        GcsError::HttpMiddleware(_) => true,
+        GcsError::TokenSource(_) => false,
+    }
+}
+
+fn backend(err: CallError<GcsError>) -> StorageError {
+    StorageError::Backend(err.to_string())
+}
+
+impl SessionStore for GcsSessions {
+    fn name(&self) -> &'static str {
+        "gcs"
+    }
+
+    fn load(&self) -> SessionFuture<'_, Vec<PhoneAuthSession>> {
+        Box::pin(async move {
+            let req = GetObjectRequest {
+                bucket: self.bucket.clone(),
+                object: SESSIONS_OBJECT.to_string(),
+                ..Default::default()
+            };
+            let download = || async {
+                match self.client.download_object(&req, &Range::default()).await {
+                    Ok(data) => Ok(Some(data)),
+                    Err(GcsError::Response(err)) if err.code == 404 => Ok(None),
+                    Err(GcsError::HttpClient(err))
+                        if err.status().is_some_and(|s| s.as_u16() == 404) =>
+                    {
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                }
+            };
+            let Some(bytes) = self
+                .upstream
+                .call_if(download, gcs_transient)
+                .await
+                .map_err(backend)?
+            else {
+                return Ok(Vec::new());
+            };
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn save<'a>(&'a self, sessions: &'a [PhoneAuthSession]) -> SessionFuture<'a, ()> {
+        Box::pin(async move {
+            let bytes = serde_json::to_vec(sessions)?;
+            let mut media = Media::new(SESSIONS_OBJECT.to_string());
+            media.content_type = "application/json".into();
+            media.content_length = Some(bytes.len() as u64);
+            let upload_type = UploadType::Simple(media);
+            let req = UploadObjectRequest {
+                bucket: self.bucket.clone(),
+                ..Default::default()
+            };
+            self.upstream
+                .call_if(
+                    || self.client.upload_object(&req, bytes.clone(), &upload_type),
+                    gcs_transient,
+                )
+                .await
+                .map_err(backend)?;
+            Ok(())
+        })
+    }
+}
+
+/// The GCS store when `OMEGA_SESSION_BUCKET` is set, else the file one.
+pub async fn from_env(store: &GatewayStore) -> Result<Arc<dyn SessionStore>, StorageError> {
+    match std::env::var("OMEGA_SESSION_BUCKET") {
+        Ok(bucket) if !bucket.trim().is_empty() => Ok(Arc::new(
+            GcsSessions::connect(bucket.trim().to_string()).await?,
+        )),
+        _ => Ok(Arc::new(FileSessions(store.clone()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_sessions_round_trip_without_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let sessions = FileSessions(GatewayStore::at(dir.path()));
+        assert!(sessions.load().await.unwrap().is_empty());
+        let session = PhoneAuthSession {
+            token_key: token_key("secret-token"),
+            phone: "9132077554".into(),
+            label: "comet".into(),
+            display_name: "Ω ********54".into(),
+            expires_at_ms: 1,
+            verified: true,
+            providers: vec!["google".into()],
+        };
+        sessions.save(std::slice::from_ref(&session)).await.unwrap();
+        assert_eq!(sessions.load().await.unwrap(), [session]);
+        let saved = std::fs::read_dir(dir.path().join("∞"))
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        assert!(!saved.contains("secret-token"));
+    }
+}
@@ -0,0 +1,270 @@
+//! Flat CSV exports of every ledger balance for external auditors.
+//!
+//! With `OMEGA_BALANCE_EXPORT` set, every `OMEGA_BALANCE_EXPORT_EVERY`th
+//! applied block (default 1) is written as `balances-<height>.csv`, one row
+//! per label: `height,master_root,planet,unit,label,balance`. `master_root`
+//! is the ledgers' master root at that height, so an auditor can recompute
+//! it from the rows. `OMEGA_BALANCE_EXPORT=store` writes under
+//! `$OMEGA_ROOT/exports/balances/`, any other value names a directory.
+//! `OMEGA_BALANCE_EXPORT_GZIP=1` gzips the files (`.csv.gz`) and
+//! `OMEGA_BALANCE_EXPORT_KEEP=N` keeps only the newest N.
+//!
+//! Files are written on a worker thread from a short queue. When the worker
+//! falls behind, blocks are skipped (and logged) rather than slowing block
+//! production.
+
+use corelib::master_root_for;
+use dlog_error::StorageError;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use omega_bank::Ledgers;
+use spec::PlanetId;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use tracing::warn;
+
+use crate::store::{write_atomic, GatewayStore};
+
+/// Snapshots waiting for the worker before new ones are skipped.
+const EXPORT_QUEUE: usize = 4;
+const FILE_PREFIX: &str = "balances-";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceExportConfig {
+    pub dir: PathBuf,
+    /// Export heights divisible by this.
+    pub every: u64,
+    pub gzip: bool,
+    /// Newest exports to keep; `None` keeps them all.
+    pub keep: Option<usize>,
+}
+
+impl BalanceExportConfig {
+    /// `None` when `OMEGA_BALANCE_EXPORT` is unset or `off`.
+    pub fn from_env(store: &GatewayStore) -> Result<Option<Self>, String> {
+        let target = match std::env::var("OMEGA_BALANCE_EXPORT") {
+            Ok(target) if !target.trim().is_empty() && target.trim() != "off" => target,
+            _ => return Ok(None),
+        };
+        let dir = match target.trim() {
+            "store" => store
+                .root()
+                .map(|root| root.join("exports").join("balances"))
+                .ok_or("OMEGA_BALANCE_EXPORT=store needs OMEGA_ROOT")?,
+            dir => PathBuf::from(dir),
+        };
+        let number = |name: &str| -> Result<Option<u64>, String> {
+            match std::env::var(name) {
+                Ok(raw) => raw
+                    .trim()
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("{name} must be a whole number, not `{raw}`")),
+                Err(_) => Ok(None),
+            }
+        };
+        let every = number("OMEGA_BALANCE_EXPORT_EVERY")?.unwrap_or(1);
+        if every == 0 {
+            return Err("OMEGA_BALANCE_EXPORT_EVERY must be at least 1".into());
+        }
+        Ok(Some(Self {
+            dir,
+            every,
+            gzip: std::env::var("OMEGA_BALANCE_EXPORT_GZIP").is_ok_and(|v| v.trim() == "1"),
+            keep: number("OMEGA_BALANCE_EXPORT_KEEP")?
+                .filter(|keep| *keep > 0)
+                .map(|keep| keep as usize),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct Snapshot {
+    height: u64,
+    ledgers: Ledgers,
+}
+
+/// Queues block snapshots for the export worker. Dropping it finishes the
+/// queued exports.
+#[derive(Debug)]
+pub struct BalanceExporter {
+    every: u64,
+    queue: Option<SyncSender<Snapshot>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl BalanceExporter {
+    pub fn spawn(config: BalanceExportConfig) -> Self {
+        let (queue, snapshots) = mpsc::sync_channel::<Snapshot>(EXPORT_QUEUE);
+        let every = config.every;
+        let worker = std::thread::spawn(move || {
+            for snapshot in snapshots {
+                if let Err(err) = export(&config, &snapshot) {
+                    warn!("[export] balances at {} failed: {err}", snapshot.height);
+                }
+            }
+        });
+        Self {
+            every,
+            queue: Some(queue),
+            worker: Some(worker),
+        }
+    }
+
+    /// Export `ledgers` as of `height` if the height is due.
+    pub fn offer(&self, height: u64, ledgers: &Ledgers) {
+        if !height.is_multiple_of(self.every) {
+            return;
+        }
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        let snapshot = Snapshot {
+            height,
+            ledgers: ledgers.clone(),
+        };
+        match queue.try_send(snapshot) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                warn!("[export] worker behind; skipping balances at {height}")
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                warn!("[export] worker stopped; skipping balances at {height}")
+            }
+        }
+    }
+}
+
+impl Drop for BalanceExporter {
+    fn drop(&mut self) {
+        self.queue.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn export(config: &BalanceExportConfig, snapshot: &Snapshot) -> Result<(), StorageError> {
+    let csv = to_csv(snapshot);
+    let mut name = format!("{FILE_PREFIX}{:012}.csv", snapshot.height);
+    let bytes = if config.gzip {
+        name.push_str(".gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(csv.as_bytes())?;
+        encoder.finish()?
+    } else {
+        csv.into_bytes()
+    };
+    write_atomic(&config.dir.join(name), &bytes)?;
+    if let Some(keep) = config.keep {
+        prune(&config.dir, keep)?;
+    }
+    Ok(())
+}
+
+fn to_csv(snapshot: &Snapshot) -> String {
+    let root = master_root_for(snapshot.height, &snapshot.ledgers);
+    let mut csv = String::from("height,master_root,planet,unit,label,balance\n");
+    for planet in PlanetId::ALL {
+        let Some(ledger) = snapshot.ledgers.get(&planet) else {
+            continue;
+        };
+        let mut labels: Vec<_> = ledger.iter().collect();
+        labels.sort();
+        for (label, balance) in labels {
+            csv.push_str(&format!(
+                "{},{root},{},{},{},{balance}\n",
+                snapshot.height,
+                planet.key(),
+                planet.zone().unit,
+                csv_field(label),
+            ));
+        }
+    }
+    csv
+}
+
+fn csv_field(raw: &str) -> String {
+    if raw.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", raw.replace('"', "\"\""))
+    } else {
+        raw.to_string()
+    }
+}
+
+/// Remove all but the newest `keep` exports; heights are zero-padded, so
+/// names sort by height.
+fn prune(dir: &Path, keep: usize) -> Result<(), StorageError> {
+    let mut exports: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(FILE_PREFIX)
+                        && (name.ends_with(".csv") || name.ends_with(".csv.gz"))
+                })
+        })
+        .collect();
+    exports.sort();
+    let excess = exports.len().saturating_sub(keep);
+    for path in exports.into_iter().take(excess) {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn due_heights_export_gzipped_and_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let exporter = BalanceExporter::spawn(BalanceExportConfig {
+            dir: dir.path().to_path_buf(),
+            every: 2,
+            gzip: true,
+            keep: Some(2),
+        });
+        let mut ledgers = Ledgers::new();
+        for height in 1..=6u64 {
+            let earth = ledgers.entry(PlanetId::Earth).or_default();
+            earth.insert(";1;a;".into(), height as u128);
+            earth.insert(";2;b,c;".into(), 7);
+            exporter.offer(height, &ledgers);
+        }
+        drop(exporter);
+
+        let mut names: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "balances-000000000004.csv.gz",
+                "balances-000000000006.csv.gz"
+            ]
+        );
+        let mut csv = String::new();
+        GzDecoder::new(std::fs::File::open(dir.path().join(&names[1])).unwrap())
+            .read_to_string(&mut csv)
+            .unwrap();
+        let root = master_root_for(6, &ledgers);
+        assert_eq!(
+            csv,
+            format!(
+                "height,master_root,planet,unit,label,balance\n\
+                 6,{root},earth,DLOG,;1;a;,6\n\
+                 6,{root},earth,DLOG,\";2;b,c;\",7\n"
+            )
+        );
+    }
+}
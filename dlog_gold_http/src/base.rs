@@ -0,0 +1,97 @@
+//! Octal alongside decimal in responses.
+//!
+//! A caller sending `Accept-Omega-Base: 8` (or `octal`), or adding
+//! `?base=8`, gets an `octal` object next to the usual decimal fields of
+//! the tick, compounding, escrow and recovery responses: the same
+//! durations, countdowns and factors as base-8 strings from
+//! [`corelib::octal`]. Without either the responses are unchanged.
+
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const BASE_HEADER: &str = "accept-omega-base";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberBase {
+    #[default]
+    Decimal,
+    Octal,
+}
+
+/// `?base=8`.
+#[derive(Debug, Default, Deserialize)]
+pub struct BaseQuery {
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+impl NumberBase {
+    /// Octal when the header or the query flag asks for it; the query wins.
+    pub fn negotiate(headers: &HeaderMap, query: &BaseQuery) -> Self {
+        let asked = query.base.as_deref().or_else(|| {
+            headers
+                .get(BASE_HEADER)
+                .and_then(|value| value.to_str().ok())
+        });
+        match asked.map(|raw| raw.trim().to_ascii_lowercase()).as_deref() {
+            Some("8" | "octal") => Self::Octal,
+            _ => Self::Decimal,
+        }
+    }
+
+    /// Attach `octal()` to `value` when octal was asked for.
+    pub fn render<T>(self, value: T, octal: impl FnOnce(&T) -> Value) -> Based<T> {
+        let octal = (self == Self::Octal).then(|| octal(&value));
+        Based { value, octal }
+    }
+}
+
+/// A response with its octal renderings, if asked for.
+#[derive(Debug, Serialize)]
+pub struct Based<T> {
+    #[serde(flatten)]
+    pub value: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub octal: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_or_query_asks_for_octal_and_the_query_wins() {
+        let mut headers = HeaderMap::new();
+        let query = |base: Option<&str>| BaseQuery {
+            base: base.map(String::from),
+        };
+        assert_eq!(
+            NumberBase::negotiate(&headers, &query(None)),
+            NumberBase::Decimal
+        );
+        headers.insert(BASE_HEADER, "Octal".parse().unwrap());
+        assert_eq!(
+            NumberBase::negotiate(&headers, &query(None)),
+            NumberBase::Octal
+        );
+        assert_eq!(
+            NumberBase::negotiate(&headers, &query(Some("10"))),
+            NumberBase::Decimal
+        );
+        assert_eq!(
+            NumberBase::negotiate(&HeaderMap::new(), &query(Some("8"))),
+            NumberBase::Octal
+        );
+
+        let tick = || serde_json::json!({ "tick": 8 });
+        let octal = |_: &Value| serde_json::json!({ "tick": "10" });
+        let plain = NumberBase::Decimal.render(tick(), octal);
+        assert_eq!(serde_json::to_value(&plain).unwrap(), tick());
+        let based = NumberBase::Octal.render(tick(), octal);
+        assert_eq!(
+            serde_json::to_value(&based).unwrap(),
+            serde_json::json!({ "tick": 8, "octal": { "tick": "10" } })
+        );
+    }
+}
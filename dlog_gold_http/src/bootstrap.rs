@@ -0,0 +1,403 @@
+//! Bootstrap manifest: `OMEGA_BOOTSTRAP=<file>` and `POST /admin/bootstrap`.
+//!
+//! One canon file says what a deployment serves. Each line is a directive
+//! followed by semicolon-framed fields; blank lines and `#` comments are
+//! skipped:
+//!
+//! ```text
+//! planet ;earth;
+//! world ;moon_dome;moon;0;160;glide;
+//! profile ;glide;0.5;
+//! show ;moon;moon-dawn:888,moon-day:888;
+//! label ;9132077554;comet;earth:1000,moon:5;
+//! dns ;∞;bank;infinity; ;omega;bank;infinity; Infinity bank
+//! ```
+//!
+//! `planet` lines narrow the planets served (all of them without any);
+//! `world`, `profile` and `show` add to or reshape the built-in ones (see
+//! [`WorldSettings`]); `label` opens a label holding starting balances;
+//! `dns` is a route in the DNS canon. Applying is idempotent: world
+//! settings are replaced as a whole, labels that exist are left alone, and
+//! routes only bump the table's version when one changes. A file with any
+//! issue applies nothing.
+
+use corelib::genesis::GenesisIssue;
+use serde::Serialize;
+use spec::PlanetId;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::labels::canonical_label_name;
+use crate::manifest::{ProfileEntry, ShowEntry, SlideEntry, WorldEntry, WorldSettings};
+
+/// Transfer-log sender of bootstrap balances.
+pub const BOOTSTRAP_LABEL: &str = ";∞;bootstrap;";
+
+/// A label the manifest opens, with its starting balances.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BootstrapLabel {
+    pub line: usize,
+    pub phone: String,
+    pub name: String,
+    pub balances: BTreeMap<PlanetId, u128>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BootstrapPlan {
+    pub world: WorldSettings,
+    pub labels: Vec<BootstrapLabel>,
+    /// Route lines in DNS canon, `<omega_path> <target> <description>`.
+    pub routes: Vec<String>,
+    pub issues: Vec<GenesisIssue>,
+    /// Line of each of `world.worlds`.
+    world_lines: Vec<usize>,
+}
+
+/// One thing applying the manifest changes (or would, on a dry run).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BootstrapChange {
+    /// `planets`, `world`, `profile`, `show`, `label` or `dns`.
+    pub section: &'static str,
+    pub key: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BootstrapReport {
+    pub dry_run: bool,
+    pub changes: Vec<BootstrapChange>,
+}
+
+impl BootstrapPlan {
+    pub fn parse(text: &str) -> Self {
+        let mut plan = Self::default();
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let raw = raw.trim();
+            if raw.is_empty() || raw.starts_with('#') {
+                continue;
+            }
+            let (directive, rest) = raw.split_once(char::is_whitespace).unwrap_or((raw, ""));
+            let rest = rest.trim();
+            let parsed = match directive {
+                "dns" => plan.route(rest),
+                _ => match fields(rest) {
+                    Some(fields) => plan.entry(line, directive, &fields),
+                    None => Err("fields must be semicolon framed (;a;b;)".to_string()),
+                },
+            };
+            if let Err(message) = parsed {
+                plan.issues.push(GenesisIssue { line, message });
+            }
+        }
+        plan.check_worlds();
+        plan.issues.sort_by_key(|issue| issue.line);
+        plan
+    }
+
+    #[cfg(test)]
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn entry(&mut self, line: usize, directive: &str, fields: &[&str]) -> Result<(), String> {
+        match (directive, fields) {
+            ("planet", [planet]) => {
+                let planet = parse_planet(planet)?;
+                if self.world.planets.contains(&planet) {
+                    return Err(format!("planet {} is listed twice", planet.key()));
+                }
+                self.world.planets.push(planet);
+            }
+            ("world", [name, planet, min_y, max_y, profile @ ..]) if profile.len() <= 1 => {
+                let min_y: f32 = parse_number("min_y", min_y)?;
+                let max_y: f32 = parse_number("max_y", max_y)?;
+                if min_y >= max_y {
+                    return Err(format!("world {name}: min_y must be below max_y"));
+                }
+                if self.world.worlds.iter().any(|world| world.name == *name) {
+                    return Err(format!("world {name} is listed twice"));
+                }
+                self.world.worlds.push(WorldEntry {
+                    name: name.to_string(),
+                    planet: parse_planet(planet)?,
+                    min_y,
+                    max_y,
+                    profile: profile.first().map(|p| p.to_string()),
+                });
+                self.world_lines.push(line);
+            }
+            ("profile", [name, scale]) => {
+                let scale: f32 = parse_number("scale", scale)?;
+                if !(scale > 0.0 && scale.is_finite()) {
+                    return Err(format!("profile {name}: scale must be positive"));
+                }
+                if self
+                    .world
+                    .profiles
+                    .iter()
+                    .any(|p| p.name.eq_ignore_ascii_case(name))
+                {
+                    return Err(format!("profile {name} is listed twice"));
+                }
+                self.world.profiles.push(ProfileEntry {
+                    name: name.to_string(),
+                    scale,
+                });
+            }
+            ("show", [id, slides]) => {
+                if self.world.shows.iter().any(|show| show.id == *id) {
+                    return Err(format!("show {id} is listed twice"));
+                }
+                let slides = slides
+                    .split(',')
+                    .map(|slide| {
+                        let (id, ticks) = slide
+                            .split_once(':')
+                            .ok_or_else(|| format!("slide `{slide}` needs <id>:<ticks>"))?;
+                        match ticks.parse() {
+                            Ok(duration_ticks) if duration_ticks > 0 => Ok(SlideEntry {
+                                id: id.to_string(),
+                                duration_ticks,
+                            }),
+                            _ => Err(format!("slide `{slide}`: ticks must be above 0")),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.world.shows.push(ShowEntry {
+                    id: id.to_string(),
+                    slides,
+                });
+            }
+            ("label", [phone, name, balances @ ..]) if balances.len() <= 1 => {
+                if !looks_like_phone(phone) {
+                    return Err(format!("`{phone}` is not a phone number"));
+                }
+                if canonical_label_name(name).ok().as_deref() != Some(*name) {
+                    return Err(format!("label `{name}` is not canonical"));
+                }
+                if self
+                    .labels
+                    .iter()
+                    .any(|label| label.phone == *phone && label.name == *name)
+                {
+                    return Err(format!("label {name} is listed twice"));
+                }
+                let mut parsed = BTreeMap::new();
+                let balances = balances.first().copied().unwrap_or_default();
+                for entry in balances.split(',').filter(|entry| !entry.is_empty()) {
+                    let (planet, amount) = entry
+                        .split_once(':')
+                        .ok_or_else(|| format!("balance `{entry}` needs <planet>:<amount>"))?;
+                    parsed.insert(parse_planet(planet)?, parse_number("balance", amount)?);
+                }
+                self.labels.push(BootstrapLabel {
+                    line,
+                    phone: phone.to_string(),
+                    name: name.to_string(),
+                    balances: parsed,
+                });
+            }
+            ("planet" | "world" | "profile" | "show" | "label", _) => {
+                return Err(format!("wrong number of fields for `{directive}`"));
+            }
+            _ => return Err(format!("unknown directive `{directive}`")),
+        }
+        Ok(())
+    }
+
+    fn route(&mut self, rest: &str) -> Result<(), String> {
+        let mut parts = rest.split_whitespace();
+        let framed = |s: Option<&str>| s.is_some_and(|s| fields(s).is_some());
+        if !framed(parts.next()) || !framed(parts.next()) {
+            return Err("expected dns <omega_path> <target> [description]".into());
+        }
+        self.routes.push(rest.to_string());
+        Ok(())
+    }
+
+    /// Worlds must sit on a served planet and use a profile there is.
+    fn check_worlds(&mut self) {
+        let known: HashSet<String> = self
+            .world
+            .profiles()
+            .into_iter()
+            .map(|(name, _)| name.to_ascii_lowercase())
+            .collect();
+        for (world, line) in self.world.worlds.iter().zip(&self.world_lines) {
+            if !self.world.serves(world.planet) {
+                self.issues.push(GenesisIssue {
+                    line: *line,
+                    message: format!(
+                        "world {} is on unserved planet {}",
+                        world.name,
+                        world.planet.key()
+                    ),
+                });
+            }
+            if let Some(profile) = &world.profile {
+                if !known.contains(&profile.to_ascii_lowercase()) {
+                    self.issues.push(GenesisIssue {
+                        line: *line,
+                        message: format!("world {}: unknown profile {profile}", world.name),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// What replacing `current` world settings with `next` changes.
+pub fn world_changes(current: &WorldSettings, next: &WorldSettings) -> Vec<BootstrapChange> {
+    let planets = |settings: &WorldSettings| {
+        (!settings.planets.is_empty()).then(|| {
+            let keys: Vec<&str> = settings.planets.iter().map(|p| p.key()).collect();
+            keys.join(",")
+        })
+    };
+    let mut changes = Vec::new();
+    diff(
+        &mut changes,
+        "planets",
+        [("planets".to_string(), planets(current))],
+        [("planets".to_string(), planets(next))],
+    );
+    let worlds = |settings: &WorldSettings| -> Vec<_> {
+        settings
+            .worlds
+            .iter()
+            .map(|world| {
+                let shape = format!(
+                    "{};{};{};{}",
+                    world.planet.key(),
+                    world.min_y,
+                    world.max_y,
+                    world.profile.as_deref().unwrap_or("")
+                );
+                (world.name.clone(), Some(shape))
+            })
+            .collect()
+    };
+    diff(&mut changes, "world", worlds(current), worlds(next));
+    let profiles = |settings: &WorldSettings| -> Vec<_> {
+        settings
+            .profiles
+            .iter()
+            .map(|profile| {
+                (
+                    profile.name.to_ascii_lowercase(),
+                    Some(profile.scale.to_string()),
+                )
+            })
+            .collect()
+    };
+    diff(&mut changes, "profile", profiles(current), profiles(next));
+    let shows = |settings: &WorldSettings| -> Vec<_> {
+        settings
+            .shows
+            .iter()
+            .map(|show| {
+                let slides: Vec<String> = show
+                    .slides
+                    .iter()
+                    .map(|slide| format!("{}:{}", slide.id, slide.duration_ticks))
+                    .collect();
+                (show.id.clone(), Some(slides.join(",")))
+            })
+            .collect()
+    };
+    diff(&mut changes, "show", shows(current), shows(next));
+    changes
+}
+
+fn diff(
+    changes: &mut Vec<BootstrapChange>,
+    section: &'static str,
+    current: impl IntoIterator<Item = (String, Option<String>)>,
+    next: impl IntoIterator<Item = (String, Option<String>)>,
+) {
+    let mut entries: BTreeMap<String, (Option<String>, Option<String>)> = BTreeMap::new();
+    for (key, value) in current {
+        entries.entry(key).or_default().0 = value;
+    }
+    for (key, value) in next {
+        entries.entry(key).or_default().1 = value;
+    }
+    changes.extend(
+        entries
+            .into_iter()
+            .filter(|(_, (from, to))| from != to)
+            .map(|(key, (from, to))| BootstrapChange {
+                section,
+                key,
+                from,
+                to,
+            }),
+    );
+}
+
+/// `;a;b;` → `["a", "b"]`; `None` unless framed.
+fn fields(text: &str) -> Option<Vec<&str>> {
+    let inner = text.strip_prefix(';')?.strip_suffix(';')?;
+    (!inner.is_empty()).then(|| inner.split(';').map(str::trim).collect())
+}
+
+fn parse_planet(raw: &str) -> Result<PlanetId, String> {
+    PlanetId::parse(raw).ok_or_else(|| format!("unknown planet `{raw}`"))
+}
+
+fn parse_number<T: std::str::FromStr>(what: &str, raw: &str) -> Result<T, String> {
+    raw.parse().map_err(|_| format!("bad {what} `{raw}`"))
+}
+
+fn looks_like_phone(phone: &str) -> bool {
+    let digits = phone.strip_prefix('+').unwrap_or(phone);
+    (7..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reports_issues_by_line() {
+        let plan = BootstrapPlan::parse(
+            "# deployment\n\
+             planet ;earth;\n\
+             world ;moon_dome;moon;0;160;\n\
+             world ;earth_low;earth;90;10;\n\
+             world ;earth_dome;earth;0;90;hover;\n\
+             show ;earth;earth-dawn:0;\n\
+             label ;9132077554;Comet;earth:5;\n\
+             label ;9132077554;comet;earth:5;\n\
+             portal ;moon;\n\
+             dns ;∞;bank; omega.bank\n",
+        );
+        let lines: Vec<usize> = plan.issues.iter().map(|issue| issue.line).collect();
+        assert_eq!(lines, [3, 4, 5, 6, 7, 9, 10]);
+        assert_eq!(plan.labels.len(), 1);
+        assert_eq!(plan.labels[0].balances[&PlanetId::Earth], 5);
+
+        let clean = BootstrapPlan::parse("profile ;glide;0.5;\nshow ;sunrise;dawn:10,day:20;\n");
+        assert!(clean.is_clean(), "{:?}", clean.issues);
+        let changes = world_changes(&WorldSettings::default(), &clean.world);
+        assert_eq!(
+            changes,
+            [
+                BootstrapChange {
+                    section: "profile",
+                    key: "glide".into(),
+                    from: None,
+                    to: Some("0.5".into()),
+                },
+                BootstrapChange {
+                    section: "show",
+                    key: "sunrise".into(),
+                    from: None,
+                    to: Some("dawn:10,day:20".into()),
+                },
+            ]
+        );
+        assert!(world_changes(&clean.world, &clean.world).is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+//! Per-stand priority queue for bridge instructions.
+//!
+//! Each bridge call queues its instructions under its stand (the player's
+//! uuid when it names none) and gets back at most [`DELIVERY_BATCH`] of the
+//! stand's queue, highest priority first; the rest wait for the stand's next
+//! call. An instruction past its `expiry_tick` is dropped rather than
+//! delivered, since a velocity meant for the tick it was computed on
+//! rubber-bands the stand when it lands late. Drops are counted per
+//! instruction for `/admin/overview`.
+
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::sync::Mutex;
+
+use crate::omega::{BridgeAction, BridgeInstruction};
+
+/// Instructions handed back per bridge call.
+pub const DELIVERY_BATCH: usize = 3;
+/// Instructions waiting per stand; past it the lowest priority goes.
+pub const QUEUE_CAP: usize = 32;
+/// Stands with instructions waiting.
+pub const MAX_STANDS: usize = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BridgeQueueStats {
+    /// Instructions waiting across every stand.
+    pub queued: usize,
+    pub delivered: u64,
+    /// Dropped past their expiry tick, by instruction.
+    pub expired: BTreeMap<String, u64>,
+    /// Dropped because a queue or the stand table was full.
+    pub overflowed: u64,
+}
+
+#[derive(Debug)]
+struct Queued {
+    seq: u64,
+    instruction: BridgeInstruction,
+}
+
+impl Queued {
+    /// Higher priority first, then oldest first.
+    fn rank(&self) -> (u8, Reverse<u64>) {
+        (self.instruction.priority, Reverse(self.seq))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank() == other.rank()
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Queues {
+    stands: HashMap<String, BinaryHeap<Queued>>,
+    seq: u64,
+    stats: BridgeQueueStats,
+}
+
+#[derive(Debug, Default)]
+pub struct BridgeQueue {
+    inner: Mutex<Queues>,
+}
+
+impl BridgeQueue {
+    /// Queue `actions` for `stand` at `now_tick` and deliver what is due.
+    pub fn deliver(
+        &self,
+        stand: &str,
+        actions: Vec<BridgeAction>,
+        now_tick: u64,
+    ) -> Vec<BridgeInstruction> {
+        let mut inner = self.inner.lock().expect("bridge queue mutex poisoned");
+        let Queues { stands, seq, stats } = &mut *inner;
+        if !stands.contains_key(stand) && stands.len() >= MAX_STANDS {
+            for queue in stands.values_mut() {
+                drop_expired(queue, now_tick, stats);
+            }
+            stands.retain(|_, queue| !queue.is_empty());
+        }
+        // With the table still full, this call's leftovers can't wait.
+        let tracked = stands.contains_key(stand) || stands.len() < MAX_STANDS;
+        let mut untracked = BinaryHeap::new();
+        let queue = if tracked {
+            stands.entry(stand.to_string()).or_default()
+        } else {
+            &mut untracked
+        };
+        for action in actions {
+            *seq += 1;
+            queue.push(Queued {
+                seq: *seq,
+                instruction: BridgeInstruction::new(action, now_tick),
+            });
+        }
+
+        let mut delivered = Vec::new();
+        while delivered.len() < DELIVERY_BATCH {
+            let Some(next) = queue.pop() else {
+                break;
+            };
+            if next.instruction.expiry_tick < now_tick {
+                count_expired(&next.instruction, stats);
+            } else {
+                delivered.push(next.instruction);
+            }
+        }
+        stats.delivered += delivered.len() as u64;
+        if queue.len() > QUEUE_CAP {
+            let mut kept = std::mem::take(queue).into_sorted_vec();
+            let excess = kept.len() - QUEUE_CAP;
+            kept.drain(..excess);
+            stats.overflowed += excess as u64;
+            *queue = kept.into();
+        }
+        if !tracked {
+            stats.overflowed += queue.len() as u64;
+        } else if queue.is_empty() {
+            stands.remove(stand);
+        }
+        delivered
+    }
+
+    pub fn stats(&self) -> BridgeQueueStats {
+        let inner = self.inner.lock().expect("bridge queue mutex poisoned");
+        BridgeQueueStats {
+            queued: inner.stands.values().map(BinaryHeap::len).sum(),
+            ..inner.stats.clone()
+        }
+    }
+}
+
+fn drop_expired(queue: &mut BinaryHeap<Queued>, now_tick: u64, stats: &mut BridgeQueueStats) {
+    queue.retain(|queued| {
+        let live = queued.instruction.expiry_tick >= now_tick;
+        if !live {
+            count_expired(&queued.instruction, stats);
+        }
+        live
+    });
+}
+
+fn count_expired(instruction: &BridgeInstruction, stats: &mut BridgeQueueStats) {
+    *stats
+        .expired
+        .entry(instruction.action.kind().to_string())
+        .or_default() += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::omega::PAPER_TICK;
+
+    fn echo(message: &str) -> BridgeAction {
+        BridgeAction::Echo {
+            stand_id: None,
+            message: message.into(),
+        }
+    }
+
+    fn velocity() -> BridgeAction {
+        BridgeAction::SetVelocity {
+            stand_id: None,
+            vx: 1.0,
+            vy: 0.0,
+            vz: 0.0,
+        }
+    }
+
+    fn position() -> BridgeAction {
+        BridgeAction::SetPosition {
+            stand_id: None,
+            x: 0.0,
+            y: 64.0,
+            z: 0.0,
+        }
+    }
+
+    #[test]
+    fn stands_get_their_highest_priority_live_instructions() {
+        let queue = BridgeQueue::default();
+        let first = queue.deliver(
+            "stand",
+            vec![echo("a"), velocity(), echo("b"), position()],
+            0,
+        );
+        let kinds: Vec<&str> = first.iter().map(|i| i.action.kind()).collect();
+        assert_eq!(kinds, ["set_position", "set_velocity", "echo"]);
+        assert!(matches!(&first[2].action, BridgeAction::Echo { message, .. } if message == "a"));
+        assert_eq!(queue.stats().queued, 1);
+
+        // A velocity that waited past its window is dropped, not delivered.
+        let late = 3 * PAPER_TICK;
+        let moves = vec![position(), position(), position(), velocity()];
+        assert_eq!(queue.deliver("other", moves, 0).len(), 3);
+        assert!(queue.deliver("other", Vec::new(), late).is_empty());
+        // Echoes keep longer.
+        let second = queue.deliver("stand", Vec::new(), late);
+        assert!(matches!(&second[..], [i] if i.action.kind() == "echo"));
+
+        let stats = queue.stats();
+        assert_eq!(stats.expired["set_velocity"], 1);
+        assert_eq!((stats.queued, stats.delivered, stats.overflowed), (0, 7, 0));
+    }
+}
@@ -0,0 +1,271 @@
+//! Supply sinks: DLOG burned by in-game actions.
+//!
+//! Interest and faucet grants only ever add to supply. Burn hooks take some
+//! back: `OMEGA_BURNS` names the actions that burn and how much, e.g.
+//! `portal_toll=21,auction_fee=2.5%,lock_upgrade=1000`. A flat hook burns
+//! its amount every time; a percentage hook burns that share of the price
+//! the caller names (an auction's sale price, say), rounded up so a paid
+//! action never burns nothing. Burned DLOG leaves the payer's ledger label
+//! and goes nowhere; the transfer log records it against [`BURN_LABEL`].
+//! Cumulative burns per planet and action live in `;∞;bank;burned;` and
+//! travel with the ledgers to read replicas.
+
+use dlog_error::FrameError;
+use serde::{Deserialize, Serialize};
+use spec::PlanetId;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::store::GatewayStore;
+
+pub const BURNED_PATH: &str = ";∞;bank;burned;";
+/// Payee on the transfer log for burns.
+pub const BURN_LABEL: &str = ";∞;burn;";
+const ACTION_MAX: usize = 32;
+
+/// What one use of an action burns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BurnRule {
+    /// Basis points of the price the caller names.
+    Percent {
+        bps: u32,
+    },
+    Flat {
+        amount: u128,
+    },
+}
+
+impl BurnRule {
+    /// `21` burns 21 flat; `2.5%` burns 2.5% (at most two decimals).
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let invalid = || format!("burn `{raw}` is not a whole amount or a percentage");
+        let Some(percent) = raw.strip_suffix('%') else {
+            let amount: u128 = raw.parse().map_err(|_| invalid())?;
+            if amount == 0 {
+                return Err("flat burns must be at least 1".into());
+            }
+            return Ok(Self::Flat { amount });
+        };
+        let (whole, fraction) = percent.split_once('.').unwrap_or((percent, ""));
+        if fraction.len() > 2 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let whole: u32 = whole.parse().map_err(|_| invalid())?;
+        let fraction: u32 = format!("{fraction:0<2}").parse().map_err(|_| invalid())?;
+        let bps = whole.saturating_mul(100).saturating_add(fraction);
+        if bps == 0 || bps > 10_000 {
+            return Err(format!("burn `{raw}` must be above 0% and at most 100%"));
+        }
+        Ok(Self::Percent { bps })
+    }
+
+    /// What this rule burns on an action priced at `price`.
+    pub fn burn(&self, price: u128) -> u128 {
+        match *self {
+            Self::Percent { bps } => price.saturating_mul(bps as u128).div_ceil(10_000),
+            Self::Flat { amount } => amount,
+        }
+    }
+}
+
+/// Burn rule per action name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct BurnHooks(pub BTreeMap<String, BurnRule>);
+
+impl BurnHooks {
+    /// `None` when `OMEGA_BURNS` is unset or `off`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match std::env::var("OMEGA_BURNS") {
+            Ok(raw) if !raw.trim().is_empty() && raw.trim() != "off" => Self::parse(&raw).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Comma-separated `action=rule` pairs.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut hooks = BTreeMap::new();
+        for pair in raw
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (action, rule) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("burn hook `{pair}` is not action=rule"))?;
+            let action = action.trim();
+            check_action(action).map_err(|err| err.to_string())?;
+            if hooks
+                .insert(action.to_string(), BurnRule::parse(rule)?)
+                .is_some()
+            {
+                return Err(format!("burn hook `{action}` is set twice"));
+            }
+        }
+        Ok(Self(hooks))
+    }
+}
+
+/// 1-32 of `a-z`, `0-9` and `_`.
+fn check_action(action: &str) -> Result<(), FrameError> {
+    let ok = !action.is_empty()
+        && action.len() <= ACTION_MAX
+        && action
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if ok {
+        Ok(())
+    } else {
+        Err(FrameError::Malformed(format!(
+            "burn action `{action}` must be 1-{ACTION_MAX} of a-z, 0-9 and '_'"
+        )))
+    }
+}
+
+/// Burned so far on one planet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BurnTotals {
+    pub total: u128,
+    pub by_action: BTreeMap<String, u128>,
+}
+
+/// Cumulative burns per planet.
+pub type BurnedSupply = BTreeMap<PlanetId, BurnTotals>;
+
+/// What `GET /omega/bank/burns` shows.
+#[derive(Debug, Clone, Serialize)]
+pub struct BurnStatus {
+    pub hooks: BurnHooks,
+    pub burned: BurnedSupply,
+}
+
+/// One burn, as the caller gets it back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BurnReceipt {
+    pub planet: PlanetId,
+    pub action: String,
+    pub burned: u128,
+    /// The burn on the transfer log, paid to [`BURN_LABEL`].
+    pub transfer_id: u64,
+    /// Burned on this planet since genesis.
+    pub burned_total: u128,
+}
+
+#[derive(Debug)]
+pub struct BurnBook {
+    hooks: BurnHooks,
+    store: GatewayStore,
+    burned: Mutex<BurnedSupply>,
+}
+
+impl BurnBook {
+    pub fn load(store: GatewayStore) -> Self {
+        let burned = store.load_json(BURNED_PATH).unwrap_or_default();
+        Self {
+            hooks: BurnHooks::default(),
+            store,
+            burned: Mutex::new(burned),
+        }
+    }
+
+    pub fn with_hooks(self, hooks: BurnHooks) -> Self {
+        Self { hooks, ..self }
+    }
+
+    /// What `action` burns on `price`; `price` is needed for percentage hooks.
+    pub fn charge(&self, action: &str, price: Option<u128>) -> Result<u128, FrameError> {
+        check_action(action)?;
+        let rule = self
+            .hooks
+            .0
+            .get(action)
+            .ok_or_else(|| FrameError::NotFound(format!("burn hook {action}")))?;
+        match (rule, price) {
+            (BurnRule::Percent { .. }, None | Some(0)) => Err(FrameError::Malformed(format!(
+                "{action} burns a share of the price; name a price"
+            ))),
+            (rule, price) => Ok(rule.burn(price.unwrap_or_default())),
+        }
+    }
+
+    /// Count `amount` burned by `action` on `planet`, returning the planet's
+    /// new total.
+    pub fn record(&self, planet: PlanetId, action: &str, amount: u128) -> u128 {
+        let mut burned = self.burned.lock().expect("burns mutex poisoned");
+        let totals = burned.entry(planet).or_default();
+        totals.total += amount;
+        *totals.by_action.entry(action.to_string()).or_default() += amount;
+        let total = totals.total;
+        self.persist(&burned);
+        total
+    }
+
+    pub fn burned(&self) -> BurnedSupply {
+        self.burned.lock().expect("burns mutex poisoned").clone()
+    }
+
+    /// Burned on `planet` since genesis.
+    pub fn total(&self, planet: PlanetId) -> u128 {
+        let burned = self.burned.lock().expect("burns mutex poisoned");
+        burned.get(&planet).map(|t| t.total).unwrap_or_default()
+    }
+
+    /// Take a leader's totals (read replicas).
+    pub fn replace(&self, totals: BurnedSupply) {
+        let mut burned = self.burned.lock().expect("burns mutex poisoned");
+        *burned = totals;
+        self.persist(&burned);
+    }
+
+    pub fn status(&self) -> BurnStatus {
+        BurnStatus {
+            hooks: self.hooks.clone(),
+            burned: self.burned(),
+        }
+    }
+
+    fn persist(&self, burned: &BurnedSupply) {
+        if let Err(err) = self.store.save_json(BURNED_PATH, burned) {
+            tracing::warn!("[burn] cannot persist totals: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hooks_parse_and_charge_flat_or_rounded_up_shares() {
+        let hooks = BurnHooks::parse("portal_toll=21, auction_fee=2.5%,lock_upgrade=1000").unwrap();
+        assert_eq!(hooks.0["auction_fee"], BurnRule::Percent { bps: 250 });
+        for bad in [
+            "toll",
+            "Toll=1",
+            "toll=0",
+            "toll=0%",
+            "toll=101%",
+            "toll=1.234%",
+            "toll=x",
+            "toll=1,toll=2",
+        ] {
+            assert!(BurnHooks::parse(bad).is_err(), "{bad}");
+        }
+
+        let book = BurnBook::load(GatewayStore::default()).with_hooks(hooks);
+        assert_eq!(book.charge("portal_toll", None).unwrap(), 21);
+        assert_eq!(book.charge("auction_fee", Some(1_000)).unwrap(), 25);
+        // A share of a tiny price still burns one unit.
+        assert_eq!(book.charge("auction_fee", Some(1)).unwrap(), 1);
+        assert_eq!(book.charge("auction_fee", None).unwrap_err().status(), 400);
+        assert_eq!(book.charge("teleport", None).unwrap_err().status(), 404);
+
+        assert_eq!(book.record(PlanetId::Earth, "portal_toll", 21), 21);
+        assert_eq!(book.record(PlanetId::Earth, "auction_fee", 25), 46);
+        let earth = &book.burned()[&PlanetId::Earth];
+        assert_eq!(earth.by_action["portal_toll"], 21);
+        assert_eq!(book.total(PlanetId::Moon), 0);
+    }
+}
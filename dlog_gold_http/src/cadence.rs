@@ -0,0 +1,142 @@
+//! Per-session tick pacing from round trips.
+//!
+//! Clients put the round trips they measure (from their clock syncs, say)
+//! into any frame's payload as `rtt_ms`; a heartbeat is a `TICK_FRAME`
+//! carrying nothing else. The gateway folds them into a smoothed round
+//! trip and its variation per session, as TCP does, and spaces the
+//! session's ticks so about [`IN_FLIGHT`] frames are under way at once:
+//! `(srtt + 4·rttvar) / IN_FLIGHT`, rounded up to whole [`FRAME_MS`]
+//! frames and kept between `OMEGA_TICK_MIN_MS` and `OMEGA_TICK_MAX_MS`.
+//! Sessions that never report a round trip tick every frame, as before.
+
+use serde::Serialize;
+use std::env;
+
+use crate::tick::FRAME_MS;
+
+/// Frames a session may have under way at its interval.
+pub const IN_FLIGHT: f64 = 8.0;
+/// Slowest interval unless `OMEGA_TICK_MAX_MS` says otherwise.
+pub const DEFAULT_MAX_MS: i64 = 256;
+/// Round trips above this are taken as this; a stalled link says nothing
+/// about the next second.
+const RTT_MAX_MS: u64 = 60_000;
+
+/// Smoothed round trip of one session (RFC 6298 weights).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RttEstimate {
+    pub srtt_ms: f64,
+    pub rttvar_ms: f64,
+    pub samples: u64,
+}
+
+impl RttEstimate {
+    pub fn new(rtt_ms: u64) -> Self {
+        let rtt = rtt_ms.min(RTT_MAX_MS) as f64;
+        Self {
+            srtt_ms: rtt,
+            rttvar_ms: rtt / 2.0,
+            samples: 1,
+        }
+    }
+
+    pub fn record(&mut self, rtt_ms: u64) {
+        let rtt = rtt_ms.min(RTT_MAX_MS) as f64;
+        self.rttvar_ms += ((self.srtt_ms - rtt).abs() - self.rttvar_ms) / 4.0;
+        self.srtt_ms += (rtt - self.srtt_ms) / 8.0;
+        self.samples += 1;
+    }
+}
+
+/// Bounds on the tick interval the gateway hands out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TickCadence {
+    pub min_ms: i64,
+    pub max_ms: i64,
+}
+
+impl Default for TickCadence {
+    fn default() -> Self {
+        Self {
+            min_ms: FRAME_MS,
+            max_ms: DEFAULT_MAX_MS,
+        }
+    }
+}
+
+/// `ms` rounded up to whole frames, at least one.
+fn whole_frames(ms: i64) -> i64 {
+    ((ms + FRAME_MS - 1) / FRAME_MS).max(1) * FRAME_MS
+}
+
+impl TickCadence {
+    /// Bounds from `OMEGA_TICK_MIN_MS` and `OMEGA_TICK_MAX_MS`, each
+    /// rounded up to whole frames.
+    pub fn from_env() -> Result<Self, String> {
+        let read = |name: &str, default: i64| match env::var(name) {
+            Ok(raw) => raw
+                .trim()
+                .parse::<i64>()
+                .map(whole_frames)
+                .map_err(|_| format!("{name} `{raw}` is not a number of ms")),
+            Err(_) => Ok(default),
+        };
+        let cadence = Self {
+            min_ms: read("OMEGA_TICK_MIN_MS", FRAME_MS)?,
+            max_ms: read("OMEGA_TICK_MAX_MS", DEFAULT_MAX_MS)?,
+        };
+        if cadence.min_ms > cadence.max_ms {
+            return Err(format!(
+                "OMEGA_TICK_MIN_MS {} is above OMEGA_TICK_MAX_MS {}",
+                cadence.min_ms, cadence.max_ms
+            ));
+        }
+        Ok(cadence)
+    }
+
+    /// Tick interval for a session with round trip `rtt`, if one is known.
+    pub fn interval_ms(&self, rtt: Option<&RttEstimate>) -> i64 {
+        let wanted = rtt.map_or(0.0, |rtt| (rtt.srtt_ms + 4.0 * rtt.rttvar_ms) / IN_FLIGHT);
+        whole_frames(wanted.ceil() as i64).clamp(self.min_ms, self.max_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_follow_the_round_trip_within_bounds() {
+        let cadence = TickCadence::default();
+        assert_eq!(cadence.interval_ms(None), FRAME_MS);
+
+        // A steady 40 ms link keeps the φ frame.
+        let mut lan = RttEstimate::new(40);
+        for _ in 0..32 {
+            lan.record(40);
+        }
+        assert!(lan.rttvar_ms < 1.0);
+        assert_eq!(cadence.interval_ms(Some(&lan)), FRAME_MS);
+
+        // A 600 ms link with jitter slows to whole frames past 600/8.
+        let mut far = RttEstimate::new(600);
+        far.record(700);
+        far.record(500);
+        let interval = cadence.interval_ms(Some(&far));
+        assert!(interval > 75 && interval % FRAME_MS == 0);
+        assert_eq!(far.samples, 3);
+
+        // A stalled link stops at the ceiling, and the floor holds too.
+        assert_eq!(
+            cadence.interval_ms(Some(&RttEstimate::new(u64::MAX))),
+            DEFAULT_MAX_MS
+        );
+        let floored = TickCadence {
+            min_ms: 48,
+            max_ms: 96,
+        };
+        assert_eq!(floored.interval_ms(Some(&lan)), 48);
+        assert_eq!(whole_frames(0), FRAME_MS);
+        assert_eq!(whole_frames(9), 16);
+    }
+}
@@ -0,0 +1,268 @@
+//! Input calibration per label and device.
+//!
+//! `/omega/bridge/input` used to scale movement by a fixed multiplier per
+//! device. A label can now keep a calibration for each device it plays on:
+//! for the `move` and `look` axes, a radial deadzone, a response curve
+//! (the deflection past the deadzone raised to `curve`), a sensitivity and
+//! per-axis inversion. Devices a label hasn't calibrated keep the old
+//! behaviour: no deadzone, a linear curve, and the manifest's device scale
+//! as the `move` sensitivity.
+
+use dlog_error::FrameError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::manifest::device_scale;
+use crate::store::GatewayStore;
+
+pub const CALIBRATION_PATH: &str = ";∞;bridge;calibration;";
+pub const DEVICES_PER_LABEL: usize = 8;
+const DEVICE_MAX: usize = 32;
+const DEADZONE_MAX: f32 = 0.9;
+const SENSITIVITY_RANGE: (f32, f32) = (0.05, 8.0);
+const CURVE_RANGE: (f32, f32) = (0.25, 4.0);
+
+/// How one stick or pointer axis pair is shaped.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisCalibration {
+    /// Deflection below this reads as zero; the rest is rescaled to start at 0.
+    pub deadzone: f32,
+    /// Exponent applied to the rescaled deflection; 1 is linear.
+    pub curve: f32,
+    pub sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.0,
+            curve: 1.0,
+            sensitivity: 1.0,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+impl AxisCalibration {
+    /// Shape one `(x, y)` reading. Direction is kept; only its length is
+    /// mapped through the deadzone and curve.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let length = x.hypot(y);
+        if length <= self.deadzone {
+            return (0.0, 0.0);
+        }
+        let shaped = ((length - self.deadzone) / (1.0 - self.deadzone)).powf(self.curve);
+        let scale = shaped / length * self.sensitivity;
+        let flip = |invert: bool| if invert { -1.0 } else { 1.0 };
+        (
+            x * scale * flip(self.invert_x),
+            y * scale * flip(self.invert_y),
+        )
+    }
+
+    fn check(&self, axis: &str) -> Result<(), FrameError> {
+        let within = |value: f32, (low, high): (f32, f32)| (low..=high).contains(&value);
+        if !within(self.deadzone, (0.0, DEADZONE_MAX)) {
+            return Err(FrameError::Malformed(format!(
+                "{axis}.deadzone must be 0-{DEADZONE_MAX}"
+            )));
+        }
+        if !within(self.curve, CURVE_RANGE) {
+            return Err(FrameError::Malformed(format!(
+                "{axis}.curve must be {}-{}",
+                CURVE_RANGE.0, CURVE_RANGE.1
+            )));
+        }
+        if !within(self.sensitivity, SENSITIVITY_RANGE) {
+            return Err(FrameError::Malformed(format!(
+                "{axis}.sensitivity must be {}-{}",
+                SENSITIVITY_RANGE.0, SENSITIVITY_RANGE.1
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A label's calibration for one device.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCalibration {
+    #[serde(rename = "move")]
+    pub movement: AxisCalibration,
+    pub look: AxisCalibration,
+}
+
+impl DeviceCalibration {
+    /// What an uncalibrated `device` gets.
+    pub fn default_for(device: Option<&str>) -> Self {
+        Self {
+            movement: AxisCalibration {
+                sensitivity: device_scale(device),
+                ..AxisCalibration::default()
+            },
+            look: AxisCalibration::default(),
+        }
+    }
+}
+
+/// A calibration update; axes left out keep their current (or default)
+/// shaping.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalibrationUpdate {
+    pub device: String,
+    #[serde(default, rename = "move")]
+    pub movement: Option<AxisCalibration>,
+    #[serde(default)]
+    pub look: Option<AxisCalibration>,
+}
+
+/// Calibrations by label key, then device.
+pub type Calibrations = BTreeMap<String, BTreeMap<String, DeviceCalibration>>;
+
+#[derive(Debug)]
+pub struct CalibrationBook {
+    store: GatewayStore,
+    labels: Mutex<Calibrations>,
+}
+
+impl CalibrationBook {
+    pub fn load(store: GatewayStore) -> Self {
+        let labels = store.load_json(CALIBRATION_PATH).unwrap_or_default();
+        Self {
+            store,
+            labels: Mutex::new(labels),
+        }
+    }
+
+    /// `label`'s calibrations by device.
+    pub fn profile(&self, label: &str) -> BTreeMap<String, DeviceCalibration> {
+        let labels = self.labels.lock().expect("calibration mutex poisoned");
+        labels.get(label).cloned().unwrap_or_default()
+    }
+
+    /// What input from `label` on `device` is shaped by.
+    pub fn resolve(&self, label: Option<&str>, device: Option<&str>) -> DeviceCalibration {
+        let labels = self.labels.lock().expect("calibration mutex poisoned");
+        label
+            .zip(device)
+            .and_then(|(label, device)| labels.get(label)?.get(device).copied())
+            .unwrap_or_else(|| DeviceCalibration::default_for(device))
+    }
+
+    pub fn update(
+        &self,
+        label: &str,
+        update: CalibrationUpdate,
+    ) -> Result<DeviceCalibration, FrameError> {
+        let device = update.device.trim().to_ascii_lowercase();
+        if device.is_empty()
+            || device.len() > DEVICE_MAX
+            || !device
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        {
+            return Err(FrameError::Malformed(format!(
+                "device must be 1-{DEVICE_MAX} of a-z, 0-9, '_' and '-'"
+            )));
+        }
+        let mut labels = self.labels.lock().expect("calibration mutex poisoned");
+        let devices = labels.entry(label.to_string()).or_default();
+        if !devices.contains_key(&device) && devices.len() >= DEVICES_PER_LABEL {
+            return Err(FrameError::Conflict(format!(
+                "at most {DEVICES_PER_LABEL} calibrated devices per label"
+            )));
+        }
+        let mut calibration = devices
+            .get(&device)
+            .copied()
+            .unwrap_or_else(|| DeviceCalibration::default_for(Some(&device)));
+        if let Some(movement) = update.movement {
+            movement.check("move")?;
+            calibration.movement = movement;
+        }
+        if let Some(look) = update.look {
+            look.check("look")?;
+            calibration.look = look;
+        }
+        devices.insert(device, calibration);
+        if let Err(err) = self.store.save_json(CALIBRATION_PATH, &*labels) {
+            tracing::warn!("[calibration] cannot persist profiles: {err}");
+        }
+        Ok(calibration)
+    }
+
+    /// Drop every profile of the labels `owned` accepts (identity
+    /// deletion). Returns how many labels had one.
+    pub fn purge_labels(&self, owned: impl Fn(&str) -> bool) -> usize {
+        let mut labels = self.labels.lock().expect("calibration mutex poisoned");
+        let before = labels.len();
+        labels.retain(|label, _| !owned(label));
+        let purged = before - labels.len();
+        if purged > 0 {
+            if let Err(err) = self.store.save_json(CALIBRATION_PATH, &*labels) {
+                tracing::warn!("[calibration] cannot persist profiles: {err}");
+            }
+        }
+        purged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncalibrated_devices_keep_their_scale_and_updates_shape_axes() {
+        let book = CalibrationBook::load(GatewayStore::default());
+        assert_eq!(book.resolve(None, Some("touch")).movement.sensitivity, 0.7);
+        let plain = book.resolve(Some(";p;x;"), Some("gamepad")).movement;
+        assert_eq!(plain.apply(0.5, -2.0), (0.45, -1.8));
+
+        let update = |movement| CalibrationUpdate {
+            device: "Gamepad".into(),
+            movement: Some(movement),
+            look: None,
+        };
+        let stick = AxisCalibration {
+            deadzone: 0.2,
+            curve: 2.0,
+            sensitivity: 2.0,
+            invert_x: false,
+            invert_y: true,
+        };
+        for bad in [
+            AxisCalibration {
+                deadzone: 0.95,
+                ..stick
+            },
+            AxisCalibration {
+                curve: 0.0,
+                ..stick
+            },
+            AxisCalibration {
+                sensitivity: f32::NAN,
+                ..stick
+            },
+        ] {
+            assert_eq!(book.update(";p;x;", update(bad)).unwrap_err().status(), 400);
+        }
+        book.update(";p;x;", update(stick)).unwrap();
+        let calibrated = book.resolve(Some(";p;x;"), Some("gamepad"));
+        assert_eq!(calibrated.look, AxisCalibration::default());
+        // Inside the deadzone nothing moves; at full tilt the curve is 1.
+        assert_eq!(calibrated.movement.apply(0.1, 0.1), (0.0, 0.0));
+        let (x, y) = calibrated.movement.apply(0.0, 1.0);
+        assert!(x.abs() < 1e-6 && (y + 2.0).abs() < 1e-6);
+        let (x, _) = calibrated.movement.apply(0.6, 0.0);
+        assert!((x - 2.0 * 0.5f32.powi(2)).abs() < 1e-6);
+        assert_eq!(book.profile(";p;x;").len(), 1);
+        assert_eq!(
+            book.resolve(Some(";p;y;"), Some("gamepad")),
+            DeviceCalibration::default_for(Some("gamepad"))
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Chat-style commands typed by players (`POST /omega/command`).
+//!
+//! Lines parse with the shared [`dlog_command`] grammar; a command's
+//! permission tag is a capability key, so `/sessions` needs a session
+//! granted `admin` at handshake. Replies carry a [`spec::UiOverlay`] for the
+//! plugin to show as is.
+
+use corelib::exchange;
+use dlog_command::{command, CommandReply, Invocation, Registry, HELP};
+use spec::{Capability, PlanetId};
+
+use crate::omega::{OmegaGateway, SessionView};
+
+pub const COMMANDS: Registry = Registry::new(&[
+    HELP,
+    command! {
+        "height" => "Chain height and tip",
+    },
+    command! {
+        "whoami" => "Your planet and capabilities",
+    },
+    command! {
+        "quote" => "Price a currency conversion",
+        args: [amount: Uint, to: Word],
+        flags: [from: Word],
+    },
+    command! {
+        "sessions" => "Live sessions per planet",
+        perm: "admin",
+    },
+]);
+
+/// Parse and run `line` for `session`.
+pub fn run(gateway: &OmegaGateway, session: &SessionView, line: &str) -> CommandReply {
+    let granted =
+        |tag: &str| Capability::parse(tag).is_some_and(|cap| session.capabilities.contains(&cap));
+    let invocation = match COMMANDS.parse(line, granted) {
+        Ok(invocation) => invocation,
+        Err(err) => return COMMANDS.reject(&err),
+    };
+    let command = invocation.command;
+    let output = match command {
+        "help" => COMMANDS.help(invocation.text("command"), granted),
+        "height" => Ok(height(gateway)),
+        "whoami" => Ok(whoami(session)),
+        "quote" => match quote(session, &invocation) {
+            Ok(lines) => Ok(lines),
+            Err(message) => return CommandReply::failed(command, message),
+        },
+        "sessions" => Ok(sessions(gateway)),
+        _ => unreachable!("registered command /{command} has no handler"),
+    };
+    match output {
+        Ok(lines) => CommandReply::done(command, lines),
+        Err(err) => COMMANDS.reject(&err),
+    }
+}
+
+fn height(gateway: &OmegaGateway) -> Vec<String> {
+    let mut lines = vec![format!("height {}", gateway.block_height())];
+    if let Some(tip) = gateway.tip() {
+        let short: String = tip.root.chars().take(16).collect();
+        lines.push(format!("tip {short}"));
+        lines.push(format!("sealed by {}", tip.producer));
+    }
+    lines
+}
+
+fn whoami(session: &SessionView) -> Vec<String> {
+    let capabilities: Vec<&str> = session.capabilities.iter().map(|c| c.key()).collect();
+    vec![
+        format!("{} on {}", session.client_id, session.planet.key()),
+        capabilities.join(" "),
+    ]
+}
+
+fn quote(session: &SessionView, invocation: &Invocation) -> Result<Vec<String>, String> {
+    let planet = |raw: &str| PlanetId::parse(raw).ok_or_else(|| format!("no planet `{raw}`"));
+    let from = match invocation.text("from") {
+        Some(raw) => planet(raw)?,
+        None => session.planet,
+    };
+    let to = planet(invocation.text("to").unwrap_or_default())?;
+    let amount = invocation.uint("amount").unwrap_or_default();
+    let (from, to) = (from.zone(), to.zone());
+    let quote = exchange::quote(&from, &to, amount as u128)
+        .ok_or_else(|| format!("{amount} {} is too little to convert", from.unit))?;
+    Ok(vec![
+        format!(
+            "{} {} → {} {}",
+            quote.sent, from.unit, quote.received, to.unit
+        ),
+        format!("fee {} {}", quote.fee, from.unit),
+    ])
+}
+
+fn sessions(gateway: &OmegaGateway) -> Vec<String> {
+    let sessions = gateway.sessions();
+    let mut lines = vec![format!("{} sessions", sessions.len())];
+    lines.extend(PlanetId::ALL.into_iter().map(|planet| {
+        let count = sessions.iter().filter(|s| s.planet == planet).count();
+        format!("{} {count}", planet.key())
+    }));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::omega::SessionKind;
+    use crate::store::GatewayStore;
+    use crate::tick;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn commands_follow_session_capabilities() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let mut session = SessionView {
+            session_id: "s1".into(),
+            client_id: "paper".into(),
+            planet: PlanetId::Moon,
+            phone: None,
+            capabilities: BTreeSet::from([Capability::Render]),
+            kind: SessionKind::Player,
+            established_ms: 0,
+            last_input_ms: 0,
+            rtt: None,
+            tick_interval_ms: tick::FRAME_MS,
+        };
+        let reply = run(&gateway, &session, "/quote 1000 earth");
+        assert!(reply.ok);
+        assert!(reply.ui.title.starts_with("1000 LUNA → "));
+        assert!(!run(&gateway, &session, "/quote 1000 pluto").ok);
+
+        let denied = run(&gateway, &session, "/sessions");
+        assert!(!denied.ok);
+        assert_eq!(denied.ui.title, "/sessions needs the admin permission");
+        assert_eq!(run(&gateway, &session, "/help").output.len(), 4);
+
+        session.capabilities.insert(Capability::Admin);
+        let reply = run(&gateway, &session, "sessions");
+        assert_eq!(reply.ui.title, "0 sessions");
+        assert_eq!(run(&gateway, &session, "/help").output.len(), 5);
+        assert_eq!(run(&gateway, &session, "/height").ui.title, "height 0");
+    }
+}
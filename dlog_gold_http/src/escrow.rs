@@ -0,0 +1,440 @@
+//! Escrow for player-to-player trades.
+//!
+//! A maker opens an escrow by locking their side (DLOG and/or an item or land
+//! reference) against a counterparty's side and a block deadline. The taker
+//! confirms by locking theirs; once both have confirmed the gateway swaps the
+//! two sides. Escrows still open when the deadline height seals are refunded.
+//! The book only tracks state — the gateway moves the locked DLOG between the
+//! parties' labels and [`ESCROW_LABEL`] around each transition, so locked
+//! funds stay in the ledger (and its root) instead of leaving supply. Items
+//! and land change hands through [`ItemRegistry`].
+
+use dlog_error::{AuthError, FrameError};
+use serde::{Deserialize, Serialize};
+use spec::PlanetId;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Longest deadline an escrow may ask for (~1 day of 8 s blocks).
+pub const MAX_DEADLINE_BLOCKS: u64 = 10_800;
+pub const ITEM_MAX: usize = 128;
+/// Closed escrows kept for lookups before the oldest are pruned.
+const CLOSED_HISTORY: usize = 1024;
+/// Ledger label holding every open escrow's locked DLOG on each planet.
+pub const ESCROW_LABEL: &str = ";∞;escrow;";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowState {
+    Open,
+    Settled,
+    Refunded,
+    Cancelled,
+}
+
+/// One party's side of the trade.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowLeg {
+    /// Ledger key of the party's label.
+    pub label: String,
+    #[serde(default)]
+    pub amount: u128,
+    /// Item or land reference handed over on settlement (e.g. `;land;earth;12;-4;`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+impl EscrowLeg {
+    pub fn new(label: String, amount: u128, item: Option<String>) -> Result<Self, FrameError> {
+        let item = item
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty());
+        if let Some(item) = &item {
+            if item.len() > ITEM_MAX {
+                return Err(FrameError::Malformed(format!(
+                    "item exceeds {ITEM_MAX} characters"
+                )));
+            }
+            if item
+                .chars()
+                .any(|c| c == '.' || c.is_whitespace() || c.is_control())
+            {
+                return Err(FrameError::Malformed(
+                    "item may not contain '.' or whitespace".into(),
+                ));
+            }
+        }
+        if amount == 0 && item.is_none() {
+            return Err(FrameError::Malformed(
+                "each side must lock an amount or an item".into(),
+            ));
+        }
+        Ok(Self {
+            label,
+            amount,
+            item,
+            confirmed: false,
+        })
+    }
+}
+
+/// One side of an escrow as requested (label may be an `@handle`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct EscrowSide {
+    pub label: String,
+    #[serde(default)]
+    pub amount: u128,
+    #[serde(default)]
+    pub item: Option<String>,
+}
+
+/// Maker's offer against what they ask of the counterparty.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EscrowRequest {
+    #[serde(default)]
+    pub planet: PlanetId,
+    pub offer: EscrowSide,
+    pub ask: EscrowSide,
+    pub deadline_blocks: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Escrow {
+    pub id: u64,
+    pub planet: PlanetId,
+    pub maker: EscrowLeg,
+    pub taker: EscrowLeg,
+    pub opened_height: u64,
+    pub deadline_height: u64,
+    pub state: EscrowState,
+    #[serde(default)]
+    pub closed_height: Option<u64>,
+}
+
+impl Escrow {
+    pub fn is_open(&self) -> bool {
+        self.state == EscrowState::Open
+    }
+
+    /// Legs whose DLOG is currently locked (confirmed and still open).
+    pub fn locked(&self) -> impl Iterator<Item = &EscrowLeg> {
+        [&self.maker, &self.taker]
+            .into_iter()
+            .filter(|leg| self.is_open() && leg.confirmed)
+    }
+
+    fn close(&mut self, state: EscrowState, height: u64) {
+        self.state = state;
+        self.closed_height = Some(height);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EscrowBook {
+    escrows: Mutex<BTreeMap<u64, Escrow>>,
+}
+
+impl EscrowBook {
+    pub fn from_escrows(escrows: Vec<Escrow>) -> Self {
+        Self {
+            escrows: Mutex::new(escrows.into_iter().map(|e| (e.id, e)).collect()),
+        }
+    }
+
+    /// Take another book's escrows wholesale (read replicas).
+    pub fn replace(&self, escrows: Vec<Escrow>) {
+        *self.escrows.lock().expect("escrow mutex poisoned") =
+            escrows.into_iter().map(|e| (e.id, e)).collect();
+    }
+
+    pub fn escrows(&self) -> Vec<Escrow> {
+        let escrows = self.escrows.lock().expect("escrow mutex poisoned");
+        escrows.values().cloned().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Escrow> {
+        let escrows = self.escrows.lock().expect("escrow mutex poisoned");
+        escrows.get(&id).cloned()
+    }
+
+    /// Record a new escrow whose maker side is already locked.
+    pub fn open(
+        &self,
+        planet: PlanetId,
+        mut maker: EscrowLeg,
+        taker: EscrowLeg,
+        height: u64,
+        deadline_blocks: u64,
+    ) -> Result<Escrow, FrameError> {
+        if !(1..=MAX_DEADLINE_BLOCKS).contains(&deadline_blocks) {
+            return Err(FrameError::Malformed(format!(
+                "deadline_blocks must be 1-{MAX_DEADLINE_BLOCKS}"
+            )));
+        }
+        if maker.label == taker.label {
+            return Err(FrameError::Malformed("cannot escrow with yourself".into()));
+        }
+        maker.confirmed = true;
+        let mut escrows = self.escrows.lock().expect("escrow mutex poisoned");
+        if let Some(item) = &maker.item {
+            if escrows
+                .values()
+                .any(|e| e.is_open() && e.maker.item.as_ref() == Some(item))
+            {
+                return Err(FrameError::Conflict(format!(
+                    "{item} is already locked in an escrow"
+                )));
+            }
+        }
+        let escrow = Escrow {
+            id: escrows.keys().next_back().map(|id| id + 1).unwrap_or(1),
+            planet,
+            maker,
+            taker,
+            opened_height: height,
+            deadline_height: height + deadline_blocks,
+            state: EscrowState::Open,
+            closed_height: None,
+        };
+        escrows.insert(escrow.id, escrow.clone());
+        Ok(escrow)
+    }
+
+    /// Check `id` is open, unexpired, and waiting on `taker_label`.
+    pub fn pending_for_taker(
+        &self,
+        id: u64,
+        taker_label: &str,
+        height: u64,
+    ) -> Result<Escrow, FrameError> {
+        let escrow = self.open_escrow(id)?;
+        if escrow.taker.label != taker_label {
+            return Err(FrameError::Conflict(format!(
+                "escrow {id} is not waiting on this label"
+            )));
+        }
+        if height > escrow.deadline_height {
+            return Err(FrameError::Conflict(format!("escrow {id} has expired")));
+        }
+        Ok(escrow)
+    }
+
+    /// Mark the taker confirmed and the escrow settled at `height`.
+    pub fn settle(&self, id: u64, height: u64) -> Result<Escrow, FrameError> {
+        self.transition(id, |escrow| {
+            escrow.taker.confirmed = true;
+            escrow.close(EscrowState::Settled, height);
+        })
+    }
+
+    /// Maker backs out before the taker confirms.
+    pub fn cancel(&self, id: u64, maker_label: &str, height: u64) -> Result<Escrow, FrameError> {
+        let escrow = self.open_escrow(id)?;
+        if escrow.maker.label != maker_label {
+            return Err(FrameError::Conflict(format!(
+                "only the maker can cancel escrow {id}"
+            )));
+        }
+        self.transition(id, |escrow| escrow.close(EscrowState::Cancelled, height))
+    }
+
+    /// Close every open escrow whose deadline is below `height`; returns them
+    /// (with their locked legs still flagged) so the caller can refund.
+    pub fn expire(&self, height: u64) -> Vec<Escrow> {
+        let mut escrows = self.escrows.lock().expect("escrow mutex poisoned");
+        let mut expired = Vec::new();
+        for escrow in escrows.values_mut() {
+            if escrow.is_open() && height > escrow.deadline_height {
+                expired.push(escrow.clone());
+                escrow.close(EscrowState::Refunded, height);
+            }
+        }
+        if !expired.is_empty() {
+            prune(&mut escrows);
+        }
+        expired
+    }
+
+    /// Open escrows with a leg whose label satisfies `is_party`.
+    pub fn open_for(&self, is_party: impl Fn(&str) -> bool) -> Vec<Escrow> {
+        let escrows = self.escrows.lock().expect("escrow mutex poisoned");
+        escrows
+            .values()
+            .filter(|e| e.is_open() && (is_party(&e.maker.label) || is_party(&e.taker.label)))
+            .cloned()
+            .collect()
+    }
+
+    fn open_escrow(&self, id: u64) -> Result<Escrow, FrameError> {
+        let escrow = self
+            .get(id)
+            .ok_or_else(|| FrameError::NotFound(format!("escrow {id}")))?;
+        if !escrow.is_open() {
+            return Err(FrameError::Conflict(format!(
+                "escrow {id} is already {:?}",
+                escrow.state
+            )));
+        }
+        Ok(escrow)
+    }
+
+    fn transition(&self, id: u64, apply: impl FnOnce(&mut Escrow)) -> Result<Escrow, FrameError> {
+        let mut escrows = self.escrows.lock().expect("escrow mutex poisoned");
+        let escrow = escrows
+            .get_mut(&id)
+            .filter(|e| e.is_open())
+            .ok_or_else(|| FrameError::Conflict(format!("escrow {id} is no longer open")))?;
+        apply(escrow);
+        let escrow = escrow.clone();
+        prune(&mut escrows);
+        Ok(escrow)
+    }
+}
+
+fn prune(escrows: &mut BTreeMap<u64, Escrow>) {
+    let closed: Vec<u64> = escrows
+        .values()
+        .filter(|e| !e.is_open())
+        .map(|e| e.id)
+        .collect();
+    for id in closed
+        .iter()
+        .take(closed.len().saturating_sub(CLOSED_HISTORY))
+    {
+        escrows.remove(id);
+    }
+}
+
+/// Admin assignment of an item to a label (canon or `@handle`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemAssignment {
+    pub item: String,
+    pub label: String,
+}
+
+/// Who holds each item or land reference (ledger label key). Items enter
+/// through an admin assignment and only move by escrow settlement.
+#[derive(Debug, Default)]
+pub struct ItemRegistry {
+    owners: Mutex<BTreeMap<String, String>>,
+}
+
+impl ItemRegistry {
+    pub fn from_owners(owners: BTreeMap<String, String>) -> Self {
+        Self {
+            owners: Mutex::new(owners),
+        }
+    }
+
+    pub fn owners(&self) -> BTreeMap<String, String> {
+        self.owners.lock().expect("items mutex poisoned").clone()
+    }
+
+    pub fn owner_of(&self, item: &str) -> Option<String> {
+        let owners = self.owners.lock().expect("items mutex poisoned");
+        owners.get(item).cloned()
+    }
+
+    pub fn assign(&self, item: &str, label: &str) {
+        let mut owners = self.owners.lock().expect("items mutex poisoned");
+        owners.insert(item.to_string(), label.to_string());
+    }
+
+    /// Fail unless `leg`'s item (if any) is held by `leg`'s label.
+    pub fn check(&self, leg: &EscrowLeg) -> Result<(), AuthError> {
+        match &leg.item {
+            Some(item) if self.owner_of(item).as_deref() != Some(leg.label.as_str()) => Err(
+                AuthError::Forbidden(format!("{item} is not held by {}", leg.label)),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Swap the items of a settled escrow: each side receives the other's.
+    pub fn settle(&self, escrow: &Escrow) {
+        let mut owners = self.owners.lock().expect("items mutex poisoned");
+        for (leg, to) in [
+            (&escrow.maker, &escrow.taker.label),
+            (&escrow.taker, &escrow.maker.label),
+        ] {
+            if let Some(item) = &leg.item {
+                owners.insert(item.clone(), to.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(label: &str, amount: u128, item: Option<&str>) -> EscrowLeg {
+        EscrowLeg::new(label.into(), amount, item.map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn legs_reject_empty_sides_and_dotted_items() {
+        assert!(EscrowLeg::new(";a;x;".into(), 0, None).is_err());
+        assert!(EscrowLeg::new(";a;x;".into(), 0, Some("  ".into())).is_err());
+        assert!(EscrowLeg::new(";a;x;".into(), 0, Some("sword.v2".into())).is_err());
+        assert!(EscrowLeg::new(";a;x;".into(), 0, Some("x".repeat(ITEM_MAX + 1))).is_err());
+        let land = leg(";a;x;", 0, Some(" ;land;earth;1;2; "));
+        assert_eq!(land.item.as_deref(), Some(";land;earth;1;2;"));
+    }
+
+    #[test]
+    fn book_locks_items_once_and_expires_past_the_deadline() {
+        let book = EscrowBook::default();
+        let sword = || leg(";a;x;", 0, Some("sword"));
+        assert!(book
+            .open(PlanetId::Earth, sword(), leg(";b;y;", 5, None), 10, 0)
+            .is_err());
+        assert!(book
+            .open(PlanetId::Earth, sword(), leg(";a;x;", 5, None), 10, 1)
+            .is_err());
+        let open = book
+            .open(PlanetId::Earth, sword(), leg(";b;y;", 5, None), 10, 2)
+            .unwrap();
+        assert_eq!(open.deadline_height, 12);
+        assert!(matches!(
+            book.open(PlanetId::Earth, sword(), leg(";c;z;", 5, None), 10, 2),
+            Err(FrameError::Conflict(_))
+        ));
+        assert!(book.pending_for_taker(open.id, ";c;z;", 11).is_err());
+        assert!(book.cancel(open.id, ";b;y;", 11).is_err());
+
+        assert!(book.expire(12).is_empty());
+        let expired = book.expire(13);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].locked().count(), 1);
+        assert_eq!(book.get(open.id).unwrap().state, EscrowState::Refunded);
+        assert!(book.settle(open.id, 13).is_err());
+    }
+
+    #[test]
+    fn registry_checks_holders_and_swaps_on_settle() {
+        let items = ItemRegistry::default();
+        items.assign("sword", ";a;x;");
+        items.assign(";land;earth;1;2;", ";b;y;");
+        assert!(items.check(&leg(";a;x;", 0, Some("sword"))).is_ok());
+        assert!(items.check(&leg(";b;y;", 0, Some("sword"))).is_err());
+        assert!(items.check(&leg(";b;y;", 0, Some("shield"))).is_err());
+        assert!(items.check(&leg(";b;y;", 3, None)).is_ok());
+
+        let book = EscrowBook::default();
+        let open = book
+            .open(
+                PlanetId::Earth,
+                leg(";a;x;", 0, Some("sword")),
+                leg(";b;y;", 0, Some(";land;earth;1;2;")),
+                0,
+                5,
+            )
+            .unwrap();
+        items.settle(&book.settle(open.id, 1).unwrap());
+        assert_eq!(items.owner_of("sword").as_deref(), Some(";b;y;"));
+        assert_eq!(items.owner_of(";land;earth;1;2;").as_deref(), Some(";a;x;"));
+    }
+}
@@ -0,0 +1,146 @@
+//! In-process Ω event bus: a bounded, sequenced log that services publish to
+//! and clients poll via `/omega/events?since=`. Events addressed to labels
+//! are also delivered to their [`NotificationInbox`], and events go to
+//! subscribed devices through the [`PushDispatcher`] when one is attached.
+//! Live readers ([`EventBus::subscribe`]) are woken once an event and its
+//! inbox deliveries are in place.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::watch;
+
+use crate::notifications::NotificationInbox;
+use crate::push::PushDispatcher;
+
+const EVENT_BACKLOG: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OmegaEvent {
+    pub seq: u64,
+    pub height: u64,
+    pub at_ms: i64,
+    pub kind: String,
+    pub payload: Value,
+}
+
+#[derive(Debug)]
+pub struct EventBus {
+    log: Mutex<EventLog>,
+    inbox: NotificationInbox,
+    push: OnceLock<PushDispatcher>,
+    /// `seq` of the newest event fully delivered.
+    latest: watch::Sender<u64>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::with_inbox(NotificationInbox::default())
+    }
+}
+
+#[derive(Debug, Default)]
+struct EventLog {
+    next_seq: u64,
+    recent: VecDeque<OmegaEvent>,
+}
+
+impl EventBus {
+    pub fn with_inbox(inbox: NotificationInbox) -> Self {
+        Self {
+            log: Mutex::default(),
+            inbox,
+            push: OnceLock::new(),
+            latest: watch::Sender::new(0),
+        }
+    }
+
+    /// Wakes on every event published from now on.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.latest.subscribe()
+    }
+
+    /// Push published events to devices; the first dispatcher attached stays.
+    pub fn attach_push(&self, push: PushDispatcher) {
+        if self.push.set(push).is_err() {
+            tracing::warn!("[push] a dispatcher is already attached");
+        }
+    }
+
+    pub fn push(&self) -> Option<&PushDispatcher> {
+        self.push.get()
+    }
+
+    pub fn inbox(&self) -> &NotificationInbox {
+        &self.inbox
+    }
+
+    pub fn publish(&self, height: u64, kind: impl Into<String>, payload: Value) -> OmegaEvent {
+        let event = self.record(height, kind, payload);
+        if let Some(push) = self.push.get() {
+            push.offer(&event, None);
+        }
+        self.latest.send_replace(event.seq);
+        event
+    }
+
+    fn record(&self, height: u64, kind: impl Into<String>, payload: Value) -> OmegaEvent {
+        let mut log = self.log.lock().expect("event bus mutex poisoned");
+        log.next_seq += 1;
+        let event = OmegaEvent {
+            seq: log.next_seq,
+            height,
+            at_ms: crate::omega::now_ms(),
+            kind: kind.into(),
+            payload,
+        };
+        if log.recent.len() >= EVENT_BACKLOG {
+            log.recent.pop_front();
+        }
+        log.recent.push_back(event.clone());
+        event
+    }
+
+    /// Publish `payload` and deliver `private` to each recipient's inbox.
+    /// Only the public payload goes on the bus, which never names labels;
+    /// pushes go only to the recipients' owners' devices.
+    pub fn publish_to(
+        &self,
+        height: u64,
+        kind: impl Into<String>,
+        payload: Value,
+        private: Value,
+        recipients: &[&str],
+    ) -> OmegaEvent {
+        let event = self.record(height, kind, payload);
+        for label in recipients {
+            self.inbox
+                .deliver(label, &event.kind, height, event.at_ms, private.clone());
+        }
+        if let Some(push) = self.push.get() {
+            let owners = recipients
+                .iter()
+                .filter_map(|label| crate::alias::label_phone(label))
+                .map(String::from)
+                .collect();
+            push.offer(&event, Some(owners));
+        }
+        self.latest.send_replace(event.seq);
+        event
+    }
+
+    /// Events with `seq > since`, oldest first.
+    pub fn since(&self, since: u64) -> Vec<OmegaEvent> {
+        let log = self.log.lock().expect("event bus mutex poisoned");
+        log.recent
+            .iter()
+            .filter(|e| e.seq > since)
+            .cloned()
+            .collect()
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.log.lock().expect("event bus mutex poisoned").next_seq
+    }
+}
@@ -0,0 +1,211 @@
+//! Public read-only explorer: blocks and public-alias balances.
+//!
+//! Everything here is safe to put behind a CDN. Handlers tag responses with
+//! an ETag derived from the chain's master root, and the shared
+//! [`cached`] layer adds per-route `Cache-Control` and answers matching
+//! `If-None-Match` with `304`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use corelib::consensus::BlockHeader;
+use dlog_http_cache::{cached, CachePolicy, Tagged};
+use serde::{Deserialize, Serialize};
+use spec::PlanetId;
+use std::sync::Arc;
+
+use crate::omega::OmegaGateway;
+
+/// Most headers one list request returns.
+pub const MAX_BLOCKS: usize = 256;
+
+/// Quoted ETag over `parts` (roots contain `∞`, so they're hashed, not echoed).
+pub fn etag(parts: &[&str]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update(b";");
+    }
+    format!("\"{}\"", &hasher.finalize().to_hex()[..32])
+}
+
+pub fn router<S>(gateway: Arc<OmegaGateway>) -> Router<S> {
+    Router::new()
+        .route("/tip", cached(get(tip), CachePolicy::TIP))
+        .route("/blocks", cached(get(blocks), CachePolicy::TIP))
+        .route("/blocks/:height", cached(get(block), CachePolicy::HISTORY))
+        .route(
+            "/balances/:planet/:handle",
+            cached(get(balance), CachePolicy::TIP),
+        )
+        .with_state(gateway)
+}
+
+fn tip_root(gateway: &OmegaGateway) -> String {
+    gateway.tip().map(|tip| tip.root).unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+struct TipResponse {
+    height: u64,
+    tip: Option<BlockHeader>,
+}
+
+async fn tip(State(gateway): State<Arc<OmegaGateway>>) -> Tagged<TipResponse> {
+    let tip = gateway.tip();
+    let root = tip.as_ref().map(|t| t.root.as_str()).unwrap_or_default();
+    Tagged(
+        etag(&["tip", root]),
+        TipResponse {
+            height: gateway.block_height(),
+            tip,
+        },
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct BlocksQuery {
+    #[serde(default)]
+    from: u64,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    64
+}
+
+async fn blocks(
+    State(gateway): State<Arc<OmegaGateway>>,
+    Query(query): Query<BlocksQuery>,
+) -> Tagged<Vec<BlockHeader>> {
+    let headers = gateway.blocks_from(query.from, query.limit.min(MAX_BLOCKS));
+    let last = headers.last().map(|h| h.root.as_str()).unwrap_or_default();
+    let tag = etag(&[
+        "blocks",
+        &query.from.to_string(),
+        &headers.len().to_string(),
+        last,
+    ]);
+    Tagged(tag, headers)
+}
+
+async fn block(
+    State(gateway): State<Arc<OmegaGateway>>,
+    Path(height): Path<u64>,
+) -> Result<Response, StatusCode> {
+    let header = gateway
+        .blocks_from(height, 1)
+        .into_iter()
+        .find(|h| h.height == height)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let mut response = Tagged(etag(&["block", &header.root]), header).into_response();
+    // The tip block can still lose fork choice to a sibling.
+    if height >= gateway.block_height() {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, CachePolicy::TIP.header_value());
+    }
+    Ok(response)
+}
+
+#[derive(Debug, Serialize)]
+struct BalanceResponse {
+    handle: String,
+    planet: PlanetId,
+    balance: String,
+    height: u64,
+}
+
+/// Balances are public only through public aliases; raw labels carry phones.
+async fn balance(
+    State(gateway): State<Arc<OmegaGateway>>,
+    Path((planet, handle)): Path<(String, String)>,
+) -> Result<Tagged<BalanceResponse>, StatusCode> {
+    let planet = PlanetId::parse(&planet).ok_or(StatusCode::NOT_FOUND)?;
+    let (view, balance) = gateway
+        .public_balance(planet, &handle)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let balance = balance.to_string();
+    // Transfers settle between seals, so the amount is part of the tag.
+    let tag = etag(&[
+        "balance",
+        &view.handle,
+        planet.key(),
+        &tip_root(&gateway),
+        &balance,
+    ]);
+    Ok(Tagged(
+        tag,
+        BalanceResponse {
+            handle: view.handle,
+            planet,
+            balance,
+            height: gateway.block_height(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::GatewayStore;
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::http::HeaderValue;
+    use tower::ServiceExt;
+
+    fn get(uri: &str, if_none_match: Option<&HeaderValue>) -> Request {
+        let mut req = Request::get(uri).body(Body::empty()).unwrap();
+        if let Some(tag) = if_none_match {
+            req.headers_mut().insert(header::IF_NONE_MATCH, tag.clone());
+        }
+        req
+    }
+
+    #[tokio::test]
+    async fn etags_revalidate_until_the_next_seal() {
+        let gateway = Arc::new(OmegaGateway::with_store(GatewayStore::default()).unwrap());
+        gateway.seal_block();
+        let app: Router = router(gateway.clone());
+
+        let first = app.clone().oneshot(get("/tip", None)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(
+            first.headers()[header::CACHE_CONTROL],
+            "public, max-age=4, stale-while-revalidate=8"
+        );
+        let tag = first.headers()[header::ETAG].clone();
+
+        let again = app.clone().oneshot(get("/tip", Some(&tag))).await.unwrap();
+        assert_eq!(again.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(again.headers()[header::ETAG], tag);
+
+        // Block 1 is the tip (short cache) until block 2 seals over it.
+        let tip_block = app.clone().oneshot(get("/blocks/1", None)).await.unwrap();
+        assert!(tip_block.headers()[header::CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .contains("max-age=4,"));
+        gateway.seal_block();
+        let history = app.clone().oneshot(get("/blocks/1", None)).await.unwrap();
+        assert!(history.headers()[header::CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .contains("max-age=300,"));
+
+        let moved = app.clone().oneshot(get("/tip", Some(&tag))).await.unwrap();
+        assert_eq!(moved.status(), StatusCode::OK);
+        assert_ne!(moved.headers()[header::ETAG], tag);
+
+        let private = app
+            .oneshot(get("/balances/earth/nobody", None))
+            .await
+            .unwrap();
+        assert_eq!(private.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,269 @@
+//! Test-deployment faucet: small daily grants to verified phones.
+//!
+//! Off unless `OMEGA_FAUCET` names the planet it pays on. Each grant mints
+//! into one of the caller's labels through the bank and is logged as a
+//! transfer from [`FAUCET_LABEL`]. Grants shrink on the airdrop curve:
+//! claim `n` pays `amount · φ^(-0.0808200400008 · n)`, but never less
+//! than one unit. The airdrop anti-farm rules apply per UTC day: one grant
+//! per phone, `OMEGA_FAUCET_PER_IP` grants per network address, and nothing
+//! once `OMEGA_FAUCET_BUDGET` is spent. Addresses are kept only as hashes.
+//! State lives in `;∞;bank;faucet;`.
+
+use dlog_error::FrameError;
+use serde::{Deserialize, Serialize};
+use spec::{PlanetId, PHI};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::store::GatewayStore;
+
+const FAUCET_PATH: &str = ";∞;bank;faucet;";
+/// Payer on the transfer log for faucet grants.
+pub const FAUCET_LABEL: &str = ";∞;faucet;";
+/// Per-claim decay exponent of the airdrop curve.
+const DECAY: f64 = 0.080_820_040_000_8;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaucetConfig {
+    pub planet: PlanetId,
+    /// The first grant; later ones decay from here.
+    pub amount: u128,
+    /// Total the faucet pays per UTC day.
+    pub daily_budget: u128,
+    /// Grants per network address per day.
+    pub per_ip: u32,
+}
+
+impl FaucetConfig {
+    /// `None` when `OMEGA_FAUCET` is unset or `off`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let planet = match std::env::var("OMEGA_FAUCET") {
+            Ok(raw) if !raw.trim().is_empty() && raw.trim() != "off" => PlanetId::parse(&raw)
+                .ok_or_else(|| format!("OMEGA_FAUCET names no planet: `{raw}`"))?,
+            _ => return Ok(None),
+        };
+        let number = |name: &str, default: u128| -> Result<u128, String> {
+            match std::env::var(name) {
+                Ok(raw) => raw
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{name} must be a whole number, not `{raw}`")),
+                Err(_) => Ok(default),
+            }
+        };
+        let config = Self {
+            planet,
+            amount: number("OMEGA_FAUCET_AMOUNT", 888)?,
+            daily_budget: number("OMEGA_FAUCET_BUDGET", 88_248)?,
+            per_ip: u32::try_from(number("OMEGA_FAUCET_PER_IP", 1)?)
+                .map_err(|_| "OMEGA_FAUCET_PER_IP is too large".to_string())?,
+        };
+        if config.amount == 0 || config.daily_budget == 0 || config.per_ip == 0 {
+            return Err("faucet amount, budget and per-IP limit must be at least 1".into());
+        }
+        Ok(Some(config))
+    }
+}
+
+/// The address a claim counts against. Behind a trusted proxy
+/// (`OMEGA_TRUSTED_PROXY=1`) that is the last `X-Forwarded-For` hop, the one
+/// the proxy appended; earlier entries come from the client and prove
+/// nothing. Otherwise the header is ignored for the socket's peer.
+pub fn client_address(
+    forwarded_for: Option<&str>,
+    peer: Option<IpAddr>,
+    trusted_proxy: bool,
+) -> Option<String> {
+    let hop = forwarded_for
+        .filter(|_| trusted_proxy)
+        .and_then(|v| v.rsplit(',').next())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty());
+    hop.map(str::to_string)
+        .or_else(|| peer.map(|ip| ip.to_string()))
+}
+
+/// What `GET /omega/faucet` shows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FaucetStatus {
+    pub planet: PlanetId,
+    /// What the next grant pays, before the budget cap.
+    pub next_amount: u128,
+    pub daily_budget: u128,
+    pub remaining_today: u128,
+    /// When today's budget and limits reset.
+    pub resets_ms: i64,
+    /// Grants since the faucet opened.
+    pub claims: u64,
+}
+
+/// One paid claim.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FaucetGrant {
+    pub planet: PlanetId,
+    pub amount: u128,
+    /// The grant on the transfer log, paid from [`FAUCET_LABEL`].
+    pub transfer_id: u64,
+    pub remaining_today: u128,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FaucetState {
+    /// UTC day the counters below cover.
+    day: i64,
+    paid_today: u128,
+    phones: BTreeSet<String>,
+    /// Hashed address → grants today.
+    ips: BTreeMap<String, u32>,
+    claims: u64,
+}
+
+#[derive(Debug)]
+pub struct Faucet {
+    config: FaucetConfig,
+    store: GatewayStore,
+    state: Mutex<FaucetState>,
+}
+
+impl Faucet {
+    pub fn load(config: FaucetConfig, store: GatewayStore) -> Self {
+        let state = store.load_json(FAUCET_PATH).unwrap_or_default();
+        Self {
+            config,
+            store,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn planet(&self) -> PlanetId {
+        self.config.planet
+    }
+
+    pub fn status(&self, now_ms: i64) -> FaucetStatus {
+        let mut state = self.state.lock().expect("faucet mutex poisoned");
+        self.roll_day(&mut state, now_ms);
+        FaucetStatus {
+            planet: self.config.planet,
+            next_amount: self.grant(state.claims),
+            daily_budget: self.config.daily_budget,
+            remaining_today: self.config.daily_budget.saturating_sub(state.paid_today),
+            resets_ms: (state.day + 1) * DAY_MS,
+            claims: state.claims,
+        }
+    }
+
+    /// Admit a claim by `owner` (a phone key) from `ip` and count it,
+    /// returning the amount to pay.
+    pub fn claim(&self, owner: &str, ip: Option<&str>, now_ms: i64) -> Result<u128, FrameError> {
+        let mut state = self.state.lock().expect("faucet mutex poisoned");
+        self.roll_day(&mut state, now_ms);
+        if state.phones.contains(owner) {
+            return Err(FrameError::Conflict(
+                "this phone already claimed from the faucet today".into(),
+            ));
+        }
+        let ip = ip.map(|ip| blake3::hash(ip.trim().as_bytes()).to_hex().to_string());
+        if let Some(ip) = &ip {
+            if state.ips.get(ip).copied().unwrap_or_default() >= self.config.per_ip {
+                return Err(FrameError::Conflict(
+                    "this network already claimed from the faucet today".into(),
+                ));
+            }
+        }
+        let remaining = self.config.daily_budget.saturating_sub(state.paid_today);
+        let amount = self.grant(state.claims).min(remaining);
+        if amount == 0 {
+            return Err(FrameError::Conflict(
+                "the faucet is dry until tomorrow".into(),
+            ));
+        }
+        state.paid_today += amount;
+        state.claims += 1;
+        state.phones.insert(owner.to_string());
+        if let Some(ip) = ip {
+            *state.ips.entry(ip).or_default() += 1;
+        }
+        if let Err(err) = self.store.save_json(FAUCET_PATH, &*state) {
+            warn!("[faucet] failed to persist {FAUCET_PATH}: {err}");
+        }
+        Ok(amount)
+    }
+
+    /// Grant for claim number `claims`; never below 1.
+    fn grant(&self, claims: u64) -> u128 {
+        let scale = PHI.powf(-DECAY * claims as f64);
+        ((self.config.amount as f64 * scale) as u128).max(1)
+    }
+
+    fn roll_day(&self, state: &mut FaucetState, now_ms: i64) {
+        let day = now_ms.div_euclid(DAY_MS);
+        if state.day != day {
+            *state = FaucetState {
+                day,
+                claims: state.claims,
+                ..FaucetState::default()
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grants_decay_and_respect_phone_ip_and_budget_limits() {
+        let config = FaucetConfig {
+            planet: PlanetId::Earth,
+            amount: 1_000,
+            daily_budget: 2_500,
+            per_ip: 2,
+        };
+        let faucet = Faucet::load(config, GatewayStore::default());
+        let noon = 20_000 * DAY_MS + DAY_MS / 2;
+
+        let first = faucet.claim("alice", Some("10.0.0.1"), noon).unwrap();
+        assert_eq!(first, 1_000);
+        assert!(faucet.claim("alice", Some("10.0.0.2"), noon).is_err());
+        let second = faucet.claim("bob", Some("10.0.0.1"), noon).unwrap();
+        assert!(second < first && second > 900);
+        assert!(faucet.claim("carol", Some("10.0.0.1"), noon).is_err());
+        // The budget caps the third grant, then the faucet is dry.
+        let third = faucet.claim("carol", None, noon).unwrap();
+        assert_eq!(first + second + third, 2_500);
+        assert!(faucet.claim("dave", None, noon).is_err());
+
+        let status = faucet.status(noon);
+        assert_eq!((status.remaining_today, status.claims), (0, 3));
+        assert_eq!(status.resets_ms, 20_001 * DAY_MS);
+        let tomorrow = faucet.status(noon + DAY_MS);
+        assert_eq!(tomorrow.remaining_today, 2_500);
+        assert!(tomorrow.next_amount < third.max(second));
+        assert!(faucet
+            .claim("alice", Some("10.0.0.1"), noon + DAY_MS)
+            .is_ok());
+    }
+
+    #[test]
+    fn claims_count_against_the_proxy_appended_hop() {
+        let peer = Some(IpAddr::from([10, 0, 0, 9]));
+        let forwarded = Some("6.6.6.6, 203.0.113.7");
+        assert_eq!(
+            client_address(forwarded, peer, true).as_deref(),
+            Some("203.0.113.7")
+        );
+        // Without a trusted proxy the header is the client's to forge.
+        assert_eq!(
+            client_address(forwarded, peer, false).as_deref(),
+            Some("10.0.0.9")
+        );
+        assert_eq!(
+            client_address(Some(" "), peer, true).as_deref(),
+            Some("10.0.0.9")
+        );
+        assert_eq!(client_address(None, None, true), None);
+    }
+}
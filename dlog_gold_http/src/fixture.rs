@@ -0,0 +1,137 @@
+//! Golden frame/ack transcripts.
+//!
+//! A transcript is one handshake followed by frames and the ack each one is
+//! expected to produce. Files live in `fixtures/` and are recorded from a live
+//! gateway with `dlog_http4_client record <path>`; the test below replays every
+//! one against a fresh in-process gateway so protocol changes show up as diffs.
+//!
+//! Acks are compared on `accepted`, the routed Ω-paths, and notes. The session
+//! id is written as `{session}` so transcripts survive re-handshaking. Bank
+//! interest accrues per sealed block, so the runner seals up to the recorded
+//! `height` first and balances replay exactly. The recorded `phone` stands in
+//! for a verified one, since the runner has no SMS step. Frames are re-signed
+//! with the replay session's `frame_key`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::frame_signing;
+use crate::omega::{
+    FrameAck, FrameEnvelope, FrameKind, HandshakeRequest, OmegaGateway, SessionGrant,
+};
+
+pub const SESSION_PLACEHOLDER: &str = "{session}";
+
+#[derive(Debug, Deserialize)]
+pub struct Transcript {
+    pub name: String,
+    /// Block height the session was recorded at (no block sealed during it).
+    #[serde(default)]
+    pub height: u64,
+    pub handshake: HandshakeRequest,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Step {
+    pub frame: RecordedFrame,
+    pub ack: ExpectedAck,
+}
+
+/// A frame as sent, minus the session id.
+#[derive(Debug, Deserialize)]
+pub struct RecordedFrame {
+    pub seq: u64,
+    pub namespace: String,
+    pub kind: FrameKind,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedAck {
+    pub accepted: bool,
+    pub routed: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+impl ExpectedAck {
+    fn observed(ack: FrameAck) -> Self {
+        let session = ack.session_id;
+        Self {
+            accepted: ack.accepted,
+            routed: ack.routed.into_iter().map(|hint| hint.omega_path).collect(),
+            notes: ack
+                .notes
+                .into_iter()
+                .map(|note| note.replace(&session, SESSION_PLACEHOLDER))
+                .collect(),
+        }
+    }
+}
+
+/// Replay `transcript` against `gateway`; the error names the first step whose
+/// ack differs and carries the observed ack as JSON.
+pub fn replay(gateway: &OmegaGateway, transcript: Transcript) -> Result<(), String> {
+    let name = transcript.name;
+    while gateway.block_height() < transcript.height {
+        gateway.seal_block();
+    }
+    let grant = SessionGrant {
+        phone: transcript.handshake.phone.clone(),
+        admin: false,
+    };
+    let handshake = gateway
+        .handle_handshake(transcript.handshake, grant)
+        .map_err(|err| format!("{name}: handshake rejected: {err}"))?;
+    for (index, step) in transcript.steps.into_iter().enumerate() {
+        let frame = FrameEnvelope {
+            session_id: handshake.session_id.clone(),
+            seq: step.frame.seq,
+            namespace: step.frame.namespace,
+            kind: step.frame.kind,
+            payload: step.frame.payload,
+            signature: None,
+        };
+        let ack = gateway.handle_frame(frame_signing::signed(frame, &handshake.frame_key));
+        let observed = ExpectedAck::observed(ack);
+        if observed != step.ack {
+            return Err(format!(
+                "{name}: step {index} diverged\n  observed {}",
+                serde_json::to_string(&observed).unwrap_or_default()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::GatewayStore;
+    use std::path::Path;
+
+    #[test]
+    fn golden_transcripts_replay() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let mut paths: Vec<_> = std::fs::read_dir(&dir)
+            .expect("fixtures dir")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "no transcripts in {}", dir.display());
+
+        let failures: Vec<String> = paths
+            .iter()
+            .filter_map(|path| {
+                let raw = std::fs::read_to_string(path).unwrap();
+                let transcript: Transcript = serde_json::from_str(&raw)
+                    .unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+                let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+                replay(&gateway, transcript).err()
+            })
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
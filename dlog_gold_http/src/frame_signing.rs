@@ -0,0 +1,175 @@
+//! Signed frames: bank frames MAC'd under a per-session key.
+//!
+//! A session id is all a frame names, and anyone who learns one could send
+//! bank frames as that session. So every handshake also hands out a fresh
+//! `frame_key`, and the client signs each frame with a MAC under it over
+//! the session id, seq, namespace and payload digest
+//! ([`frame_signing_bytes`]), sent as the frame's `signature`. Bank frames
+//! (`QUERY`, `EVENT`, and the `MINE_JOB`/`MINE_RESULT` frames that pay into
+//! the bank) with a bad signature are rejected, and so are unsigned ones
+//! under `OMEGA_FRAME_SIGNATURES=require`, the default. `flag` lets unsigned
+//! reads from clients that predate frame keys through with a note; bank
+//! writes must be signed either way. A signed frame's seq has to beat the
+//! session's last one, so a captured frame can't be replayed. Replicas check
+//! before they relay, so the leader doesn't check `RELAY` frames again.
+
+use corelib::consensus::{frame_signing_bytes, BlockSigner, KeyedSigner};
+use uuid::Uuid;
+
+use crate::omega::{FrameEnvelope, FrameKind, OmegaGateway};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignaturePolicy {
+    /// Let unsigned bank reads through with a note.
+    Flag,
+    /// Reject unsigned bank frames.
+    #[default]
+    Require,
+}
+
+impl SignaturePolicy {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim() {
+            "flag" => Some(Self::Flag),
+            "require" => Some(Self::Require),
+            _ => None,
+        }
+    }
+}
+
+/// A fresh session key: two v4 UUIDs as hex.
+pub fn mint_frame_key() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn signing_bytes(frame: &FrameEnvelope) -> Vec<u8> {
+    frame_signing_bytes(
+        &frame.session_id,
+        frame.seq,
+        &frame.namespace,
+        &frame.payload,
+    )
+}
+
+/// `frame` signed under `frame_key`, as a client would send it.
+#[cfg(test)]
+pub fn signed(mut frame: FrameEnvelope, frame_key: &str) -> FrameEnvelope {
+    frame.signature = Some(KeyedSigner::from_secret(frame_key).sign(&signing_bytes(&frame)));
+    frame
+}
+
+/// Kinds that reach the bank, and so must be signed.
+pub fn needs_signature(kind: &FrameKind) -> bool {
+    matches!(
+        kind,
+        FrameKind::Query | FrameKind::Event | FrameKind::MineJob | FrameKind::MineResult
+    )
+}
+
+/// Check `frame` against its session's `frame_key`: a note when an unsigned
+/// frame is let through, or why it's rejected.
+pub fn check(
+    policy: SignaturePolicy,
+    frame_key: Option<&str>,
+    frame: &FrameEnvelope,
+) -> Result<Option<String>, String> {
+    if !needs_signature(&frame.kind) {
+        return Ok(None);
+    }
+    match (frame.signature.as_deref(), frame_key) {
+        (None, _) if policy == SignaturePolicy::Flag && !OmegaGateway::is_bank_write(frame) => {
+            Ok(Some(format!(
+                "{:?} unsigned: flagged; sign with the handshake's frame_key",
+                frame.kind
+            )))
+        }
+        (None, _) => Err(format!("{:?} rejected: unsigned frame", frame.kind)),
+        (Some(signature), Some(key))
+            if KeyedSigner::from_secret(key).verify(&signing_bytes(frame), signature) =>
+        {
+            Ok(None)
+        }
+        (Some(_), _) => Err(format!("{:?} rejected: bad frame signature", frame.kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(seq: u64) -> FrameEnvelope {
+        FrameEnvelope {
+            session_id: "s1".into(),
+            seq,
+            namespace: ";∞;bank;".into(),
+            kind: FrameKind::Event,
+            payload: serde_json::json!({ "kind": "transfer", "amount": 5 }),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn bank_frames_need_the_session_key() {
+        let key = mint_frame_key();
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, mint_frame_key());
+
+        let good = signed(transfer(1), &key);
+        for policy in [SignaturePolicy::Flag, SignaturePolicy::Require] {
+            assert_eq!(check(policy, Some(&key), &good), Ok(None));
+        }
+        // Flagging only lets unsigned reads through; writes are refused.
+        let balance = FrameEnvelope {
+            kind: FrameKind::Query,
+            payload: serde_json::json!({ "kind": "balance" }),
+            ..transfer(1)
+        };
+        assert!(check(SignaturePolicy::Flag, Some(&key), &balance)
+            .unwrap()
+            .is_some());
+        let mine = FrameEnvelope {
+            kind: FrameKind::MineResult,
+            ..transfer(1)
+        };
+        for policy in [SignaturePolicy::Flag, SignaturePolicy::Require] {
+            assert_eq!(
+                check(policy, Some(&key), &transfer(1)),
+                Err("Event rejected: unsigned frame".into())
+            );
+            assert_eq!(
+                check(policy, Some(&key), &mine),
+                Err("MineResult rejected: unsigned frame".into())
+            );
+        }
+        assert_eq!(SignaturePolicy::default(), SignaturePolicy::Require);
+
+        // A signature doesn't carry over to another seq, key or session.
+        let replayed = FrameEnvelope {
+            seq: 2,
+            ..good.clone()
+        };
+        let hijacked = FrameEnvelope {
+            session_id: "s2".into(),
+            ..good.clone()
+        };
+        let forged = signed(transfer(1), &mint_frame_key());
+        for frame in [&replayed, &hijacked, &forged] {
+            assert_eq!(
+                check(SignaturePolicy::Flag, Some(&key), frame),
+                Err("Event rejected: bad frame signature".into())
+            );
+        }
+        assert!(check(SignaturePolicy::Flag, None, &good).is_err());
+
+        let tick = FrameEnvelope {
+            kind: FrameKind::TickFrame,
+            ..transfer(3)
+        };
+        assert_eq!(check(SignaturePolicy::Require, None, &tick), Ok(None));
+        assert_eq!(
+            SignaturePolicy::parse(" require "),
+            Some(SignaturePolicy::Require)
+        );
+        assert_eq!(SignaturePolicy::parse("off"), None);
+    }
+}
@@ -0,0 +1,483 @@
+//! WebSocket channel for frames (`GET /omega/ws?session=`).
+//!
+//! A client holding a session streams [`FrameEnvelope`]s as JSON messages
+//! and gets each one's [`FrameAck`] back on the same socket, next to what the
+//! gateway pushes on its own: bus events, notifications to the session's
+//! labels (incoming transfers, grants, gifts) and a tick per sealed block.
+//! Every message names its namespace (a frame's own for its ack,
+//! [`EVENTS_NAMESPACE`], [`NOTIFICATIONS_NAMESPACE`] and [`TICK_NAMESPACE`]
+//! for pushes, [`SOCKET_NAMESPACE`] for the socket itself), so one socket
+//! carries them all and clients split them apart again. Frames are ordered
+//! per namespace: a `seq` at or below the last one accepted in its namespace
+//! is refused as a replay.
+//!
+//! A session has one socket; opening a second closes the first with
+//! `replaced`. The socket closes when its session does.
+
+use axum::extract::ws::{Message, WebSocket};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::events::OmegaEvent;
+use crate::notifications::Notification;
+use crate::omega::{now_ms, FrameAck, FrameEnvelope, OmegaGateway};
+
+pub const EVENTS_NAMESPACE: &str = ";∞;events;";
+pub const NOTIFICATIONS_NAMESPACE: &str = ";∞;notifications;";
+pub const TICK_NAMESPACE: &str = ";∞;tick;";
+pub const SOCKET_NAMESPACE: &str = ";∞;socket;";
+/// Time between pings, and between checks that the session is still open.
+pub const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// One message from the gateway.
+#[derive(Debug, Clone, Serialize)]
+pub struct SocketMessage {
+    pub namespace: String,
+    #[serde(flatten)]
+    pub body: SocketBody,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SocketBody {
+    /// The ack of a frame sent on the socket.
+    Ack { ack: FrameAck },
+    /// An event from the bus, as `/omega/events` lists it.
+    Event { event: OmegaEvent },
+    /// A notification delivered to one of the session's labels.
+    Notification { notification: Notification },
+    /// A block sealed at `height`; `tick` and `next_tick_ms` as in acks.
+    Tick {
+        height: u64,
+        tick: u64,
+        next_tick_ms: i64,
+    },
+    /// A message that isn't a frame.
+    Error { message: String },
+    /// Why the gateway is closing the socket.
+    Closed { reason: String },
+}
+
+impl SocketMessage {
+    fn new(namespace: &str, body: SocketBody) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            body,
+        }
+    }
+}
+
+/// Traffic of one namespace on a socket.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NamespaceStats {
+    pub frames: u64,
+    pub last_seq: Option<u64>,
+}
+
+/// One open socket, for `/admin/sockets`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SocketView {
+    pub session_id: String,
+    pub connection: u64,
+    pub opened_ms: i64,
+    pub namespaces: BTreeMap<String, NamespaceStats>,
+}
+
+#[derive(Debug)]
+struct Socket {
+    connection: u64,
+    opened_ms: i64,
+    namespaces: BTreeMap<String, NamespaceStats>,
+    replaced: Arc<Notify>,
+}
+
+/// A socket's claim on its session.
+#[derive(Debug, Clone)]
+pub struct SocketHandle {
+    pub connection: u64,
+    /// Notified when another socket takes the session over.
+    pub replaced: Arc<Notify>,
+}
+
+/// The open socket of each session.
+#[derive(Debug, Default)]
+pub struct SocketRegistry {
+    sockets: Mutex<HashMap<String, Socket>>,
+    next_connection: AtomicU64,
+}
+
+impl SocketRegistry {
+    /// Give `session_id` a new socket, replacing the one it had.
+    pub fn open(&self, session_id: &str, now_ms: i64) -> SocketHandle {
+        let handle = SocketHandle {
+            connection: self.next_connection.fetch_add(1, Ordering::Relaxed) + 1,
+            replaced: Arc::new(Notify::new()),
+        };
+        let socket = Socket {
+            connection: handle.connection,
+            opened_ms: now_ms,
+            namespaces: BTreeMap::new(),
+            replaced: handle.replaced.clone(),
+        };
+        let mut sockets = self.sockets.lock().expect("sockets mutex poisoned");
+        if let Some(previous) = sockets.insert(session_id.to_string(), socket) {
+            previous.replaced.notify_one();
+        }
+        handle
+    }
+
+    /// Forget `connection`, unless another socket has taken its session.
+    pub fn close(&self, session_id: &str, connection: u64) {
+        let mut sockets = self.sockets.lock().expect("sockets mutex poisoned");
+        if sockets
+            .get(session_id)
+            .is_some_and(|socket| socket.connection == connection)
+        {
+            sockets.remove(session_id);
+        }
+    }
+
+    /// Count a frame in `namespace`; why it may not go, if its `seq` is
+    /// not past the last one accepted there.
+    pub fn admit(
+        &self,
+        session_id: &str,
+        connection: u64,
+        namespace: &str,
+        seq: u64,
+    ) -> Result<(), String> {
+        let mut sockets = self.sockets.lock().expect("sockets mutex poisoned");
+        let Some(socket) = sockets
+            .get_mut(session_id)
+            .filter(|socket| socket.connection == connection)
+        else {
+            return Err("socket replaced".into());
+        };
+        let stats = socket.namespaces.entry(namespace.to_string()).or_default();
+        if let Some(last) = stats.last_seq.filter(|last| seq <= *last) {
+            return Err(format!("stale seq {seq} in {namespace} (last {last})"));
+        }
+        stats.frames += 1;
+        stats.last_seq = Some(seq);
+        Ok(())
+    }
+
+    /// Open sockets, oldest first.
+    pub fn list(&self) -> Vec<SocketView> {
+        let sockets = self.sockets.lock().expect("sockets mutex poisoned");
+        let mut views: Vec<SocketView> = sockets
+            .iter()
+            .map(|(session_id, socket)| SocketView {
+                session_id: session_id.clone(),
+                connection: socket.connection,
+                opened_ms: socket.opened_ms,
+                namespaces: socket.namespaces.clone(),
+            })
+            .collect();
+        views.sort_by_key(|view| view.connection);
+        views
+    }
+}
+
+/// How far a socket's pushes have got.
+#[derive(Debug, Clone, Copy)]
+struct Cursors {
+    event: u64,
+    notification: u64,
+}
+
+impl Cursors {
+    /// Start after everything already delivered.
+    fn now(gateway: &OmegaGateway) -> Self {
+        Self {
+            event: gateway.events().last_seq(),
+            notification: gateway.events().inbox().last_id(),
+        }
+    }
+}
+
+/// Events and `session_id`'s notifications past `cursors`, moving them on.
+fn pushes(gateway: &OmegaGateway, session_id: &str, cursors: &mut Cursors) -> Vec<SocketMessage> {
+    let mut messages = Vec::new();
+    for event in gateway.events().since(cursors.event) {
+        cursors.event = event.seq;
+        let sealed = (event.kind == "block_sealed").then_some(event.height);
+        messages.push(SocketMessage::new(
+            EVENTS_NAMESPACE,
+            SocketBody::Event { event },
+        ));
+        if let Some(height) = sealed {
+            messages.push(SocketMessage::new(
+                TICK_NAMESPACE,
+                SocketBody::Tick {
+                    height,
+                    tick: gateway.current_tick(),
+                    next_tick_ms: gateway
+                        .ticks()
+                        .next_tick_ms(gateway.tick_interval_ms(session_id)),
+                },
+            ));
+        }
+    }
+    for notification in gateway.session_notifications(session_id, cursors.notification) {
+        cursors.notification = notification.id;
+        messages.push(SocketMessage::new(
+            NOTIFICATIONS_NAMESPACE,
+            SocketBody::Notification { notification },
+        ));
+    }
+    messages
+}
+
+/// The frame `raw` carries, or the message refusing it.
+fn read_frame(
+    gateway: &OmegaGateway,
+    registry: &SocketRegistry,
+    session_id: &str,
+    connection: u64,
+    raw: &[u8],
+) -> Result<FrameEnvelope, Box<SocketMessage>> {
+    let mut frame: FrameEnvelope = serde_json::from_slice(raw).map_err(|err| {
+        Box::new(SocketMessage::new(
+            SOCKET_NAMESPACE,
+            SocketBody::Error {
+                message: format!("malformed frame: {err}"),
+            },
+        ))
+    })?;
+    if frame.session_id.is_empty() {
+        frame.session_id = session_id.to_string();
+    }
+    let admitted = if frame.session_id != session_id {
+        Err("frame session does not match the socket".to_string())
+    } else {
+        registry.admit(session_id, connection, &frame.namespace, frame.seq)
+    };
+    match admitted {
+        Ok(()) => Ok(frame),
+        Err(note) => Err(Box::new(SocketMessage::new(
+            &frame.namespace,
+            SocketBody::Ack {
+                ack: gateway.frame_ack(&frame, false, vec![note]),
+            },
+        ))),
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &SocketMessage) -> bool {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text)).await.is_ok()
+}
+
+/// Serve `session_id`'s socket until either side closes it; `route` handles
+/// each frame as `POST /omega/frame` would.
+pub async fn serve<F, Fut>(
+    mut socket: WebSocket,
+    gateway: Arc<OmegaGateway>,
+    registry: Arc<SocketRegistry>,
+    session_id: String,
+    route: F,
+) where
+    F: Fn(FrameEnvelope) -> Fut,
+    Fut: Future<Output = FrameAck>,
+{
+    let handle = registry.open(&session_id, now_ms());
+    let mut bus = gateway.events().subscribe();
+    let mut cursors = Cursors::now(&gateway);
+    let mut ping =
+        tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    let reason = loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let raw = match incoming {
+                    Some(Ok(Message::Text(text))) => text.into_bytes(),
+                    Some(Ok(Message::Binary(bytes))) => bytes,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break None,
+                    Some(Ok(_)) => continue,
+                };
+                let frame =
+                    read_frame(&gateway, &registry, &session_id, handle.connection, &raw);
+                let reply = match frame {
+                    Ok(frame) => {
+                        let namespace = frame.namespace.clone();
+                        let ack = route(frame).await;
+                        SocketMessage::new(&namespace, SocketBody::Ack { ack })
+                    }
+                    Err(refused) => *refused,
+                };
+                if !send(&mut socket, &reply).await {
+                    break None;
+                }
+            }
+            changed = bus.changed() => {
+                if changed.is_err() {
+                    break Some("gateway shutting down");
+                }
+                let mut open = true;
+                for message in pushes(&gateway, &session_id, &mut cursors) {
+                    open = send(&mut socket, &message).await;
+                    if !open {
+                        break;
+                    }
+                }
+                if !open {
+                    break None;
+                }
+            }
+            _ = handle.replaced.notified() => break Some("replaced"),
+            _ = ping.tick() => {
+                if !gateway.has_session(&session_id) {
+                    break Some("session closed");
+                }
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    break None;
+                }
+            }
+        }
+    };
+    if let Some(reason) = reason {
+        let closed = SocketMessage::new(
+            SOCKET_NAMESPACE,
+            SocketBody::Closed {
+                reason: reason.into(),
+            },
+        );
+        send(&mut socket, &closed).await;
+        let _ = socket.close().await;
+    }
+    registry.close(&session_id, handle.connection);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::omega::{FrameKind, HandshakeRequest, SessionGrant, SessionKind};
+    use crate::store::GatewayStore;
+    use spec::PlanetId;
+
+    fn session(gateway: &OmegaGateway) -> String {
+        gateway
+            .handle_handshake(
+                HandshakeRequest {
+                    client_id: "c".into(),
+                    capabilities: vec!["banking".into()],
+                    requested_routes: Vec::new(),
+                    phone: None,
+                    session_token: None,
+                    planet: PlanetId::Earth,
+                    peer_mac: None,
+                    session_kind: SessionKind::Player,
+                },
+                SessionGrant {
+                    phone: Some("9132077554".into()),
+                    admin: false,
+                },
+            )
+            .unwrap()
+            .session_id
+    }
+
+    #[tokio::test]
+    async fn one_socket_per_session_and_frames_in_order_per_namespace() {
+        let registry = SocketRegistry::default();
+        let first = registry.open("s", 1);
+        let second = registry.open("s", 2);
+        tokio::time::timeout(Duration::from_secs(1), first.replaced.notified())
+            .await
+            .expect("the first socket is told it was replaced");
+        assert_eq!(
+            registry.admit("s", first.connection, "a", 1),
+            Err("socket replaced".to_string())
+        );
+        registry.close("s", first.connection);
+        assert_eq!(registry.list().len(), 1);
+
+        let conn = second.connection;
+        assert!(registry.admit("s", conn, ";∞;bank;", 5).is_ok());
+        assert!(registry.admit("s", conn, ";∞;bank;", 5).is_err());
+        assert!(registry.admit("s", conn, ";∞;game;", 3).is_ok());
+        assert!(registry.admit("s", conn, ";∞;bank;", 6).is_ok());
+        let view = &registry.list()[0];
+        assert_eq!(view.namespaces[";∞;bank;"].frames, 2);
+        assert_eq!(view.namespaces[";∞;bank;"].last_seq, Some(6));
+
+        registry.close("s", conn);
+        assert_eq!(registry.list().len(), 0);
+    }
+
+    #[test]
+    fn pushes_carry_events_ticks_and_the_sessions_notifications() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        gateway.freeze_interest();
+        let session_id = session(&gateway);
+        let registry = SocketRegistry::default();
+        let handle = registry.open(&session_id, 0);
+        let mut bus = gateway.events().subscribe();
+        let mut cursors = Cursors::now(&gateway);
+
+        let raw = serde_json::to_vec(&serde_json::json!({
+            "session_id": "",
+            "seq": 1,
+            "namespace": ";∞;bank;infinity;transfer;",
+            "kind": "EVENT",
+            "payload": {
+                "kind": "transfer",
+                "from": ";9132077554;comet;",
+                "to": ";9132077554;fun;",
+                "amount": 5,
+            },
+        }))
+        .unwrap();
+        let frame = read_frame(&gateway, &registry, &session_id, handle.connection, &raw)
+            .expect("the socket's session fills in");
+        assert_eq!(frame.kind, FrameKind::Event);
+        assert!(gateway.handle_frame(gateway.sign(frame)).accepted);
+        assert!(read_frame(&gateway, &registry, &session_id, handle.connection, &raw).is_err());
+        // The bus wakes once per event and the socket pushes each of them.
+        assert!(bus.has_changed().unwrap());
+        let transfer_seq = *bus.borrow_and_update();
+
+        gateway
+            .events()
+            .publish(7, "block_sealed", serde_json::json!({}));
+        assert!(bus.has_changed().unwrap());
+        assert_eq!(*bus.borrow_and_update(), transfer_seq + 1);
+        let messages = pushes(&gateway, &session_id, &mut cursors);
+        let pushed: Vec<u64> = messages
+            .iter()
+            .filter_map(|m| match &m.body {
+                SocketBody::Event { event } => Some(event.seq),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pushed, [transfer_seq, transfer_seq + 1]);
+        let shape: Vec<(&str, &str)> = messages
+            .iter()
+            .map(|m| {
+                let kind = match &m.body {
+                    SocketBody::Event { event } => event.kind.as_str(),
+                    SocketBody::Tick { .. } => "tick",
+                    SocketBody::Notification { notification } => notification.kind.as_str(),
+                    _ => "other",
+                };
+                (m.namespace.as_str(), kind)
+            })
+            .collect();
+        assert_eq!(
+            shape,
+            [
+                (EVENTS_NAMESPACE, "transfer"),
+                (EVENTS_NAMESPACE, "block_sealed"),
+                (TICK_NAMESPACE, "tick"),
+                (NOTIFICATIONS_NAMESPACE, "transfer"),
+            ]
+        );
+        assert!(pushes(&gateway, &session_id, &mut cursors).is_empty());
+        assert!(pushes(&gateway, "someone-else", &mut Cursors::now(&gateway)).is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! Genesis provisioning: `dlog_gold_http genesis <file>`.
+//!
+//! Loads a canon genesis file (see [`corelib::genesis`]) into an empty
+//! `OMEGA_ROOT`. On top of the corelib checks, labels must already be in
+//! the label charset and no phone may hold more than [`LABELS_PER_PHONE`].
+//! Phones are stored keyed when privacy mode is on. A file with any error
+//! writes nothing; a clean one writes the ledger, the label book, one label
+//! file per wallet and block 1, so the gateway boots at height 1 on the
+//! report's master root.
+
+use corelib::genesis::{GenesisPlan, GenesisReport};
+use corelib::privacy::PhonePrivacy;
+use dlog_error::StorageError;
+use std::collections::{HashMap, VecDeque};
+
+use crate::labels::{canonical_label_name, LabelRecord, LABELS_PER_PHONE};
+use crate::omega::{BlockCursor, BLOCK_CURSOR_PATH, BLOCK_WINDOW_PATH, LABELS_PATH, LEDGER_PATH};
+use crate::store::GatewayStore;
+
+/// Check and seal `text`; unless the report has errors or `dry_run` is set,
+/// write the result to `store`. Refuses a store that already has a chain or
+/// a ledger.
+pub fn provision(
+    store: &GatewayStore,
+    privacy: &PhonePrivacy,
+    text: &str,
+    producer: &str,
+    sealed_ms: i64,
+    dry_run: bool,
+) -> Result<GenesisReport, StorageError> {
+    let cursor = store.load_json_strict::<BlockCursor>(BLOCK_CURSOR_PATH)?;
+    if cursor.is_some_and(|cursor| cursor.height > 0) || store.load_text(LEDGER_PATH).is_some() {
+        return Err(StorageError::Config(
+            "genesis needs an empty OMEGA_ROOT; this one already has a ledger or blocks".into(),
+        ));
+    }
+
+    let mut plan = GenesisPlan::parse(text);
+    let mut per_phone: HashMap<String, usize> = HashMap::new();
+    let mut rejected = Vec::new();
+    for wallet in &mut plan.wallets {
+        let label = &wallet.file.label;
+        if canonical_label_name(label).ok().as_ref() != Some(label) {
+            rejected.push((wallet.line, format!("label `{label}` is not canonical")));
+            continue;
+        }
+        wallet.file.phone = privacy.phone_key(&wallet.file.phone);
+        let count = per_phone.entry(wallet.file.phone.clone()).or_default();
+        *count += 1;
+        if *count > LABELS_PER_PHONE {
+            rejected.push((
+                wallet.line,
+                format!("more than {LABELS_PER_PHONE} labels for one phone"),
+            ));
+        }
+    }
+    for (line, message) in rejected {
+        plan.wallets.retain(|wallet| wallet.line != line);
+        plan.reject(line, message);
+    }
+
+    let report = plan.seal(producer, sealed_ms);
+    if dry_run || !report.is_clean() {
+        return Ok(report);
+    }
+    let records: Vec<LabelRecord> = plan
+        .wallets
+        .iter()
+        .map(|wallet| LabelRecord {
+            label: wallet.key(),
+            name: wallet.file.label.clone(),
+            owner_phone: wallet.file.phone.clone(),
+            created_ms: sealed_ms,
+            archived_ms: None,
+        })
+        .collect();
+    for wallet in &plan.wallets {
+        store.save_label_file(&wallet.file)?;
+    }
+    store.save_json(LEDGER_PATH, &plan.ledgers())?;
+    store.save_json(LABELS_PATH, &records)?;
+    store.save_json(BLOCK_WINDOW_PATH, &VecDeque::from([report.block.clone()]))?;
+    // The cursor goes last: a run cut short leaves no height behind, so
+    // it can be retried once the partial files are removed.
+    store.save_json(
+        BLOCK_CURSOR_PATH,
+        &BlockCursor {
+            height: 1,
+            last_seal_ms: sealed_ms,
+            tip: Some(report.block.clone()),
+        },
+    )?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::LabelStatus;
+    use crate::omega::OmegaGateway;
+    use spec::PlanetId;
+    use tempfile::tempdir;
+
+    const GENESIS: &str = "\
+;9132077554;vortex;earth:8000;∞;∞;∞;∞;∞;∞;∞;
+;9132077554;comet;earth:800,moon:8;∞;∞;∞;∞;∞;∞;∞;
+;9132077554;vortex;earth:8000;∞;∞;∞;∞;∞;∞;∞;
+";
+
+    #[test]
+    fn provisioned_roots_boot_at_block_one_with_their_wallets() {
+        let dir = tempdir().unwrap();
+        let store = GatewayStore::at(dir.path());
+        let privacy = PhonePrivacy::disabled();
+
+        let bad = format!("{GENESIS};9132077554;Bad Name;earth:1;∞;∞;∞;∞;∞;∞;∞;\n");
+        let report = provision(&store, &privacy, &bad, "genesis", 1_000, false).unwrap();
+        assert_eq!(report.errors[0].line, 4);
+        assert!(store.load_text(LEDGER_PATH).is_none());
+        let dry = provision(&store, &privacy, GENESIS, "genesis", 1_000, true).unwrap();
+        assert!(dry.is_clean() && store.load_text(LEDGER_PATH).is_none());
+
+        let report = provision(&store, &privacy, GENESIS, "genesis", 1_000, false).unwrap();
+        assert_eq!((report.wallets, report.duplicates.len()), (2, 1));
+        assert_eq!(report.master_root, dry.master_root);
+
+        let gateway = OmegaGateway::with_store(store.clone()).unwrap();
+        assert_eq!(gateway.block_height(), 1);
+        assert_eq!(gateway.tip().unwrap().root, report.block.root);
+        let labels = gateway.labels_for("9132077554");
+        assert_eq!(labels.len(), 2);
+        assert!(labels.iter().all(|view| view.status == LabelStatus::Open));
+        let comet = labels
+            .iter()
+            .find(|view| view.label.contains("comet"))
+            .unwrap();
+        assert_eq!(comet.balances[&PlanetId::Moon], 8);
+
+        assert!(provision(&store, &privacy, GENESIS, "genesis", 2_000, false).is_err());
+    }
+}
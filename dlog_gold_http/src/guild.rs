@@ -0,0 +1,531 @@
+//! Guild accounts: shared treasuries run by roles.
+//!
+//! A guild is founded by a set of labels and owns a treasury label,
+//! `;∞;guild;<name>;`, which the bank ledgers hold like any other. Members
+//! can view the guild and its balances, treasurers can spend up to the
+//! guild's `treasurer_cap` at a time, and founders manage roles and spend
+//! without a cap. A spend above `approval_threshold` waits as a proposal
+//! until `approvals` distinct phones among the founders and treasurers have
+//! signed it. The book only tracks state; the gateway moves the funds.
+
+use dlog_error::{AuthError, DlogError, FrameError};
+use serde::{Deserialize, Serialize};
+use spec::PlanetId;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+pub const GUILD_MEMBERS_MAX: usize = 64;
+pub const APPROVALS_MAX: usize = 16;
+/// Spends kept per guild (pending and done) before the oldest done go.
+const SPEND_HISTORY: usize = 256;
+const GUILD_NAME_MAX: usize = 32;
+
+/// Ledger label holding `name`'s treasury.
+pub fn treasury_label(name: &str) -> String {
+    format!(";∞;guild;{name};")
+}
+
+/// Lowercase and enforce the guild name charset (the label charset).
+pub fn canonical_guild_name(raw: &str) -> Result<String, FrameError> {
+    let name = raw.trim().to_ascii_lowercase();
+    if name.is_empty() || name.len() > GUILD_NAME_MAX {
+        return Err(FrameError::Malformed(format!(
+            "guild name must be 1-{GUILD_NAME_MAX} characters"
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return Err(FrameError::Malformed(
+            "guild name may only use a-z, 0-9, '_' and '-'".into(),
+        ));
+    }
+    Ok(name)
+}
+
+/// Roles in ascending order of what they may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuildRole {
+    Member,
+    Treasurer,
+    Founder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuildPolicy {
+    /// Spends above this need `approvals` signers.
+    #[serde(default)]
+    pub approval_threshold: u128,
+    #[serde(default = "default_approvals")]
+    pub approvals: usize,
+    /// Most a treasurer may spend at once.
+    #[serde(default)]
+    pub treasurer_cap: u128,
+}
+
+fn default_approvals() -> usize {
+    2
+}
+
+impl GuildPolicy {
+    fn validate(&self) -> Result<(), FrameError> {
+        if !(1..=APPROVALS_MAX).contains(&self.approvals) {
+            return Err(FrameError::Malformed(format!(
+                "approvals must be 1-{APPROVALS_MAX}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendState {
+    Pending,
+    Executed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuildSpend {
+    pub id: u64,
+    pub planet: PlanetId,
+    /// Ledger key of the recipient.
+    pub to: String,
+    pub amount: u128,
+    pub proposed_by: String,
+    /// Signing labels, the proposer first.
+    pub approvals: Vec<String>,
+    pub state: SpendState,
+    pub proposed_ms: i64,
+    #[serde(default)]
+    pub executed_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Guild {
+    pub name: String,
+    pub treasury: String,
+    pub created_ms: i64,
+    pub policy: GuildPolicy,
+    /// Ledger key of each member label and its role.
+    pub members: BTreeMap<String, GuildRole>,
+    #[serde(default)]
+    pub spends: BTreeMap<u64, GuildSpend>,
+}
+
+impl Guild {
+    /// The highest role among the labels `is_mine` accepts, and that label.
+    pub fn role_of(&self, is_mine: impl Fn(&str) -> bool) -> Option<(String, GuildRole)> {
+        self.members
+            .iter()
+            .filter(|(label, _)| is_mine(label))
+            .max_by_key(|(_, role)| **role)
+            .map(|(label, role)| (label.clone(), *role))
+    }
+
+    /// Whether `spend` may be paid out: small enough to skip approval, or
+    /// signed by enough distinct phones that still hold a signing role.
+    pub fn approved(&self, spend: &GuildSpend) -> bool {
+        if spend.amount <= self.policy.approval_threshold {
+            return true;
+        }
+        let signers: BTreeSet<&str> = spend
+            .approvals
+            .iter()
+            .filter(|label| self.members.get(*label) >= Some(&GuildRole::Treasurer))
+            .map(|label| crate::alias::label_phone(label).unwrap_or(label))
+            .collect();
+        signers.len() >= self.policy.approvals
+    }
+
+    fn member(&self, is_mine: impl Fn(&str) -> bool) -> Result<(String, GuildRole), AuthError> {
+        self.role_of(is_mine)
+            .ok_or_else(|| AuthError::Forbidden(format!("not a member of guild {}", self.name)))
+    }
+
+    fn signer(&self, is_mine: impl Fn(&str) -> bool) -> Result<(String, GuildRole), AuthError> {
+        let (label, role) = self.member(is_mine)?;
+        if role < GuildRole::Treasurer {
+            return Err(AuthError::Forbidden(format!(
+                "only treasurers and founders move guild {} funds",
+                self.name
+            )));
+        }
+        Ok((label, role))
+    }
+}
+
+/// A guild as one of its members sees it.
+#[derive(Debug, Clone, Serialize)]
+pub struct GuildView {
+    #[serde(flatten)]
+    pub guild: Guild,
+    /// The caller's highest role.
+    pub role: GuildRole,
+    /// Treasury balance per planet.
+    pub balances: BTreeMap<PlanetId, u128>,
+}
+
+/// Found a guild: its name, founding labels (canon or `@handle`, at least
+/// one the caller's) and spending policy.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildCharter {
+    pub name: String,
+    pub founders: Vec<String>,
+    #[serde(flatten)]
+    pub policy: GuildPolicy,
+}
+
+/// Pay `amount` out of a treasury to `to` (canon or `@handle`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpendRequest {
+    #[serde(default)]
+    pub planet: PlanetId,
+    pub to: String,
+    pub amount: u128,
+}
+
+#[derive(Debug, Default)]
+pub struct GuildBook {
+    guilds: Mutex<BTreeMap<String, Guild>>,
+}
+
+impl GuildBook {
+    pub fn from_guilds(guilds: Vec<Guild>) -> Self {
+        Self {
+            guilds: Mutex::new(guilds.into_iter().map(|g| (g.name.clone(), g)).collect()),
+        }
+    }
+
+    pub fn guilds(&self) -> Vec<Guild> {
+        let guilds = self.guilds.lock().expect("guild mutex poisoned");
+        guilds.values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Guild> {
+        let guilds = self.guilds.lock().expect("guild mutex poisoned");
+        guilds.get(name).cloned()
+    }
+
+    /// The guild `name`, if a label `is_mine` accepts belongs to it.
+    pub fn view(&self, name: &str, is_mine: impl Fn(&str) -> bool) -> Result<Guild, DlogError> {
+        let guild = self
+            .get(name)
+            .ok_or_else(|| FrameError::NotFound(format!("guild {name}")))?;
+        guild.member(is_mine)?;
+        Ok(guild)
+    }
+
+    /// Guilds with a member label `is_mine` accepts.
+    pub fn member_of(&self, is_mine: impl Fn(&str) -> bool) -> Vec<Guild> {
+        let guilds = self.guilds.lock().expect("guild mutex poisoned");
+        guilds
+            .values()
+            .filter(|g| g.members.keys().any(|label| is_mine(label)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn found(
+        &self,
+        name: &str,
+        founders: &[String],
+        policy: GuildPolicy,
+        now_ms: i64,
+    ) -> Result<Guild, FrameError> {
+        policy.validate()?;
+        let members: BTreeMap<String, GuildRole> = founders
+            .iter()
+            .map(|label| (label.clone(), GuildRole::Founder))
+            .collect();
+        if members.is_empty() || members.len() > GUILD_MEMBERS_MAX {
+            return Err(FrameError::Malformed(format!(
+                "a guild needs 1-{GUILD_MEMBERS_MAX} founding labels"
+            )));
+        }
+        let mut guilds = self.guilds.lock().expect("guild mutex poisoned");
+        if guilds.contains_key(name) {
+            return Err(FrameError::Conflict(format!("guild {name} already exists")));
+        }
+        let guild = Guild {
+            name: name.to_string(),
+            treasury: treasury_label(name),
+            created_ms: now_ms,
+            policy,
+            members,
+            spends: BTreeMap::new(),
+        };
+        guilds.insert(name.to_string(), guild.clone());
+        Ok(guild)
+    }
+
+    /// Founders give `label` a role, or remove it with `None`. The last
+    /// founder can't be demoted.
+    pub fn set_role(
+        &self,
+        name: &str,
+        is_mine: impl Fn(&str) -> bool,
+        label: &str,
+        role: Option<GuildRole>,
+    ) -> Result<Guild, DlogError> {
+        self.update(name, |guild| {
+            if guild.member(is_mine)?.1 != GuildRole::Founder {
+                return Err(AuthError::Forbidden(format!(
+                    "only founders manage guild {name} roles"
+                ))
+                .into());
+            }
+            let founders = guild
+                .members
+                .iter()
+                .filter(|(l, r)| **r == GuildRole::Founder && l.as_str() != label)
+                .count();
+            if founders == 0 && role != Some(GuildRole::Founder) {
+                return Err(FrameError::Conflict(format!(
+                    "guild {name} needs at least one founder"
+                ))
+                .into());
+            }
+            match role {
+                Some(role) => {
+                    if !guild.members.contains_key(label)
+                        && guild.members.len() >= GUILD_MEMBERS_MAX
+                    {
+                        return Err(FrameError::Conflict(format!(
+                            "guild {name} already has {GUILD_MEMBERS_MAX} members"
+                        ))
+                        .into());
+                    }
+                    guild.members.insert(label.to_string(), role);
+                }
+                None => {
+                    guild.members.remove(label);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Record a spend proposed by one of the caller's signing labels.
+    pub fn propose(
+        &self,
+        name: &str,
+        is_mine: impl Fn(&str) -> bool,
+        planet: PlanetId,
+        to: &str,
+        amount: u128,
+        now_ms: i64,
+    ) -> Result<(Guild, GuildSpend), DlogError> {
+        if amount == 0 {
+            return Err(FrameError::Malformed("amount must be positive".into()).into());
+        }
+        let mut spend = None;
+        let guild = self.update(name, |guild| {
+            let (label, role) = guild.signer(is_mine)?;
+            if role == GuildRole::Treasurer && amount > guild.policy.treasurer_cap {
+                return Err(AuthError::Forbidden(format!(
+                    "treasurers may spend at most {} at once",
+                    guild.policy.treasurer_cap
+                ))
+                .into());
+            }
+            if guild.spends.len() >= SPEND_HISTORY {
+                let done = guild
+                    .spends
+                    .values()
+                    .find(|s| s.state == SpendState::Executed)
+                    .map(|s| s.id)
+                    .ok_or_else(|| {
+                        FrameError::Conflict(format!("guild {name} has too many pending spends"))
+                    })?;
+                guild.spends.remove(&done);
+            }
+            let proposed = GuildSpend {
+                id: guild
+                    .spends
+                    .keys()
+                    .next_back()
+                    .map(|id| id + 1)
+                    .unwrap_or(1),
+                planet,
+                to: to.to_string(),
+                amount,
+                proposed_by: label.clone(),
+                approvals: vec![label],
+                state: SpendState::Pending,
+                proposed_ms: now_ms,
+                executed_ms: None,
+            };
+            guild.spends.insert(proposed.id, proposed.clone());
+            spend = Some(proposed);
+            Ok(())
+        })?;
+        Ok((guild, spend.expect("propose records a spend")))
+    }
+
+    /// Sign pending spend `id` with one of the caller's signing labels.
+    /// Signing twice is harmless, so a spend that failed to pay out can be
+    /// retried by approving again.
+    pub fn approve(
+        &self,
+        name: &str,
+        id: u64,
+        is_mine: impl Fn(&str) -> bool,
+    ) -> Result<(Guild, GuildSpend), DlogError> {
+        let guild = self.update(name, |guild| {
+            let (label, _) = guild.signer(is_mine)?;
+            let spend = pending(guild, id)?;
+            if !spend.approvals.contains(&label) {
+                spend.approvals.push(label);
+            }
+            Ok(())
+        })?;
+        let spend = guild.spends[&id].clone();
+        Ok((guild, spend))
+    }
+
+    /// Mark spend `id` paid out.
+    pub fn executed(&self, name: &str, id: u64, now_ms: i64) -> Result<GuildSpend, DlogError> {
+        let guild = self.update(name, |guild| {
+            let spend = pending(guild, id)?;
+            spend.state = SpendState::Executed;
+            spend.executed_ms = Some(now_ms);
+            Ok(())
+        })?;
+        Ok(guild.spends[&id].clone())
+    }
+
+    /// Drop the member labels `owned` accepts (identity deletion), and fold
+    /// them into `tombstone` where past spends name them. The guilds they
+    /// left, and whether anything changed.
+    pub fn purge_members(
+        &self,
+        owned: impl Fn(&str) -> bool,
+        tombstone: &str,
+    ) -> (Vec<String>, bool) {
+        let mut guilds = self.guilds.lock().expect("guild mutex poisoned");
+        let (mut left, mut changed) = (Vec::new(), false);
+        for guild in guilds.values_mut() {
+            let before = guild.members.len();
+            guild.members.retain(|label, _| !owned(label));
+            if guild.members.len() < before {
+                left.push(guild.name.clone());
+                changed = true;
+            }
+            for spend in guild.spends.values_mut() {
+                let labels = [&mut spend.to, &mut spend.proposed_by]
+                    .into_iter()
+                    .chain(spend.approvals.iter_mut());
+                for label in labels.filter(|label| owned(label)) {
+                    *label = tombstone.to_string();
+                    changed = true;
+                }
+            }
+        }
+        (left, changed)
+    }
+
+    fn update(
+        &self,
+        name: &str,
+        apply: impl FnOnce(&mut Guild) -> Result<(), DlogError>,
+    ) -> Result<Guild, DlogError> {
+        let mut guilds = self.guilds.lock().expect("guild mutex poisoned");
+        let guild = guilds
+            .get_mut(name)
+            .ok_or_else(|| FrameError::NotFound(format!("guild {name}")))?;
+        apply(guild)?;
+        Ok(guild.clone())
+    }
+}
+
+fn pending(guild: &mut Guild, id: u64) -> Result<&mut GuildSpend, FrameError> {
+    let spend = guild
+        .spends
+        .get_mut(&id)
+        .ok_or_else(|| FrameError::NotFound(format!("guild spend {id}")))?;
+    if spend.state != SpendState::Pending {
+        return Err(FrameError::Conflict(format!(
+            "guild spend {id} was already executed"
+        )));
+    }
+    Ok(spend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_gate_spends_and_approvals_count_distinct_phones() {
+        let book = GuildBook::default();
+        let policy = GuildPolicy {
+            approval_threshold: 100,
+            approvals: 2,
+            treasurer_cap: 500,
+        };
+        let founders = [";9132077554;comet;".to_string()];
+        book.found("miners", &founders, policy, 0).unwrap();
+        assert!(book.found("miners", &founders, policy, 0).is_err());
+        assert!(canonical_guild_name("the miners").is_err());
+
+        let phone = |phone: &'static str| move |label: &str| label.starts_with(phone);
+        let founder = phone(";9132077554;");
+        let treasurer = phone(";5550001111;");
+        let outsider = phone(";5550002222;");
+        assert!(book.view("miners", outsider).is_err());
+        book.set_role(
+            "miners",
+            founder,
+            ";5550001111;main;",
+            Some(GuildRole::Treasurer),
+        )
+        .unwrap();
+        book.set_role(
+            "miners",
+            founder,
+            ";5550002222;alt;",
+            Some(GuildRole::Member),
+        )
+        .unwrap();
+        assert_eq!(book.member_of(outsider).len(), 1);
+        assert!(book
+            .set_role("miners", treasurer, ";5550002222;alt;", None)
+            .is_err());
+        assert!(book
+            .set_role("miners", founder, ";9132077554;comet;", None)
+            .is_err());
+
+        // Members can't spend; treasurers are capped.
+        assert!(book
+            .propose("miners", outsider, PlanetId::Earth, ";x;y;", 10, 0)
+            .is_err());
+        assert!(book
+            .propose("miners", treasurer, PlanetId::Earth, ";x;y;", 501, 0)
+            .is_err());
+        let (guild, small) = book
+            .propose("miners", treasurer, PlanetId::Earth, ";x;y;", 100, 0)
+            .unwrap();
+        assert!(guild.approved(&small));
+        let (guild, big) = book
+            .propose("miners", treasurer, PlanetId::Earth, ";x;y;", 400, 0)
+            .unwrap();
+        assert!(!guild.approved(&big));
+        // A second label of the same phone doesn't count twice.
+        book.set_role(
+            "miners",
+            founder,
+            ";5550001111;side;",
+            Some(GuildRole::Treasurer),
+        )
+        .unwrap();
+        let (guild, again) = book.approve("miners", big.id, treasurer).unwrap();
+        assert!(!guild.approved(&again));
+        let (guild, signed) = book.approve("miners", big.id, founder).unwrap();
+        assert!(guild.approved(&signed));
+        book.executed("miners", big.id, 5).unwrap();
+        assert!(book.approve("miners", big.id, founder).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Continuous economy checks behind `GET /admin/invariants`.
+//!
+//! Every `OMEGA_INVARIANTS_EVERY_SECS` (default 30; `0` turns the loop
+//! off) the gateway checks its invariants
+//! ([`OmegaGateway::check_invariants`](crate::omega::OmegaGateway::check_invariants))
+//! and keeps the report. Violations the [`AlertGate`] lets through are
+//! logged, audited and, with `OMEGA_INVARIANT_WEBHOOK` set, POSTed there as
+//! JSON carrying each one's expected and actual value.
+
+use corelib::invariants::{AlertGate, InvariantReport, Violation};
+use reqwest::Client;
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_EVERY_SECS: u64 = 30;
+
+#[derive(Debug)]
+pub struct InvariantMonitor {
+    latest: Mutex<Option<InvariantReport>>,
+    gate: Mutex<AlertGate>,
+    webhook: Option<String>,
+    http: Client,
+}
+
+impl InvariantMonitor {
+    pub fn new(webhook: Option<String>) -> Self {
+        Self {
+            latest: Mutex::new(None),
+            gate: Mutex::default(),
+            webhook,
+            http: Client::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("OMEGA_INVARIANT_WEBHOOK")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+        )
+    }
+
+    /// Time between background checks; `None` when they are off.
+    pub fn interval_from_env() -> Option<Duration> {
+        let secs = env::var("OMEGA_INVARIANTS_EVERY_SECS")
+            .ok()
+            .and_then(|raw| raw.trim().parse().ok())
+            .unwrap_or(DEFAULT_EVERY_SECS);
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    pub fn latest(&self) -> Option<InvariantReport> {
+        self.latest
+            .lock()
+            .expect("invariants mutex poisoned")
+            .clone()
+    }
+
+    /// Keep `report` as the latest and return the violations to alert on.
+    pub fn record(&self, report: InvariantReport) -> Vec<Violation> {
+        let fresh = self
+            .gate
+            .lock()
+            .expect("invariant gate mutex poisoned")
+            .admit(&report);
+        *self.latest.lock().expect("invariants mutex poisoned") = Some(report);
+        fresh
+    }
+
+    /// Log `violations` and post them to the webhook, if one is set.
+    pub async fn alert(&self, node_id: &str, violations: &[Violation]) {
+        for violation in violations {
+            warn!(
+                "[invariants] {} broken for {}: expected {}, found {}",
+                violation.invariant, violation.subject, violation.expected, violation.actual
+            );
+        }
+        let Some(url) = &self.webhook else {
+            return;
+        };
+        let body = serde_json::json!({
+            "service": "dlog_gold_http",
+            "node_id": node_id,
+            "violations": violations,
+        });
+        let sent = self
+            .http
+            .post(url)
+            .timeout(Duration::from_secs(5))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        if let Err(err) = sent {
+            warn!("[invariants] webhook failed: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_break_alerts_once_it_is_seen_twice() {
+        let monitor = InvariantMonitor::new(None);
+        let mut broken = InvariantReport::new(1);
+        broken.expect("supply", "earth", 10u128, 9);
+        assert!(monitor.record(broken.clone()).is_empty());
+        assert_eq!(monitor.record(broken.clone()).len(), 1);
+        assert!(monitor.record(broken).is_empty());
+        assert_eq!(monitor.latest().unwrap().violations.len(), 1);
+    }
+}
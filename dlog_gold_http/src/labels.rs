@@ -0,0 +1,255 @@
+//! Explicit account labels (`;9132077554;comet;`).
+//!
+//! Labels used to appear the first time money moved to them, so a typo in a
+//! transfer minted an orphan. Owners can now create labels up front (charset
+//! checked, at most [`LABELS_PER_PHONE`] open per phone), list them, and
+//! archive empty ones. Archived labels refuse incoming transfers. With
+//! `OMEGA_STRICT_LABELS=1` a transfer must also go to a label that was
+//! created, or that some ledger already holds from before the book existed.
+
+use corelib::label_file::{self, LabelFileError};
+use dlog_error::{BankError, FrameError};
+use serde::{Deserialize, Serialize};
+use spec::PlanetId;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+pub const LABELS_PER_PHONE: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelRecord {
+    /// Ledger key (`;<phone key>;<name>;`).
+    pub label: String,
+    pub name: String,
+    pub owner_phone: String,
+    pub created_ms: i64,
+    #[serde(default)]
+    pub archived_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelStatus {
+    Open,
+    Archived,
+    /// Holds funds but was never created (it appeared when money moved).
+    Implicit,
+}
+
+/// A label as its owner sees it.
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelView {
+    pub label: String,
+    pub status: LabelStatus,
+    pub created_ms: Option<i64>,
+    pub archived_ms: Option<i64>,
+    pub balances: BTreeMap<PlanetId, u128>,
+}
+
+#[derive(Debug, Default)]
+pub struct LabelBook {
+    by_label: Mutex<HashMap<String, LabelRecord>>,
+    strict: bool,
+}
+
+/// [`label_file::canonical_label`], refusals as malformed frames.
+pub fn canonical_label_name(raw: &str) -> Result<String, FrameError> {
+    label_file::canonical_label(raw).map_err(|err| match err {
+        LabelFileError::Invalid(why) => FrameError::Malformed(why),
+        other => FrameError::Malformed(other.to_string()),
+    })
+}
+
+impl LabelBook {
+    pub fn from_records(records: Vec<LabelRecord>) -> Self {
+        let book = Self::default();
+        book.replace(records);
+        book
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Take another book's records wholesale (read replicas).
+    pub fn replace(&self, records: Vec<LabelRecord>) {
+        *self.by_label.lock().expect("label mutex poisoned") =
+            records.into_iter().map(|r| (r.label.clone(), r)).collect();
+    }
+
+    pub fn records(&self) -> Vec<LabelRecord> {
+        let mut records: Vec<_> = self
+            .by_label
+            .lock()
+            .expect("label mutex poisoned")
+            .values()
+            .cloned()
+            .collect();
+        records.sort_by(|a, b| a.label.cmp(&b.label));
+        records
+    }
+
+    pub fn get(&self, label: &str) -> Option<LabelRecord> {
+        self.by_label
+            .lock()
+            .expect("label mutex poisoned")
+            .get(label)
+            .cloned()
+    }
+
+    /// Open `label` for `owner_phone`; creating an archived label reopens it.
+    pub fn create(
+        &self,
+        label: &str,
+        name: &str,
+        owner_phone: &str,
+        now_ms: i64,
+    ) -> Result<LabelRecord, FrameError> {
+        let mut by_label = self.by_label.lock().expect("label mutex poisoned");
+        if by_label
+            .get(label)
+            .is_some_and(|record| record.archived_ms.is_none())
+        {
+            return Err(FrameError::Conflict(format!("label {name} already exists")));
+        }
+        let open = by_label
+            .values()
+            .filter(|r| r.owner_phone == owner_phone && r.archived_ms.is_none())
+            .count();
+        if open >= LABELS_PER_PHONE {
+            return Err(FrameError::Conflict(format!(
+                "at most {LABELS_PER_PHONE} open labels per phone"
+            )));
+        }
+        let record = LabelRecord {
+            label: label.to_string(),
+            name: name.to_string(),
+            owner_phone: owner_phone.to_string(),
+            created_ms: now_ms,
+            archived_ms: None,
+        };
+        by_label.insert(label.to_string(), record.clone());
+        Ok(record)
+    }
+
+    /// Archive `label`. One that was never created is recorded as archived,
+    /// so implicit labels can be retired too; the caller checks it's empty.
+    pub fn archive(
+        &self,
+        label: &str,
+        name: &str,
+        owner_phone: &str,
+        now_ms: i64,
+    ) -> Result<LabelRecord, FrameError> {
+        let mut by_label = self.by_label.lock().expect("label mutex poisoned");
+        let record = by_label
+            .entry(label.to_string())
+            .or_insert_with(|| LabelRecord {
+                label: label.to_string(),
+                name: name.to_string(),
+                owner_phone: owner_phone.to_string(),
+                created_ms: now_ms,
+                archived_ms: None,
+            });
+        if record.archived_ms.is_some() {
+            return Err(FrameError::Conflict(format!(
+                "label {name} is already archived"
+            )));
+        }
+        record.archived_ms = Some(now_ms);
+        Ok(record.clone())
+    }
+
+    /// Archive `old` and open `new` for `owner_phone` in its place (label
+    /// recovery). Either may be missing from the book; neither counts
+    /// against the per-phone cap.
+    pub fn rebind(&self, old: &str, new: &str, name: &str, owner_phone: &str, now_ms: i64) {
+        let mut by_label = self.by_label.lock().expect("label mutex poisoned");
+        let retired = by_label
+            .entry(old.to_string())
+            .or_insert_with(|| LabelRecord {
+                label: old.to_string(),
+                name: name.to_string(),
+                owner_phone: crate::alias::label_phone(old)
+                    .unwrap_or_default()
+                    .to_string(),
+                created_ms: now_ms,
+                archived_ms: None,
+            });
+        retired.archived_ms.get_or_insert(now_ms);
+        by_label.insert(
+            new.to_string(),
+            LabelRecord {
+                label: new.to_string(),
+                name: name.to_string(),
+                owner_phone: owner_phone.to_string(),
+                created_ms: now_ms,
+                archived_ms: None,
+            },
+        );
+    }
+
+    /// Whether `label` may receive a transfer; `in_ledger` says whether any
+    /// ledger already holds it.
+    pub fn check_recipient(&self, label: &str, in_ledger: bool) -> Result<(), BankError> {
+        let open = match self
+            .by_label
+            .lock()
+            .expect("label mutex poisoned")
+            .get(label)
+        {
+            Some(record) => record.archived_ms.is_none(),
+            None => !self.strict || in_ledger,
+        };
+        if !open {
+            return Err(BankError::UnknownLabel(label.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use corelib::label_file::LABEL_MAX;
+
+    #[test]
+    fn labels_are_validated_capped_and_archivable() {
+        assert_eq!(canonical_label_name(";Comet;").unwrap(), "comet");
+        assert!(canonical_label_name("").is_err());
+        assert!(canonical_label_name("rent money").is_err());
+        assert!(canonical_label_name(&"x".repeat(LABEL_MAX + 1)).is_err());
+
+        let book = LabelBook::default();
+        for i in 0..LABELS_PER_PHONE {
+            book.create(&format!(";p;l{i};"), &format!("l{i}"), "p", 1)
+                .unwrap();
+        }
+        let over = book.create(";p;extra;", "extra", "p", 1).unwrap_err();
+        assert_eq!(over.status(), 409);
+        assert!(book.create(";p;l0;", "l0", "p", 1).is_err());
+        book.create(";q;l0;", "l0", "q", 1).unwrap();
+
+        book.archive(";p;l0;", "l0", "p", 2).unwrap();
+        assert!(book.archive(";p;l0;", "l0", "p", 3).is_err());
+        assert_eq!(
+            book.check_recipient(";p;l0;", true),
+            Err(BankError::UnknownLabel(";p;l0;".into()))
+        );
+        // Archiving frees quota, and creating again reopens.
+        book.create(";p;extra;", "extra", "p", 4).unwrap();
+        assert!(book.create(";p;l0;", "l0", "p", 4).is_err());
+        book.archive(";p;extra;", "extra", "p", 5).unwrap();
+        book.create(";p;l0;", "l0", "p", 6).unwrap();
+        book.check_recipient(";p;l0;", false).unwrap();
+
+        // Lenient books accept anything not archived; strict ones need the
+        // label created or already in a ledger.
+        book.check_recipient(";p;typo;", false).unwrap();
+        let strict = LabelBook::from_records(book.records()).strict(true);
+        assert!(strict.check_recipient(";p;typo;", false).is_err());
+        strict.check_recipient(";p;legacy;", true).unwrap();
+        strict.check_recipient(";p;l1;", false).unwrap();
+    }
+}
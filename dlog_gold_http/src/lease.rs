@@ -0,0 +1,286 @@
+//! Producer lease for hot-standby failover (`OMEGA_CONSENSUS=lease`).
+//!
+//! Every node in the deployment points `OMEGA_LEASE_DIR` at the same shared
+//! directory. The lease is one JSON file there (`producer;lease`) naming the
+//! holder, when the lease runs out, and a term that grows on each handover.
+//! The holder renews it on every seal tick; a standby whose tick finds it
+//! expired (the holder died) takes it over and starts sealing. A node that
+//! shuts down cleanly expires its own lease so a standby takes over on its
+//! next tick rather than after the TTL. Reads and writes go through a lock
+//! file so two standbys can't both win the same expired lease.
+
+use dlog_error::StorageError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::store::write_atomic;
+
+const LEASE_FILE: &str = "producer;lease";
+const LOCK_FILE: &str = "producer;lease;lock";
+/// A lock file older than this was left by a crashed node.
+const STALE_LOCK: Duration = Duration::from_secs(5);
+
+/// What the lease file holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseRecord {
+    pub holder: String,
+    pub expires_ms: i64,
+    pub term: u64,
+}
+
+/// Lease state for `/omega/status`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LeaseStatus {
+    /// Whether this node holds the lease (and so seals).
+    pub held: bool,
+    pub holder: Option<String>,
+    pub term: u64,
+    pub expires_ms: i64,
+    /// Times this node took the lease over.
+    pub acquired: u64,
+    /// Times this node lost it to expiry or another holder.
+    pub lost: u64,
+    pub last_change_ms: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ProducerLease {
+    dir: PathBuf,
+    node_id: String,
+    ttl_ms: i64,
+    state: Mutex<LeaseState>,
+}
+
+#[derive(Debug, Default)]
+struct LeaseState {
+    status: LeaseStatus,
+    /// Set on shutdown; the node never takes the lease again.
+    released: bool,
+}
+
+impl ProducerLease {
+    pub fn new(dir: impl Into<PathBuf>, node_id: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            node_id: node_id.into(),
+            ttl_ms: ttl.as_millis() as i64,
+            state: Mutex::new(LeaseState::default()),
+        }
+    }
+
+    /// `OMEGA_LEASE_DIR` (required) and `OMEGA_LEASE_TTL_SECONDS` (default
+    /// three block intervals).
+    pub fn from_env(node_id: &str, block_interval: Duration) -> Result<Self, String> {
+        let dir = std::env::var("OMEGA_LEASE_DIR")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or("lease needs OMEGA_LEASE_DIR")?;
+        let ttl = match std::env::var("OMEGA_LEASE_TTL_SECONDS") {
+            Ok(raw) => {
+                let secs: f64 = raw
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("OMEGA_LEASE_TTL_SECONDS {raw:?} is not a number"))?;
+                Duration::from_secs_f64(secs)
+            }
+            Err(_) => block_interval * 3,
+        };
+        if ttl <= block_interval {
+            return Err(format!(
+                "lease TTL {}s must outlast a block interval ({}s)",
+                ttl.as_secs_f64(),
+                block_interval.as_secs_f64()
+            ));
+        }
+        Ok(Self::new(dir, node_id, ttl))
+    }
+
+    /// Renew the lease if this node holds it, or take it over if it has
+    /// expired. Returns whether this node may seal now. When the lease can't
+    /// be read, a holder keeps sealing only until the expiry it last wrote,
+    /// since nobody else may take the lease before then.
+    pub fn tick(&self, now_ms: i64) -> bool {
+        if self.state.lock().expect("lease mutex poisoned").released {
+            return false;
+        }
+        let outcome = self.with_lock(|current| {
+            let next = match current {
+                Some(lease) if lease.holder == self.node_id => LeaseRecord {
+                    expires_ms: now_ms + self.ttl_ms,
+                    ..lease
+                },
+                Some(lease) if lease.expires_ms > now_ms => return Ok(((lease, false), None)),
+                other => LeaseRecord {
+                    holder: self.node_id.clone(),
+                    expires_ms: now_ms + self.ttl_ms,
+                    term: other.map_or(1, |lease| lease.term + 1),
+                },
+            };
+            Ok(((next.clone(), true), Some(next)))
+        });
+        self.record(outcome, now_ms)
+    }
+
+    /// Expire the lease now if this node holds it, and stop taking it.
+    pub fn release(&self, now_ms: i64) {
+        self.state.lock().expect("lease mutex poisoned").released = true;
+        let outcome = self.with_lock(|current| {
+            let lease = current.unwrap_or(LeaseRecord {
+                holder: String::new(),
+                expires_ms: now_ms,
+                term: 0,
+            });
+            if lease.holder != self.node_id {
+                return Ok(((lease, false), None));
+            }
+            info!("[lease] {} released the producer lease", self.node_id);
+            let expired = LeaseRecord {
+                expires_ms: now_ms,
+                ..lease
+            };
+            Ok(((expired.clone(), false), Some(expired)))
+        });
+        self.record(outcome, now_ms);
+    }
+
+    pub fn status(&self) -> LeaseStatus {
+        self.state
+            .lock()
+            .expect("lease mutex poisoned")
+            .status
+            .clone()
+    }
+
+    fn record(&self, outcome: Result<(LeaseRecord, bool), StorageError>, now_ms: i64) -> bool {
+        let mut state = self.state.lock().expect("lease mutex poisoned");
+        let released = state.released;
+        let status = &mut state.status;
+        let (lease, held) = match outcome {
+            Ok(ok) => {
+                status.last_error = None;
+                ok
+            }
+            Err(err) => {
+                warn!("[lease] {err}");
+                status.last_error = Some(err.to_string());
+                (
+                    LeaseRecord {
+                        holder: status.holder.clone().unwrap_or_default(),
+                        expires_ms: status.expires_ms,
+                        term: status.term,
+                    },
+                    status.held && status.expires_ms > now_ms && !released,
+                )
+            }
+        };
+        if held != status.held {
+            if held {
+                status.acquired += 1;
+                info!(
+                    "[lease] {} holds the producer lease (term {})",
+                    self.node_id, lease.term
+                );
+            } else {
+                status.lost += 1;
+                info!(
+                    "[lease] {} no longer holds the producer lease",
+                    self.node_id
+                );
+            }
+            status.last_change_ms = Some(now_ms);
+        }
+        status.held = held;
+        status.holder = Some(lease.holder).filter(|h| !h.is_empty());
+        status.term = lease.term;
+        status.expires_ms = lease.expires_ms;
+        held
+    }
+
+    /// Run `decide` on the current lease under the lock file and write the
+    /// record it returns, if any.
+    fn with_lock<T>(
+        &self,
+        decide: impl FnOnce(Option<LeaseRecord>) -> Result<(T, Option<LeaseRecord>), StorageError>,
+    ) -> Result<T, StorageError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let lock = self.dir.join(LOCK_FILE);
+        if let Err(err) = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock)
+        {
+            if err.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(err.into());
+            }
+            let age = std::fs::metadata(&lock)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if age.is_none_or(|age| age < STALE_LOCK) {
+                return Err(StorageError::Backend("lease lock is busy".into()));
+            }
+            warn!("[lease] clearing a stale lease lock");
+            std::fs::remove_file(&lock)?;
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock)?;
+        }
+        let result = self.read().and_then(|current| {
+            let (value, next) = decide(current)?;
+            if let Some(next) = next {
+                write_atomic(&self.dir.join(LEASE_FILE), &serde_json::to_vec(&next)?)?;
+            }
+            Ok(value)
+        });
+        if let Err(err) = std::fs::remove_file(&lock) {
+            warn!("[lease] failed to remove the lease lock: {err}");
+        }
+        result
+    }
+
+    fn read(&self) -> Result<Option<LeaseRecord>, StorageError> {
+        match std::fs::read(self.dir.join(LEASE_FILE)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn standby_takes_over_on_expiry_and_on_release() {
+        let dir = tempdir().unwrap();
+        let ttl = Duration::from_millis(100);
+        let a = ProducerLease::new(dir.path(), "a", ttl);
+        let b = ProducerLease::new(dir.path(), "b", ttl);
+
+        assert!(a.tick(0));
+        assert!(!b.tick(50));
+        assert!(a.tick(80)); // renewed until 180
+        assert!(!b.tick(150));
+        assert_eq!(b.status().holder.as_deref(), Some("a"));
+
+        // `a` stalls past its TTL; `b` takes over with a new term.
+        assert!(b.tick(200));
+        assert!(!a.tick(210));
+        let status = a.status();
+        assert_eq!((status.acquired, status.lost, status.term), (1, 1, 2));
+
+        // A clean shutdown hands over without waiting out the TTL.
+        b.release(220);
+        assert!(a.tick(230));
+        assert!(!b.tick(400));
+        assert_eq!(a.status().term, 3);
+        assert_eq!(b.status().lost, 1);
+    }
+}
@@ -1,30 +1,135 @@
+mod admission;
+mod alias;
+mod audit;
+mod auth_store;
+mod balance_export;
+mod base;
+mod bootstrap;
+mod bridge_queue;
+mod cadence;
+mod burn;
+mod calibration;
+mod commands;
+mod escrow;
+mod events;
+mod explorer;
+mod faucet;
+#[cfg(test)]
+mod fixture;
+mod frame_signing;
+mod frame_socket;
+mod genesis;
+mod guild;
+mod invariants;
+mod labels;
+mod lease;
+mod manifest;
+mod metrics;
+mod mining;
+mod mojang;
+mod monetary;
+mod notifications;
 mod omega;
+mod overview;
+mod peer;
+mod push;
+mod quota;
+mod recording;
+mod recovery;
+mod replica;
+mod schema;
+mod sky_assets;
+mod spending;
+mod status_feed;
+mod store;
+mod tick;
+mod transfer;
 
 use axum::{
-    body::Body,
-    extract::{Query, State},
-    http::{Request, StatusCode, Uri},
+    body::{Body, Bytes},
+    extract::{ws::WebSocketUpgrade, ConnectInfo, DefaultBodyLimit, Path, Query, State},
+    http::{HeaderMap, Method, Request, StatusCode, Uri},
     middleware::{self, Next},
-    response::{Html, IntoResponse, Redirect, Response},
+    response::{
+        sse::{Event, Sse},
+        Html, IntoResponse, Redirect, Response,
+    },
     routing::{get, post},
     Json, Router,
 };
-use spec::SkyShowConfig;
+use admission::{AdmissionConfig, AdmissionStats, HandshakeAdmission};
+use alias::{AliasRecord, AliasView};
+use audit::{AuditEntry, SessionTimeline};
+use auth_store::{token_key, PhoneAuthSession, SessionStore};
+use balance_export::{BalanceExportConfig, BalanceExporter};
+use corelib::consensus::{
+    Consensus, ExternalSigner, KeyedSigner, LeasedProducer, PeerSigned, RoundRobin, SingleProducer,
+};
+use corelib::exchange::{self, Quote};
+use corelib::invariants::InvariantReport;
+use corelib::octal::{octal_f64, octal_int, octal_ratio};
+use corelib::privacy::{redact_phone, redact_phones, PhonePrivacy};
+use corelib::ScheduledTask;
+use dlog_command::CommandReply;
+use dlog_error::{AuthError, BankError, DlogError, FrameError, StorageError};
+use dlog_http_cache::{cached, CachePolicy, Tagged};
+use dlog_profiling::{AllocStats, CountingAlloc, ProfileParams};
+#[cfg(feature = "chaos")]
+use dlog_resilience::chaos::{self, FaultPlan};
+use dlog_resilience::{
+    BreakerConfig, BreakerSnapshot, BreakerState, CallError, RetryPolicy, Upstream,
+};
+use escrow::{Escrow, EscrowRequest, ItemAssignment};
+use burn::{BurnHooks, BurnReceipt, BurnStatus};
+use cadence::TickCadence;
+use faucet::{FaucetConfig, FaucetGrant, FaucetStatus};
+use frame_signing::SignaturePolicy;
+use frame_socket::{SocketRegistry, SocketView};
+use events::OmegaEvent;
+use base::{BaseQuery, Based, NumberBase};
+use bootstrap::{BootstrapPlan, BootstrapReport};
+use calibration::{CalibrationUpdate, DeviceCalibration};
+use mojang::{MojangVerification, MojangVerifier, VerificationStatus};
+use monetary::{MonetaryChangeRequest, MonetaryHistory};
+use labels::LabelView;
+use recovery::{GuardianRequest, GuardianSet, Recovery};
+use lease::{LeaseStatus, ProducerLease};
+use spec::{
+    Capability, CompoundingMode, CurrencyZone, MonetaryChange, MonetarySpec, PlanetId, SkyAssetRef,
+    SkyShowConfig, PHI_TICK_HZ,
+};
+use recording::{InputDump, RecordingStatus};
+use replica::{spawn_replica_sync, Replica, ReplicaState, ReplicaStatus, PEER_MAC_HEADER};
+use peer::{spawn_peer_sync, BlocksResponse, PeerSet, PeerStatus, SYNC_BATCH};
+use sky_assets::{SkyAssets, SKY_ASSET_MAX_BYTES};
+use status_feed::{Resume, StatusFeed, HEARTBEAT_INTERVAL, OBSERVE_INTERVAL};
+use invariants::InvariantMonitor;
+use guild::{GuildCharter, GuildRole, GuildSpend, GuildView, SpendRequest};
+use notifications::InboxView;
+use push::{Device, DeviceRegistration, Platform, PushAdapter, StubPush, WebPush};
+use store::GatewayStore;
+use spending::SpendingSummary;
+use transfer::{TransferRecord, TransferSearch};
 use omega::{
-    AxisMode, BridgeInputSnapshot, BridgeInstruction, BridgePositionSnapshot, FrameAck,
-    FrameEnvelope, GatewayStatus, HandshakeRequest, HandshakeResponse, IdentityDescriptor,
-    OmegaGateway,
+    now_ms, AxisMode, BridgeInputSnapshot, BridgeInstruction, BridgePositionSnapshot,
+    CompoundingStatus, DeletionReceipt, DnsImport, EscrowLock, FrameAck, FrameEnvelope,
+    GatewayStatus, HandshakeRequest, HandshakeResponse, IdentityDescriptor, MigrationCode,
+    OmegaGateway, SessionGrant, SessionView, DEFAULT_SPECTATOR_SLOTS,
 };
-use dlog_sky::SkyTimeline;
+use dlog_sky::{SkyEvent, SkyPreview, SkyRender, SkyTimeline};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use futures_util::stream::{self, Stream};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
     env,
     net::SocketAddr,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use tokio::net::TcpListener;
+use tokio::sync::Notify;
 use tracing::{error, info, warn};
 
 #[derive(Clone)]
@@ -32,7 +137,31 @@ struct AppState {
     gateway: Arc<OmegaGateway>,
     presence: Client,
     presence_base: String,
+    /// `PRESENCE_SERVICE_TOKEN`: vouches for identity deletes of records
+    /// with no phone session (Mojang logins only).
+    presence_token: Option<String>,
+    /// Retry + circuit breaker around every presence call.
+    presence_upstream: Arc<Upstream>,
+    /// Checks `/identity/mojang` claims against Mojang before presence.
+    mojang: Arc<MojangVerifier>,
     phone_auth: Arc<PhoneAuth>,
+    /// Other gateways exchanging sealed blocks with this one.
+    peers: Arc<PeerSet>,
+    /// Producer lease under `OMEGA_CONSENSUS=lease`.
+    lease: Option<Arc<ProducerLease>>,
+    /// Set on a read replica (`OMEGA_REPLICA_OF`).
+    replica: Option<Arc<Replica>>,
+    /// Status deltas behind `/omega/status/stream`.
+    status_feed: Arc<StatusFeed>,
+    /// Latest invariant report and its alerts (`/admin/invariants`).
+    invariants: Arc<InvariantMonitor>,
+    /// Frame sockets open on `/omega/ws`, one per session.
+    sockets: Arc<SocketRegistry>,
+    /// Paces handshakes so a reconnect wave arrives spread out.
+    admission: Arc<HandshakeAdmission>,
+    /// `OMEGA_TRUSTED_PROXY=1`: a proxy appends the client's address to
+    /// `X-Forwarded-For`.
+    trusted_proxy: bool,
 }
 
 #[allow(dead_code)]
@@ -76,12 +205,406 @@ struct HealthResponse {
     status: &'static str,
     gateway_id: String,
     boot_ms: i64,
+    incarnation: u64,
+    upstreams: Vec<BreakerSnapshot>,
 }
 
 #[derive(Debug, Serialize, Clone)]
 struct SkyTimelineResponse {
     total_duration_ticks: u64,
     show: SkyShowConfig,
+    /// Planet timelines only: auroras and comets starting after the tip.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    upcoming_events: Vec<SkyEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SkyPreviewQuery {
+    #[serde(default)]
+    from_tick: u64,
+    /// Span to sample; defaults to one full loop of the show.
+    #[serde(default)]
+    ticks: Option<u64>,
+    #[serde(default = "default_preview_stride")]
+    stride: u64,
+}
+
+fn default_preview_stride() -> u64 {
+    8
+}
+
+#[derive(Debug, Deserialize)]
+struct TickQuery {
+    /// Wall time to convert to a tick.
+    #[serde(default)]
+    at_ms: Option<i64>,
+    /// Tick to convert to the wall time it starts at.
+    #[serde(default)]
+    at_tick: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct TickResponse {
+    tick: u64,
+    tick_hz: f64,
+    now_ms: i64,
+    next_tick_ms: i64,
+    boot_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tick_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_tick: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ms_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SkyRenderQuery {
+    /// Show tick to render; defaults to where the chain height puts it.
+    #[serde(default)]
+    tick: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct SkyRenderResponse {
+    world: String,
+    planet: PlanetId,
+    #[serde(flatten)]
+    render: SkyRender,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleRequest {
+    height: u64,
+    kind: String,
+    #[serde(default)]
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ScheduleListResponse {
+    block_height: u64,
+    pending: Vec<ScheduledTask>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    #[serde(default)]
+    since: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlocksQuery {
+    #[serde(default)]
+    from: u64,
+    #[serde(default = "default_blocks_limit")]
+    limit: usize,
+}
+
+fn default_blocks_limit() -> usize {
+    SYNC_BATCH
+}
+
+/// `/omega/status`: the gateway snapshot plus how its peers are doing.
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    #[serde(flatten)]
+    gateway: GatewayStatus,
+    peers: Vec<PeerStatus>,
+    /// Blocks the furthest reachable peer is ahead of this node.
+    sync_lag: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lease: Option<LeaseStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replica: Option<ReplicaStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct EventsResponse {
+    last_seq: u64,
+    events: Vec<OmegaEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BootstrapQuery {
+    /// List the changes without applying them.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DnsImportQuery {
+    /// Accept a table whose version is not newer than the current one.
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    #[serde(default = "default_audit_limit")]
+    limit: usize,
+}
+
+fn default_audit_limit() -> usize {
+    100
+}
+
+/// Heights `/admin/metrics` covers without a `from`.
+const DEFAULT_METRICS_SPAN: u64 = 10_000;
+
+#[derive(Debug, Deserialize)]
+struct MetricsQuery {
+    #[serde(default)]
+    planet: PlanetId,
+    from: Option<u64>,
+    to: Option<u64>,
+    #[serde(default = "default_metrics_points")]
+    points: usize,
+}
+
+fn default_metrics_points() -> usize {
+    240
+}
+
+/// Verified-session credentials carried by alias management calls.
+#[derive(Debug, Deserialize)]
+struct SessionAuth {
+    phone: String,
+    session_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AliasRegisterRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    handle: String,
+    /// Label segment to alias; defaults to the session's label.
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    public: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelCreateRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    label: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuildFoundRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    #[serde(flatten)]
+    charter: GuildCharter,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuildRoleRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    label: String,
+    /// New role; omitted or `null` removes the label from the guild.
+    #[serde(default)]
+    role: Option<GuildRole>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuildDepositRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    from: String,
+    #[serde(default)]
+    planet: PlanetId,
+    amount: u128,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuildSpendRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    #[serde(flatten)]
+    spend: SpendRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationsReadRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    /// Notifications to mark; all of them when empty.
+    #[serde(default)]
+    ids: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationsPurgeRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    /// Unread ones too, not just read ones.
+    #[serde(default)]
+    all: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimelineQuery {
+    /// Width of each bucket of frame counts.
+    #[serde(default = "default_timeline_bucket_ms")]
+    bucket_ms: i64,
+}
+
+fn default_timeline_bucket_ms() -> i64 {
+    60_000
+}
+
+#[derive(Debug, Deserialize)]
+struct FaucetClaimRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    /// One of the caller's labels, canon or `@handle`.
+    label: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BurnRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    /// One of the caller's labels, canon or `@handle`.
+    label: String,
+    #[serde(default)]
+    planet: PlanetId,
+    /// Hook name from `OMEGA_BURNS`, e.g. `portal_toll`.
+    action: String,
+    /// What the action cost, for hooks that burn a percentage of it.
+    price: Option<u128>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceRegisterRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    #[serde(flatten)]
+    device: DeviceRegistration,
+}
+
+#[derive(Debug, Serialize)]
+struct PushKeyResponse {
+    /// `applicationServerKey` for `pushManager.subscribe`; absent when web
+    /// push is off.
+    vapid_public_key: Option<String>,
+}
+
+/// How many notifications a read or purge call touched.
+#[derive(Debug, Serialize)]
+struct NotificationsChanged {
+    changed: usize,
+    unread: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct AliasReverseQuery {
+    label: String,
+    #[serde(default)]
+    phone: Option<String>,
+    #[serde(default)]
+    session_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EscrowOpenRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    #[serde(flatten)]
+    escrow: EscrowRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct CalibrationRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    #[serde(flatten)]
+    update: CalibrationUpdate,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuardianSetRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    #[serde(flatten)]
+    guardians: GuardianRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecoveryOpenRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    /// Label to recover onto the caller's phone, canon or `@handle`.
+    label: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecoveryApproveRequest {
+    #[serde(flatten)]
+    auth: SessionAuth,
+    /// The caller's guardian label, canon or `@handle`.
+    guardian: String,
+}
+
+/// `/omega/exchange/quote`: what a cross-zone transfer would deliver.
+#[derive(Debug, Deserialize)]
+struct ExchangeQuoteQuery {
+    from: PlanetId,
+    to: PlanetId,
+    amount: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ExchangeQuoteResponse {
+    from: PlanetId,
+    from_unit: &'static str,
+    to: PlanetId,
+    to_unit: &'static str,
+    #[serde(flatten)]
+    quote: Quote,
+}
+
+/// `/omega/bank/transfers`: one owned label's history, filtered.
+#[derive(Debug, Deserialize)]
+struct TransferSearchQuery {
+    phone: String,
+    session_token: String,
+    label: String,
+    #[serde(default)]
+    planet: Option<PlanetId>,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    from_height: u64,
+    #[serde(default)]
+    to_height: Option<u64>,
+    #[serde(default = "default_transfer_limit")]
+    limit: usize,
+}
+
+fn default_transfer_limit() -> usize {
+    50
+}
+
+#[derive(Debug, Deserialize)]
+struct SpendingQuery {
+    phone: String,
+    session_token: String,
+    label: String,
+    #[serde(default)]
+    planet: Option<PlanetId>,
+    #[serde(default)]
+    from_height: u64,
+    #[serde(default)]
+    to_height: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,6 +616,13 @@ struct MojangPresencePayload {
     display_name: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct MojangIdentityResponse {
+    /// Presence accepted the login.
+    forwarded: bool,
+    verification: MojangVerification,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct WebPresencePayload {
     phone: String,
@@ -114,7 +644,7 @@ struct PhoneStartRequest {
 struct PhoneStartResponse {
     session_token: String,
     expires_in_ms: i64,
-    providers: Vec<&'static str>,
+    providers: Vec<String>,
     biometric_required: bool,
     instructions: &'static str,
 }
@@ -147,6 +677,12 @@ struct BridgeInputPayload {
     stand_id: Option<String>,
     device: Option<String>,
     profile: Option<String>,
+    /// World the player is in, whose profile applies when none is named.
+    #[serde(default)]
+    world: Option<String>,
+    /// Player's label, canon or `@handle`, for its input calibration.
+    #[serde(default)]
+    label: Option<String>,
     #[serde(default)]
     buttons: Vec<ButtonEvent>,
     #[serde(default)]
@@ -223,6 +759,8 @@ impl BridgeInputPayload {
             stand_id: self.stand_id,
             device: self.device,
             profile: self.profile,
+            world: self.world,
+            label: self.label,
             buttons: self
                 .buttons
                 .into_iter()
@@ -238,247 +776,2488 @@ impl BridgeInputPayload {
     }
 }
 
-impl ButtonEvent {
-    fn into_button_snapshot(self) -> omega::BridgeButtonSnapshot {
-        omega::BridgeButtonSnapshot {
-            action: self.action,
-            state: match self.state {
-                ButtonStatePayload::Pressed => omega::ButtonState::Pressed,
-                ButtonStatePayload::Released => omega::ButtonState::Released,
-                ButtonStatePayload::Held => omega::ButtonState::Held,
-            },
-        }
-    }
+impl ButtonEvent {
+    fn into_button_snapshot(self) -> omega::BridgeButtonSnapshot {
+        omega::BridgeButtonSnapshot {
+            action: self.action,
+            state: match self.state {
+                ButtonStatePayload::Pressed => omega::ButtonState::Pressed,
+                ButtonStatePayload::Released => omega::ButtonState::Released,
+                ButtonStatePayload::Held => omega::ButtonState::Held,
+            },
+        }
+    }
+}
+
+impl AxisEvent {
+    fn into_axis_snapshot(self) -> omega::BridgeAxisSnapshot {
+        omega::BridgeAxisSnapshot {
+            action: self.action,
+            x: self.x,
+            y: self.y,
+            mode: match self.mode.unwrap_or_default() {
+                AxisModePayload::Relative => AxisMode::Relative,
+                AxisModePayload::Absolute => AxisMode::Absolute,
+            },
+        }
+    }
+}
+
+impl BridgePositionPayload {
+    fn into_snapshot(self) -> BridgePositionSnapshot {
+        BridgePositionSnapshot {
+            player_uuid: self.player_uuid,
+            session_id: self.session_id,
+            stand_id: self.stand_id,
+            world: self.world,
+            pos: self.position.into_vec3(),
+            velocity: self.velocity.map(|v| v.into_vec3()),
+            rotation: self.rotation.map(|r| r.into_rotation()),
+        }
+    }
+}
+
+impl Vec3Payload {
+    fn into_vec3(self) -> omega::Vec3f {
+        omega::Vec3f {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
+}
+
+impl RotationPayload {
+    fn into_rotation(self) -> omega::Rotation2d {
+        omega::Rotation2d {
+            yaw: self.yaw,
+            pitch: self.pitch,
+        }
+    }
+}
+
+const HOST_REDIRECTS: &[(&str, &str)] = &[
+    ("pool.dlog.gold", "minepool.gold"),
+    ("www.pool.dlog.gold", "minepool.gold"),
+    ("locks.dlog.gold", "locks.gold"),
+    ("www.locks.dlog.gold", "locks.gold"),
+];
+
+const SIGNUP_FRAMES: [&str; 4] = [
+    ";signup;frame=omega_shell;Deploy;the;GOLDEN;WALLET;STACK;protect;the;infinity;bank;",
+    ";signup;frame=phone_ignition;POST;/auth/phone/start;receive;session_token;Apple/Google;biometrics;required;",
+    ";signup;frame=biometric_lock;POST;/auth/phone/confirm;send;session_token;biometric_signature;phone=identity;",
+    ";signup;frame=http4_bridge;set;DLOG_PHONE/DLOG_LABEL;run;dlog_http4_client;handshake;tick;mine;",
+];
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .with_level(true)
+        .fmt_fields(redacting_fields())
+        .init();
+
+    // `dlog_gold_http migrate [--dry-run]` upgrades the files under
+    // OMEGA_ROOT and exits before anything loads them.
+    if env::args().nth(1).as_deref() == Some("migrate") {
+        let dry_run = env::args().skip(2).any(|arg| arg == "--dry-run");
+        match GatewayStore::from_env().migrate(dry_run) {
+            Ok(report) => {
+                print!("{report}");
+                if report.failures() > 0 {
+                    std::process::exit(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("migrate failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // `dlog_gold_http genesis <file> [--dry-run]` provisions the genesis
+    // wallets into an empty OMEGA_ROOT and seals block 1. The block names
+    // OMEGA_NODE_ID as its producer, or `genesis` when that is unset.
+    if env::args().nth(1).as_deref() == Some("genesis") {
+        let args: Vec<String> = env::args().skip(2).collect();
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        let Some(path) = args.iter().find(|arg| !arg.starts_with("--")) else {
+            eprintln!("usage: dlog_gold_http genesis <file> [--dry-run]");
+            std::process::exit(2);
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("genesis failed: {path}: {err}");
+                std::process::exit(1);
+            }
+        };
+        let producer = env::var("OMEGA_NODE_ID").unwrap_or_else(|_| "genesis".into());
+        match genesis::provision(
+            &GatewayStore::from_env(),
+            &PhonePrivacy::from_env(),
+            &text,
+            &producer,
+            now_ms(),
+            dry_run,
+        ) {
+            Ok(report) => {
+                print!("{report}");
+                if !report.is_clean() {
+                    std::process::exit(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("genesis failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Cloud Run injects PORT; default to 8080 for local runs
+    let port: u16 = env::var("PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(8080);
+
+    let presence_base =
+        env::var("PRESENCE_BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:4000".to_string());
+
+    let privacy = PhonePrivacy::from_env();
+    if privacy.enabled() {
+        info!("[privacy] phone numbers are stored as salted keys");
+    }
+
+    let store = GatewayStore::from_env();
+    let mut gateway = OmegaGateway::with_store(store.clone())
+        .expect("failed to load gateway state from OMEGA_ROOT")
+        .with_privacy(privacy.clone());
+    match BalanceExportConfig::from_env(&store) {
+        Ok(Some(config)) => {
+            info!(
+                "[export] balances every {} blocks to {}",
+                config.every,
+                config.dir.display()
+            );
+            gateway = gateway.with_balance_export(BalanceExporter::spawn(config));
+        }
+        Ok(None) => {}
+        Err(err) => warn!("[export] {err}; balance exports are off"),
+    }
+    let mut lease = None;
+    match consensus_from_env() {
+        Ok(Some((node_id, consensus, peer_key))) => {
+            info!("[consensus] node {node_id} running {}", consensus.name());
+            if consensus.name() == "lease" {
+                // Sealing without the lease could fork a live producer.
+                match ProducerLease::from_env(&node_id, block_interval()) {
+                    Ok(producer_lease) => lease = Some(Arc::new(producer_lease)),
+                    Err(err) => {
+                        error!("[lease] {err}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            gateway = gateway
+                .with_consensus(node_id, consensus)
+                .with_peer_key(peer_key);
+        }
+        Ok(None) => {
+            // Replicas and the leaders they follow share the peer key too.
+            if let Some(peer_key) = peer_key_from_env() {
+                gateway = gateway.with_peer_key(peer_key);
+            }
+        }
+        Err(err) => warn!("[consensus] {err}; running as a single node"),
+    }
+    let replica = Replica::from_env().map(Arc::new);
+    if let Some(replica) = &replica {
+        info!("[replica] read replica of {}", replica.leader());
+        if peer_key_from_env().is_none() {
+            error!("[replica] OMEGA_REPLICA_OF needs OMEGA_PEER_KEY");
+            std::process::exit(1);
+        }
+    }
+    match quota::FrameQuotas::from_env() {
+        Ok(quotas) => gateway = gateway.with_quotas(quotas),
+        Err(err) => warn!("[quota] {err}; keeping the default frame quotas"),
+    }
+    if let Ok(raw) = env::var("OMEGA_COMPOUNDING") {
+        match CompoundingMode::parse(&raw) {
+            Some(mode) => {
+                info!("[bank] holder interest compounds {}", mode.key());
+                gateway = gateway.with_compounding(mode);
+            }
+            None => warn!("[bank] unknown OMEGA_COMPOUNDING `{raw}`; compounding per block"),
+        }
+    }
+    if env::var("OMEGA_STRICT_LABELS").is_ok_and(|v| v.trim() == "1") {
+        info!("[labels] strict: transfers only reach created labels");
+        gateway = gateway.with_strict_labels(true);
+    }
+    if let Ok(raw) = env::var("OMEGA_MINING_DIFFICULTY") {
+        match raw.trim().parse::<u32>() {
+            Ok(bits) if bits <= mining::DIFFICULTY_MAX => {
+                info!("[mining] results need {bits} leading zero bits");
+                gateway = gateway.with_mining_difficulty(bits);
+            }
+            _ => warn!(
+                "[mining] OMEGA_MINING_DIFFICULTY `{raw}` is not 0..={}; keeping the φ difficulty",
+                mining::DIFFICULTY_MAX
+            ),
+        }
+    }
+    if let Ok(raw) = env::var("OMEGA_FRAME_SIGNATURES") {
+        match SignaturePolicy::parse(&raw) {
+            Some(SignaturePolicy::Flag) => {
+                warn!("[frames] unsigned bank reads are flagged; bank writes must still be signed");
+                gateway = gateway.with_frame_signatures(SignaturePolicy::Flag);
+            }
+            Some(SignaturePolicy::Require) => info!("[frames] unsigned bank frames are rejected"),
+            None => warn!(
+                "[frames] unknown OMEGA_FRAME_SIGNATURES `{raw}`; rejecting unsigned bank frames"
+            ),
+        }
+    }
+    match FaucetConfig::from_env() {
+        Ok(Some(config)) => {
+            warn!(
+                "[faucet] open on {}: {} per phone per day, {} a day in total",
+                config.planet.key(),
+                config.amount,
+                config.daily_budget
+            );
+            gateway = gateway.with_faucet(config);
+        }
+        Ok(None) => {}
+        Err(err) => warn!("[faucet] {err}; the faucet stays closed"),
+    }
+    match BurnHooks::from_env() {
+        Ok(Some(hooks)) => {
+            info!(
+                "[burn] hooks on: {}",
+                hooks.0.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+            gateway = gateway.with_burn_hooks(hooks);
+        }
+        Ok(None) => {}
+        Err(err) => warn!("[burn] {err}; nothing burns"),
+    }
+    if let Ok(raw) = env::var("OMEGA_SPECTATOR_SLOTS") {
+        match raw.trim().parse::<usize>() {
+            Ok(slots) => {
+                info!("[sessions] {slots} spectator slots");
+                gateway = gateway.with_spectator_slots(slots);
+            }
+            Err(_) => warn!(
+                "[sessions] OMEGA_SPECTATOR_SLOTS `{raw}` is not a count; keeping {DEFAULT_SPECTATOR_SLOTS}"
+            ),
+        }
+    }
+    match TickCadence::from_env() {
+        Ok(cadence) => {
+            info!(
+                "[sessions] tick intervals {}-{} ms",
+                cadence.min_ms, cadence.max_ms
+            );
+            gateway = gateway.with_tick_cadence(cadence);
+        }
+        Err(err) => warn!("[sessions] {err}; tick intervals keep their defaults"),
+    }
+    let mut push_adapters: Vec<Box<dyn PushAdapter>> = vec![
+        Box::new(StubPush(Platform::Apns)),
+        Box::new(StubPush(Platform::Fcm)),
+    ];
+    match WebPush::from_env() {
+        Ok(Some(web)) => {
+            info!("[push] web push on");
+            push_adapters.push(Box::new(web));
+        }
+        Ok(None) => info!("[push] no OMEGA_VAPID_* keys; web push is off"),
+        Err(err) => warn!("[push] {err}; web push is off"),
+    }
+    gateway = gateway.with_push(push_adapters);
+    if let Ok(path) = env::var("OMEGA_BOOTSTRAP") {
+        let plan = match std::fs::read_to_string(&path) {
+            Ok(text) => BootstrapPlan::parse(&text),
+            Err(err) => {
+                error!("[bootstrap] cannot read {path}: {err}");
+                std::process::exit(1);
+            }
+        };
+        match gateway.apply_bootstrap(&plan, false) {
+            Ok(report) => info!("[bootstrap] {path}: {} changes", report.changes.len()),
+            Err(err) => {
+                error!("[bootstrap] {path}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    #[cfg(feature = "chaos")]
+    match chaos::load_env() {
+        Ok(0) => {}
+        Ok(count) => warn!("[chaos] OMEGA_CHAOS: faults injected into {count} upstreams"),
+        Err(err) => warn!("[chaos] OMEGA_CHAOS ignored: {err}"),
+    }
+
+    let phone_auth = match auth_store::from_env(&store).await {
+        Ok(sessions) => match PhoneAuth::with_store(privacy, sessions.clone()).await {
+            Ok(auth) => {
+                info!(
+                    "[auth] {} sessions restored from {}",
+                    auth.sessions.lock().expect("phone auth lock").len(),
+                    sessions.name()
+                );
+                Arc::new(auth)
+            }
+            Err(err) => {
+                error!(
+                    "[auth] cannot restore sessions from {}: {err}",
+                    sessions.name()
+                );
+                std::process::exit(1);
+            }
+        },
+        Err(err) => {
+            error!("[auth] {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let admission = match AdmissionConfig::from_env() {
+        Ok(Some(config)) => {
+            info!(
+                "[sessions] admitting {} handshakes/s, bursts of {}",
+                config.per_second, config.burst
+            );
+            Some(config)
+        }
+        Ok(None) => {
+            info!("[sessions] handshake admission off");
+            None
+        }
+        Err(err) => {
+            let config = AdmissionConfig::default();
+            warn!(
+                "[sessions] {err}; admitting {} handshakes/s",
+                config.per_second
+            );
+            Some(config)
+        }
+    };
+    let state = AppState {
+        gateway: Arc::new(gateway),
+        presence: Client::new(),
+        presence_base,
+        presence_token: env::var("PRESENCE_SERVICE_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty()),
+        presence_upstream: Arc::new(Upstream::new(
+            "presence",
+            RetryPolicy::default(),
+            BreakerConfig::default(),
+        )),
+        mojang: Arc::new(MojangVerifier::from_env()),
+        phone_auth,
+        peers: Arc::new(PeerSet::from_env()),
+        lease,
+        replica,
+        status_feed: Arc::new(StatusFeed::default()),
+        invariants: Arc::new(InvariantMonitor::from_env()),
+        sockets: Arc::new(SocketRegistry::default()),
+        admission: Arc::new(HandshakeAdmission::new(admission)),
+        trusted_proxy: env::var("OMEGA_TRUSTED_PROXY").is_ok_and(|v| v.trim() == "1"),
+    };
+
+    let app = Router::new()
+        .route("/", get(root))
+        .route("/signup", get(signup_page))
+        .route("/signup/frame", get(signup_frame))
+        .route("/signup/qr", get(signup_qr))
+        .route("/health", get(health))
+        .route("/sky/timeline/default", get(sky_timeline_default))
+        .route("/sky/timeline/:planet", get(sky_timeline_planet))
+        .route("/sky/shows/:id/preview", get(sky_show_preview))
+        .route("/sky/render/:world", get(sky_render))
+        .route("/sky/assets/:key", get(sky_asset))
+        .route(
+            "/omega/sky/slides/:slide_id/asset",
+            axum::routing::put(sky_asset_register)
+                .layer(DefaultBodyLimit::max(SKY_ASSET_MAX_BYTES)),
+        )
+        .route("/omega/status", get(status))
+        .route("/omega/status/stream", get(status_stream))
+        .route("/omega/handshake", post(handshake))
+        .route("/omega/frame", post(frame))
+        .route("/omega/ws", get(omega_ws))
+        .route("/omega/command", post(run_command))
+        .route("/omega/bridge/input", post(bridge_input))
+        .route("/omega/bridge/position", post(bridge_position))
+        .route(
+            "/omega/bridge/manifest",
+            cached(get(bridge_manifest), CachePolicy::TIP),
+        )
+        .route("/omega/schedule", get(schedule_list).post(schedule_create))
+        .route(
+            "/omega/schedule/:id",
+            axum::routing::delete(schedule_cancel),
+        )
+        .route("/omega/events", get(events_since))
+        .route("/omega/blocks", get(blocks_since))
+        .route("/omega/replica/state", get(replica_state))
+        .route("/omega/bank/transfers", get(transfers_search))
+        .route("/omega/bank/transfers/summary", get(transfers_summary))
+        .route("/omega/zones", get(currency_zones))
+        .route("/v1/spec/monetary/history", get(monetary_history))
+        .route("/omega/bank/compounding", get(bank_compounding))
+        .route("/omega/bank/burns", get(bank_burns))
+        .route("/omega/bank/burn", post(bank_burn))
+        .route("/omega/tick", get(tick_status))
+        .route("/omega/exchange/quote", get(exchange_quote))
+        .route("/omega/escrows", get(escrows_open).post(escrow_open))
+        .route("/omega/guilds", get(guilds_list).post(guild_found))
+        .route("/omega/guilds/:name", get(guild_show))
+        .route("/omega/guilds/:name/members", post(guild_role))
+        .route("/omega/guilds/:name/deposit", post(guild_deposit))
+        .route("/omega/guilds/:name/spends", post(guild_spend))
+        .route(
+            "/omega/guilds/:name/spends/:id/approve",
+            post(guild_approve),
+        )
+        .route("/omega/notifications", get(notifications_list))
+        .route("/omega/notifications/read", post(notifications_read))
+        .route("/omega/notifications/purge", post(notifications_purge))
+        .route(
+            "/omega/push/devices",
+            get(devices_list).post(device_register),
+        )
+        .route(
+            "/omega/push/devices/:id",
+            axum::routing::delete(device_remove),
+        )
+        .route("/omega/push/vapid", get(push_key))
+        .route("/omega/faucet", get(faucet_status))
+        .route("/omega/faucet/claim", post(faucet_claim))
+        .route("/omega/escrows/:id/confirm", post(escrow_confirm))
+        .route("/omega/escrows/:id/cancel", post(escrow_cancel))
+        .route("/omega/items", axum::routing::put(item_assign))
+        .route("/omega/labels", get(labels_list).post(label_create))
+        .route("/omega/labels/:label/archive", post(label_archive))
+        .route(
+            "/omega/labels/:label/calibration",
+            get(label_calibrations).put(label_calibrate),
+        )
+        .route(
+            "/omega/labels/:label/guardians",
+            get(label_guardians_show).post(label_guardians),
+        )
+        .route(
+            "/omega/recoveries",
+            get(recoveries_open).post(recovery_open),
+        )
+        .route("/omega/recoveries/:id/approve", post(recovery_approve))
+        .route("/omega/recoveries/:id/cancel", post(recovery_cancel))
+        .route("/omega/aliases", post(alias_register))
+        .route("/omega/aliases/reverse", get(alias_reverse))
+        .route(
+            "/omega/aliases/:handle",
+            get(alias_lookup).delete(alias_release),
+        )
+        .route("/identity/mojang", post(identity_mojang))
+        .route("/identity/web", post(identity_web))
+        .route("/identity/delete", post(identity_delete))
+        .route("/omega/audit", get(audit_recent))
+        .route("/admin/overview", get(admin_overview))
+        .route("/admin/metrics", get(admin_metrics))
+        .route("/admin/invariants", get(admin_invariants))
+        .route("/admin/sessions", get(admin_sessions))
+        .route("/admin/sockets", get(admin_sockets))
+        .route("/admin/admission", get(admin_admission))
+        .route("/omega/sessions/:id/timeline", get(session_timeline))
+        .route("/omega/sessions/:id/migration", post(session_migration_mint))
+        .route("/omega/sessions/migrate", post(session_migrate))
+        .route(
+            "/admin/sessions/:id",
+            axum::routing::delete(admin_session_kick),
+        )
+        .route("/admin/locks", get(admin_locks))
+        .route("/admin/config/reload", post(admin_config_reload))
+        .route("/admin/bootstrap", post(admin_bootstrap))
+        .route("/admin/monetary/schedule", post(admin_monetary_schedule))
+        .route("/omega/recordings", get(recordings_list))
+        .route(
+            "/omega/recordings/:player",
+            axum::routing::put(recording_flag).delete(recording_unflag),
+        )
+        .route("/omega/recordings/:player/dump", post(recording_dump))
+        .route("/debug/pprof/profile", get(pprof_profile))
+        .route("/debug/pprof/allocs", get(pprof_allocs))
+        .route("/omega/dns/records", get(dns_export).put(dns_import))
+        .route("/auth/phone/start", post(auth_phone_start))
+        .route("/auth/phone/confirm", post(auth_phone_confirm))
+        .nest("/explorer", explorer::router(state.gateway.clone()));
+    #[cfg(feature = "chaos")]
+    let app = app.route(
+        "/omega/chaos",
+        get(chaos_plans).put(chaos_update).delete(chaos_clear),
+    );
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            replica_writes,
+        ))
+        .layer(middleware::from_fn(host_redirect))
+        .with_state(state.clone());
+
+    // A replica takes its blocks and balances from the leader only.
+    if let Some(replica) = &state.replica {
+        if !state.peers.is_empty() {
+            warn!("[replica] ignoring OMEGA_PEERS; replicas follow their leader");
+        }
+        spawn_replica_sync(state.gateway.clone(), replica.clone());
+    } else {
+        spawn_block_producer(
+            state.gateway.clone(),
+            state.peers.clone(),
+            state.lease.clone(),
+        );
+        spawn_peer_sync(state.gateway.clone(), state.peers.clone());
+    }
+    spawn_status_feed(state.clone());
+    spawn_session_writer(state.phone_auth.clone());
+    match InvariantMonitor::interval_from_env() {
+        Some(every) => spawn_invariant_checks(state.clone(), every),
+        None => info!("[invariants] OMEGA_INVARIANTS_EVERY_SECS=0; background checks are off"),
+    }
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("dlog.gold Ω-edge listening on http://{addr}");
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .expect("failed to bind TCP listener");
+
+    if let Err(err) = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(state.lease.clone()))
+    .await
+    {
+        error!("server error: {err}");
+    }
+}
+
+/// Resolves on Ctrl-C or SIGTERM (Cloud Run's stop signal), after handing the
+/// producer lease to a standby so it doesn't wait out the TTL.
+async fn shutdown_signal(lease: Option<Arc<ProducerLease>>) {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                warn!("[shutdown] cannot listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("[shutdown] draining connections");
+    if let Some(lease) = lease {
+        lease.release(now_ms());
+    }
+}
+
+/// Tracing field formatter that masks phone numbers in messages and `phone` fields.
+fn redacting_fields() -> impl for<'writer> tracing_subscriber::fmt::FormatFields<'writer> {
+    use tracing_subscriber::field::MakeExt;
+
+    tracing_subscriber::fmt::format::debug_fn(|writer, field, value| {
+        let rendered = format!("{value:?}");
+        match field.name() {
+            "message" => write!(writer, "{}", redact_phones(&rendered)),
+            "phone" => write!(writer, "phone={}", redact_phone(&rendered)),
+            name => write!(writer, "{name}={}", redact_phones(&rendered)),
+        }
+    })
+    .delimited(" ")
+}
+
+/// This node's id, the policy it runs and the shared peer key.
+type NodeConsensus = (String, Box<dyn Consensus>, KeyedSigner);
+
+/// Multi-node consensus from `OMEGA_CONSENSUS` (`single`, `round_robin` or
+/// `external`). `OMEGA_NODE_ID` names this node; `OMEGA_CONSENSUS_NODES` lists
+/// producers (the first is the `single` leader); `external` signs with
+/// `OMEGA_SIGNER_KEY`. Every mode needs `OMEGA_PEER_KEY`: it MACs `BLOCK`
+/// frames, and `single`/`round_robin` headers, so a producer's name alone
+/// can't mint blocks. Unset means this gateway is its own sole producer.
+fn consensus_from_env() -> Result<Option<NodeConsensus>, String> {
+    let Some(mode) = env::var("OMEGA_CONSENSUS")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    let node_id = env::var("OMEGA_NODE_ID")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .ok_or("OMEGA_NODE_ID is required with OMEGA_CONSENSUS")?;
+    let nodes: Vec<String> = env::var("OMEGA_CONSENSUS_NODES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(String::from)
+        .collect();
+    let peer_key = peer_key_from_env().ok_or("OMEGA_PEER_KEY is required with OMEGA_CONSENSUS")?;
+
+    let consensus: Box<dyn Consensus> = match mode.trim() {
+        "single" => Box::new(PeerSigned {
+            policy: SingleProducer {
+                leader: nodes.first().cloned().unwrap_or_else(|| node_id.clone()),
+            },
+            signer: peer_key.clone(),
+        }),
+        "round_robin" if nodes.is_empty() => {
+            return Err("round_robin needs OMEGA_CONSENSUS_NODES".into())
+        }
+        "round_robin" => Box::new(PeerSigned {
+            policy: RoundRobin { nodes },
+            signer: peer_key.clone(),
+        }),
+        "lease" => Box::new(PeerSigned {
+            policy: LeasedProducer { nodes },
+            signer: peer_key.clone(),
+        }),
+        "external" => {
+            let secret = env::var("OMEGA_SIGNER_KEY")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .ok_or("external needs OMEGA_SIGNER_KEY")?;
+            Box::new(ExternalSigner {
+                signer: KeyedSigner::from_secret(&secret),
+            })
+        }
+        other => return Err(format!("unknown OMEGA_CONSENSUS {other:?}")),
+    };
+    Ok(Some((node_id, consensus, peer_key)))
+}
+
+fn peer_key_from_env() -> Option<KeyedSigner> {
+    env::var("OMEGA_PEER_KEY")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(|secret| KeyedSigner::from_secret(&secret))
+}
+
+fn block_interval() -> Duration {
+    Duration::from_secs_f64(MonetarySpec::default().target_block_seconds)
+}
+
+/// Seal one block every `target_block_seconds` and fire due scheduled tasks.
+/// Nodes that aren't the producer for the next height, or don't hold the
+/// producer lease, wait for peers' blocks.
+fn spawn_block_producer(
+    gateway: Arc<OmegaGateway>,
+    peers: Arc<PeerSet>,
+    lease: Option<Arc<ProducerLease>>,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(block_interval());
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if lease.as_ref().is_some_and(|lease| !lease.tick(now_ms())) {
+                continue;
+            }
+            if !gateway.may_produce() {
+                continue;
+            }
+            let seal = gateway.seal_block();
+            peers.announce(&gateway, &seal.header);
+            for task in &seal.fired {
+                info!(
+                    "[scheduler] fired task {} ({}) at height {} ({} ms)",
+                    task.id, task.kind, seal.height, seal.sealed_ms
+                );
+            }
+        }
+    });
+}
+
+/// Admin guard: X-Admin-Token must match OMEGA_ADMIN_TOKEN. Without a
+/// configured token every admin route is refused.
+fn require_admin_token(headers: &HeaderMap) -> Result<(), AuthError> {
+    let expected = env::var("OMEGA_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+        .ok_or(AuthError::AdminRequired)?;
+    let ok = headers
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == expected);
+    if !ok {
+        return Err(AuthError::AdminRequired);
+    }
+    Ok(())
+}
+
+async fn root() -> Html<String> {
+    let html = r#"<!doctype html>
+<html lang=\"en\">
+<head>
+  <meta charset=\"utf-8\" />
+  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />
+  <title>dlog.gold · Minecraft</title>
+  <style>
+    body { font-family: sans-serif; background: #05060a; color: #e2e8f0; margin: 0; padding: 24px; }
+    .card { max-width: 640px; margin: 0 auto; background: #0b0f1a; border: 1px solid #1f2937; border-radius: 12px; padding: 20px 24px; box-shadow: 0 8px 30px rgba(0,0,0,0.35); }
+    h1 { margin-top: 0; }
+    code { background: #111827; padding: 2px 6px; border-radius: 6px; color: #93c5fd; }
+    ul { padding-left: 18px; }
+    a { color: #93c5fd; text-decoration: none; }
+    a:hover { text-decoration: underline; }
+  </style>
+</head>
+<body>
+  <div class=\"card\">
+    <h1>Welcome to dlog.gold</h1>
+    <p>Join the Minecraft network:</p>
+    <ul>
+      <li><strong>Java:</strong> <code>dlog.gold</code> (port 25565)</li>
+      <li><strong>Bedrock:</strong> <code>dlog.gold</code> (port 19132)</li>
+    </ul>
+    <p>APIs:</p>
+    <ul>
+      <li><a href=\"/sky/timeline/default\">/sky/timeline/default</a> – default sky show timeline</li>
+      <li><a href=\"/sky/timeline/moon\">/sky/timeline/:planet</a> – per-planet sky show (earth, moon, mars, sun)</li>
+      <li><a href=\"/sky/shows/default/preview?ticks=888\">/sky/shows/:id/preview</a> – sampled slide, blend, phase and audio band for plotting</li>
+      <li><a href=\"/sky/render/earth\">/sky/render/:world</a> – sky and fog colours plus world time for the Paper plugin</li>
+      <li>/sky/assets/:key – hash-addressed slide textures named by timeline <code>asset.url</code></li>
+      <li><a href=\"/health\">/health</a> – health check</li>
+      <li><a href=\"/omega/status\">/omega/status</a> – omega gateway status</li>
+    </ul>
+    <p>Have fun. ✨</p>
+  </div>
+</body>
+</html>"#;
+    Html(html.to_string())
+}
+
+async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
+    let upstreams = vec![state.presence_upstream.snapshot(), state.mojang.snapshot()];
+    let degraded = upstreams.iter().any(|u| u.state != BreakerState::Closed);
+    Json(HealthResponse {
+        status: if degraded { "degraded" } else { "ok" },
+        gateway_id: state.gateway.id().to_string(),
+        boot_ms: state.gateway.boot_ms(),
+        incarnation: state.gateway.identity().incarnation,
+        upstreams,
+    })
+}
+
+async fn sky_timeline_default(State(state): State<AppState>) -> Json<SkyTimelineResponse> {
+    let timeline = state
+        .gateway
+        .sky_timeline("default")
+        .unwrap_or_else(SkyTimeline::default_eight);
+    Json(SkyTimelineResponse {
+        total_duration_ticks: timeline.total_duration_ticks(),
+        show: state.gateway.sky_assets().resolve(timeline.show().clone()),
+        upcoming_events: Vec::new(),
+    })
+}
+
+async fn sky_timeline_planet(
+    State(state): State<AppState>,
+    Path(planet): Path<String>,
+) -> Result<Json<SkyTimelineResponse>, StatusCode> {
+    let planet = PlanetId::parse(&planet).ok_or(StatusCode::NOT_FOUND)?;
+    let timeline = state
+        .gateway
+        .sky_timeline(planet.key())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(SkyTimelineResponse {
+        total_duration_ticks: timeline.total_duration_ticks(),
+        show: state.gateway.sky_assets().resolve(timeline.show().clone()),
+        upcoming_events: state.gateway.upcoming_sky_events(planet),
+    }))
+}
+
+/// Upload a slide texture; the body is the image, typed by `Content-Type`.
+async fn sky_asset_register(
+    State(state): State<AppState>,
+    Path(slide_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<SkyAssetRef>, DlogError> {
+    state.require_admin(&headers)?;
+    let mime = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    SkyAssets::validate(&slide_id, mime, body.len())?;
+    let asset = state
+        .gateway
+        .sky_assets()
+        .register(&slide_id, mime, body.to_vec())?;
+    info!("[sky] {slide_id} -> {} ({} bytes)", asset.url, asset.size);
+    state.gateway.audit().record(
+        "admin",
+        "sky_asset_register",
+        serde_json::json!({ "slide": slide_id, "hash": asset.hash, "size": asset.size }),
+    );
+    Ok(Json(asset))
+}
+
+/// Slide texture bytes. They never change under a key, so caches may keep
+/// them forever.
+async fn sky_asset(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Response, StatusCode> {
+    let blob = state
+        .gateway
+        .sky_assets()
+        .blob(&key)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok((
+        [
+            ("content-type", blob.mime),
+            (
+                "cache-control",
+                "public, max-age=31536000, immutable".to_string(),
+            ),
+        ],
+        blob.bytes,
+    )
+        .into_response())
+}
+
+/// Sampled timeline segment for plotting; `id` is `default`, a planet or a
+/// show from the bootstrap manifest.
+async fn sky_show_preview(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<SkyPreviewQuery>,
+) -> Result<Json<SkyPreview>, StatusCode> {
+    let timeline = state
+        .gateway
+        .sky_timeline(&id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(SkyPreview::new(
+        id,
+        &timeline,
+        query.from_tick,
+        query.ticks,
+        query.stride,
+    )))
+}
+
+/// Sky, fog and world time for a Minecraft world (`earth`, `moon_shell`, …).
+async fn sky_render(
+    State(state): State<AppState>,
+    Path(world): Path<String>,
+    Query(query): Query<SkyRenderQuery>,
+) -> Result<Json<SkyRenderResponse>, StatusCode> {
+    let planet = PlanetId::from_world(&world).ok_or(StatusCode::NOT_FOUND)?;
+    let timeline = state
+        .gateway
+        .sky_timeline(planet.key())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let tick = query
+        .tick
+        .unwrap_or_else(|| state.gateway.sky_tick(&timeline));
+    Ok(Json(SkyRenderResponse {
+        world,
+        planet,
+        render: timeline.render(tick),
+    }))
+}
+
+/// Open a session, unless admission defers it (`503` with a backoff hint).
+async fn handshake(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<HandshakeRequest>,
+) -> Result<Json<HandshakeResponse>, Response> {
+    // Gateway peers skip admission; a reconnect wave is clients.
+    let retry_after_ms = match payload.peer_mac {
+        Some(_) => None,
+        None => state
+            .admission
+            .admit(&payload.client_id, now_ms())
+            .map_err(IntoResponse::into_response)?,
+    };
+    let mut response =
+        open_session(&state, &headers, payload).map_err(IntoResponse::into_response)?;
+    response.retry_after_ms = retry_after_ms;
+    Ok(Json(response))
+}
+
+fn open_session(
+    state: &AppState,
+    headers: &HeaderMap,
+    payload: HandshakeRequest,
+) -> Result<HandshakeResponse, DlogError> {
+    let identity = match (payload.phone.as_deref(), payload.session_token.as_deref()) {
+        (Some(phone), Some(token)) => Some(
+            state
+                .phone_auth
+                .verified_identity(token, phone)
+                .ok_or(AuthError::Unverified)?,
+        ),
+        // Gateway peers prove the peer key instead; the gateway checks it.
+        _ if payload.peer_mac.is_some() => None,
+        _ => return Err(AuthError::MissingCredentials.into()),
+    };
+    // Sessions only carry admin if the handshake itself was admin-authorized.
+    let admin = Capability::Admin.key();
+    let wants_admin = payload
+        .capabilities
+        .iter()
+        .any(|c| c.trim().eq_ignore_ascii_case(admin));
+    if wants_admin {
+        require_admin_token(headers)?;
+    }
+
+    let grant = SessionGrant {
+        phone: identity.as_ref().map(|identity| identity.phone.clone()),
+        admin: wants_admin,
+    };
+    let mut response = state.gateway.handle_handshake(payload, grant)?;
+    response.identity = identity;
+    Ok(response)
+}
+
+async fn frame(
+    State(state): State<AppState>,
+    Json(payload): Json<FrameEnvelope>,
+) -> Json<FrameAck> {
+    Json(route_frame(&state, payload).await)
+}
+
+/// Handle a frame, relaying bank writes to the leader on a replica.
+async fn route_frame(state: &AppState, payload: FrameEnvelope) -> FrameAck {
+    if let Some(replica) = &state.replica {
+        if OmegaGateway::is_bank_write(&payload) {
+            return replica.relay(&state.gateway, payload).await;
+        }
+    }
+    state.gateway.handle_frame(payload)
+}
+
+#[derive(Debug, Deserialize)]
+struct FrameSocketQuery {
+    session: Option<String>,
+}
+
+/// `/omega/frame` over a WebSocket, with pushed events; the session comes
+/// from `?session=` (browsers can't set headers on upgrades) or
+/// `X-Omega-Session`.
+async fn omega_ws(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<FrameSocketQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, AuthError> {
+    let session_id = query
+        .session
+        .or_else(|| {
+            headers
+                .get("x-omega-session")
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        })
+        .filter(|id| state.gateway.has_session(id))
+        .ok_or(AuthError::Unverified)?;
+    Ok(upgrade.on_upgrade(move |socket| {
+        let (gateway, sockets) = (state.gateway.clone(), state.sockets.clone());
+        frame_socket::serve(socket, gateway, sockets, session_id, move |frame| {
+            let state = state.clone();
+            async move { route_frame(&state, frame).await }
+        })
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplicaStateQuery {
+    #[serde(default)]
+    after: u64,
+}
+
+/// Bank state for read replicas; the request carries a peer MAC.
+async fn replica_state(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ReplicaStateQuery>,
+) -> Result<Json<ReplicaState>, AuthError> {
+    let mac = headers
+        .get(PEER_MAC_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !state
+        .gateway
+        .verify_peer_mac(&replica::state_request_bytes(query.after), mac)
+    {
+        return Err(AuthError::Forbidden("peer credential required".into()));
+    }
+    Ok(Json(state.gateway.replica_state(query.after)))
+}
+
+/// HTTP routes a read replica refuses; their frames are relayed instead.
+const REPLICA_WRITE_PATHS: &[&str] = &[
+    "/omega/escrows",
+    "/omega/guilds",
+    "/omega/items",
+    "/omega/aliases",
+    "/omega/labels",
+    "/omega/recoveries",
+    "/omega/notifications",
+    "/omega/push/devices",
+    "/omega/faucet",
+    "/omega/bank/burn",
+    "/omega/schedule",
+    "/omega/dns/records",
+    "/identity/delete",
+];
+
+/// On a replica, turn writes to leader-owned state away with `409` naming
+/// the leader.
+async fn replica_writes(State(state): State<AppState>, req: Request<Body>, next: Next) -> Response {
+    if let Some(replica) = &state.replica {
+        let write = !matches!(*req.method(), Method::GET | Method::HEAD);
+        let path = req.uri().path();
+        if write && REPLICA_WRITE_PATHS.iter().any(|p| path.starts_with(p)) {
+            let body = format!("read replica: send writes to {}", replica.leader());
+            return (StatusCode::CONFLICT, body).into_response();
+        }
+    }
+    next.run(req).await
+}
+
+async fn status(State(state): State<AppState>) -> Json<StatusResponse> {
+    Json(state.status_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusStreamQuery {
+    cursor: Option<u64>,
+}
+
+/// `/omega/status` as server-sent events: a `snapshot` (unless the cursor
+/// from `?cursor=` or `Last-Event-ID` is still retained), then a `delta`
+/// per change and a `heartbeat` after each quiet stretch. Event ids are
+/// the feed's `seq`, so reconnecting clients resume where they stopped.
+async fn status_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StatusStreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let cursor = query.cursor.or_else(|| {
+        headers
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+    });
+    let feed = state.status_feed.clone();
+    let updates = feed.subscribe();
+    let mut pending = VecDeque::new();
+    let cursor = queue_status_events(feed.resume(cursor), &mut pending);
+    Sse::new(stream::unfold(
+        (feed, updates, pending, cursor),
+        |(feed, mut updates, mut pending, mut cursor)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((Ok(event), (feed, updates, pending, cursor)));
+                }
+                updates.borrow_and_update();
+                let caught_up = queue_status_events(feed.resume(Some(cursor)), &mut pending);
+                if !pending.is_empty() {
+                    cursor = caught_up;
+                    continue;
+                }
+                tokio::select! {
+                    changed = updates.changed() => {
+                        if changed.is_err() {
+                            return None;
+                        }
+                    }
+                    _ = tokio::time::sleep(HEARTBEAT_INTERVAL) => {
+                        let seq = serde_json::json!({ "seq": cursor });
+                        pending.push_back(status_event("heartbeat", cursor, &seq));
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// Queue the events for `resume`; returns the `seq` they bring a client to.
+fn queue_status_events(resume: Resume, pending: &mut VecDeque<Event>) -> u64 {
+    match resume {
+        Resume::Snapshot(snapshot) => {
+            pending.push_back(status_event("snapshot", snapshot.seq, &snapshot));
+            snapshot.seq
+        }
+        Resume::Deltas(deltas) => {
+            let mut seq = 0;
+            for delta in deltas {
+                seq = delta.seq;
+                pending.push_back(status_event("delta", delta.seq, &delta));
+            }
+            seq
+        }
+    }
+}
+
+fn status_event<T: Serialize>(kind: &str, seq: u64, data: &T) -> Event {
+    Event::default()
+        .event(kind)
+        .id(seq.to_string())
+        .data(serde_json::to_string(data).unwrap_or_default())
+}
+
+/// Sample the status for the stream every [`OBSERVE_INTERVAL`].
+fn spawn_status_feed(state: AppState) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(OBSERVE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            state.observe_status();
+        }
+    });
+}
+
+/// Save phone auth sessions after changes; bursts coalesce into one write.
+fn spawn_session_writer(auth: Arc<PhoneAuth>) {
+    tokio::spawn(async move {
+        loop {
+            auth.changed.notified().await;
+            auth.persist().await;
+        }
+    });
+}
+
+/// Check the economy's invariants every `every`.
+fn spawn_invariant_checks(state: AppState, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            state.check_invariants().await;
+        }
+    });
+}
+
+async fn schedule_list(State(state): State<AppState>) -> Json<ScheduleListResponse> {
+    Json(ScheduleListResponse {
+        block_height: state.gateway.block_height(),
+        pending: state.gateway.scheduled_tasks(),
+    })
+}
+
+async fn schedule_create(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ScheduleRequest>,
+) -> Result<Json<ScheduledTask>, DlogError> {
+    state.require_admin(&headers)?;
+    let task = state
+        .gateway
+        .schedule_task(payload.height, payload.kind, payload.payload)?;
+    Ok(Json(task))
+}
+
+async fn schedule_cancel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Json<ScheduledTask>, DlogError> {
+    state.require_admin(&headers)?;
+    let task = state
+        .gateway
+        .cancel_task(id)
+        .ok_or_else(|| FrameError::NotFound(format!("task {id}")))?;
+    Ok(Json(task))
+}
+
+async fn events_since(
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+) -> Json<EventsResponse> {
+    let events = state.gateway.events();
+    Json(EventsResponse {
+        last_seq: events.last_seq(),
+        events: events.since(query.since),
+    })
+}
+
+/// Retained block headers from `from` on; peers pull this to catch up.
+async fn blocks_since(
+    State(state): State<AppState>,
+    Query(query): Query<BlocksQuery>,
+) -> Json<BlocksResponse> {
+    let gateway = &state.gateway;
+    Json(BlocksResponse {
+        height: gateway.block_height(),
+        tip: gateway.tip(),
+        headers: gateway.blocks_from(query.from, query.limit.min(SYNC_BATCH)),
+    })
+}
+
+async fn audit_recent(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.gateway.audit().recent(query.limit)))
+}
+
+async fn admin_overview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<overview::Overview>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.gateway.overview()))
+}
+
+/// The latest invariant report, checking now when the background loop
+/// hasn't produced one (admin token).
+async fn admin_invariants(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<InvariantReport>, AuthError> {
+    state.require_admin(&headers)?;
+    let report = match state.invariants.latest() {
+        Some(report) => report,
+        None => state.check_invariants().await,
+    };
+    Ok(Json(report))
+}
+
+async fn admin_metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<MetricsQuery>,
+) -> Result<Json<metrics::MetricsSeries>, AuthError> {
+    state.require_admin(&headers)?;
+    let to = query.to.unwrap_or_else(|| state.gateway.block_height());
+    let from = query
+        .from
+        .unwrap_or_else(|| to.saturating_sub(DEFAULT_METRICS_SPAN));
+    Ok(Json(state.gateway.metrics().series(
+        query.planet,
+        from,
+        to,
+        query.points,
+    )))
+}
+
+async fn admin_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SessionView>>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.gateway.sessions()))
+}
+
+/// Open frame sockets with their per-namespace traffic.
+async fn admin_sockets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SocketView>>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.sockets.list()))
+}
+
+async fn admin_admission(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdmissionStats>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.admission.stats(now_ms())))
+}
+
+/// What a session did, for support: operators, or the session itself via
+/// `X-Omega-Session`.
+async fn session_timeline(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<Json<SessionTimeline>, DlogError> {
+    let own = headers
+        .get("x-omega-session")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|header| header == session_id);
+    if !own {
+        state.require_admin(&headers)?;
+    }
+    if query.bucket_ms < 1_000 {
+        return Err(FrameError::Malformed("bucket_ms must be at least 1000".into()).into());
+    }
+    let timeline = state
+        .gateway
+        .session_timeline(&session_id, query.bucket_ms)
+        .ok_or_else(|| FrameError::NotFound(format!("session {session_id}")))?;
+    Ok(Json(timeline))
+}
+
+/// Mint a code that moves this session to another device.
+async fn session_migration_mint(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<MigrationCode>, AuthError> {
+    let identity = state.verified(&auth)?;
+    let code = state
+        .gateway
+        .mint_migration_code(&session_id, identity, &auth.session_token)?;
+    Ok(Json(code))
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionMigrateRequest {
+    code: String,
+    client_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionMigrateResponse {
+    #[serde(flatten)]
+    handshake: HandshakeResponse,
+    /// Phone-auth token for the new device; the old device's stops working.
+    session_token: Option<String>,
+}
+
+/// Redeem a migration code on the new device.
+async fn session_migrate(
+    State(state): State<AppState>,
+    Json(payload): Json<SessionMigrateRequest>,
+) -> Result<Json<SessionMigrateResponse>, AuthError> {
+    let moved = state
+        .gateway
+        .redeem_migration_code(&payload.code, payload.client_id)?;
+    let session_token = state.phone_auth.rotate(&moved.auth_token);
+    info!(
+        "[session] {} migrated to {}",
+        moved.replaced_session, moved.handshake.session_id
+    );
+    state.gateway.audit().record(
+        "session",
+        "session_migrate",
+        serde_json::json!({
+            "from": moved.replaced_session,
+            "to": moved.handshake.session_id,
+            "planet": moved.handshake.planet,
+        }),
+    );
+    Ok(Json(SessionMigrateResponse {
+        handshake: moved.handshake,
+        session_token,
+    }))
+}
+
+async fn admin_session_kick(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionView>, DlogError> {
+    state.require_admin(&headers)?;
+    let kicked = state.gateway.kick_session(&session_id)?;
+    info!("[admin] kicked session {session_id}");
+    state.gateway.audit().record(
+        "admin",
+        "session_kick",
+        serde_json::json!({ "session_id": session_id, "planet": kicked.planet }),
+    );
+    Ok(Json(kicked))
+}
+
+async fn admin_locks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<EscrowLock>>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.gateway.escrow_locks()))
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigReloadResponse {
+    dns: DnsImport,
+}
+
+/// Re-read hand-edited config under `OMEGA_ROOT` (today the DNS route table).
+async fn admin_config_reload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ConfigReloadResponse>, DlogError> {
+    state.require_admin(&headers)?;
+    let dns = state.gateway.reload_dns()?;
+    info!(
+        "[dns] reloaded route table v{} ({} records)",
+        dns.version, dns.records
+    );
+    state.gateway.audit().record(
+        "admin",
+        "config_reload",
+        serde_json::json!({ "dns_version": dns.version, "dns_records": dns.records }),
+    );
+    Ok(Json(ConfigReloadResponse { dns }))
+}
+
+/// Apply a bootstrap manifest (see `bootstrap.rs`) sent as the body; with
+/// `?dry_run=true`, only list what it would change.
+async fn admin_bootstrap(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<BootstrapQuery>,
+    body: String,
+) -> Result<Json<BootstrapReport>, DlogError> {
+    state.require_admin(&headers)?;
+    let plan = BootstrapPlan::parse(&body);
+    let report = state.gateway.apply_bootstrap(&plan, query.dry_run)?;
+    if !report.dry_run {
+        state.gateway.audit().record(
+            "admin",
+            "bootstrap",
+            serde_json::json!({ "changes": report.changes }),
+        );
+    }
+    Ok(Json(report))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RecordingFlagRequest {
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordingDumpRequest {
+    report_id: String,
+}
+
+async fn recordings_list(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<RecordingStatus>>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(state.gateway.flagged_recordings()))
+}
+
+/// Flag a player: their bridge input is recorded from now on.
+async fn recording_flag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(player): Path<String>,
+    payload: Option<Json<RecordingFlagRequest>>,
+) -> Result<Json<RecordingStatus>, AuthError> {
+    state.require_admin(&headers)?;
+    let reason = payload.and_then(|Json(p)| p.reason);
+    Ok(Json(state.gateway.flag_recording(&player, reason)))
+}
+
+async fn recording_unflag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(player): Path<String>,
+) -> Result<StatusCode, DlogError> {
+    state.require_admin(&headers)?;
+    state.gateway.unflag_recording(&player)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// File a report: dump the flagged player's recent input as evidence.
+async fn recording_dump(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(player): Path<String>,
+    Json(payload): Json<RecordingDumpRequest>,
+) -> Result<Json<InputDump>, DlogError> {
+    state.require_admin(&headers)?;
+    Ok(Json(
+        state.gateway.dump_recording(&player, &payload.report_id)?,
+    ))
+}
+
+/// CPU profile as pprof protobuf (`go tool pprof -http=: profile.pb`).
+async fn pprof_profile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ProfileParams>,
+) -> Result<Response, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(dlog_profiling::profile_response(params).await)
+}
+
+async fn pprof_allocs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AllocStats>, AuthError> {
+    state.require_admin(&headers)?;
+    Ok(Json(dlog_profiling::alloc_stats()))
+}
+
+async fn dns_export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AuthError> {
+    state.require_admin(&headers)?;
+    Ok((
+        [("content-type", "text/plain; charset=utf-8")],
+        state.gateway.export_dns(),
+    )
+        .into_response())
+}
+
+async fn dns_import(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<DnsImportQuery>,
+    body: String,
+) -> Result<Json<DnsImport>, DlogError> {
+    state.require_admin(&headers)?;
+    let imported = state.gateway.import_dns(&body, query.force)?;
+    info!(
+        "[dns] imported route table v{} ({} records)",
+        imported.version, imported.records
+    );
+    state.gateway.audit().record(
+        "admin",
+        "dns_import",
+        serde_json::json!({ "version": imported.version, "records": imported.records }),
+    );
+    Ok(Json(imported))
+}
+
+impl AppState {
+    /// Check the invariants, keep the report and raise what the alert gate
+    /// lets through.
+    async fn check_invariants(&self) -> InvariantReport {
+        let report = self.gateway.check_invariants();
+        let fresh = self.invariants.record(report.clone());
+        if !fresh.is_empty() {
+            self.gateway.audit().record(
+                "invariants",
+                "invariant_broken",
+                serde_json::json!({ "violations": fresh }),
+            );
+            self.invariants.alert(self.gateway.node_id(), &fresh).await;
+        }
+        report
+    }
+
+    fn status_response(&self) -> StatusResponse {
+        StatusResponse {
+            gateway: self.gateway.status(),
+            peers: self.peers.statuses(),
+            sync_lag: self.peers.sync_lag(),
+            lease: self.lease.as_ref().map(|lease| lease.status()),
+            replica: self.replica.as_ref().map(|replica| replica.status()),
+        }
+    }
+
+    /// Feed the status, the tick latencies and the live sessions to
+    /// [`StatusFeed::observe`].
+    fn observe_status(&self) {
+        let mut status = serde_json::to_value(self.status_response()).unwrap_or_default();
+        if let Some(fields) = status.as_object_mut() {
+            let ticks = serde_json::to_value(self.gateway.tick_stats()).unwrap_or_default();
+            fields.insert("sim_ticks".into(), ticks);
+        }
+        let sessions = self
+            .gateway
+            .sessions()
+            .into_iter()
+            .map(|session| session.session_id)
+            .collect();
+        self.status_feed.observe(now_ms(), status, sessions);
+    }
+
+    /// Admin token, or an `X-Omega-Session` whose handshake was granted admin.
+    fn require_admin(&self, headers: &HeaderMap) -> Result<(), AuthError> {
+        match headers.get("x-omega-session").and_then(|v| v.to_str().ok()) {
+            Some(session_id) => self.gateway.require(session_id, Capability::Admin),
+            None => require_admin_token(headers),
+        }
+    }
+
+    /// The `X-Omega-Session` header must name a session granted `cap` to the
+    /// same phone as `identity`.
+    fn require_session(
+        &self,
+        headers: &HeaderMap,
+        identity: &IdentityDescriptor,
+        cap: Capability,
+    ) -> Result<(), AuthError> {
+        let session_id = headers
+            .get("x-omega-session")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(AuthError::Unverified)?;
+        self.gateway.require_for(session_id, &identity.phone, cap)
+    }
+
+    /// Resolve a verified phone session or reject with 401.
+    fn verified(&self, auth: &SessionAuth) -> Result<IdentityDescriptor, AuthError> {
+        self.phone_auth
+            .verified_identity(&auth.session_token, &auth.phone)
+            .ok_or(AuthError::Unverified)
+    }
+}
+
+async fn alias_register(
+    State(state): State<AppState>,
+    Json(payload): Json<AliasRegisterRequest>,
+) -> Result<Json<AliasRecord>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let label = payload.label.unwrap_or(identity.label);
+    let canon = format!(";{};{};", identity.phone, label.trim_matches(';'));
+    let record = state.gateway.register_alias(
+        &payload.handle,
+        &canon,
+        &identity.phone,
+        payload.public,
+    )?;
+    Ok(Json(record))
+}
+
+async fn label_create(
+    State(state): State<AppState>,
+    Json(payload): Json<LabelCreateRequest>,
+) -> Result<Json<LabelView>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let label = state
+        .gateway
+        .create_label(&identity.phone, &payload.label)?;
+    Ok(Json(label))
+}
+
+async fn labels_list(
+    State(state): State<AppState>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<Vec<LabelView>>, AuthError> {
+    let identity = state.verified(&auth)?;
+    Ok(Json(state.gateway.labels_for(&identity.phone)))
+}
+
+async fn label_archive(
+    State(state): State<AppState>,
+    Path(label): Path<String>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<LabelView>, DlogError> {
+    let identity = state.verified(&auth)?;
+    let label = state.gateway.archive_label(&identity.phone, &label)?;
+    Ok(Json(label))
+}
+
+async fn alias_release(
+    State(state): State<AppState>,
+    Path(handle): Path<String>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<AliasRecord>, DlogError> {
+    let identity = state.verified(&auth)?;
+    let record = state.gateway.release_alias(&handle, &identity.phone)?;
+    Ok(Json(record))
+}
+
+async fn alias_lookup(
+    State(state): State<AppState>,
+    Path(handle): Path<String>,
+) -> Result<Json<AliasView>, StatusCode> {
+    state
+        .gateway
+        .lookup_alias(&handle)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn alias_reverse(
+    State(state): State<AppState>,
+    Query(query): Query<AliasReverseQuery>,
+) -> Result<Json<AliasView>, StatusCode> {
+    let requester = match (query.phone, query.session_token) {
+        (Some(phone), Some(session_token)) => state
+            .verified(&SessionAuth {
+                phone,
+                session_token,
+            })
+            .ok()
+            .map(|identity| identity.phone),
+        _ => None,
+    };
+    state
+        .gateway
+        .reverse_alias(&query.label, requester.as_deref())
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Each zone with the rates it pays on the next block.
+async fn currency_zones(State(state): State<AppState>) -> Json<Vec<CurrencyZone>> {
+    Json(
+        state
+            .gateway
+            .monetary_history()
+            .zones
+            .into_iter()
+            .map(|zone| CurrencyZone {
+                monetary: zone.monetary,
+                ..zone.planet.zone()
+            })
+            .collect(),
+    )
+}
+
+async fn monetary_history(State(state): State<AppState>) -> Json<MonetaryHistory> {
+    Json(state.gateway.monetary_history())
+}
+
+/// Schedule a change to a zone's rates at a future height.
+async fn admin_monetary_schedule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<MonetaryChangeRequest>,
+) -> Result<Json<MonetaryChange>, DlogError> {
+    state.require_admin(&headers)?;
+    let change = state.gateway.schedule_monetary_change(request)?;
+    state
+        .gateway
+        .audit()
+        .record("admin", "monetary_schedule", serde_json::json!(change));
+    Ok(Json(change))
+}
+
+async fn bank_compounding(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(base): Query<BaseQuery>,
+) -> Json<Based<CompoundingStatus>> {
+    let status = state.gateway.compounding();
+    Json(
+        NumberBase::negotiate(&headers, &base).render(status, |status| {
+            let zones: Vec<_> = status
+                .zones
+                .iter()
+                .map(|zone| {
+                    let factor = |ppm: u64| octal_ratio(ppm.into(), 1_000_000);
+                    serde_json::json!({
+                        "planet": zone.planet,
+                        "per_block_factor": factor(zone.per_block_factor_ppm),
+                        "period_factor": factor(zone.period_factor_ppm),
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "period_blocks": octal_int(status.period_blocks.into()),
+                "zones": zones,
+            })
+        }),
+    )
+}
+
+async fn bank_burns(State(state): State<AppState>) -> Json<BurnStatus> {
+    Json(state.gateway.burn_status())
+}
+
+/// Burn what an action costs from one of the caller's labels.
+async fn bank_burn(
+    State(state): State<AppState>,
+    Json(payload): Json<BurnRequest>,
+) -> Result<Json<BurnReceipt>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let receipt = state.gateway.burn(
+        &identity.phone,
+        &payload.label,
+        payload.planet,
+        &payload.action,
+        payload.price,
+    )?;
+    Ok(Json(receipt))
+}
+
+#[cfg(feature = "chaos")]
+type FaultPlans = std::collections::BTreeMap<String, FaultPlan>;
+
+/// Fault plans injected into upstream calls (`chaos` builds only).
+#[cfg(feature = "chaos")]
+async fn chaos_plans(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FaultPlans>, DlogError> {
+    state.require_admin(&headers)?;
+    Ok(Json(chaos::plans()))
+}
+
+/// Replace the plans of the named upstreams; an all-zero plan clears one.
+#[cfg(feature = "chaos")]
+async fn chaos_update(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(plans): Json<FaultPlans>,
+) -> Result<Json<FaultPlans>, DlogError> {
+    state.require_admin(&headers)?;
+    for (upstream, plan) in &plans {
+        warn!("[chaos] {upstream}: {plan:?}");
+        chaos::set(upstream, *plan);
+    }
+    state
+        .gateway
+        .audit()
+        .record("admin", "chaos_update", serde_json::json!(plans));
+    Ok(Json(chaos::plans()))
+}
+
+#[cfg(feature = "chaos")]
+async fn chaos_clear(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FaultPlans>, DlogError> {
+    state.require_admin(&headers)?;
+    chaos::clear();
+    info!("[chaos] faults cleared");
+    Ok(Json(chaos::plans()))
+}
+
+/// The gateway's φ tick, with `at_ms` and `at_tick` converted on the same
+/// clock.
+async fn tick_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<TickQuery>,
+    Query(base): Query<BaseQuery>,
+) -> Json<Based<TickResponse>> {
+    let ticks = state.gateway.ticks();
+    let tick = TickResponse {
+        tick: state.gateway.current_tick(),
+        tick_hz: PHI_TICK_HZ,
+        now_ms: ticks.now_ms(),
+        next_tick_ms: ticks.next_frame_ms(),
+        boot_ms: ticks.boot_ms(),
+        at_ms: query.at_ms,
+        tick_at: query.at_ms.map(|ms| state.gateway.tick_at(ms)),
+        at_tick: query.at_tick,
+        ms_at: query.at_tick.map(|tick| ticks.ms_at(tick)),
+    };
+    Json(NumberBase::negotiate(&headers, &base).render(tick, |tick| {
+        serde_json::json!({
+            "tick": octal_int(tick.tick.into()),
+            "tick_hz": octal_f64(tick.tick_hz),
+            "tick_ms": octal_f64(1_000.0 / tick.tick_hz),
+            "next_tick_in_ms": octal_int((tick.next_tick_ms - tick.now_ms).max(0) as u128),
+        })
+    }))
+}
+
+async fn exchange_quote(
+    Query(query): Query<ExchangeQuoteQuery>,
+) -> Result<Json<ExchangeQuoteResponse>, DlogError> {
+    let (from, to) = (query.from.zone(), query.to.zone());
+    let amount = query.amount as u128;
+    let quote = exchange::quote(&from, &to, amount).ok_or(BankError::Unconvertible(amount))?;
+    Ok(Json(ExchangeQuoteResponse {
+        from: from.planet,
+        from_unit: from.unit,
+        to: to.planet,
+        to_unit: to.unit,
+        quote,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandRequest {
+    line: String,
+}
+
+/// Run a typed command for the `X-Omega-Session` session. Commands that fail
+/// still answer 200 with `ok: false`, so the plugin can show the overlay.
+async fn run_command(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CommandRequest>,
+) -> Result<Json<CommandReply>, DlogError> {
+    let session_id = headers
+        .get("x-omega-session")
+        .and_then(|v| v.to_str().ok())
+        .ok_or(AuthError::Unverified)?;
+    let session = state
+        .gateway
+        .sessions()
+        .into_iter()
+        .find(|session| session.session_id == session_id)
+        .ok_or(AuthError::Unverified)?;
+    Ok(Json(commands::run(&state.gateway, &session, &req.line)))
+}
+
+async fn transfers_search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<TransferSearchQuery>,
+) -> Result<Json<Vec<TransferRecord>>, DlogError> {
+    let identity = state.verified(&SessionAuth {
+        phone: query.phone,
+        session_token: query.session_token,
+    })?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let search = TransferSearch {
+        label: String::new(),
+        planet: query.planet,
+        memo: query.memo.filter(|m| !m.trim().is_empty()),
+        tag: query.tag.filter(|t| !t.trim().is_empty()),
+        heights: query.from_height..=query.to_height.unwrap_or(u64::MAX),
+        limit: query.limit,
+    };
+    let records = state
+        .gateway
+        .search_transfers(&identity.phone, &query.label, search)?;
+    Ok(Json(records))
+}
+
+/// One of the caller's labels' inflows and outflows by category and
+/// counterparty, for the wallet's spending view.
+async fn transfers_summary(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SpendingQuery>,
+) -> Result<Json<SpendingSummary>, DlogError> {
+    let identity = state.verified(&SessionAuth {
+        phone: query.phone,
+        session_token: query.session_token,
+    })?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let summary = state.gateway.spending_summary(
+        &identity.phone,
+        &query.label,
+        query.planet,
+        query.from_height..=query.to_height.unwrap_or(u64::MAX),
+    )?;
+    Ok(Json(summary))
+}
+
+/// Open escrows involving the caller's labels.
+async fn escrows_open(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(auth): Query<SessionAuth>,
+    Query(base): Query<BaseQuery>,
+) -> Result<Json<Vec<Based<Escrow>>>, AuthError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let (base, height) = (
+        NumberBase::negotiate(&headers, &base),
+        state.gateway.block_height(),
+    );
+    let escrows = state.gateway.open_escrows(&identity.phone);
+    Ok(Json(
+        escrows
+            .into_iter()
+            .map(|escrow| {
+                base.render(escrow, |escrow| {
+                    let deadline = escrow.deadline_height;
+                    serde_json::json!({
+                        "deadline_height": octal_int(deadline.into()),
+                        "blocks_left": octal_int(deadline.saturating_sub(height).into()),
+                    })
+                })
+            })
+            .collect(),
+    ))
+}
+
+async fn escrow_open(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<EscrowOpenRequest>,
+) -> Result<Json<Escrow>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let escrow = state.gateway.open_escrow(&identity.phone, payload.escrow)?;
+    Ok(Json(escrow))
+}
+
+async fn escrow_confirm(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<Escrow>, DlogError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let escrow = state.gateway.confirm_escrow(&identity.phone, id)?;
+    Ok(Json(escrow))
+}
+
+async fn escrow_cancel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<Escrow>, DlogError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let escrow = state.gateway.cancel_escrow(&identity.phone, id)?;
+    Ok(Json(escrow))
+}
+
+/// The caller's input calibrations for one of their labels, by device.
+async fn label_calibrations(
+    State(state): State<AppState>,
+    Path(label): Path<String>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<std::collections::BTreeMap<String, DeviceCalibration>>, DlogError> {
+    let identity = state.verified(&auth)?;
+    Ok(Json(state.gateway.calibrations(&identity.phone, &label)?))
+}
+
+async fn label_calibrate(
+    State(state): State<AppState>,
+    Path(label): Path<String>,
+    Json(payload): Json<CalibrationRequest>,
+) -> Result<Json<DeviceCalibration>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let calibration = state
+        .gateway
+        .calibrate(&identity.phone, &label, payload.update)?;
+    Ok(Json(calibration))
+}
+
+async fn label_guardians_show(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(label): Path<String>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<GuardianSet>, DlogError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    Ok(Json(state.gateway.guardians_of(&identity.phone, &label)?))
+}
+
+/// Nominate guardians who can recover one of the caller's labels.
+async fn label_guardians(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(label): Path<String>,
+    Json(payload): Json<GuardianSetRequest>,
+) -> Result<Json<GuardianSet>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let set = state
+        .gateway
+        .set_guardians(&identity.phone, &label, payload.guardians)?;
+    Ok(Json(set))
 }
 
-impl AxisEvent {
-    fn into_axis_snapshot(self) -> omega::BridgeAxisSnapshot {
-        omega::BridgeAxisSnapshot {
-            action: self.action,
-            x: self.x,
-            y: self.y,
-            mode: match self.mode.unwrap_or_default() {
-                AxisModePayload::Relative => AxisMode::Relative,
-                AxisModePayload::Absolute => AxisMode::Absolute,
-            },
-        }
-    }
+/// Open recoveries of, to, or guarded by the caller's labels.
+async fn recoveries_open(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(auth): Query<SessionAuth>,
+    Query(base): Query<BaseQuery>,
+) -> Result<Json<Vec<Based<Recovery>>>, AuthError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let (base, height) = (
+        NumberBase::negotiate(&headers, &base),
+        state.gateway.block_height(),
+    );
+    let recoveries = state.gateway.open_recoveries(&identity.phone);
+    Ok(Json(
+        recoveries
+            .into_iter()
+            .map(|recovery| {
+                base.render(recovery, |recovery| {
+                    let unlock = recovery.unlock_height;
+                    let left = unlock.map(|h| octal_int(h.saturating_sub(height).into()));
+                    serde_json::json!({
+                        "expires_height": octal_int(recovery.expires_height.into()),
+                        "unlock_height": unlock.map(|h| octal_int(h.into())),
+                        "blocks_until_unlock": left,
+                    })
+                })
+            })
+            .collect(),
+    ))
 }
 
-impl BridgePositionPayload {
-    fn into_snapshot(self) -> BridgePositionSnapshot {
-        BridgePositionSnapshot {
-            player_uuid: self.player_uuid,
-            session_id: self.session_id,
-            stand_id: self.stand_id,
-            world: self.world,
-            pos: self.position.into_vec3(),
-            velocity: self.velocity.map(|v| v.into_vec3()),
-            rotation: self.rotation.map(|r| r.into_rotation()),
-        }
-    }
+async fn recovery_open(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RecoveryOpenRequest>,
+) -> Result<Json<Recovery>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let recovery = state
+        .gateway
+        .request_recovery(&identity.phone, &payload.label)?;
+    Ok(Json(recovery))
 }
 
-impl Vec3Payload {
-    fn into_vec3(self) -> omega::Vec3f {
-        omega::Vec3f {
-            x: self.x,
-            y: self.y,
-            z: self.z,
-        }
-    }
+async fn recovery_approve(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(payload): Json<RecoveryApproveRequest>,
+) -> Result<Json<Recovery>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let recovery = state
+        .gateway
+        .approve_recovery(&identity.phone, id, &payload.guardian)?;
+    Ok(Json(recovery))
 }
 
-impl RotationPayload {
-    fn into_rotation(self) -> omega::Rotation2d {
-        omega::Rotation2d {
-            yaw: self.yaw,
-            pitch: self.pitch,
-        }
-    }
+async fn recovery_cancel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<Recovery>, DlogError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let recovery = state.gateway.cancel_recovery(&identity.phone, id)?;
+    Ok(Json(recovery))
 }
 
-const HOST_REDIRECTS: &[(&str, &str)] = &[
-    ("pool.dlog.gold", "minepool.gold"),
-    ("www.pool.dlog.gold", "minepool.gold"),
-    ("locks.dlog.gold", "locks.gold"),
-    ("www.locks.dlog.gold", "locks.gold"),
-];
-
-const SIGNUP_FRAMES: [&str; 4] = [
-    ";signup;frame=omega_shell;Deploy;the;GOLDEN;WALLET;STACK;protect;the;infinity;bank;",
-    ";signup;frame=phone_ignition;POST;/auth/phone/start;receive;session_token;Apple/Google;biometrics;required;",
-    ";signup;frame=biometric_lock;POST;/auth/phone/confirm;send;session_token;biometric_signature;phone=identity;",
-    ";signup;frame=http4_bridge;set;DLOG_PHONE/DLOG_LABEL;run;dlog_http4_client;handshake;tick;mine;",
-];
+/// Guilds the caller has a label in.
+async fn guilds_list(
+    State(state): State<AppState>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<Vec<GuildView>>, AuthError> {
+    let identity = state.verified(&auth)?;
+    Ok(Json(state.gateway.guilds_for(&identity.phone)))
+}
 
-#[tokio::main]
-async fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .with_target(false)
-        .with_level(true)
-        .init();
+async fn guild_found(
+    State(state): State<AppState>,
+    Json(payload): Json<GuildFoundRequest>,
+) -> Result<Json<GuildView>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let guild = state.gateway.found_guild(&identity.phone, payload.charter)?;
+    Ok(Json(guild))
+}
 
-    // Cloud Run injects PORT; default to 8080 for local runs
-    let port: u16 = env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(8080);
+async fn guild_show(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<GuildView>, DlogError> {
+    let identity = state.verified(&auth)?;
+    Ok(Json(state.gateway.guild(&identity.phone, &name)?))
+}
 
-    let presence_base =
-        env::var("PRESENCE_BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:4000".to_string());
+async fn guild_role(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<GuildRoleRequest>,
+) -> Result<Json<GuildView>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let guild =
+        state
+            .gateway
+            .set_guild_role(&identity.phone, &name, &payload.label, payload.role)?;
+    Ok(Json(guild))
+}
 
-    let state = AppState {
-        gateway: Arc::new(OmegaGateway::new()),
-        presence: Client::new(),
-        presence_base,
-        phone_auth: Arc::new(PhoneAuth::default()),
-    };
+async fn guild_deposit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(payload): Json<GuildDepositRequest>,
+) -> Result<Json<GuildView>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let guild = state.gateway.guild_deposit(
+        &identity.phone,
+        &name,
+        &payload.from,
+        payload.planet,
+        payload.amount,
+    )?;
+    Ok(Json(guild))
+}
 
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/signup", get(signup_page))
-        .route("/signup/frame", get(signup_frame))
-        .route("/signup/qr", get(signup_qr))
-        .route("/health", get(health))
-        .route("/sky/timeline/default", get(sky_timeline_default))
-        .route("/omega/status", get(status))
-        .route("/omega/handshake", post(handshake))
-        .route("/omega/frame", post(frame))
-        .route("/omega/bridge/input", post(bridge_input))
-        .route("/omega/bridge/position", post(bridge_position))
-        .route("/identity/mojang", post(identity_mojang))
-        .route("/identity/web", post(identity_web))
-        .route("/auth/phone/start", post(auth_phone_start))
-        .route("/auth/phone/confirm", post(auth_phone_confirm))
-        .layer(middleware::from_fn(host_redirect))
-        .with_state(state);
+async fn guild_spend(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(payload): Json<GuildSpendRequest>,
+) -> Result<Json<GuildSpend>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let spend = state
+        .gateway
+        .guild_spend(&identity.phone, &name, payload.spend)?;
+    Ok(Json(spend))
+}
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    info!("dlog.gold Ω-edge listening on http://{addr}");
+async fn guild_approve(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((name, id)): Path<(String, u64)>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<GuildSpend>, DlogError> {
+    let identity = state.verified(&auth)?;
+    state.require_session(&headers, &identity, Capability::Banking)?;
+    let spend = state
+        .gateway
+        .approve_guild_spend(&identity.phone, &name, id)?;
+    Ok(Json(spend))
+}
 
-    let listener = TcpListener::bind(addr)
-        .await
-        .expect("failed to bind TCP listener");
+/// The caller's inbox, with an unread badge for the HUD.
+async fn notifications_list(
+    State(state): State<AppState>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<InboxView>, AuthError> {
+    let identity = state.verified(&auth)?;
+    Ok(Json(state.gateway.notifications(&identity.phone)))
+}
 
-    if let Err(err) = axum::serve(listener, app).await {
-        error!("server error: {err}");
-    }
+async fn notifications_read(
+    State(state): State<AppState>,
+    Json(payload): Json<NotificationsReadRequest>,
+) -> Result<Json<NotificationsChanged>, AuthError> {
+    let identity = state.verified(&payload.auth)?;
+    let changed = state
+        .gateway
+        .mark_notifications_read(&identity.phone, &payload.ids);
+    Ok(Json(NotificationsChanged {
+        changed,
+        unread: state.gateway.notifications(&identity.phone).unread,
+    }))
 }
 
-async fn root() -> Html<String> {
-    let html = r#"<!doctype html>
-<html lang=\"en\">
-<head>
-  <meta charset=\"utf-8\" />
-  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />
-  <title>dlog.gold · Minecraft</title>
-  <style>
-    body { font-family: sans-serif; background: #05060a; color: #e2e8f0; margin: 0; padding: 24px; }
-    .card { max-width: 640px; margin: 0 auto; background: #0b0f1a; border: 1px solid #1f2937; border-radius: 12px; padding: 20px 24px; box-shadow: 0 8px 30px rgba(0,0,0,0.35); }
-    h1 { margin-top: 0; }
-    code { background: #111827; padding: 2px 6px; border-radius: 6px; color: #93c5fd; }
-    ul { padding-left: 18px; }
-    a { color: #93c5fd; text-decoration: none; }
-    a:hover { text-decoration: underline; }
-  </style>
-</head>
-<body>
-  <div class=\"card\">
-    <h1>Welcome to dlog.gold</h1>
-    <p>Join the Minecraft network:</p>
-    <ul>
-      <li><strong>Java:</strong> <code>dlog.gold</code> (port 25565)</li>
-      <li><strong>Bedrock:</strong> <code>dlog.gold</code> (port 19132)</li>
-    </ul>
-    <p>APIs:</p>
-    <ul>
-      <li><a href=\"/sky/timeline/default\">/sky/timeline/default</a> – default sky show timeline</li>
-      <li><a href=\"/health\">/health</a> – health check</li>
-      <li><a href=\"/omega/status\">/omega/status</a> – omega gateway status</li>
-    </ul>
-    <p>Have fun. ✨</p>
-  </div>
-</body>
-</html>"#;
-    Html(html.to_string())
+async fn notifications_purge(
+    State(state): State<AppState>,
+    Json(payload): Json<NotificationsPurgeRequest>,
+) -> Result<Json<NotificationsChanged>, AuthError> {
+    let identity = state.verified(&payload.auth)?;
+    let changed = state
+        .gateway
+        .purge_notifications(&identity.phone, payload.all);
+    Ok(Json(NotificationsChanged {
+        changed,
+        unread: state.gateway.notifications(&identity.phone).unread,
+    }))
 }
 
-async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok",
-        gateway_id: state.gateway.id().to_string(),
-        boot_ms: state.gateway.boot_ms(),
-    })
+/// Remaining faucet budget; `404` when the faucet is off.
+async fn faucet_status(State(state): State<AppState>) -> Result<Json<FaucetStatus>, StatusCode> {
+    state
+        .gateway
+        .faucet_status()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-async fn sky_timeline_default() -> Json<SkyTimelineResponse> {
-    let timeline = SkyTimeline::default_eight();
-    Json(SkyTimelineResponse {
-        total_duration_ticks: timeline.total_duration_ticks(),
-        show: timeline.show().clone(),
-    })
+/// Pay today's faucet grant into one of the caller's labels.
+async fn faucet_claim(
+    State(state): State<AppState>,
+    connect: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(payload): Json<FaucetClaimRequest>,
+) -> Result<Json<FaucetGrant>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let ip = faucet::client_address(
+        headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()),
+        connect.map(|ConnectInfo(addr)| addr.ip()),
+        state.trusted_proxy,
+    );
+    let grant = state
+        .gateway
+        .faucet_claim(&identity.phone, &payload.label, ip.as_deref())?;
+    Ok(Json(grant))
 }
 
-async fn handshake(
+/// Register a browser subscription or APNs/FCM token for push notifications.
+async fn device_register(
     State(state): State<AppState>,
-    Json(payload): Json<HandshakeRequest>,
-) -> Result<Json<HandshakeResponse>, StatusCode> {
-    let phone = payload
-        .phone
-        .as_deref()
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-    let token = payload
-        .session_token
-        .as_deref()
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-    let identity = state
-        .phone_auth
-        .verified_identity(token, phone)
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+    Json(payload): Json<DeviceRegisterRequest>,
+) -> Result<Json<Device>, DlogError> {
+    let identity = state.verified(&payload.auth)?;
+    let device = state
+        .gateway
+        .register_device(&identity.phone, payload.device)?;
+    Ok(Json(device))
+}
 
-    let mut response = state.gateway.handle_handshake(payload);
-    response.identity = Some(identity);
-    Ok(Json(response))
+async fn devices_list(
+    State(state): State<AppState>,
+    Query(auth): Query<SessionAuth>,
+) -> Result<Json<Vec<Device>>, AuthError> {
+    let identity = state.verified(&auth)?;
+    Ok(Json(state.gateway.devices(&identity.phone)))
 }
 
-async fn frame(
+async fn device_remove(
     State(state): State<AppState>,
-    Json(payload): Json<FrameEnvelope>,
-) -> Json<FrameAck> {
-    let response = state.gateway.handle_frame(payload);
-    Json(response)
+    Path(id): Path<String>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<StatusCode, DlogError> {
+    let identity = state.verified(&auth)?;
+    if !state.gateway.remove_device(&identity.phone, &id) {
+        return Err(FrameError::NotFound(format!("device {id}")).into());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn push_key(State(state): State<AppState>) -> Json<PushKeyResponse> {
+    Json(PushKeyResponse {
+        vapid_public_key: state.gateway.push_key().map(String::from),
+    })
 }
 
-async fn status(State(state): State<AppState>) -> Json<GatewayStatus> {
-    Json(state.gateway.status())
+/// Record who holds an item or land reference so escrows can trade it.
+async fn item_assign(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ItemAssignment>,
+) -> Result<Json<ItemAssignment>, DlogError> {
+    state.require_admin(&headers)?;
+    let assigned = state.gateway.assign_item(payload)?;
+    state.gateway.audit().record(
+        "admin",
+        "item_assign",
+        serde_json::json!({ "item": assigned.item }),
+    );
+    Ok(Json(assigned))
 }
 
+/// Forward a Mojang login to presence once the uuid's profile carries the
+/// claimed gamer tag; the body says how the claim was checked.
 async fn identity_mojang(
     State(state): State<AppState>,
     Json(payload): Json<MojangPresencePayload>,
-) -> StatusCode {
-    let url = format!("{}/presence/mojang", state.presence_base);
-    match state.presence.post(url).json(&payload).send().await {
-        Ok(resp) if resp.status().is_success() => StatusCode::NO_CONTENT,
-        Ok(resp) => StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
-        Err(_) => StatusCode::BAD_GATEWAY,
-    }
+) -> Result<(StatusCode, Json<MojangIdentityResponse>), DlogError> {
+    let verification = state
+        .mojang
+        .verify(&payload.mojang_uuid, &payload.gamer_tag, now_ms())
+        .await?;
+    let (status, forwarded) = if verification.status.passes() {
+        let status = forward_presence(&state, "mojang", &payload).await;
+        if status.is_success() {
+            (StatusCode::OK, true)
+        } else {
+            (status, false)
+        }
+    } else {
+        let status = match verification.status {
+            VerificationStatus::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            VerificationStatus::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::FORBIDDEN,
+        };
+        (status, false)
+    };
+    let body = MojangIdentityResponse {
+        forwarded,
+        verification,
+    };
+    Ok((status, Json(body)))
 }
 
 async fn identity_web(
     State(state): State<AppState>,
     Json(payload): Json<WebPresencePayload>,
 ) -> StatusCode {
-    let url = format!("{}/presence/web", state.presence_base);
-    match state.presence.post(url).json(&payload).send().await {
-        Ok(resp) if resp.status().is_success() => StatusCode::NO_CONTENT,
-        Ok(resp) => StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
-        Err(_) => StatusCode::BAD_GATEWAY,
+    forward_presence(&state, "web", &payload).await
+}
+
+/// POST a login assertion to presence and relay its status (5xx are retried).
+async fn forward_presence<T: Serialize>(state: &AppState, kind: &str, payload: &T) -> StatusCode {
+    let url = format!("{}/presence/{kind}", state.presence_base);
+    let result = state
+        .presence_upstream
+        .call_if(
+            || async {
+                let resp = state.presence.post(&url).json(payload).send().await?;
+                if resp.status().is_server_error() {
+                    resp.error_for_status_ref()?;
+                }
+                Ok(resp.status())
+            },
+            presence_transient,
+        )
+        .await;
+    match result {
+        Ok(status) if status.is_success() => StatusCode::NO_CONTENT,
+        Ok(status) => StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
+        Err(err) => {
+            warn!("presence {kind} forward failed: {err}");
+            StatusCode::BAD_GATEWAY
+        }
     }
 }
 
+/// Connection failures, timeouts and 5xx are worth retrying; 4xx are not.
+fn presence_transient(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.status().is_some_and(|s| s.is_server_error())
+}
+
+/// Erase a phone's personal data: presence, auth sessions, push devices,
+/// aliases, and bank labels (folded into an anonymous tombstone). The receipt lands in the audit log.
+async fn identity_delete(
+    State(state): State<AppState>,
+    Json(auth): Json<SessionAuth>,
+) -> Result<Json<DeletionReceipt>, DlogError> {
+    let identity = state.verified(&auth)?;
+
+    let presence_purged = match delete_presence(&state, &identity.phone, &auth.session_token).await
+    {
+        Ok(purged) => purged,
+        Err(CallError::Failed(err)) if err.status() == Some(reqwest::StatusCode::FORBIDDEN) => {
+            return Err(AuthError::Forbidden(
+                "presence is bound to another session for this phone; confirm again and retry"
+                    .to_string(),
+            )
+            .into());
+        }
+        Err(err) => {
+            warn!("presence deletion failed: {err}");
+            return Err(DlogError::Upstream(
+                "presence service unavailable; nothing was deleted".to_string(),
+            ));
+        }
+    };
+    let sessions_purged = state.phone_auth.purge_phone(&identity.phone);
+    let mut receipt = state.gateway.forget_phone(&identity.phone);
+    receipt.sessions_purged = sessions_purged;
+    receipt.presence_purged = presence_purged;
+    receipt.devices_purged = state.gateway.purge_devices(&identity.phone);
+
+    state.gateway.audit().record(
+        format!("subject:{}", receipt.subject_digest),
+        "identity_delete",
+        serde_json::to_value(&receipt).unwrap_or_default(),
+    );
+    info!(
+        "identity deleted (receipt {}, {} labels tombstoned)",
+        receipt.receipt_id,
+        receipt.labels_tombstoned.len()
+    );
+    Ok(Json(receipt))
+}
+
 async fn bridge_input(
     State(state): State<AppState>,
     Json(payload): Json<BridgeInputPayload>,
@@ -491,6 +3270,13 @@ async fn bridge_input(
     })
 }
 
+/// Worlds, movement profiles, portals and sky shows for the Paper plugin;
+/// the ETag follows the manifest `version`.
+async fn bridge_manifest(State(state): State<AppState>) -> Tagged<manifest::WorldManifest> {
+    let manifest = state.gateway.bridge_manifest();
+    Tagged(explorer::etag(&["manifest", &manifest.version]), manifest)
+}
+
 async fn bridge_position(
     State(state): State<AppState>,
     Json(payload): Json<BridgePositionPayload>,
@@ -513,14 +3299,17 @@ async fn auth_phone_start(
         .unwrap_or_else(|| "comet".to_string());
     let display_name = payload
         .display_name
-        .unwrap_or_else(|| format!("Ω {}", phone));
+        .unwrap_or_else(|| format!("Ω {}", redact_phone(&phone)));
 
-    let session = state
-        .phone_auth
-        .start_session(phone, label, display_name, vec!["google", "apple"]);
+    let (session_token, session) = state.phone_auth.start_session(
+        phone,
+        label,
+        display_name,
+        vec!["google".into(), "apple".into()],
+    );
 
     Json(PhoneStartResponse {
-        session_token: session.token,
+        session_token,
         expires_in_ms: session.expires_at_ms,
         providers: session.providers,
         biometric_required: true,
@@ -565,23 +3354,63 @@ async fn auth_phone_confirm(
 async fn register_presence(
     state: &AppState,
     identity: &PhoneAuthIdentity,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), CallError<reqwest::Error>> {
     let payload = WebPresencePayload {
         phone: identity.phone.clone(),
         label: identity.label.clone(),
         session_token: identity.session_token.clone(),
         display_name: identity.display_name.clone(),
     };
+    let url = format!("{}/presence/web", state.presence_base);
 
     state
-        .presence
-        .post(format!("{}/presence/web", state.presence_base))
-        .json(&payload)
-        .send()
-        .await?
-        .error_for_status()?;
+        .presence_upstream
+        .call_if(
+            || async {
+                state
+                    .presence
+                    .post(&url)
+                    .json(&payload)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            },
+            presence_transient,
+        )
+        .await
+}
 
-    Ok(())
+/// Ask the presence service to drop the phone's record; `Ok(false)` if none
+/// existed. Presence only deletes for the phone's verified session, or with
+/// the service token when the record has none.
+async fn delete_presence(
+    state: &AppState,
+    phone: &str,
+    session_token: &str,
+) -> Result<bool, CallError<reqwest::Error>> {
+    let url = format!("{}/presence/{}", state.presence_base, phone);
+    state
+        .presence_upstream
+        .call_if(
+            || async {
+                let mut request = state
+                    .presence
+                    .delete(&url)
+                    .header("x-session-token", session_token);
+                if let Some(token) = &state.presence_token {
+                    request = request.header("x-service-token", token);
+                }
+                let resp = request.send().await?;
+                if resp.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(false);
+                }
+                resp.error_for_status()?;
+                Ok(true)
+            },
+            presence_transient,
+        )
+        .await
 }
 
 #[allow(dead_code)]
@@ -741,32 +3570,65 @@ async fn signup_qr(Query(params): Query<QrParams>) -> Response {
 
 #[derive(Debug, Default)]
 struct PhoneAuth {
+    /// Sessions by the digest of their token ([`auth_store::token_key`]).
     sessions: Mutex<HashMap<String, PhoneAuthSession>>,
-}
-
-#[derive(Debug, Clone)]
-struct PhoneAuthSession {
-    token: String,
-    phone: String,
-    label: String,
-    display_name: String,
-    expires_at_ms: i64,
-    verified: bool,
-    providers: Vec<&'static str>,
+    privacy: PhonePrivacy,
+    /// Where sessions are written after every change; `None` keeps them in
+    /// memory only.
+    store: Option<Arc<dyn SessionStore>>,
+    changed: Notify,
 }
 
 impl PhoneAuth {
+    /// Restore the unexpired sessions `store` holds and keep writing there.
+    async fn with_store(
+        privacy: PhonePrivacy,
+        store: Arc<dyn SessionStore>,
+    ) -> Result<Self, StorageError> {
+        let now = epoch_ms();
+        let sessions = store
+            .load()
+            .await?
+            .into_iter()
+            .filter(|session| session.expires_at_ms >= now)
+            .map(|session| (session.token_key.clone(), session))
+            .collect();
+        Ok(Self {
+            sessions: Mutex::new(sessions),
+            privacy,
+            store: Some(store),
+            changed: Notify::new(),
+        })
+    }
+
+    /// Write the unexpired sessions to the store, if there is one.
+    async fn persist(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let now = epoch_ms();
+        let sessions: Vec<PhoneAuthSession> = {
+            let mut guard = self.sessions.lock().expect("phone auth lock");
+            guard.retain(|_, session| session.expires_at_ms >= now);
+            guard.values().cloned().collect()
+        };
+        if let Err(err) = store.save(&sessions).await {
+            warn!("[auth] failed to save sessions to {}: {err}", store.name());
+        }
+    }
+
+    /// Start a session; returns its token and the session.
     fn start_session(
         &self,
         phone: String,
         label: String,
         display_name: String,
-        providers: Vec<&'static str>,
-    ) -> PhoneAuthSession {
+        providers: Vec<String>,
+    ) -> (String, PhoneAuthSession) {
         let token = uuid::Uuid::new_v4().to_string();
         let session = PhoneAuthSession {
-            token: token.clone(),
-            phone,
+            token_key: token_key(&token),
+            phone: self.privacy.phone_key(&phone),
             label,
             display_name,
             expires_at_ms: epoch_ms() + 5 * 60 * 1000,
@@ -776,8 +3638,9 @@ impl PhoneAuth {
         self.sessions
             .lock()
             .expect("phone auth lock")
-            .insert(token.clone(), session.clone());
-        session
+            .insert(session.token_key.clone(), session.clone());
+        self.changed.notify_one();
+        (token, session)
     }
 
     fn confirm_session(
@@ -788,37 +3651,65 @@ impl PhoneAuth {
         if biometric_signature.trim().is_empty() {
             return None;
         }
+        let key = token_key(token);
         let mut guard = self.sessions.lock().expect("phone auth lock");
-        let entry = guard.get_mut(token)?;
+        let entry = guard.get_mut(&key)?;
         if entry.expires_at_ms < epoch_ms() {
-            guard.remove(token);
+            guard.remove(&key);
+            self.changed.notify_one();
             return None;
         }
         entry.verified = true;
+        self.changed.notify_one();
 
         Some(PhoneAuthIdentity {
             phone: entry.phone.clone(),
             label: entry.label.clone(),
             display_name: entry.display_name.clone(),
-            session_token: entry.token.clone(),
+            session_token: token.to_string(),
         })
     }
 
+    /// Move a verified auth session to a fresh token (device migration); the
+    /// old token stops working. `None` once it has expired.
+    fn rotate(&self, token: &str) -> Option<String> {
+        let mut guard = self.sessions.lock().expect("phone auth lock");
+        let mut session = guard.remove(&token_key(token))?;
+        self.changed.notify_one();
+        if session.expires_at_ms < epoch_ms() || !session.verified {
+            return None;
+        }
+        let token = uuid::Uuid::new_v4().to_string();
+        session.token_key = token_key(&token);
+        guard.insert(session.token_key.clone(), session);
+        Some(token)
+    }
+
+    /// Drop every auth session for `phone`; returns how many were removed.
+    fn purge_phone(&self, phone: &str) -> usize {
+        let key = self.privacy.phone_key(phone);
+        let mut guard = self.sessions.lock().expect("phone auth lock");
+        let before = guard.len();
+        guard.retain(|_, session| session.phone != key);
+        self.changed.notify_one();
+        before - guard.len()
+    }
+
     fn verified_identity(
         &self,
         token: &str,
         phone: &str,
     ) -> Option<IdentityDescriptor> {
         let guard = self.sessions.lock().expect("phone auth lock");
-        let entry = guard.get(token)?;
+        let entry = guard.get(&token_key(token))?;
         if entry.expires_at_ms < epoch_ms() || !entry.verified {
             return None;
         }
-        if entry.phone != phone {
+        if entry.phone != self.privacy.phone_key(phone) {
             return None;
         }
         Some(IdentityDescriptor {
-            phone: entry.phone.clone(),
+            phone: phone.trim().to_string(),
             label: entry.label.clone(),
             display_name: entry.display_name.clone(),
             presence_state: "online".into(),
@@ -0,0 +1,459 @@
+//! World manifest for the Paper bridge (`GET /omega/bridge/manifest`).
+//!
+//! The plugin learns from it which worlds the gateway knows and their Y
+//! bounds (positions outside are clamped), the movement profiles and device
+//! scales `/omega/bridge/input` applies, the portals to place, and the sky
+//! shows it may be asked to render. `version` is the sha-less digest of the
+//! rest of the body; the plugin keeps it and compares on its next fetch
+//! (or revalidates with the response's ETag) to notice config drift.
+//!
+//! Portals default to the canon spawn portal (`portal-0` at the origin of
+//! every shell world, as in `stack/coords_mapping.json`). Operators replace
+//! them with a JSON list at `$OMEGA_ROOT/∞/bridge;portals`, read on every
+//! request so edits show up without a restart.
+//!
+//! A bootstrap manifest ([`crate::bootstrap`]) narrows the planets served
+//! and adds or reshapes worlds, movement profiles and sky shows through
+//! [`WorldSettings`]; without one the built-in worlds stand.
+
+use corelib::shaless_json_digest;
+use dlog_sky::SkyTimeline;
+use serde::{Deserialize, Serialize};
+use spec::{PlanetId, SkyShowConfig, SkySlideRef};
+use tracing::warn;
+
+use crate::omega::PHI_F32;
+use crate::store::GatewayStore;
+
+/// Bumped when the manifest's shape changes.
+pub const MANIFEST_SCHEMA: u32 = 2;
+const PORTALS_PATH: &str = ";∞;bridge;portals;";
+/// Worlds, profiles and shows set by the last bootstrap manifest.
+pub const WORLD_SETTINGS_PATH: &str = ";∞;bootstrap;world;";
+
+/// Blocks per unit of `move` axis, before profile and device scales.
+pub const INPUT_VELOCITY_SCALE: f32 = 0.08;
+/// Upward velocity of a jump, over φ.
+pub const INPUT_ASCENT_SCALE: f32 = 0.16;
+/// Top of worlds without their own bounds.
+pub const DEFAULT_WORLD_MAX_Y: f32 = 320.0;
+
+/// Movement profiles by name and their velocity multiplier; anything else
+/// moves as `default`.
+const MOVEMENT_PROFILES: [(&str, f32); 3] = [("default", 1.0), ("surf", 1.35), ("bhop", 1.2)];
+/// Input devices that move slower than keyboard and mouse.
+const DEVICE_SCALES: [(&str, f32); 3] = [("keyboard", 1.0), ("touch", 0.7), ("gamepad", 0.9)];
+
+/// Velocity multiplier of movement profile `name` (case-insensitive).
+pub fn profile_scale(name: Option<&str>) -> f32 {
+    name.and_then(|name| {
+        MOVEMENT_PROFILES
+            .iter()
+            .find(|(profile, _)| profile.eq_ignore_ascii_case(name))
+    })
+    .map_or(1.0, |(_, scale)| *scale)
+}
+
+pub fn device_scale(device: Option<&str>) -> f32 {
+    device
+        .and_then(|device| DEVICE_SCALES.iter().find(|(name, _)| *name == device))
+        .map_or(1.0, |(_, scale)| *scale)
+}
+
+/// Allowed Y range of `world`.
+pub fn world_bounds(world: &str) -> (f32, f32) {
+    match world {
+        "moon_shell" | "moon_core" => (0.0, 160.0),
+        "mars_shell" | "mars_core" => (0.0, 210.0),
+        "sun_shell" | "sun_core" => (32.0, 400.0),
+        "earth_core" => (0.0, 128.0),
+        _ => (0.0, DEFAULT_WORLD_MAX_Y),
+    }
+}
+
+/// What a bootstrap manifest set on top of the built-in worlds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldSettings {
+    /// Planets this deployment serves; every planet when empty.
+    #[serde(default)]
+    pub planets: Vec<PlanetId>,
+    /// Worlds added, or built-in ones reshaped (matched by name).
+    #[serde(default)]
+    pub worlds: Vec<WorldEntry>,
+    /// Movement profiles added, or built-in ones rescaled.
+    #[serde(default)]
+    pub profiles: Vec<ProfileEntry>,
+    /// Sky shows added, or the `default` and planet shows replaced.
+    #[serde(default)]
+    pub shows: Vec<ShowEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldEntry {
+    pub name: String,
+    pub planet: PlanetId,
+    pub min_y: f32,
+    pub max_y: f32,
+    /// Profile of bridge input that names none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShowEntry {
+    /// `default`, a planet key, or a new id.
+    pub id: String,
+    pub slides: Vec<SlideEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlideEntry {
+    pub id: String,
+    pub duration_ticks: u64,
+}
+
+impl WorldSettings {
+    pub fn load(store: &GatewayStore) -> Self {
+        store.load_json(WORLD_SETTINGS_PATH).unwrap_or_default()
+    }
+
+    pub fn serves(&self, planet: PlanetId) -> bool {
+        self.planets.is_empty() || self.planets.contains(&planet)
+    }
+
+    fn world(&self, name: &str) -> Option<&WorldEntry> {
+        self.worlds.iter().find(|world| world.name == name)
+    }
+
+    /// Allowed Y range of `world`.
+    pub fn world_bounds(&self, world: &str) -> (f32, f32) {
+        self.world(world)
+            .map_or_else(|| world_bounds(world), |w| (w.min_y, w.max_y))
+    }
+
+    /// Velocity multiplier of `profile`, or of `world`'s own profile when
+    /// the input names none.
+    pub fn profile_scale(&self, profile: Option<&str>, world: &str) -> f32 {
+        let name = profile.or_else(|| self.world(world)?.profile.as_deref());
+        name.and_then(|name| {
+            self.profiles
+                .iter()
+                .find(|entry| entry.name.eq_ignore_ascii_case(name))
+        })
+        .map_or_else(|| profile_scale(name), |entry| entry.scale)
+    }
+
+    /// Built-in profiles with the manifest's on top.
+    pub fn profiles(&self) -> Vec<(String, f32)> {
+        let mut profiles: Vec<(String, f32)> = MOVEMENT_PROFILES
+            .iter()
+            .map(|(name, scale)| (name.to_string(), *scale))
+            .collect();
+        for entry in &self.profiles {
+            match profiles
+                .iter_mut()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&entry.name))
+            {
+                Some(profile) => profile.1 = entry.scale,
+                None => profiles.push((entry.name.clone(), entry.scale)),
+            }
+        }
+        profiles
+    }
+
+    /// Sky show `id`: the manifest's, else `default` or a served planet's.
+    pub fn timeline(&self, id: &str) -> Option<SkyTimeline> {
+        if let Some(show) = self.shows.iter().find(|show| show.id == id) {
+            let slides = show
+                .slides
+                .iter()
+                .map(|slide| SkySlideRef {
+                    id: slide.id.clone(),
+                    duration_ticks: slide.duration_ticks,
+                    asset: None,
+                    gradient: Vec::new(),
+                })
+                .collect();
+            return Some(SkyTimeline::new(SkyShowConfig { slides }));
+        }
+        if id == "default" {
+            return Some(SkyTimeline::default_eight());
+        }
+        PlanetId::parse(id)
+            .filter(|planet| self.serves(*planet))
+            .map(SkyTimeline::for_planet)
+    }
+
+    /// Ids of every show there is: `default`, served planets, then the
+    /// manifest's own.
+    pub fn show_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = std::iter::once("default")
+            .chain(
+                PlanetId::ALL
+                    .into_iter()
+                    .filter(|planet| self.serves(*planet))
+                    .map(PlanetId::key),
+            )
+            .map(String::from)
+            .collect();
+        for show in &self.shows {
+            if !ids.contains(&show.id) {
+                ids.push(show.id.clone());
+            }
+        }
+        ids
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldManifest {
+    pub schema: u32,
+    pub version: String,
+    #[serde(flatten)]
+    pub body: ManifestBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestBody {
+    pub worlds: Vec<WorldSpec>,
+    pub profiles: Vec<MovementProfile>,
+    pub devices: Vec<DeviceScale>,
+    pub portals: Vec<Portal>,
+    pub sky_shows: Vec<SkyShowSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldSpec {
+    pub name: String,
+    pub planet: PlanetId,
+    pub min_y: f32,
+    pub max_y: f32,
+    pub surface_gravity_mps2: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MovementProfile {
+    pub name: String,
+    /// Blocks per tick per unit of `move` axis on keyboard and mouse.
+    pub velocity_scale: f32,
+    /// Upward velocity of a jump.
+    pub ascent: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceScale {
+    pub device: &'static str,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Portal {
+    pub id: String,
+    pub world: String,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// World the portal leads to, if it leads anywhere yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_world: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkyShowSpec {
+    /// `default` or a planet key, as `/sky/shows/:id/preview` takes.
+    pub id: String,
+    pub slides: Vec<String>,
+    pub total_duration_ticks: u64,
+}
+
+impl WorldManifest {
+    pub fn build(store: &GatewayStore, settings: &WorldSettings) -> Self {
+        let builtin = PlanetId::ALL
+            .into_iter()
+            .filter(|planet| settings.serves(*planet))
+            .flat_map(|planet| {
+                ["shell", "core"].map(|layer| (format!("{}_{layer}", planet.key()), planet))
+            });
+        let declared = settings
+            .worlds
+            .iter()
+            .map(|world| (world.name.clone(), world.planet));
+        let mut worlds: Vec<WorldSpec> = Vec::new();
+        for (name, planet) in builtin.chain(declared) {
+            if worlds.iter().any(|world| world.name == name) {
+                continue;
+            }
+            let (min_y, max_y) = settings.world_bounds(&name);
+            let planet = settings.world(&name).map_or(planet, |world| world.planet);
+            worlds.push(WorldSpec {
+                profile: settings
+                    .world(&name)
+                    .and_then(|world| world.profile.clone()),
+                name,
+                planet,
+                min_y,
+                max_y,
+                surface_gravity_mps2: planet.profile().surface_gravity_mps2,
+            });
+        }
+        let profiles = settings
+            .profiles()
+            .into_iter()
+            .map(|(name, scale)| MovementProfile {
+                name,
+                velocity_scale: PHI_F32 * INPUT_VELOCITY_SCALE * scale,
+                ascent: PHI_F32 * INPUT_ASCENT_SCALE,
+            })
+            .collect();
+        let devices = DEVICE_SCALES
+            .iter()
+            .map(|(device, scale)| DeviceScale {
+                device,
+                scale: *scale,
+            })
+            .collect();
+        let sky_shows = settings
+            .show_ids()
+            .into_iter()
+            .filter_map(|id| Some((settings.timeline(&id)?, id)))
+            .map(|(timeline, id)| SkyShowSpec {
+                id,
+                slides: timeline
+                    .show()
+                    .slides
+                    .iter()
+                    .map(|s| s.id.clone())
+                    .collect(),
+                total_duration_ticks: timeline.total_duration_ticks(),
+            })
+            .collect();
+        let body = ManifestBody {
+            worlds,
+            profiles,
+            devices,
+            portals: portals(store, settings),
+            sky_shows,
+        };
+        Self {
+            schema: MANIFEST_SCHEMA,
+            version: shaless_json_digest(&(MANIFEST_SCHEMA, &body)),
+            body,
+        }
+    }
+}
+
+fn portals(store: &GatewayStore, settings: &WorldSettings) -> Vec<Portal> {
+    if let Some(text) = store.load_text(PORTALS_PATH) {
+        match serde_json::from_str(&text) {
+            Ok(portals) => return portals,
+            Err(err) => warn!("[manifest] ignoring {PORTALS_PATH}: {err}"),
+        }
+    }
+    PlanetId::ALL
+        .into_iter()
+        .filter(|planet| settings.serves(*planet))
+        .map(|planet| Portal {
+            id: "portal-0".into(),
+            world: format!("{}_shell", planet.key()),
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            to_world: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_versions_follow_portal_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = GatewayStore::at(dir.path());
+        let settings = WorldSettings::default();
+        let manifest = WorldManifest::build(&store, &settings);
+        assert_eq!(manifest.body.worlds.len(), PlanetId::ALL.len() * 2);
+        let sun = &manifest
+            .body
+            .worlds
+            .iter()
+            .find(|w| w.name == "sun_core")
+            .unwrap();
+        assert_eq!((sun.min_y, sun.max_y), (32.0, 400.0));
+        assert_eq!(manifest.body.portals.len(), PlanetId::ALL.len());
+        assert_eq!(manifest.body.sky_shows[0].slides.len(), 8);
+        assert_eq!(
+            WorldManifest::build(&store, &settings).version,
+            manifest.version
+        );
+
+        store
+            .save_text(
+                PORTALS_PATH,
+                r#"[{"id":"gate","world":"moon_shell","x":1,"y":64,"z":-2,"to_world":"moon_core"}]"#,
+            )
+            .unwrap();
+        let edited = WorldManifest::build(&store, &settings);
+        assert_eq!(
+            edited.body.portals[0].to_world.as_deref(),
+            Some("moon_core")
+        );
+        assert_ne!(edited.version, manifest.version);
+
+        store.save_text(PORTALS_PATH, "not json").unwrap();
+        assert_eq!(
+            WorldManifest::build(&store, &settings).version,
+            manifest.version
+        );
+        assert_eq!(profile_scale(Some("SURF")), 1.35);
+        assert_eq!(device_scale(Some("mouse")), 1.0);
+    }
+
+    #[test]
+    fn settings_narrow_planets_and_reshape_worlds() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = GatewayStore::at(dir.path());
+        let settings = WorldSettings {
+            planets: vec![PlanetId::Earth, PlanetId::Moon],
+            worlds: vec![WorldEntry {
+                name: "moon_dome".into(),
+                planet: PlanetId::Moon,
+                min_y: 0.0,
+                max_y: 160.0,
+                profile: Some("glide".into()),
+            }],
+            profiles: vec![ProfileEntry {
+                name: "glide".into(),
+                scale: 0.5,
+            }],
+            shows: vec![ShowEntry {
+                id: "moon".into(),
+                slides: vec![SlideEntry {
+                    id: "moon-dawn".into(),
+                    duration_ticks: 888,
+                }],
+            }],
+        };
+        let manifest = WorldManifest::build(&store, &settings);
+        assert_eq!(manifest.body.worlds.len(), 5);
+        assert_eq!(manifest.body.portals.len(), 2);
+        assert_eq!(settings.world_bounds("moon_dome"), (0.0, 160.0));
+        assert_eq!(settings.profile_scale(None, "moon_dome"), 0.5);
+        assert_eq!(settings.profile_scale(Some("surf"), "moon_dome"), 1.35);
+        assert_eq!(settings.timeline("moon").unwrap().show().slides.len(), 1);
+        assert!(settings.timeline("mars").is_none());
+        assert_ne!(
+            manifest.version,
+            WorldManifest::build(&store, &WorldSettings::default()).version
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Mining jobs: proof of work paid into the InfinityBank.
+//!
+//! A `MINE_JOB` frame opens a job for a label the session's phone owns: a
+//! challenge and a difficulty, counted in leading zero bits of the sha-less
+//! hash. The difficulty is the work of one block at the φ tick rate
+//! (`PHI_TICK_HZ` hashes a second for `target_block_seconds`, about 2^16),
+//! so a miner hashing at the tick rate earns about one reward a block;
+//! `OMEGA_MINING_DIFFICULTY` overrides it. A `MINE_RESULT` frame names the
+//! job and a nonce, and `challenge;nonce` must hash with at least that many
+//! leading zeros. An accepted result closes the job and pays [`REWARD`] from
+//! [`MINING_LABEL`] into the label. Jobs expire after [`JOB_TTL_BLOCKS`];
+//! at most [`OPEN_JOBS_MAX`] are open at once, the oldest giving way. Jobs
+//! live in memory on the primary: replicas relay mining frames to it.
+
+use corelib::shaless_leading_zeros;
+use dlog_error::FrameError;
+use serde::Serialize;
+use spec::{MonetarySpec, PlanetId};
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::tick;
+
+/// Payer on the transfer log for mining rewards.
+pub const MINING_LABEL: &str = ";∞;mining;";
+/// Paid for each accepted result.
+pub const REWARD: u128 = 88;
+pub const JOB_TTL_BLOCKS: u64 = 16;
+pub const OPEN_JOBS_MAX: usize = 1024;
+/// Accepted results count towards the hash rate for this long.
+const RATE_WINDOW_MS: i64 = 60_000;
+/// Beyond this the odds of a result are too slim to matter.
+pub const DIFFICULTY_MAX: u32 = 64;
+
+/// Leading zero bits worth one block of hashing at the φ tick rate.
+pub fn phi_difficulty(spec: &MonetarySpec) -> u32 {
+    tick::ticks_per_block(spec).max(1).ilog2()
+}
+
+/// A job as opened for a `MINE_JOB` frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiningJob {
+    pub id: u64,
+    /// Ledger key the reward goes to.
+    pub label: String,
+    pub planet: PlanetId,
+    pub challenge: String,
+    pub difficulty: u32,
+    pub expires_height: u64,
+}
+
+impl MiningJob {
+    fn solved_by(&self, nonce: u64) -> bool {
+        let input = format!("{};{nonce}", self.challenge);
+        shaless_leading_zeros(input.as_bytes()) >= self.difficulty
+    }
+}
+
+/// The `mining` slice of `/omega/status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MiningStatus {
+    pub difficulty: u32,
+    /// Open jobs.
+    pub queue_depth: usize,
+    /// Hashes a second the results accepted over the last minute prove.
+    pub hash_rate: f64,
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+#[derive(Debug)]
+pub struct JobQueue {
+    difficulty: u32,
+    next_id: u64,
+    /// By id, so the oldest job comes first.
+    open: BTreeMap<u64, MiningJob>,
+    /// When each recent result was accepted and the hashes it proves.
+    proofs: VecDeque<(i64, f64)>,
+    accepted: u64,
+    rejected: u64,
+}
+
+impl JobQueue {
+    pub fn new(difficulty: u32) -> Self {
+        Self {
+            difficulty: difficulty.min(DIFFICULTY_MAX),
+            next_id: 1,
+            open: BTreeMap::new(),
+            proofs: VecDeque::new(),
+            accepted: 0,
+            rejected: 0,
+        }
+    }
+
+    /// Open a job paying `label` on `planet`, issued at `height`.
+    pub fn open(
+        &mut self,
+        label: String,
+        planet: PlanetId,
+        challenge: String,
+        height: u64,
+    ) -> MiningJob {
+        self.expire(height);
+        while self.open.len() >= OPEN_JOBS_MAX {
+            self.open.pop_first();
+        }
+        let job = MiningJob {
+            id: self.next_id,
+            label,
+            planet,
+            challenge,
+            difficulty: self.difficulty,
+            expires_height: height + JOB_TTL_BLOCKS,
+        };
+        self.next_id += 1;
+        self.open.insert(job.id, job.clone());
+        job
+    }
+
+    /// Close job `id` if `nonce` solves it, giving back the job to pay.
+    pub fn submit(
+        &mut self,
+        id: u64,
+        nonce: u64,
+        height: u64,
+        now_ms: i64,
+    ) -> Result<MiningJob, FrameError> {
+        self.expire(height);
+        let Some(job) = self.open.get(&id) else {
+            self.rejected += 1;
+            return Err(FrameError::NotFound(format!("open mining job {id}")));
+        };
+        if !job.solved_by(nonce) {
+            self.rejected += 1;
+            return Err(FrameError::Conflict(format!(
+                "nonce {nonce} misses difficulty {}",
+                job.difficulty
+            )));
+        }
+        let job = self.open.remove(&id).expect("job checked above");
+        self.accepted += 1;
+        self.proofs
+            .push_back((now_ms, 2f64.powi(job.difficulty as i32)));
+        Ok(job)
+    }
+
+    pub fn status(&mut self, now_ms: i64) -> MiningStatus {
+        while self
+            .proofs
+            .front()
+            .is_some_and(|(at, _)| now_ms - at >= RATE_WINDOW_MS)
+        {
+            self.proofs.pop_front();
+        }
+        let hashes: f64 = self.proofs.iter().map(|(_, hashes)| hashes).sum();
+        MiningStatus {
+            difficulty: self.difficulty,
+            queue_depth: self.open.len(),
+            hash_rate: hashes * 1000.0 / RATE_WINDOW_MS as f64,
+            accepted: self.accepted,
+            rejected: self.rejected,
+        }
+    }
+
+    fn expire(&mut self, height: u64) {
+        self.open.retain(|_, job| job.expires_height > height);
+    }
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new(phi_difficulty(&MonetarySpec::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(job: &MiningJob) -> u64 {
+        (0..).find(|nonce| job.solved_by(*nonce)).unwrap()
+    }
+
+    #[test]
+    fn jobs_close_on_a_solving_nonce_and_expire() {
+        assert_eq!(phi_difficulty(&MonetarySpec::default()), 16);
+
+        let mut queue = JobQueue::new(6);
+        let job = queue.open(";1;a;".into(), PlanetId::Earth, "c1".into(), 10);
+        assert_eq!(
+            (job.difficulty, job.expires_height),
+            (6, 10 + JOB_TTL_BLOCKS)
+        );
+        let nonce = solve(&job);
+        let miss = (0..).find(|n| !job.solved_by(*n)).unwrap();
+        assert!(matches!(
+            queue.submit(job.id, miss, 11, 0),
+            Err(FrameError::Conflict(_))
+        ));
+        assert_eq!(queue.submit(job.id, nonce, 11, 0), Ok(job.clone()));
+        // Closed jobs pay once.
+        assert!(matches!(
+            queue.submit(job.id, nonce, 11, 0),
+            Err(FrameError::NotFound(_))
+        ));
+
+        let status = queue.status(1_000);
+        assert_eq!(
+            (status.accepted, status.rejected, status.queue_depth),
+            (1, 2, 0)
+        );
+        assert_eq!(status.hash_rate, 64.0 / 60.0);
+        assert_eq!(queue.status(RATE_WINDOW_MS).hash_rate, 0.0);
+
+        let stale = queue.open(";1;a;".into(), PlanetId::Earth, "c2".into(), 10);
+        let nonce = solve(&stale);
+        assert!(queue
+            .submit(stale.id, nonce, 10 + JOB_TTL_BLOCKS, 0)
+            .is_err());
+
+        for n in 0..OPEN_JOBS_MAX + 1 {
+            queue.open(";1;a;".into(), PlanetId::Earth, format!("w{n}"), 40);
+        }
+        assert_eq!(queue.status(0).queue_depth, OPEN_JOBS_MAX);
+    }
+}
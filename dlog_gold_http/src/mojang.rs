@@ -0,0 +1,330 @@
+//! Mojang profile checks for `/identity/mojang`.
+//!
+//! A login assertion claims a `mojang_uuid` and a `gamer_tag`. Before it is
+//! forwarded to presence, the uuid's profile is fetched from the Mojang
+//! session server (`OMEGA_MOJANG_SESSION_URL`, default
+//! `https://sessionserver.mojang.com`) and its current name must match the
+//! tag, ignoring case. Profiles are cached for [`PROFILE_TTL_MS`] (unknown
+//! uuids for [`MISSING_TTL_MS`]) and uncached lookups are capped at
+//! `OMEGA_MOJANG_LOOKUPS_PER_MINUTE` (default 120) so a burst of logins
+//! can't get the gateway throttled by Mojang. `OMEGA_MOJANG_VERIFY=off`
+//! forwards unchecked, for local servers in offline mode.
+
+use dlog_error::FrameError;
+use dlog_resilience::{BreakerConfig, BreakerSnapshot, RetryPolicy, Upstream};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const DEFAULT_SESSION_URL: &str = "https://sessionserver.mojang.com";
+const DEFAULT_LOOKUPS_PER_MINUTE: u32 = 120;
+const WINDOW_MS: i64 = 60_000;
+/// How long a fetched profile is trusted; names can change, but rarely.
+pub const PROFILE_TTL_MS: i64 = 10 * 60_000;
+/// How long a uuid Mojang doesn't know stays unknown.
+pub const MISSING_TTL_MS: i64 = 60_000;
+const CACHE_MAX: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    /// The uuid's current name is the claimed tag.
+    Verified,
+    /// The uuid belongs to someone named differently.
+    Mismatch,
+    /// Mojang has no profile for the uuid.
+    Unknown,
+    /// The lookup budget is spent; retry after the window.
+    RateLimited,
+    /// Mojang couldn't be reached.
+    Unavailable,
+    /// Verification is turned off.
+    Skipped,
+}
+
+impl VerificationStatus {
+    /// Whether the assertion may go on to presence.
+    pub fn passes(self) -> bool {
+        matches!(self, Self::Verified | Self::Skipped)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MojangVerification {
+    pub status: VerificationStatus,
+    /// The uuid without dashes, lowercase.
+    pub mojang_uuid: String,
+    pub claimed_tag: String,
+    /// The profile's current name, when Mojang answered with one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_name: Option<String>,
+    /// Answered from the cache rather than a fresh lookup.
+    pub cached: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionProfile {
+    name: String,
+}
+
+#[derive(Debug, Clone)]
+struct CachedProfile {
+    /// `None` when Mojang has no such uuid.
+    name: Option<String>,
+    expires_ms: i64,
+}
+
+#[derive(Debug, Default)]
+struct Budget {
+    window: i64,
+    used: u32,
+}
+
+#[derive(Debug)]
+pub struct MojangVerifier {
+    client: Client,
+    /// `None` when verification is off.
+    session_url: Option<String>,
+    per_minute: u32,
+    upstream: Upstream,
+    cache: Mutex<HashMap<String, CachedProfile>>,
+    budget: Mutex<Budget>,
+}
+
+/// `mojang_uuid` with or without dashes, as 32 lowercase hex digits.
+pub fn normalize_uuid(raw: &str) -> Result<String, FrameError> {
+    let uuid: String = raw
+        .trim()
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if uuid.len() != 32 || !uuid.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(FrameError::Malformed(format!(
+            "mojang_uuid {raw:?} is not a uuid"
+        )));
+    }
+    Ok(uuid)
+}
+
+impl MojangVerifier {
+    pub fn new(session_url: Option<String>, per_minute: u32) -> Self {
+        Self {
+            client: Client::new(),
+            session_url: session_url.map(|url| url.trim_end_matches('/').to_string()),
+            per_minute,
+            upstream: Upstream::new("mojang", RetryPolicy::default(), BreakerConfig::default()),
+            cache: Mutex::new(HashMap::new()),
+            budget: Mutex::new(Budget::default()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let off = std::env::var("OMEGA_MOJANG_VERIFY")
+            .is_ok_and(|value| value.trim().eq_ignore_ascii_case("off"));
+        let session_url = std::env::var("OMEGA_MOJANG_SESSION_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_SESSION_URL.to_string());
+        let per_minute = std::env::var("OMEGA_MOJANG_LOOKUPS_PER_MINUTE")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_LOOKUPS_PER_MINUTE);
+        Self::new((!off).then_some(session_url), per_minute)
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        self.upstream.snapshot()
+    }
+
+    /// Check that `mojang_uuid` is currently named `gamer_tag`.
+    pub async fn verify(
+        &self,
+        mojang_uuid: &str,
+        gamer_tag: &str,
+        now_ms: i64,
+    ) -> Result<MojangVerification, FrameError> {
+        let uuid = normalize_uuid(mojang_uuid)?;
+        let result = |status, profile_name, cached| MojangVerification {
+            status,
+            mojang_uuid: uuid.clone(),
+            claimed_tag: gamer_tag.to_string(),
+            profile_name,
+            cached,
+        };
+        let Some(session_url) = &self.session_url else {
+            return Ok(result(VerificationStatus::Skipped, None, false));
+        };
+
+        let (name, cached) = match self.cached(&uuid, now_ms) {
+            Some(name) => (name, true),
+            None => {
+                if !self.spend(now_ms) {
+                    return Ok(result(VerificationStatus::RateLimited, None, false));
+                }
+                match self.lookup(session_url, &uuid).await {
+                    Ok(name) => {
+                        self.remember(&uuid, name.clone(), now_ms);
+                        (name, false)
+                    }
+                    Err(err) => {
+                        tracing::warn!("[mojang] profile lookup for {uuid} failed: {err}");
+                        return Ok(result(VerificationStatus::Unavailable, None, false));
+                    }
+                }
+            }
+        };
+        let status = match &name {
+            None => VerificationStatus::Unknown,
+            Some(name) if name.eq_ignore_ascii_case(gamer_tag.trim()) => {
+                VerificationStatus::Verified
+            }
+            Some(_) => VerificationStatus::Mismatch,
+        };
+        Ok(result(status, name, cached))
+    }
+
+    fn cached(&self, uuid: &str, now_ms: i64) -> Option<Option<String>> {
+        let cache = self.cache.lock().expect("mojang cache mutex poisoned");
+        cache
+            .get(uuid)
+            .filter(|entry| entry.expires_ms > now_ms)
+            .map(|entry| entry.name.clone())
+    }
+
+    fn remember(&self, uuid: &str, name: Option<String>, now_ms: i64) {
+        let ttl = if name.is_some() {
+            PROFILE_TTL_MS
+        } else {
+            MISSING_TTL_MS
+        };
+        let mut cache = self.cache.lock().expect("mojang cache mutex poisoned");
+        if cache.len() >= CACHE_MAX {
+            cache.retain(|_, entry| entry.expires_ms > now_ms);
+            if cache.len() >= CACHE_MAX {
+                cache.clear();
+            }
+        }
+        cache.insert(
+            uuid.to_string(),
+            CachedProfile {
+                name,
+                expires_ms: now_ms + ttl,
+            },
+        );
+    }
+
+    /// Take one lookup from this minute's budget.
+    fn spend(&self, now_ms: i64) -> bool {
+        let window = now_ms.div_euclid(WINDOW_MS);
+        let mut budget = self.budget.lock().expect("mojang budget mutex poisoned");
+        if budget.window != window {
+            *budget = Budget { window, used: 0 };
+        }
+        if budget.used >= self.per_minute {
+            return false;
+        }
+        budget.used += 1;
+        true
+    }
+
+    /// The uuid's current name, or `None` if Mojang has no such profile
+    /// (it answers 204 or 404).
+    async fn lookup(&self, session_url: &str, uuid: &str) -> Result<Option<String>, String> {
+        let url = format!("{session_url}/session/minecraft/profile/{uuid}");
+        self.upstream
+            .call_if(
+                || async {
+                    let resp = self.client.get(&url).send().await?;
+                    if matches!(
+                        resp.status(),
+                        StatusCode::NO_CONTENT | StatusCode::NOT_FOUND
+                    ) {
+                        return Ok(None);
+                    }
+                    let profile: SessionProfile = resp.error_for_status()?.json().await?;
+                    Ok(Some(profile.name))
+                },
+                |err: &reqwest::Error| {
+                    err.is_connect()
+                        || err.is_timeout()
+                        || err.status().is_some_and(|s| s.is_server_error())
+                },
+            )
+            .await
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// A session server that knows one profile and counts its lookups.
+    async fn session_server(lookups: Arc<AtomicU32>) -> String {
+        let app = Router::new().route(
+            "/session/minecraft/profile/:uuid",
+            get(move |Path(uuid): Path<String>| {
+                lookups.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    if uuid == "069a79f444e94726a5befca90e38aaf5" {
+                        Ok(Json(serde_json::json!({ "id": uuid, "name": "Notch" })))
+                    } else {
+                        Err(axum::http::StatusCode::NO_CONTENT)
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn claims_are_checked_against_the_profile_cached_and_rate_limited() {
+        let lookups = Arc::new(AtomicU32::new(0));
+        let verifier = MojangVerifier::new(Some(session_server(lookups.clone()).await), 2);
+        let notch = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+
+        let verified = verifier.verify(notch, "notch", 0).await.unwrap();
+        assert_eq!(verified.status, VerificationStatus::Verified);
+        assert_eq!(verified.profile_name.as_deref(), Some("Notch"));
+        assert!(!verified.cached);
+        // The second claim on the uuid is answered from the cache.
+        let forged = verifier.verify(notch, "jeb_", 1_000).await.unwrap();
+        assert_eq!(
+            (forged.status, forged.cached),
+            (VerificationStatus::Mismatch, true)
+        );
+        assert!(!forged.status.passes());
+
+        let stranger = "00000000000000000000000000000001";
+        let unknown = verifier.verify(stranger, "x", 2_000).await.unwrap();
+        assert_eq!(unknown.status, VerificationStatus::Unknown);
+        let other = "00000000000000000000000000000002";
+        let limited = verifier.verify(other, "x", 3_000).await.unwrap();
+        assert_eq!(limited.status, VerificationStatus::RateLimited);
+        assert_eq!(lookups.load(Ordering::Relaxed), 2);
+        // A new minute brings a new budget; an expired profile is fetched again.
+        let later = verifier
+            .verify(notch, "Notch", PROFILE_TTL_MS)
+            .await
+            .unwrap();
+        assert_eq!(
+            (later.status, later.cached),
+            (VerificationStatus::Verified, false)
+        );
+
+        assert!(verifier.verify("not-a-uuid", "x", 0).await.is_err());
+        let off = MojangVerifier::new(None, 0);
+        let skipped = off.verify(notch, "anyone", 0).await.unwrap();
+        assert!(skipped.status.passes());
+    }
+}
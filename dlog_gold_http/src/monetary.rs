@@ -0,0 +1,141 @@
+//! The economy calendar: scheduled changes to a zone's rates.
+//!
+//! An admin schedules a [`MonetaryChange`] for a future height with a
+//! reason and the label keys that approved it (`POST
+//! /admin/monetary/schedule`). The bank pays every period from that height
+//! on at the new rates; nothing is applied by hand. The whole schedule,
+//! paid or not, lives in `;∞;bank;monetary;`, travels with the ledgers to
+//! read replicas, and is public at `GET /v1/spec/monetary/history`.
+
+use corelib::label_file::LabelFile;
+use dlog_error::BankError;
+use serde::{Deserialize, Serialize};
+use spec::{CompoundingMode, MonetaryChange, MonetarySpec, PlanetId};
+
+pub const MONETARY_PATH: &str = ";∞;bank;monetary;";
+const APPROVERS_MAX: usize = 16;
+
+/// Body of `POST /admin/monetary/schedule`. Rates left out keep the ones in
+/// effect at `effective_height`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MonetaryChangeRequest {
+    pub effective_height: u64,
+    pub planet: PlanetId,
+    #[serde(default)]
+    pub holder_interest_apy: Option<f64>,
+    #[serde(default)]
+    pub miner_inflation_apy: Option<f64>,
+    pub reason: String,
+    pub approvers: Vec<String>,
+}
+
+impl MonetaryChangeRequest {
+    /// The change this asks for, over `current` (the rates it replaces).
+    pub fn into_change(
+        self,
+        current: MonetarySpec,
+        scheduled_ms: i64,
+    ) -> Result<MonetaryChange, BankError> {
+        Ok(MonetaryChange {
+            effective_height: self.effective_height,
+            planet: self.planet,
+            monetary: MonetarySpec {
+                holder_interest_apy: self
+                    .holder_interest_apy
+                    .unwrap_or(current.holder_interest_apy),
+                miner_inflation_apy: self
+                    .miner_inflation_apy
+                    .unwrap_or(current.miner_inflation_apy),
+                ..current
+            },
+            reason: self.reason.trim().to_string(),
+            approvers: canonical_approvers(&self.approvers)?,
+            scheduled_ms,
+        })
+    }
+}
+
+/// Approvers as canon label keys, each once and in the order given.
+fn canonical_approvers(approvers: &[String]) -> Result<Vec<String>, BankError> {
+    if approvers.len() > APPROVERS_MAX {
+        return Err(BankError::InvalidSchedule(format!(
+            "at most {APPROVERS_MAX} approvers"
+        )));
+    }
+    let mut keys: Vec<String> = Vec::new();
+    for approver in approvers {
+        let key = LabelFile::for_label_key(approver)
+            .ok_or_else(|| {
+                BankError::InvalidSchedule(format!(
+                    "approver `{approver}` is not a label key (;phone;label;)"
+                ))
+            })?
+            .key();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+/// What `GET /v1/spec/monetary/history` shows.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonetaryHistory {
+    pub height: u64,
+    pub compounding: CompoundingMode,
+    /// Each zone's rates for the next block.
+    pub zones: Vec<ZoneMonetary>,
+    /// Every change, oldest first.
+    pub changes: Vec<ScheduledChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneMonetary {
+    pub planet: PlanetId,
+    pub unit: &'static str,
+    pub monetary: MonetarySpec,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledChange {
+    #[serde(flatten)]
+    pub change: MonetaryChange,
+    /// Whether the chain has reached `effective_height`.
+    pub applied: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(approvers: &[&str]) -> MonetaryChangeRequest {
+        MonetaryChangeRequest {
+            effective_height: 10,
+            planet: PlanetId::Mars,
+            holder_interest_apy: Some(0.5),
+            miner_inflation_apy: None,
+            reason: " ramp Mars up ".into(),
+            approvers: approvers.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn requests_fill_in_current_rates_and_canonical_approvers() {
+        let current = PlanetId::Mars.zone().monetary;
+        let change = request(&["9132077554;comet", ";9132077554;comet;", ";5550001111;fun;"])
+            .into_change(current.clone(), 7)
+            .unwrap();
+        assert_eq!(change.monetary.holder_interest_apy, 0.5);
+        assert_eq!(
+            change.monetary.miner_inflation_apy,
+            current.miner_inflation_apy
+        );
+        assert_eq!(change.reason, "ramp Mars up");
+        assert_eq!(change.approvers, [";9132077554;comet;", ";5550001111;fun;"]);
+
+        assert!(matches!(
+            request(&["comet"]).into_change(current, 7),
+            Err(BankError::InvalidSchedule(_))
+        ));
+    }
+}
@@ -0,0 +1,214 @@
+//! Per-label notification inboxes.
+//!
+//! The public event bus never names labels, so players who were offline miss
+//! what happened to theirs. Events published with recipients
+//! ([`crate::events::EventBus::publish_to`]) also land in each recipient
+//! label's inbox, persisted as `;∞;notifications;` so they survive restarts.
+//! Each inbox keeps its newest [`INBOX_LEN`] entries; owners list them, mark
+//! them read, and purge them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use spec::{UiBadge, UiOverlay};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::store::GatewayStore;
+
+const NOTIFICATIONS_PATH: &str = ";∞;notifications;";
+/// Notifications kept per label; the oldest go first.
+pub const INBOX_LEN: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: u64,
+    /// Ledger key of the label it was delivered to.
+    pub label: String,
+    pub kind: String,
+    pub height: u64,
+    pub at_ms: i64,
+    #[serde(default)]
+    pub payload: Value,
+    #[serde(default)]
+    pub read: bool,
+}
+
+/// One owner's view of their inboxes, with the HUD badge to show for them.
+#[derive(Debug, Clone, Serialize)]
+pub struct InboxView {
+    pub unread: usize,
+    /// Newest first.
+    pub notifications: Vec<Notification>,
+    pub ui: UiOverlay,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Inboxes {
+    next_id: u64,
+    by_label: BTreeMap<String, VecDeque<Notification>>,
+}
+
+#[derive(Debug, Default)]
+pub struct NotificationInbox {
+    store: GatewayStore,
+    inboxes: Mutex<Inboxes>,
+}
+
+impl NotificationInbox {
+    pub fn load(store: GatewayStore) -> Self {
+        let inboxes = store.load_json(NOTIFICATIONS_PATH).unwrap_or_default();
+        Self {
+            store,
+            inboxes: Mutex::new(inboxes),
+        }
+    }
+
+    fn persist(&self, inboxes: &Inboxes) {
+        if let Err(err) = self.store.save_json(NOTIFICATIONS_PATH, inboxes) {
+            warn!("[notifications] failed to persist {NOTIFICATIONS_PATH}: {err}");
+        }
+    }
+
+    pub fn deliver(&self, label: &str, kind: &str, height: u64, at_ms: i64, payload: Value) {
+        let mut inboxes = self.inboxes.lock().expect("inbox mutex poisoned");
+        inboxes.next_id += 1;
+        let notification = Notification {
+            id: inboxes.next_id,
+            label: label.to_string(),
+            kind: kind.to_string(),
+            height,
+            at_ms,
+            payload,
+            read: false,
+        };
+        let inbox = inboxes.by_label.entry(label.to_string()).or_default();
+        if inbox.len() >= INBOX_LEN {
+            inbox.pop_front();
+        }
+        inbox.push_back(notification);
+        self.persist(&inboxes);
+    }
+
+    /// Notifications of the labels `owns` accepts, newest first.
+    pub fn view(&self, owns: impl Fn(&str) -> bool) -> InboxView {
+        let inboxes = self.inboxes.lock().expect("inbox mutex poisoned");
+        let mut notifications: Vec<Notification> = inboxes
+            .by_label
+            .iter()
+            .filter(|(label, _)| owns(label))
+            .flat_map(|(_, inbox)| inbox.iter().cloned())
+            .collect();
+        notifications.sort_by_key(|n| std::cmp::Reverse(n.id));
+        let unread = notifications.iter().filter(|n| !n.read).count();
+        InboxView {
+            unread,
+            notifications,
+            ui: badge_overlay(unread),
+        }
+    }
+
+    /// Notifications of the labels `owns` accepts with an id past `after`,
+    /// oldest first.
+    pub fn after(&self, owns: impl Fn(&str) -> bool, after: u64) -> Vec<Notification> {
+        let inboxes = self.inboxes.lock().expect("inbox mutex poisoned");
+        let mut notifications: Vec<Notification> = inboxes
+            .by_label
+            .iter()
+            .filter(|(label, _)| owns(label))
+            .flat_map(|(_, inbox)| inbox.iter().filter(|n| n.id > after).cloned())
+            .collect();
+        notifications.sort_by_key(|n| n.id);
+        notifications
+    }
+
+    /// Id of the newest notification delivered to any label.
+    pub fn last_id(&self) -> u64 {
+        self.inboxes.lock().expect("inbox mutex poisoned").next_id
+    }
+
+    /// Mark the given ids read, or every notification when `ids` is empty.
+    /// Returns how many changed.
+    pub fn mark_read(&self, owns: impl Fn(&str) -> bool, ids: &[u64]) -> usize {
+        let mut inboxes = self.inboxes.lock().expect("inbox mutex poisoned");
+        let mut changed = 0;
+        for notification in inboxes
+            .by_label
+            .iter_mut()
+            .filter(|(label, _)| owns(label))
+            .flat_map(|(_, inbox)| inbox.iter_mut())
+            .filter(|n| !n.read && (ids.is_empty() || ids.contains(&n.id)))
+        {
+            notification.read = true;
+            changed += 1;
+        }
+        if changed > 0 {
+            self.persist(&inboxes);
+        }
+        changed
+    }
+
+    /// Drop read notifications, or all of them with `all`. Returns how many went.
+    pub fn purge(&self, owns: impl Fn(&str) -> bool, all: bool) -> usize {
+        let mut inboxes = self.inboxes.lock().expect("inbox mutex poisoned");
+        let mut purged = 0;
+        for (_, inbox) in inboxes.by_label.iter_mut().filter(|(label, _)| owns(label)) {
+            let before = inbox.len();
+            inbox.retain(|n| !all && !n.read);
+            purged += before - inbox.len();
+        }
+        inboxes.by_label.retain(|_, inbox| !inbox.is_empty());
+        if purged > 0 {
+            self.persist(&inboxes);
+        }
+        purged
+    }
+}
+
+/// HUD hint for `unread` notifications: one `inbox` badge, none at zero.
+fn badge_overlay(unread: usize) -> UiOverlay {
+    UiOverlay {
+        badges: (unread > 0)
+            .then(|| UiBadge {
+                kind: "inbox".into(),
+                count: unread as u32,
+            })
+            .into_iter()
+            .collect(),
+        ..UiOverlay::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inboxes_persist_cap_and_clear_per_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = NotificationInbox::load(GatewayStore::at(dir.path()));
+        let mine = |label: &str| label.starts_with(";9132077554;");
+        for i in 0..INBOX_LEN as u64 + 2 {
+            inbox.deliver(";9132077554;fun;", "transfer", i, 0, Value::Null);
+        }
+        inbox.deliver(";9132077554;comet;", "escrow_expiring", 9, 0, Value::Null);
+        inbox.deliver(";5550001111;main;", "escrow_opened", 9, 0, Value::Null);
+
+        let view = NotificationInbox::load(GatewayStore::at(dir.path())).view(mine);
+        assert_eq!(view.unread, INBOX_LEN + 1);
+        assert_eq!(view.notifications[0].kind, "escrow_expiring");
+        assert_eq!(view.notifications.last().unwrap().height, 2);
+        assert_eq!(view.ui.badges[0].count as usize, INBOX_LEN + 1);
+
+        let newest = view.notifications[0].id;
+        assert_eq!(inbox.mark_read(mine, &[newest]), 1);
+        assert_eq!(inbox.mark_read(mine, &[newest]), 0);
+        assert_eq!(inbox.purge(mine, false), 1);
+        assert_eq!(inbox.mark_read(mine, &[]), INBOX_LEN);
+        assert_eq!(inbox.purge(mine, true), INBOX_LEN);
+        let emptied = inbox.view(mine);
+        assert_eq!(emptied.unread, 0);
+        assert!(emptied.ui.badges.is_empty());
+        assert_eq!(inbox.view(|label| !mine(label)).unread, 1);
+    }
+}
@@ -1,19 +1,99 @@
+use corelib::consensus::{
+    fork_choice, BlockHeader, BlockSigner, Consensus, ConsensusError, ConsensusMessage,
+    KeyedSigner, SingleProducer, GENESIS_ROOT,
+};
+use corelib::exchange;
+use corelib::invariants::InvariantReport;
+use corelib::label_file::LabelFile;
+use corelib::privacy::{redact_phones, subject_digest, PhonePrivacy};
+use corelib::{master_root_for, shaless_json_digest, HeightScheduler, ScheduledTask};
+use dlog_error::{AuthError, BankError, DlogError, FrameError, StorageError};
+use dlog_sky::{sky_event_at, upcoming_sky_events, SkyEvent, SkyTimeline, SKY_EVENT_LOOKAHEAD};
+use omega_bank::{Bank, Ledgers};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use spec::{Capability, CompoundingMode, MonetaryChange, MonetarySpec, PlanetId, PHI_TICK_HZ};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
 use uuid::Uuid;
 
-const PHI_F32: f32 = 1.618_034_f32;
-const INPUT_VELOCITY_SCALE: f32 = 0.08;
-const INPUT_ASCENT_SCALE: f32 = 0.16;
-const DEFAULT_WORLD_MAX_Y: f32 = 320.0;
+use crate::alias::{AliasRecord, AliasRegistry, AliasView};
+use crate::audit::{AuditLog, FrameTrace, SessionTimeline};
+use crate::balance_export::BalanceExporter;
+use crate::bootstrap::{
+    world_changes, BootstrapChange, BootstrapPlan, BootstrapReport, BOOTSTRAP_LABEL,
+};
+use crate::bridge_queue::BridgeQueue;
+use crate::burn::{BurnBook, BurnHooks, BurnReceipt, BurnStatus, BURN_LABEL};
+use crate::cadence::{RttEstimate, TickCadence};
+use crate::calibration::{CalibrationBook, CalibrationUpdate, DeviceCalibration};
+use crate::escrow::{
+    Escrow, EscrowBook, EscrowLeg, EscrowRequest, ItemAssignment, ItemRegistry, ESCROW_LABEL,
+};
+use crate::events::EventBus;
+use crate::faucet::{Faucet, FaucetConfig, FaucetGrant, FaucetStatus, FAUCET_LABEL};
+use crate::frame_signing::{self, SignaturePolicy};
+use crate::guild::{
+    canonical_guild_name, Guild, GuildBook, GuildCharter, GuildRole, GuildSpend, GuildView,
+    SpendRequest,
+};
+use crate::labels::{canonical_label_name, LabelBook, LabelRecord, LabelStatus, LabelView};
+use crate::manifest::{
+    WorldManifest, WorldSettings, INPUT_ASCENT_SCALE, INPUT_VELOCITY_SCALE, WORLD_SETTINGS_PATH,
+};
+use crate::metrics::MetricsRecorder;
+use crate::mining::{JobQueue, MiningJob, MiningStatus, MINING_LABEL, REWARD};
+use crate::monetary::{
+    MonetaryChangeRequest, MonetaryHistory, ScheduledChange, ZoneMonetary, MONETARY_PATH,
+};
+use crate::notifications::{InboxView, Notification, NotificationInbox};
+use crate::overview::{
+    top_movers, BankOverview, BlockOverview, FrameMeter, LatencySummary, LatencyWindow, Overview,
+    SessionCounts,
+};
+use crate::push::{Device, DeviceBook, DeviceRegistration, PushAdapter, PushDispatcher};
+use crate::quota::{FrameQuotas, QuotaStatus};
+use crate::recording::{InputDump, InputRecorder, RecordingStatus};
+use crate::recovery::{GuardianRequest, GuardianSet, Recovery, RecoveryBook, RecoverySnapshot};
+use crate::replica::{RelayedFrame, ReplicaState};
+use crate::sky_assets::SkyAssets;
+use crate::spending::{SpendingCache, SpendingSummary};
+use crate::store::GatewayStore;
+use crate::tick::{self, TickAuthority};
+use crate::transfer::{
+    BatchEntry, TransferLog, TransferMeta, TransferRecord, TransferSearch, BATCH_MAX,
+};
+
+pub const PHI_F32: f32 = 1.618_034_f32;
+pub const BLOCK_CURSOR_PATH: &str = ";∞;blocks;cursor;";
+pub const BLOCK_WINDOW_PATH: &str = ";∞;blocks;recent;";
+/// Recent headers kept to answer peers' missing-block requests.
+pub const BLOCK_WINDOW: usize = 1024;
+pub const SCHEDULER_PATH: &str = ";∞;scheduler;tasks;";
+const ALIAS_PATH: &str = ";∞;aliases;registry;";
+pub const LABELS_PATH: &str = ";∞;labels;registry;";
+const DNS_RECORDS_PATH: &str = ";∞;dns;records;";
+const TRANSFERS_PATH: &str = ";∞;bank;transfers;";
+const ESCROWS_PATH: &str = ";∞;bank;escrows;";
+/// Blocks before an open escrow's deadline that its parties are warned.
+pub const ESCROW_EXPIRY_NOTICE: u64 = 10;
+const ITEMS_PATH: &str = ";∞;bank;items;";
+const GUILDS_PATH: &str = ";∞;bank;guilds;";
+const RECOVERIES_PATH: &str = ";∞;bank;recoveries;";
+/// Balances, saved on every seal and escrow transition so escrowed funds
+/// and the book restart together.
+pub const LEDGER_PATH: &str = ";∞;bank;ledger;";
+pub const IDENTITY_PATH: &str = ";∞;gateway;identity;";
 
 /// Incoming handshake payload from an HTTP-4 client.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HandshakeRequest {
     pub client_id: String,
+    /// Capability keys (`render`, `banking`, ...); empty asks for the defaults.
     #[serde(default)]
     pub capabilities: Vec<String>,
     #[serde(default)]
@@ -22,6 +102,44 @@ pub struct HandshakeRequest {
     pub phone: Option<String>,
     #[serde(default)]
     pub session_token: Option<String>,
+    /// Planet this session is bound to (defaults to Earth).
+    #[serde(default)]
+    pub planet: PlanetId,
+    /// Gateway peers prove the shared peer key here instead of a phone
+    /// (see [`OmegaGateway::peer_handshake`]).
+    #[serde(default)]
+    pub peer_mac: Option<String>,
+    #[serde(default)]
+    pub session_kind: SessionKind,
+}
+
+/// What a session is for, chosen at handshake.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionKind {
+    #[default]
+    Player,
+    /// Watches without touching the world: only [`SPECTATOR_CAPABILITIES`],
+    /// frames that move the world are refused, and live spectators are
+    /// capped separately from players.
+    Spectator,
+}
+
+/// All a spectator session may hold, and what it gets by default.
+pub const SPECTATOR_CAPABILITIES: [Capability; 3] =
+    [Capability::Render, Capability::Audio, Capability::Dns];
+/// Live spectator sessions allowed unless `OMEGA_SPECTATOR_SLOTS` says otherwise.
+pub const DEFAULT_SPECTATOR_SLOTS: usize = 256;
+
+/// Who is opening a session, as established by the caller (the HTTP
+/// handshake or the fixture runner). Capabilities come from this, not from
+/// what the request asks for.
+#[derive(Debug, Clone, Default)]
+pub struct SessionGrant {
+    /// Verified phone: unlocks `banking` and `mining` and owns its labels.
+    pub phone: Option<String>,
+    /// The handshake carried a valid admin token.
+    pub admin: bool,
 }
 
 /// Response issued once a session is registered.
@@ -33,6 +151,21 @@ pub struct HandshakeResponse {
     pub router_epoch_ms: i64,
     pub granted_routes: Vec<RouteHint>,
     pub identity: Option<IdentityDescriptor>,
+    pub planet: PlanetId,
+    pub capabilities: Vec<Capability>,
+    /// Frame kinds this gateway handles; others are refused with an
+    /// `unsupported kind` note, so clients can check before sending.
+    pub frame_kinds: Vec<FrameKind>,
+    pub session_kind: SessionKind,
+    pub gateway_id: String,
+    /// Boots of this gateway id so far; a change means the gateway restarted.
+    pub incarnation: u64,
+    /// Set while handshakes are being deferred: how long to wait before
+    /// re-handshaking should this session drop (see `admission`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// This session's key for signing bank frames (see `frame_signing`).
+    pub frame_key: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -56,6 +189,72 @@ pub enum FrameKind {
     Audio,
     Game,
     Input,
+    /// Consensus traffic between gateway nodes ([`ConsensusMessage`] payload).
+    Block,
+    /// A read replica's bank frame, applied by the leader ([`RelayedFrame`] payload).
+    Relay,
+    /// A kind this build doesn't know (a newer edge mid-rollout). Decodes
+    /// instead of failing the whole frame, and is refused when handled.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl FrameKind {
+    /// Every kind the gateway handles, as listed in handshake responses.
+    pub const SUPPORTED: [FrameKind; 11] = [
+        FrameKind::TickFrame,
+        FrameKind::Query,
+        FrameKind::Event,
+        FrameKind::MineJob,
+        FrameKind::MineResult,
+        FrameKind::Dns,
+        FrameKind::Audio,
+        FrameKind::Game,
+        FrameKind::Input,
+        FrameKind::Block,
+        FrameKind::Relay,
+    ];
+
+    /// Name for logs and meters; unknown kinds share one, since the client picks them.
+    pub fn name(&self) -> String {
+        match self {
+            FrameKind::Unknown(_) => "Unknown".into(),
+            kind => format!("{kind:?}"),
+        }
+    }
+
+    /// Kinds that change the world, which spectator sessions may not send.
+    pub fn moves_world(&self) -> bool {
+        matches!(
+            self,
+            FrameKind::TickFrame | FrameKind::Game | FrameKind::Input
+        )
+    }
+
+    /// Capability a session needs before this frame reaches its service.
+    pub fn capability(&self) -> Option<Capability> {
+        match self {
+            FrameKind::Query | FrameKind::Event => Some(Capability::Banking),
+            FrameKind::MineJob | FrameKind::MineResult => Some(Capability::Mining),
+            FrameKind::Block | FrameKind::Relay => Some(Capability::Peer),
+            _ => None,
+        }
+    }
+
+    /// Ω-path of the service this frame is dispatched to. Quotas match on
+    /// it, since the client-chosen `namespace` doesn't pick the service.
+    pub fn service_path(&self) -> &'static str {
+        match self {
+            FrameKind::Query | FrameKind::Event | FrameKind::Relay => ";∞;bank;",
+            FrameKind::MineJob | FrameKind::MineResult => ";∞;mining;",
+            FrameKind::Dns => ";∞;dns;",
+            FrameKind::Audio => ";∞;audio;",
+            FrameKind::Game | FrameKind::TickFrame => ";∞;game;",
+            FrameKind::Input => ";∞;input;",
+            FrameKind::Block => ";∞;consensus;",
+            FrameKind::Unknown(_) => ";∞;",
+        }
+    }
 }
 
 /// Envelope around a binary HTTP-4 frame. The payload itself stays opaque (`serde_json::Value`)
@@ -68,6 +267,10 @@ pub struct FrameEnvelope {
     pub kind: FrameKind,
     #[serde(default)]
     pub payload: serde_json::Value,
+    /// MAC under the session's `frame_key`; bank frames need one (see
+    /// `frame_signing`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 /// Router response with DNS hints and tick metadata.
@@ -76,18 +279,234 @@ pub struct FrameAck {
     pub session_id: String,
     pub seq: u64,
     pub accepted: bool,
+    /// Gateway φ tick when the frame was acked.
+    pub tick: u64,
+    /// When the session should send its next tick: the start of its next
+    /// `tick_interval_ms` on the gateway's frame grid.
     pub next_tick_ms: i64,
+    /// The session's tick interval, stretched past one frame when its
+    /// reported round trips are long.
+    pub tick_interval_ms: i64,
     pub routed: Vec<RouteHint>,
     pub notes: Vec<String>,
 }
 
+/// Who this gateway is across restarts, kept at [`IDENTITY_PATH`]. The id
+/// is minted on the first boot of a root; every boot after bumps
+/// `incarnation`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayIdentity {
+    pub gateway_id: String,
+    pub first_boot_ms: i64,
+    pub incarnation: u64,
+}
+
+impl GatewayIdentity {
+    /// Load the stored identity as the next incarnation, or mint one. A
+    /// store without a root mints a fresh identity every boot, and so does
+    /// one that can't be written; a corrupt identity file is an error.
+    fn boot(store: &GatewayStore) -> Result<Self, StorageError> {
+        let identity = match store.load_json_strict::<Self>(IDENTITY_PATH)? {
+            Some(previous) => Self {
+                incarnation: previous.incarnation + 1,
+                ..previous
+            },
+            None => Self {
+                gateway_id: Uuid::new_v4().to_string(),
+                first_boot_ms: now_ms(),
+                incarnation: 1,
+            },
+        };
+        if let Err(err) = store.save_json(IDENTITY_PATH, &identity) {
+            warn!("[store] failed to persist {IDENTITY_PATH}: {err}");
+        }
+        Ok(identity)
+    }
+}
+
 /// Snapshot of the gateway for observability endpoints.
 #[derive(Debug, Clone, Serialize)]
 pub struct GatewayStatus {
     pub gateway_id: String,
     pub boot_ms: i64,
+    pub first_boot_ms: i64,
+    pub incarnation: u64,
     pub session_count: usize,
+    /// Spectator sessions among `session_count`, and how many may be live.
+    pub spectators: usize,
+    pub spectator_slots: usize,
     pub services: Vec<&'static str>,
+    pub block_height: u64,
+    pub scheduled_tasks: usize,
+    pub planets: Vec<PlanetStatus>,
+    /// Last telemetry report from the speaker engine, if one has arrived.
+    pub audio: Option<AudioTelemetry>,
+    pub consensus: ConsensusStatus,
+    pub quotas: QuotaStatus,
+    pub mining: MiningStatus,
+}
+
+/// How holder interest compounds here (`GET /omega/bank/compounding`).
+#[derive(Debug, Clone, Serialize)]
+pub struct CompoundingStatus {
+    pub mode: CompoundingMode,
+    /// Blocks between interest payments.
+    pub period_blocks: u64,
+    pub zones: Vec<ZoneCompounding>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneCompounding {
+    pub planet: PlanetId,
+    pub unit: &'static str,
+    /// Growth over one block at the zone's rate, compounded per block.
+    pub per_block_factor_ppm: u64,
+    /// Growth paid every `period_blocks` under the active mode.
+    pub period_factor_ppm: u64,
+    /// Taken out of supply by burn hooks since genesis.
+    pub burned: u128,
+}
+
+/// Who this node is, which policy it runs and where its chain tip sits.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsensusStatus {
+    pub policy: &'static str,
+    pub node_id: String,
+    /// Whether this node may seal the next height.
+    pub producing: bool,
+    pub tip_root: Option<String>,
+    pub tip_producer: Option<String>,
+    /// Peers that acknowledged the current tip.
+    pub acceptances: Vec<String>,
+}
+
+/// Per-planet slice of the gateway status.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanetStatus {
+    pub planet: PlanetId,
+    /// Unit the planet's ledger counts in.
+    pub unit: &'static str,
+    pub sessions: usize,
+    pub bank_labels: usize,
+    /// sha-less digest of the planet's ledger; sims fold it into their world hash.
+    pub bank_root: String,
+    pub game_ticks: u64,
+    /// Auroras and comets starting in the next [`SKY_EVENT_LOOKAHEAD`] blocks.
+    pub sky_events: Vec<SkyEvent>,
+}
+
+/// Speaker-engine health, sent as `AUDIO` frames with `"kind": "telemetry"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioTelemetry {
+    /// Times the output device drained the buffer before the mixer refilled it.
+    pub underruns: u64,
+    /// Render time per callback-sized block, average and worst over the window.
+    pub render_us_avg: u64,
+    pub render_us_max: u64,
+    /// Audio queued ahead of the wall clock (output latency estimate).
+    pub buffered_ms: i64,
+    pub active_voices: usize,
+    pub sample_rate: u32,
+    /// Stamped by the gateway on receipt.
+    #[serde(default)]
+    pub received_ms: i64,
+}
+
+/// Block producer cursor, persisted so heights survive restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockCursor {
+    pub height: u64,
+    pub last_seal_ms: i64,
+    /// Header of the last sealed block; absent for cursors written before
+    /// blocks were chained (the next seal starts from genesis).
+    #[serde(default)]
+    pub tip: Option<BlockHeader>,
+}
+
+/// Outcome of sealing one block: the new height plus any tasks that came due.
+#[derive(Debug, Clone)]
+pub struct BlockSeal {
+    pub height: u64,
+    pub sealed_ms: i64,
+    pub header: BlockHeader,
+    pub fired: Vec<ScheduledTask>,
+}
+
+/// Result of replacing the DNS route table.
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsImport {
+    pub version: u64,
+    pub records: usize,
+}
+
+/// A live session as operators see it; plaintext phones are redacted.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionView {
+    pub session_id: String,
+    pub client_id: String,
+    pub planet: PlanetId,
+    pub phone: Option<String>,
+    pub capabilities: BTreeSet<Capability>,
+    pub kind: SessionKind,
+    pub established_ms: i64,
+    pub last_input_ms: i64,
+    /// Smoothed round trip the client reported, if any.
+    pub rtt: Option<RttEstimate>,
+    pub tick_interval_ms: i64,
+}
+
+/// A one-time code that moves a verified session to another device.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationCode {
+    pub code: String,
+    pub expires_ms: i64,
+}
+
+/// A session moved to a new device by a redeemed [`MigrationCode`].
+#[derive(Debug)]
+pub struct MigratedSession {
+    pub handshake: HandshakeResponse,
+    /// The session it replaced, now closed.
+    pub replaced_session: String,
+    /// Phone-auth token the old device minted the code with, for the caller
+    /// to revoke.
+    pub auth_token: String,
+}
+
+/// One side of an open escrow whose DLOG or item is locked.
+#[derive(Debug, Clone, Serialize)]
+pub struct EscrowLock {
+    pub escrow_id: u64,
+    pub planet: PlanetId,
+    /// Redacted ledger label of the party.
+    pub label: String,
+    pub amount: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    pub deadline_height: u64,
+}
+
+/// Proof that a phone's personal data was erased. Holds no plaintext phone:
+/// `subject_digest` is keyed from `OMEGA_PHONE_SALT`, so support holding the
+/// salt can confirm a deletion given the phone. Without a salt the key is
+/// random per boot and the digest can't be checked after a restart.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletionReceipt {
+    pub receipt_id: String,
+    pub deleted_ms: i64,
+    pub subject_digest: String,
+    pub sessions_purged: usize,
+    pub presence_purged: bool,
+    pub devices_purged: usize,
+    pub notifications_purged: usize,
+    pub guilds_left: Vec<String>,
+    pub guardian_sets_purged: usize,
+    pub calibrations_purged: usize,
+    pub aliases_purged: Vec<String>,
+    pub labels_tombstoned: Vec<String>,
+    pub tombstone_label: String,
+    pub tombstoned_balance: u128,
+    pub supply_after: u128,
 }
 
 /// A structured pointer to an Omega subsystem.
@@ -105,25 +524,30 @@ pub struct BridgeInputSnapshot {
     pub stand_id: Option<String>,
     pub device: Option<String>,
     pub profile: Option<String>,
+    /// World the player is in; its profile applies when `profile` is unset.
+    pub world: Option<String>,
+    /// Player's label (canon or `@handle`), whose calibration shapes the axes.
+    pub label: Option<String>,
     pub buttons: Vec<BridgeButtonSnapshot>,
     pub axes: Vec<BridgeAxisSnapshot>,
     pub timestamp_ms: Option<i64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BridgeButtonSnapshot {
     pub action: String,
     pub state: ButtonState,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ButtonState {
     Pressed,
     Released,
     Held,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BridgeAxisSnapshot {
     pub action: String,
     pub x: f32,
@@ -131,7 +555,8 @@ pub struct BridgeAxisSnapshot {
     pub mode: AxisMode,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AxisMode {
     Relative,
     Absolute,
@@ -148,22 +573,47 @@ pub struct BridgePositionSnapshot {
     pub rotation: Option<Rotation2d>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Vec3f {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Rotation2d {
     pub yaw: f32,
     pub pitch: f32,
 }
 
+/// φ ticks in one Paper server tick (20 Hz); bridge instructions expire
+/// in whole Paper ticks.
+pub const PAPER_TICK: u64 = (PHI_TICK_HZ / 20.0) as u64;
+
+/// An instruction for the Paper plugin, as queued per stand.
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeInstruction {
+    /// Delivered highest first.
+    pub priority: u8,
+    /// Last φ tick the instruction still applies at; it is dropped after.
+    pub expiry_tick: u64,
+    #[serde(flatten)]
+    pub action: BridgeAction,
+}
+
+impl BridgeInstruction {
+    pub fn new(action: BridgeAction, now_tick: u64) -> Self {
+        Self {
+            priority: action.priority(),
+            expiry_tick: now_tick + action.lifetime_ticks(),
+            action,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "instruction", rename_all = "snake_case")]
-pub enum BridgeInstruction {
+pub enum BridgeAction {
     SetVelocity {
         stand_id: Option<String>,
         vx: f32,
@@ -187,603 +637,6494 @@ pub enum BridgeInstruction {
     },
 }
 
+impl BridgeAction {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::SetVelocity { .. } => "set_velocity",
+            Self::SetPosition { .. } => "set_position",
+            Self::AlignRotation { .. } => "align_rotation",
+            Self::Echo { .. } => "echo",
+        }
+    }
+
+    /// Authoritative positions beat motion, motion beats looks, and echoes
+    /// go when there is room.
+    fn priority(&self) -> u8 {
+        match self {
+            Self::SetPosition { .. } => 3,
+            Self::SetVelocity { .. } => 2,
+            Self::AlignRotation { .. } => 1,
+            Self::Echo { .. } => 0,
+        }
+    }
+
+    /// Velocities and rotations are stale a couple of Paper ticks on.
+    fn lifetime_ticks(&self) -> u64 {
+        match self {
+            Self::SetVelocity { .. } | Self::AlignRotation { .. } => 2 * PAPER_TICK,
+            Self::SetPosition { .. } => 8 * PAPER_TICK,
+            Self::Echo { .. } => 64 * PAPER_TICK,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 struct SessionInfo {
     client_id: String,
-    capabilities: Vec<String>,
+    planet: PlanetId,
+    /// Phone key of the verified phone; bank transfers must spend from it.
+    phone: Option<String>,
+    capabilities: BTreeSet<Capability>,
+    kind: SessionKind,
     established_ms: i64,
     last_input_ms: i64,
+    rtt: Option<RttEstimate>,
+    /// Handed out at handshake; bank frames are signed with it.
+    frame_key: String,
+    /// Highest seq of a signed frame seen on this key; lower or equal seqs
+    /// are replays.
+    last_signed_seq: Option<u64>,
+}
+
+/// Migration codes stay redeemable this long.
+const MIGRATION_CODE_MS: i64 = 2 * 60 * 1000;
+
+#[derive(Debug, Clone)]
+struct PendingMigration {
+    session_id: String,
+    identity: IdentityDescriptor,
+    auth_token: String,
+    expires_ms: i64,
 }
 
 /// In-memory gateway placeholder. Later this becomes the QUIC/HTTP-4 kernel.
 #[derive(Debug)]
 pub struct OmegaGateway {
-    id: String,
-    boot_ms: i64,
+    identity: GatewayIdentity,
+    ticks: TickAuthority,
     sessions: Mutex<HashMap<String, SessionInfo>>,
+    /// Unredeemed migration codes (dashes stripped) → the session they move.
+    migrations: Mutex<HashMap<String, PendingMigration>>,
+    /// Live spectator sessions allowed (`OMEGA_SPECTATOR_SLOTS`).
+    spectator_slots: usize,
+    /// Bounds on session tick intervals (`OMEGA_TICK_MIN_MS`/`_MAX_MS`).
+    cadence: TickCadence,
     services: OmegaServices,
+    store: GatewayStore,
+    blocks: Mutex<BlockCursor>,
+    recent_blocks: Mutex<VecDeque<BlockHeader>>,
+    scheduler: Mutex<HeightScheduler>,
+    events: EventBus,
+    devices: Arc<DeviceBook>,
+    audit: AuditLog,
+    privacy: PhonePrivacy,
+    /// Secret key for deletion receipt digests.
+    subject_key: [u8; 32],
+    escrows: EscrowBook,
+    items: ItemRegistry,
+    guilds: GuildBook,
+    recoveries: RecoveryBook,
+    metrics: MetricsRecorder,
+    /// CSV balance exports for auditors (`OMEGA_BALANCE_EXPORT`).
+    balance_export: Option<BalanceExporter>,
+    faucet: Option<Faucet>,
+    /// Supply sinks (`OMEGA_BURNS`) and what they burned.
+    burns: BurnBook,
+    recordings: InputRecorder,
+    bridge_queue: BridgeQueue,
+    calibrations: CalibrationBook,
+    quotas: FrameQuotas,
+    /// Spending summaries of the current tip.
+    spending: SpendingCache,
+    sky_assets: SkyAssets,
+    /// Planets served and worlds, profiles and shows from the bootstrap manifest.
+    world: Mutex<WorldSettings>,
+    frames: FrameMeter,
+    /// Time the game engine spent on each recent `TickFrame`.
+    tick_latency: LatencyWindow,
+    node_id: String,
+    consensus: Box<dyn Consensus>,
+    /// Shared key every `BLOCK` frame must be MAC'd with; `None` refuses them all.
+    peer_signer: Option<KeyedSigner>,
+    /// What happens to unsigned bank frames (`OMEGA_FRAME_SIGNATURES`).
+    frame_signatures: SignaturePolicy,
+    /// Nodes that acknowledged the current tip, reset on every new tip.
+    acceptances: Mutex<Vec<String>>,
 }
 
 impl OmegaGateway {
-    pub fn new() -> Self {
-        Self {
-            id: Uuid::new_v4().to_string(),
-            boot_ms: now_ms(),
+    /// Boot a gateway whose block cursor and scheduler persist through `store`.
+    /// A corrupt cursor, block window or scheduler file is an error rather
+    /// than a silent reset to height 0.
+    pub fn with_store(store: GatewayStore) -> Result<Self, StorageError> {
+        let blocks = store
+            .load_json_strict::<BlockCursor>(BLOCK_CURSOR_PATH)?
+            .unwrap_or_default();
+        let recent_blocks = store
+            .load_json_strict::<VecDeque<BlockHeader>>(BLOCK_WINDOW_PATH)?
+            .unwrap_or_default();
+        let scheduler = store
+            .load_json_strict::<HeightScheduler>(SCHEDULER_PATH)?
+            .unwrap_or_default();
+        let aliases = store
+            .load_json::<Vec<AliasRecord>>(ALIAS_PATH)
+            .map(AliasRegistry::from_records)
+            .unwrap_or_default();
+        let labels = store
+            .load_json::<Vec<LabelRecord>>(LABELS_PATH)
+            .map(LabelBook::from_records)
+            .unwrap_or_default();
+        let dns = store
+            .load_text(DNS_RECORDS_PATH)
+            .and_then(|text| match DnsRouter::from_canon(&text) {
+                Ok(router) => Some(router),
+                Err(err) => {
+                    warn!("[dns] ignoring {DNS_RECORDS_PATH}: {err}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        let transfers = store
+            .load_json::<Vec<TransferRecord>>(TRANSFERS_PATH)
+            .map(TransferLog::from_records)
+            .unwrap_or_default();
+        let escrows = store
+            .load_json::<Vec<Escrow>>(ESCROWS_PATH)
+            .map(EscrowBook::from_escrows)
+            .unwrap_or_default();
+        let items = store
+            .load_json::<BTreeMap<String, String>>(ITEMS_PATH)
+            .map(ItemRegistry::from_owners)
+            .unwrap_or_default();
+        let guilds = store
+            .load_json::<Vec<Guild>>(GUILDS_PATH)
+            .map(GuildBook::from_guilds)
+            .unwrap_or_default();
+        let recoveries = store
+            .load_json::<RecoverySnapshot>(RECOVERIES_PATH)
+            .map(RecoveryBook::from_snapshot)
+            .unwrap_or_default();
+        let monetary = store
+            .load_json::<Vec<MonetaryChange>>(MONETARY_PATH)
+            .unwrap_or_default();
+        let last_transfer = transfers.last_id();
+        let ledgers = match store.load_json_strict::<Ledgers>(LEDGER_PATH)? {
+            Some(ledgers) => Some(ledgers),
+            None => ledgers_from_label_files(
+                InfinityBank::default().snapshot(),
+                store.load_label_files(),
+                &escrows.escrows(),
+            ),
+        };
+        let identity = GatewayIdentity::boot(&store)?;
+        info!(
+            "[omega] gateway {} incarnation {}",
+            identity.gateway_id, identity.incarnation
+        );
+        Ok(Self {
+            node_id: identity.gateway_id.clone(),
+            consensus: Box::new(SingleProducer {
+                leader: identity.gateway_id.clone(),
+            }),
+            peer_signer: None,
+            frame_signatures: SignaturePolicy::default(),
+            acceptances: Mutex::new(Vec::new()),
+            identity,
+            ticks: TickAuthority::start(),
             sessions: Mutex::new(HashMap::new()),
-            services: OmegaServices::default(),
+            migrations: Mutex::new(HashMap::new()),
+            spectator_slots: DEFAULT_SPECTATOR_SLOTS,
+            cadence: TickCadence::default(),
+            services: OmegaServices {
+                aliases,
+                labels,
+                dns: Mutex::new(dns),
+                banking: InfinityBank::default()
+                    .restored(ledgers, blocks.height)
+                    .with_transfers(transfers)
+                    .with_schedule(monetary),
+                ..OmegaServices::default()
+            },
+            audit: AuditLog::new(store.clone()),
+            spending: SpendingCache::default(),
+            sky_assets: SkyAssets::load(store.clone()),
+            world: Mutex::new(WorldSettings::load(&store)),
+            metrics: MetricsRecorder::new(store.clone(), last_transfer),
+            balance_export: None,
+            faucet: None,
+            burns: BurnBook::load(store.clone()),
+            events: EventBus::with_inbox(NotificationInbox::load(store.clone())),
+            devices: Arc::new(DeviceBook::load(store.clone())),
+            calibrations: CalibrationBook::load(store.clone()),
+            store,
+            blocks: Mutex::new(blocks),
+            recent_blocks: Mutex::new(recent_blocks),
+            scheduler: Mutex::new(scheduler),
+            privacy: PhonePrivacy::disabled(),
+            subject_key: random_key(),
+            escrows,
+            items,
+            guilds,
+            recoveries,
+            recordings: InputRecorder::default(),
+            bridge_queue: BridgeQueue::default(),
+            quotas: FrameQuotas::default(),
+            frames: FrameMeter::default(),
+            tick_latency: LatencyWindow::default(),
+        })
+    }
+
+    /// Store phones as salted keys in the bank ledger and alias registry.
+    pub fn with_privacy(mut self, privacy: PhonePrivacy) -> Self {
+        let transfers = std::mem::take(&mut self.services.banking.transfers);
+        // A restored ledger is already keyed; only the seed needs re-keying.
+        let restored = self.store.load_json::<Ledgers>(LEDGER_PATH);
+        let compounding = self.services.banking.bank().compounding();
+        let schedule = self.services.banking.bank().schedule().to_vec();
+        self.services.banking = InfinityBank::with_privacy(privacy.clone())
+            .restored(restored, self.block_height())
+            .with_transfers(transfers)
+            .with_schedule(schedule);
+        self.services.banking.bank().set_compounding(compounding);
+        if let Some(key) = privacy.subject_key() {
+            self.subject_key = key;
         }
+        self.privacy = privacy;
+        self
+    }
+
+    /// Compound holder interest per `mode` instead of every block
+    /// (`OMEGA_COMPOUNDING`). Every node of a chain must run the same mode.
+    pub fn with_compounding(self, mode: CompoundingMode) -> Self {
+        self.services.banking.bank().set_compounding(mode);
+        self
+    }
+
+    /// Export every ledger balance as CSV on due blocks.
+    pub fn with_balance_export(mut self, exporter: BalanceExporter) -> Self {
+        self.balance_export = Some(exporter);
+        self
+    }
+
+    /// Push events to registered devices through `adapters`. Needs a Tokio
+    /// runtime for the send task.
+    pub fn with_push(self, adapters: Vec<Box<dyn PushAdapter>>) -> Self {
+        self.events
+            .attach_push(PushDispatcher::spawn(self.devices.clone(), adapters));
+        self
+    }
+
+    /// Allow `slots` live spectator sessions (`OMEGA_SPECTATOR_SLOTS`); 0
+    /// turns spectating off.
+    pub fn with_spectator_slots(mut self, slots: usize) -> Self {
+        self.spectator_slots = slots;
+        self
+    }
+
+    /// Keep session tick intervals within `cadence`.
+    pub fn with_tick_cadence(mut self, cadence: TickCadence) -> Self {
+        self.cadence = cadence;
+        self
+    }
+
+    /// Burn DLOG on the actions `hooks` names (`OMEGA_BURNS`).
+    pub fn with_burn_hooks(mut self, hooks: BurnHooks) -> Self {
+        self.burns = self.burns.with_hooks(hooks);
+        self
+    }
+
+    /// Open the test faucet (`OMEGA_FAUCET`).
+    pub fn with_faucet(mut self, config: FaucetConfig) -> Self {
+        self.faucet = Some(Faucet::load(config, self.store.clone()));
+        self
+    }
+
+    /// Replace the default frame quotas (`;∞;bank;` at 600 frames/min).
+    pub fn with_quotas(mut self, quotas: FrameQuotas) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// Ask mining results for `bits` leading zero bits instead of the φ tick
+    /// rate's (`OMEGA_MINING_DIFFICULTY`).
+    pub fn with_mining_difficulty(mut self, bits: u32) -> Self {
+        self.services.mining = MiningDispatch::with_difficulty(bits);
+        self
+    }
+
+    /// Refuse transfers to labels that were never created (`OMEGA_STRICT_LABELS`).
+    pub fn with_strict_labels(mut self, strict: bool) -> Self {
+        self.services.labels = std::mem::take(&mut self.services.labels).strict(strict);
+        self
+    }
+
+    /// Join a multi-node deployment as `node_id` under `consensus`. Without
+    /// this the gateway is its own single producer.
+    pub fn with_consensus(mut self, node_id: String, consensus: Box<dyn Consensus>) -> Self {
+        self.node_id = node_id;
+        self.consensus = consensus;
+        self
+    }
+
+    /// Accept `BLOCK` frames MAC'd with this shared peer key. Without one
+    /// the gateway refuses all consensus traffic.
+    pub fn with_peer_key(mut self, signer: KeyedSigner) -> Self {
+        self.peer_signer = Some(signer);
+        self
+    }
+
+    /// Flag (the default) or reject unsigned bank frames
+    /// (`OMEGA_FRAME_SIGNATURES`).
+    pub fn with_frame_signatures(mut self, policy: SignaturePolicy) -> Self {
+        self.frame_signatures = policy;
+        self
     }
 
     pub fn id(&self) -> &str {
-        &self.id
+        &self.identity.gateway_id
+    }
+
+    pub fn identity(&self) -> &GatewayIdentity {
+        &self.identity
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
     }
 
     pub fn boot_ms(&self) -> i64 {
-        self.boot_ms
+        self.ticks.boot_ms()
+    }
+
+    pub fn ticks(&self) -> &TickAuthority {
+        &self.ticks
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        self.ticks.current_tick()
+    }
+
+    /// φ tick in progress at wall time `ms` on this gateway.
+    pub fn tick_at(&self, ms: i64) -> u64 {
+        self.ticks.tick_at(ms)
     }
 
     pub fn status(&self) -> GatewayStatus {
-        let sessions = self.sessions.lock().expect("sessions mutex poisoned");
+        let height = self.block_height();
+        let (session_count, spectators, planets) = {
+            let sessions = self.sessions.lock().expect("sessions mutex poisoned");
+            let planets = PlanetId::ALL
+                .into_iter()
+                .map(|planet| PlanetStatus {
+                    planet,
+                    unit: planet.zone().unit,
+                    sessions: sessions.values().filter(|s| s.planet == planet).count(),
+                    bank_labels: self.services.banking.label_count(planet),
+                    bank_root: self.services.banking.ledger_root(planet),
+                    game_ticks: self.services.game.ticks(planet),
+                    sky_events: upcoming_sky_events(
+                        planet,
+                        height.saturating_add(1),
+                        SKY_EVENT_LOOKAHEAD,
+                        sky_ticks_per_block(),
+                    ),
+                })
+                .collect();
+            (sessions.len(), spectator_count(&sessions), planets)
+        };
         GatewayStatus {
-            gateway_id: self.id.clone(),
-            boot_ms: self.boot_ms,
-            session_count: sessions.len(),
+            gateway_id: self.identity.gateway_id.clone(),
+            boot_ms: self.boot_ms(),
+            first_boot_ms: self.identity.first_boot_ms,
+            incarnation: self.identity.incarnation,
+            session_count,
+            spectators,
+            spectator_slots: self.spectator_slots,
             services: self.services.list(),
+            block_height: height,
+            scheduled_tasks: self
+                .scheduler
+                .lock()
+                .expect("scheduler mutex poisoned")
+                .len(),
+            planets,
+            audio: self.services.speaker.telemetry(),
+            consensus: self.consensus_status(),
+            quotas: self.quotas.status(),
+            mining: self.services.mining.status(),
         }
     }
 
-    /// Registers a session and emits route hints for the requested namespaces.
-    pub fn handle_handshake(&self, req: HandshakeRequest) -> HandshakeResponse {
-        let session_id = Uuid::new_v4().to_string();
-        let granted_routes = if req.requested_routes.is_empty() {
-            self.default_routes()
-        } else {
-            req.requested_routes
-                .iter()
-                .flat_map(|route| self.route_for_namespace(route, FrameKind::Dns))
-                .collect()
+    /// Everything the admin dashboard shows, in one read.
+    pub fn overview(&self) -> Overview {
+        let now = now_ms();
+        let sessions = {
+            let sessions = self.sessions.lock().expect("sessions mutex poisoned");
+            let mut by_planet = BTreeMap::new();
+            for info in sessions.values() {
+                *by_planet.entry(info.planet).or_default() += 1;
+            }
+            SessionCounts {
+                total: sessions.len(),
+                verified: sessions.values().filter(|s| s.phone.is_some()).count(),
+                spectators: spectator_count(&sessions),
+                by_planet,
+            }
         };
-
-        let mut guard = self.sessions.lock().expect("sessions mutex poisoned");
-        guard.insert(
-            session_id.clone(),
-            SessionInfo {
-                client_id: req.client_id,
-                capabilities: req.capabilities,
-                established_ms: now_ms(),
-                last_input_ms: now_ms(),
+        let supply = self
+            .services
+            .banking
+            .snapshot()
+            .into_iter()
+            .map(|(planet, ledger)| (planet, ledger.values().sum()))
+            .collect();
+        let transfers = self.services.banking.transfers.records();
+        let blocks = {
+            let cursor = self.blocks.lock().expect("blocks mutex poisoned");
+            BlockOverview {
+                height: cursor.height,
+                last_seal_ms: (cursor.last_seal_ms > 0).then_some(cursor.last_seal_ms),
+                tip_producer: cursor.tip.as_ref().map(|tip| tip.producer.clone()),
+            }
+        };
+        Overview {
+            at_ms: now,
+            uptime_ms: now - self.boot_ms(),
+            sessions,
+            frames: self.frames.rates(now),
+            bank: BankOverview {
+                supply,
+                burned: self
+                    .burns
+                    .burned()
+                    .into_iter()
+                    .map(|(planet, totals)| (planet, totals.total))
+                    .collect(),
+                top_movers: top_movers(&transfers),
+                transfers_considered: transfers.len(),
             },
-        );
-        drop(guard);
-
-        HandshakeResponse {
-            session_id,
-            kernel_version: "omega-http4-edge@0.1.0".into(),
-            motd: "Welcome to the Ω gateway — route via DNS frames and stay phi-synced.".into(),
-            router_epoch_ms: self.boot_ms,
-            granted_routes,
-            identity: None,
+            blocks,
+            sim_ticks: self.tick_stats(),
+            storage: self.store.stats(),
+            bridge: self.bridge_queue.stats(),
         }
     }
 
-    /// Stub router: inspects the frame kind and whispers where it would flow.
-    pub fn handle_frame(&self, frame: FrameEnvelope) -> FrameAck {
-        let mut notes = self.validate_session(&frame.session_id);
-        if frame.kind == FrameKind::Input {
-            self.bump_input_timestamp(&frame.session_id);
-        }
-        notes.extend(self.services.dispatch(&frame));
-        let routed = self.route_for_namespace(&frame.namespace, frame.kind.clone());
-        FrameAck {
-            session_id: frame.session_id,
-            seq: frame.seq,
-            accepted: true,
-            next_tick_ms: now_ms() + 8,
-            routed,
-            notes,
+    pub fn compounding(&self) -> CompoundingStatus {
+        let bank = self.services.banking.bank();
+        CompoundingStatus {
+            mode: bank.compounding(),
+            period_blocks: bank.compounding().period_blocks(),
+            zones: PlanetId::ALL
+                .into_iter()
+                .map(|planet| ZoneCompounding {
+                    planet,
+                    unit: planet.zone().unit,
+                    per_block_factor_ppm: bank.per_block_factor_ppm(planet),
+                    period_factor_ppm: bank.period_factor_ppm(planet),
+                    burned: self.burns.total(planet),
+                })
+                .collect(),
         }
     }
 
-    fn validate_session(&self, session_id: &str) -> Vec<String> {
-        let guard = self.sessions.lock().expect("sessions mutex poisoned");
-        if guard.contains_key(session_id) {
-            vec![format!("session:{session_id} ok")]
-        } else {
-            vec![format!(
-                "session:{session_id} unknown — router will accept but should re-handshake"
-            )]
-        }
+    /// Schedule a change to a zone's rates (`POST /admin/monetary/schedule`);
+    /// the bank pays at them once the chain reaches its height.
+    pub fn schedule_monetary_change(
+        &self,
+        request: MonetaryChangeRequest,
+    ) -> Result<MonetaryChange, BankError> {
+        let mut bank = self.services.banking.bank();
+        let current = bank.monetary_at(request.planet, request.effective_height);
+        let mut change = request.into_change(current, now_ms())?;
+        change.approvers = change
+            .approvers
+            .iter()
+            .map(|approver| self.privacy.label_key(approver))
+            .collect();
+        bank.schedule_change(change.clone())?;
+        let schedule = bank.schedule().to_vec();
+        drop(bank);
+        self.persist(MONETARY_PATH, &schedule);
+        Ok(change)
     }
 
-    fn bump_input_timestamp(&self, session_id: &str) {
-        let mut guard = self.sessions.lock().expect("sessions mutex poisoned");
-        if let Some(info) = guard.get_mut(session_id) {
-            info.last_input_ms = now_ms();
+    /// Rates now and every scheduled change (`GET /v1/spec/monetary/history`).
+    pub fn monetary_history(&self) -> MonetaryHistory {
+        let height = self.block_height();
+        let bank = self.services.banking.bank();
+        MonetaryHistory {
+            height,
+            compounding: bank.compounding(),
+            zones: PlanetId::ALL
+                .into_iter()
+                .map(|planet| ZoneMonetary {
+                    planet,
+                    unit: planet.zone().unit,
+                    monetary: bank.monetary_at(planet, height.saturating_add(1)),
+                })
+                .collect(),
+            changes: bank
+                .schedule()
+                .iter()
+                .map(|change| ScheduledChange {
+                    change: change.clone(),
+                    applied: change.effective_height <= height,
+                })
+                .collect(),
         }
     }
 
-    fn default_routes(&self) -> Vec<RouteHint> {
-        vec![
-            RouteHint {
-                omega_path: ";∞;dns;router;".into(),
-                target: "omega.dns.router".into(),
-                confidence: 0.99,
-            },
-            RouteHint {
-                omega_path: ";∞;bank;infinity;".into(),
-                target: "omega.bank.infinity".into(),
-                confidence: 0.92,
-            },
-            RouteHint {
-                omega_path: ";∞;speaker;engine;".into(),
-                target: "omega.audio.stack".into(),
-                confidence: 0.9,
-            },
-        ]
+    /// Game engine time over the recent `TickFrame`s.
+    pub fn tick_stats(&self) -> LatencySummary {
+        self.tick_latency.summary()
     }
 
-    fn route_for_namespace(&self, namespace: &str, kind: FrameKind) -> Vec<RouteHint> {
-        let cleaned = namespace.trim_matches(';');
-        let mut hints = Vec::new();
-        let kind_hint = match kind {
-            FrameKind::TickFrame => ("tick", "omega.sim.kernel"),
-            FrameKind::Query => ("query", "omega.search"),
-            FrameKind::Event => ("event", "omega.event.bus"),
-            FrameKind::MineJob => ("mine_job", "omega.mining.dispatch"),
-            FrameKind::MineResult => ("mine_result", "omega.mining.result"),
-            FrameKind::Dns => ("dns", "omega.dns.router"),
-            FrameKind::Audio => ("audio", "omega.audio.stack"),
-            FrameKind::Game => ("game", "omega.game.engine"),
-            FrameKind::Input => ("input", "omega.input.buffer"),
-        };
-
-        hints.push(RouteHint {
-            omega_path: format!(";∞;{cleaned};{kind_key};", kind_key = kind_hint.0),
-            target: kind_hint.1.into(),
-            confidence: 0.88,
-        });
-
-        // Secondary route to illustrate multi-hop DNS.
-        if cleaned.contains("bank") || cleaned.contains("vortex") {
-            hints.push(RouteHint {
-                omega_path: ";∞;bank;gravity;router;".into(),
-                target: "omega.bank.gravity".into(),
-                confidence: 0.77,
-            });
+    fn consensus_status(&self) -> ConsensusStatus {
+        let tip = self.tip();
+        ConsensusStatus {
+            policy: self.consensus.name(),
+            node_id: self.node_id.clone(),
+            producing: self.may_produce(),
+            tip_root: tip.as_ref().map(|t| t.root.clone()),
+            tip_producer: tip.map(|t| t.producer),
+            acceptances: self
+                .acceptances
+                .lock()
+                .expect("acceptances mutex poisoned")
+                .clone(),
         }
+    }
 
-        hints
+    pub fn events(&self) -> &EventBus {
+        &self.events
     }
 
-    pub fn process_bridge_input(&self, snapshot: BridgeInputSnapshot) -> Vec<BridgeInstruction> {
-        if let Some(session_id) = snapshot.session_id.as_deref() {
-            self.bump_input_timestamp(session_id);
-        }
-        let mut instructions = Vec::new();
+    pub fn block_height(&self) -> u64 {
+        self.blocks.lock().expect("blocks mutex poisoned").height
+    }
 
-        let profile_scale = match snapshot.profile.as_deref() {
-            Some(name) if name.eq_ignore_ascii_case("surf") => 1.35,
-            Some(name) if name.eq_ignore_ascii_case("bhop") => 1.2,
-            _ => 1.0,
+    pub fn tip(&self) -> Option<BlockHeader> {
+        self.blocks
+            .lock()
+            .expect("blocks mutex poisoned")
+            .tip
+            .clone()
+    }
+
+    /// Up to `limit` retained headers from height `from` on, oldest first.
+    pub fn blocks_from(&self, from: u64, limit: usize) -> Vec<BlockHeader> {
+        self.recent_blocks
+            .lock()
+            .expect("recent blocks mutex poisoned")
+            .iter()
+            .filter(|header| header.height >= from)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Record `header` as the newest retained block, replacing any header
+    /// already held at its height.
+    fn remember_block(&self, header: &BlockHeader) {
+        let mut recent = self
+            .recent_blocks
+            .lock()
+            .expect("recent blocks mutex poisoned");
+        while recent
+            .back()
+            .is_some_and(|last| last.height >= header.height)
+        {
+            recent.pop_back();
+        }
+        if recent.len() >= BLOCK_WINDOW {
+            recent.pop_front();
+        }
+        recent.push_back(header.clone());
+        self.persist(BLOCK_WINDOW_PATH, &*recent);
+    }
+
+    /// Whether the consensus policy lets this node seal the next height.
+    pub fn may_produce(&self) -> bool {
+        self.consensus
+            .may_propose(&self.node_id, self.block_height().saturating_add(1))
+    }
+
+    /// Queue a task for a future block height. Heights already sealed are rejected.
+    pub fn schedule_task(
+        &self,
+        height: u64,
+        kind: String,
+        payload: Value,
+    ) -> Result<ScheduledTask, FrameError> {
+        // Hold the cursor until the task is queued so a seal can't slip
+        // between the height check and the insert and skip the task.
+        let blocks = self.blocks.lock().expect("blocks mutex poisoned");
+        let current = blocks.height;
+        if height <= current {
+            return Err(FrameError::StaleHeight { height, current });
+        }
+        let mut scheduler = self.scheduler.lock().expect("scheduler mutex poisoned");
+        drop(blocks);
+        let task = scheduler.schedule(height, kind, payload);
+        self.persist(SCHEDULER_PATH, &*scheduler);
+        Ok(task)
+    }
+
+    pub fn cancel_task(&self, id: u64) -> Option<ScheduledTask> {
+        let mut scheduler = self.scheduler.lock().expect("scheduler mutex poisoned");
+        let task = scheduler.cancel(id)?;
+        self.persist(SCHEDULER_PATH, &*scheduler);
+        Some(task)
+    }
+
+    pub fn scheduled_tasks(&self) -> Vec<ScheduledTask> {
+        let scheduler = self.scheduler.lock().expect("scheduler mutex poisoned");
+        scheduler.pending().cloned().collect()
+    }
+
+    /// Produce the next block locally and fire every task that came due.
+    pub fn seal_block(&self) -> BlockSeal {
+        let blocks = self.blocks.lock().expect("blocks mutex poisoned");
+        let prev_root = blocks
+            .tip
+            .as_ref()
+            .map_or(GENESIS_ROOT, |tip| tip.root.as_str());
+        let height = blocks.height.saturating_add(1);
+        let mut header = BlockHeader::at(
+            height,
+            prev_root,
+            self.node_id.clone(),
+            now_ms(),
+            &self.services.banking.state_root(height),
+        );
+        self.consensus.seal(&mut header);
+        self.apply_block(blocks, header)
+    }
+
+    /// Apply a block proposed by a peer. It must pass the consensus policy
+    /// and extend the local tip; a competing block at the tip's height
+    /// replaces it only if fork choice prefers it.
+    pub fn accept_block(&self, header: BlockHeader) -> Result<BlockSeal, FrameError> {
+        self.consensus.validate(&header).map_err(consensus_error)?;
+        let mut blocks = self.blocks.lock().expect("blocks mutex poisoned");
+        let current = blocks.height;
+        if header.height <= current {
+            let sibling = blocks.tip.as_ref().is_some_and(|tip| {
+                tip.height == header.height
+                    && tip.prev_root == header.prev_root
+                    && fork_choice(tip, &header).root != tip.root
+            });
+            if !sibling {
+                return Err(FrameError::StaleHeight {
+                    height: header.height,
+                    current,
+                });
+            }
+            // Tasks already fired at this height stay fired; only the tip moves.
+            info!(
+                "[consensus] height {} switches to {}'s block",
+                header.height, header.producer
+            );
+            blocks.last_seal_ms = header.sealed_ms;
+            blocks.tip = Some(header.clone());
+            self.persist(BLOCK_CURSOR_PATH, &*blocks);
+            self.remember_block(&header);
+            self.acceptances
+                .lock()
+                .expect("acceptances mutex poisoned")
+                .clear();
+            return Ok(BlockSeal {
+                height: header.height,
+                sealed_ms: header.sealed_ms,
+                header,
+                fired: Vec::new(),
+            });
+        }
+        let extends = match &blocks.tip {
+            Some(tip) => header.extends(Some(tip)),
+            // Unchained cursor: accept anything rooted at genesis one past our height.
+            None => header.height == current + 1 && header.prev_root == GENESIS_ROOT,
         };
-        let device_scale = match snapshot.device.as_deref() {
-            Some("touch") => 0.7,
-            Some("gamepad") => 0.9,
-            _ => 1.0,
+        if !extends {
+            return Err(consensus_error(ConsensusError::UnknownParent {
+                height: header.height,
+                prev_root: header.prev_root,
+            }));
+        }
+        Ok(self.apply_block(blocks, header))
+    }
+
+    /// Switch to a peer's fork: `headers` chain from one of our retained
+    /// blocks (or genesis) up towards the peer's `tip`. Only a chain whose
+    /// tip is higher than ours is taken, and every header must pass the
+    /// policy before the local tip moves. Tasks already fired stay fired.
+    pub fn switch_fork(
+        &self,
+        headers: Vec<BlockHeader>,
+        tip: &BlockHeader,
+    ) -> Result<BlockSeal, FrameError> {
+        let first = headers
+            .first()
+            .ok_or_else(|| FrameError::Malformed("empty fork".into()))?;
+        let ancestor = first.height.checked_sub(1).and_then(|h| self.block_at(h));
+        let rooted = first.extends(ancestor.as_ref());
+        if !rooted || !headers.windows(2).all(|w| w[1].extends(Some(&w[0]))) {
+            return Err(FrameError::Conflict(format!(
+                "fork at {} doesn't chain onto a retained block",
+                first.height
+            )));
+        }
+        for header in headers.iter().chain([tip]) {
+            self.consensus.validate(header).map_err(consensus_error)?;
+        }
+        let current = self.block_height();
+        if tip.height <= current {
+            return Err(FrameError::StaleHeight {
+                height: tip.height,
+                current,
+            });
+        }
+        warn!(
+            "[consensus] switching to {}'s fork from height {}",
+            tip.producer, first.height
+        );
+        let mut seal = None;
+        for header in headers {
+            let blocks = self.blocks.lock().expect("blocks mutex poisoned");
+            seal = Some(self.apply_block(blocks, header));
+        }
+        Ok(seal.expect("fork has at least one header"))
+    }
+
+    /// Jump straight to a peer's `tip` when the blocks in between are no
+    /// longer retained anywhere we can reach (a snapshot of the cursor).
+    pub fn adopt_checkpoint(&self, tip: BlockHeader) -> Result<BlockSeal, FrameError> {
+        self.consensus.validate(&tip).map_err(consensus_error)?;
+        let blocks = self.blocks.lock().expect("blocks mutex poisoned");
+        if tip.height <= blocks.height {
+            return Err(FrameError::StaleHeight {
+                height: tip.height,
+                current: blocks.height,
+            });
+        }
+        warn!(
+            "[consensus] jumping from {} to checkpoint {} by {}",
+            blocks.height, tip.height, tip.producer
+        );
+        Ok(self.apply_block(blocks, tip))
+    }
+
+    /// Retained header at `height`.
+    pub fn block_at(&self, height: u64) -> Option<BlockHeader> {
+        self.recent_blocks
+            .lock()
+            .expect("recent blocks mutex poisoned")
+            .iter()
+            .find(|header| header.height == height)
+            .cloned()
+    }
+
+    /// Move the tip to `header`, then publish events and fire due tasks.
+    fn apply_block(
+        &self,
+        mut blocks: MutexGuard<'_, BlockCursor>,
+        header: BlockHeader,
+    ) -> BlockSeal {
+        let previous_height = blocks.height;
+        blocks.height = header.height;
+        blocks.last_seal_ms = header.sealed_ms;
+        blocks.tip = Some(header.clone());
+        let cursor = blocks.clone();
+        drop(blocks);
+        self.persist(BLOCK_CURSOR_PATH, &cursor);
+        self.remember_block(&header);
+        self.acceptances
+            .lock()
+            .expect("acceptances mutex poisoned")
+            .clear();
+        self.events.publish(
+            cursor.height,
+            "block_sealed".to_string(),
+            serde_json::json!({
+                "sealed_ms": cursor.last_seal_ms,
+                "root": header.root,
+                "producer": header.producer,
+            }),
+        );
+
+        let world = self.world_settings();
+        for planet in PlanetId::ALL {
+            let Some(sky) = world.timeline(planet.key()) else {
+                continue;
+            };
+            let slide = sky_slide_at_height(&sky, cursor.height);
+            if slide != sky_slide_at_height(&sky, previous_height) {
+                self.events.publish(
+                    cursor.height,
+                    "sky_transition",
+                    serde_json::json!({
+                        "planet": planet,
+                        "slide": slide,
+                        "tick": sky_tick_at_height(&sky, cursor.height),
+                    }),
+                );
+            }
+            // Speakers play the event's stinger; clients draw its overlay.
+            if let Some(event) = sky_event_at(planet, cursor.height, sky_ticks_per_block()) {
+                self.events.publish(
+                    cursor.height,
+                    "sky_event",
+                    serde_json::to_value(&event).unwrap_or_default(),
+                );
+            }
+        }
+
+        let fired = {
+            let mut scheduler = self.scheduler.lock().expect("scheduler mutex poisoned");
+            let due = scheduler.take_due(cursor.height);
+            if !due.is_empty() {
+                self.persist(SCHEDULER_PATH, &*scheduler);
+            }
+            due
         };
-        let velocity_scale = PHI_F32 * INPUT_VELOCITY_SCALE * profile_scale * device_scale;
+        for task in &fired {
+            let payload = serde_json::json!({ "task_id": task.id, "payload": task.payload });
+            // Tasks naming a label (a gift's recipient) also reach its inbox
+            // and only its owner's devices.
+            match task.payload.get("label").and_then(Value::as_str) {
+                Some(label) => {
+                    self.events.publish_to(
+                        cursor.height,
+                        task.kind.clone(),
+                        payload.clone(),
+                        payload,
+                        &[label],
+                    );
+                }
+                None => {
+                    self.events
+                        .publish(cursor.height, task.kind.clone(), payload);
+                }
+            }
+        }
+        self.services.banking.accrue_to(cursor.height);
+        self.refund_expired_escrows(cursor.height);
+        self.advance_recoveries(cursor.height);
+        let ledgers = self.services.banking.snapshot();
+        self.persist(LEDGER_PATH, &ledgers);
+        self.metrics.record(
+            cursor.height,
+            cursor.last_seal_ms,
+            &ledgers,
+            &self.services.banking.transfers,
+            self.services.mining.take_results(),
+        );
+        if let Some(exporter) = &self.balance_export {
+            exporter.offer(cursor.height, &ledgers);
+        }
+        self.refold_label_files();
 
-        if let Some(axis) = snapshot.axes.iter().find(|axis| {
-            axis.action.eq_ignore_ascii_case("move")
-                || axis.action.eq_ignore_ascii_case("move_plane")
-        }) {
-            match axis.mode {
-                AxisMode::Relative => instructions.push(BridgeInstruction::SetVelocity {
-                    stand_id: snapshot.stand_id.clone(),
-                    vx: axis.x * velocity_scale,
-                    vy: 0.0,
-                    vz: axis.y * velocity_scale,
-                }),
-                AxisMode::Absolute => instructions.push(BridgeInstruction::SetPosition {
-                    stand_id: snapshot.stand_id.clone(),
-                    x: axis.x,
-                    y: snapshot.timestamp_ms.unwrap_or_default() as f32 % 32.0,
-                    z: axis.y,
-                }),
+        BlockSeal {
+            height: cursor.height,
+            sealed_ms: cursor.last_seal_ms,
+            header,
+            fired,
+        }
+    }
+
+    /// Rewrite the per-label universe file of every label whose balance moved
+    /// since the last block. Existing files are merged so history refs that
+    /// fell out of the transfer log and hand-set key metadata survive; labels
+    /// no ledger knows any more (tombstoned) lose their file.
+    fn refold_label_files(&self) {
+        let bank = &self.services.banking;
+        for label in bank.take_touched() {
+            let Some(mut newer) = LabelFile::for_label_key(&label) else {
+                continue;
+            };
+            let Some(balances) = bank.label_balances(&label) else {
+                if let Err(err) = self.store.remove_label_file(&newer) {
+                    warn!("[refold] failed to remove {}: {err}", newer.filename());
+                }
+                continue;
+            };
+            newer.balances = balances;
+            for id in bank.transfers.history(&label) {
+                newer.push_history(format!("t{id}"));
+            }
+            if let Err(err) = newer.validate() {
+                warn!("[refold] skipping {label}: {err}");
+                continue;
+            }
+            let file = match self.store.load_label_file(&newer) {
+                Some(mut on_disk) => match on_disk.merge(&newer) {
+                    Ok(()) => on_disk,
+                    Err(err) => {
+                        // Leave the file alone rather than drop its keys.
+                        warn!("[refold] keeping {}: {err}", newer.filename());
+                        continue;
+                    }
+                },
+                None => newer,
+            };
+            if let Err(err) = self.store.save_label_file(&file) {
+                warn!("[refold] failed to write {}: {err}", file.filename());
             }
         }
+    }
 
-        if let Some(axis) = snapshot
-            .axes
+    /// Handshake a peer sends to open its `peer` session here.
+    pub fn peer_handshake(&self) -> HandshakeRequest {
+        HandshakeRequest {
+            client_id: self.node_id.clone(),
+            capabilities: vec![Capability::Peer.key().to_string()],
+            requested_routes: Vec::new(),
+            phone: None,
+            session_token: None,
+            planet: PlanetId::default(),
+            session_kind: SessionKind::Player,
+            peer_mac: self
+                .peer_signer
+                .as_ref()
+                .map(|signer| signer.sign(&peer_handshake_bytes(&self.node_id))),
+        }
+    }
+
+    /// `BLOCK` frame carrying `message` on `session_id`, MAC'd with the peer key.
+    pub fn consensus_frame(&self, session_id: &str, message: &ConsensusMessage) -> FrameEnvelope {
+        let mut payload = serde_json::to_value(message).unwrap_or_default();
+        if let (Some(signer), Some(fields)) = (&self.peer_signer, payload.as_object_mut()) {
+            let mac = signer.sign(&message.signing_bytes());
+            fields.insert("peer_mac".into(), Value::String(mac));
+        }
+        FrameEnvelope {
+            session_id: session_id.to_string(),
+            seq: self.block_height(),
+            namespace: ";∞;consensus;".into(),
+            kind: FrameKind::Block,
+            payload,
+            signature: None,
+        }
+    }
+
+    /// A `BLOCK` frame must carry a `peer_mac` over its message under the
+    /// shared peer key; a node without the key takes no consensus traffic.
+    fn verify_peer_frame(&self, frame: &FrameEnvelope) -> Result<(), String> {
+        let signer = self
+            .peer_signer
+            .as_ref()
+            .ok_or("BLOCK rejected: this node has no peer key")?;
+        let message = serde_json::from_value::<ConsensusMessage>(frame.payload.clone())
+            .map_err(|err| format!("BLOCK rejected: malformed block frame: {err}"))?;
+        let mac = frame
+            .payload
+            .get("peer_mac")
+            .and_then(Value::as_str)
+            .ok_or("BLOCK rejected: missing peer credential")?;
+        if !signer.verify(&message.signing_bytes(), mac) {
+            return Err("BLOCK rejected: bad peer credential".into());
+        }
+        Ok(())
+    }
+
+    /// Proposals and acceptances arriving as `BLOCK` frames.
+    fn handle_consensus(&self, frame: &FrameEnvelope) -> Vec<String> {
+        match serde_json::from_value::<ConsensusMessage>(frame.payload.clone()) {
+            Ok(ConsensusMessage::Proposal { header }) => {
+                let height = header.height;
+                match self.accept_block(header) {
+                    Ok(seal) => vec![format!(
+                        "block {} accepted root {}",
+                        seal.height, seal.header.root
+                    )],
+                    Err(err) => vec![format!("block {height} rejected: {err}")],
+                }
+            }
+            Ok(ConsensusMessage::Acceptance { height, root, node }) => {
+                let on_tip = self
+                    .tip()
+                    .is_some_and(|tip| tip.height == height && tip.root == root);
+                if !on_tip {
+                    return vec![format!(
+                        "acceptance from {node} for height {height} is not our tip"
+                    )];
+                }
+                let mut acceptances = self.acceptances.lock().expect("acceptances mutex poisoned");
+                if !acceptances.contains(&node) {
+                    acceptances.push(node.clone());
+                }
+                vec![format!(
+                    "acceptance from {node} recorded for height {height}"
+                )]
+            }
+            Err(err) => vec![format!("malformed block frame: {err}")],
+        }
+    }
+
+    pub fn register_alias(
+        &self,
+        handle: &str,
+        label: &str,
+        owner_phone: &str,
+        public: bool,
+    ) -> Result<AliasRecord, DlogError> {
+        let record = self.services.aliases.register(
+            handle,
+            &self.privacy.label_key(label),
+            &self.privacy.phone_key(owner_phone),
+            public,
+        )?;
+        self.persist(ALIAS_PATH, &self.services.aliases.records());
+        Ok(record)
+    }
+
+    pub fn release_alias(&self, handle: &str, owner_phone: &str) -> Result<AliasRecord, DlogError> {
+        let record = self
+            .services
+            .aliases
+            .release(handle, &self.privacy.phone_key(owner_phone))?;
+        self.persist(ALIAS_PATH, &self.services.aliases.records());
+        Ok(record)
+    }
+
+    pub fn lookup_alias(&self, handle: &str) -> Option<AliasView> {
+        self.services.aliases.lookup(handle).map(|r| r.view())
+    }
+
+    /// Open a new label `;<phone>;<name>;` for `phone`.
+    pub fn create_label(&self, phone: &str, name: &str) -> Result<LabelView, DlogError> {
+        let name = canonical_label_name(name)?;
+        let key = self.privacy.label_key(&format!(";{phone};{name};"));
+        let labels = &self.services.labels;
+        labels.create(&key, &name, &self.privacy.phone_key(phone), now_ms())?;
+        self.persist(LABELS_PATH, &labels.records());
+        Ok(self.label_view(phone, &key))
+    }
+
+    /// Archive one of `phone`'s labels; it must be empty on every planet.
+    pub fn archive_label(&self, phone: &str, name: &str) -> Result<LabelView, DlogError> {
+        let name = canonical_label_name(name)?;
+        let key = self.privacy.label_key(&format!(";{phone};{name};"));
+        let balances = self.services.banking.label_balances(&key);
+        let labels = &self.services.labels;
+        if balances.is_none() && labels.get(&key).is_none() {
+            return Err(FrameError::NotFound(format!("label {name}")).into());
+        }
+        if let Some((planet, balance)) = balances.iter().flatten().find(|(_, b)| **b > 0) {
+            return Err(FrameError::Conflict(format!(
+                "label {name} still holds {balance} on {planet}"
+            ))
+            .into());
+        }
+        labels.archive(&key, &name, &self.privacy.phone_key(phone), now_ms())?;
+        self.persist(LABELS_PATH, &labels.records());
+        Ok(self.label_view(phone, &key))
+    }
+
+    /// Every label of `phone`'s: created ones, and ones that only exist
+    /// because money moved to them.
+    pub fn labels_for(&self, phone: &str) -> Vec<LabelView> {
+        let owner = self.privacy.phone_key(phone);
+        let mut keys: BTreeSet<String> = self
+            .services
+            .labels
+            .records()
+            .into_iter()
+            .filter(|record| record.owner_phone == owner)
+            .map(|record| record.label)
+            .collect();
+        keys.extend(self.services.banking.owned_labels(&owner));
+        keys.iter().map(|key| self.label_view(phone, key)).collect()
+    }
+
+    /// Input calibrations of one of `owner_phone`'s labels, by device.
+    pub fn calibrations(
+        &self,
+        owner_phone: &str,
+        label: &str,
+    ) -> Result<BTreeMap<String, DeviceCalibration>, DlogError> {
+        let label = self.owned_label(owner_phone, label)?;
+        Ok(self.calibrations.profile(&label))
+    }
+
+    /// Calibrate how one of `owner_phone`'s labels moves and looks on a device.
+    pub fn calibrate(
+        &self,
+        owner_phone: &str,
+        label: &str,
+        update: CalibrationUpdate,
+    ) -> Result<DeviceCalibration, DlogError> {
+        let label = self.owned_label(owner_phone, label)?;
+        Ok(self.calibrations.update(&label, update)?)
+    }
+
+    fn label_view(&self, phone: &str, key: &str) -> LabelView {
+        let record = self.services.labels.get(key);
+        let name = record.as_ref().map_or_else(
+            || {
+                key.trim_matches(';')
+                    .split_once(';')
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_default()
+            },
+            |record| record.name.clone(),
+        );
+        let status = match &record {
+            Some(record) if record.archived_ms.is_some() => LabelStatus::Archived,
+            Some(_) => LabelStatus::Open,
+            None => LabelStatus::Implicit,
+        };
+        LabelView {
+            label: format!(";{phone};{name};"),
+            status,
+            created_ms: record.as_ref().map(|r| r.created_ms),
+            archived_ms: record.and_then(|r| r.archived_ms),
+            balances: self
+                .services
+                .banking
+                .label_balances(key)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Ledger key for `label` (canon or `@handle`) if `owner_phone` owns it.
+    fn owned_label(&self, owner_phone: &str, label: &str) -> Result<String, DlogError> {
+        let label = self
+            .privacy
+            .label_key(&self.services.aliases.resolve_label(label)?);
+        let owner = self.privacy.phone_key(owner_phone);
+        if crate::alias::label_phone(&label) != Some(owner.as_str()) {
+            return Err(AuthError::Forbidden("label is not owned by this phone".into()).into());
+        }
+        Ok(label)
+    }
+
+    /// Transfers touching `label` (canon or `@handle`) that match `search`.
+    /// Only the phone owning the label may search it.
+    pub fn search_transfers(
+        &self,
+        owner_phone: &str,
+        label: &str,
+        mut search: TransferSearch,
+    ) -> Result<Vec<TransferRecord>, DlogError> {
+        search.label = self.owned_label(owner_phone, label)?;
+        Ok(self.services.banking.transfers.search(&search))
+    }
+
+    /// Inflows and outflows of `label` (canon or `@handle`) over `heights`,
+    /// by category and counterparty. Only heights below the tip count (see
+    /// [`crate::spending`]); only the phone owning the label may ask.
+    pub fn spending_summary(
+        &self,
+        owner_phone: &str,
+        label: &str,
+        planet: Option<PlanetId>,
+        heights: RangeInclusive<u64>,
+    ) -> Result<SpendingSummary, DlogError> {
+        let label = self.owned_label(owner_phone, label)?;
+        let tip = self.block_height();
+        let heights = *heights.start()..=(*heights.end()).min(tip.saturating_sub(1));
+        let compute = || {
+            // At height 0 nothing has been sealed yet.
+            let records = match tip {
+                0 => Vec::new(),
+                _ => self.services.banking.transfers.records(),
+            };
+            SpendingSummary::compute(&label, planet, heights.clone(), &records)
+        };
+        Ok(self
+            .spending
+            .get_or_compute(tip, &label, planet, heights.clone(), compute))
+    }
+
+    /// Remaining faucet budget; `None` when the faucet is off.
+    pub fn faucet_status(&self) -> Option<FaucetStatus> {
+        self.faucet.as_ref().map(|faucet| faucet.status(now_ms()))
+    }
+
+    /// Pay today's faucet grant into `owner_phone`'s `label`, claimed from
+    /// network address `ip`.
+    pub fn faucet_claim(
+        &self,
+        owner_phone: &str,
+        label: &str,
+        ip: Option<&str>,
+    ) -> Result<FaucetGrant, DlogError> {
+        let faucet = self
+            .faucet
+            .as_ref()
+            .ok_or_else(|| FrameError::NotFound("the faucet is off".into()))?;
+        let label = self.owned_label(owner_phone, label)?;
+        let owner = self.privacy.phone_key(owner_phone);
+        let amount = faucet.claim(&owner, ip, now_ms())?;
+        let planet = faucet.planet();
+        let height = self.block_height();
+        let bank = &self.services.banking;
+        bank.credit(planet, &label, amount);
+        let record = bank.transfers.append(
+            planet,
+            height,
+            FAUCET_LABEL,
+            &label,
+            amount,
+            TransferMeta::default(),
+        );
+        self.persist(LEDGER_PATH, &bank.snapshot());
+        self.persist(TRANSFERS_PATH, &bank.transfers.records());
+        let notice = serde_json::json!({ "planet": planet, "amount": amount });
+        self.events
+            .publish_to(height, "faucet_grant", notice.clone(), notice, &[&label]);
+        info!("[faucet] granted {amount} on {}", planet.key());
+        Ok(FaucetGrant {
+            planet,
+            amount,
+            transfer_id: record.id,
+            remaining_today: faucet.status(now_ms()).remaining_today,
+        })
+    }
+
+    /// Configured burn hooks and what they burned so far.
+    pub fn burn_status(&self) -> BurnStatus {
+        self.burns.status()
+    }
+
+    /// Burn what `action` costs, priced at `price`, from `owner_phone`'s
+    /// `label` on `planet`.
+    pub fn burn(
+        &self,
+        owner_phone: &str,
+        label: &str,
+        planet: PlanetId,
+        action: &str,
+        price: Option<u128>,
+    ) -> Result<BurnReceipt, DlogError> {
+        let amount = self.burns.charge(action, price)?;
+        let label = self.owned_label(owner_phone, label)?;
+        let height = self.block_height();
+        let bank = &self.services.banking;
+        bank.debit(planet, &label, amount)?;
+        let record = bank.transfers.append(
+            planet,
+            height,
+            &label,
+            BURN_LABEL,
+            amount,
+            TransferMeta::default(),
+        );
+        let burned_total = self.burns.record(planet, action, amount);
+        self.persist(LEDGER_PATH, &bank.snapshot());
+        self.persist(TRANSFERS_PATH, &bank.transfers.records());
+        let notice = serde_json::json!({ "planet": planet, "action": action, "amount": amount });
+        self.events.publish(height, "burn", notice);
+        info!("[burn] {action} burned {amount} on {}", planet.key());
+        Ok(BurnReceipt {
+            planet,
+            action: action.to_string(),
+            burned: amount,
+            transfer_id: record.id,
+            burned_total,
+        })
+    }
+
+    /// Lock the maker's side and open an escrow against the counterparty.
+    pub fn open_escrow(&self, owner_phone: &str, req: EscrowRequest) -> Result<Escrow, DlogError> {
+        let maker = self.owned_label(owner_phone, &req.offer.label)?;
+        let taker = self
+            .privacy
+            .label_key(&self.services.aliases.resolve_label(&req.ask.label)?);
+        let maker = EscrowLeg::new(maker, req.offer.amount, req.offer.item)?;
+        let taker = EscrowLeg::new(taker, req.ask.amount, req.ask.item)?;
+        self.items.check(&maker)?;
+        let bank = &self.services.banking;
+        let (label, amount) = (maker.label.clone(), maker.amount);
+        bank.move_funds(req.planet, &label, ESCROW_LABEL, amount)?;
+        let escrow = self
+            .escrows
+            .open(
+                req.planet,
+                maker,
+                taker,
+                self.block_height(),
+                req.deadline_blocks,
+            )
+            .inspect_err(|_| bank.release(req.planet, &label, amount))?;
+        self.escrow_changed(&escrow, "escrow_opened");
+        Ok(escrow)
+    }
+
+    /// Counterparty locks their side; both sides then swap immediately.
+    pub fn confirm_escrow(&self, owner_phone: &str, id: u64) -> Result<Escrow, DlogError> {
+        let pending = self
+            .escrows
+            .get(id)
+            .ok_or_else(|| FrameError::NotFound(format!("escrow {id}")))?;
+        let taker = self.owned_label(owner_phone, &pending.taker.label)?;
+        let height = self.block_height();
+        let pending = self.escrows.pending_for_taker(id, &taker, height)?;
+        self.items.check(&pending.taker)?;
+        let bank = &self.services.banking;
+        let amount = pending.taker.amount;
+        bank.move_funds(pending.planet, &taker, ESCROW_LABEL, amount)?;
+        let escrow = self
+            .escrows
+            .settle(id, height)
+            .inspect_err(|_| bank.release(pending.planet, &taker, amount))?;
+        bank.release(escrow.planet, &escrow.taker.label, escrow.maker.amount);
+        bank.release(escrow.planet, &escrow.maker.label, escrow.taker.amount);
+        self.items.settle(&escrow);
+        self.persist(ITEMS_PATH, &self.items.owners());
+        self.escrow_changed(&escrow, "escrow_settled");
+        Ok(escrow)
+    }
+
+    /// Maker withdraws an escrow the counterparty hasn't confirmed yet.
+    pub fn cancel_escrow(&self, owner_phone: &str, id: u64) -> Result<Escrow, DlogError> {
+        let pending = self
+            .escrows
+            .get(id)
+            .ok_or_else(|| FrameError::NotFound(format!("escrow {id}")))?;
+        let maker = self.owned_label(owner_phone, &pending.maker.label)?;
+        let escrow = self.escrows.cancel(id, &maker, self.block_height())?;
+        self.services
+            .banking
+            .release(escrow.planet, &maker, escrow.maker.amount);
+        self.escrow_changed(&escrow, "escrow_cancelled");
+        Ok(escrow)
+    }
+
+    /// Record who holds `item` (admin). An item locked in an open escrow
+    /// can't be reassigned under it.
+    pub fn assign_item(&self, req: ItemAssignment) -> Result<ItemAssignment, DlogError> {
+        let label = self
+            .privacy
+            .label_key(&self.services.aliases.resolve_label(&req.label)?);
+        let leg = EscrowLeg::new(label, 0, Some(req.item))?;
+        let item = leg.item.unwrap_or_default();
+        if !self
+            .escrows
+            .open_for(|_| true)
             .iter()
-            .find(|axis| axis.action.eq_ignore_ascii_case("look"))
+            .all(|e| e.maker.item.as_ref() != Some(&item))
         {
-            instructions.push(BridgeInstruction::AlignRotation {
-                stand_id: snapshot.stand_id.clone(),
-                yaw: axis.x * 4.0,
-                pitch: axis.y * 4.0,
-            });
+            return Err(FrameError::Conflict(format!("{item} is locked in an escrow")).into());
         }
+        self.items.assign(&item, &leg.label);
+        self.persist(ITEMS_PATH, &self.items.owners());
+        Ok(ItemAssignment {
+            item,
+            label: leg.label,
+        })
+    }
 
-        let jump_pressed = snapshot.buttons.iter().any(|button| {
-            button.action.eq_ignore_ascii_case("jump")
-                && matches!(button.state, ButtonState::Pressed | ButtonState::Held)
+    /// Open escrows where one of `owner_phone`'s labels is a party.
+    pub fn open_escrows(&self, owner_phone: &str) -> Vec<Escrow> {
+        let owner = self.privacy.phone_key(owner_phone);
+        self.escrows
+            .open_for(|label| crate::alias::label_phone(label) == Some(owner.as_str()))
+    }
+
+    /// Refund the locked sides of escrows whose deadline is below `height`.
+    fn refund_expired_escrows(&self, height: u64) {
+        for escrow in self.escrows.escrows() {
+            if escrow.is_open() && escrow.deadline_height == height + ESCROW_EXPIRY_NOTICE {
+                self.notify_escrow(&escrow, "escrow_expiring");
+            }
+        }
+        for escrow in self.escrows.expire(height) {
+            for leg in escrow.locked() {
+                self.services
+                    .banking
+                    .release(escrow.planet, &leg.label, leg.amount);
+            }
+            info!("[escrow] refunded {} at height {height}", escrow.id);
+            self.escrow_changed(&escrow, "escrow_refunded");
+        }
+    }
+
+    /// Persist the book with the ledger and publish `kind`.
+    fn escrow_changed(&self, escrow: &Escrow, kind: &str) {
+        self.persist(ESCROWS_PATH, &self.escrows.escrows());
+        self.persist(LEDGER_PATH, &self.services.banking.snapshot());
+        self.notify_escrow(escrow, kind);
+    }
+
+    /// Publish `kind` for `escrow` and put it in both parties' inboxes.
+    /// Labels stay off the public bus.
+    fn notify_escrow(&self, escrow: &Escrow, kind: &str) {
+        let payload = serde_json::json!({
+            "escrow_id": escrow.id,
+            "planet": escrow.planet,
+            "deadline_height": escrow.deadline_height,
         });
-        if jump_pressed {
-            instructions.push(BridgeInstruction::SetVelocity {
-                stand_id: snapshot.stand_id.clone(),
-                vx: 0.0,
-                vy: PHI_F32 * INPUT_ASCENT_SCALE,
-                vz: 0.0,
-            });
+        self.events.publish_to(
+            self.block_height(),
+            kind,
+            payload.clone(),
+            payload,
+            &[&escrow.maker.label, &escrow.taker.label],
+        );
+    }
+
+    /// Nominate guardians for one of `owner_phone`'s labels.
+    pub fn set_guardians(
+        &self,
+        owner_phone: &str,
+        label: &str,
+        req: GuardianRequest,
+    ) -> Result<GuardianSet, DlogError> {
+        let label = self.owned_label(owner_phone, label)?;
+        let guardians = req
+            .guardians
+            .iter()
+            .map(|guardian| {
+                self.services
+                    .aliases
+                    .resolve_label(guardian)
+                    .map(|g| self.privacy.label_key(&g))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let set = self.recoveries.set_guardians(GuardianSet {
+            label,
+            guardians,
+            threshold: req.threshold,
+            delay_blocks: req.delay_blocks,
+        })?;
+        self.persist(RECOVERIES_PATH, &self.recoveries.snapshot());
+        let payload = serde_json::json!({
+            "guardians": set.guardians.len(),
+            "threshold": set.threshold,
+            "delay_blocks": set.delay_blocks,
+        });
+        let parties: Vec<&str> = std::iter::once(set.label.as_str())
+            .chain(set.guardians.iter().map(String::as_str))
+            .collect();
+        self.events.publish_to(
+            self.block_height(),
+            "recovery_guardians_set",
+            payload.clone(),
+            payload,
+            &parties,
+        );
+        Ok(set)
+    }
+
+    /// Guardians of one of `owner_phone`'s labels.
+    pub fn guardians_of(&self, owner_phone: &str, label: &str) -> Result<GuardianSet, DlogError> {
+        let label = self.owned_label(owner_phone, label)?;
+        self.recoveries
+            .guardians(&label)
+            .ok_or_else(|| FrameError::NotFound("guardians for this label".into()).into())
+    }
+
+    /// Ask, from `phone`, to recover `label` (canon or `@handle`) under the
+    /// same name on `phone`.
+    pub fn request_recovery(&self, phone: &str, label: &str) -> Result<Recovery, DlogError> {
+        let label = self
+            .privacy
+            .label_key(&self.services.aliases.resolve_label(label)?);
+        let name = label
+            .trim_matches(';')
+            .split_once(';')
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_default();
+        let new_label = self.privacy.label_key(&format!(";{phone};{name};"));
+        let recovery = self
+            .recoveries
+            .request(&label, &new_label, self.block_height())?;
+        self.recovery_changed(&recovery, "recovery_requested");
+        Ok(recovery)
+    }
+
+    /// Approve recovery `id` as `guardian`, one of `phone`'s labels.
+    pub fn approve_recovery(
+        &self,
+        phone: &str,
+        id: u64,
+        guardian: &str,
+    ) -> Result<Recovery, DlogError> {
+        let guardian = self.owned_label(phone, guardian)?;
+        let pending = self
+            .recoveries
+            .get(id)
+            .ok_or_else(|| FrameError::NotFound(format!("recovery {id}")))?;
+        if !pending.guardians.contains(&guardian) {
+            return Err(AuthError::Forbidden(format!("not a guardian of recovery {id}")).into());
         }
+        if crate::alias::label_phone(&guardian) == crate::alias::label_phone(&pending.new_label) {
+            return Err(AuthError::Forbidden(
+                "the requesting phone can't approve its own recovery".into(),
+            )
+            .into());
+        }
+        let recovery = self
+            .recoveries
+            .approve(id, &guardian, self.block_height())?;
+        self.recovery_changed(&recovery, "recovery_approved");
+        if recovery.unlock_height.is_some() {
+            self.notify_recovery(&recovery, "recovery_time_locked");
+        }
+        Ok(recovery)
+    }
 
-        if instructions.is_empty() {
-            instructions.push(BridgeInstruction::Echo {
-                stand_id: snapshot.stand_id,
-                message: format!(
-                    "input captured for {} at {}",
-                    snapshot.player_uuid,
-                    snapshot.timestamp_ms.unwrap_or_default()
-                ),
-            });
+    /// Call off an open recovery: the label's current phone vetoes it, or
+    /// the requester withdraws.
+    pub fn cancel_recovery(&self, phone: &str, id: u64) -> Result<Recovery, DlogError> {
+        let pending = self
+            .recoveries
+            .get(id)
+            .ok_or_else(|| FrameError::NotFound(format!("recovery {id}")))?;
+        let caller = self.privacy.phone_key(phone);
+        if ![&pending.label, &pending.new_label]
+            .iter()
+            .any(|label| crate::alias::label_phone(label) == Some(caller.as_str()))
+        {
+            return Err(AuthError::Forbidden(format!("not a party to recovery {id}")).into());
+        }
+        let recovery = self.recoveries.cancel(id, self.block_height())?;
+        self.recovery_changed(&recovery, "recovery_cancelled");
+        Ok(recovery)
+    }
+
+    /// Open recoveries of, to, or guarded by one of `phone`'s labels.
+    pub fn open_recoveries(&self, phone: &str) -> Vec<Recovery> {
+        let owner = self.privacy.phone_key(phone);
+        self.recoveries
+            .open_for(|label| crate::alias::label_phone(label) == Some(owner.as_str()))
+    }
+
+    /// Expire stale requests and complete recoveries whose time lock ran out
+    /// by `height`.
+    fn advance_recoveries(&self, height: u64) {
+        let (expired, completed) = self.recoveries.advance(height);
+        for recovery in &expired {
+            self.recovery_changed(recovery, "recovery_expired");
+        }
+        for recovery in &completed {
+            self.complete_recovery(recovery);
+            info!("[recovery] {} completed at height {height}", recovery.id);
+            self.recovery_changed(recovery, "recovery_completed");
+        }
+    }
+
+    /// Move the label's balances, alias and record to the new label.
+    fn complete_recovery(&self, recovery: &Recovery) {
+        let (old, new) = (recovery.label.as_str(), recovery.new_label.as_str());
+        let bank = &self.services.banking;
+        for (planet, balance) in bank.label_balances(old).unwrap_or_default() {
+            if let Err(err) = bank.move_funds(planet, old, new, balance) {
+                warn!("[recovery] cannot move {old} on {planet}: {err}");
+            }
+        }
+        self.persist(LEDGER_PATH, &bank.snapshot());
+        let owner = crate::alias::label_phone(new).unwrap_or_default();
+        if self.services.aliases.rebind(old, new, owner).is_some() {
+            self.persist(ALIAS_PATH, &self.services.aliases.records());
+        }
+        let name = old
+            .trim_matches(';')
+            .split_once(';')
+            .map(|(_, name)| name)
+            .unwrap_or_default();
+        let labels = &self.services.labels;
+        labels.rebind(old, new, name, owner, now_ms());
+        self.persist(LABELS_PATH, &labels.records());
+    }
+
+    fn recovery_changed(&self, recovery: &Recovery, kind: &str) {
+        self.persist(RECOVERIES_PATH, &self.recoveries.snapshot());
+        self.notify_recovery(recovery, kind);
+    }
+
+    /// Publish `kind` for `recovery` to both labels' and the guardians'
+    /// inboxes. Labels stay off the public bus.
+    fn notify_recovery(&self, recovery: &Recovery, kind: &str) {
+        let payload = serde_json::json!({
+            "recovery_id": recovery.id,
+            "status": recovery.status,
+            "approvals": recovery.approvals.len(),
+            "threshold": recovery.threshold,
+            "unlock_height": recovery.unlock_height,
+        });
+        self.events.publish_to(
+            self.block_height(),
+            kind,
+            payload.clone(),
+            payload,
+            &recovery.parties(),
+        );
+    }
+
+    /// Found a guild over `charter.founders`, at least one of which must be
+    /// `owner_phone`'s.
+    pub fn found_guild(
+        &self,
+        owner_phone: &str,
+        charter: GuildCharter,
+    ) -> Result<GuildView, DlogError> {
+        let name = canonical_guild_name(&charter.name)?;
+        let founders = charter
+            .founders
+            .iter()
+            .map(|label| {
+                Ok(self
+                    .privacy
+                    .label_key(&self.services.aliases.resolve_label(label)?))
+            })
+            .collect::<Result<Vec<String>, BankError>>()?;
+        let owns = self.owns(owner_phone);
+        if !founders.iter().any(|label| owns(label)) {
+            return Err(AuthError::Forbidden("a founding label must be yours".into()).into());
+        }
+        let guild = self
+            .guilds
+            .found(&name, &founders, charter.policy, now_ms())?;
+        self.persist(GUILDS_PATH, &self.guilds.guilds());
+        info!(
+            "[guild] founded {name} with {} founders",
+            guild.members.len()
+        );
+        self.guild(owner_phone, &name)
+    }
+
+    /// Guilds with one of `owner_phone`'s labels as a member.
+    pub fn guilds_for(&self, owner_phone: &str) -> Vec<GuildView> {
+        let owns = self.owns(owner_phone);
+        self.guilds
+            .member_of(&owns)
+            .into_iter()
+            .filter_map(|guild| self.guild_view(guild, &owns))
+            .collect()
+    }
+
+    pub fn guild(&self, owner_phone: &str, name: &str) -> Result<GuildView, DlogError> {
+        let owns = self.owns(owner_phone);
+        let guild = self.guilds.view(name, &owns)?;
+        Ok(self
+            .guild_view(guild, &owns)
+            .expect("view checked membership"))
+    }
+
+    /// Give `label` a role in guild `name`, or remove it with `None` (founders).
+    pub fn set_guild_role(
+        &self,
+        owner_phone: &str,
+        name: &str,
+        label: &str,
+        role: Option<GuildRole>,
+    ) -> Result<GuildView, DlogError> {
+        let label = self
+            .privacy
+            .label_key(&self.services.aliases.resolve_label(label)?);
+        self.guilds
+            .set_role(name, self.owns(owner_phone), &label, role)?;
+        self.persist(GUILDS_PATH, &self.guilds.guilds());
+        self.guild(owner_phone, name)
+    }
+
+    /// Move `amount` from one of `owner_phone`'s labels into a treasury.
+    /// Anyone may fund a guild they belong to.
+    pub fn guild_deposit(
+        &self,
+        owner_phone: &str,
+        name: &str,
+        from: &str,
+        planet: PlanetId,
+        amount: u128,
+    ) -> Result<GuildView, DlogError> {
+        let from = self.owned_label(owner_phone, from)?;
+        let guild = self.guilds.view(name, self.owns(owner_phone))?;
+        if amount == 0 {
+            return Err(BankError::ZeroAmount.into());
+        }
+        let bank = &self.services.banking;
+        bank.move_funds(planet, &from, &guild.treasury, amount)?;
+        bank.transfers.append(
+            planet,
+            self.block_height(),
+            &from,
+            &guild.treasury,
+            amount,
+            TransferMeta::default(),
+        );
+        self.persist(LEDGER_PATH, &bank.snapshot());
+        self.guild(owner_phone, name)
+    }
+
+    /// Propose a treasury spend; it pays out at once if the policy allows.
+    pub fn guild_spend(
+        &self,
+        owner_phone: &str,
+        name: &str,
+        req: SpendRequest,
+    ) -> Result<GuildSpend, DlogError> {
+        let to = self
+            .privacy
+            .label_key(&self.services.aliases.resolve_label(&req.to)?);
+        let bank = &self.services.banking;
+        self.services
+            .labels
+            .check_recipient(&to, bank.label_balances(&to).is_some())?;
+        let (guild, spend) = self.guilds.propose(
+            name,
+            self.owns(owner_phone),
+            req.planet,
+            &to,
+            req.amount,
+            now_ms(),
+        )?;
+        self.persist(GUILDS_PATH, &self.guilds.guilds());
+        self.settle_guild_spend(&guild, spend)
+    }
+
+    /// Sign pending spend `id`; the spend pays out once it has enough signers.
+    pub fn approve_guild_spend(
+        &self,
+        owner_phone: &str,
+        name: &str,
+        id: u64,
+    ) -> Result<GuildSpend, DlogError> {
+        let (guild, spend) = self.guilds.approve(name, id, self.owns(owner_phone))?;
+        self.persist(GUILDS_PATH, &self.guilds.guilds());
+        self.settle_guild_spend(&guild, spend)
+    }
+
+    /// Pay `spend` out of the treasury if `guild`'s policy allows it yet.
+    fn settle_guild_spend(
+        &self,
+        guild: &Guild,
+        spend: GuildSpend,
+    ) -> Result<GuildSpend, DlogError> {
+        if !guild.approved(&spend) {
+            return Ok(spend);
         }
+        let bank = &self.services.banking;
+        bank.move_funds(spend.planet, &guild.treasury, &spend.to, spend.amount)?;
+        let height = self.block_height();
+        bank.transfers.append(
+            spend.planet,
+            height,
+            &guild.treasury,
+            &spend.to,
+            spend.amount,
+            TransferMeta::default(),
+        );
+        let spend = self.guilds.executed(&guild.name, spend.id, now_ms())?;
+        self.persist(GUILDS_PATH, &self.guilds.guilds());
+        self.persist(LEDGER_PATH, &bank.snapshot());
+        info!("[guild] {} paid spend {}", guild.name, spend.id);
+        self.events.publish_to(
+            height,
+            "guild_spend",
+            serde_json::json!({ "planet": spend.planet, "amount": spend.amount }),
+            serde_json::json!({
+                "planet": spend.planet,
+                "amount": spend.amount,
+                "guild": guild.name,
+            }),
+            &[&spend.to],
+        );
+        Ok(spend)
+    }
+
+    fn guild_view(&self, guild: Guild, owns: impl Fn(&str) -> bool) -> Option<GuildView> {
+        let (_, role) = guild.role_of(owns)?;
+        let balances = self
+            .services
+            .banking
+            .label_balances(&guild.treasury)
+            .unwrap_or_default();
+        Some(GuildView {
+            guild,
+            role,
+            balances,
+        })
+    }
+
+    /// Whether a ledger key belongs to `owner_phone`.
+    fn owns(&self, owner_phone: &str) -> impl Fn(&str) -> bool {
+        let owner = self.privacy.phone_key(owner_phone);
+        move |label| crate::alias::label_phone(label) == Some(owner.as_str())
+    }
+
+    /// Notifications delivered to `owner_phone`'s labels, newest first.
+    pub fn notifications(&self, owner_phone: &str) -> InboxView {
+        let owner = self.privacy.phone_key(owner_phone);
+        self.events
+            .inbox()
+            .view(|label| crate::alias::label_phone(label) == Some(owner.as_str()))
+    }
+
+    /// Notifications to `session_id`'s labels with an id past `after`,
+    /// oldest first; none for a session without a verified phone.
+    pub fn session_notifications(&self, session_id: &str, after: u64) -> Vec<Notification> {
+        let Some(owner) = self.session_phone(session_id) else {
+            return Vec::new();
+        };
+        self.events.inbox().after(
+            |label| crate::alias::label_phone(label) == Some(owner.as_str()),
+            after,
+        )
+    }
+
+    /// Mark `owner_phone`'s notifications `ids` read (all when empty).
+    pub fn mark_notifications_read(&self, owner_phone: &str, ids: &[u64]) -> usize {
+        let owner = self.privacy.phone_key(owner_phone);
+        self.events.inbox().mark_read(
+            |label| crate::alias::label_phone(label) == Some(owner.as_str()),
+            ids,
+        )
+    }
+
+    /// Drop `owner_phone`'s read notifications, or every one with `all`.
+    pub fn purge_notifications(&self, owner_phone: &str, all: bool) -> usize {
+        let owner = self.privacy.phone_key(owner_phone);
+        self.events.inbox().purge(
+            |label| crate::alias::label_phone(label) == Some(owner.as_str()),
+            all,
+        )
+    }
+
+    /// Register a device to push `owner_phone`'s notifications to.
+    pub fn register_device(
+        &self,
+        owner_phone: &str,
+        registration: DeviceRegistration,
+    ) -> Result<Device, FrameError> {
+        let owner = self.privacy.phone_key(owner_phone);
+        self.devices.register(&owner, registration, now_ms())
+    }
+
+    pub fn devices(&self, owner_phone: &str) -> Vec<Device> {
+        self.devices.list(&self.privacy.phone_key(owner_phone))
+    }
+
+    /// Drop every push device `owner_phone` registered; how many.
+    pub fn purge_devices(&self, owner_phone: &str) -> usize {
+        self.devices
+            .purge_phone(&self.privacy.phone_key(owner_phone))
+    }
+
+    pub fn remove_device(&self, owner_phone: &str, id: &str) -> bool {
+        self.devices
+            .remove(&self.privacy.phone_key(owner_phone), id)
+    }
+
+    /// VAPID key browsers subscribe with; `None` without Web Push.
+    pub fn push_key(&self) -> Option<&str> {
+        self.events.push().and_then(|push| push.web_key())
+    }
+
+    /// Start (or keep) recording a player's bridge input for review.
+    pub fn flag_recording(&self, player_uuid: &str, reason: Option<String>) -> RecordingStatus {
+        let status = self.recordings.flag(player_uuid, reason);
+        self.audit.record(
+            "admin",
+            "input_recording_flagged",
+            serde_json::json!({ "player_uuid": player_uuid, "reason": status.reason }),
+        );
+        status
+    }
+
+    pub fn unflag_recording(&self, player_uuid: &str) -> Result<(), FrameError> {
+        if !self.recordings.unflag(player_uuid) {
+            return Err(FrameError::NotFound(format!(
+                "no recording for {player_uuid}"
+            )));
+        }
+        self.audit.record(
+            "admin",
+            "input_recording_unflagged",
+            serde_json::json!({ "player_uuid": player_uuid }),
+        );
+        Ok(())
+    }
+
+    pub fn flagged_recordings(&self) -> Vec<RecordingStatus> {
+        self.recordings.flagged()
+    }
+
+    /// Dump a flagged player's buffer as evidence for `report_id`, persisted
+    /// at `;∞;reports;<report_id>;<player_uuid>;inputs;` so players filed
+    /// under one report keep separate dumps.
+    pub fn dump_recording(
+        &self,
+        player_uuid: &str,
+        report_id: &str,
+    ) -> Result<InputDump, FrameError> {
+        let dump = self.recordings.dump(player_uuid, report_id)?;
+        self.persist(
+            &format!(";∞;reports;{report_id};{player_uuid};inputs;"),
+            &dump,
+        );
+        self.audit.record(
+            "admin",
+            "input_recording_dumped",
+            serde_json::json!({
+                "report_id": report_id,
+                "player_uuid": player_uuid,
+                "samples": dump.samples.len(),
+            }),
+        );
+        Ok(dump)
+    }
+
+    /// Balance behind a public alias on `planet`; private aliases stay hidden.
+    pub fn public_balance(&self, planet: PlanetId, handle: &str) -> Option<(AliasView, u128)> {
+        let record = self.services.aliases.lookup(handle).filter(|r| r.public)?;
+        let balance = self.services.banking.balance_of(planet, &record.label);
+        Some((record.view(), balance))
+    }
+
+    pub fn reverse_alias(&self, label: &str, requester_phone: Option<&str>) -> Option<AliasView> {
+        let requester = requester_phone.map(|phone| self.privacy.phone_key(phone));
+        self.services
+            .aliases
+            .reverse(&self.privacy.label_key(label), requester.as_deref())
+    }
+
+    /// Erase everything the gateway holds for `phone`: aliases, notification
+    /// inboxes, guild memberships, guardian sets and input calibrations are
+    /// dropped and bank labels fold into an anonymous tombstone. The caller purges auth sessions, presence and
+    /// push devices, then records the receipt in the audit log.
+    pub fn forget_phone(&self, phone: &str) -> DeletionReceipt {
+        let phone_key = self.privacy.phone_key(phone);
+        let receipt_id = Uuid::new_v4().simple().to_string();
+        let tombstone_label = format!(";tombstone;{receipt_id};");
+
+        let aliases = self.services.aliases.purge_owner(&phone_key);
+        if !aliases.is_empty() {
+            self.persist(ALIAS_PATH, &self.services.aliases.records());
+        }
+        let notifications_purged = self.purge_notifications(&phone_key, true);
+        let (labels, balance, supply) = self
+            .services
+            .banking
+            .tombstone_owner(&phone_key, &tombstone_label);
+        let owned = |label: &str| crate::alias::label_phone(label) == Some(phone_key.as_str());
+        if self
+            .services
+            .banking
+            .transfers
+            .tombstone(owned, &tombstone_label)
+        {
+            self.persist(TRANSFERS_PATH, &self.services.banking.transfers.records());
+        }
+        let (guilds_left, guilds_changed) = self.guilds.purge_members(owned, &tombstone_label);
+        if guilds_changed {
+            self.persist(GUILDS_PATH, &self.guilds.guilds());
+        }
+        let guardian_sets_purged =
+            self.recoveries
+                .purge_labels(owned, &tombstone_label, self.block_height());
+        self.persist(RECOVERIES_PATH, &self.recoveries.snapshot());
+        let calibrations_purged = self.calibrations.purge_labels(owned);
+        // Drop the erased labels' files now rather than at the next seal.
+        self.refold_label_files();
+
+        DeletionReceipt {
+            receipt_id,
+            deleted_ms: now_ms(),
+            subject_digest: subject_digest(&self.subject_key, &phone_key),
+            sessions_purged: 0,
+            presence_purged: false,
+            devices_purged: 0,
+            notifications_purged,
+            guilds_left,
+            guardian_sets_purged,
+            calibrations_purged,
+            aliases_purged: aliases.into_iter().map(|r| r.handle).collect(),
+            labels_tombstoned: labels,
+            tombstone_label,
+            tombstoned_balance: balance,
+            supply_after: supply,
+        }
+    }
+
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    pub fn metrics(&self) -> &MetricsRecorder {
+        &self.metrics
+    }
+
+    /// What the Paper bridge should know about worlds, movement and shows.
+    pub fn bridge_manifest(&self) -> WorldManifest {
+        WorldManifest::build(&self.store, &self.world_settings())
+    }
+
+    pub fn world_settings(&self) -> WorldSettings {
+        self.world.lock().expect("world mutex poisoned").clone()
+    }
+
+    /// Sky show `id` as this deployment runs it, if there is one.
+    pub fn sky_timeline(&self, id: &str) -> Option<SkyTimeline> {
+        self.world_settings().timeline(id)
+    }
+
+    pub fn sky_assets(&self) -> &SkyAssets {
+        &self.sky_assets
+    }
+
+    /// Where `sky` stands at the current height; shows advance with blocks.
+    pub fn sky_tick(&self, sky: &SkyTimeline) -> u64 {
+        sky_tick_at_height(sky, self.block_height())
+    }
+
+    /// Sky events on `planet` starting after the tip, within the lookahead.
+    pub fn upcoming_sky_events(&self, planet: PlanetId) -> Vec<SkyEvent> {
+        upcoming_sky_events(
+            planet,
+            self.block_height().saturating_add(1),
+            SKY_EVENT_LOOKAHEAD,
+            sky_ticks_per_block(),
+        )
+    }
+
+    #[cfg(test)]
+    pub fn freeze_interest(&self) {
+        self.services.banking.freeze_interest();
+    }
+
+    /// Current route table as a canon semicolon file.
+    pub fn export_dns(&self) -> String {
+        self.services
+            .dns
+            .lock()
+            .expect("dns mutex poisoned")
+            .to_canon()
+    }
+
+    /// Replace the route table. The incoming version must be newer than the
+    /// current one unless `force` is set.
+    pub fn import_dns(&self, text: &str, force: bool) -> Result<DnsImport, FrameError> {
+        let incoming = DnsRouter::from_canon(text).map_err(FrameError::Malformed)?;
+        let mut dns = self.services.dns.lock().expect("dns mutex poisoned");
+        if !force && incoming.version <= dns.version {
+            return Err(FrameError::Conflict(format!(
+                "version {} is not newer than {}",
+                incoming.version, dns.version
+            )));
+        }
+        *dns = incoming;
+        if let Err(err) = self.store.save_text(DNS_RECORDS_PATH, &dns.to_canon()) {
+            warn!("[store] failed to persist {DNS_RECORDS_PATH}: {err}");
+        }
+        Ok(DnsImport {
+            version: dns.version,
+            records: dns.records.len(),
+        })
+    }
+
+    /// Re-read the route table from `dns;records` under `OMEGA_ROOT`, for
+    /// operators who edit the file in place. Its version isn't checked.
+    pub fn reload_dns(&self) -> Result<DnsImport, FrameError> {
+        let text = self
+            .store
+            .load_text(DNS_RECORDS_PATH)
+            .ok_or_else(|| FrameError::NotFound(format!("{DNS_RECORDS_PATH} under OMEGA_ROOT")))?;
+        let reloaded = DnsRouter::from_canon(&text).map_err(FrameError::Malformed)?;
+        let mut dns = self.services.dns.lock().expect("dns mutex poisoned");
+        *dns = reloaded;
+        Ok(DnsImport {
+            version: dns.version,
+            records: dns.records.len(),
+        })
+    }
+
+    /// Apply a bootstrap manifest (see [`crate::bootstrap`]), or with
+    /// `dry_run` only list what applying it would change. Labels that exist
+    /// already are left alone; routes are merged into the table, whose
+    /// version goes up by one when any of them changes.
+    pub fn apply_bootstrap(
+        &self,
+        plan: &BootstrapPlan,
+        dry_run: bool,
+    ) -> Result<BootstrapReport, DlogError> {
+        if let Some(issue) = plan.issues.first() {
+            return Err(
+                FrameError::Malformed(format!("line {}: {}", issue.line, issue.message)).into(),
+            );
+        }
+        let incoming = DnsRouter::from_canon(&plan.routes.join("\n"))
+            .map_err(|err| FrameError::Malformed(format!("dns routes: {err}")))?;
+        let mut changes = world_changes(&self.world_settings(), &plan.world);
+
+        let mut opened = Vec::new();
+        for label in &plan.labels {
+            let canon = format!(";{};{};", label.phone, label.name);
+            let key = self.privacy.label_key(&canon);
+            if self.services.labels.get(&key).is_some()
+                || self.services.banking.label_balances(&key).is_some()
+            {
+                continue;
+            }
+            let balances: Vec<String> = label
+                .balances
+                .iter()
+                .map(|(planet, amount)| format!("{}:{amount}", planet.key()))
+                .collect();
+            changes.push(BootstrapChange {
+                section: "label",
+                key: redact_phones(&canon),
+                from: None,
+                to: Some(balances.join(",")),
+            });
+            opened.push((label, key));
+        }
+
+        let mut routes: Vec<(String, DnsRecord)> = Vec::new();
+        {
+            let dns = self.services.dns.lock().expect("dns mutex poisoned");
+            let shown = |record: &DnsRecord| format!("{} {}", record.target, record.description);
+            let mut incoming: Vec<_> = incoming.records.into_iter().collect();
+            incoming.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, record) in incoming {
+                let from = dns.records.get(&key).map(shown);
+                if from.as_deref() == Some(shown(&record).as_str()) {
+                    continue;
+                }
+                changes.push(BootstrapChange {
+                    section: "dns",
+                    key: record.omega_path.clone(),
+                    from,
+                    to: Some(shown(&record)),
+                });
+                routes.push((key, record));
+            }
+        }
+        if dry_run {
+            return Ok(BootstrapReport { dry_run, changes });
+        }
+
+        *self.world.lock().expect("world mutex poisoned") = plan.world.clone();
+        self.persist(WORLD_SETTINGS_PATH, &plan.world);
+        if !opened.is_empty() {
+            let height = self.block_height();
+            let bank = &self.services.banking;
+            for (label, key) in &opened {
+                self.create_label(&label.phone, &label.name)?;
+                for (planet, amount) in &label.balances {
+                    bank.credit(*planet, key, *amount);
+                    bank.transfers.append(
+                        *planet,
+                        height,
+                        BOOTSTRAP_LABEL,
+                        key,
+                        *amount,
+                        TransferMeta::default(),
+                    );
+                }
+            }
+            self.persist(LEDGER_PATH, &bank.snapshot());
+            self.persist(TRANSFERS_PATH, &bank.transfers.records());
+        }
+        if !routes.is_empty() {
+            let mut dns = self.services.dns.lock().expect("dns mutex poisoned");
+            dns.records.extend(routes);
+            dns.version += 1;
+            if let Err(err) = self.store.save_text(DNS_RECORDS_PATH, &dns.to_canon()) {
+                warn!("[store] failed to persist {DNS_RECORDS_PATH}: {err}");
+            }
+        }
+        info!("[bootstrap] applied {} changes", changes.len());
+        Ok(BootstrapReport { dry_run, changes })
+    }
+
+    /// Every live session, oldest first.
+    pub fn sessions(&self) -> Vec<SessionView> {
+        let sessions = self.sessions.lock().expect("sessions mutex poisoned");
+        let mut views: Vec<SessionView> = sessions
+            .iter()
+            .map(|(session_id, info)| SessionView {
+                session_id: session_id.clone(),
+                client_id: info.client_id.clone(),
+                planet: info.planet,
+                phone: info.phone.as_deref().map(redact_phones),
+                capabilities: info.capabilities.clone(),
+                kind: info.kind,
+                established_ms: info.established_ms,
+                last_input_ms: info.last_input_ms,
+                rtt: info.rtt,
+                tick_interval_ms: self.cadence.interval_ms(info.rtt.as_ref()),
+            })
+            .collect();
+        views.sort_by(|a, b| {
+            (a.established_ms, &a.session_id).cmp(&(b.established_ms, &b.session_id))
+        });
+        views
+    }
+
+    /// Drop a session; its next frames carry no capabilities until the
+    /// client handshakes again.
+    pub fn kick_session(&self, session_id: &str) -> Result<SessionView, FrameError> {
+        let view = self
+            .sessions()
+            .into_iter()
+            .find(|view| view.session_id == session_id)
+            .ok_or_else(|| FrameError::NotFound(format!("session {session_id}")))?;
+        self.sessions
+            .lock()
+            .expect("sessions mutex poisoned")
+            .remove(session_id);
+        Ok(view)
+    }
+
+    /// Check the economy's invariants now: each zone's ledger sums to its
+    /// genesis plus what was minted minus what was burned, and the escrow
+    /// label holds exactly what open escrows lock. Balances are unsigned, so
+    /// an overdraft can't be stored; one that wrapped breaks `supply`.
+    pub fn check_invariants(&self) -> InvariantReport {
+        let mut report = InvariantReport::new(now_ms());
+        let mut locked = BTreeMap::<PlanetId, u128>::new();
+        for escrow in self.escrows.escrows() {
+            for leg in escrow.locked() {
+                *locked.entry(escrow.planet).or_default() += leg.amount;
+            }
+        }
+        let bank = self.services.banking.bank();
+        for (planet, account) in bank.supply_accounts() {
+            report.expect(
+                "supply",
+                planet.key(),
+                account.expected(),
+                bank.supply_of(*planet),
+            );
+            report.expect(
+                "escrow_locked",
+                planet.key(),
+                locked.get(planet).copied().unwrap_or_default(),
+                bank.balance_of(*planet, ESCROW_LABEL),
+            );
+        }
+        report
+    }
+
+    /// Locked sides of every open escrow, by escrow id.
+    pub fn escrow_locks(&self) -> Vec<EscrowLock> {
+        let mut escrows = self.escrows.escrows();
+        escrows.sort_by_key(|escrow| escrow.id);
+        escrows
+            .iter()
+            .flat_map(|escrow| {
+                escrow.locked().map(|leg| EscrowLock {
+                    escrow_id: escrow.id,
+                    planet: escrow.planet,
+                    label: redact_phones(&leg.label),
+                    amount: leg.amount,
+                    item: leg.item.clone(),
+                    deadline_height: escrow.deadline_height,
+                })
+            })
+            .collect()
+    }
+
+    fn persist<T: Serialize>(&self, omega_path: &str, value: &T) {
+        if let Err(err) = self.store.save_json(omega_path, value) {
+            warn!("[store] failed to persist {omega_path}: {err}");
+        }
+    }
+
+    /// Registers a session and emits route hints for the requested namespaces.
+    /// Unknown capability keys reject the whole handshake, and so does asking
+    /// for one that `grant` doesn't entitle: `banking`/`mining` need a verified
+    /// phone, `admin` the admin token, `peer` a valid `peer_mac`. Spectators
+    /// get [`SPECTATOR_CAPABILITIES`] and no more, and are turned away with
+    /// a `409` once every spectator slot is taken.
+    pub fn handle_handshake(
+        &self,
+        req: HandshakeRequest,
+        grant: SessionGrant,
+    ) -> Result<HandshakeResponse, DlogError> {
+        let mut capabilities = parse_capabilities(&req.capabilities)?;
+        if req.session_kind == SessionKind::Spectator {
+            if req.capabilities.is_empty() {
+                capabilities = SPECTATOR_CAPABILITIES.into();
+            }
+            if let Some(cap) = capabilities
+                .iter()
+                .find(|cap| !SPECTATOR_CAPABILITIES.contains(cap))
+            {
+                return Err(AuthError::Forbidden(format!(
+                    "spectator sessions can't have the {cap} capability"
+                ))
+                .into());
+            }
+        }
+        let peer = match (&self.peer_signer, &req.peer_mac) {
+            (Some(signer), Some(mac)) => signer.verify(&peer_handshake_bytes(&req.client_id), mac),
+            _ => false,
+        };
+        for cap in &capabilities {
+            let (entitled, needs) = match cap {
+                Capability::Banking | Capability::Mining => {
+                    (grant.phone.is_some(), "a verified phone")
+                }
+                Capability::Admin => (grant.admin, "the admin token"),
+                Capability::Peer => (peer, "the peer key"),
+                _ => (true, ""),
+            };
+            if !entitled {
+                return Err(AuthError::Forbidden(format!("{cap} capability needs {needs}")).into());
+            }
+        }
+        let session_id = Uuid::new_v4().to_string();
+        let granted_routes = if req.requested_routes.is_empty() {
+            self.default_routes()
+        } else {
+            req.requested_routes
+                .iter()
+                .flat_map(|route| self.route_for_namespace(route, FrameKind::Dns))
+                .collect()
+        };
+
+        let mut guard = self.sessions.lock().expect("sessions mutex poisoned");
+        if req.session_kind == SessionKind::Spectator
+            && spectator_count(&guard) >= self.spectator_slots
+        {
+            return Err(FrameError::Conflict(format!(
+                "all {} spectator slots are taken",
+                self.spectator_slots
+            ))
+            .into());
+        }
+        let info = SessionInfo {
+            client_id: req.client_id,
+            planet: req.planet,
+            phone: grant.phone.map(|phone| self.privacy.phone_key(&phone)),
+            capabilities,
+            kind: req.session_kind,
+            established_ms: now_ms(),
+            last_input_ms: now_ms(),
+            rtt: None,
+            frame_key: frame_signing::mint_frame_key(),
+            last_signed_seq: None,
+        };
+        guard.insert(session_id.clone(), info.clone());
+        drop(guard);
+
+        Ok(self.handshake_response(session_id, granted_routes, &info))
+    }
+
+    fn handshake_response(
+        &self,
+        session_id: String,
+        granted_routes: Vec<RouteHint>,
+        info: &SessionInfo,
+    ) -> HandshakeResponse {
+        HandshakeResponse {
+            session_id,
+            kernel_version: "omega-http4-edge@0.1.0".into(),
+            motd: "Welcome to the Ω gateway — route via DNS frames and stay phi-synced.".into(),
+            router_epoch_ms: self.boot_ms(),
+            granted_routes,
+            identity: None,
+            planet: info.planet,
+            capabilities: info.capabilities.iter().cloned().collect(),
+            frame_kinds: FrameKind::SUPPORTED.to_vec(),
+            session_kind: info.kind,
+            gateway_id: self.identity.gateway_id.clone(),
+            incarnation: self.identity.incarnation,
+            retry_after_ms: None,
+            frame_key: info.frame_key.clone(),
+        }
+    }
+
+    /// Mint a one-time code that moves `session_id` to another device. The
+    /// session must belong to `identity`'s verified phone; a new code
+    /// replaces one the session hasn't redeemed yet.
+    pub fn mint_migration_code(
+        &self,
+        session_id: &str,
+        identity: IdentityDescriptor,
+        auth_token: &str,
+    ) -> Result<MigrationCode, AuthError> {
+        match self.session_phone(session_id) {
+            None => return Err(AuthError::Unverified),
+            Some(phone) if phone != self.privacy.phone_key(&identity.phone) => {
+                return Err(AuthError::Forbidden(
+                    "session belongs to another phone".into(),
+                ))
+            }
+            Some(_) => {}
+        }
+        let raw = Uuid::new_v4().simple().to_string()[..12].to_ascii_uppercase();
+        let now = now_ms();
+        let expires_ms = now + MIGRATION_CODE_MS;
+        let mut migrations = self.migrations.lock().expect("migrations mutex poisoned");
+        migrations
+            .retain(|_, pending| pending.session_id != session_id && pending.expires_ms >= now);
+        migrations.insert(
+            raw.clone(),
+            PendingMigration {
+                session_id: session_id.to_string(),
+                identity,
+                auth_token: auth_token.to_string(),
+                expires_ms,
+            },
+        );
+        Ok(MigrationCode {
+            code: format!("{}-{}-{}", &raw[..4], &raw[4..8], &raw[8..]),
+            expires_ms,
+        })
+    }
+
+    /// Redeem a migration code on the new device. A new session takes over
+    /// the old one's phone, planet, kind and capabilities (admin excepted:
+    /// it rides on the admin token, not the phone), and the old session is
+    /// closed. Inboxes and push devices are keyed by phone, so they follow.
+    /// A code works once; unknown, used and expired codes are all `401`.
+    pub fn redeem_migration_code(
+        &self,
+        code: &str,
+        client_id: String,
+    ) -> Result<MigratedSession, AuthError> {
+        let key = code
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let pending = self
+            .migrations
+            .lock()
+            .expect("migrations mutex poisoned")
+            .remove(&key)
+            .filter(|pending| pending.expires_ms >= now_ms())
+            .ok_or(AuthError::Unverified)?;
+        let mut sessions = self.sessions.lock().expect("sessions mutex poisoned");
+        let mut info = sessions
+            .remove(&pending.session_id)
+            .ok_or(AuthError::Unverified)?;
+        info.capabilities.remove(&Capability::Admin);
+        info.client_id = client_id;
+        info.established_ms = now_ms();
+        info.last_input_ms = now_ms();
+        info.rtt = None;
+        info.frame_key = frame_signing::mint_frame_key();
+        info.last_signed_seq = None;
+        let session_id = Uuid::new_v4().to_string();
+        sessions.insert(session_id.clone(), info.clone());
+        drop(sessions);
+
+        let mut handshake = self.handshake_response(session_id, self.default_routes(), &info);
+        handshake.identity = Some(pending.identity);
+        Ok(MigratedSession {
+            handshake,
+            replaced_session: pending.session_id,
+            auth_token: pending.auth_token,
+        })
+    }
+
+    /// Fail unless `session_id` was granted `cap` at handshake.
+    pub fn require(&self, session_id: &str, cap: Capability) -> Result<(), AuthError> {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        let info = guard.get(session_id).ok_or(AuthError::Unverified)?;
+        if info.capabilities.contains(&cap) {
+            Ok(())
+        } else {
+            Err(AuthError::Forbidden(format!(
+                "session lacks {cap} capability"
+            )))
+        }
+    }
+
+    /// Like [`require`](Self::require), and the session must also belong to
+    /// `phone` (HTTP routes that authenticate the phone separately).
+    pub fn require_for(
+        &self,
+        session_id: &str,
+        phone: &str,
+        cap: Capability,
+    ) -> Result<(), AuthError> {
+        self.require(session_id, cap)?;
+        if self.session_phone(session_id) != Some(self.privacy.phone_key(phone)) {
+            return Err(AuthError::Forbidden(
+                "session belongs to another phone".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Who a frame counts against: the verified phone, the peer node, or one
+    /// shared bucket for sessions without a verified identity, so opening
+    /// more sessions doesn't buy more quota.
+    fn quota_identity(&self, session_id: &str) -> String {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        match guard.get(session_id) {
+            Some(SessionInfo {
+                phone: Some(phone), ..
+            }) => format!("phone;{phone}"),
+            Some(info) if info.capabilities.contains(&Capability::Peer) => {
+                format!("peer;{}", info.client_id)
+            }
+            _ => "anonymous".into(),
+        }
+    }
+
+    fn session_phone(&self, session_id: &str) -> Option<String> {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        guard.get(session_id).and_then(|info| info.phone.clone())
+    }
+
+    fn session_frame_key(&self, session_id: &str) -> Option<String> {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        guard.get(session_id).map(|info| info.frame_key.clone())
+    }
+
+    /// `frame` as a client would send it: signed under its session's key,
+    /// with a seq past the last signed one.
+    #[cfg(test)]
+    pub fn sign(&self, mut frame: FrameEnvelope) -> FrameEnvelope {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        let Some(info) = guard.get(&frame.session_id) else {
+            return frame;
+        };
+        if frame_signing::needs_signature(&frame.kind) {
+            frame.seq = frame
+                .seq
+                .max(info.last_signed_seq.map_or(0, |last| last + 1));
+        }
+        frame_signing::signed(frame, &info.frame_key)
+    }
+
+    /// Record `frame`'s seq as the session's last signed one, or why it's a
+    /// replay: a signed frame only ever runs once.
+    fn advance_signed_seq(&self, frame: &FrameEnvelope) -> Result<(), String> {
+        let mut guard = self.sessions.lock().expect("sessions mutex poisoned");
+        let Some(info) = guard.get_mut(&frame.session_id) else {
+            return Ok(());
+        };
+        if let Some(last) = info.last_signed_seq.filter(|last| frame.seq <= *last) {
+            return Err(format!(
+                "{:?} rejected: replayed seq {} (last signed seq {last})",
+                frame.kind, frame.seq
+            ));
+        }
+        info.last_signed_seq = Some(frame.seq);
+        Ok(())
+    }
+
+    /// Stub router: inspects the frame kind and whispers where it would flow.
+    pub fn handle_frame(&self, frame: FrameEnvelope) -> FrameAck {
+        self.frames.record(&frame.kind.name(), now_ms());
+        let mut notes = self.validate_session(&frame.session_id);
+        if let FrameKind::Unknown(kind) = &frame.kind {
+            notes.push(format!("unsupported kind {kind}"));
+            return self.frame_ack(&frame, false, notes);
+        }
+        if let Some(reason) = self.admit(&frame, &mut notes) {
+            notes.push(reason);
+            return self.frame_ack(&frame, false, notes);
+        }
+        if frame.kind == FrameKind::Input {
+            self.bump_input_timestamp(&frame.session_id);
+        }
+        self.record_rtt(&frame);
+        let planet = self.session_planet(&frame.session_id);
+        match frame.kind {
+            FrameKind::Block => notes.extend(self.handle_consensus(&frame)),
+            FrameKind::Relay => notes.extend(self.handle_relay(&frame)),
+            _ => {
+                let owner = self.session_phone(&frame.session_id);
+                let started = Instant::now();
+                notes.extend(self.dispatch(&frame, planet, owner.as_deref()));
+                if frame.kind == FrameKind::TickFrame {
+                    let micros = started.elapsed().as_micros();
+                    self.tick_latency
+                        .record(micros.try_into().unwrap_or(u64::MAX));
+                }
+            }
+        }
+        self.frame_ack(&frame, true, notes)
+    }
+
+    /// Why `frame` may not run (a spectator moving the world, missing
+    /// capability, bad peer credential, bad or missing frame signature,
+    /// spent quota), if anything; quota usage and unsigned frame notes go
+    /// into `notes`.
+    fn admit(&self, frame: &FrameEnvelope, notes: &mut Vec<String>) -> Option<String> {
+        if frame.kind.moves_world()
+            && self.session_kind(&frame.session_id) == SessionKind::Spectator
+        {
+            return Some(format!(
+                "{:?} rejected: spectator sessions are read-only",
+                frame.kind
+            ));
+        }
+        match frame.kind.capability() {
+            Some(cap) => self
+                .require(&frame.session_id, cap)
+                .err()
+                .map(|err| format!("{:?} rejected: {err}", frame.kind)),
+            None => None,
+        }
+        .or_else(|| match frame.kind {
+            FrameKind::Block => self.verify_peer_frame(frame).err(),
+            FrameKind::Relay => self.verify_relay_frame(frame).err(),
+            _ => None,
+        })
+        .or_else(|| {
+            let frame_key = self.session_frame_key(&frame.session_id);
+            match frame_signing::check(self.frame_signatures, frame_key.as_deref(), frame) {
+                Ok(flagged) => {
+                    notes.extend(flagged);
+                    None
+                }
+                Err(rejected) => Some(rejected),
+            }
+            .or_else(|| {
+                (frame_signing::needs_signature(&frame.kind) && frame.signature.is_some())
+                    .then(|| self.advance_signed_seq(frame).err())
+                    .flatten()
+            })
+        })
+        .or_else(|| {
+            // Relayed frames were already counted against the caller on the replica.
+            if frame.kind == FrameKind::Relay {
+                return None;
+            }
+            let identity = self.quota_identity(&frame.session_id);
+            match self
+                .quotas
+                .check(&identity, frame.kind.service_path(), now_ms())
+            {
+                Ok(usage) => {
+                    notes.extend(usage);
+                    None
+                }
+                Err(exceeded) => Some(exceeded),
+            }
+        })
+    }
+
+    fn dispatch(
+        &self,
+        frame: &FrameEnvelope,
+        planet: PlanetId,
+        owner: Option<&str>,
+    ) -> Vec<String> {
+        let notes = self
+            .services
+            .dispatch(frame, planet, owner, &self.events, self.block_height());
+        if matches!(frame.kind, FrameKind::Event | FrameKind::MineResult) {
+            self.persist(TRANSFERS_PATH, &self.services.banking.transfers.records());
+        }
+        notes
+    }
+
+    /// Ack for `frame`, with route hints when it went through. The ack goes
+    /// on the session's frame trail for its timeline.
+    pub fn frame_ack(&self, frame: &FrameEnvelope, accepted: bool, notes: Vec<String>) -> FrameAck {
+        let routed = if accepted {
+            self.route_for_namespace(&frame.namespace, frame.kind.clone())
+        } else {
+            Vec::new()
+        };
+        let mut errors: Vec<String> = notes
+            .iter()
+            .filter(|note| note.contains("rejected") || note.starts_with("unsupported"))
+            .cloned()
+            .collect();
+        if !accepted && errors.is_empty() {
+            errors.extend(notes.last().cloned());
+        }
+        self.audit.record_frame(
+            &frame.session_id,
+            FrameTrace {
+                at_ms: now_ms(),
+                kind: frame.kind.name(),
+                accepted,
+                routes: routed.iter().map(|hint| hint.target.clone()).collect(),
+                errors,
+            },
+        );
+        let tick_interval_ms = self.tick_interval_ms(&frame.session_id);
+        FrameAck {
+            session_id: frame.session_id.clone(),
+            seq: frame.seq,
+            accepted,
+            tick: self.ticks.current_tick(),
+            next_tick_ms: self.ticks.next_tick_ms(tick_interval_ms),
+            tick_interval_ms,
+            routed,
+            notes,
+        }
+    }
+
+    /// What `session_id` did, for support; `None` when nothing is known.
+    pub fn session_timeline(&self, session_id: &str, bucket_ms: i64) -> Option<SessionTimeline> {
+        self.audit.timeline(session_id, bucket_ms)
+    }
+
+    /// Frames a read replica must not apply itself: bank frames that move
+    /// funds, and mining frames (jobs are open on the primary only).
+    pub fn is_bank_write(frame: &FrameEnvelope) -> bool {
+        match frame.kind {
+            FrameKind::MineJob | FrameKind::MineResult => true,
+            FrameKind::Query | FrameKind::Event => matches!(
+                frame.payload.get("kind").and_then(Value::as_str),
+                Some("transfer" | "batch_transfer")
+            ),
+            _ => false,
+        }
+    }
+
+    /// Wrap a client's bank frame as a `RELAY` frame on `leader_session`,
+    /// after the same admission checks it would get here. The ack to send
+    /// back instead when it may not go.
+    pub fn relay_frame(
+        &self,
+        frame: &FrameEnvelope,
+        leader_session: &str,
+    ) -> Result<FrameEnvelope, FrameAck> {
+        let mut notes = self.validate_session(&frame.session_id);
+        let rejected = self.admit(frame, &mut notes).or_else(|| {
+            self.peer_signer
+                .is_none()
+                .then(|| "replica has no peer key".into())
+        });
+        if let Some(reason) = rejected {
+            notes.push(reason);
+            return Err(self.frame_ack(frame, false, notes));
+        }
+        let mut relayed = RelayedFrame {
+            phone: self.session_phone(&frame.session_id),
+            planet: self.session_planet(&frame.session_id),
+            kind: frame.kind.clone(),
+            payload: frame.payload.clone(),
+            peer_mac: String::new(),
+        };
+        relayed.peer_mac = self.peer_mac(&relayed.signing_bytes()).unwrap_or_default();
+        Ok(FrameEnvelope {
+            session_id: leader_session.to_string(),
+            seq: frame.seq,
+            namespace: frame.namespace.clone(),
+            kind: FrameKind::Relay,
+            payload: serde_json::to_value(relayed).unwrap_or_default(),
+            signature: None,
+        })
+    }
+
+    /// A `RELAY` frame must carry a `peer_mac` over what it relays.
+    fn verify_relay_frame(&self, frame: &FrameEnvelope) -> Result<(), String> {
+        let relayed = serde_json::from_value::<RelayedFrame>(frame.payload.clone())
+            .map_err(|err| format!("RELAY rejected: malformed relay frame: {err}"))?;
+        if !matches!(relayed.kind, FrameKind::Query | FrameKind::Event) {
+            return Err("RELAY rejected: only bank frames are relayed".into());
+        }
+        if !self.verify_peer_mac(&relayed.signing_bytes(), &relayed.peer_mac) {
+            return Err("RELAY rejected: bad peer credential".into());
+        }
+        Ok(())
+    }
+
+    /// Apply a replica's bank frame as the session it came from there.
+    fn handle_relay(&self, frame: &FrameEnvelope) -> Vec<String> {
+        let Ok(relayed) = serde_json::from_value::<RelayedFrame>(frame.payload.clone()) else {
+            return vec!["RELAY rejected: malformed relay frame".into()];
+        };
+        let inner = FrameEnvelope {
+            kind: relayed.kind,
+            payload: relayed.payload,
+            ..frame.clone()
+        };
+        self.dispatch(&inner, relayed.planet, relayed.phone.as_deref())
+    }
+
+    /// MAC over `message` under the shared peer key, if this node has one.
+    pub fn peer_mac(&self, message: &[u8]) -> Option<String> {
+        self.peer_signer.as_ref().map(|signer| signer.sign(message))
+    }
+
+    pub fn verify_peer_mac(&self, message: &[u8], mac: &str) -> bool {
+        self.peer_signer
+            .as_ref()
+            .is_some_and(|signer| signer.verify(message, mac))
+    }
+
+    /// Id of the newest transfer held; replicas pull the ones after it.
+    pub fn last_transfer_id(&self) -> u64 {
+        self.services.banking.transfers.last_id()
+    }
+
+    /// Bank state for a replica holding transfers up to `after`.
+    pub fn replica_state(&self, after: u64) -> ReplicaState {
+        let (height, tip) = {
+            let blocks = self.blocks.lock().expect("blocks mutex poisoned");
+            (blocks.height, blocks.tip.clone())
+        };
+        let bank = &self.services.banking;
+        ReplicaState {
+            height,
+            tip,
+            ledgers: bank.snapshot(),
+            burned: self.burns.burned(),
+            monetary: bank.bank().schedule().to_vec(),
+            transfers: bank.transfers.after(after),
+            escrows: self.escrows.escrows(),
+            aliases: self.services.aliases.records(),
+            labels: self.services.labels.records(),
+            recoveries: self.recoveries.snapshot(),
+        }
+    }
+
+    /// Load a leader's bank state on a replica and persist it, so a
+    /// restarted replica serves the last state it saw until the next pull.
+    pub fn ingest_replica(&self, state: ReplicaState) {
+        let bank = &self.services.banking;
+        bank.replace(state.ledgers, state.height);
+        self.burns.replace(state.burned);
+        bank.bank().restore_schedule(state.monetary);
+        bank.transfers.ingest(state.transfers);
+        self.escrows.replace(state.escrows);
+        self.services.aliases.replace(state.aliases);
+        self.services.labels.replace(state.labels);
+        self.recoveries.replace(state.recoveries);
+        let cursor = {
+            let mut blocks = self.blocks.lock().expect("blocks mutex poisoned");
+            blocks.height = state.height;
+            if let Some(tip) = &state.tip {
+                blocks.last_seal_ms = tip.sealed_ms;
+            }
+            blocks.tip = state.tip.clone();
+            blocks.clone()
+        };
+        self.persist(BLOCK_CURSOR_PATH, &cursor);
+        if let Some(tip) = &state.tip {
+            if self.block_at(tip.height).as_ref() != Some(tip) {
+                self.remember_block(tip);
+            }
+        }
+        self.persist(LEDGER_PATH, &bank.snapshot());
+        self.persist(MONETARY_PATH, &bank.bank().schedule());
+        self.persist(TRANSFERS_PATH, &bank.transfers.records());
+        self.persist(ESCROWS_PATH, &self.escrows.escrows());
+        self.persist(ALIAS_PATH, &self.services.aliases.records());
+        self.persist(LABELS_PATH, &self.services.labels.records());
+    }
+
+    fn validate_session(&self, session_id: &str) -> Vec<String> {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        if guard.contains_key(session_id) {
+            vec![format!("session:{session_id} ok")]
+        } else {
+            vec![format!(
+                "session:{session_id} unknown — router will accept but should re-handshake"
+            )]
+        }
+    }
+
+    pub fn has_session(&self, session_id: &str) -> bool {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        guard.contains_key(session_id)
+    }
+
+    /// Kind a session was opened as; unknown sessions count as players.
+    fn session_kind(&self, session_id: &str) -> SessionKind {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        guard
+            .get(session_id)
+            .map(|info| info.kind)
+            .unwrap_or_default()
+    }
+
+    /// Planet a session was bound to at handshake; unknown sessions land on Earth.
+    pub fn session_planet(&self, session_id: &str) -> PlanetId {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        guard
+            .get(session_id)
+            .map(|info| info.planet)
+            .unwrap_or_default()
+    }
+
+    /// Fold a round trip the client reported (`rtt_ms`) into its session.
+    fn record_rtt(&self, frame: &FrameEnvelope) {
+        let Some(rtt_ms) = frame.payload.get("rtt_ms").and_then(Value::as_u64) else {
+            return;
+        };
+        let mut guard = self.sessions.lock().expect("sessions mutex poisoned");
+        if let Some(info) = guard.get_mut(&frame.session_id) {
+            match &mut info.rtt {
+                Some(rtt) => rtt.record(rtt_ms),
+                None => info.rtt = Some(RttEstimate::new(rtt_ms)),
+            }
+        }
+    }
+
+    /// Tick interval of `session_id`; one frame for unknown sessions.
+    pub fn tick_interval_ms(&self, session_id: &str) -> i64 {
+        let guard = self.sessions.lock().expect("sessions mutex poisoned");
+        let rtt = guard.get(session_id).and_then(|info| info.rtt);
+        self.cadence.interval_ms(rtt.as_ref())
+    }
+
+    fn bump_input_timestamp(&self, session_id: &str) {
+        let mut guard = self.sessions.lock().expect("sessions mutex poisoned");
+        if let Some(info) = guard.get_mut(session_id) {
+            info.last_input_ms = now_ms();
+        }
+    }
+
+    fn default_routes(&self) -> Vec<RouteHint> {
+        vec![
+            RouteHint {
+                omega_path: ";∞;dns;router;".into(),
+                target: "omega.dns.router".into(),
+                confidence: 0.99,
+            },
+            RouteHint {
+                omega_path: ";∞;bank;infinity;".into(),
+                target: "omega.bank.infinity".into(),
+                confidence: 0.92,
+            },
+            RouteHint {
+                omega_path: ";∞;speaker;engine;".into(),
+                target: "omega.audio.stack".into(),
+                confidence: 0.9,
+            },
+        ]
+    }
+
+    fn route_for_namespace(&self, namespace: &str, kind: FrameKind) -> Vec<RouteHint> {
+        let cleaned = namespace.trim_matches(';');
+        let mut hints = Vec::new();
+        let kind_hint = match kind {
+            FrameKind::TickFrame => ("tick", "omega.sim.kernel"),
+            FrameKind::Query => ("query", "omega.search"),
+            FrameKind::Event => ("event", "omega.event.bus"),
+            FrameKind::MineJob => ("mine_job", "omega.mining.dispatch"),
+            FrameKind::MineResult => ("mine_result", "omega.mining.result"),
+            FrameKind::Dns => ("dns", "omega.dns.router"),
+            FrameKind::Audio => ("audio", "omega.audio.stack"),
+            FrameKind::Game => ("game", "omega.game.engine"),
+            FrameKind::Input => ("input", "omega.input.buffer"),
+            FrameKind::Block => ("block", "omega.consensus"),
+            FrameKind::Relay => ("relay", "omega.bank.leader"),
+            FrameKind::Unknown(_) => return hints,
+        };
+
+        hints.push(RouteHint {
+            omega_path: format!(";∞;{cleaned};{kind_key};", kind_key = kind_hint.0),
+            target: kind_hint.1.into(),
+            confidence: 0.88,
+        });
+
+        // Secondary route to illustrate multi-hop DNS.
+        if cleaned.contains("bank") || cleaned.contains("vortex") {
+            hints.push(RouteHint {
+                omega_path: ";∞;bank;gravity;router;".into(),
+                target: "omega.bank.gravity".into(),
+                confidence: 0.77,
+            });
+        }
+
+        hints
+    }
+
+    /// Queue `actions` for `stand` and hand back what is due now.
+    fn deliver_bridge(&self, stand: &str, actions: Vec<BridgeAction>) -> Vec<BridgeInstruction> {
+        self.bridge_queue
+            .deliver(stand, actions, self.ticks.current_tick())
+    }
+
+    pub fn process_bridge_input(&self, snapshot: BridgeInputSnapshot) -> Vec<BridgeInstruction> {
+        self.recordings.record_input(&snapshot);
+        let stand = stand_key(&snapshot.stand_id, &snapshot.player_uuid);
+        if let Some(session_id) = snapshot.session_id.as_deref() {
+            self.bump_input_timestamp(session_id);
+        }
+        let mut actions = Vec::new();
+
+        let label = snapshot.label.as_deref().and_then(|label| {
+            let label = self.services.aliases.resolve_label(label).ok()?;
+            Some(self.privacy.label_key(&label))
+        });
+        let calibration = self
+            .calibrations
+            .resolve(label.as_deref(), snapshot.device.as_deref());
+        let velocity_scale = PHI_F32
+            * INPUT_VELOCITY_SCALE
+            * self.world_settings().profile_scale(
+                snapshot.profile.as_deref(),
+                snapshot.world.as_deref().unwrap_or_default(),
+            );
+
+        if let Some(axis) = snapshot.axes.iter().find(|axis| {
+            axis.action.eq_ignore_ascii_case("move")
+                || axis.action.eq_ignore_ascii_case("move_plane")
+        }) {
+            match axis.mode {
+                AxisMode::Relative => {
+                    let (x, y) = calibration.movement.apply(axis.x, axis.y);
+                    actions.push(BridgeAction::SetVelocity {
+                        stand_id: snapshot.stand_id.clone(),
+                        vx: x * velocity_scale,
+                        vy: 0.0,
+                        vz: y * velocity_scale,
+                    })
+                }
+                AxisMode::Absolute => actions.push(BridgeAction::SetPosition {
+                    stand_id: snapshot.stand_id.clone(),
+                    x: axis.x,
+                    y: snapshot.timestamp_ms.unwrap_or_default() as f32 % 32.0,
+                    z: axis.y,
+                }),
+            }
+        }
+
+        if let Some(axis) = snapshot
+            .axes
+            .iter()
+            .find(|axis| axis.action.eq_ignore_ascii_case("look"))
+        {
+            let (x, y) = calibration.look.apply(axis.x, axis.y);
+            actions.push(BridgeAction::AlignRotation {
+                stand_id: snapshot.stand_id.clone(),
+                yaw: x * 4.0,
+                pitch: y * 4.0,
+            });
+        }
+
+        let jump_pressed = snapshot.buttons.iter().any(|button| {
+            button.action.eq_ignore_ascii_case("jump")
+                && matches!(button.state, ButtonState::Pressed | ButtonState::Held)
+        });
+        if jump_pressed {
+            actions.push(BridgeAction::SetVelocity {
+                stand_id: snapshot.stand_id.clone(),
+                vx: 0.0,
+                vy: PHI_F32 * INPUT_ASCENT_SCALE,
+                vz: 0.0,
+            });
+        }
+
+        if actions.is_empty() {
+            actions.push(BridgeAction::Echo {
+                stand_id: snapshot.stand_id.clone(),
+                message: format!(
+                    "input captured for {} at {}",
+                    snapshot.player_uuid,
+                    snapshot.timestamp_ms.unwrap_or_default()
+                ),
+            });
+        }
+
+        self.deliver_bridge(&stand, actions)
+    }
+
+    pub fn process_bridge_position(
+        &self,
+        snapshot: BridgePositionSnapshot,
+    ) -> Vec<BridgeInstruction> {
+        self.recordings.record_position(&snapshot);
+        let stand = stand_key(&snapshot.stand_id, &snapshot.player_uuid);
+        if let Some(session_id) = snapshot.session_id.as_deref() {
+            self.bump_input_timestamp(session_id);
+            let planet = self.session_planet(session_id);
+            if let Some(world_planet) = PlanetId::from_world(&snapshot.world) {
+                if world_planet != planet {
+                    let refusal = BridgeAction::Echo {
+                        stand_id: snapshot.stand_id,
+                        message: format!(
+                            "world {} belongs to {world_planet}; session is bound to {planet}",
+                            snapshot.world
+                        ),
+                    };
+                    return self.deliver_bridge(&stand, vec![refusal]);
+                }
+            }
+        }
+
+        let (min_y, max_y) = self.world_settings().world_bounds(&snapshot.world);
+        let clamped_y = snapshot.pos.y.clamp(min_y, max_y);
+        let mut actions = vec![BridgeAction::SetPosition {
+            stand_id: snapshot.stand_id.clone(),
+            x: snapshot.pos.x,
+            y: clamped_y,
+            z: snapshot.pos.z,
+        }];
+
+        if let Some(velocity) = snapshot.velocity {
+            actions.push(BridgeAction::SetVelocity {
+                stand_id: snapshot.stand_id.clone(),
+                vx: velocity.x,
+                vy: velocity.y,
+                vz: velocity.z,
+            });
+        }
+
+        if let Some(rotation) = snapshot.rotation {
+            actions.push(BridgeAction::AlignRotation {
+                stand_id: snapshot.stand_id,
+                yaw: rotation.yaw,
+                pitch: rotation.pitch,
+            });
+        }
+
+        actions.push(BridgeAction::Echo {
+            stand_id: None,
+            message: format!(
+                "position sync for {} in {}",
+                snapshot.player_uuid, snapshot.world
+            ),
+        });
+
+        self.deliver_bridge(&stand, actions)
+    }
+}
+
+/// Queue key for a bridge call: its stand, or the player's own.
+fn stand_key(stand_id: &Option<String>, player_uuid: &str) -> String {
+    stand_id
+        .clone()
+        .unwrap_or_else(|| format!("player-{player_uuid}"))
+}
+
+/// Sky ticks that pass per sealed block: the φ heartbeat over one target
+/// block interval. Slides follow the height, so every node publishes the
+/// same `sky_transition` at the same block.
+fn sky_ticks_per_block() -> u64 {
+    tick::ticks_per_block(&MonetarySpec::default())
+}
+
+fn sky_tick_at_height(sky: &SkyTimeline, height: u64) -> u64 {
+    let total = u128::from(sky.total_duration_ticks().max(1));
+    (u128::from(height) * u128::from(sky_ticks_per_block()) % total) as u64
+}
+
+fn sky_slide_at_height(sky: &SkyTimeline, height: u64) -> Option<String> {
+    sky.slide_at_tick(sky_tick_at_height(sky, height))
+        .map(|slide| slide.id.clone())
+}
+
+fn consensus_error(err: ConsensusError) -> FrameError {
+    match err {
+        ConsensusError::NotProducer { .. } | ConsensusError::UnknownParent { .. } => {
+            FrameError::Conflict(err.to_string())
+        }
+        _ => FrameError::Malformed(err.to_string()),
+    }
+}
+
+/// 32 random bytes from two v4 UUIDs.
+fn random_key() -> [u8; 32] {
+    let mut key = [0; 32];
+    key[..16].copy_from_slice(Uuid::new_v4().as_bytes());
+    key[16..].copy_from_slice(Uuid::new_v4().as_bytes());
+    key
+}
+
+/// Aggregates all Ω services that sit behind the HTTP-4 router.
+#[derive(Debug, Default)]
+struct OmegaServices {
+    dns: Mutex<DnsRouter>,
+    banking: InfinityBank,
+    mining: MiningDispatch,
+    speaker: SpeakerEngine,
+    game: GameEngine,
+    aliases: AliasRegistry,
+    labels: LabelBook,
+}
+
+impl OmegaServices {
+    fn list(&self) -> Vec<&'static str> {
+        vec![
+            "omega.dns.router",
+            "omega.bank.infinity",
+            "omega.mining.dispatch",
+            "omega.audio.stack",
+            "omega.game.engine",
+        ]
+    }
+
+    /// `owner` is the session's phone key; bank transfers spend only from it.
+    fn dispatch(
+        &self,
+        frame: &FrameEnvelope,
+        planet: PlanetId,
+        owner: Option<&str>,
+        events: &EventBus,
+        height: u64,
+    ) -> Vec<String> {
+        let mut notes = Vec::new();
+        match frame.kind {
+            FrameKind::Dns => notes.push(
+                self.dns
+                    .lock()
+                    .expect("dns mutex poisoned")
+                    .resolve(&frame.namespace),
+            ),
+            FrameKind::MineJob | FrameKind::MineResult => notes.push(self.mining.handle(
+                frame,
+                planet,
+                owner,
+                (&self.banking, &self.aliases),
+                events,
+                height,
+            )),
+            FrameKind::Audio => notes.push(self.speaker.handle(frame)),
+            FrameKind::Game | FrameKind::TickFrame => notes.push(self.game.handle(frame, planet)),
+            FrameKind::Query | FrameKind::Event => notes.extend(self.banking.handle(
+                frame,
+                planet,
+                owner,
+                (&self.aliases, &self.labels),
+                events,
+                height,
+            )),
+            FrameKind::Input => notes.push("input frame buffered".into()),
+            // Consensus, relay and unknown frames are handled by the gateway
+            // before dispatch.
+            FrameKind::Block | FrameKind::Relay | FrameKind::Unknown(_) => {}
+        }
+        notes
+    }
+}
+
+/// Ω-path → service table. Persisted as a canon semicolon file at
+/// `;∞;dns;records;` (see [`DnsRouter::to_canon`]).
+#[derive(Debug)]
+struct DnsRouter {
+    version: u64,
+    records: HashMap<String, DnsRecord>,
+}
+
+#[derive(Debug, Clone)]
+struct DnsRecord {
+    omega_path: String,
+    target: String,
+    description: String,
+}
+
+impl Default for DnsRouter {
+    fn default() -> Self {
+        let mut records = HashMap::new();
+        for (omega_path, target, description) in [
+            (";∞;dns;router;", "omega.dns.router", "Omega path router"),
+            (
+                ";∞;bank;infinity;",
+                "omega.bank.infinity",
+                "Gravity-backed Infinity bank",
+            ),
+            (
+                ";∞;bank;gravity;router;",
+                "omega.bank.gravity",
+                "VORTEX/COMET gravity router",
+            ),
+            (
+                ";∞;speaker;engine;",
+                "omega.audio.stack",
+                "Omega speaker engine",
+            ),
+            (
+                ";∞;game;engine;",
+                "omega.game.engine",
+                "Simulation + gameplay kernel",
+            ),
+            (
+                ";∞;mining;dispatch;",
+                "omega.mining.dispatch",
+                "Hash dispatch + silicon rails",
+            ),
+            (
+                ";∞;mining;result;",
+                "omega.mining.result",
+                "Mining result verifier",
+            ),
+        ] {
+            let record = DnsRecord {
+                omega_path: omega_path.into(),
+                target: target.into(),
+                description: description.into(),
+            };
+            records.insert(Self::canonical_key(&record.omega_path), record);
+        }
+
+        Self {
+            version: 0,
+            records,
+        }
+    }
+}
+
+impl DnsRouter {
+    fn resolve(&self, namespace: &str) -> String {
+        let key = Self::canonical_key(namespace);
+        if let Some(record) = self.records.get(&key) {
+            return format!(
+                "dns::{key} → {target} ({desc})",
+                target = record.target,
+                desc = record.description
+            );
+        }
+
+        for fallback in Self::fallback_keys(&key) {
+            if let Some(record) = self.records.get(&fallback) {
+                return format!(
+                    "dns::{key} → {target} (via {path})",
+                    target = record.target,
+                    path = record.omega_path
+                );
+            }
+        }
+
+        format!("dns::{key} → (unmapped) request router-registration")
+    }
+
+    /// Canon export: a `# version N` header, then one record per line as
+    /// `<omega_path> <target> <description>`. Targets are written with
+    /// semicolons instead of dots (`;omega;bank;infinity;`) to keep dots out
+    /// of canon files.
+    fn to_canon(&self) -> String {
+        let mut records: Vec<&DnsRecord> = self.records.values().collect();
+        records.sort_by(|a, b| a.omega_path.cmp(&b.omega_path));
+        let mut out = format!("# version {}\n", self.version);
+        for record in records {
+            out.push_str(&format!(
+                "{} ;{}; {}\n",
+                record.omega_path,
+                record.target.replace('.', ";"),
+                record.description
+            ));
+        }
+        out
+    }
+
+    fn from_canon(text: &str) -> Result<Self, String> {
+        let mut version = 0;
+        let mut records = HashMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(v) = comment.trim().strip_prefix("version") {
+                    version = v
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("line {}: bad version", idx + 1))?;
+                }
+                continue;
+            }
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (Some(omega_path), Some(target)) = (parts.next(), parts.next()) else {
+                return Err(format!("line {}: expected <omega_path> <target>", idx + 1));
+            };
+            let framed = |s: &str| s.len() > 2 && s.starts_with(';') && s.ends_with(';');
+            if !framed(omega_path) || !framed(target) {
+                return Err(format!(
+                    "line {}: paths must be semicolon framed (;a;b;)",
+                    idx + 1
+                ));
+            }
+            let record = DnsRecord {
+                omega_path: omega_path.to_string(),
+                target: target
+                    .split(';')
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("."),
+                description: parts.next().unwrap_or_default().trim().to_string(),
+            };
+            let key = Self::canonical_key(omega_path);
+            if records.insert(key.clone(), record).is_some() {
+                return Err(format!("line {}: duplicate route {key}", idx + 1));
+            }
+        }
+        Ok(Self { version, records })
+    }
+
+    fn canonical_key(namespace: &str) -> String {
+        namespace
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(|segment| segment.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn fallback_keys(key: &str) -> Vec<String> {
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let mut fallbacks = Vec::new();
+        while parts.len() > 1 {
+            parts.pop();
+            fallbacks.push(parts.join("."));
+        }
+        fallbacks
+    }
+}
+
+/// Rebuild the ledgers from the per-label files the last refold left, for a
+/// root that has them but no `bank;ledger` snapshot. Only labels that moved
+/// have a file, so they are laid over the `seed` ledgers; locked escrow
+/// funds have none either and are summed back from the open escrows.
+fn ledgers_from_label_files(
+    seed: Ledgers,
+    files: Vec<LabelFile>,
+    escrows: &[Escrow],
+) -> Option<Ledgers> {
+    if files.is_empty() {
+        return None;
+    }
+    let mut ledgers = seed;
+    for file in files {
+        let key = format!(";{};{};", file.phone, file.label);
+        for (planet, balance) in file.balances {
+            ledgers
+                .entry(planet)
+                .or_default()
+                .insert(key.clone(), balance);
+        }
+    }
+    for escrow in escrows {
+        for leg in escrow.locked() {
+            *ledgers
+                .entry(escrow.planet)
+                .or_default()
+                .entry(ESCROW_LABEL.to_string())
+                .or_default() += leg.amount;
+        }
+    }
+    Some(ledgers)
+}
+
+#[derive(Debug)]
+struct InfinityBank {
+    /// Ledgers, accrual height and compounding mode (`omega_bank`).
+    bank: Mutex<Bank>,
+    #[allow(dead_code)]
+    interest_apy_bps: u32,
+    privacy: PhonePrivacy,
+    transfers: TransferLog,
+    /// Labels whose balance moved since the last refold.
+    touched: Mutex<BTreeSet<String>>,
+}
+
+impl Default for InfinityBank {
+    fn default() -> Self {
+        Self::with_privacy(PhonePrivacy::disabled())
+    }
+}
+
+impl InfinityBank {
+    fn with_privacy(privacy: PhonePrivacy) -> Self {
+        let mut ledger = HashMap::new();
+        ledger.insert(privacy.label_key(";9132077554;comet;"), 1_000_000);
+        ledger.insert(privacy.label_key(";9132077554;vortex1;"), 5_000_000);
+        ledger.insert(privacy.label_key(";9132077554;fun;"), 80_000);
+        let mut ledgers = Ledgers::new();
+        ledgers.insert(PlanetId::Earth, ledger);
+        Self {
+            // Locked escrow funds don't earn; refunds pay back exactly what was locked.
+            bank: Mutex::new(Bank::new(ledgers).with_exempt(ESCROW_LABEL)),
+            interest_apy_bps: 6180,
+            privacy,
+            transfers: TransferLog::default(),
+            touched: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    fn bank(&self) -> MutexGuard<'_, Bank> {
+        self.bank.lock().expect("bank mutex poisoned")
+    }
+
+    /// Replace the seed ledger with one loaded from the store, if any. A
+    /// restored ledger already carries interest up to `height`.
+    fn restored(self, ledgers: Option<Ledgers>, height: u64) -> Self {
+        if let Some(ledgers) = ledgers {
+            self.replace(ledgers, height);
+        }
+        self
+    }
+
+    /// Take a leader's ledgers as they stood at `height` (read replicas).
+    fn replace(&self, ledgers: Ledgers, height: u64) {
+        self.bank().restore(ledgers, height);
+    }
+
+    fn with_transfers(self, transfers: TransferLog) -> Self {
+        Self { transfers, ..self }
+    }
+
+    /// Scheduled rate changes, as stored; see [`Bank::schedule_change`].
+    fn with_schedule(self, changes: Vec<MonetaryChange>) -> Self {
+        self.bank().restore_schedule(changes);
+        self
+    }
+
+    fn snapshot(&self) -> Ledgers {
+        self.bank().snapshot()
+    }
+
+    /// Stop interest so balances stay exact across seals (tests).
+    #[cfg(test)]
+    fn freeze_interest(&self) {
+        self.bank().freeze_interest();
+    }
+
+    /// Pay interest up to `height`; see [`Bank::accrue`].
+    fn accrue_to(&self, height: u64) {
+        let earned = self.bank().accrue(height);
+        // Interest moves balances too, so their label files need a refold.
+        self.touch(earned.iter().map(String::as_str));
+    }
+
+    /// Ack notes for one bank frame; a batch transfer adds one per entry.
+    fn handle(
+        &self,
+        frame: &FrameEnvelope,
+        planet: PlanetId,
+        owner: Option<&str>,
+        (aliases, labels): (&AliasRegistry, &LabelBook),
+        events: &EventBus,
+        height: u64,
+    ) -> Vec<String> {
+        let note = match frame
+            .payload
+            .get("kind")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+        {
+            "balance_query" => {
+                let raw = frame
+                    .payload
+                    .get("label")
+                    .and_then(Value::as_str)
+                    .unwrap_or(";<unknown>;");
+                let label = match aliases.resolve_label(raw) {
+                    Ok(label) => self.privacy.label_key(&label),
+                    Err(err) => return vec![format!("bank::balance rejected ({err})")],
+                };
+                let balance = self.balance_of(planet, &label);
+                format!("bank::balance {raw} = {balance}")
+            }
+            "transfer" => {
+                match self.handle_transfer(&frame.payload, planet, owner, aliases, labels, height) {
+                    Ok(moved) => {
+                        // Labels stay off the public bus; listeners only need the
+                        // beat. The recipient hears the rest in their inbox.
+                        events.publish_to(
+                            height,
+                            "transfer",
+                            serde_json::json!({ "planet": planet, "amount": moved.amount }),
+                            moved.notice,
+                            &[&moved.to],
+                        );
+                        moved.note
+                    }
+                    Err(err) => format!("bank::transfer rejected ({err})"),
+                }
+            }
+            "batch_transfer" => {
+                match self.handle_batch(&frame.payload, planet, owner, aliases, labels, height) {
+                    Ok((summary, paid)) => {
+                        for moved in &paid {
+                            events.publish_to(
+                                height,
+                                "transfer",
+                                serde_json::json!({ "planet": planet, "amount": moved.amount }),
+                                moved.notice.clone(),
+                                &[&moved.to],
+                            );
+                        }
+                        return std::iter::once(summary)
+                            .chain(paid.into_iter().map(|moved| moved.note))
+                            .collect();
+                    }
+                    Err(err) => format!("bank::batch_transfer rejected ({err})"),
+                }
+            }
+            _ => format!(
+                "bank::{} routed (seq {})",
+                frame.namespace.trim_matches(';'),
+                frame.seq
+            ),
+        };
+        vec![note]
+    }
+
+    fn balance_of(&self, planet: PlanetId, label: &str) -> u128 {
+        self.bank().balance_of(planet, label)
+    }
+
+    /// Move `amount` from `from` to `to` on `planet`'s ledger (escrow locks).
+    fn move_funds(
+        &self,
+        planet: PlanetId,
+        from: &str,
+        to: &str,
+        amount: u128,
+    ) -> Result<(), BankError> {
+        if amount == 0 {
+            return Ok(());
+        }
+        self.bank().transfer(planet, from, to, amount)?;
+        self.touch([from, to]);
+        Ok(())
+    }
+
+    /// Pay `amount` of locked escrow funds out to `label`.
+    fn release(&self, planet: PlanetId, label: &str, amount: u128) {
+        if let Err(err) = self.move_funds(planet, ESCROW_LABEL, label, amount) {
+            warn!("[escrow] cannot release {amount} to {label}: {err}");
+        }
+    }
+
+    /// Take `amount` out of `label` and out of supply (burns).
+    fn debit(&self, planet: PlanetId, label: &str, amount: u128) -> Result<(), BankError> {
+        self.bank().debit(planet, label, amount)?;
+        self.touch([label]);
+        Ok(())
+    }
+
+    /// Mint `amount` into `label` (faucet grants and tests).
+    fn credit(&self, planet: PlanetId, label: &str, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        self.bank().credit(planet, label, amount);
+        self.touch([label]);
+    }
+
+    fn touch<'a>(&self, labels: impl IntoIterator<Item = &'a str>) {
+        let mut touched = self.touched.lock().expect("touched mutex poisoned");
+        touched.extend(labels.into_iter().map(str::to_string));
+    }
+
+    /// Labels touched since the last call.
+    fn take_touched(&self) -> BTreeSet<String> {
+        std::mem::take(&mut *self.touched.lock().expect("touched mutex poisoned"))
+    }
+
+    /// Balance of `label` on every planet that holds it; `None` once no
+    /// ledger knows the label (e.g. after tombstoning).
+    fn label_balances(&self, label: &str) -> Option<BTreeMap<PlanetId, u128>> {
+        let bank = self.bank();
+        let balances: BTreeMap<_, _> = bank
+            .ledgers()
+            .iter()
+            .filter_map(|(planet, ledger)| ledger.get(label).map(|b| (*planet, *b)))
+            .collect();
+        (!balances.is_empty()).then_some(balances)
+    }
+
+    /// Ledger labels owned by `phone_key`, on any planet.
+    fn owned_labels(&self, phone_key: &str) -> BTreeSet<String> {
+        self.bank()
+            .ledgers()
+            .values()
+            .flat_map(|ledger| ledger.keys())
+            .filter(|label| crate::alias::label_phone(label) == Some(phone_key))
+            .cloned()
+            .collect()
+    }
+
+    fn label_count(&self, planet: PlanetId) -> usize {
+        let bank = self.bank();
+        bank.ledgers()
+            .get(&planet)
+            .map(HashMap::len)
+            .unwrap_or_default()
+    }
+
+    /// Master root over every planet's ledger at `height`; block headers
+    /// chain it so a block commits to the balances it was sealed on.
+    fn state_root(&self, height: u64) -> String {
+        master_root_for(height, self.bank().ledgers())
+    }
+
+    /// Digest of one planet's ledger; the canonical encoding sorts labels,
+    /// so equal ledgers give equal roots on every node.
+    fn ledger_root(&self, planet: PlanetId) -> String {
+        match self.bank().ledgers().get(&planet) {
+            Some(ledger) => shaless_json_digest(ledger),
+            None => shaless_json_digest(&HashMap::<String, u128>::new()),
+        }
+    }
+
+    /// Fold every label owned by `phone_key` into one anonymous tombstone
+    /// label per planet. Balances move, never vanish, so total supply is unchanged.
+    fn tombstone_owner(&self, phone_key: &str, tombstone: &str) -> (Vec<String>, u128, u128) {
+        let mut bank = self.bank();
+        let supply =
+            |bank: &Bank| -> u128 { PlanetId::ALL.into_iter().map(|p| bank.supply_of(p)).sum() };
+        let supply_before = supply(&bank);
+        let emptied = bank.tombstone_labels(
+            |label| crate::alias::label_phone(label) == Some(phone_key),
+            tombstone,
+        );
+        let mut moved = 0u128;
+        let mut segments = Vec::new();
+        for (_, label, balance) in &emptied {
+            moved += balance;
+            self.touch([label.as_str()]);
+            segments.push(
+                label
+                    .trim_matches(';')
+                    .split_once(';')
+                    .map(|(_, rest)| rest.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        if moved > 0 {
+            self.touch([tombstone]);
+        }
+        let supply_after = supply(&bank);
+        debug_assert_eq!(supply_before, supply_after, "tombstoning changed supply");
+        segments.sort();
+        segments.dedup();
+        (segments, moved, supply_after)
+    }
+
+    fn handle_transfer(
+        &self,
+        payload: &Value,
+        planet: PlanetId,
+        owner: Option<&str>,
+        aliases: &AliasRegistry,
+        labels: &LabelBook,
+        height: u64,
+    ) -> Result<Moved, BankError> {
+        let resolve = |key: &str, missing: &str| {
+            let raw = payload.get(key).and_then(Value::as_str).unwrap_or(missing);
+            aliases
+                .resolve_label(raw)
+                .map(|label| self.privacy.label_key(&label))
+        };
+        let (from, to) = match (
+            resolve("from", ";<missing-from>;"),
+            resolve("to", ";<missing-to>;"),
+        ) {
+            (Ok(from), Ok(to)) => (from, to),
+            (Err(err), _) | (_, Err(err)) => return Err(err),
+        };
+        let (from, to) = (from.as_str(), to.as_str());
+        if owner.is_none() || crate::alias::label_phone(from) != owner {
+            return Err(BankError::NotOwner(from.to_string()));
+        }
+        labels.check_recipient(to, self.label_balances(to).is_some())?;
+        let amount = payload.get("amount").and_then(Value::as_u64).unwrap_or(0) as u128;
+
+        if amount == 0 {
+            return Err(BankError::ZeroAmount);
+        }
+        let meta = TransferMeta::from_payload(payload)?;
+        let to_planet = match payload.get("to_planet").and_then(Value::as_str) {
+            None => planet,
+            Some(raw) => PlanetId::parse(raw).ok_or_else(|| {
+                BankError::InvalidMetadata(format!("to_planet {raw:?} is not a planet"))
+            })?,
+        };
+        let quote = exchange::quote(&planet.zone(), &to_planet.zone(), amount)
+            .ok_or(BankError::Unconvertible(amount))?;
+
+        self.bank()
+            .exchange((planet, to_planet), from, to, amount, quote.received)?;
+        self.touch([from, to]);
+        let notice = serde_json::json!({
+            "planet": to_planet,
+            "amount": quote.received,
+            "from": redact_phones(from),
+            "memo": meta.memo,
+        });
+        let note = if to_planet == planet {
+            let record = self
+                .transfers
+                .append(planet, height, from, to, amount, meta);
+            format!("bank::transfer {amount} {from} → {to} ok (#{})", record.id)
+        } else {
+            let record =
+                self.transfers
+                    .append_exchange((planet, to_planet), height, from, to, &quote, meta);
+            format!(
+                "bank::transfer {amount} {} {from} → {} {} {to} ok (#{}, fee {})",
+                planet.zone().unit,
+                quote.received,
+                to_planet.zone().unit,
+                record.id,
+                quote.fee
+            )
+        };
+        Ok(Moved {
+            note,
+            amount,
+            to: to.to_string(),
+            notice,
+        })
+    }
+
+    /// Pay every entry of a `batch_transfer` from one owned label on
+    /// `planet`, or none of them: each entry is checked like a `transfer`,
+    /// then the total must fit the source balance. Returns the summary note
+    /// and one result per entry.
+    fn handle_batch(
+        &self,
+        payload: &Value,
+        planet: PlanetId,
+        owner: Option<&str>,
+        aliases: &AliasRegistry,
+        labels: &LabelBook,
+        height: u64,
+    ) -> Result<(String, Vec<Moved>), BankError> {
+        let raw = payload
+            .get("from")
+            .and_then(Value::as_str)
+            .unwrap_or(";<missing-from>;");
+        let from = self.privacy.label_key(&aliases.resolve_label(raw)?);
+        if owner.is_none() || crate::alias::label_phone(&from) != owner {
+            return Err(BankError::NotOwner(from));
+        }
+        let meta = TransferMeta::from_payload(payload)?;
+        let raw_entries = payload
+            .get("entries")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if raw_entries.is_empty() || raw_entries.len() > BATCH_MAX {
+            return Err(BankError::InvalidMetadata(format!(
+                "entries must hold 1 to {BATCH_MAX} payouts"
+            )));
+        }
+        let mut entries = Vec::with_capacity(raw_entries.len());
+        for (index, entry) in raw_entries.iter().enumerate() {
+            let in_entry = |err: BankError| BankError::BatchEntry {
+                index,
+                reason: err.to_string(),
+            };
+            let raw = entry
+                .get("to")
+                .and_then(Value::as_str)
+                .unwrap_or(";<missing-to>;");
+            let to = aliases
+                .resolve_label(raw)
+                .map(|label| self.privacy.label_key(&label))
+                .map_err(in_entry)?;
+            labels
+                .check_recipient(&to, self.label_balances(&to).is_some())
+                .map_err(in_entry)?;
+            let amount = entry.get("amount").and_then(Value::as_u64).unwrap_or(0) as u128;
+            if amount == 0 {
+                return Err(in_entry(BankError::ZeroAmount));
+            }
+            let meta = TransferMeta::from_payload(entry).map_err(in_entry)?;
+            entries.push(BatchEntry { to, amount, meta });
+        }
+        let payouts: Vec<_> = entries.iter().map(|e| (e.to.as_str(), e.amount)).collect();
+        let total = self.bank().transfer_many(planet, &from, &payouts)?;
+        self.touch(std::iter::once(from.as_str()).chain(entries.iter().map(|e| e.to.as_str())));
+
+        let record = self
+            .transfers
+            .append_batch(planet, height, &from, meta, entries);
+        let summary = format!(
+            "bank::batch_transfer {total} {from} → {} payouts ok (#{})",
+            record.batch.len(),
+            record.id
+        );
+        let paid = record.batch.iter().enumerate().map(|(index, entry)| Moved {
+            note: format!(
+                "bank::batch_transfer[{index}] {} → {} ok",
+                entry.amount, entry.to
+            ),
+            amount: entry.amount,
+            to: entry.to.clone(),
+            notice: serde_json::json!({
+                "planet": planet,
+                "amount": entry.amount,
+                "from": redact_phones(&from),
+                "memo": entry.meta.memo,
+            }),
+        });
+        Ok((summary, paid.collect()))
+    }
+}
+
+/// A transfer that went through: the ack note, the amount sent, and the
+/// `transfer` notification for the recipient label. A batch transfer gives
+/// one per entry.
+struct Moved {
+    note: String,
+    amount: u128,
+    to: String,
+    notice: Value,
+}
+
+/// Mining jobs and their payouts (see [`crate::mining`]).
+#[derive(Debug, Default)]
+struct MiningDispatch {
+    jobs: Mutex<JobQueue>,
+    /// Results verified since the last block, for the block metrics.
+    results: AtomicU64,
+}
+
+impl MiningDispatch {
+    fn with_difficulty(bits: u32) -> Self {
+        Self {
+            jobs: Mutex::new(JobQueue::new(bits)),
+            results: AtomicU64::new(0),
+        }
+    }
+
+    fn jobs(&self) -> MutexGuard<'_, JobQueue> {
+        self.jobs.lock().expect("mining mutex poisoned")
+    }
+
+    /// `MINE_JOB` opens a job for `payload.label`, which `owner` must own;
+    /// `MINE_RESULT` settles `payload.job_id` with `payload.nonce` and pays
+    /// the job's label out of `bank`.
+    fn handle(
+        &self,
+        frame: &FrameEnvelope,
+        planet: PlanetId,
+        owner: Option<&str>,
+        (bank, aliases): (&InfinityBank, &AliasRegistry),
+        events: &EventBus,
+        height: u64,
+    ) -> String {
+        match frame.kind {
+            FrameKind::MineJob => {
+                match self.open(&frame.payload, planet, owner, bank, aliases, height) {
+                    Ok(job) => format!(
+                        "mining::job {} challenge {} difficulty {} expires at height {}",
+                        job.id, job.challenge, job.difficulty, job.expires_height
+                    ),
+                    Err(err) => format!("mining::job rejected ({err})"),
+                }
+            }
+            FrameKind::MineResult => match self.settle(&frame.payload, bank, events, height) {
+                Ok((job, transfer_id)) => {
+                    format!(
+                        "mining::result job {} paid {REWARD} (#{transfer_id})",
+                        job.id
+                    )
+                }
+                Err(err) => format!("mining::result rejected ({err})"),
+            },
+            _ => "mining received unexpected frame".into(),
+        }
+    }
+
+    fn open(
+        &self,
+        payload: &Value,
+        planet: PlanetId,
+        owner: Option<&str>,
+        bank: &InfinityBank,
+        aliases: &AliasRegistry,
+        height: u64,
+    ) -> Result<MiningJob, DlogError> {
+        let raw = payload
+            .get("label")
+            .and_then(Value::as_str)
+            .ok_or_else(|| FrameError::Malformed("MINE_JOB needs a label".into()))?;
+        let label = bank.privacy.label_key(&aliases.resolve_label(raw)?);
+        if owner.is_none() || crate::alias::label_phone(&label) != owner {
+            return Err(BankError::NotOwner(label).into());
+        }
+        let challenge = Uuid::new_v4().simple().to_string();
+        Ok(self.jobs().open(label, planet, challenge, height))
+    }
+
+    /// Close the job a result solves and pay its label; the job and the id
+    /// of the reward transfer.
+    fn settle(
+        &self,
+        payload: &Value,
+        bank: &InfinityBank,
+        events: &EventBus,
+        height: u64,
+    ) -> Result<(MiningJob, u64), FrameError> {
+        let field = |name: &str| {
+            payload
+                .get(name)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| FrameError::Malformed(format!("MINE_RESULT needs a numeric {name}")))
+        };
+        let (id, nonce) = (field("job_id")?, field("nonce")?);
+        let job = self.jobs().submit(id, nonce, height, now_ms())?;
+        bank.credit(job.planet, &job.label, REWARD);
+        let record = bank.transfers.append(
+            job.planet,
+            height,
+            MINING_LABEL,
+            &job.label,
+            REWARD,
+            TransferMeta::default(),
+        );
+        self.results.fetch_add(1, Ordering::Relaxed);
+        let notice = serde_json::json!({ "planet": job.planet, "amount": REWARD });
+        events.publish_to(
+            height,
+            "mining_reward",
+            notice.clone(),
+            notice,
+            &[&job.label],
+        );
+        Ok((job, record.id))
+    }
+
+    fn status(&self) -> MiningStatus {
+        self.jobs().status(now_ms())
+    }
+
+    fn take_results(&self) -> u64 {
+        self.results.swap(0, Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+struct SpeakerEngine {
+    telemetry: Mutex<Option<AudioTelemetry>>,
+}
+
+impl SpeakerEngine {
+    fn handle(&self, frame: &FrameEnvelope) -> String {
+        if frame.payload.get("kind").and_then(Value::as_str) != Some("telemetry") {
+            return format!(
+                "speaker scheduled audio burst for namespace {}",
+                frame.namespace
+            );
+        }
+        match serde_json::from_value::<AudioTelemetry>(frame.payload.clone()) {
+            Ok(mut report) => {
+                report.received_ms = now_ms();
+                let note = format!(
+                    "speaker telemetry: {} underruns, render {}/{}us, {} voices",
+                    report.underruns,
+                    report.render_us_avg,
+                    report.render_us_max,
+                    report.active_voices
+                );
+                *self.telemetry.lock().expect("speaker mutex poisoned") = Some(report);
+                note
+            }
+            Err(err) => format!("speaker telemetry rejected ({err})"),
+        }
+    }
+
+    fn telemetry(&self) -> Option<AudioTelemetry> {
+        self.telemetry
+            .lock()
+            .expect("speaker mutex poisoned")
+            .clone()
+    }
+}
+
+/// Game/sim routing with a tick counter partitioned per planet.
+#[derive(Debug, Default)]
+struct GameEngine {
+    ticks: Mutex<HashMap<PlanetId, u64>>,
+}
+
+impl GameEngine {
+    fn handle(&self, frame: &FrameEnvelope, planet: PlanetId) -> String {
+        let tick = {
+            let mut ticks = self.ticks.lock().expect("game ticks mutex poisoned");
+            let tick = ticks.entry(planet).or_default();
+            if frame.kind == FrameKind::TickFrame {
+                *tick += 1;
+            }
+            *tick
+        };
+        format!(
+            "game tick routed for {} on {planet} (seq {}, tick {tick})",
+            frame.namespace, frame.seq
+        )
+    }
+
+    fn ticks(&self, planet: PlanetId) -> u64 {
+        let ticks = self.ticks.lock().expect("game ticks mutex poisoned");
+        ticks.get(&planet).copied().unwrap_or_default()
+    }
+}
+
+pub(crate) fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// Bytes a peer MACs to open a `peer` session as `node_id`. A replayed
+/// handshake gains nothing: every `BLOCK` frame carries its own MAC.
+fn peer_handshake_bytes(node_id: &str) -> Vec<u8> {
+    format!("handshake;{node_id}").into_bytes()
+}
+
+fn spectator_count(sessions: &HashMap<String, SessionInfo>) -> usize {
+    sessions
+        .values()
+        .filter(|s| s.kind == SessionKind::Spectator)
+        .count()
+}
+
+/// Typed capability set for a handshake; nothing requested means the defaults.
+fn parse_capabilities(raw: &[String]) -> Result<BTreeSet<Capability>, FrameError> {
+    if raw.is_empty() {
+        return Ok(Capability::DEFAULT.into_iter().collect());
+    }
+    raw.iter()
+        .map(|key| {
+            Capability::parse(key)
+                .ok_or_else(|| FrameError::Malformed(format!("unknown capability {key:?}")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::AxisCalibration;
+    use crate::escrow::{EscrowSide, EscrowState, ItemAssignment};
+    use crate::guild::{GuildPolicy, SpendState};
+    use crate::recovery::GuardianRequest;
+    use crate::transfer::{BATCH_LABEL, EXCHANGE_LABEL};
+    use tempfile::tempdir;
+
+    #[test]
+    fn scheduled_tasks_fire_at_height_and_survive_restart() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let task = gateway
+            .schedule_task(2, "gift_unlock".into(), serde_json::json!({ "gift": 7 }))
+            .expect("future height accepted");
+        assert!(gateway.seal_block().fired.is_empty());
+        drop(gateway);
+
+        let restarted = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        assert_eq!(restarted.block_height(), 1);
+        assert!(restarted
+            .schedule_task(1, "late".into(), Value::Null)
+            .is_err());
+
+        let seal = restarted.seal_block();
+        assert_eq!(seal.height, 2);
+        assert_eq!(seal.fired, vec![task]);
+        let kinds: Vec<_> = restarted
+            .events()
+            .since(0)
+            .into_iter()
+            .map(|e| e.kind)
+            .filter(|kind| kind != "sky_transition")
+            .collect();
+        assert_eq!(kinds, ["block_sealed", "gift_unlock"]);
+        assert!(restarted.scheduled_tasks().is_empty());
+        drop(restarted);
+
+        // A torn cursor refuses to boot instead of restarting at height 0.
+        std::fs::write(
+            dir.path().join("∞").join("blocks;cursor"),
+            b"{\"height\": 2",
+        )
+        .unwrap();
+        assert!(OmegaGateway::with_store(GatewayStore::at(dir.path())).is_err());
+    }
+
+    #[test]
+    fn sky_transitions_follow_block_height_per_planet() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        for _ in 0..20 {
+            gateway.seal_block();
+        }
+        let transitions: Vec<_> = gateway
+            .events()
+            .since(0)
+            .into_iter()
+            .filter(|e| e.kind == "sky_transition")
+            .collect();
+        // 64 ticks per block into 888-tick slides: the first change is at 14.
+        assert_eq!(sky_ticks_per_block() % 7_104, 64);
+        assert_eq!(transitions.len(), PlanetId::ALL.len());
+        assert!(transitions.iter().all(|e| e.height == 14));
+        let mars = transitions
+            .iter()
+            .find(|e| e.payload["planet"] == "mars")
+            .expect("mars transition");
+        assert!(mars.payload["slide"].as_str().unwrap().starts_with("mars-"));
+
+        // Sky events come from the height alone: the bus carries exactly the
+        // ones `sky_event_at` draws, and status looks ahead from the tip.
+        let published: Vec<_> = gateway
+            .events()
+            .since(0)
+            .into_iter()
+            .filter(|e| e.kind == "sky_event")
+            .map(|e| {
+                (
+                    e.height,
+                    e.payload["planet"].clone(),
+                    e.payload["kind"].clone(),
+                )
+            })
+            .collect();
+        let drawn: Vec<_> = (1..=20)
+            .flat_map(|height| {
+                PlanetId::ALL
+                    .into_iter()
+                    .filter_map(move |planet| sky_event_at(planet, height, sky_ticks_per_block()))
+            })
+            .map(|e| {
+                let json = serde_json::to_value(&e).unwrap();
+                (e.height, json["planet"].clone(), json["kind"].clone())
+            })
+            .collect();
+        assert!(!drawn.is_empty());
+        assert_eq!(published, drawn);
+        let status = gateway.status();
+        assert!(status
+            .planets
+            .iter()
+            .flat_map(|p| &p.sky_events)
+            .all(|e| (21..21 + SKY_EVENT_LOOKAHEAD).contains(&e.height)));
+    }
+
+    #[test]
+    fn round_robin_nodes_follow_each_others_blocks() {
+        let node = |id: &str, key: &str| {
+            OmegaGateway::with_store(GatewayStore::default())
+                .unwrap()
+                .with_consensus(
+                    id.into(),
+                    Box::new(corelib::consensus::PeerSigned {
+                        policy: corelib::consensus::RoundRobin {
+                            nodes: vec!["a".into(), "b".into()],
+                        },
+                        signer: KeyedSigner::from_secret(key),
+                    }),
+                )
+                .with_peer_key(KeyedSigner::from_secret(key))
+        };
+        let (a, b) = (node("a", "peers"), node("b", "peers"));
+
+        // Height 1 belongs to b. Without the peer key neither a bare frame
+        // nor an outsider's correctly named block gets in.
+        assert!(!a.may_produce() && b.may_produce());
+        let intruder = node("b", "guess");
+        let forged = ConsensusMessage::Proposal {
+            header: intruder.seal_block().header,
+        };
+        let peer_session = |to: &OmegaGateway, from: &OmegaGateway| {
+            to.handle_handshake(from.peer_handshake(), SessionGrant::default())
+                .map(|response| response.session_id)
+        };
+        assert!(matches!(
+            peer_session(&a, &intruder),
+            Err(DlogError::Auth(AuthError::Forbidden(_)))
+        ));
+        let (on_a, on_b) = (peer_session(&a, &b).unwrap(), peer_session(&b, &a).unwrap());
+        let mut unsigned = b.consensus_frame(&on_a, &forged);
+        unsigned.payload.as_object_mut().unwrap().remove("peer_mac");
+        assert!(!a.handle_frame(unsigned).accepted);
+        assert!(
+            !a.handle_frame(intruder.consensus_frame(&on_a, &forged))
+                .accepted
+        );
+        let ConsensusMessage::Proposal { header: forged } = forged else {
+            unreachable!()
+        };
+        assert!(a.accept_block(forged).is_err());
+        assert_eq!(a.block_height(), 0);
+
+        let header = b.seal_block().header;
+        assert!(
+            a.handle_frame(b.consensus_frame(
+                &on_a,
+                &ConsensusMessage::Proposal {
+                    header: header.clone(),
+                }
+            ))
+            .accepted
+        );
+        assert_eq!(a.tip(), Some(header.clone()));
+        b.handle_frame(a.consensus_frame(
+            &on_b,
+            &ConsensusMessage::Acceptance {
+                height: 1,
+                root: header.root.clone(),
+                node: "a".into(),
+            },
+        ));
+        assert_eq!(b.status().consensus.acceptances, ["a"]);
+
+        // b producing out of turn is refused; the proposal is replayed as stale.
+        let out_of_turn = b.seal_block().header;
+        assert!(matches!(
+            a.accept_block(out_of_turn),
+            Err(FrameError::Conflict(_))
+        ));
+        let root = header.root.clone();
+        assert!(matches!(
+            a.accept_block(header),
+            Err(FrameError::StaleHeight {
+                height: 1,
+                current: 1
+            })
+        ));
+        assert!(a.may_produce());
+        assert_eq!(a.seal_block().header.prev_root, root);
+    }
+
+    #[test]
+    fn forget_phone_tombstones_labels_and_keeps_supply() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path()))
+            .unwrap()
+            .with_privacy(PhonePrivacy::with_salt("pepper"));
+        gateway
+            .register_alias("luke", ";9132077554;fun;", "9132077554", true)
+            .unwrap();
+        let supply_before: u128 = gateway.services.banking.bank().ledgers()[&PlanetId::Earth]
+            .values()
+            .sum();
+        let inbox = gateway.events().inbox();
+        for label in [";9132077554;fun;", ";5550000000;fun;"] {
+            let label = gateway.privacy.label_key(label);
+            inbox.deliver(&label, "transfer", 1, 1, Value::Null);
+        }
+        let policy = GuildPolicy {
+            approval_threshold: 100,
+            approvals: 1,
+            treasurer_cap: 100,
+        };
+        let founders = [";9132077554;comet;", ";5550000000;main;"];
+        let charter = GuildCharter {
+            name: "miners".into(),
+            founders: founders.iter().map(|f| f.to_string()).collect(),
+            policy,
+        };
+        gateway.found_guild("9132077554", charter).unwrap();
+        let key = |label: &str| gateway.privacy.label_key(label);
+        for (label, guardian) in [
+            (";9132077554;fun;", ";5550000000;main;"),
+            (";5550000000;main;", ";9132077554;comet;"),
+            (";5550000000;fun;", ";9132077554;comet;"),
+        ] {
+            gateway
+                .recoveries
+                .set_guardians(GuardianSet {
+                    label: key(label),
+                    guardians: vec![key(guardian), key(";7770000000;x;")],
+                    threshold: 2,
+                    delay_blocks: 5,
+                })
+                .unwrap();
+        }
+        let open = gateway
+            .recoveries
+            .request(&key(";5550000000;fun;"), &key(";6660000000;fun;"), 1)
+            .unwrap();
+        for (phone, label) in [
+            ("9132077554", ";9132077554;comet;"),
+            ("5550000000", ";5550000000;main;"),
+        ] {
+            let update = CalibrationUpdate {
+                device: "gamepad".into(),
+                movement: None,
+                look: None,
+            };
+            gateway.calibrate(phone, label, update).unwrap();
+        }
+
+        let receipt = gateway.forget_phone("9132077554");
+        assert_eq!(receipt.aliases_purged, vec!["luke".to_string()]);
+        assert_eq!(receipt.notifications_purged, 1);
+        assert_eq!(gateway.purge_notifications("9132077554", true), 0);
+        assert_eq!(gateway.purge_notifications("5550000000", true), 1);
+        assert_eq!(receipt.guilds_left, vec!["miners".to_string()]);
+        assert_eq!(receipt.guardian_sets_purged, 3);
+        assert!(gateway
+            .recoveries
+            .guardians(&key(";9132077554;fun;"))
+            .is_none());
+        let kept = gateway
+            .recoveries
+            .guardians(&key(";5550000000;main;"))
+            .unwrap();
+        assert_eq!(
+            (kept.guardians, kept.threshold),
+            (vec![key(";7770000000;x;")], 1)
+        );
+        let cancelled = gateway.recoveries.get(open.id).unwrap();
+        assert_eq!(cancelled.status, crate::recovery::RecoveryStatus::Cancelled);
+        assert!(cancelled.guardians.contains(&receipt.tombstone_label));
+        assert_eq!(receipt.calibrations_purged, 1);
+        assert!(gateway
+            .calibrations
+            .profile(&key(";9132077554;comet;"))
+            .is_empty());
+        assert_eq!(
+            gateway
+                .calibrations
+                .profile(&key(";5550000000;main;"))
+                .len(),
+            1
+        );
+        let members = &gateway.guild("5550000000", "miners").unwrap().guild.members;
+        assert_eq!(
+            members.keys().cloned().collect::<Vec<_>>(),
+            vec![gateway.privacy.label_key(";5550000000;main;")]
+        );
+        assert_eq!(receipt.labels_tombstoned, vec!["comet", "fun", "vortex1"]);
+        assert_eq!(receipt.supply_after, supply_before);
+        assert!(!receipt.subject_digest.contains("9132077554"));
+        assert!(gateway.lookup_alias("luke").is_none());
+
+        let bank = gateway.services.banking.bank();
+        let ledger = &bank.ledgers()[&PlanetId::Earth];
+        assert_eq!(ledger.len(), 1);
+        assert!(ledger.contains_key(&receipt.tombstone_label));
+    }
+
+    /// Session for the seeded phone that owns `;9132077554;…;` labels.
+    fn handshake(gateway: &OmegaGateway, planet: PlanetId, capabilities: &[&str]) -> String {
+        gateway
+            .handle_handshake(
+                HandshakeRequest {
+                    client_id: "c".into(),
+                    capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+                    requested_routes: Vec::new(),
+                    phone: None,
+                    session_token: None,
+                    planet,
+                    peer_mac: None,
+                    session_kind: SessionKind::Player,
+                },
+                SessionGrant {
+                    phone: Some("9132077554".into()),
+                    admin: false,
+                },
+            )
+            .unwrap()
+            .session_id
+    }
+
+    #[test]
+    fn replicas_serve_reads_and_relay_writes_to_the_leader() {
+        let peered = || {
+            OmegaGateway::with_store(GatewayStore::default())
+                .unwrap()
+                .with_peer_key(KeyedSigner::from_secret("k"))
+        };
+        let (leader, replica) = (peered(), peered());
+        leader.freeze_interest();
+        leader.seal_block();
+        let pull = || replica.ingest_replica(leader.replica_state(replica.last_transfer_id()));
+        pull();
+        assert_eq!(replica.block_height(), 1);
+        assert_eq!(replica.tip(), leader.tip());
+
+        let transfer = replica.sign(FrameEnvelope {
+            session_id: handshake(&replica, PlanetId::Earth, &["banking"]),
+            seq: 7,
+            namespace: ";bank;".into(),
+            kind: FrameKind::Event,
+            payload: serde_json::json!({
+                "kind": "transfer",
+                "from": ";9132077554;comet;",
+                "to": ";9132077554;fun;",
+                "amount": 10,
+            }),
+            signature: None,
+        });
+        assert!(OmegaGateway::is_bank_write(&transfer));
+        let leader_session = leader
+            .handle_handshake(
+                replica.peer_handshake(),
+                SessionGrant {
+                    phone: None,
+                    admin: false,
+                },
+            )
+            .unwrap()
+            .session_id;
+        let relay = replica.relay_frame(&transfer, &leader_session).unwrap();
+        let mut forged = relay.clone();
+        forged.payload["payload"]["amount"] = 1_000.into();
+        assert!(!leader.handle_frame(forged).accepted);
+        let ack = leader.handle_frame(relay);
+        assert!(ack.accepted);
+        assert!(ack.notes.iter().any(|n| n.contains("bank::transfer 10")));
+
+        // The replica never applies the write; it sees it on the next pull.
+        let comet = |gateway: &OmegaGateway| {
+            gateway
+                .services
+                .banking
+                .balance_of(PlanetId::Earth, ";9132077554;comet;")
+        };
+        assert_eq!(comet(&replica), 1_000_000);
+        pull();
+        pull();
+        assert_eq!(comet(&replica), 999_990);
+        assert_eq!(replica.services.banking.transfers.records().len(), 1);
+        assert_eq!(
+            replica.status().planets[0].bank_root,
+            leader.status().planets[0].bank_root
+        );
+    }
+
+    #[test]
+    fn quotas_follow_the_service_and_the_verified_phone() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_quotas(FrameQuotas::new(crate::quota::parse(";∞;bank;=2").unwrap()));
+        let frame = |session_id: &str, namespace: &str| FrameEnvelope {
+            session_id: session_id.into(),
+            seq: 1,
+            namespace: namespace.into(),
+            kind: FrameKind::Query,
+            payload: serde_json::json!({ "kind": "balance_query", "label": ";9132077554;fun;" }),
+            signature: None,
+        };
+        // A fresh session and an unrelated namespace still hit the same bucket.
+        let first = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let second = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        assert!(
+            gateway
+                .handle_frame(gateway.sign(frame(&first, ";bank;")))
+                .accepted
+        );
+        assert!(
+            gateway
+                .handle_frame(gateway.sign(frame(&second, ";sky;")))
+                .accepted
+        );
+        let third = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let ack = gateway.handle_frame(gateway.sign(frame(&third, ";anything;")));
+        assert!(!ack.accepted);
+        assert!(ack
+            .notes
+            .iter()
+            .any(|n| n.starts_with("quota ;∞;bank; exceeded")));
+        assert_eq!(gateway.status().quotas.rejections[";∞;bank;"], 1);
+    }
+
+    #[test]
+    fn sessions_are_partitioned_by_planet() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let earth = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let moon = handshake(&gateway, PlanetId::Moon, &["banking", "game"]);
+        let frame = |session_id: &str, kind, payload| FrameEnvelope {
+            session_id: session_id.into(),
+            seq: 1,
+            namespace: ";bank;".into(),
+            kind,
+            payload,
+            signature: None,
+        };
+        let balance = serde_json::json!({ "kind": "balance_query", "label": ";9132077554;fun;" });
+
+        let earth_notes = gateway
+            .handle_frame(gateway.sign(frame(&earth, FrameKind::Query, balance.clone())))
+            .notes;
+        assert!(!earth_notes.iter().any(|n| n.ends_with("= 0")));
+        let moon_notes = gateway
+            .handle_frame(gateway.sign(frame(&moon, FrameKind::Query, balance)))
+            .notes;
+        assert!(moon_notes.iter().any(|n| n.ends_with("= 0")));
+
+        gateway.handle_frame(gateway.sign(frame(&moon, FrameKind::TickFrame, Value::Null)));
+        let status = gateway.status();
+        let moon_status = status
+            .planets
+            .iter()
+            .find(|p| p.planet == PlanetId::Moon)
+            .unwrap();
+        assert_eq!((moon_status.sessions, moon_status.game_ticks), (1, 1));
+        assert_eq!(status.planets[0].bank_labels, 3);
+
+        let echo = gateway.process_bridge_position(BridgePositionSnapshot {
+            player_uuid: "p".into(),
+            session_id: Some(moon.clone()),
+            stand_id: None,
+            world: "mars_shell".into(),
+            pos: Vec3f {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            velocity: None,
+            rotation: None,
+        });
+        assert!(matches!(
+            &echo[..],
+            [BridgeInstruction {
+                action: BridgeAction::Echo { .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn transfers_publish_amount_but_not_labels() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let stranger = gateway
+            .handle_handshake(
+                HandshakeRequest {
+                    client_id: "s".into(),
+                    capabilities: vec!["banking".into()],
+                    requested_routes: Vec::new(),
+                    phone: None,
+                    session_token: None,
+                    planet: PlanetId::Earth,
+                    peer_mac: None,
+                    session_kind: SessionKind::Player,
+                },
+                SessionGrant {
+                    phone: Some("5550000000".into()),
+                    admin: false,
+                },
+            )
+            .unwrap()
+            .session_id;
+        let transfer = |amount: u64| FrameEnvelope {
+            session_id: session.clone(),
+            seq: 1,
+            namespace: ";bank;".into(),
+            kind: FrameKind::Event,
+            payload: serde_json::json!({
+                "kind": "transfer",
+                "from": ";9132077554;comet;",
+                "to": ";9132077554;fun;",
+                "amount": amount,
+            }),
+            signature: None,
+        };
+        gateway.handle_frame(gateway.sign(transfer(0)));
+        // Another phone's session can't spend from the seeded labels.
+        let stolen = gateway.handle_frame(gateway.sign(FrameEnvelope {
+            session_id: stranger,
+            ..transfer(5)
+        }));
+        assert!(stolen
+            .notes
+            .iter()
+            .any(|n| n.contains("is not owned by this session")));
+        gateway.handle_frame(gateway.sign(transfer(88)));
+
+        let events = gateway.events().since(0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, "transfer");
+        assert_eq!(events[0].payload["amount"], 88);
+        assert!(!events[0].payload.to_string().contains("9132077554"));
+    }
+
+    #[test]
+    fn bank_frames_are_checked_against_the_session_frame_key() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_frame_signatures(SignaturePolicy::Require);
+        let open = || {
+            gateway
+                .handle_handshake(
+                    HandshakeRequest {
+                        client_id: "c".into(),
+                        capabilities: vec!["banking".into()],
+                        requested_routes: Vec::new(),
+                        phone: None,
+                        session_token: None,
+                        planet: PlanetId::Earth,
+                        peer_mac: None,
+                        session_kind: SessionKind::Player,
+                    },
+                    SessionGrant {
+                        phone: Some("9132077554".into()),
+                        admin: false,
+                    },
+                )
+                .unwrap()
+        };
+        let (session, other) = (open(), open());
+        assert_ne!(session.frame_key, other.frame_key);
+        let balance = FrameEnvelope {
+            session_id: session.session_id.clone(),
+            seq: 1,
+            namespace: ";∞;bank;".into(),
+            kind: FrameKind::Query,
+            payload: serde_json::json!({
+                "kind": "balance_query",
+                "label": ";9132077554;comet;",
+            }),
+            signature: None,
+        };
+
+        let unsigned = gateway.handle_frame(balance.clone());
+        assert!(!unsigned.accepted);
+        assert!(unsigned
+            .notes
+            .contains(&"Query rejected: unsigned frame".to_string()));
+        let stolen = gateway.handle_frame(frame_signing::signed(balance.clone(), &other.frame_key));
+        assert!(!stolen.accepted);
+        let signed_balance = frame_signing::signed(balance.clone(), &session.frame_key);
+        let signed = gateway.handle_frame(signed_balance.clone());
+        assert!(signed.accepted, "{:?}", signed.notes);
+
+        // A captured frame doesn't run twice, and seqs only move forward.
+        let replayed = gateway.handle_frame(signed_balance);
+        assert!(!replayed.accepted);
+        assert!(replayed
+            .notes
+            .contains(&"Query rejected: replayed seq 1 (last signed seq 1)".to_string()));
+        let next = FrameEnvelope {
+            seq: 2,
+            ..balance.clone()
+        };
+        assert!(
+            gateway
+                .handle_frame(frame_signing::signed(next.clone(), &session.frame_key))
+                .accepted
+        );
+        assert!(
+            !gateway
+                .handle_frame(frame_signing::signed(balance.clone(), &session.frame_key))
+                .accepted
+        );
+        // A frame that doesn't verify can't push the seq forward either.
+        let forged = FrameEnvelope {
+            seq: 1_000,
+            ..frame_signing::signed(next, &other.frame_key)
+        };
+        assert!(!gateway.handle_frame(forged).accepted);
+        let third = FrameEnvelope {
+            seq: 3,
+            ..balance.clone()
+        };
+        assert!(
+            gateway
+                .handle_frame(frame_signing::signed(third, &session.frame_key))
+                .accepted
+        );
+
+        // Flagging lets reads from clients that predate frame keys through,
+        // with a note.
+        let lenient = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_frame_signatures(SignaturePolicy::Flag);
+        let ack = lenient.handle_frame(FrameEnvelope {
+            session_id: handshake(&lenient, PlanetId::Earth, &["banking"]),
+            ..balance
+        });
+        assert!(ack.accepted);
+        assert!(ack
+            .notes
+            .iter()
+            .any(|n| n.starts_with("Query unsigned: flagged")));
+    }
+
+    #[test]
+    fn batch_transfers_pay_every_entry_or_none() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        gateway.services.banking.freeze_interest();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let batch = |entries: Value| {
+            let frame = FrameEnvelope {
+                session_id: session.clone(),
+                seq: 1,
+                namespace: ";bank;".into(),
+                kind: FrameKind::Event,
+                payload: serde_json::json!({
+                    "kind": "batch_transfer",
+                    "from": ";9132077554;vortex1;",
+                    "memo": "pool payout",
+                    "entries": entries,
+                }),
+                signature: None,
+            };
+            gateway.handle_frame(gateway.sign(frame))
+        };
+        let bank = &gateway.services.banking;
+        let balance = |label: &str| bank.balance_of(PlanetId::Earth, label);
+
+        // The total overdraws, and entry 1 is bad: nothing moves either way.
+        let overdrawn = batch(serde_json::json!([
+            { "to": ";9132077554;fun;", "amount": 4_000_000 },
+            { "to": ";9132077554;comet;", "amount": 2_000_000 },
+        ]));
+        assert!(overdrawn.notes.iter().any(|n| n.contains("insufficient")));
+        let bad = batch(serde_json::json!([
+            { "to": ";9132077554;fun;", "amount": 10 },
+            { "to": ";9132077554;comet;", "amount": 0 },
+        ]));
+        assert!(bad.notes.iter().any(|n| n.contains("entry 1: amount=0")));
+        assert_eq!(balance(";9132077554;vortex1;"), 5_000_000);
+        assert!(bank.transfers.records().is_empty());
+
+        let paid = batch(serde_json::json!([
+            { "to": ";9132077554;fun;", "amount": 300, "tag": "payroll" },
+            { "to": ";9132077554;comet;", "amount": 700 },
+        ]));
+        // After the session note: the batch, then one line per payout.
+        let notes = &paid.notes[1..];
+        assert_eq!(notes.len(), 3, "{:?}", paid.notes);
+        assert!(notes[0].contains("1000") && notes[0].contains("2 payouts ok"));
+        assert!(notes[2].starts_with("bank::batch_transfer[1] 700"));
+        assert_eq!(balance(";9132077554;vortex1;"), 5_000_000 - 1000);
+        assert_eq!(balance(";9132077554;fun;"), 80_000 + 300);
+        assert_eq!(balance(";9132077554;comet;"), 1_000_000 + 700);
+
+        let records = bank.transfers.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            (records[0].to.as_str(), records[0].amount),
+            (BATCH_LABEL, 1000)
+        );
+        assert_eq!(records[0].batch[0].meta.tag.as_deref(), Some("payroll"));
+        assert_eq!(
+            bank.transfers.history(";9132077554;comet;"),
+            [records[0].id]
+        );
+        let events = gateway.events().since(0);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.kind == "transfer"));
+    }
+
+    #[test]
+    fn strict_labels_refuse_typos_and_archived_labels() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path()))
+            .unwrap()
+            .with_strict_labels(true);
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let send = |to: &str| {
+            gateway
+                .handle_frame(gateway.sign(FrameEnvelope {
+                    session_id: session.clone(),
+                    seq: 1,
+                    namespace: ";bank;".into(),
+                    kind: FrameKind::Event,
+                    payload: serde_json::json!({
+                        "kind": "transfer",
+                        "from": ";9132077554;comet;",
+                        "to": to,
+                        "amount": 100,
+                    }),
+                    signature: None,
+                }))
+                .notes
+                .join(" | ")
+        };
+
+        // Seeded labels predate the book and still receive.
+        assert!(send(";9132077554;fun;").contains("ok"));
+        assert!(send(";9132077554;rnet;").contains("is not an open label"));
+        let rent = gateway.create_label("9132077554", "Rent").unwrap();
+        assert_eq!(
+            (rent.label.as_str(), rent.status),
+            (";9132077554;rent;", LabelStatus::Open)
+        );
+        assert!(send(";9132077554;rent;").contains("ok"));
+
+        let err = gateway.archive_label("9132077554", "rent").unwrap_err();
+        assert_eq!(err.status(), 409);
+        assert_eq!(
+            gateway
+                .archive_label("9132077554", "nope")
+                .unwrap_err()
+                .status(),
+            404
+        );
+        gateway.archive_label("9132077554", "vortex1").unwrap_err();
+
+        let listed = gateway.labels_for("9132077554");
+        let status = |name: &str| {
+            listed
+                .iter()
+                .find(|l| l.label == format!(";9132077554;{name};"))
+                .map(|l| l.status)
+        };
+        assert_eq!(status("rent"), Some(LabelStatus::Open));
+        assert_eq!(status("comet"), Some(LabelStatus::Implicit));
+        assert_eq!(status("rnet"), None);
+        assert!(gateway.labels_for("5550000000").is_empty());
+
+        // An empty label can be archived, then refuses deposits; the book
+        // survives a restart.
+        gateway.create_label("9132077554", "spare").unwrap();
+        gateway.archive_label("9132077554", "spare").unwrap();
+        assert!(send(";9132077554;spare;").contains("is not an open label"));
+        let restarted = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let spare = restarted
+            .labels_for("9132077554")
+            .into_iter()
+            .find(|l| l.label == ";9132077554;spare;")
+            .unwrap();
+        assert_eq!(spare.status, LabelStatus::Archived);
+    }
+
+    #[test]
+    fn transfer_memos_persist_and_search() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let transfer = |memo: &str, tag: &str| {
+            gateway.handle_frame(gateway.sign(FrameEnvelope {
+                session_id: session.clone(),
+                seq: 1,
+                namespace: ";bank;".into(),
+                kind: FrameKind::Event,
+                payload: serde_json::json!({
+                    "kind": "transfer",
+                    "from": ";9132077554;comet;",
+                    "to": ";9132077554;fun;",
+                    "amount": 10,
+                    "memo": memo,
+                    "tag": tag,
+                }),
+                signature: None,
+            }))
+        };
+        transfer("March rent", "rent");
+        transfer("pizza night", "food");
+        let rejected = transfer("lunch at cafe.com", "food");
+        assert!(rejected
+            .notes
+            .iter()
+            .any(|n| n.contains("invalid transfer memo")));
+
+        let search = |memo: Option<&str>, tag: Option<&str>| TransferSearch {
+            label: String::new(),
+            planet: None,
+            memo: memo.map(String::from),
+            tag: tag.map(String::from),
+            heights: 0..=u64::MAX,
+            limit: 10,
+        };
+        let rent = gateway
+            .search_transfers("9132077554", ";9132077554;fun;", search(Some("rent"), None))
+            .unwrap();
+        assert_eq!(rent.len(), 1);
+        assert_eq!(rent[0].meta.tag.as_deref(), Some("rent"));
+        assert!(gateway
+            .search_transfers("5550000000", ";9132077554;fun;", search(None, None))
+            .is_err());
+
+        // A restarted gateway reloads the history, newest first.
+        let reloaded = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let all = reloaded
+            .search_transfers("9132077554", ";9132077554;comet;", search(None, None))
+            .unwrap();
+        let memos: Vec<_> = all.iter().map(|r| r.meta.memo.as_deref()).collect();
+        assert_eq!(memos, [Some("pizza night"), Some("March rent")]);
+    }
+
+    #[test]
+    fn faucet_grants_mint_into_owned_labels_once_a_day() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        assert!(gateway.faucet_status().is_none());
+        assert!(gateway
+            .faucet_claim("9132077554", ";9132077554;fun;", None)
+            .is_err());
+
+        let gateway = gateway.with_faucet(FaucetConfig {
+            planet: PlanetId::Moon,
+            amount: 888,
+            daily_budget: 10_000,
+            per_ip: 1,
+        });
+        assert!(gateway
+            .faucet_claim("9132077554", ";5550001111;main;", None)
+            .is_err());
+        let grant = gateway
+            .faucet_claim("9132077554", ";9132077554;fun;", Some("10.0.0.1"))
+            .unwrap();
+        assert_eq!((grant.planet, grant.amount), (PlanetId::Moon, 888));
+        let bank = &gateway.services.banking;
+        assert_eq!(bank.balance_of(PlanetId::Moon, ";9132077554;fun;"), 888);
+        assert_eq!(bank.transfers.records()[0].from, FAUCET_LABEL);
+        assert!(gateway
+            .faucet_claim("9132077554", ";9132077554;comet;", None)
+            .is_err());
+        assert_eq!(
+            gateway.faucet_status().unwrap().remaining_today,
+            10_000 - 888
+        );
+    }
+
+    #[test]
+    fn mining_results_that_meet_the_difficulty_pay_the_job_label() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_mining_difficulty(4);
+        let miner = handshake(&gateway, PlanetId::Moon, &["mining"]);
+        let frame = |kind, payload| FrameEnvelope {
+            session_id: miner.clone(),
+            seq: 1,
+            namespace: ";∞;mining;".into(),
+            kind,
+            payload,
+            signature: None,
+        };
+        let noted = |ack: FrameAck, prefix: &str| {
+            ack.notes
+                .into_iter()
+                .find(|note| note.starts_with(prefix))
+                .unwrap_or_else(|| panic!("no `{prefix}` note"))
+        };
+        let not_ours = serde_json::json!({ "label": ";5550001111;main;" });
+        noted(
+            gateway.handle_frame(gateway.sign(frame(FrameKind::MineJob, not_ours))),
+            "mining::job rejected",
+        );
+        let ours = serde_json::json!({ "label": ";9132077554;fun;" });
+        let note = noted(
+            gateway.handle_frame(gateway.sign(frame(FrameKind::MineJob, ours))),
+            "mining::job ",
+        );
+        // "mining::job <id> challenge <challenge> difficulty 4 …"
+        let words: Vec<&str> = note.split(' ').collect();
+        let (job_id, challenge) = (words[1].parse::<u64>().unwrap(), words[3]);
+        let solves = |nonce: &u64| {
+            corelib::shaless_leading_zeros(format!("{challenge};{nonce}").as_bytes()) >= 4
+        };
+        let nonce = (0..).find(solves).unwrap();
+        let miss = (0..).find(|n| !solves(n)).unwrap();
+        let result = |nonce: u64| {
+            frame(
+                FrameKind::MineResult,
+                serde_json::json!({ "job_id": job_id, "nonce": nonce }),
+            )
+        };
+
+        noted(
+            gateway.handle_frame(gateway.sign(result(miss))),
+            "mining::result rejected",
+        );
+        noted(
+            gateway.handle_frame(gateway.sign(result(nonce))),
+            "mining::result job",
+        );
+        let bank = &gateway.services.banking;
+        assert_eq!(bank.balance_of(PlanetId::Moon, ";9132077554;fun;"), REWARD);
+        assert_eq!(bank.transfers.records()[0].from, MINING_LABEL);
+        // A job pays once.
+        noted(
+            gateway.handle_frame(gateway.sign(result(nonce))),
+            "mining::result rejected",
+        );
+
+        let status = gateway.status().mining;
+        assert_eq!((status.difficulty, status.queue_depth), (4, 0));
+        assert_eq!((status.accepted, status.rejected), (1, 2));
+        assert!(status.hash_rate > 0.0);
+        assert_eq!(gateway.services.mining.take_results(), 1);
+        assert!(OmegaGateway::is_bank_write(&result(nonce)));
+    }
+
+    #[test]
+    fn burns_take_dlog_out_of_supply_and_count_it() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_burn_hooks(BurnHooks::parse("portal_toll=21,auction_fee=2.5%").unwrap());
+        let fun = ";9132077554;fun;";
+        let supply = |gateway: &OmegaGateway| gateway.overview().bank.supply[&PlanetId::Earth];
+        let before = supply(&gateway);
+
+        let toll = gateway
+            .burn("9132077554", fun, PlanetId::Earth, "portal_toll", None)
+            .unwrap();
+        let fee = gateway
+            .burn(
+                "9132077554",
+                fun,
+                PlanetId::Earth,
+                "auction_fee",
+                Some(1_000),
+            )
+            .unwrap();
+        assert_eq!((toll.burned, fee.burned, fee.burned_total), (21, 25, 46));
+        assert_eq!(supply(&gateway), before - 46);
+        let bank = &gateway.services.banking;
+        assert_eq!(bank.balance_of(PlanetId::Earth, fun), 80_000 - 46);
+        assert_eq!(bank.transfers.records()[1].to, BURN_LABEL);
+
+        // Other people's labels, unknown hooks and overdrafts burn nothing.
+        assert!(gateway
+            .burn("5550001111", fun, PlanetId::Earth, "portal_toll", None)
+            .is_err());
+        assert!(gateway
+            .burn("9132077554", fun, PlanetId::Earth, "teleport", None)
+            .is_err());
+        assert!(gateway
+            .burn("9132077554", fun, PlanetId::Moon, "portal_toll", None)
+            .is_err());
+        assert_eq!(gateway.overview().bank.burned[&PlanetId::Earth], 46);
+        let earth = &gateway.compounding().zones[0];
+        assert_eq!((earth.planet, earth.burned), (PlanetId::Earth, 46));
+        assert_eq!(
+            gateway.burn_status().burned[&PlanetId::Earth].by_action["auction_fee"],
+            25
+        );
+        assert_eq!(
+            gateway.replica_state(0).burned,
+            gateway.burn_status().burned
+        );
+    }
+
+    #[test]
+    fn cross_zone_transfers_convert_and_log_both_legs() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let transfer = |to_planet: &str, amount: u64| {
+            gateway.handle_frame(gateway.sign(FrameEnvelope {
+                session_id: session.clone(),
+                seq: 1,
+                namespace: ";bank;".into(),
+                kind: FrameKind::Event,
+                payload: serde_json::json!({
+                    "kind": "transfer",
+                    "from": ";9132077554;comet;",
+                    "to": ";9132077554;fun;",
+                    "amount": amount,
+                    "to_planet": to_planet,
+                    "memo": "moon fund",
+                }),
+                signature: None,
+            }))
+        };
+        transfer("moon", 100_000);
+        let bank = &gateway.services.banking;
+        let quote =
+            exchange::quote(&PlanetId::Earth.zone(), &PlanetId::Moon.zone(), 100_000).unwrap();
+        assert_eq!(quote.fee, 382);
+        assert_eq!(
+            bank.balance_of(PlanetId::Earth, ";9132077554;comet;"),
+            900_000
+        );
+        assert_eq!(bank.balance_of(PlanetId::Earth, ";9132077554;fun;"), 80_000);
+        assert_eq!(
+            bank.balance_of(PlanetId::Moon, ";9132077554;fun;"),
+            quote.received
+        );
+
+        let legs = bank.transfers.records();
+        assert_eq!(legs.len(), 2);
+        let (sent, received) = (&legs[0], &legs[1]);
+        assert_eq!((sent.planet, sent.amount), (PlanetId::Earth, 100_000));
+        assert_eq!(
+            (received.planet, received.amount),
+            (PlanetId::Moon, quote.received)
+        );
+        assert_eq!(sent.to, EXCHANGE_LABEL);
+        assert_eq!(received.from, EXCHANGE_LABEL);
+        assert_eq!(sent.exchange.as_ref().unwrap().pair, received.id);
+        assert_eq!(received.exchange.as_ref().unwrap().pair, sent.id);
+        assert_eq!(received.meta.memo.as_deref(), Some("moon fund"));
+
+        for bad in [transfer("pluto", 10), transfer("sun", 1)] {
+            assert!(bad
+                .notes
+                .iter()
+                .any(|n| n.contains("bank::transfer rejected")));
+        }
+        assert_eq!(bank.transfers.records().len(), 2);
+    }
+
+    #[test]
+    fn sealing_refolds_touched_labels_into_label_files() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        gateway.freeze_interest();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        for _ in 0..2 {
+            gateway.handle_frame(gateway.sign(FrameEnvelope {
+                session_id: session.clone(),
+                seq: 1,
+                namespace: ";bank;".into(),
+                kind: FrameKind::Event,
+                payload: serde_json::json!({
+                    "kind": "transfer",
+                    "from": ";9132077554;comet;",
+                    "to": ";9132077554;fun;",
+                    "amount": 10,
+                }),
+                signature: None,
+            }));
+        }
+        let fun = LabelFile::new("9132077554", "fun");
+        assert!(gateway.store.load_label_file(&fun).is_none());
+
+        gateway.seal_block();
+        let on_disk = gateway.store.load_label_file(&fun).unwrap();
+        assert_eq!(on_disk.balances[&PlanetId::Earth], 80_020);
+        assert_eq!(on_disk.history, ["t1", "t2"]);
+        let vortex = LabelFile::new("9132077554", "vortex1");
+        assert!(gateway.store.load_label_file(&vortex).is_none());
+
+        // Hand-set key metadata survives the next refold.
+        let mut keyed = on_disk.clone();
+        keyed.keys.insert("device".into(), "ph01".into());
+        gateway.store.save_label_file(&keyed).unwrap();
+        gateway
+            .services
+            .banking
+            .credit(PlanetId::Moon, ";9132077554;fun;", 5);
+        gateway.seal_block();
+        let on_disk = gateway.store.load_label_file(&fun).unwrap();
+        assert_eq!(on_disk.balances[&PlanetId::Moon], 5);
+        assert_eq!(on_disk.keys["device"], "ph01");
+
+        gateway.forget_phone("9132077554");
+        assert!(gateway.store.load_label_file(&fun).is_none());
+    }
+
+    #[test]
+    fn invariants_hold_through_payments_and_catch_a_skewed_ledger() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let bank = &gateway.services.banking;
+        bank.credit(PlanetId::Moon, ";5550001111;main;", 500);
+        bank.debit(PlanetId::Earth, ";9132077554;fun;", 80).unwrap();
+        gateway.seal_block();
+        gateway
+            .open_escrow(
+                "9132077554",
+                EscrowRequest {
+                    planet: PlanetId::Earth,
+                    offer: EscrowSide {
+                        label: ";9132077554;comet;".into(),
+                        amount: 30_000,
+                        item: None,
+                    },
+                    ask: EscrowSide {
+                        label: ";5550001111;main;".into(),
+                        amount: 10,
+                        item: None,
+                    },
+                    deadline_blocks: 4,
+                },
+            )
+            .unwrap();
+        let report = gateway.check_invariants();
+        assert!(report.ok(), "{:?}", report.violations);
+        assert_eq!(report.checked["supply"], PlanetId::ALL.len());
+
+        bank.bank()
+            .inject_unbooked(PlanetId::Mars, ";5550001111;main;", 7);
+        let report = gateway.check_invariants();
+        assert_eq!(report.violations.len(), 1);
+        let broken = &report.violations[0];
+        assert_eq!(
+            (broken.invariant.as_str(), broken.subject.as_str()),
+            ("supply", "mars")
+        );
+        assert_eq!(broken.expected, serde_json::json!(0));
+        assert_eq!(broken.actual, serde_json::json!(7));
+    }
+
+    #[test]
+    fn bootstrap_applies_once_and_dry_runs_show_the_diff() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let plan = BootstrapPlan::parse(
+            "planet ;earth;\nplanet ;moon;\nworld ;moon_dome;moon;0;160;surf;\n\
+             label ;5550002222;vault;moon:40;\n\
+             dns ;∞;bank;infinity; ;omega;bank;vault; Vault bank\n",
+        );
+        assert!(plan.is_clean(), "{:?}", plan.issues);
+        let version = gateway.services.dns.lock().unwrap().version;
+
+        let preview = gateway.apply_bootstrap(&plan, true).unwrap();
+        let sections: Vec<&str> = preview.changes.iter().map(|c| c.section).collect();
+        assert_eq!(sections, ["planets", "world", "label", "dns"]);
+        assert!(gateway.world_settings().planets.is_empty());
+
+        let applied = gateway.apply_bootstrap(&plan, false).unwrap();
+        assert_eq!(applied.changes, preview.changes);
+        assert_eq!(
+            gateway
+                .services
+                .banking
+                .balance_of(PlanetId::Moon, ";5550002222;vault;"),
+            40
+        );
+        assert_eq!(gateway.services.dns.lock().unwrap().version, version + 1);
+        assert_eq!(gateway.bridge_manifest().body.portals.len(), 2);
+        assert!(gateway.sky_timeline("mars").is_none());
+        assert!(gateway.check_invariants().ok());
+
+        assert!(gateway
+            .apply_bootstrap(&plan, false)
+            .unwrap()
+            .changes
+            .is_empty());
+        let reopened = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        assert_eq!(reopened.world_settings(), plan.world);
+        assert!(reopened
+            .apply_bootstrap(&plan, true)
+            .unwrap()
+            .changes
+            .is_empty());
+    }
+
+    #[test]
+    fn escrows_settle_on_confirm_and_refund_past_deadline() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let bank = &gateway.services.banking;
+        bank.freeze_interest();
+        bank.credit(PlanetId::Earth, ";5550001111;main;", 500);
+        let supply = || -> u128 { bank.bank().ledgers()[&PlanetId::Earth].values().sum() };
+        let total = supply();
+        let balance = |label: &str| bank.balance_of(PlanetId::Earth, label);
+        let side = |label: &str, amount: u128, item: Option<&str>| EscrowSide {
+            label: label.into(),
+            amount,
+            item: item.map(String::from),
+        };
+        let request = |deadline_blocks| EscrowRequest {
+            planet: PlanetId::Earth,
+            offer: side(";9132077554;fun;", 30_000, None),
+            ask: side(";5550001111;main;", 0, Some(";land;earth;12;-4;")),
+            deadline_blocks,
+        };
+
+        let land = ";land;earth;12;-4;";
+        let open = gateway.open_escrow("9132077554", request(4)).unwrap();
+        assert_eq!(balance(";9132077554;fun;"), 50_000);
+        assert_eq!(balance(ESCROW_LABEL), 30_000);
+        assert_eq!(supply(), total);
+        assert_eq!(gateway.open_escrows("5550001111").len(), 1);
+        assert!(gateway.confirm_escrow("9132077554", open.id).is_err());
+        // main has to hold the land it is asked for before it can confirm.
+        assert!(matches!(
+            gateway.confirm_escrow("5550001111", open.id),
+            Err(DlogError::Auth(AuthError::Forbidden(_)))
+        ));
+        let assign = |label: &str| ItemAssignment {
+            item: land.into(),
+            label: label.into(),
+        };
+        gateway.assign_item(assign(";5550001111;main;")).unwrap();
+        let settled = gateway.confirm_escrow("5550001111", open.id).unwrap();
+        assert_eq!(settled.state, EscrowState::Settled);
+        assert_eq!(balance(";5550001111;main;"), 30_500);
+        assert_eq!(
+            gateway.items.owner_of(land).as_deref(),
+            Some(";9132077554;fun;")
+        );
+        assert!(gateway.confirm_escrow("5550001111", open.id).is_err());
+
+        let lapsing = gateway.open_escrow("9132077554", request(1)).unwrap();
+        gateway.seal_block();
+        assert_eq!(gateway.open_escrows("9132077554").len(), 1);
+        gateway.seal_block();
+        assert!(gateway.open_escrows("9132077554").is_empty());
+        assert_eq!(balance(";9132077554;fun;"), 50_000);
+        assert_eq!(supply(), total);
+
+        let kinds: Vec<_> = gateway
+            .events()
+            .since(0)
+            .into_iter()
+            .filter(|e| e.kind.starts_with("escrow_"))
+            .map(|e| (e.kind, e.payload["escrow_id"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("escrow_opened".to_string(), open.id),
+                ("escrow_settled".to_string(), open.id),
+                ("escrow_opened".to_string(), lapsing.id),
+                ("escrow_refunded".to_string(), lapsing.id),
+            ]
+        );
+    }
+
+    #[test]
+    fn bridge_input_follows_the_labels_device_calibration() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let stick = |label: Option<&str>| {
+            let instructions = gateway.process_bridge_input(BridgeInputSnapshot {
+                player_uuid: "p1".into(),
+                session_id: None,
+                stand_id: None,
+                device: Some("gamepad".into()),
+                profile: None,
+                world: None,
+                label: label.map(String::from),
+                buttons: Vec::new(),
+                axes: vec![BridgeAxisSnapshot {
+                    action: "move".into(),
+                    x: 0.8,
+                    y: 0.1,
+                    mode: AxisMode::Relative,
+                }],
+                timestamp_ms: None,
+            });
+            match instructions[0].action {
+                BridgeAction::SetVelocity { vx, vz, .. } => (vx, vz),
+                ref other => panic!("unexpected {other:?}"),
+            }
+        };
+        let full = PHI_F32 * INPUT_VELOCITY_SCALE;
+        let (vx, _) = stick(Some(";9132077554;comet;"));
+        assert!((vx - 0.8 * 0.9 * full).abs() < 1e-6);
+
+        let update = CalibrationUpdate {
+            device: "gamepad".into(),
+            movement: Some(AxisCalibration {
+                deadzone: 0.15,
+                sensitivity: 0.5,
+                invert_x: true,
+                ..AxisCalibration::default()
+            }),
+            look: None,
+        };
+        assert!(gateway
+            .calibrate("5550001111", ";9132077554;comet;", update.clone())
+            .is_err());
+        gateway
+            .calibrate("9132077554", ";9132077554;comet;", update)
+            .unwrap();
+        assert_eq!(
+            gateway
+                .calibrations("9132077554", ";9132077554;comet;")
+                .unwrap()["gamepad"]
+                .movement
+                .sensitivity,
+            0.5
+        );
+        let (vx, vz) = stick(Some(";9132077554;comet;"));
+        assert!(vx < 0.0 && vz > 0.0);
+        // The deadzone takes its share off the deflection.
+        assert!(vx.hypot(vz) < 0.5 * full * 0.8f32.hypot(0.1));
+        // Input naming no label keeps the device scale.
+        assert!((stick(None).0 - 0.8 * 0.9 * full).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bridge_input_without_a_profile_moves_at_its_worlds_profile() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let plan =
+            BootstrapPlan::parse("world ;moon_dome;moon;0;160;glide;\nprofile ;glide;0.5;\n");
+        gateway.apply_bootstrap(&plan, false).unwrap();
+        let stick = |world: Option<&str>, profile: Option<&str>| {
+            let instructions = gateway.process_bridge_input(BridgeInputSnapshot {
+                player_uuid: "p1".into(),
+                session_id: None,
+                stand_id: None,
+                device: Some("keyboard".into()),
+                profile: profile.map(String::from),
+                world: world.map(String::from),
+                label: None,
+                buttons: Vec::new(),
+                axes: vec![BridgeAxisSnapshot {
+                    action: "move".into(),
+                    x: 1.0,
+                    y: 0.0,
+                    mode: AxisMode::Relative,
+                }],
+                timestamp_ms: None,
+            });
+            match instructions[0].action {
+                BridgeAction::SetVelocity { vx, .. } => vx,
+                ref other => panic!("unexpected {other:?}"),
+            }
+        };
+        let full = PHI_F32 * INPUT_VELOCITY_SCALE;
+        assert!((stick(Some("moon_dome"), None) - 0.5 * full).abs() < 1e-6);
+        // A named profile wins over the world's; other worlds move as `default`.
+        assert!((stick(Some("moon_dome"), Some("surf")) - 1.35 * full).abs() < 1e-6);
+        assert!((stick(Some("earth_shell"), None) - full).abs() < 1e-6);
+        assert!((stick(None, None) - full).abs() < 1e-6);
+    }
+
+    #[test]
+    fn guardians_recover_a_label_to_a_new_phone_after_the_time_lock() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let bank = &gateway.services.banking;
+        bank.freeze_interest();
+        let fun = ";9132077554;fun;";
+        let balance = bank.balance_of(PlanetId::Earth, fun);
+        gateway
+            .register_alias("funbag", fun, "9132077554", false)
+            .unwrap();
+        let guardians = |threshold| GuardianRequest {
+            guardians: vec![";5550001111;main;".into(), ";5550002222;spare;".into()],
+            threshold,
+            delay_blocks: 2,
+        };
+        assert!(gateway
+            .set_guardians("5550001111", fun, guardians(2))
+            .is_err());
+        gateway
+            .set_guardians("9132077554", "@funbag", guardians(2))
+            .unwrap();
+
+        // The old phone can veto a request it didn't make.
+        let vetoed = gateway.request_recovery("5559990000", fun).unwrap();
+        assert!(gateway.cancel_recovery("5550001111", vetoed.id).is_err());
+        gateway.cancel_recovery("9132077554", vetoed.id).unwrap();
+
+        let open = gateway.request_recovery("5559990000", "@funbag").unwrap();
+        assert_eq!(open.new_label, ";5559990000;fun;");
+        assert_eq!(gateway.open_recoveries("5550002222").len(), 1);
+        let approve =
+            |phone: &str, guardian: &str| gateway.approve_recovery(phone, open.id, guardian);
+        assert!(matches!(
+            approve("5550003333", ";5550003333;x;"),
+            Err(DlogError::Auth(AuthError::Forbidden(_)))
+        ));
+        approve("5550001111", ";5550001111;main;").unwrap();
+        let locked = approve("5550002222", ";5550002222;spare;").unwrap();
+        let unlock = locked.unlock_height.unwrap();
+        assert_eq!(unlock, gateway.block_height() + 2);
+
+        while gateway.block_height() + 1 < unlock {
+            gateway.seal_block();
+        }
+        assert_eq!(bank.balance_of(PlanetId::Earth, fun), balance);
+        gateway.seal_block();
+        assert!(gateway.open_recoveries("5559990000").is_empty());
+        assert_eq!(bank.balance_of(PlanetId::Earth, fun), 0);
+        assert_eq!(
+            bank.balance_of(PlanetId::Earth, ";5559990000;fun;"),
+            balance
+        );
+        assert_eq!(
+            gateway.services.aliases.resolve_label("@funbag").unwrap(),
+            ";5559990000;fun;"
+        );
+        assert_eq!(
+            gateway.labels_for("5559990000")[0].status,
+            LabelStatus::Open
+        );
+        assert!(gateway.services.labels.check_recipient(fun, true).is_err());
+        assert_eq!(
+            gateway
+                .guardians_of("5559990000", "@funbag")
+                .unwrap()
+                .threshold,
+            2
+        );
+
+        let kinds: Vec<_> = gateway
+            .notifications("5559990000")
+            .notifications
+            .into_iter()
+            .map(|n| n.kind)
+            .filter(|kind| kind != "recovery_cancelled")
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "recovery_completed",
+                "recovery_time_locked",
+                "recovery_approved",
+                "recovery_approved",
+                "recovery_requested",
+                "recovery_requested",
+            ]
+        );
+        assert!(!gateway
+            .events()
+            .since(0)
+            .iter()
+            .any(|e| e.payload.to_string().contains("9132077554")));
+    }
+
+    #[test]
+    fn transfers_and_escrows_land_in_the_parties_inboxes() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        gateway.services.banking.freeze_interest();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        gateway.handle_frame(gateway.sign(FrameEnvelope {
+            session_id: session,
+            seq: 1,
+            namespace: ";bank;".into(),
+            kind: FrameKind::Event,
+            payload: serde_json::json!({
+                "kind": "transfer",
+                "from": ";9132077554;comet;",
+                "to": ";5550001111;main;",
+                "amount": 40,
+                "memo": "for the map",
+            }),
+            signature: None,
+        }));
+        let escrow = gateway
+            .open_escrow(
+                "9132077554",
+                EscrowRequest {
+                    planet: PlanetId::Earth,
+                    offer: EscrowSide {
+                        label: ";9132077554;fun;".into(),
+                        amount: 10,
+                        item: None,
+                    },
+                    ask: EscrowSide {
+                        label: ";5550001111;main;".into(),
+                        amount: 20,
+                        item: None,
+                    },
+                    deadline_blocks: ESCROW_EXPIRY_NOTICE + 1,
+                },
+            )
+            .unwrap();
+        gateway.seal_block();
+
+        let inbox = gateway.notifications("5550001111");
+        let kinds: Vec<_> = inbox
+            .notifications
+            .iter()
+            .map(|n| n.kind.as_str())
+            .collect();
+        assert_eq!(kinds, ["escrow_expiring", "escrow_opened", "transfer"]);
+        assert_eq!(inbox.unread, 3);
+        assert_eq!(inbox.ui.badges[0].count, 3);
+        let received = &inbox.notifications[2].payload;
+        assert_eq!(received["amount"], 40);
+        assert_eq!(received["memo"], "for the map");
+        assert!(!received.to_string().contains("9132077554"));
+        assert_eq!(inbox.notifications[0].payload["escrow_id"], escrow.id);
+        // The sender only hears about the escrow, and the bus names no one.
+        assert_eq!(gateway.notifications("9132077554").notifications.len(), 2);
+        assert!(!gateway
+            .events()
+            .since(0)
+            .iter()
+            .any(|e| e.payload.to_string().contains("5550001111")));
+
+        assert_eq!(gateway.mark_notifications_read("5550001111", &[]), 3);
+        assert_eq!(gateway.purge_notifications("9132077554", false), 0);
+        let restarted = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let inbox = restarted.notifications("5550001111");
+        assert_eq!(inbox.unread, 0);
+        assert!(inbox.ui.badges.is_empty());
+        assert_eq!(restarted.purge_notifications("5550001111", false), 3);
+        assert_eq!(restarted.notifications("9132077554").unread, 2);
+    }
+
+    #[test]
+    fn guild_treasuries_pay_out_by_role_and_approval() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let bank = &gateway.services.banking;
+        bank.freeze_interest();
+        bank.credit(PlanetId::Earth, ";5550001111;main;", 500);
+        let charter = |founders: &[&str]| GuildCharter {
+            name: "Miners".into(),
+            founders: founders.iter().map(|f| f.to_string()).collect(),
+            policy: GuildPolicy {
+                approval_threshold: 100,
+                approvals: 2,
+                treasurer_cap: 300,
+            },
+        };
+        // Someone else's labels alone can't found a guild.
+        assert!(gateway
+            .found_guild("5550002222", charter(&[";9132077554;comet;"]))
+            .is_err());
+        let founded = gateway
+            .found_guild("9132077554", charter(&[";9132077554;comet;"]))
+            .unwrap();
+        assert_eq!(founded.guild.treasury, ";∞;guild;miners;");
+        assert_eq!(founded.role, GuildRole::Founder);
+        gateway
+            .set_guild_role(
+                "9132077554",
+                "miners",
+                ";5550001111;main;",
+                Some(GuildRole::Treasurer),
+            )
+            .unwrap();
+        assert!(gateway.guild("5550002222", "miners").is_err());
+
+        gateway
+            .guild_deposit(
+                "9132077554",
+                "miners",
+                ";9132077554;comet;",
+                PlanetId::Earth,
+                1_000,
+            )
+            .unwrap();
+        let spend = |phone: &str, amount: u128| {
+            gateway.guild_spend(
+                phone,
+                "miners",
+                SpendRequest {
+                    planet: PlanetId::Earth,
+                    to: ";5550002222;alt;".into(),
+                    amount,
+                },
+            )
+        };
+        assert_eq!(spend("5550001111", 80).unwrap().state, SpendState::Executed);
+        assert!(spend("5550001111", 301).is_err());
+        let pending = spend("5550001111", 250).unwrap();
+        assert_eq!(pending.state, SpendState::Pending);
+        let paid = gateway
+            .approve_guild_spend("9132077554", "miners", pending.id)
+            .unwrap();
+        assert_eq!(paid.state, SpendState::Executed);
+        assert_eq!(bank.balance_of(PlanetId::Earth, ";5550002222;alt;"), 330);
+        assert_eq!(bank.balance_of(PlanetId::Earth, ";∞;guild;miners;"), 670);
+        assert_eq!(gateway.notifications("5550002222").unread, 2);
 
-        instructions
+        let restarted = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let guilds = restarted.guilds_for("5550001111");
+        assert_eq!(guilds.len(), 1);
+        assert_eq!(guilds[0].role, GuildRole::Treasurer);
+        assert_eq!(guilds[0].balances[&PlanetId::Earth], 670);
+        assert_eq!(guilds[0].guild.spends.len(), 2);
     }
 
-    pub fn process_bridge_position(
-        &self,
-        snapshot: BridgePositionSnapshot,
-    ) -> Vec<BridgeInstruction> {
-        if let Some(session_id) = snapshot.session_id.as_deref() {
-            self.bump_input_timestamp(session_id);
-        }
+    #[test]
+    fn interest_accrues_per_block_not_per_millisecond() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let bank = &gateway.services.banking;
+        let comet = || bank.balance_of(PlanetId::Earth, ";9132077554;comet;");
+        let root = bank.ledger_root(PlanetId::Earth);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(comet(), 1_000_000);
+        assert_eq!(bank.ledger_root(PlanetId::Earth), root);
 
-        let (min_y, max_y) = bounds_for_world(&snapshot.world);
-        let clamped_y = snapshot.pos.y.clamp(min_y, max_y);
-        let mut instructions = vec![BridgeInstruction::SetPosition {
-            stand_id: snapshot.stand_id.clone(),
-            x: snapshot.pos.x,
-            y: clamped_y,
-            z: snapshot.pos.z,
-        }];
+        gateway.seal_block();
+        let factor = |planet: PlanetId| bank.bank().per_block_factor_ppm(planet);
+        assert_eq!(factor(PlanetId::Earth), 1_020_000);
+        // Each zone pays its own APY: the Moon's 100% beats Earth's 61.8%.
+        assert!(factor(PlanetId::Moon) > factor(PlanetId::Earth));
+        assert!(factor(PlanetId::Sun) < factor(PlanetId::Mars));
+        assert_eq!(comet(), 1_020_000);
+        // A height paid once (e.g. re-applied on a fork switch) isn't paid again.
+        bank.accrue_to(1);
+        assert_eq!(comet(), 1_020_000);
 
-        if let Some(velocity) = snapshot.velocity {
-            instructions.push(BridgeInstruction::SetVelocity {
-                stand_id: snapshot.stand_id.clone(),
-                vx: velocity.x,
-                vy: velocity.y,
-                vz: velocity.z,
-            });
-        }
+        // The sim's world hash compares bank roots across nodes, so a node
+        // sealing the same heights later must report the same roots.
+        let later = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        later.seal_block();
+        let roots = |gateway: &OmegaGateway| -> Vec<String> {
+            gateway
+                .status()
+                .planets
+                .into_iter()
+                .map(|p| p.bank_root)
+                .collect()
+        };
+        assert_eq!(roots(&later), roots(&gateway));
+    }
 
-        if let Some(rotation) = snapshot.rotation {
-            instructions.push(BridgeInstruction::AlignRotation {
-                stand_id: snapshot.stand_id,
-                yaw: rotation.yaw,
-                pitch: rotation.pitch,
-            });
-        }
+    #[test]
+    fn scheduled_rate_changes_apply_at_their_height_and_persist() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let request = |effective_height, approvers: &[&str]| MonetaryChangeRequest {
+            effective_height,
+            planet: PlanetId::Earth,
+            holder_interest_apy: Some(0.309),
+            miner_inflation_apy: None,
+            reason: "cool Earth down".into(),
+            approvers: approvers.iter().map(|a| a.to_string()).collect(),
+        };
+        let change = gateway
+            .schedule_monetary_change(request(2, &[";9132077554;comet;"]))
+            .unwrap();
+        assert_eq!(change.monetary.target_block_seconds, 8.0);
+        assert!(gateway.schedule_monetary_change(request(3, &[])).is_err());
+        assert!(gateway
+            .schedule_monetary_change(request(3, &["comet"]))
+            .is_err());
 
-        instructions.push(BridgeInstruction::Echo {
-            stand_id: None,
-            message: format!(
-                "position sync for {} in {}",
-                snapshot.player_uuid, snapshot.world
-            ),
-        });
+        let bank = &gateway.services.banking;
+        let comet = || bank.balance_of(PlanetId::Earth, ";9132077554;comet;");
+        gateway.seal_block();
+        assert_eq!(comet(), 1_020_000);
+        assert!(!gateway.monetary_history().changes[0].applied);
+        gateway.seal_block();
+        assert_eq!(comet(), 1_030_200);
+        let history = gateway.monetary_history();
+        assert!(history.changes[0].applied);
+        assert_eq!(history.zones[0].monetary.holder_interest_apy, 0.309);
+        assert_eq!(gateway.replica_state(0).monetary.len(), 1);
 
-        instructions
+        let restarted = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        assert_eq!(restarted.monetary_history().changes.len(), 1);
     }
-}
 
-fn bounds_for_world(world: &str) -> (f32, f32) {
-    match world {
-        "moon_shell" | "moon_core" => (0.0, 160.0),
-        "mars_shell" | "mars_core" => (0.0, 210.0),
-        "sun_shell" | "sun_core" => (32.0, 400.0),
-        "earth_core" => (0.0, 128.0),
-        _ => (0.0, DEFAULT_WORLD_MAX_Y),
-    }
-}
+    #[test]
+    fn gateway_identity_survives_restarts_as_new_incarnations() {
+        let dir = tempdir().unwrap();
+        let boot = || OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let first = boot().identity().clone();
+        assert_eq!(first.incarnation, 1);
 
-/// Aggregates all Ω services that sit behind the HTTP-4 router.
-#[derive(Debug, Default)]
-struct OmegaServices {
-    dns: DnsRouter,
-    banking: InfinityBank,
-    mining: MiningDispatch,
-    speaker: SpeakerEngine,
-    game: GameEngine,
-}
+        let gateway = boot();
+        assert_eq!(gateway.id(), first.gateway_id);
+        assert_eq!(gateway.node_id(), first.gateway_id);
+        let status = gateway.status();
+        assert_eq!(
+            (status.first_boot_ms, status.incarnation),
+            (first.first_boot_ms, 2)
+        );
+        let session = gateway
+            .handle_handshake(
+                HandshakeRequest {
+                    client_id: "c".into(),
+                    capabilities: Vec::new(),
+                    requested_routes: Vec::new(),
+                    phone: None,
+                    session_token: None,
+                    planet: PlanetId::Earth,
+                    peer_mac: None,
+                    session_kind: SessionKind::Player,
+                },
+                SessionGrant::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            (session.gateway_id.as_str(), session.incarnation),
+            (first.gateway_id.as_str(), 2)
+        );
 
-impl OmegaServices {
-    fn list(&self) -> Vec<&'static str> {
-        vec![
-            "omega.dns.router",
-            "omega.bank.infinity",
-            "omega.mining.dispatch",
-            "omega.audio.stack",
-            "omega.game.engine",
-        ]
+        // Without a root every boot is a new gateway.
+        let ephemeral = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        assert_ne!(ephemeral.id(), first.gateway_id);
+        assert_eq!(ephemeral.identity().incarnation, 1);
     }
 
-    fn dispatch(&self, frame: &FrameEnvelope) -> Vec<String> {
-        let mut notes = Vec::new();
-        match frame.kind {
-            FrameKind::Dns => notes.push(self.dns.resolve(&frame.namespace)),
-            FrameKind::MineJob | FrameKind::MineResult => {
-                notes.push(self.mining.handle(frame));
-            }
-            FrameKind::Audio => notes.push(self.speaker.handle(frame)),
-            FrameKind::Game | FrameKind::TickFrame => notes.push(self.game.handle(frame)),
-            FrameKind::Query | FrameKind::Event => notes.push(self.banking.handle(frame)),
-            FrameKind::Input => notes.push("input frame buffered".into()),
-        }
-        notes
+    #[test]
+    fn compounding_modes_pay_close_to_per_block() {
+        let after = |mode: CompoundingMode, height: u64| {
+            let gateway = OmegaGateway::with_store(GatewayStore::default())
+                .unwrap()
+                .with_compounding(mode);
+            gateway.services.banking.accrue_to(height);
+            gateway
+                .services
+                .banking
+                .balance_of(PlanetId::Earth, ";9132077554;comet;")
+        };
+        // Octaves pay nothing until a height divisible by 8, then 8 × 2%.
+        assert_eq!(after(CompoundingMode::PerEightBlocks, 7), 1_000_000);
+        assert_eq!(after(CompoundingMode::PerEightBlocks, 8), 1_160_000);
+        assert_eq!(after(CompoundingMode::Continuous, 1), 1_020_201);
+
+        let per_block = after(CompoundingMode::PerBlock, 64) as f64;
+        let per_eight = after(CompoundingMode::PerEightBlocks, 64) as f64;
+        let continuous = after(CompoundingMode::Continuous, 64) as f64;
+        assert!(per_eight < per_block && per_block < continuous);
+        assert!((continuous - per_eight) / per_block < 0.1);
+
+        let status = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_compounding(CompoundingMode::PerEightBlocks)
+            .compounding();
+        assert_eq!(status.period_blocks, 8);
+        assert_eq!(status.zones[0].period_factor_ppm, 1_160_000);
+        assert_eq!(omega_bank::exp_ppm(0), 1_000_000);
+        assert_eq!(omega_bank::exp_ppm(1_000_000), 2_718_282);
     }
-}
 
-#[derive(Debug)]
-struct DnsRouter {
-    records: HashMap<String, DnsRecord>,
-}
+    #[test]
+    fn escrowed_funds_survive_a_restart_without_minting() {
+        let dir = tempdir().unwrap();
+        let boot = || OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let supply = |gateway: &OmegaGateway| -> u128 {
+            gateway.services.banking.snapshot()[&PlanetId::Earth]
+                .values()
+                .sum()
+        };
+        let gateway = boot();
+        gateway.services.banking.freeze_interest();
+        let total = supply(&gateway);
+        let side = |label: &str, amount| EscrowSide {
+            label: label.into(),
+            amount,
+            item: None,
+        };
+        gateway
+            .open_escrow(
+                "9132077554",
+                EscrowRequest {
+                    planet: PlanetId::Earth,
+                    offer: side(";9132077554;fun;", 30_000),
+                    ask: side(";5550001111;main;", 1),
+                    deadline_blocks: 1,
+                },
+            )
+            .unwrap();
+        drop(gateway);
 
-#[derive(Debug, Clone)]
-struct DnsRecord {
-    omega_path: String,
-    target: String,
-    description: &'static str,
-}
+        let restarted = boot();
+        restarted.services.banking.freeze_interest();
+        let balance = |label| {
+            restarted
+                .services
+                .banking
+                .balance_of(PlanetId::Earth, label)
+        };
+        assert_eq!(balance(";9132077554;fun;"), 50_000);
+        assert_eq!(supply(&restarted), total);
+        restarted.seal_block();
+        restarted.seal_block();
+        assert!(restarted.open_escrows("9132077554").is_empty());
+        assert_eq!(balance(";9132077554;fun;"), 80_000);
+        assert_eq!(balance(ESCROW_LABEL), 0);
+        assert_eq!(supply(&restarted), total);
+    }
 
-impl Default for DnsRouter {
-    fn default() -> Self {
-        let mut records = HashMap::new();
-        for record in [
-            DnsRecord {
-                omega_path: ";∞;dns;router;".into(),
-                target: "omega.dns.router".into(),
-                description: "Omega path router",
-            },
-            DnsRecord {
-                omega_path: ";∞;bank;infinity;".into(),
-                target: "omega.bank.infinity".into(),
-                description: "Gravity-backed Infinity bank",
-            },
-            DnsRecord {
-                omega_path: ";∞;bank;gravity;router;".into(),
-                target: "omega.bank.gravity".into(),
-                description: "VORTEX/COMET gravity router",
-            },
-            DnsRecord {
-                omega_path: ";∞;speaker;engine;".into(),
-                target: "omega.audio.stack".into(),
-                description: "Omega speaker engine",
-            },
-            DnsRecord {
-                omega_path: ";∞;game;engine;".into(),
-                target: "omega.game.engine".into(),
-                description: "Simulation + gameplay kernel",
-            },
-            DnsRecord {
-                omega_path: ";∞;mining;dispatch;".into(),
-                target: "omega.mining.dispatch".into(),
-                description: "Hash dispatch + silicon rails",
-            },
-            DnsRecord {
-                omega_path: ";∞;mining;result;".into(),
-                target: "omega.mining.result".into(),
-                description: "Mining result verifier",
-            },
-        ] {
-            records.insert(Self::canonical_key(&record.omega_path), record);
-        }
+    #[test]
+    fn label_files_restore_the_ledger_without_a_snapshot() {
+        let dir = tempdir().unwrap();
+        let boot = || OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let gateway = boot();
+        gateway
+            .services
+            .banking
+            .move_funds(
+                PlanetId::Earth,
+                ";9132077554;comet;",
+                ";9132077554;fun;",
+                10,
+            )
+            .unwrap();
+        gateway.seal_block();
+        let before = gateway.services.banking.snapshot();
+        drop(gateway);
 
-        Self { records }
+        std::fs::remove_file(dir.path().join("∞").join("bank;ledger")).unwrap();
+        // Sealing paid a block of interest on every label, so untouched seed
+        // labels come back from their files too, not from the seed.
+        let restarted = boot();
+        assert_eq!(restarted.services.banking.snapshot(), before);
+        assert!(
+            restarted
+                .services
+                .banking
+                .balance_of(PlanetId::Earth, ";9132077554;fun;")
+                > 80_010
+        );
     }
-}
 
-impl DnsRouter {
-    fn resolve(&self, namespace: &str) -> String {
-        let key = Self::canonical_key(namespace);
-        if let Some(record) = self.records.get(&key) {
-            return format!(
-                "dns::{key} → {target} ({desc})",
-                target = record.target,
-                desc = record.description
+    #[test]
+    fn capabilities_gate_bank_and_mining_frames() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let request = |capabilities: &[&str]| HandshakeRequest {
+            client_id: "c".into(),
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            requested_routes: Vec::new(),
+            phone: None,
+            session_token: None,
+            planet: PlanetId::Earth,
+            peer_mac: None,
+            session_kind: SessionKind::Player,
+        };
+        let status = |result: Result<HandshakeResponse, DlogError>| result.unwrap_err().status();
+        assert_eq!(
+            status(
+                gateway
+                    .handle_handshake(request(&["banking", "teleport"]), SessionGrant::default())
+            ),
+            400
+        );
+        // Grants follow the verified identity, not the request.
+        for cap in ["banking", "mining", "admin", "peer"] {
+            assert_eq!(
+                status(gateway.handle_handshake(request(&[cap]), SessionGrant::default())),
+                403,
+                "{cap}"
             );
         }
 
-        for fallback in Self::fallback_keys(&key) {
-            if let Some(record) = self.records.get(&fallback) {
-                return format!(
-                    "dns::{key} → {target} (via {path})",
-                    target = record.target,
-                    path = record.omega_path
-                );
-            }
-        }
+        let plain = handshake(&gateway, PlanetId::Earth, &[]);
+        let miner = handshake(&gateway, PlanetId::Earth, &["Mining", "render"]);
+        assert!(gateway.require(&plain, Capability::Render).is_ok());
+        assert_eq!(
+            gateway.require(&plain, Capability::Banking),
+            Err(AuthError::Forbidden(
+                "session lacks banking capability".into()
+            ))
+        );
+        assert_eq!(
+            gateway.require("nope", Capability::Admin),
+            Err(AuthError::Unverified)
+        );
 
-        format!("dns::{key} → (unmapped) request router-registration")
+        let job = |session_id: &str, kind| FrameEnvelope {
+            session_id: session_id.into(),
+            seq: 7,
+            namespace: ";mine;".into(),
+            kind,
+            payload: Value::Null,
+            signature: None,
+        };
+        let denied = gateway.handle_frame(gateway.sign(job(&plain, FrameKind::MineJob)));
+        assert!(!denied.accepted && denied.routed.is_empty());
+        assert!(
+            gateway
+                .handle_frame(gateway.sign(job(&miner, FrameKind::MineJob)))
+                .accepted
+        );
+        assert!(
+            !gateway
+                .handle_frame(gateway.sign(job(&miner, FrameKind::Query)))
+                .accepted
+        );
     }
 
-    fn canonical_key(namespace: &str) -> String {
-        namespace
-            .split(';')
-            .filter(|s| !s.is_empty())
-            .map(|segment| segment.to_ascii_lowercase())
-            .collect::<Vec<_>>()
-            .join(".")
-    }
+    #[test]
+    fn spectators_watch_read_only_within_their_own_slots() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_spectator_slots(1);
+        let request = |capabilities: &[&str], session_kind| HandshakeRequest {
+            client_id: "streamer".into(),
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            requested_routes: Vec::new(),
+            phone: None,
+            session_token: None,
+            planet: PlanetId::Moon,
+            peer_mac: None,
+            session_kind,
+        };
+        let grant = || SessionGrant {
+            phone: Some("9132077554".into()),
+            admin: false,
+        };
+        assert_eq!(
+            gateway
+                .handle_handshake(
+                    request(&["render", "input"], SessionKind::Spectator),
+                    grant()
+                )
+                .unwrap_err()
+                .status(),
+            403
+        );
+        let watcher = gateway
+            .handle_handshake(request(&[], SessionKind::Spectator), grant())
+            .unwrap();
+        assert_eq!(watcher.session_kind, SessionKind::Spectator);
+        assert_eq!(watcher.capabilities, SPECTATOR_CAPABILITIES);
+        // The spectator budget is full; players still get in.
+        assert_eq!(
+            gateway
+                .handle_handshake(request(&[], SessionKind::Spectator), grant())
+                .unwrap_err()
+                .status(),
+            409
+        );
+        let player = handshake(&gateway, PlanetId::Moon, &[]);
 
-    fn fallback_keys(key: &str) -> Vec<String> {
-        let mut parts: Vec<&str> = key.split('.').collect();
-        let mut fallbacks = Vec::new();
-        while parts.len() > 1 {
-            parts.pop();
-            fallbacks.push(parts.join("."));
+        let send = |session_id: &str, kind| {
+            gateway.handle_frame(FrameEnvelope {
+                session_id: session_id.into(),
+                seq: 1,
+                namespace: ";game;".into(),
+                kind,
+                payload: Value::Null,
+                signature: None,
+            })
+        };
+        for kind in [FrameKind::Input, FrameKind::Game, FrameKind::TickFrame] {
+            let ack = send(&watcher.session_id, kind.clone());
+            assert!(!ack.accepted, "{kind:?}");
+            assert!(ack.notes.contains(&format!(
+                "{kind:?} rejected: spectator sessions are read-only"
+            )));
+            assert!(send(&player, kind).accepted);
         }
-        fallbacks
+        assert!(send(&watcher.session_id, FrameKind::Dns).accepted);
+
+        let status = gateway.status();
+        assert_eq!((status.session_count, status.spectators), (2, 1));
+        assert_eq!(status.spectator_slots, 1);
+        assert_eq!(gateway.overview().sessions.spectators, 1);
+        gateway.kick_session(&watcher.session_id).unwrap();
+        assert!(gateway
+            .handle_handshake(request(&["audio"], SessionKind::Spectator), grant())
+            .is_ok());
     }
-}
 
-#[derive(Debug)]
-struct InfinityBank {
-    ledger: Mutex<HashMap<String, u128>>,
-    #[allow(dead_code)]
-    interest_apy_bps: u32,
-    last_tick_ms: Mutex<i64>,
-    per_tick_factor_ppm: u64,
-}
+    #[test]
+    fn slow_sessions_are_paced_from_their_round_trips() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default())
+            .unwrap()
+            .with_tick_cadence(TickCadence {
+                min_ms: tick::FRAME_MS,
+                max_ms: 128,
+            });
+        let fast = handshake(&gateway, PlanetId::Earth, &[]);
+        let slow = handshake(&gateway, PlanetId::Earth, &[]);
+        let heartbeat = |session_id: &str, rtt_ms: u64| {
+            gateway.handle_frame(FrameEnvelope {
+                session_id: session_id.into(),
+                seq: 1,
+                namespace: ";game;".into(),
+                kind: FrameKind::TickFrame,
+                payload: serde_json::json!({ "rtt_ms": rtt_ms }),
+                signature: None,
+            })
+        };
 
-impl Default for InfinityBank {
-    fn default() -> Self {
-        let mut ledger = HashMap::new();
-        ledger.insert(";9132077554;comet;".into(), 1_000_000);
-        ledger.insert(";9132077554;vortex1;".into(), 5_000_000);
-        ledger.insert(";9132077554;fun;".into(), 80_000);
-        Self {
-            ledger: Mutex::new(ledger),
-            interest_apy_bps: 6180,
-            last_tick_ms: Mutex::new(now_ms()),
-            per_tick_factor_ppm: Self::phi_tick_factor_ppm(),
+        let ack = heartbeat(&fast, 20);
+        assert_eq!(ack.tick_interval_ms, tick::FRAME_MS);
+        for _ in 0..8 {
+            heartbeat(&slow, 800);
         }
-    }
-}
+        let ack = heartbeat(&slow, 900);
+        assert!(ack.tick_interval_ms > tick::FRAME_MS && ack.tick_interval_ms <= 128);
+        assert_eq!(ack.tick_interval_ms % tick::FRAME_MS, 0);
+        assert_eq!(
+            (ack.next_tick_ms - gateway.ticks().boot_ms()) % ack.tick_interval_ms,
+            0
+        );
+        assert!(ack.next_tick_ms > now_ms() - ack.tick_interval_ms);
 
-impl InfinityBank {
-    fn phi_tick_factor_ppm() -> u64 {
-        1_000_020
+        // A stalled link stops at the ceiling; sessions show what they got.
+        heartbeat(&slow, 120_000);
+        assert_eq!(gateway.tick_interval_ms(&slow), 128);
+        let views = gateway.sessions();
+        let view = views.iter().find(|v| v.session_id == slow).unwrap();
+        assert_eq!(
+            (view.tick_interval_ms, view.rtt.unwrap().samples),
+            (128, 10)
+        );
+        assert_eq!(gateway.tick_interval_ms("gone"), tick::FRAME_MS);
     }
 
-    fn accrue_interest(&self) {
-        let now = now_ms();
-        let mut last = self
-            .last_tick_ms
-            .lock()
-            .expect("bank last_tick mutex poisoned");
-
-        if now <= *last {
-            return;
-        }
+    #[test]
+    fn flagged_players_record_redacted_input_and_dump_per_report() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let position = |player: &str, stand: &str| BridgePositionSnapshot {
+            player_uuid: player.into(),
+            session_id: Some("secret-session".into()),
+            stand_id: Some(stand.into()),
+            world: "world".into(),
+            pos: Vec3f {
+                x: 1.0,
+                y: 70.0,
+                z: -2.0,
+            },
+            velocity: None,
+            rotation: None,
+        };
 
-        let ticks = ((now - *last) / 8).max(1) as u64;
-        let factor = self.per_tick_factor_ppm as u128;
-        let mut ledger = self.ledger.lock().expect("ledger mutex poisoned");
+        gateway.process_bridge_position(position("cheater", "player-cheater"));
+        gateway.flag_recording("cheater", Some("speed report".into()));
+        gateway.process_bridge_position(position("cheater", "player-cheater"));
+        gateway.process_bridge_position(position("cheater", "player-bystander"));
+        gateway.process_bridge_position(position("bystander", "player-bystander"));
+        gateway.process_bridge_input(BridgeInputSnapshot {
+            player_uuid: "cheater".into(),
+            session_id: None,
+            stand_id: None,
+            device: Some("keyboard".into()),
+            profile: None,
+            world: None,
+            label: None,
+            buttons: vec![BridgeButtonSnapshot {
+                action: "jump".into(),
+                state: ButtonState::Pressed,
+            }],
+            axes: Vec::new(),
+            timestamp_ms: Some(42),
+        });
 
-        for balance in ledger.values_mut() {
-            for _ in 0..ticks {
-                *balance = (*balance * factor) / 1_000_000;
-            }
-        }
+        assert!(matches!(
+            gateway.dump_recording("cheater", "../etc"),
+            Err(FrameError::Malformed(_))
+        ));
+        assert!(matches!(
+            gateway.dump_recording("bystander", "r-1"),
+            Err(FrameError::NotFound(_))
+        ));
+        let dump = gateway.dump_recording("cheater", "r-1").unwrap();
+        assert_eq!(dump.reason.as_deref(), Some("speed report"));
+        let json = serde_json::to_value(&dump.samples).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+        assert_eq!(json[0]["stand_id"], "player-cheater");
+        assert_eq!(json[1]["stand_id"], "redacted");
+        assert_eq!(json[2]["buttons"][0]["state"], "pressed");
+        assert!(!json.to_string().contains("secret-session"));
+        gateway.flag_recording("bystander", None);
+        gateway.dump_recording("bystander", "r-1").unwrap();
+        let reports = dir.path().join("∞");
+        assert!(reports.join("reports;r-1;cheater;inputs").exists());
+        assert!(reports.join("reports;r-1;bystander;inputs").exists());
 
-        *last = now;
+        assert!(gateway.unflag_recording("cheater").is_ok());
+        assert!(gateway.unflag_recording("bystander").is_ok());
+        assert!(gateway.flagged_recordings().is_empty());
     }
 
-    fn handle(&self, frame: &FrameEnvelope) -> String {
-        self.accrue_interest();
-        match frame
-            .payload
-            .get("kind")
-            .and_then(Value::as_str)
-            .unwrap_or("unknown")
-        {
-            "balance_query" => {
-                let label = frame
-                    .payload
-                    .get("label")
-                    .and_then(Value::as_str)
-                    .unwrap_or(";<unknown>;");
-                let balance = self.balance_of(label);
-                format!("bank::balance {label} = {balance}")
-            }
-            "transfer" => self.handle_transfer(&frame.payload),
-            _ => format!(
-                "bank::{} routed (seq {})",
-                frame.namespace.trim_matches(';'),
-                frame.seq
-            ),
-        }
-    }
+    #[test]
+    fn audio_telemetry_surfaces_in_status() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        assert!(gateway.status().audio.is_none());
+
+        let ack = gateway.handle_frame(FrameEnvelope {
+            session_id: "speakers".into(),
+            seq: 1,
+            namespace: ";∞;speaker;engine;".into(),
+            kind: FrameKind::Audio,
+            payload: serde_json::json!({
+                "kind": "telemetry",
+                "underruns": 2,
+                "render_us_avg": 40,
+                "render_us_max": 310,
+                "buffered_ms": 96,
+                "active_voices": 3,
+                "sample_rate": 44_100,
+            }),
+            signature: None,
+        });
+        assert!(ack.notes.iter().any(|n| n.contains("2 underruns")));
 
-    fn balance_of(&self, label: &str) -> u128 {
-        let ledger = self.ledger.lock().expect("ledger mutex poisoned");
-        ledger.get(label).copied().unwrap_or_default()
+        let audio = gateway.status().audio.expect("telemetry recorded");
+        assert_eq!((audio.underruns, audio.active_voices), (2, 3));
+        assert!(audio.received_ms > 0);
     }
 
-    fn handle_transfer(&self, payload: &Value) -> String {
-        let from = payload
-            .get("from")
-            .and_then(Value::as_str)
-            .unwrap_or(";<missing-from>;");
-        let to = payload
-            .get("to")
-            .and_then(Value::as_str)
-            .unwrap_or(";<missing-to>;");
-        let amount = payload.get("amount").and_then(Value::as_u64).unwrap_or(0) as u128;
+    #[test]
+    fn dns_table_round_trips_through_canon_file() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        let exported = gateway.export_dns();
+        assert!(exported.starts_with("# version 0\n"));
+        assert!(exported.contains(";∞;bank;infinity; ;omega;bank;infinity; Gravity-backed"));
 
-        if amount == 0 {
-            return "bank::transfer rejected (amount=0)".into();
-        }
+        let bumped = exported.replace("# version 0", "# version 1")
+            + ";∞;sky;show; ;omega;sky;show; Sky slideshow\n";
+        assert!(gateway.import_dns(&exported, false).is_err());
+        assert!(gateway.import_dns(";∞;a; omega.a", true).is_err());
+        let imported = gateway.import_dns(&bumped, false).unwrap();
+        assert_eq!((imported.version, imported.records), (1, 8));
 
-        let mut ledger = self.ledger.lock().expect("ledger mutex poisoned");
-        let from_balance = ledger.get(from).copied().unwrap_or_default();
-        if from_balance < amount {
-            return format!(
-                "bank::transfer rejected ({from} insufficient: {from_balance} < {amount})"
-            );
-        }
+        let restarted = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        assert_eq!(restarted.export_dns(), gateway.export_dns());
+        let dns = restarted.services.dns.lock().unwrap();
+        assert!(dns.resolve(";∞;sky;show;").contains("omega.sky.show"));
+        drop(dns);
+
+        // An operator edits the file in place and reloads, version unchecked.
+        let edited = bumped.replace(";∞;sky;show; ;omega;sky;show; Sky slideshow\n", "");
+        GatewayStore::at(dir.path())
+            .save_text(DNS_RECORDS_PATH, &edited)
+            .unwrap();
+        let reloaded = restarted.reload_dns().unwrap();
+        assert_eq!((reloaded.version, reloaded.records), (1, 7));
+        let memory_only = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        assert_eq!(memory_only.reload_dns().unwrap_err().status(), 404);
+    }
+
+    #[test]
+    fn operators_list_and_kick_sessions_and_see_escrow_locks() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        gateway.freeze_interest();
+        let session = handshake(&gateway, PlanetId::Moon, &["banking"]);
+        let sessions = gateway.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].planet, PlanetId::Moon);
+        assert_eq!(sessions[0].phone.as_deref(), Some("********54"));
+        assert!(sessions[0].capabilities.contains(&Capability::Banking));
 
-        let to_balance = ledger.get(to).copied().unwrap_or_default();
-        ledger.insert(from.into(), from_balance - amount);
-        ledger.insert(to.into(), to_balance + amount);
+        gateway.kick_session(&session).unwrap();
+        assert!(gateway.sessions().is_empty());
+        assert!(gateway.require(&session, Capability::Banking).is_err());
+        assert_eq!(gateway.kick_session(&session).unwrap_err().status(), 404);
 
-        format!("bank::transfer {amount} {from} → {to} ok")
+        let side = |label: &str, amount: u128| EscrowSide {
+            label: label.into(),
+            amount,
+            item: None,
+        };
+        let open = gateway
+            .open_escrow(
+                "9132077554",
+                EscrowRequest {
+                    planet: PlanetId::Earth,
+                    offer: side(";9132077554;fun;", 30_000),
+                    ask: side(";5550001111;main;", 10),
+                    deadline_blocks: 4,
+                },
+            )
+            .unwrap();
+        let locks = gateway.escrow_locks();
+        assert_eq!(locks.len(), 1);
+        assert_eq!(
+            (locks[0].escrow_id, locks[0].label.as_str(), locks[0].amount),
+            (open.id, ";********54;fun;", 30_000)
+        );
+        assert_eq!(locks[0].deadline_height, open.deadline_height);
     }
-}
 
-#[derive(Debug, Default)]
-struct MiningDispatch;
+    #[test]
+    fn migration_codes_move_a_session_to_a_new_device_once() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let old = handshake(&gateway, PlanetId::Mars, &["banking", "mining"]);
+        let identity = |phone: &str| IdentityDescriptor {
+            phone: phone.into(),
+            label: "comet".into(),
+            display_name: "Ω".into(),
+            presence_state: "online".into(),
+        };
+        assert_eq!(
+            gateway
+                .mint_migration_code(&old, identity("5550001111"), "t")
+                .unwrap_err()
+                .status(),
+            403
+        );
+        let first = gateway
+            .mint_migration_code(&old, identity("9132077554"), "t0")
+            .unwrap();
+        let code = gateway
+            .mint_migration_code(&old, identity("9132077554"), "t1")
+            .unwrap();
+        assert_eq!(code.code.len(), 14);
+        // Only the newest code counts.
+        assert!(gateway
+            .redeem_migration_code(&first.code, "d".into())
+            .is_err());
 
-impl MiningDispatch {
-    fn handle(&self, frame: &FrameEnvelope) -> String {
-        match frame.kind {
-            FrameKind::MineJob => format!("mining dispatched job seq {}", frame.seq),
-            FrameKind::MineResult => format!("mining verified result seq {}", frame.seq),
-            _ => "mining received unexpected frame".into(),
-        }
+        let moved = gateway
+            .redeem_migration_code(&code.code.to_lowercase(), "desktop".into())
+            .unwrap();
+        assert_eq!(moved.replaced_session, old);
+        assert_eq!(moved.auth_token, "t1");
+        let new = &moved.handshake.session_id;
+        assert_ne!(new, &old);
+        assert_eq!(moved.handshake.planet, PlanetId::Mars);
+        assert_eq!(
+            moved.handshake.identity.as_ref().unwrap().phone,
+            "9132077554"
+        );
+        assert!(gateway.require(new, Capability::Mining).is_ok());
+        assert!(gateway.require(&old, Capability::Banking).is_err());
+        let sessions = gateway.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].client_id, "desktop");
+        assert_eq!(sessions[0].phone.as_deref(), Some("********54"));
+
+        assert_eq!(
+            gateway
+                .redeem_migration_code(&code.code, "again".into())
+                .unwrap_err(),
+            AuthError::Unverified
+        );
+        assert!(gateway
+            .mint_migration_code(&old, identity("9132077554"), "t1")
+            .is_err());
     }
-}
 
-#[derive(Debug, Default)]
-struct SpeakerEngine;
+    #[test]
+    fn session_timelines_count_frames_and_redact_rejections() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        assert!(gateway.session_timeline(&session, 60_000).is_none());
+        let frame = |kind, payload| FrameEnvelope {
+            session_id: session.clone(),
+            seq: 1,
+            namespace: ";bank;".into(),
+            kind,
+            payload,
+            signature: None,
+        };
+        gateway.handle_frame(gateway.sign(frame(FrameKind::Query, Value::Null)));
+        gateway.handle_frame(gateway.sign(frame(FrameKind::MineJob, Value::Null)));
+        gateway.handle_frame(gateway.sign(frame(
+            FrameKind::Event,
+            serde_json::json!({
+                "kind": "transfer",
+                "from": ";5550001111;main;",
+                "to": ";9132077554;fun;",
+                "amount": 10,
+            }),
+        )));
 
-impl SpeakerEngine {
-    fn handle(&self, frame: &FrameEnvelope) -> String {
-        format!(
-            "speaker scheduled audio burst for namespace {}",
-            frame.namespace
-        )
+        let timeline = gateway.session_timeline(&session, 3_600_000).unwrap();
+        let frames: u32 = timeline
+            .buckets
+            .iter()
+            .flat_map(|b| b.frames.values())
+            .sum();
+        let rejected: u32 = timeline.buckets.iter().map(|b| b.rejected).sum();
+        assert_eq!((frames, rejected), (3, 1));
+        assert!(!timeline.last_routes.is_empty());
+        assert_eq!(timeline.last_errors.len(), 2);
+        assert_eq!(timeline.last_errors[0].kind, "Event");
+        assert!(timeline.last_errors[0].note.contains("rejected"));
+        assert!(!timeline.last_errors[0].note.contains("5550001111"));
+        assert_eq!(timeline.last_errors[1].kind, "MineJob");
     }
-}
 
-#[derive(Debug, Default)]
-struct GameEngine;
+    #[test]
+    fn unknown_frame_kinds_decode_and_are_refused() {
+        let gateway = OmegaGateway::with_store(GatewayStore::default()).unwrap();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let frame: FrameEnvelope = serde_json::from_value(serde_json::json!({
+            "session_id": session,
+            "seq": 9,
+            "namespace": ";∞;holo;",
+            "kind": "HOLOGRAM",
+        }))
+        .unwrap();
+        assert_eq!(frame.kind, FrameKind::Unknown("HOLOGRAM".into()));
+        assert_eq!(serde_json::to_value(&frame.kind).unwrap(), "HOLOGRAM");
+        let known: FrameKind = serde_json::from_value("TICK_FRAME".into()).unwrap();
+        assert_eq!(known, FrameKind::TickFrame);
 
-impl GameEngine {
-    fn handle(&self, frame: &FrameEnvelope) -> String {
-        format!(
-            "game tick routed for {} (seq {})",
-            frame.namespace, frame.seq
-        )
+        let ack = gateway.handle_frame(frame);
+        assert!(!ack.accepted);
+        assert!(ack.routed.is_empty());
+        assert!(ack.notes.iter().any(|n| n == "unsupported kind HOLOGRAM"));
+        assert_eq!(gateway.overview().frames.totals["Unknown"], 1);
+        assert!(!FrameKind::SUPPORTED
+            .iter()
+            .any(|kind| matches!(kind, FrameKind::Unknown(_))));
     }
-}
 
-fn now_ms() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as i64
+    #[test]
+    fn overview_aggregates_sessions_frames_bank_blocks_and_storage() {
+        let dir = tempdir().unwrap();
+        let gateway = OmegaGateway::with_store(GatewayStore::at(dir.path())).unwrap();
+        gateway.freeze_interest();
+        let session = handshake(&gateway, PlanetId::Earth, &["banking"]);
+        let frame = |kind, payload| FrameEnvelope {
+            session_id: session.clone(),
+            seq: 1,
+            namespace: ";bank;".into(),
+            kind,
+            payload,
+            signature: None,
+        };
+        let transfer = serde_json::json!({
+            "kind": "transfer",
+            "from": ";9132077554;comet;",
+            "to": ";9132077554;fun;",
+            "amount": 40,
+        });
+        gateway.handle_frame(gateway.sign(frame(FrameKind::Event, transfer)));
+        for _ in 0..3 {
+            gateway.handle_frame(gateway.sign(frame(FrameKind::TickFrame, Value::Null)));
+        }
+        gateway.seal_block();
+
+        let overview = gateway.overview();
+        assert_eq!(overview.sessions.total, 1);
+        assert_eq!(overview.sessions.verified, 1);
+        assert_eq!(overview.sessions.by_planet[&PlanetId::Earth], 1);
+        assert_eq!(overview.frames.totals["TickFrame"], 3);
+        assert_eq!(overview.frames.totals["Event"], 1);
+        assert!(overview.frames.per_sec["TickFrame"] > 0.0);
+        assert_eq!(overview.bank.supply[&PlanetId::Earth], 6_080_000);
+        assert_eq!(overview.bank.transfers_considered, 1);
+        let movers = &overview.bank.top_movers;
+        assert_eq!(movers.len(), 2);
+        assert!(movers.iter().all(|m| m.volume == 40 && m.transfers == 1));
+        assert!(!movers.iter().any(|m| m.label.contains("9132077554")));
+        assert_eq!(overview.blocks.height, 1);
+        assert!(overview.blocks.last_seal_ms.is_some());
+        assert_eq!(overview.sim_ticks.samples, 3);
+        assert!(overview.storage.writes > 0);
+        assert_eq!(overview.storage.write_errors, 0);
+    }
 }
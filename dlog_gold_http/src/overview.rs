@@ -0,0 +1,255 @@
+//! One-call aggregate for the admin dashboard (`GET /admin/overview`).
+//!
+//! Most of it is read off the subsystems as they stand (sessions, ledgers,
+//! the transfer log, the block cursor, store counters). Two meters exist
+//! only for it: frames per kind over the last minute, and how long the game
+//! engine took over each `TickFrame`, kept for the last
+//! [`LATENCY_WINDOW`] ticks.
+
+use corelib::privacy::redact_phones;
+use serde::Serialize;
+use spec::PlanetId;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::bridge_queue::BridgeQueueStats;
+use crate::store::StoreStats;
+use crate::transfer::TransferRecord;
+
+/// Seconds the frame rates average over.
+pub const RATE_WINDOW_SECS: i64 = 60;
+/// Tick timings kept for the percentiles.
+pub const LATENCY_WINDOW: usize = 1024;
+/// Labels listed under `top_movers`.
+pub const TOP_MOVERS: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Overview {
+    pub at_ms: i64,
+    pub uptime_ms: i64,
+    pub sessions: SessionCounts,
+    pub frames: FrameRates,
+    pub bank: BankOverview,
+    pub blocks: BlockOverview,
+    pub sim_ticks: LatencySummary,
+    pub storage: StoreStats,
+    pub bridge: BridgeQueueStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionCounts {
+    pub total: usize,
+    /// Sessions bound to a verified phone.
+    pub verified: usize,
+    pub spectators: usize,
+    pub by_planet: BTreeMap<PlanetId, usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FrameRates {
+    pub window_secs: i64,
+    /// Frames per second by kind over the window.
+    pub per_sec: BTreeMap<String, f64>,
+    /// Frames by kind since boot.
+    pub totals: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BankOverview {
+    /// Sum of every ledger balance (escrow included), per planet.
+    pub supply: BTreeMap<PlanetId, u128>,
+    /// Taken out of supply by burn hooks since genesis, per planet.
+    pub burned: BTreeMap<PlanetId, u128>,
+    /// Labels with the most volume (sent plus received) in the recent
+    /// transfer log, phones redacted.
+    pub top_movers: Vec<Mover>,
+    /// Transfers the movers were counted over.
+    pub transfers_considered: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mover {
+    pub label: String,
+    pub planet: PlanetId,
+    pub volume: u128,
+    pub transfers: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlockOverview {
+    pub height: u64,
+    pub last_seal_ms: Option<i64>,
+    pub tip_producer: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+/// The [`TOP_MOVERS`] labels by volume across `records`, busiest first.
+pub fn top_movers(records: &[TransferRecord]) -> Vec<Mover> {
+    let mut volumes = HashMap::<(PlanetId, &str), (u128, u64)>::new();
+    for record in records {
+        for label in [&record.from, &record.to] {
+            let entry = volumes.entry((record.planet, label)).or_default();
+            entry.0 = entry.0.saturating_add(record.amount);
+            entry.1 += 1;
+        }
+    }
+    let mut movers: Vec<Mover> = volumes
+        .into_iter()
+        .map(|((planet, label), (volume, transfers))| Mover {
+            label: redact_phones(label),
+            planet,
+            volume,
+            transfers,
+        })
+        .collect();
+    movers.sort_by(|a, b| {
+        b.volume
+            .cmp(&a.volume)
+            .then_with(|| a.planet.cmp(&b.planet))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    movers.truncate(TOP_MOVERS);
+    movers
+}
+
+#[derive(Debug, Default)]
+struct MeterState {
+    totals: BTreeMap<String, u64>,
+    /// (unix second, frames by kind in that second), oldest first.
+    seconds: VecDeque<(i64, BTreeMap<String, u64>)>,
+}
+
+/// Frames per kind, since boot and per second over the last minute.
+#[derive(Debug, Default)]
+pub struct FrameMeter {
+    state: Mutex<MeterState>,
+}
+
+impl FrameMeter {
+    pub fn record(&self, kind: &str, now_ms: i64) {
+        let second = now_ms.div_euclid(1000);
+        let mut state = self.state.lock().expect("frame meter mutex poisoned");
+        *state.totals.entry(kind.to_string()).or_default() += 1;
+        if state.seconds.back().map(|(s, _)| *s) != Some(second) {
+            state.seconds.push_back((second, BTreeMap::new()));
+        }
+        while state
+            .seconds
+            .front()
+            .is_some_and(|(s, _)| *s <= second - RATE_WINDOW_SECS)
+        {
+            state.seconds.pop_front();
+        }
+        if let Some((_, counts)) = state.seconds.back_mut() {
+            *counts.entry(kind.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn rates(&self, now_ms: i64) -> FrameRates {
+        let since = now_ms.div_euclid(1000) - RATE_WINDOW_SECS;
+        let state = self.state.lock().expect("frame meter mutex poisoned");
+        let mut per_sec = BTreeMap::<String, f64>::new();
+        for (_, counts) in state.seconds.iter().filter(|(s, _)| *s > since) {
+            for (kind, count) in counts {
+                *per_sec.entry(kind.clone()).or_default() += *count as f64;
+            }
+        }
+        for rate in per_sec.values_mut() {
+            *rate /= RATE_WINDOW_SECS as f64;
+        }
+        FrameRates {
+            window_secs: RATE_WINDOW_SECS,
+            per_sec,
+            totals: state.totals.clone(),
+        }
+    }
+}
+
+/// The last [`LATENCY_WINDOW`] durations, in microseconds.
+#[derive(Debug, Default)]
+pub struct LatencyWindow {
+    samples: Mutex<VecDeque<u64>>,
+}
+
+impl LatencyWindow {
+    pub fn record(&self, micros: u64) {
+        let mut samples = self.samples.lock().expect("latency mutex poisoned");
+        if samples.len() == LATENCY_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(micros);
+    }
+
+    /// Nearest-rank percentiles over the window.
+    pub fn summary(&self) -> LatencySummary {
+        let mut sorted: Vec<u64> = self
+            .samples
+            .lock()
+            .expect("latency mutex poisoned")
+            .iter()
+            .copied()
+            .collect();
+        sorted.sort_unstable();
+        let rank = |p: usize| match sorted.len() {
+            0 => 0,
+            n => sorted[(n * p).div_ceil(100).clamp(1, n) - 1],
+        };
+        LatencySummary {
+            samples: sorted.len(),
+            p50_us: rank(50),
+            p90_us: rank(90),
+            p99_us: rank(99),
+            max_us: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meters_roll_over_their_windows() {
+        let meter = FrameMeter::default();
+        for _ in 0..120 {
+            meter.record("Query", 1_000);
+        }
+        meter.record("TickFrame", 30_500);
+        let rates = meter.rates(31_000);
+        assert_eq!(rates.per_sec["Query"], 2.0);
+        assert_eq!(rates.totals["TickFrame"], 1);
+        // A minute later the burst has left the window but not the totals.
+        meter.record("TickFrame", 61_000);
+        let later = meter.rates(61_000);
+        assert!(!later.per_sec.contains_key("Query"));
+        assert_eq!(later.totals["Query"], 120);
+
+        let window = LatencyWindow::default();
+        assert_eq!(window.summary(), LatencySummary::default());
+        for micros in 1..=100 {
+            window.record(micros);
+        }
+        let summary = window.summary();
+        assert_eq!(
+            (
+                summary.p50_us,
+                summary.p90_us,
+                summary.p99_us,
+                summary.max_us
+            ),
+            (50, 90, 99, 100)
+        );
+        for _ in 0..LATENCY_WINDOW {
+            window.record(7);
+        }
+        assert_eq!(window.summary().max_us, 7);
+    }
+}
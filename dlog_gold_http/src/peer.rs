@@ -0,0 +1,487 @@
+//! Block gossip between gateway instances.
+//!
+//! Peers are other gateways' base URLs (`OMEGA_PEERS`, comma separated).
+//! Freshly sealed blocks are pushed to every peer as `BLOCK` proposal frames;
+//! a sync loop pulls `/omega/blocks` from each peer, applies anything past
+//! the local tip through consensus validation, and acknowledges it. When the
+//! peer's blocks don't chain onto our tip, the loop walks back to a common
+//! ancestor and takes the peer's fork if it is longer; when the peer no
+//! longer retains the blocks we'd need, it jumps to the peer's tip. Each
+//! peer's reachability, height and rejected blocks feed `/omega/status`.
+
+use corelib::consensus::{BlockHeader, ConsensusMessage};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::omega::{now_ms, OmegaGateway, BLOCK_WINDOW};
+use dlog_error::FrameError;
+
+/// Headers requested per pull.
+pub const SYNC_BATCH: usize = 64;
+const SYNC_INTERVAL: Duration = Duration::from_secs(4);
+const PEER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Reply of `GET /omega/blocks`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocksResponse {
+    pub height: u64,
+    pub tip: Option<BlockHeader>,
+    pub headers: Vec<BlockHeader>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PeerStatus {
+    pub url: String,
+    pub reachable: bool,
+    pub height: Option<u64>,
+    pub tip_root: Option<String>,
+    /// Blocks the peer is ahead of us (negative when behind).
+    pub lag: i64,
+    pub last_seen_ms: Option<i64>,
+    /// Failed requests; these mark the peer unreachable.
+    pub failures: u64,
+    pub last_error: Option<String>,
+    /// Blocks from a reachable peer that failed validation.
+    pub rejections: u64,
+    pub last_rejection: Option<String>,
+}
+
+/// Why a sync round with one peer stopped.
+#[derive(Debug)]
+enum SyncError {
+    Unreachable(String),
+    Rejected(String),
+}
+
+#[derive(Debug)]
+struct Peer {
+    url: String,
+    status: Mutex<PeerStatus>,
+    /// `peer` session opened on the remote gateway, reused until it's refused.
+    session: Mutex<Option<String>>,
+}
+
+/// The parts of the remote handshake and frame replies a peer reads.
+#[derive(Debug, Deserialize)]
+struct PeerSession {
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerAck {
+    accepted: bool,
+    notes: Vec<String>,
+}
+
+impl Peer {
+    fn seen(&self, blocks: &BlocksResponse, local_height: u64) {
+        let mut status = self.status.lock().expect("peer mutex poisoned");
+        status.reachable = true;
+        status.height = Some(blocks.height);
+        status.tip_root = blocks.tip.as_ref().map(|tip| tip.root.clone());
+        status.lag = blocks.height as i64 - local_height as i64;
+        status.last_seen_ms = Some(now_ms());
+    }
+
+    fn refresh_lag(&self, local_height: u64) {
+        let mut status = self.status.lock().expect("peer mutex poisoned");
+        if let Some(height) = status.height {
+            status.lag = height as i64 - local_height as i64;
+        }
+    }
+
+    fn failed(&self, err: impl ToString) {
+        let mut status = self.status.lock().expect("peer mutex poisoned");
+        status.reachable = false;
+        status.failures += 1;
+        status.last_error = Some(err.to_string());
+    }
+
+    /// The peer answered, but with blocks we won't apply; it stays reachable
+    /// so its lag still shows in `sync_lag`.
+    fn rejected(&self, err: String) {
+        let mut status = self.status.lock().expect("peer mutex poisoned");
+        status.rejections += 1;
+        status.last_rejection = Some(err);
+    }
+}
+
+#[derive(Debug)]
+pub struct PeerSet {
+    client: Client,
+    peers: Vec<Peer>,
+}
+
+impl PeerSet {
+    pub fn new(urls: impl IntoIterator<Item = String>) -> Self {
+        let peers = urls
+            .into_iter()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .map(|url| Peer {
+                status: Mutex::new(PeerStatus {
+                    url: url.clone(),
+                    ..PeerStatus::default()
+                }),
+                url,
+                session: Mutex::new(None),
+            })
+            .collect();
+        let client = Client::builder()
+            .timeout(PEER_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { client, peers }
+    }
+
+    pub fn from_env() -> Self {
+        let urls = std::env::var("OMEGA_PEERS").unwrap_or_default();
+        Self::new(urls.split(',').map(String::from))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    pub fn statuses(&self) -> Vec<PeerStatus> {
+        self.peers
+            .iter()
+            .map(|peer| peer.status.lock().expect("peer mutex poisoned").clone())
+            .collect()
+    }
+
+    /// Blocks the furthest reachable peer is ahead of us.
+    pub fn sync_lag(&self) -> u64 {
+        self.statuses()
+            .iter()
+            .filter(|status| status.reachable)
+            .map(|status| status.lag.max(0) as u64)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Push a locally sealed block to every peer. Each push runs on its own
+    /// task so a slow peer never holds up the producer loop.
+    pub fn announce(self: &Arc<Self>, gateway: &Arc<OmegaGateway>, header: &BlockHeader) {
+        for index in 0..self.peers.len() {
+            let (peers, gateway) = (self.clone(), gateway.clone());
+            let message = ConsensusMessage::Proposal {
+                header: header.clone(),
+            };
+            tokio::spawn(async move {
+                let peer = &peers.peers[index];
+                if let Err(err) = peers.send(&gateway, peer, &message).await {
+                    warn!("[peer] announce to {}: {err}", peer.url);
+                    peer.failed(err);
+                }
+            });
+        }
+    }
+
+    /// Pull and apply blocks from every peer that is ahead of us.
+    pub async fn sync(&self, gateway: &OmegaGateway) {
+        for peer in &self.peers {
+            match self.sync_peer(gateway, peer).await {
+                Ok(()) => {}
+                Err(SyncError::Unreachable(err)) => {
+                    warn!("[peer] sync from {}: {err}", peer.url);
+                    peer.failed(err);
+                }
+                Err(SyncError::Rejected(err)) => {
+                    warn!("[peer] rejected blocks from {}: {err}", peer.url);
+                    peer.rejected(err);
+                }
+            }
+        }
+    }
+
+    async fn fetch(&self, peer: &Peer, from: u64) -> Result<BlocksResponse, SyncError> {
+        self.client
+            .get(format!("{}/omega/blocks", peer.url))
+            .query(&[("from", from), ("limit", SYNC_BATCH as u64)])
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| SyncError::Unreachable(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| SyncError::Unreachable(e.to_string()))
+    }
+
+    async fn sync_peer(&self, gateway: &OmegaGateway, peer: &Peer) -> Result<(), SyncError> {
+        loop {
+            let blocks = self.fetch(peer, gateway.block_height() + 1).await?;
+            peer.seen(&blocks, gateway.block_height());
+            let Some(first) = blocks.headers.first() else {
+                return Ok(());
+            };
+
+            let before = gateway.tip();
+            let result = if first.extends(before.as_ref()) {
+                // Headers are validated one by one against the tip they extend;
+                // keep whatever prefix applied even if a later one is rejected.
+                let mut result = Ok(());
+                for header in blocks.headers {
+                    let height = header.height;
+                    if let Err(err) = gateway.accept_block(header) {
+                        result = Err(SyncError::Rejected(format!("block {height}: {err}")));
+                        break;
+                    }
+                }
+                result
+            } else {
+                self.recover(gateway, peer, blocks).await
+            };
+            peer.refresh_lag(gateway.block_height());
+            if let Some(tip) = gateway.tip().filter(|tip| Some(tip) != before.as_ref()) {
+                info!("[peer] synced to {} from {}", tip.height, peer.url);
+                self.acknowledge(gateway, peer, tip).await;
+            }
+            result?;
+        }
+    }
+
+    /// `blocks` didn't chain onto our tip. Walk back a batch at a time to the
+    /// newest block we share with the peer (genesis counts) and take its
+    /// fork from there; if the peer no longer retains blocks that far back,
+    /// or we've walked past our own window, jump to its tip instead.
+    async fn recover(
+        &self,
+        gateway: &OmegaGateway,
+        peer: &Peer,
+        blocks: BlocksResponse,
+    ) -> Result<(), SyncError> {
+        let rejected = |err: FrameError| SyncError::Rejected(err.to_string());
+        let Some(tip) = blocks.tip else {
+            return Err(SyncError::Rejected("peer sent blocks without a tip".into()));
+        };
+        let mut from = gateway.block_height() + 1;
+        let floor = from.saturating_sub(BLOCK_WINDOW as u64).max(1);
+        let mut headers = blocks.headers;
+        loop {
+            let shared = headers.iter().rev().find_map(|theirs| {
+                if gateway
+                    .block_at(theirs.height)
+                    .is_some_and(|ours| ours.root == theirs.root)
+                {
+                    Some(theirs.height)
+                } else {
+                    theirs.extends(None).then_some(0)
+                }
+            });
+            if let Some(shared) = shared {
+                let fork = self.fetch(peer, shared + 1).await?.headers;
+                info!("[peer] {} forked from us at height {shared}", peer.url);
+                return gateway.switch_fork(fork, &tip).map(drop).map_err(rejected);
+            }
+            let pruned = headers.first().is_none_or(|first| first.height > from);
+            if pruned || from <= floor {
+                info!(
+                    "[peer] {} no longer has our fork point; jumping to its tip",
+                    peer.url
+                );
+                return gateway.adopt_checkpoint(tip).map(drop).map_err(rejected);
+            }
+            from = from.saturating_sub(SYNC_BATCH as u64).max(floor);
+            headers = self.fetch(peer, from).await?.headers;
+        }
+    }
+
+    /// Tell `peer` we applied its blocks up to `tip`. A lost acceptance only
+    /// affects the peer's status output, so failures are just logged.
+    async fn acknowledge(&self, gateway: &OmegaGateway, peer: &Peer, tip: BlockHeader) {
+        let message = ConsensusMessage::Acceptance {
+            height: tip.height,
+            root: tip.root,
+            node: gateway.node_id().to_string(),
+        };
+        if let Err(err) = self.send(gateway, peer, &message).await {
+            warn!("[peer] acknowledge {} to {}: {err}", tip.height, peer.url);
+        }
+    }
+
+    /// Our `peer` session on `peer`, handshaking with the peer key if needed.
+    async fn session(&self, gateway: &OmegaGateway, peer: &Peer) -> Result<String, String> {
+        if let Some(session_id) = peer.session.lock().expect("peer mutex poisoned").clone() {
+            return Ok(session_id);
+        }
+        let opened: PeerSession = self
+            .client
+            .post(format!("{}/omega/handshake", peer.url))
+            .json(&gateway.peer_handshake())
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| format!("handshake: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("handshake: {e}"))?;
+        *peer.session.lock().expect("peer mutex poisoned") = Some(opened.session_id.clone());
+        Ok(opened.session_id)
+    }
+
+    async fn send(
+        &self,
+        gateway: &OmegaGateway,
+        peer: &Peer,
+        message: &ConsensusMessage,
+    ) -> Result<(), String> {
+        let session_id = self.session(gateway, peer).await?;
+        let ack: PeerAck = self
+            .client
+            .post(format!("{}/omega/frame", peer.url))
+            .json(&gateway.consensus_frame(&session_id, message))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !ack.accepted {
+            // Most likely the peer restarted and forgot the session.
+            *peer.session.lock().expect("peer mutex poisoned") = None;
+            return Err(ack.notes.join("; "));
+        }
+        Ok(())
+    }
+}
+
+/// Pull from peers every few seconds, well inside one block interval.
+pub fn spawn_peer_sync(gateway: Arc<OmegaGateway>, peers: Arc<PeerSet>) {
+    if peers.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SYNC_INTERVAL);
+        loop {
+            ticker.tick().await;
+            peers.sync(&gateway).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::GatewayStore;
+    use axum::{extract::Query, routing::get, Json, Router};
+    use corelib::consensus::SingleProducer;
+    use std::collections::HashMap;
+
+    fn node(id: &str) -> Arc<OmegaGateway> {
+        Arc::new(
+            OmegaGateway::with_store(GatewayStore::default())
+                .unwrap()
+                .with_consensus(
+                    id.into(),
+                    Box::new(SingleProducer {
+                        leader: "leader".into(),
+                    }),
+                ),
+        )
+    }
+
+    /// Serve `gateway`'s `/omega/blocks` on an ephemeral port, as if it had
+    /// pruned every block below `floor`.
+    async fn serve_blocks(gateway: Arc<OmegaGateway>, floor: u64) -> String {
+        let app = Router::new()
+            .route(
+                "/omega/blocks",
+                get(move |Query(query): Query<HashMap<String, u64>>| {
+                    let gateway = gateway.clone();
+                    async move {
+                        let from = query.get("from").copied().unwrap_or(0).max(floor);
+                        Json(BlocksResponse {
+                            height: gateway.block_height(),
+                            tip: gateway.tip(),
+                            headers: gateway.blocks_from(from, SYNC_BATCH),
+                        })
+                    }
+                }),
+            )
+            .route("/omega/frame", axum::routing::post(|| async { "ok" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn follower_pulls_missing_blocks_and_reports_lag() {
+        let leader = node("leader");
+        for _ in 0..3 {
+            leader.seal_block();
+        }
+        let url = serve_blocks(leader.clone(), 0).await;
+
+        let follower = node("follower");
+        let peers = PeerSet::new([url, "http://127.0.0.1:9".to_string()]);
+        peers.sync(&follower).await;
+
+        assert_eq!(follower.block_height(), 3);
+        assert_eq!(follower.tip(), leader.tip());
+        let statuses = peers.statuses();
+        assert!(statuses[0].reachable);
+        assert_eq!((statuses[0].height, statuses[0].lag), (Some(3), 0));
+        assert!(!statuses[1].reachable && statuses[1].failures == 1);
+        assert_eq!(peers.sync_lag(), 0);
+
+        // A forged block from the follower never reaches the leader's chain.
+        let forged = follower.seal_block().header;
+        assert!(leader.accept_block(forged).is_err());
+        assert_eq!(leader.block_height(), 3);
+    }
+
+    #[tokio::test]
+    async fn follower_takes_a_longer_fork_or_jumps_past_pruned_blocks() {
+        let leader = node("leader");
+        let rival = node("leader");
+        for _ in 0..2 {
+            let header = leader.seal_block().header;
+            rival.accept_block(header).unwrap();
+        }
+        // Same producer and ledger, so only the seal time tells block 3 apart.
+        rival.seal_block();
+        std::thread::sleep(Duration::from_millis(2));
+        for _ in 0..4 {
+            leader.seal_block();
+        }
+        assert_ne!(rival.block_at(3), leader.block_at(3));
+
+        // Block 3 no longer chains; the rival walks back to 2 and switches.
+        let peers = PeerSet::new([serve_blocks(leader.clone(), 0).await]);
+        peers.sync(&rival).await;
+        assert_eq!(rival.tip(), leader.tip());
+        assert_eq!(rival.block_at(3), leader.block_at(3));
+
+        // A newcomer whose next block the peer has pruned jumps to its tip.
+        let newcomer = node("newcomer");
+        let peers = PeerSet::new([serve_blocks(leader.clone(), 4).await]);
+        peers.sync(&newcomer).await;
+        assert_eq!(newcomer.tip(), leader.tip());
+        assert_eq!(peers.statuses()[0].rejections, 0);
+    }
+
+    #[tokio::test]
+    async fn rejected_blocks_keep_the_peer_reachable_and_lagging() {
+        // Blocks sealed by a node the policy doesn't recognise as producer.
+        let rogue = node("rogue");
+        for _ in 0..3 {
+            rogue.seal_block();
+        }
+        let follower = node("follower");
+        let peers = PeerSet::new([serve_blocks(rogue, 0).await]);
+        peers.sync(&follower).await;
+
+        assert_eq!(follower.block_height(), 0);
+        let status = &peers.statuses()[0];
+        assert!(status.reachable);
+        assert_eq!((status.failures, status.rejections), (0, 1));
+        assert!(status.last_rejection.is_some());
+        assert_eq!(peers.sync_lag(), 3);
+    }
+}
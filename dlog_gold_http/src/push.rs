@@ -0,0 +1,724 @@
+//! Push notifications to registered devices.
+//!
+//! Phones register devices with a verified session: a browser's Web Push
+//! subscription, or an APNs/FCM token. Each device names the event kinds it
+//! wants (`gift_unlock` and `auction_*` by default). Events on the bus go to
+//! every device subscribed to their kind; events delivered to label inboxes
+//! go only to the devices of the phone that owns the label. Sending happens
+//! on a background task from a short queue, so a slow push service never
+//! holds up a seal; when the queue is full, pushes are skipped and logged.
+//!
+//! Web Push is sent as RFC 8291 `aes128gcm` with VAPID (RFC 8292) keys from
+//! `OMEGA_VAPID_PUBLIC_KEY` / `OMEGA_VAPID_PRIVATE_KEY` (base64url, as
+//! `web-push generate-vapid-keys` prints them) and `OMEGA_VAPID_SUBJECT`.
+//! APNs and FCM are stubs that log what they would send. Devices live in
+//! `;∞;push;devices;`, keyed by the hashed phone.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use dlog_error::FrameError;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use ring::{aead, agreement, hkdf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::events::OmegaEvent;
+use crate::store::GatewayStore;
+
+const DEVICES_PATH: &str = ";∞;push;devices;";
+/// Devices one phone may register; the oldest goes first.
+pub const MAX_DEVICES: usize = 8;
+/// Kinds a device hears when it registers without naming any.
+pub const DEFAULT_TOPICS: [&str; 2] = ["gift_unlock", "auction_*"];
+/// Events waiting for the push task before new ones are skipped.
+const PUSH_QUEUE: usize = 64;
+/// How long a push service should hold an undelivered message.
+const WEB_PUSH_TTL_SECS: u32 = 24 * 60 * 60;
+/// VAPID tokens are good for 12 hours (RFC 8292 allows up to 24).
+const VAPID_TTL_SECS: i64 = 12 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Web,
+    Apns,
+    Fcm,
+}
+
+/// A browser's `PushSubscription`, as `JSON.stringify` writes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebSubscription {
+    pub endpoint: String,
+    pub keys: WebSubscriptionKeys,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebSubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    pub id: String,
+    pub platform: Platform,
+    /// APNs/FCM device token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<WebSubscription>,
+    pub topics: Vec<String>,
+    pub registered_ms: i64,
+}
+
+impl Device {
+    pub fn wants(&self, kind: &str) -> bool {
+        self.topics
+            .iter()
+            .any(|topic| match topic.strip_suffix('*') {
+                Some(prefix) => kind.starts_with(prefix),
+                None => topic == kind,
+            })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceRegistration {
+    pub platform: Platform,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub subscription: Option<WebSubscription>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+/// Registered devices per owner (hashed phone).
+#[derive(Debug, Default)]
+pub struct DeviceBook {
+    store: GatewayStore,
+    devices: Mutex<BTreeMap<String, Vec<Device>>>,
+}
+
+impl DeviceBook {
+    pub fn load(store: GatewayStore) -> Self {
+        let devices = store.load_json(DEVICES_PATH).unwrap_or_default();
+        Self {
+            store,
+            devices: Mutex::new(devices),
+        }
+    }
+
+    fn persist(&self, devices: &BTreeMap<String, Vec<Device>>) {
+        if let Err(err) = self.store.save_json(DEVICES_PATH, devices) {
+            warn!("[push] failed to persist {DEVICES_PATH}: {err}");
+        }
+    }
+
+    /// Add a device for `owner`. Registering the same token or endpoint
+    /// again replaces the old entry.
+    pub fn register(
+        &self,
+        owner: &str,
+        registration: DeviceRegistration,
+        now_ms: i64,
+    ) -> Result<Device, FrameError> {
+        let malformed = |msg: &str| FrameError::Malformed(msg.to_string());
+        match registration.platform {
+            Platform::Web => {
+                let subscription = registration
+                    .subscription
+                    .as_ref()
+                    .ok_or_else(|| malformed("web devices need a subscription"))?;
+                if !subscription.endpoint.starts_with("https://") {
+                    return Err(malformed("subscription endpoint must be https"));
+                }
+                SubscriberKeys::decode(subscription)?;
+            }
+            Platform::Apns | Platform::Fcm => {
+                if registration
+                    .token
+                    .as_deref()
+                    .is_none_or(|t| t.trim().is_empty())
+                {
+                    return Err(malformed("apns and fcm devices need a token"));
+                }
+            }
+        }
+        let topics = if registration.topics.is_empty() {
+            DEFAULT_TOPICS.map(String::from).to_vec()
+        } else {
+            registration.topics
+        };
+        let device = Device {
+            id: Uuid::new_v4().to_string(),
+            platform: registration.platform,
+            token: registration.token,
+            subscription: registration.subscription,
+            topics,
+            registered_ms: now_ms,
+        };
+
+        let mut devices = self.devices.lock().expect("push devices mutex poisoned");
+        let owned = devices.entry(owner.to_string()).or_default();
+        owned.retain(|d| d.token != device.token || d.subscription != device.subscription);
+        if owned.len() >= MAX_DEVICES {
+            owned.remove(0);
+        }
+        owned.push(device.clone());
+        self.persist(&devices);
+        Ok(device)
+    }
+
+    pub fn list(&self, owner: &str) -> Vec<Device> {
+        let devices = self.devices.lock().expect("push devices mutex poisoned");
+        devices.get(owner).cloned().unwrap_or_default()
+    }
+
+    pub fn remove(&self, owner: &str, id: &str) -> bool {
+        let mut devices = self.devices.lock().expect("push devices mutex poisoned");
+        let Some(owned) = devices.get_mut(owner) else {
+            return false;
+        };
+        let before = owned.len();
+        owned.retain(|d| d.id != id);
+        let removed = owned.len() < before;
+        devices.retain(|_, owned| !owned.is_empty());
+        if removed {
+            self.persist(&devices);
+        }
+        removed
+    }
+
+    /// Devices subscribed to `kind`: every owner's, or only `owners`'.
+    pub fn subscribers(&self, kind: &str, owners: Option<&[String]>) -> Vec<Device> {
+        let devices = self.devices.lock().expect("push devices mutex poisoned");
+        devices
+            .iter()
+            .filter(|(owner, _)| owners.is_none_or(|owners| owners.contains(owner)))
+            .flat_map(|(_, owned)| owned.iter())
+            .filter(|device| device.wants(kind))
+            .cloned()
+            .collect()
+    }
+
+    /// Drop every device `owner` registered (identity deletion); how many.
+    pub fn purge_phone(&self, owner: &str) -> usize {
+        let mut devices = self.devices.lock().expect("push devices mutex poisoned");
+        let purged = devices.remove(owner).map_or(0, |owned| owned.len());
+        if purged > 0 {
+            self.persist(&devices);
+        }
+        purged
+    }
+
+    /// Drop a device the push service says is gone.
+    fn forget(&self, id: &str) {
+        let mut devices = self.devices.lock().expect("push devices mutex poisoned");
+        for owned in devices.values_mut() {
+            owned.retain(|d| d.id != id);
+        }
+        devices.retain(|_, owned| !owned.is_empty());
+        self.persist(&devices);
+    }
+}
+
+/// What a device shows.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PushMessage {
+    pub kind: String,
+    pub title: String,
+    pub body: String,
+    pub height: u64,
+    pub data: Value,
+}
+
+impl PushMessage {
+    pub fn for_event(event: &OmegaEvent) -> Self {
+        let title = match event.kind.as_str() {
+            "gift_unlock" => "Gift unlocked".to_string(),
+            kind if kind.starts_with("auction") => "Auction update".to_string(),
+            kind => kind.replace('_', " "),
+        };
+        Self {
+            kind: event.kind.clone(),
+            title,
+            body: format!("{} at block {}", event.kind.replace('_', " "), event.height),
+            height: event.height,
+            data: event.payload.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    /// The device unsubscribed or its token expired; it is dropped.
+    Gone,
+    Failed(String),
+}
+
+impl fmt::Display for PushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Gone => f.write_str("device gone"),
+            PushError::Failed(msg) => f.write_str(msg),
+        }
+    }
+}
+
+pub type PushFuture<'a> = Pin<Box<dyn Future<Output = Result<(), PushError>> + Send + 'a>>;
+
+/// One push service.
+pub trait PushAdapter: fmt::Debug + Send + Sync {
+    fn platform(&self) -> Platform;
+    fn send<'a>(&'a self, device: &'a Device, message: &'a PushMessage) -> PushFuture<'a>;
+
+    /// Key clients subscribe with, for services that have one.
+    fn server_key(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Logs what APNs or FCM would be sent.
+#[derive(Debug)]
+pub struct StubPush(pub Platform);
+
+impl PushAdapter for StubPush {
+    fn platform(&self) -> Platform {
+        self.0
+    }
+
+    fn send<'a>(&'a self, device: &'a Device, message: &'a PushMessage) -> PushFuture<'a> {
+        Box::pin(async move {
+            info!(
+                "[push] {:?} stub: `{}` to device {}",
+                self.0, message.title, device.id
+            );
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct WebPush {
+    client: reqwest::Client,
+    key_pair: EcdsaKeyPair,
+    public_key: String,
+    subject: String,
+}
+
+impl WebPush {
+    /// `None` unless both VAPID keys are set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let (Ok(public), Ok(private)) = (
+            std::env::var("OMEGA_VAPID_PUBLIC_KEY"),
+            std::env::var("OMEGA_VAPID_PRIVATE_KEY"),
+        ) else {
+            return Ok(None);
+        };
+        let subject = std::env::var("OMEGA_VAPID_SUBJECT")
+            .unwrap_or_else(|_| "mailto:ops@dlog.gold".to_string());
+        Self::new(public.trim(), private.trim(), subject).map(Some)
+    }
+
+    pub fn new(public_key: &str, private_key: &str, subject: String) -> Result<Self, String> {
+        let decode = |raw: &str| {
+            URL_SAFE_NO_PAD
+                .decode(raw.trim_end_matches('='))
+                .map_err(|err| format!("VAPID key is not base64url: {err}"))
+        };
+        let key_pair = EcdsaKeyPair::from_private_key_and_public_key(
+            &ECDSA_P256_SHA256_FIXED_SIGNING,
+            &decode(private_key)?,
+            &decode(public_key)?,
+            &SystemRandom::new(),
+        )
+        .map_err(|err| format!("VAPID keys don't form a P-256 pair: {err}"))?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            key_pair,
+            public_key: public_key.trim_end_matches('=').to_string(),
+            subject,
+        })
+    }
+
+    /// `Authorization` header value for `endpoint`.
+    fn vapid(&self, endpoint: &str) -> Result<String, PushError> {
+        let audience = url::Url::parse(endpoint)
+            .map_err(|err| PushError::Failed(format!("bad endpoint: {err}")))?
+            .origin()
+            .ascii_serialization();
+        let claims = serde_json::json!({
+            "aud": audience,
+            "exp": crate::omega::now_ms() / 1000 + VAPID_TTL_SECS,
+            "sub": self.subject,
+        });
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), signing_input.as_bytes())
+            .map_err(|_| PushError::Failed("VAPID signing failed".into()))?;
+        Ok(format!(
+            "vapid t={signing_input}.{}, k={}",
+            URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            self.public_key
+        ))
+    }
+}
+
+impl PushAdapter for WebPush {
+    fn platform(&self) -> Platform {
+        Platform::Web
+    }
+
+    fn send<'a>(&'a self, device: &'a Device, message: &'a PushMessage) -> PushFuture<'a> {
+        Box::pin(async move {
+            let subscription = device
+                .subscription
+                .as_ref()
+                .ok_or_else(|| PushError::Failed("no subscription".into()))?;
+            let payload =
+                serde_json::to_vec(message).map_err(|err| PushError::Failed(err.to_string()))?;
+            let body = encrypt(&SubscriberKeys::decode(subscription)?, &payload)?;
+            let response = self
+                .client
+                .post(&subscription.endpoint)
+                .header("authorization", self.vapid(&subscription.endpoint)?)
+                .header("content-encoding", "aes128gcm")
+                .header("content-type", "application/octet-stream")
+                .header("ttl", WEB_PUSH_TTL_SECS.to_string())
+                .header("urgency", "normal")
+                .body(body)
+                .send()
+                .await
+                .map_err(|err| PushError::Failed(err.to_string()))?;
+            match response.status().as_u16() {
+                200..=299 => Ok(()),
+                404 | 410 => Err(PushError::Gone),
+                status => Err(PushError::Failed(format!("push service answered {status}"))),
+            }
+        })
+    }
+
+    /// The VAPID `applicationServerKey`.
+    fn server_key(&self) -> Option<&str> {
+        Some(&self.public_key)
+    }
+}
+
+/// A subscription's keys: the browser's P-256 point and auth secret.
+struct SubscriberKeys {
+    public: Vec<u8>,
+    auth: Vec<u8>,
+}
+
+impl SubscriberKeys {
+    fn decode(subscription: &WebSubscription) -> Result<Self, FrameError> {
+        let decode = |raw: &str| URL_SAFE_NO_PAD.decode(raw.trim_end_matches('=')).ok();
+        match (
+            decode(&subscription.keys.p256dh),
+            decode(&subscription.keys.auth),
+        ) {
+            (Some(public), Some(auth)) if public.len() == 65 && auth.len() == 16 => {
+                Ok(Self { public, auth })
+            }
+            _ => Err(FrameError::Malformed(
+                "subscription keys must be a 65-byte p256dh and 16-byte auth, base64url".into(),
+            )),
+        }
+    }
+}
+
+impl From<FrameError> for PushError {
+    fn from(err: FrameError) -> Self {
+        PushError::Failed(err.to_string())
+    }
+}
+
+/// HKDF output length for ring.
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn hkdf(salt: &[u8], ikm: &[u8], info: &[&[u8]], len: usize) -> Result<Vec<u8>, PushError> {
+    let failed = |_| PushError::Failed("key derivation failed".into());
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(ikm);
+    let mut out = vec![0; len];
+    prk.expand(info, Len(len))
+        .map_err(failed)?
+        .fill(&mut out)
+        .map_err(failed)?;
+    Ok(out)
+}
+
+/// RFC 8291 message encryption: one `aes128gcm` record for `keys`.
+fn encrypt(keys: &SubscriberKeys, plaintext: &[u8]) -> Result<Vec<u8>, PushError> {
+    let failed = |what: &str| PushError::Failed(format!("web push {what} failed"));
+    let rng = SystemRandom::new();
+    let ephemeral = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)
+        .map_err(|_| failed("key generation"))?;
+    let server_public = ephemeral
+        .compute_public_key()
+        .map_err(|_| failed("key generation"))?;
+    let subscriber = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, &keys.public);
+    let shared = agreement::agree_ephemeral(ephemeral, &subscriber, |secret| secret.to_vec())
+        .map_err(|_| failed("key agreement"))?;
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt).map_err(|_| failed("salt"))?;
+    seal(keys, server_public.as_ref(), &shared, salt, plaintext)
+}
+
+fn seal(
+    keys: &SubscriberKeys,
+    server_public: &[u8],
+    shared: &[u8],
+    salt: [u8; 16],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, PushError> {
+    let (cek, nonce) = content_keys(keys, server_public, shared, &salt)?;
+    let key = aead::UnboundKey::new(&aead::AES_128_GCM, &cek)
+        .map_err(|_| PushError::Failed("web push key failed".into()))?;
+    let nonce = aead::Nonce::try_assume_unique_for_key(&nonce)
+        .map_err(|_| PushError::Failed("web push nonce failed".into()))?;
+    // A single, last record: the data, then the 0x02 delimiter.
+    let mut record = plaintext.to_vec();
+    record.push(2);
+    aead::LessSafeKey::new(key)
+        .seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut record)
+        .map_err(|_| PushError::Failed("web push encryption failed".into()))?;
+
+    let record_size = (record.len() as u32).max(4096);
+    let mut body = Vec::with_capacity(21 + server_public.len() + record.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&record_size.to_be_bytes());
+    body.push(server_public.len() as u8);
+    body.extend_from_slice(server_public);
+    body.extend_from_slice(&record);
+    Ok(body)
+}
+
+/// Content-encryption key and nonce for one message (RFC 8291 §3.4).
+fn content_keys(
+    keys: &SubscriberKeys,
+    server_public: &[u8],
+    shared: &[u8],
+    salt: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), PushError> {
+    let ikm = hkdf(
+        &keys.auth,
+        shared,
+        &[b"WebPush: info\0", &keys.public, server_public],
+        32,
+    )?;
+    let cek = hkdf(salt, &ikm, &[b"Content-Encoding: aes128gcm\0"], 16)?;
+    let nonce = hkdf(salt, &ikm, &[b"Content-Encoding: nonce\0"], 12)?;
+    Ok((cek, nonce))
+}
+
+#[derive(Debug)]
+struct PushJob {
+    event: OmegaEvent,
+    /// Only these owners' devices; every subscriber when `None`.
+    owners: Option<Vec<String>>,
+}
+
+/// Queues events for the push task.
+#[derive(Debug)]
+pub struct PushDispatcher {
+    queue: mpsc::Sender<PushJob>,
+    web_key: Option<String>,
+}
+
+impl PushDispatcher {
+    /// Start the push task; needs a Tokio runtime.
+    pub fn spawn(devices: Arc<DeviceBook>, adapters: Vec<Box<dyn PushAdapter>>) -> Self {
+        let web_key = adapters
+            .iter()
+            .find(|adapter| adapter.platform() == Platform::Web)
+            .and_then(|adapter| adapter.server_key().map(String::from));
+        let (queue, mut jobs) = mpsc::channel::<PushJob>(PUSH_QUEUE);
+        tokio::spawn(async move {
+            while let Some(job) = jobs.recv().await {
+                let message = PushMessage::for_event(&job.event);
+                let targets = devices.subscribers(&job.event.kind, job.owners.as_deref());
+                for device in targets {
+                    let Some(adapter) = adapters.iter().find(|a| a.platform() == device.platform)
+                    else {
+                        continue;
+                    };
+                    match adapter.send(&device, &message).await {
+                        Ok(()) => {}
+                        Err(PushError::Gone) => {
+                            info!("[push] device {} unsubscribed; dropping it", device.id);
+                            devices.forget(&device.id);
+                        }
+                        Err(err) => warn!("[push] {} to {} failed: {err}", message.kind, device.id),
+                    }
+                }
+            }
+        });
+        Self { queue, web_key }
+    }
+
+    /// VAPID key for browsers to subscribe with, when Web Push is on.
+    pub fn web_key(&self) -> Option<&str> {
+        self.web_key.as_deref()
+    }
+
+    /// Push `event` to subscribers, or only to `owners`' devices.
+    pub fn offer(&self, event: &OmegaEvent, owners: Option<Vec<String>>) {
+        let job = PushJob {
+            event: event.clone(),
+            owners,
+        };
+        match self.queue.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(job)) => {
+                warn!("[push] queue full; skipping {}", job.event.kind)
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn web_device(keys: WebSubscriptionKeys) -> DeviceRegistration {
+        DeviceRegistration {
+            platform: Platform::Web,
+            token: None,
+            subscription: Some(WebSubscription {
+                endpoint: "https://push.example/abc".into(),
+                keys,
+            }),
+            topics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn devices_register_per_owner_and_subscribe_to_topics() {
+        let book = DeviceBook::default();
+        let keys = WebSubscriptionKeys {
+            p256dh: URL_SAFE_NO_PAD.encode([4u8; 65]),
+            auth: URL_SAFE_NO_PAD.encode([1u8; 16]),
+        };
+        let web = book.register("alice", web_device(keys.clone()), 1).unwrap();
+        assert!(web.wants("auction_closed") && web.wants("gift_unlock"));
+        assert!(!web.wants("transfer"));
+        // Re-registering the same subscription replaces it.
+        book.register("alice", web_device(keys), 2).unwrap();
+        assert_eq!(book.list("alice").len(), 1);
+
+        let apns = DeviceRegistration {
+            platform: Platform::Apns,
+            token: Some("tok".into()),
+            subscription: None,
+            topics: vec!["transfer".into()],
+        };
+        book.register("bob", apns.clone(), 3).unwrap();
+        assert!(book
+            .register(
+                "bob",
+                DeviceRegistration {
+                    token: None,
+                    ..apns
+                },
+                4
+            )
+            .is_err());
+        assert!(book
+            .register(
+                "bob",
+                web_device(WebSubscriptionKeys {
+                    p256dh: "short".into(),
+                    auth: "x".into()
+                }),
+                5
+            )
+            .is_err());
+
+        assert_eq!(book.subscribers("gift_unlock", None).len(), 1);
+        assert_eq!(book.subscribers("transfer", None).len(), 1);
+        let only_alice = ["alice".to_string()];
+        assert!(book.subscribers("transfer", Some(&only_alice)).is_empty());
+        let id = book.list("alice")[0].id.clone();
+        assert!(!book.remove("bob", &id));
+        assert!(book.remove("alice", &id));
+        assert!(book.list("alice").is_empty());
+    }
+
+    #[test]
+    fn purging_a_phone_drops_only_its_devices() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = DeviceBook::load(GatewayStore::at(dir.path()));
+        let apns = |token: &str| DeviceRegistration {
+            platform: Platform::Apns,
+            token: Some(token.into()),
+            subscription: None,
+            topics: vec!["transfer".into()],
+        };
+        book.register("alice", apns("a1"), 1).unwrap();
+        book.register("alice", apns("a2"), 2).unwrap();
+        book.register("bob", apns("b1"), 3).unwrap();
+
+        assert_eq!(book.purge_phone("alice"), 2);
+        assert_eq!(book.purge_phone("alice"), 0);
+        let owners = ["alice".to_string()];
+        assert!(book.subscribers("transfer", Some(&owners)).is_empty());
+        assert_eq!(book.subscribers("transfer", None).len(), 1);
+        // The purge is persisted, so a restart doesn't bring them back.
+        let reloaded = DeviceBook::load(GatewayStore::at(dir.path()));
+        assert!(reloaded.list("alice").is_empty());
+        assert_eq!(reloaded.list("bob").len(), 1);
+    }
+
+    #[test]
+    fn web_push_records_decrypt_with_the_subscriber_key() {
+        let rng = SystemRandom::new();
+        let subscriber =
+            agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+        let keys = SubscriberKeys {
+            public: subscriber.compute_public_key().unwrap().as_ref().to_vec(),
+            auth: vec![7; 16],
+        };
+        let body = encrypt(&keys, b"gift unlocked").unwrap();
+
+        let (salt, rest) = body.split_at(16);
+        assert_eq!(u32::from_be_bytes(rest[..4].try_into().unwrap()), 4096);
+        let id_len = rest[4] as usize;
+        let (server_public, record) = rest[5..].split_at(id_len);
+        let server = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, server_public);
+        let shared =
+            agreement::agree_ephemeral(subscriber, &server, |secret| secret.to_vec()).unwrap();
+        let (cek, nonce) = content_keys(&keys, server_public, &shared, salt).unwrap();
+        let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek).unwrap());
+        let mut record = record.to_vec();
+        let plain = key
+            .open_in_place(
+                aead::Nonce::try_assume_unique_for_key(&nonce).unwrap(),
+                aead::Aad::empty(),
+                &mut record,
+            )
+            .unwrap();
+        assert_eq!(plain, b"gift unlocked\x02");
+    }
+}
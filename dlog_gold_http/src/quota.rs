@@ -0,0 +1,188 @@
+//! Per-identity, per-service frame quotas.
+//!
+//! Each quota caps how many frames one identity (verified phone, peer node,
+//! or the shared anonymous bucket) may send to a service path per minute
+//! (fixed one-minute windows). The path is the service the frame kind
+//! dispatches to (`;∞;bank;`, `;∞;mining;`, …), never the client-chosen
+//! namespace. The longest matching prefix wins, so `;∞;bank;` can be
+//! tighter than a catch-all `;∞;`. Configured with `OMEGA_FRAME_QUOTAS` as
+//! `prefix=limit` pairs separated by commas or whitespace
+//! (`;∞;bank;=600, ;∞;mining;=1200`); `off` disables quotas.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+const WINDOW_MS: i64 = 60_000;
+/// Acks start carrying usage once a session passes this share of its quota.
+const NEAR_LIMIT_PERCENT: u32 = 80;
+/// Applied when `OMEGA_FRAME_QUOTAS` is unset.
+const DEFAULT_QUOTAS: &str = ";∞;bank;=600";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NamespaceQuota {
+    pub prefix: String,
+    pub per_minute: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaStatus {
+    pub limits: Vec<NamespaceQuota>,
+    /// Frames rejected per prefix since boot.
+    pub rejections: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    window: i64,
+    /// (identity, index into `limits`) → frames this window.
+    counts: HashMap<(String, usize), u32>,
+}
+
+#[derive(Debug)]
+pub struct FrameQuotas {
+    /// Longest prefix first.
+    limits: Vec<NamespaceQuota>,
+    usage: Mutex<Usage>,
+    rejections: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Default for FrameQuotas {
+    fn default() -> Self {
+        Self::new(parse(DEFAULT_QUOTAS).expect("default quotas parse"))
+    }
+}
+
+impl FrameQuotas {
+    pub fn new(mut limits: Vec<NamespaceQuota>) -> Self {
+        limits.sort_by_key(|quota| std::cmp::Reverse(quota.prefix.len()));
+        Self {
+            limits,
+            usage: Mutex::new(Usage::default()),
+            rejections: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Quotas from `OMEGA_FRAME_QUOTAS`, or the defaults when unset.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("OMEGA_FRAME_QUOTAS") {
+            Ok(spec) => parse(&spec).map(Self::new),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Count one frame from `identity` to `service`. `Ok` carries a usage
+    /// note once the identity nears its limit; `Err` is the rejection.
+    pub fn check(
+        &self,
+        identity: &str,
+        service: &str,
+        now_ms: i64,
+    ) -> Result<Option<String>, String> {
+        let Some((index, quota)) = self
+            .limits
+            .iter()
+            .enumerate()
+            .find(|(_, quota)| service.starts_with(&quota.prefix))
+        else {
+            return Ok(None);
+        };
+        let window = now_ms.div_euclid(WINDOW_MS);
+        let mut usage = self.usage.lock().expect("quota mutex poisoned");
+        if usage.window != window {
+            usage.window = window;
+            usage.counts.clear();
+        }
+        let used = usage
+            .counts
+            .entry((identity.to_string(), index))
+            .or_default();
+        if *used >= quota.per_minute {
+            drop(usage);
+            let mut rejections = self.rejections.lock().expect("quota mutex poisoned");
+            *rejections.entry(quota.prefix.clone()).or_default() += 1;
+            let retry_s = ((window + 1) * WINDOW_MS - now_ms + 999) / 1000;
+            return Err(format!(
+                "quota {} exceeded ({}/min); retry in {retry_s}s",
+                quota.prefix, quota.per_minute
+            ));
+        }
+        *used += 1;
+        let near =
+            u64::from(*used) * 100 >= u64::from(quota.per_minute) * u64::from(NEAR_LIMIT_PERCENT);
+        Ok(near.then(|| {
+            format!(
+                "quota {} {}/{} per min",
+                quota.prefix, used, quota.per_minute
+            )
+        }))
+    }
+
+    pub fn status(&self) -> QuotaStatus {
+        QuotaStatus {
+            limits: self.limits.clone(),
+            rejections: self
+                .rejections
+                .lock()
+                .expect("quota mutex poisoned")
+                .clone(),
+        }
+    }
+}
+
+/// Parse `prefix=limit` pairs; `off` (or an empty spec) means no quotas.
+pub fn parse(spec: &str) -> Result<Vec<NamespaceQuota>, String> {
+    if spec.trim().eq_ignore_ascii_case("off") {
+        return Ok(Vec::new());
+    }
+    spec.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (prefix, limit) = pair
+                .rsplit_once('=')
+                .ok_or_else(|| format!("quota `{pair}` is not prefix=limit"))?;
+            if !prefix.starts_with(';') || !prefix.ends_with(';') {
+                return Err(format!("quota prefix `{prefix}` must be ;-framed"));
+            }
+            let per_minute = limit
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("quota limit `{limit}` must be a positive integer"))?;
+            Ok(NamespaceQuota {
+                prefix: prefix.to_string(),
+                per_minute,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix_limits_each_identity_per_minute() {
+        assert!(parse("bank=5").is_err());
+        assert!(parse(";∞;bank;=0").is_err());
+        assert!(parse("off").unwrap().is_empty());
+
+        let quotas = FrameQuotas::new(parse(";∞;=100, ;∞;bank;=5").unwrap());
+        let bank = ";∞;bank;";
+        let notes: Vec<_> = (0..5)
+            .map(|_| quotas.check("a", bank, 1_000).unwrap())
+            .collect();
+        assert_eq!(notes[2], None);
+        assert_eq!(notes[3].as_deref(), Some("quota ;∞;bank; 4/5 per min"));
+        assert_eq!(
+            quotas.check("a", bank, 1_000),
+            Err("quota ;∞;bank; exceeded (5/min); retry in 59s".into())
+        );
+
+        assert_eq!(quotas.check("b", bank, 1_000), Ok(None));
+        assert_eq!(quotas.check("a", ";∞;sky;", 1_000), Ok(None));
+        assert_eq!(quotas.check("a", ";∞;mining;", 1_000), Ok(None));
+        assert_eq!(quotas.check("a", bank, 61_000), Ok(None));
+        assert_eq!(quotas.status().rejections[";∞;bank;"], 1);
+    }
+}
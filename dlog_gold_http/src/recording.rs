@@ -0,0 +1,277 @@
+//! Opt-in input recording for anti-cheat review.
+//!
+//! Nothing is recorded until a moderator flags a player. From then on each
+//! bridge input and position snapshot for that player lands in a bounded ring
+//! buffer, and filing a report dumps the buffer as evidence. Session ids are
+//! never kept, and stand ids other than the flagged player's own
+//! (`player-<uuid>`) are redacted so a dump carries no one else's data.
+
+use dlog_error::FrameError;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::omega::{
+    now_ms, BridgeAxisSnapshot, BridgeButtonSnapshot, BridgeInputSnapshot, BridgePositionSnapshot,
+    Rotation2d, Vec3f,
+};
+
+/// Samples kept per flagged player; the oldest fall off first.
+pub const RECORDING_CAPACITY: usize = 4096;
+pub const REPORT_ID_MAX: usize = 64;
+const REDACTED: &str = "redacted";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedSample {
+    Input {
+        /// Gateway receipt time.
+        at_ms: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_ms: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stand_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+        buttons: Vec<BridgeButtonSnapshot>,
+        axes: Vec<BridgeAxisSnapshot>,
+    },
+    Position {
+        at_ms: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stand_id: Option<String>,
+        world: String,
+        pos: Vec3f,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        velocity: Option<Vec3f>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rotation: Option<Rotation2d>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingStatus {
+    pub player_uuid: String,
+    pub flagged_at_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub samples: usize,
+}
+
+/// Evidence written when a report is filed.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDump {
+    pub report_id: String,
+    pub player_uuid: String,
+    pub flagged_at_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub dumped_at_ms: i64,
+    pub samples: Vec<RecordedSample>,
+}
+
+#[derive(Debug)]
+struct Recording {
+    flagged_at_ms: i64,
+    reason: Option<String>,
+    samples: VecDeque<RecordedSample>,
+}
+
+impl Recording {
+    fn push(&mut self, sample: RecordedSample) {
+        if self.samples.len() >= RECORDING_CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InputRecorder {
+    recordings: Mutex<HashMap<String, Recording>>,
+}
+
+impl InputRecorder {
+    /// Start recording `player_uuid`. Re-flagging keeps the buffer and
+    /// updates the reason.
+    pub fn flag(&self, player_uuid: &str, reason: Option<String>) -> RecordingStatus {
+        let mut recordings = self.recordings.lock().expect("recorder mutex poisoned");
+        let recording = recordings
+            .entry(player_uuid.to_string())
+            .or_insert_with(|| Recording {
+                flagged_at_ms: now_ms(),
+                reason: None,
+                samples: VecDeque::new(),
+            });
+        if reason.is_some() {
+            recording.reason = reason;
+        }
+        status(player_uuid, recording)
+    }
+
+    /// Stop recording and drop the buffer.
+    pub fn unflag(&self, player_uuid: &str) -> bool {
+        let mut recordings = self.recordings.lock().expect("recorder mutex poisoned");
+        recordings.remove(player_uuid).is_some()
+    }
+
+    pub fn flagged(&self) -> Vec<RecordingStatus> {
+        let recordings = self.recordings.lock().expect("recorder mutex poisoned");
+        let mut flagged: Vec<_> = recordings
+            .iter()
+            .map(|(player, recording)| status(player, recording))
+            .collect();
+        flagged.sort_by(|a, b| a.player_uuid.cmp(&b.player_uuid));
+        flagged
+    }
+
+    pub fn record_input(&self, snapshot: &BridgeInputSnapshot) {
+        self.record(&snapshot.player_uuid, |player| RecordedSample::Input {
+            at_ms: now_ms(),
+            client_ms: snapshot.timestamp_ms,
+            stand_id: own_stand(player, &snapshot.stand_id),
+            device: snapshot.device.clone(),
+            profile: snapshot.profile.clone(),
+            buttons: snapshot.buttons.clone(),
+            axes: snapshot.axes.clone(),
+        });
+    }
+
+    pub fn record_position(&self, snapshot: &BridgePositionSnapshot) {
+        self.record(&snapshot.player_uuid, |player| RecordedSample::Position {
+            at_ms: now_ms(),
+            stand_id: own_stand(player, &snapshot.stand_id),
+            world: snapshot.world.clone(),
+            pos: snapshot.pos,
+            velocity: snapshot.velocity,
+            rotation: snapshot.rotation,
+        });
+    }
+
+    /// Copy the buffer of a flagged player for report `report_id`. Recording
+    /// continues so follow-up reports still have context. Both ids end up in
+    /// the dump's store key, so both are held to the same charset.
+    pub fn dump(&self, player_uuid: &str, report_id: &str) -> Result<InputDump, FrameError> {
+        for (field, value) in [("report_id", report_id), ("player_uuid", player_uuid)] {
+            if value.is_empty()
+                || value.len() > REPORT_ID_MAX
+                || !value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(FrameError::Malformed(format!(
+                    "{field} must be 1-{REPORT_ID_MAX} letters, digits, '_' or '-'"
+                )));
+            }
+        }
+        let recordings = self.recordings.lock().expect("recorder mutex poisoned");
+        let recording = recordings
+            .get(player_uuid)
+            .ok_or_else(|| FrameError::NotFound(format!("no recording for {player_uuid}")))?;
+        Ok(InputDump {
+            report_id: report_id.to_string(),
+            player_uuid: player_uuid.to_string(),
+            flagged_at_ms: recording.flagged_at_ms,
+            reason: recording.reason.clone(),
+            dumped_at_ms: now_ms(),
+            samples: recording.samples.iter().cloned().collect(),
+        })
+    }
+
+    fn record(&self, player_uuid: &str, sample: impl FnOnce(&str) -> RecordedSample) {
+        let mut recordings = self.recordings.lock().expect("recorder mutex poisoned");
+        if let Some(recording) = recordings.get_mut(player_uuid) {
+            recording.push(sample(player_uuid));
+        }
+    }
+}
+
+fn status(player_uuid: &str, recording: &Recording) -> RecordingStatus {
+    RecordingStatus {
+        player_uuid: player_uuid.to_string(),
+        flagged_at_ms: recording.flagged_at_ms,
+        reason: recording.reason.clone(),
+        samples: recording.samples.len(),
+    }
+}
+
+/// Keep a stand id only if it is the recorded player's own stand,
+/// `player-<uuid>`.
+fn own_stand(player_uuid: &str, stand_id: &Option<String>) -> Option<String> {
+    stand_id.as_ref().map(|stand| {
+        if stand.strip_prefix("player-") == Some(player_uuid) {
+            stand.clone()
+        } else {
+            REDACTED.to_string()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(player: &str, stand: Option<&str>) -> BridgePositionSnapshot {
+        BridgePositionSnapshot {
+            player_uuid: player.into(),
+            session_id: None,
+            stand_id: stand.map(String::from),
+            world: "world".into(),
+            pos: Vec3f {
+                x: 0.0,
+                y: 64.0,
+                z: 0.0,
+            },
+            velocity: None,
+            rotation: None,
+        }
+    }
+
+    #[test]
+    fn only_the_players_own_stand_survives() {
+        let own = |stand: &str| own_stand("ab-1", &Some(stand.into()));
+        assert_eq!(own("player-ab-1").as_deref(), Some("player-ab-1"));
+        // Substrings of another stand id used to pass.
+        assert_eq!(own("player-ab-12").as_deref(), Some(REDACTED));
+        assert_eq!(own("xplayer-ab-1").as_deref(), Some(REDACTED));
+        assert_eq!(own("ab-1").as_deref(), Some(REDACTED));
+        assert_eq!(own_stand("ab-1", &None), None);
+    }
+
+    #[test]
+    fn buffers_only_flagged_players_and_drop_the_oldest() {
+        let recorder = InputRecorder::default();
+        recorder.record_position(&position("p", None));
+        assert!(recorder.flagged().is_empty());
+
+        recorder.flag("p", Some("fly".into()));
+        for _ in 0..RECORDING_CAPACITY + 3 {
+            recorder.record_position(&position("p", Some("player-p")));
+        }
+        recorder.record_position(&position("q", None));
+        let status = recorder.flag("p", None);
+        assert_eq!(status.samples, RECORDING_CAPACITY);
+        assert_eq!(status.reason.as_deref(), Some("fly"));
+
+        assert!(recorder.dump("p", "").is_err());
+        assert!(recorder.dump("p", &"r".repeat(REPORT_ID_MAX + 1)).is_err());
+        assert!(matches!(
+            recorder.dump("../p", "r-1"),
+            Err(FrameError::Malformed(_))
+        ));
+        assert!(matches!(
+            recorder.dump("q", "r-1"),
+            Err(FrameError::NotFound(_))
+        ));
+        assert_eq!(
+            recorder.dump("p", "r-1").unwrap().samples.len(),
+            RECORDING_CAPACITY
+        );
+
+        assert!(recorder.unflag("p"));
+        assert!(!recorder.unflag("p"));
+        assert!(recorder.dump("p", "r-1").is_err());
+    }
+}
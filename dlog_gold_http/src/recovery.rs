@@ -0,0 +1,477 @@
+//! Label recovery through guardians.
+//!
+//! A label's owner nominates guardian labels held by other phones, how many
+//! of them must approve ([`GuardianSet::threshold`]) and a time lock in
+//! blocks. Someone who lost the phone asks from a new one to recover the
+//! label; once enough guardians approve, the time lock starts, and when it
+//! runs out the gateway rebinds the label's balances and aliases to the same
+//! name under the new phone. Until then the old phone (or the requester) can
+//! cancel. Requests that don't gather their approvals within
+//! [`REQUEST_TTL_BLOCKS`] expire. The book only tracks state; the gateway
+//! moves funds, aliases and label records when a recovery completes.
+
+use dlog_error::FrameError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub const GUARDIANS_MAX: usize = 8;
+/// Longest time lock an owner may set (~1 week of 8 s blocks).
+pub const MAX_DELAY_BLOCKS: u64 = 75_600;
+/// Blocks a request has to gather its approvals (~1 day).
+pub const REQUEST_TTL_BLOCKS: u64 = 10_800;
+/// Closed recoveries kept for lookups before the oldest are pruned.
+const CLOSED_HISTORY: usize = 256;
+
+/// Who can vouch for a label, and how long a recovery waits after they do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardianSet {
+    /// Ledger key of the guarded label.
+    pub label: String,
+    /// Ledger keys of the guardian labels.
+    pub guardians: Vec<String>,
+    pub threshold: usize,
+    pub delay_blocks: u64,
+}
+
+/// Guardians as an owner nominates them (labels may be `@handle`s).
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuardianRequest {
+    pub guardians: Vec<String>,
+    pub threshold: usize,
+    pub delay_blocks: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryStatus {
+    /// Gathering guardian approvals.
+    Pending,
+    /// Approved; completes at `unlock_height`.
+    TimeLocked,
+    Completed,
+    Cancelled,
+    Expired,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recovery {
+    pub id: u64,
+    /// Ledger key of the label being recovered.
+    pub label: String,
+    /// The same name under the requesting phone.
+    pub new_label: String,
+    /// Guardians and threshold as they stood when the request was made.
+    pub guardians: Vec<String>,
+    pub threshold: usize,
+    pub delay_blocks: u64,
+    /// Guardian labels that approved, in order.
+    pub approvals: Vec<String>,
+    pub requested_height: u64,
+    /// Last height approvals are accepted at.
+    pub expires_height: u64,
+    #[serde(default)]
+    pub unlock_height: Option<u64>,
+    pub status: RecoveryStatus,
+    #[serde(default)]
+    pub closed_height: Option<u64>,
+}
+
+impl Recovery {
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self.status,
+            RecoveryStatus::Pending | RecoveryStatus::TimeLocked
+        )
+    }
+
+    /// Labels told about every step: both ends and the guardians.
+    pub fn parties(&self) -> Vec<&str> {
+        [&self.label, &self.new_label]
+            .into_iter()
+            .chain(&self.guardians)
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn close(&mut self, status: RecoveryStatus, height: u64) {
+        self.status = status;
+        self.closed_height = Some(height);
+    }
+}
+
+/// What `;∞;bank;recoveries;` holds, and what replicas pull.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoverySnapshot {
+    pub guardians: Vec<GuardianSet>,
+    pub recoveries: Vec<Recovery>,
+}
+
+#[derive(Debug, Default)]
+struct Books {
+    guardians: BTreeMap<String, GuardianSet>,
+    recoveries: BTreeMap<u64, Recovery>,
+}
+
+#[derive(Debug, Default)]
+pub struct RecoveryBook {
+    books: Mutex<Books>,
+}
+
+impl RecoveryBook {
+    pub fn from_snapshot(snapshot: RecoverySnapshot) -> Self {
+        let book = Self::default();
+        book.replace(snapshot);
+        book
+    }
+
+    /// Take another book's state wholesale (read replicas).
+    pub fn replace(&self, snapshot: RecoverySnapshot) {
+        *self.books.lock().expect("recovery mutex poisoned") = Books {
+            guardians: snapshot
+                .guardians
+                .into_iter()
+                .map(|set| (set.label.clone(), set))
+                .collect(),
+            recoveries: snapshot.recoveries.into_iter().map(|r| (r.id, r)).collect(),
+        };
+    }
+
+    pub fn snapshot(&self) -> RecoverySnapshot {
+        let books = self.books.lock().expect("recovery mutex poisoned");
+        RecoverySnapshot {
+            guardians: books.guardians.values().cloned().collect(),
+            recoveries: books.recoveries.values().cloned().collect(),
+        }
+    }
+
+    pub fn guardians(&self, label: &str) -> Option<GuardianSet> {
+        let books = self.books.lock().expect("recovery mutex poisoned");
+        books.guardians.get(label).cloned()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Recovery> {
+        let books = self.books.lock().expect("recovery mutex poisoned");
+        books.recoveries.get(&id).cloned()
+    }
+
+    /// Replace `set.label`'s guardians. Guardians are deduplicated; none may
+    /// share the label's phone, which is the one that might get lost.
+    pub fn set_guardians(&self, mut set: GuardianSet) -> Result<GuardianSet, FrameError> {
+        let owner = crate::alias::label_phone(&set.label);
+        set.guardians.sort();
+        set.guardians.dedup();
+        if set.guardians.is_empty() || set.guardians.len() > GUARDIANS_MAX {
+            return Err(FrameError::Malformed(format!(
+                "name 1-{GUARDIANS_MAX} guardians"
+            )));
+        }
+        if set
+            .guardians
+            .iter()
+            .any(|guardian| crate::alias::label_phone(guardian) == owner)
+        {
+            return Err(FrameError::Malformed(
+                "guardians must be labels of other phones".into(),
+            ));
+        }
+        if !(1..=set.guardians.len()).contains(&set.threshold) {
+            return Err(FrameError::Malformed(format!(
+                "threshold must be 1-{}",
+                set.guardians.len()
+            )));
+        }
+        if !(1..=MAX_DELAY_BLOCKS).contains(&set.delay_blocks) {
+            return Err(FrameError::Malformed(format!(
+                "delay_blocks must be 1-{MAX_DELAY_BLOCKS}"
+            )));
+        }
+        let mut books = self.books.lock().expect("recovery mutex poisoned");
+        if books
+            .recoveries
+            .values()
+            .any(|r| r.is_open() && r.label == set.label)
+        {
+            return Err(FrameError::Conflict(
+                "guardians can't change while a recovery is open".into(),
+            ));
+        }
+        books.guardians.insert(set.label.clone(), set.clone());
+        Ok(set)
+    }
+
+    /// Ask to move `label` to `new_label`; one open request per label.
+    pub fn request(
+        &self,
+        label: &str,
+        new_label: &str,
+        height: u64,
+    ) -> Result<Recovery, FrameError> {
+        if label == new_label {
+            return Err(FrameError::Conflict(
+                "this phone already owns the label".into(),
+            ));
+        }
+        let mut books = self.books.lock().expect("recovery mutex poisoned");
+        let set = books
+            .guardians
+            .get(label)
+            .cloned()
+            .ok_or_else(|| FrameError::NotFound("guardians for this label".into()))?;
+        if books
+            .recoveries
+            .values()
+            .any(|r| r.is_open() && r.label == label)
+        {
+            return Err(FrameError::Conflict(
+                "a recovery of this label is already open".into(),
+            ));
+        }
+        let recovery = Recovery {
+            id: books
+                .recoveries
+                .keys()
+                .next_back()
+                .map(|id| id + 1)
+                .unwrap_or(1),
+            label: label.to_string(),
+            new_label: new_label.to_string(),
+            guardians: set.guardians,
+            threshold: set.threshold,
+            delay_blocks: set.delay_blocks,
+            approvals: Vec::new(),
+            requested_height: height,
+            expires_height: height + REQUEST_TTL_BLOCKS,
+            unlock_height: None,
+            status: RecoveryStatus::Pending,
+            closed_height: None,
+        };
+        books.recoveries.insert(recovery.id, recovery.clone());
+        Ok(recovery)
+    }
+
+    /// Count `guardian`'s approval; reaching the threshold starts the time
+    /// lock. The caller checks `guardian` is one of the recovery's.
+    pub fn approve(&self, id: u64, guardian: &str, height: u64) -> Result<Recovery, FrameError> {
+        let mut books = self.books.lock().expect("recovery mutex poisoned");
+        let recovery = books
+            .recoveries
+            .get_mut(&id)
+            .ok_or_else(|| FrameError::NotFound(format!("recovery {id}")))?;
+        if recovery.status != RecoveryStatus::Pending || height > recovery.expires_height {
+            return Err(FrameError::Conflict(format!(
+                "recovery {id} is not waiting on approvals"
+            )));
+        }
+        if recovery.approvals.iter().any(|g| g == guardian) {
+            return Err(FrameError::Conflict(format!(
+                "this guardian already approved recovery {id}"
+            )));
+        }
+        recovery.approvals.push(guardian.to_string());
+        if recovery.approvals.len() >= recovery.threshold {
+            recovery.status = RecoveryStatus::TimeLocked;
+            recovery.unlock_height = Some(height + recovery.delay_blocks);
+        }
+        Ok(recovery.clone())
+    }
+
+    pub fn cancel(&self, id: u64, height: u64) -> Result<Recovery, FrameError> {
+        let mut books = self.books.lock().expect("recovery mutex poisoned");
+        let recovery = books
+            .recoveries
+            .get_mut(&id)
+            .filter(|r| r.is_open())
+            .ok_or_else(|| FrameError::Conflict(format!("recovery {id} is not open")))?;
+        recovery.close(RecoveryStatus::Cancelled, height);
+        let recovery = recovery.clone();
+        prune(&mut books.recoveries);
+        Ok(recovery)
+    }
+
+    /// Close what `height` ends: pending requests past their window expire,
+    /// time locks that ran out complete (their guardians move to the new
+    /// label). Returns `(expired, completed)`.
+    pub fn advance(&self, height: u64) -> (Vec<Recovery>, Vec<Recovery>) {
+        let mut books = self.books.lock().expect("recovery mutex poisoned");
+        let (mut expired, mut completed) = (Vec::new(), Vec::new());
+        for recovery in books.recoveries.values_mut() {
+            match recovery.status {
+                RecoveryStatus::Pending if height > recovery.expires_height => {
+                    recovery.close(RecoveryStatus::Expired, height);
+                    expired.push(recovery.clone());
+                }
+                RecoveryStatus::TimeLocked
+                    if recovery
+                        .unlock_height
+                        .is_some_and(|unlock| height >= unlock) =>
+                {
+                    recovery.close(RecoveryStatus::Completed, height);
+                    completed.push(recovery.clone());
+                }
+                _ => {}
+            }
+        }
+        for recovery in &completed {
+            if let Some(mut set) = books.guardians.remove(&recovery.label) {
+                set.label = recovery.new_label.clone();
+                books.guardians.insert(set.label.clone(), set);
+            }
+        }
+        if !expired.is_empty() || !completed.is_empty() {
+            prune(&mut books.recoveries);
+        }
+        (expired, completed)
+    }
+
+    /// Forget the labels `owned` accepts (identity deletion): their guardian
+    /// sets go, they stop guarding other labels (thresholds shrink to the
+    /// guardians left, sets left without any go too), open recoveries naming
+    /// them are cancelled, and every recovery names `tombstone` instead.
+    /// Returns how many guardian sets were dropped or changed.
+    pub fn purge_labels(
+        &self,
+        owned: impl Fn(&str) -> bool,
+        tombstone: &str,
+        height: u64,
+    ) -> usize {
+        let mut books = self.books.lock().expect("recovery mutex poisoned");
+        let mut changed = 0;
+        books.guardians.retain(|label, set| {
+            if owned(label) {
+                changed += 1;
+                return false;
+            }
+            let before = set.guardians.len();
+            set.guardians.retain(|guardian| !owned(guardian));
+            if set.guardians.len() != before {
+                changed += 1;
+                set.threshold = set.threshold.min(set.guardians.len());
+            }
+            !set.guardians.is_empty()
+        });
+        let mut touched = false;
+        for recovery in books.recoveries.values_mut() {
+            if !recovery.parties().into_iter().any(&owned) {
+                continue;
+            }
+            touched = true;
+            if recovery.is_open() {
+                recovery.close(RecoveryStatus::Cancelled, height);
+            }
+            for label in [&mut recovery.label, &mut recovery.new_label]
+                .into_iter()
+                .chain(&mut recovery.guardians)
+                .chain(&mut recovery.approvals)
+            {
+                if owned(label) {
+                    *label = tombstone.to_string();
+                }
+            }
+        }
+        if touched {
+            prune(&mut books.recoveries);
+        }
+        changed
+    }
+
+    /// Open recoveries with a party satisfying `is_party`.
+    pub fn open_for(&self, is_party: impl Fn(&str) -> bool) -> Vec<Recovery> {
+        let books = self.books.lock().expect("recovery mutex poisoned");
+        books
+            .recoveries
+            .values()
+            .filter(|r| r.is_open() && r.parties().into_iter().any(&is_party))
+            .cloned()
+            .collect()
+    }
+}
+
+fn prune(recoveries: &mut BTreeMap<u64, Recovery>) {
+    let closed: Vec<u64> = recoveries
+        .values()
+        .filter(|r| !r.is_open())
+        .map(|r| r.id)
+        .collect();
+    for id in closed
+        .iter()
+        .take(closed.len().saturating_sub(CLOSED_HISTORY))
+    {
+        recoveries.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(guardians: &[&str], threshold: usize) -> GuardianSet {
+        GuardianSet {
+            label: ";a;comet;".into(),
+            guardians: guardians.iter().map(|g| g.to_string()).collect(),
+            threshold,
+            delay_blocks: 5,
+        }
+    }
+
+    #[test]
+    fn approvals_start_the_time_lock_and_unlock_moves_guardians() {
+        let book = RecoveryBook::default();
+        assert!(book.set_guardians(set(&[], 1)).is_err());
+        assert!(book.set_guardians(set(&[";b;x;"], 2)).is_err());
+        assert!(book.set_guardians(set(&[";a;spare;"], 1)).is_err());
+        assert_eq!(
+            book.request(";a;comet;", ";n;comet;", 1)
+                .unwrap_err()
+                .status(),
+            404
+        );
+        let guardians = book
+            .set_guardians(set(&[";c;y;", ";b;x;", ";b;x;", ";d;z;"], 2))
+            .unwrap();
+        assert_eq!(guardians.guardians, [";b;x;", ";c;y;", ";d;z;"]);
+
+        assert!(book.request(";a;comet;", ";a;comet;", 1).is_err());
+        let open = book.request(";a;comet;", ";n;comet;", 1).unwrap();
+        assert_eq!(
+            book.request(";a;comet;", ";m;comet;", 1)
+                .unwrap_err()
+                .status(),
+            409
+        );
+        assert!(book.set_guardians(set(&[";b;x;"], 1)).is_err());
+
+        let first = book.approve(open.id, ";b;x;", 2).unwrap();
+        assert_eq!(first.status, RecoveryStatus::Pending);
+        assert!(book.approve(open.id, ";b;x;", 2).is_err());
+        let locked = book.approve(open.id, ";d;z;", 3).unwrap();
+        assert_eq!(locked.status, RecoveryStatus::TimeLocked);
+        assert_eq!(locked.unlock_height, Some(8));
+        assert!(book.approve(open.id, ";c;y;", 4).is_err());
+
+        assert_eq!(book.advance(7), (vec![], vec![]));
+        let (_, completed) = book.advance(8);
+        assert_eq!(completed[0].status, RecoveryStatus::Completed);
+        assert!(book.guardians(";a;comet;").is_none());
+        assert_eq!(book.guardians(";n;comet;").unwrap().threshold, 2);
+        assert!(book.cancel(open.id, 9).is_err());
+    }
+
+    #[test]
+    fn unapproved_requests_expire_and_can_be_cancelled() {
+        let book = RecoveryBook::default();
+        book.set_guardians(set(&[";b;x;"], 1)).unwrap();
+        let open = book.request(";a;comet;", ";n;comet;", 10).unwrap();
+        let (expired, _) = book.advance(10 + REQUEST_TTL_BLOCKS + 1);
+        assert_eq!(expired[0].id, open.id);
+        assert!(book.approve(open.id, ";b;x;", 10).is_err());
+
+        let again = book.request(";a;comet;", ";n;comet;", 20).unwrap();
+        assert_eq!(again.id, open.id + 1);
+        assert_eq!(
+            book.cancel(again.id, 21).unwrap().status,
+            RecoveryStatus::Cancelled
+        );
+        assert!(book.open_for(|_| true).is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+//! Read replicas of the gateway bank (`OMEGA_REPLICA_OF=<leader url>`).
+//!
+//! A replica never seals or applies a bank write itself. Every few seconds
+//! it pulls the leader's bank state (`GET /omega/replica/state`): the tip,
+//! the ledgers, burn totals and rate schedule, transfers newer than the last one it holds, escrows,
+//! aliases and labels. Balance queries, transfer search, label lists, status
+//! roots and the explorer are then answered locally. Frames that would move funds are relayed to
+//! the leader over the replica's `peer` session as `RELAY` frames carrying
+//! the caller's phone and planet, MAC'd under `OMEGA_PEER_KEY`, and the
+//! leader's notes come back in the ack. Both sides need the same peer key
+//! (and the same privacy salt, since ledgers are keyed by it).
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use spec::{MonetaryChange, PlanetId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::alias::AliasRecord;
+use crate::burn::BurnedSupply;
+use crate::escrow::Escrow;
+use crate::labels::LabelRecord;
+use crate::omega::{now_ms, FrameAck, FrameEnvelope, FrameKind, OmegaGateway};
+use crate::recovery::RecoverySnapshot;
+use crate::transfer::TransferRecord;
+use corelib::consensus::BlockHeader;
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(4);
+const LEADER_TIMEOUT: Duration = Duration::from_secs(3);
+/// Header carrying the peer MAC over [`state_request_bytes`].
+pub const PEER_MAC_HEADER: &str = "x-omega-peer-mac";
+
+/// Reply of `GET /omega/replica/state?after=`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaState {
+    pub height: u64,
+    pub tip: Option<BlockHeader>,
+    pub ledgers: HashMap<PlanetId, HashMap<String, u128>>,
+    #[serde(default)]
+    pub burned: BurnedSupply,
+    #[serde(default)]
+    pub monetary: Vec<MonetaryChange>,
+    /// Transfers with an id above `after`, oldest first.
+    pub transfers: Vec<TransferRecord>,
+    pub escrows: Vec<Escrow>,
+    pub aliases: Vec<AliasRecord>,
+    #[serde(default)]
+    pub labels: Vec<LabelRecord>,
+    #[serde(default)]
+    pub recoveries: RecoverySnapshot,
+}
+
+/// What the peer MAC on a state request covers.
+pub fn state_request_bytes(after: u64) -> Vec<u8> {
+    format!("replica;{after}").into_bytes()
+}
+
+/// Payload of a `RELAY` frame: a bank frame a replica took from one of its
+/// sessions, for the leader to apply as that session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayedFrame {
+    /// Phone key of the replica session, if it had one.
+    pub phone: Option<String>,
+    pub planet: PlanetId,
+    pub kind: FrameKind,
+    #[serde(default)]
+    pub payload: Value,
+    #[serde(default)]
+    pub peer_mac: String,
+}
+
+impl RelayedFrame {
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "relay;{};{};{:?};",
+            self.phone.as_deref().unwrap_or_default(),
+            self.planet,
+            self.kind
+        )
+        .into_bytes();
+        bytes.extend(corelib::canonical::to_vec(&self.payload).unwrap_or_default());
+        bytes
+    }
+}
+
+/// Replica health for `/omega/status`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplicaStatus {
+    pub leader: String,
+    /// Leader height at the last successful pull.
+    pub height: u64,
+    pub last_sync_ms: Option<i64>,
+    pub syncs: u64,
+    pub failures: u64,
+    pub last_error: Option<String>,
+    /// Frames relayed to the leader, and how many of those failed to arrive.
+    pub relayed: u64,
+    pub relay_failures: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderSession {
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderAck {
+    accepted: bool,
+    notes: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Replica {
+    leader: String,
+    client: Client,
+    /// `peer` session opened on the leader, reused until it's refused.
+    session: Mutex<Option<String>>,
+    status: Mutex<ReplicaStatus>,
+}
+
+impl Replica {
+    pub fn new(leader: &str) -> Self {
+        let leader = leader.trim().trim_end_matches('/').to_string();
+        let client = Client::builder()
+            .timeout(LEADER_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            status: Mutex::new(ReplicaStatus {
+                leader: leader.clone(),
+                ..ReplicaStatus::default()
+            }),
+            leader,
+            client,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// `OMEGA_REPLICA_OF`, unset or blank for a normal gateway.
+    pub fn from_env() -> Option<Self> {
+        std::env::var("OMEGA_REPLICA_OF")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| Self::new(&url))
+    }
+
+    pub fn leader(&self) -> &str {
+        &self.leader
+    }
+
+    pub fn status(&self) -> ReplicaStatus {
+        self.status.lock().expect("replica mutex poisoned").clone()
+    }
+
+    /// Pull the leader's bank state and load it.
+    pub async fn sync(&self, gateway: &OmegaGateway) -> Result<(), String> {
+        let after = gateway.last_transfer_id();
+        let mac = gateway
+            .peer_mac(&state_request_bytes(after))
+            .ok_or("replica needs OMEGA_PEER_KEY")?;
+        let state: ReplicaState = self
+            .client
+            .get(format!("{}/omega/replica/state", self.leader))
+            .query(&[("after", after)])
+            .header(PEER_MAC_HEADER, mac)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let height = state.height;
+        if height != gateway.block_height() {
+            info!("[replica] following {} at height {height}", self.leader);
+        }
+        gateway.ingest_replica(state);
+        let mut status = self.status.lock().expect("replica mutex poisoned");
+        status.height = height;
+        status.last_sync_ms = Some(now_ms());
+        status.syncs += 1;
+        status.last_error = None;
+        Ok(())
+    }
+
+    /// Send a bank write to the leader as the session that made it.
+    pub async fn relay(&self, gateway: &OmegaGateway, frame: FrameEnvelope) -> FrameAck {
+        let outcome = match self.session(gateway).await {
+            Ok(session) => match gateway.relay_frame(&frame, &session) {
+                Ok(relay) => self.send(relay).await,
+                Err(ack) => return ack,
+            },
+            Err(err) => Err(err),
+        };
+        let mut status = self.status.lock().expect("replica mutex poisoned");
+        status.relayed += 1;
+        match outcome {
+            Ok(ack) => {
+                if !ack.accepted {
+                    // Most likely the leader restarted and forgot the session.
+                    *self.session.lock().expect("replica mutex poisoned") = None;
+                }
+                let mut notes = vec![format!("replica: relayed to leader {}", self.leader)];
+                notes.extend(ack.notes);
+                gateway.frame_ack(&frame, ack.accepted, notes)
+            }
+            Err(err) => {
+                status.relay_failures += 1;
+                warn!("[replica] relay to {}: {err}", self.leader);
+                let note = format!(
+                    "replica: leader {} unreachable ({err}); frame not applied",
+                    self.leader
+                );
+                gateway.frame_ack(&frame, false, vec![note])
+            }
+        }
+    }
+
+    async fn send(&self, relay: FrameEnvelope) -> Result<LeaderAck, String> {
+        self.client
+            .post(format!("{}/omega/frame", self.leader))
+            .json(&relay)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Our `peer` session on the leader, handshaking with the peer key if needed.
+    async fn session(&self, gateway: &OmegaGateway) -> Result<String, String> {
+        if let Some(session_id) = self.session.lock().expect("replica mutex poisoned").clone() {
+            return Ok(session_id);
+        }
+        let opened: LeaderSession = self
+            .client
+            .post(format!("{}/omega/handshake", self.leader))
+            .json(&gateway.peer_handshake())
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| format!("handshake: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("handshake: {e}"))?;
+        *self.session.lock().expect("replica mutex poisoned") = Some(opened.session_id.clone());
+        Ok(opened.session_id)
+    }
+
+    fn failed(&self, err: String) {
+        warn!("[replica] sync from {}: {err}", self.leader);
+        let mut status = self.status.lock().expect("replica mutex poisoned");
+        status.failures += 1;
+        status.last_error = Some(err);
+    }
+}
+
+/// Pull from the leader every few seconds, well inside one block interval.
+pub fn spawn_replica_sync(gateway: Arc<OmegaGateway>, replica: Arc<Replica>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SYNC_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = replica.sync(&gateway).await {
+                replica.failed(err);
+            }
+        }
+    });
+}
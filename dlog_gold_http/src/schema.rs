@@ -0,0 +1,132 @@
+//! Schema history of the gateway's versioned JSON files under `OMEGA_ROOT`.
+//!
+//! `GatewayStore` upgrades these files as it loads them and stamps the
+//! current version as it saves, so a type only changes together with a new
+//! step here. `dlog_gold_http migrate` rewrites them in place. Files whose
+//! root is a list or a keyed map (ledger, transfers, escrows) carry no
+//! version and still evolve through `#[serde(default)]` fields.
+
+use corelib::migrate::{Migration, Schema};
+use serde_json::{Map, Value};
+
+use crate::omega::{BLOCK_CURSOR_PATH, SCHEDULER_PATH};
+
+fn block_cursor_v1(object: &mut Map<String, Value>) -> Result<(), String> {
+    if !object.get("height").is_some_and(Value::is_u64) {
+        return Err("cursor without a height".into());
+    }
+    object.entry("last_seal_ms").or_insert(0.into());
+    object.entry("tip").or_insert(Value::Null);
+    Ok(())
+}
+
+/// Scheduler files from before ids were persisted resume counting from the
+/// highest stored task (`next_id` is the last id handed out), so new tasks
+/// never reuse one.
+fn scheduler_v1(object: &mut Map<String, Value>) -> Result<(), String> {
+    let tasks = object
+        .entry("tasks")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let highest = tasks
+        .as_object()
+        .ok_or("tasks is not a map")?
+        .values()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|task| task.get("id").and_then(Value::as_u64))
+        .max();
+    object
+        .entry("next_id")
+        .or_insert(highest.unwrap_or_default().into());
+    Ok(())
+}
+
+pub const BLOCK_CURSOR: Schema = Schema {
+    kind: "block_cursor",
+    migrations: &[Migration {
+        to: 1,
+        note: "fill the seal time and tip of cursors from before chained blocks",
+        apply: block_cursor_v1,
+    }],
+};
+
+pub const SCHEDULER: Schema = Schema {
+    kind: "scheduler",
+    migrations: &[Migration {
+        to: 1,
+        note: "derive next_id from the stored tasks",
+        apply: scheduler_v1,
+    }],
+};
+
+/// Versioned files by Ω path.
+pub const SCHEMAS: [(&str, &Schema); 2] = [
+    (BLOCK_CURSOR_PATH, &BLOCK_CURSOR),
+    (SCHEDULER_PATH, &SCHEDULER),
+];
+
+pub fn for_path(omega_path: &str) -> Option<&'static Schema> {
+    SCHEMAS
+        .iter()
+        .find(|(path, _)| *path == omega_path)
+        .map(|(_, schema)| *schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::omega::BlockCursor;
+    use crate::store::GatewayStore;
+    use corelib::HeightScheduler;
+    use tempfile::tempdir;
+
+    #[test]
+    fn migrate_upgrades_legacy_files_in_place() {
+        let dir = tempdir().unwrap();
+        let files = dir.path().join("∞");
+        std::fs::create_dir_all(&files).unwrap();
+        std::fs::write(files.join("blocks;cursor"), r#"{"height":7}"#).unwrap();
+        std::fs::write(
+            files.join("scheduler;tasks"),
+            r#"{"tasks":{"9":[{"id":4,"height":9,"kind":"gift_unlock"}]}}"#,
+        )
+        .unwrap();
+        let store = GatewayStore::at(dir.path());
+
+        let dry = store.migrate(true).unwrap();
+        assert_eq!(dry.kinds["block_cursor"].upgraded[BLOCK_CURSOR_PATH], 0);
+        assert!(!std::fs::read_to_string(files.join("blocks;cursor"))
+            .unwrap()
+            .contains("schema_version"));
+
+        let report = store.migrate(false).unwrap();
+        assert_eq!(report.failures(), 0);
+        assert_eq!(report.kinds["scheduler"].upgraded.len(), 1);
+        assert!(store.migrate(true).unwrap().kinds["scheduler"]
+            .upgraded
+            .is_empty());
+
+        let cursor: BlockCursor = store.load_json_strict(BLOCK_CURSOR_PATH).unwrap().unwrap();
+        assert_eq!((cursor.height, cursor.tip), (7, None));
+        let mut scheduler: HeightScheduler =
+            store.load_json_strict(SCHEDULER_PATH).unwrap().unwrap();
+        let task = scheduler.schedule(12, "gift_unlock", Value::Null);
+        assert_eq!(task.id, 5);
+    }
+
+    #[test]
+    fn strict_loads_refuse_files_from_a_newer_binary() {
+        let dir = tempdir().unwrap();
+        let files = dir.path().join("∞");
+        std::fs::create_dir_all(&files).unwrap();
+        std::fs::write(
+            files.join("blocks;cursor"),
+            r#"{"height":7,"last_seal_ms":0,"schema_version":99}"#,
+        )
+        .unwrap();
+        let store = GatewayStore::at(dir.path());
+        assert!(store
+            .load_json_strict::<BlockCursor>(BLOCK_CURSOR_PATH)
+            .is_err());
+    }
+}
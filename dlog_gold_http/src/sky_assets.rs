@@ -0,0 +1,217 @@
+//! Hash-addressed textures for sky slides.
+//!
+//! An admin uploads a slide's image; the gateway takes its sha-less digest,
+//! stores the bytes under `;∞;sky;asset;<key>;` and points the slide at it.
+//! Timeline responses then carry each slide's `asset` (hash, mime, size,
+//! url), so every client fetches the same bytes and can check them. `url` is
+//! `OMEGA_SKY_ASSET_BASE_URL/<key>` (a CDN in front of the bucket), or this
+//! gateway's own `/sky/assets/<key>` when unset. `key` is the last 64 base-8
+//! digits of the digest, short enough for a file name. Without `OMEGA_ROOT`
+//! uploads last until restart.
+
+use dlog_error::{FrameError, StorageError};
+use serde::{Deserialize, Serialize};
+use spec::{PlanetId, SkyAssetRef, SkyShowConfig};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::store::GatewayStore;
+
+pub use dlog_sky::SKY_ASSET_MAX_BYTES;
+
+const SKY_ASSETS_PATH: &str = ";∞;sky;assets;";
+const KEY_DIGITS: usize = 64;
+const DEFAULT_BASE_URL: &str = "/sky/assets";
+
+/// What a slide points at; the url is derived when a timeline is served, so
+/// moving the CDN doesn't need re-uploads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredAsset {
+    hash: String,
+    mime: String,
+    size: u64,
+}
+
+/// An asset's bytes with the mime it was uploaded as.
+#[derive(Debug, Clone)]
+pub struct AssetBlob {
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SkyAssets {
+    store: GatewayStore,
+    base_url: String,
+    /// Slide id → asset.
+    slides: Mutex<BTreeMap<String, StoredAsset>>,
+    /// Blobs uploaded or read since boot, by key.
+    blobs: Mutex<HashMap<String, AssetBlob>>,
+}
+
+/// File-name-sized tail of a `;∞;sha-less;<digits>;` digest.
+pub fn asset_key(hash: &str) -> String {
+    let digits = hash
+        .trim_end_matches(';')
+        .rsplit(';')
+        .next()
+        .unwrap_or_default();
+    digits[digits.len().saturating_sub(KEY_DIGITS)..].to_string()
+}
+
+fn blob_path(key: &str) -> String {
+    format!(";∞;sky;asset;{key};")
+}
+
+/// Slide ids any served timeline can contain.
+fn known_slide(slide_id: &str) -> bool {
+    std::iter::once(SkyShowConfig::default_eight())
+        .chain(PlanetId::ALL.map(SkyShowConfig::for_planet))
+        .any(|show| show.slides.iter().any(|slide| slide.id == slide_id))
+}
+
+impl SkyAssets {
+    pub fn load(store: GatewayStore) -> Self {
+        let base_url = std::env::var("OMEGA_SKY_ASSET_BASE_URL")
+            .ok()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        Self::with_base_url(store, base_url)
+    }
+
+    fn with_base_url(store: GatewayStore, base_url: String) -> Self {
+        let slides = store.load_json(SKY_ASSETS_PATH).unwrap_or_default();
+        Self {
+            store,
+            base_url,
+            slides: Mutex::new(slides),
+            blobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn asset_ref(&self, asset: &StoredAsset) -> SkyAssetRef {
+        SkyAssetRef {
+            hash: asset.hash.clone(),
+            mime: asset.mime.clone(),
+            size: asset.size,
+            url: format!("{}/{}", self.base_url, asset_key(&asset.hash)),
+        }
+    }
+
+    /// Store `bytes` as `slide_id`'s texture, replacing any earlier one.
+    pub fn register(
+        &self,
+        slide_id: &str,
+        mime: &str,
+        bytes: Vec<u8>,
+    ) -> Result<SkyAssetRef, StorageError> {
+        let asset = StoredAsset {
+            hash: corelib::shaless_digest(&bytes),
+            mime: mime.to_string(),
+            size: bytes.len() as u64,
+        };
+        let key = asset_key(&asset.hash);
+        self.store.save_bytes(&blob_path(&key), &bytes)?;
+        self.blobs
+            .lock()
+            .expect("sky assets mutex poisoned")
+            .insert(
+                key,
+                AssetBlob {
+                    mime: asset.mime.clone(),
+                    bytes,
+                },
+            );
+        let mut slides = self.slides.lock().expect("sky assets mutex poisoned");
+        slides.insert(slide_id.to_string(), asset.clone());
+        self.store.save_json(SKY_ASSETS_PATH, &*slides)?;
+        Ok(self.asset_ref(&asset))
+    }
+
+    /// Check an upload before anything is hashed or written.
+    pub fn validate(slide_id: &str, mime: &str, size: usize) -> Result<(), FrameError> {
+        if !known_slide(slide_id) {
+            return Err(FrameError::NotFound(format!("slide {slide_id}")));
+        }
+        dlog_sky::check_asset(mime, size).map_err(FrameError::Malformed)
+    }
+
+    /// Fill in `asset` on every slide that has one registered.
+    pub fn resolve(&self, mut show: SkyShowConfig) -> SkyShowConfig {
+        let slides = self.slides.lock().expect("sky assets mutex poisoned");
+        for slide in &mut show.slides {
+            slide.asset = slides.get(&slide.id).map(|asset| self.asset_ref(asset));
+        }
+        show
+    }
+
+    /// Bytes for `key`, if some slide points at them.
+    pub fn blob(&self, key: &str) -> Option<AssetBlob> {
+        if let Some(blob) = self
+            .blobs
+            .lock()
+            .expect("sky assets mutex poisoned")
+            .get(key)
+        {
+            return Some(blob.clone());
+        }
+        let mime = self
+            .slides
+            .lock()
+            .expect("sky assets mutex poisoned")
+            .values()
+            .find(|asset| asset_key(&asset.hash) == key)?
+            .mime
+            .clone();
+        let blob = AssetBlob {
+            mime,
+            bytes: self.store.load_bytes(&blob_path(key))?,
+        };
+        self.blobs
+            .lock()
+            .expect("sky assets mutex poisoned")
+            .insert(key.to_string(), blob.clone());
+        Some(blob)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_slides_resolve_to_hash_addressed_urls_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let boot = || {
+            SkyAssets::with_base_url(
+                GatewayStore::at(dir.path()),
+                "https://cdn.example/sky".to_string(),
+            )
+        };
+        let assets = boot();
+        let png = b"\x89PNG fake slide".to_vec();
+
+        assert!(SkyAssets::validate("slide-9", "image/png", png.len()).is_err());
+        assert!(SkyAssets::validate("moon-slide-1", "text/html", png.len()).is_err());
+        assert!(SkyAssets::validate("moon-slide-1", "image/png", 0).is_err());
+        SkyAssets::validate("moon-slide-1", "image/png", png.len()).unwrap();
+
+        let asset = assets
+            .register("moon-slide-1", "image/png", png.clone())
+            .unwrap();
+        assert_eq!(asset.hash, corelib::shaless_digest(&png));
+        assert_eq!(asset.size, png.len() as u64);
+        let key = asset_key(&asset.hash);
+        assert_eq!(key.len(), KEY_DIGITS);
+        assert_eq!(asset.url, format!("https://cdn.example/sky/{key}"));
+
+        let restarted = boot();
+        let show = restarted.resolve(SkyShowConfig::for_planet(PlanetId::Moon));
+        assert_eq!(show.slides[0].asset.as_ref(), Some(&asset));
+        assert!(show.slides[1].asset.is_none());
+        let blob = restarted.blob(&key).unwrap();
+        assert_eq!((blob.mime.as_str(), blob.bytes), ("image/png", png));
+        assert!(restarted.blob("0123").is_none());
+    }
+}
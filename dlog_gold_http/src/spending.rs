@@ -0,0 +1,228 @@
+//! Spending view: one label's inflows and outflows over a block range,
+//! rolled up per planet by category (the transfer's `tag`) and by
+//! counterparty, from the transfer log.
+//!
+//! A transfer is recorded at the tip it was made on and sealed into the
+//! next block, so only heights below the tip are final. Summaries cover
+//! those alone, which lets [`SpendingCache`] keep them until the next seal.
+//! Batch payouts count per entry, each under its own tag (the batch's when
+//! it has none); both legs of a cross-zone transfer have the exchange as
+//! their counterparty.
+
+use serde::Serialize;
+use spec::PlanetId;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+
+use crate::transfer::{TransferMeta, TransferRecord};
+
+/// Category of transfers without a tag.
+pub const UNCATEGORIZED: &str = "uncategorized";
+/// Summaries kept per sealed block before the cache starts over.
+const CACHE_MAX: usize = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Flow {
+    pub inflow: u128,
+    pub outflow: u128,
+    pub transfers: u64,
+}
+
+impl Flow {
+    fn add(&mut self, inflow: bool, amount: u128) {
+        if inflow {
+            self.inflow += amount;
+        } else {
+            self.outflow += amount;
+        }
+        self.transfers += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PlanetSpending {
+    pub total: Flow,
+    pub categories: BTreeMap<String, Flow>,
+    pub counterparties: BTreeMap<String, Flow>,
+}
+
+impl PlanetSpending {
+    fn add(&mut self, inflow: bool, amount: u128, counterparty: &str, meta: &TransferMeta) {
+        let category = meta.tag.as_deref().unwrap_or(UNCATEGORIZED);
+        self.total.add(inflow, amount);
+        self.categories
+            .entry(category.to_string())
+            .or_default()
+            .add(inflow, amount);
+        self.counterparties
+            .entry(counterparty.to_string())
+            .or_default()
+            .add(inflow, amount);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpendingSummary {
+    pub label: String,
+    pub from_height: u64,
+    /// Last height covered: the requested one, or the last sealed one.
+    pub to_height: u64,
+    pub planets: BTreeMap<PlanetId, PlanetSpending>,
+}
+
+impl SpendingSummary {
+    /// Roll up `records` touching `label` at `heights` (on `planet` only,
+    /// when given).
+    pub fn compute(
+        label: &str,
+        planet: Option<PlanetId>,
+        heights: RangeInclusive<u64>,
+        records: &[TransferRecord],
+    ) -> Self {
+        let mut planets: BTreeMap<PlanetId, PlanetSpending> = BTreeMap::new();
+        let records = records.iter().filter(|record| {
+            heights.contains(&record.height) && planet.is_none_or(|planet| planet == record.planet)
+        });
+        for record in records {
+            let mut add = |inflow, amount, counterparty: &str, meta: &TransferMeta| {
+                planets
+                    .entry(record.planet)
+                    .or_default()
+                    .add(inflow, amount, counterparty, meta);
+            };
+            if record.batch.is_empty() {
+                if record.from == label {
+                    add(false, record.amount, &record.to, &record.meta);
+                }
+                if record.to == label {
+                    add(true, record.amount, &record.from, &record.meta);
+                }
+                continue;
+            }
+            for entry in &record.batch {
+                let meta = TransferMeta {
+                    tag: entry.meta.tag.clone().or_else(|| record.meta.tag.clone()),
+                    ..TransferMeta::default()
+                };
+                if record.from == label {
+                    add(false, entry.amount, &entry.to, &meta);
+                }
+                if entry.to == label {
+                    add(true, entry.amount, &record.from, &meta);
+                }
+            }
+        }
+        Self {
+            label: label.to_string(),
+            from_height: *heights.start(),
+            to_height: *heights.end(),
+            planets,
+        }
+    }
+}
+
+type CacheKey = (String, Option<PlanetId>, u64, u64);
+
+/// Summaries computed at the current tip; the first lookup after a seal
+/// starts over.
+#[derive(Debug, Default)]
+pub struct SpendingCache {
+    inner: Mutex<(u64, HashMap<CacheKey, SpendingSummary>)>,
+}
+
+impl SpendingCache {
+    pub fn get_or_compute(
+        &self,
+        tip: u64,
+        label: &str,
+        planet: Option<PlanetId>,
+        heights: RangeInclusive<u64>,
+        compute: impl FnOnce() -> SpendingSummary,
+    ) -> SpendingSummary {
+        let key = (label.to_string(), planet, *heights.start(), *heights.end());
+        let mut inner = self.inner.lock().expect("spending cache mutex poisoned");
+        let (height, summaries) = &mut *inner;
+        if *height != tip || summaries.len() >= CACHE_MAX {
+            *height = tip;
+            summaries.clear();
+        }
+        summaries.entry(key).or_insert_with(compute).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::{BatchEntry, TransferLog};
+
+    fn tagged(tag: &str) -> TransferMeta {
+        TransferMeta {
+            tag: Some(tag.into()),
+            ..TransferMeta::default()
+        }
+    }
+
+    #[test]
+    fn rolls_up_by_category_and_counterparty() {
+        let me = ";9132077554;comet;";
+        let log = TransferLog::default();
+        log.append(PlanetId::Earth, 1, me, ";1;shop;", 30, tagged("food"));
+        log.append(PlanetId::Earth, 2, me, ";1;shop;", 20, tagged("food"));
+        log.append(
+            PlanetId::Earth,
+            2,
+            ";2;boss;",
+            me,
+            500,
+            TransferMeta::default(),
+        );
+        log.append(PlanetId::Moon, 3, me, ";1;shop;", 7, tagged("rent"));
+        log.append_batch(
+            PlanetId::Earth,
+            3,
+            ";2;boss;",
+            tagged("payroll"),
+            vec![
+                BatchEntry {
+                    to: me.into(),
+                    amount: 40,
+                    meta: TransferMeta::default(),
+                },
+                BatchEntry {
+                    to: ";3;pal;".into(),
+                    amount: 40,
+                    meta: TransferMeta::default(),
+                },
+            ],
+        );
+        log.append(PlanetId::Earth, 9, me, ";1;shop;", 1, tagged("food"));
+
+        let records = log.records();
+        let summary = SpendingSummary::compute(me, None, 0..=8, &records);
+        let earth = &summary.planets[&PlanetId::Earth];
+        assert_eq!(
+            earth.total,
+            Flow {
+                inflow: 540,
+                outflow: 50,
+                transfers: 4
+            }
+        );
+        assert_eq!(earth.categories["food"].outflow, 50);
+        assert_eq!(earth.categories[UNCATEGORIZED].inflow, 500);
+        assert_eq!(earth.categories["payroll"].inflow, 40);
+        assert_eq!(earth.counterparties[";2;boss;"].transfers, 2);
+        assert_eq!(summary.planets[&PlanetId::Moon].total.outflow, 7);
+
+        let moon = SpendingSummary::compute(me, Some(PlanetId::Moon), 0..=2, &records);
+        assert!(moon.planets.is_empty());
+
+        let cache = SpendingCache::default();
+        let first = cache.get_or_compute(9, me, None, 0..=8, || summary.clone());
+        let cached = cache.get_or_compute(9, me, None, 0..=8, || unreachable!());
+        assert_eq!(first, cached);
+        let resealed = cache.get_or_compute(10, me, None, 0..=8, || moon.clone());
+        assert_eq!(resealed, moon);
+    }
+}
@@ -0,0 +1,233 @@
+//! Live status deltas for dashboards (`GET /omega/status/stream`).
+//!
+//! A background task observes the `/omega/status` body (plus the sim tick
+//! latencies) every [`OBSERVE_INTERVAL`] and records what changed as a
+//! [`StatusDelta`]: every field that moved, two levels deep
+//! (`block_height`, `consensus.tip_root`, `sim_ticks.p99_us`, …), and how
+//! many sessions joined and left. Session ids never leave the feed, since
+//! they authenticate frames. The newest [`FEED_LEN`] deltas are kept with
+//! increasing `seq` numbers; a client resuming from a cursor still in that
+//! window gets the deltas it missed, anyone else starts from a snapshot.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How often the status is sampled for changes.
+pub const OBSERVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Quiet time after which a stream sends a heartbeat.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// Deltas kept for resuming clients.
+pub const FEED_LEN: usize = 256;
+/// Object levels the diff descends before comparing values whole.
+const DIFF_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusDelta {
+    pub seq: u64,
+    pub at_ms: i64,
+    /// Dotted path → new value; `null` for a field that went away.
+    pub changed: BTreeMap<String, Value>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub joined: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub left: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Full status at `seq`, for clients with no usable cursor.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusSnapshot {
+    pub seq: u64,
+    pub status: Value,
+}
+
+/// What a client resuming after a cursor is sent first.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resume {
+    Deltas(Vec<StatusDelta>),
+    Snapshot(StatusSnapshot),
+}
+
+#[derive(Debug, Default)]
+struct Feed {
+    seq: u64,
+    status: Value,
+    sessions: BTreeSet<String>,
+    deltas: VecDeque<StatusDelta>,
+}
+
+#[derive(Debug)]
+pub struct StatusFeed {
+    feed: Mutex<Feed>,
+    /// Latest `seq`, for streams waiting on the next delta.
+    latest: watch::Sender<u64>,
+}
+
+impl Default for StatusFeed {
+    fn default() -> Self {
+        Self {
+            feed: Mutex::new(Feed::default()),
+            latest: watch::Sender::new(0),
+        }
+    }
+}
+
+impl StatusFeed {
+    /// Record `status` and the live `sessions`; returns the delta if anything
+    /// changed. The first observation only sets the baseline.
+    pub fn observe(
+        &self,
+        at_ms: i64,
+        status: Value,
+        sessions: BTreeSet<String>,
+    ) -> Option<StatusDelta> {
+        let mut feed = self.feed.lock().expect("status feed mutex poisoned");
+        if feed.status.is_null() {
+            feed.status = status;
+            feed.sessions = sessions;
+            return None;
+        }
+        let mut changed = BTreeMap::new();
+        diff("", &feed.status, &status, DIFF_DEPTH, &mut changed);
+        let joined = sessions.difference(&feed.sessions).count();
+        let left = feed.sessions.difference(&sessions).count();
+        feed.status = status;
+        feed.sessions = sessions;
+        if changed.is_empty() && joined == 0 && left == 0 {
+            return None;
+        }
+        feed.seq += 1;
+        let delta = StatusDelta {
+            seq: feed.seq,
+            at_ms,
+            changed,
+            joined,
+            left,
+        };
+        if feed.deltas.len() >= FEED_LEN {
+            feed.deltas.pop_front();
+        }
+        feed.deltas.push_back(delta.clone());
+        self.latest.send_replace(feed.seq);
+        Some(delta)
+    }
+
+    /// Catch a client up from `cursor` (the last `seq` it saw).
+    pub fn resume(&self, cursor: Option<u64>) -> Resume {
+        let feed = self.feed.lock().expect("status feed mutex poisoned");
+        let oldest = feed.deltas.front().map_or(feed.seq + 1, |d| d.seq);
+        match cursor {
+            Some(cursor) if cursor <= feed.seq && cursor + 1 >= oldest => Resume::Deltas(
+                feed.deltas
+                    .iter()
+                    .filter(|d| d.seq > cursor)
+                    .cloned()
+                    .collect(),
+            ),
+            _ => Resume::Snapshot(StatusSnapshot {
+                seq: feed.seq,
+                status: feed.status.clone(),
+            }),
+        }
+    }
+
+    /// Changes whenever a delta is recorded.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.latest.subscribe()
+    }
+}
+
+/// Paths under `prefix` whose values differ, descending `depth` object levels.
+fn diff(prefix: &str, old: &Value, new: &Value, depth: usize, out: &mut BTreeMap<String, Value>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) if depth > 0 => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                diff(
+                    &path,
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    depth - 1,
+                    out,
+                );
+            }
+        }
+        _ if old != new => {
+            out.insert(prefix.to_string(), new.clone());
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sessions(ids: &[&str]) -> BTreeSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn deltas_carry_changed_paths_and_resume_from_cursors() {
+        let feed = StatusFeed::default();
+        let mut rx = feed.subscribe();
+        let status = json!({
+            "block_height": 4,
+            "consensus": { "producing": true, "tip_root": "a" },
+            "planets": [{ "planet": "earth", "sessions": 1 }],
+        });
+        assert!(feed.observe(1, status.clone(), sessions(&["s1"])).is_none());
+        assert!(feed.observe(2, status.clone(), sessions(&["s1"])).is_none());
+        assert!(!rx.has_changed().unwrap());
+
+        let moved = json!({
+            "block_height": 5,
+            "consensus": { "producing": true, "tip_root": "b" },
+            "planets": [{ "planet": "earth", "sessions": 2 }],
+            "replica": { "leader": "x" },
+        });
+        let delta = feed.observe(3, moved, sessions(&["s2", "s3"])).unwrap();
+        assert_eq!(delta.seq, 1);
+        assert_eq!((delta.joined, delta.left), (2, 1));
+        assert_eq!(
+            delta.changed.keys().collect::<Vec<_>>(),
+            ["block_height", "consensus.tip_root", "planets", "replica"]
+        );
+        assert_eq!(delta.changed["planets"][0]["sessions"], 2);
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(*rx.borrow_and_update(), 1);
+        let gone = feed.observe(4, status, sessions(&["s2", "s3"])).unwrap();
+        assert_eq!(gone.changed["replica"], Value::Null);
+
+        assert_eq!(feed.resume(Some(1)), Resume::Deltas(vec![gone]));
+        assert_eq!(feed.resume(Some(2)), Resume::Deltas(Vec::new()));
+        let Resume::Snapshot(snapshot) = feed.resume(None) else {
+            panic!("no cursor starts from a snapshot");
+        };
+        assert_eq!(
+            (snapshot.seq, &snapshot.status["block_height"]),
+            (2, &json!(4))
+        );
+        for height in 0..FEED_LEN as u64 {
+            feed.observe(5, json!({ "block_height": height + 10 }), BTreeSet::new());
+        }
+        assert!(matches!(feed.resume(Some(1)), Resume::Snapshot(_)));
+        assert!(matches!(feed.resume(Some(99_999)), Resume::Snapshot(_)));
+        assert!(
+            matches!(feed.resume(Some(FEED_LEN as u64 - 1)), Resume::Deltas(d) if d.len() == 3)
+        );
+    }
+}
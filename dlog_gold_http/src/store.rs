@@ -0,0 +1,321 @@
+//! File-backed persistence for gateway state under `OMEGA_ROOT`.
+//!
+//! Keys are Ω paths (`;∞;scheduler;tasks;`) and map onto canon filenames
+//! inside `$OMEGA_ROOT/∞/` (`scheduler;tasks`). Without `OMEGA_ROOT` the store
+//! is memory-only and every save is a no-op. Per-label files keep their full
+//! canon name (`;phone;label;∞;…;hash;`) since the `∞` segments are part of it.
+//! Files with a schema in [`crate::schema`] are upgraded on load and stamped
+//! with their version on save. Clones share one set of write/read counters,
+//! reported by [`GatewayStore::stats`].
+
+use corelib::label_file::LabelFile;
+use corelib::migrate::MigrationReport;
+use dlog_error::StorageError;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+#[derive(Debug, Clone, Default)]
+pub struct GatewayStore {
+    root: Option<PathBuf>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    writes: AtomicU64,
+    write_errors: AtomicU64,
+    read_errors: AtomicU64,
+}
+
+/// Store traffic since boot. Memory-only stores count nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StoreStats {
+    pub writes: u64,
+    pub write_errors: u64,
+    /// Files that existed but couldn't be read or decoded.
+    pub read_errors: u64,
+    /// `write_errors / writes`, 0 before the first write.
+    pub write_error_rate: f64,
+}
+
+impl GatewayStore {
+    pub fn from_env() -> Self {
+        let root = std::env::var("OMEGA_ROOT")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(PathBuf::from);
+        Self {
+            root,
+            counters: Arc::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn at(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+            counters: Arc::default(),
+        }
+    }
+
+    /// `$OMEGA_ROOT`, or `None` for a memory-only store.
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    pub fn stats(&self) -> StoreStats {
+        let writes = self.counters.writes.load(Ordering::Relaxed);
+        let write_errors = self.counters.write_errors.load(Ordering::Relaxed);
+        StoreStats {
+            writes,
+            write_errors,
+            read_errors: self.counters.read_errors.load(Ordering::Relaxed),
+            write_error_rate: if writes == 0 {
+                0.0
+            } else {
+                write_errors as f64 / writes as f64
+            },
+        }
+    }
+
+    fn counted_write(&self, result: Result<(), StorageError>) -> Result<(), StorageError> {
+        self.counters.writes.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.counters.write_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn counted_read<T>(&self, result: Result<T, StorageError>) -> Result<T, StorageError> {
+        if result.is_err() {
+            self.counters.read_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn path_for(&self, omega_path: &str) -> Option<PathBuf> {
+        let name = omega_path
+            .split(';')
+            .filter(|s| !s.is_empty() && *s != "∞")
+            .collect::<Vec<_>>()
+            .join(";");
+        self.root.as_ref().map(|root| root.join("∞").join(name))
+    }
+
+    pub fn load_json<T: DeserializeOwned>(&self, omega_path: &str) -> Option<T> {
+        let path = self.path_for(omega_path)?;
+        let bytes = std::fs::read(&path).ok()?;
+        match self.counted_read(decode(omega_path, &bytes)) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("[store] ignoring unreadable {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Like `load_json`, but a file that exists and doesn't parse is an
+    /// error. Used for state that must not silently reset (block cursor,
+    /// scheduler).
+    pub fn load_json_strict<T: DeserializeOwned>(
+        &self,
+        omega_path: &str,
+    ) -> Result<Option<T>, StorageError> {
+        let Some(path) = self.path_for(omega_path) else {
+            return Ok(None);
+        };
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Some(self.counted_read(decode(omega_path, &bytes))?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => self.counted_read(Err(err.into())),
+        }
+    }
+
+    pub fn save_json<T: Serialize>(&self, omega_path: &str, value: &T) -> Result<(), StorageError> {
+        let Some(path) = self.path_for(omega_path) else {
+            return Ok(());
+        };
+        let mut value = serde_json::to_value(value)?;
+        if let Some(schema) = crate::schema::for_path(omega_path) {
+            schema.stamp(&mut value);
+        }
+        self.counted_write(write_atomic(&path, &serde_json::to_vec_pretty(&value)?))
+    }
+
+    /// Upgrade every versioned file to its current schema. A dry run reads
+    /// and reports without writing anything back.
+    pub fn migrate(&self, dry_run: bool) -> Result<MigrationReport, StorageError> {
+        let mut report = MigrationReport::new(dry_run);
+        for (omega_path, schema) in crate::schema::SCHEMAS {
+            let Some(path) = self.path_for(omega_path) else {
+                continue;
+            };
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let value = match serde_json::from_slice::<Value>(&bytes) {
+                Ok(value) => value,
+                Err(err) => {
+                    report.unreadable(schema, omega_path, err);
+                    continue;
+                }
+            };
+            if let Some(upgraded) = report.check(schema, omega_path, value) {
+                if !dry_run {
+                    write_atomic(&path, &serde_json::to_vec_pretty(&upgraded)?)?;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn load_text(&self, omega_path: &str) -> Option<String> {
+        std::fs::read_to_string(self.path_for(omega_path)?).ok()
+    }
+
+    pub fn save_text(&self, omega_path: &str, text: &str) -> Result<(), StorageError> {
+        let Some(path) = self.path_for(omega_path) else {
+            return Ok(());
+        };
+        self.counted_write(write_atomic(&path, text.as_bytes()))
+    }
+
+    pub fn load_bytes(&self, omega_path: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.path_for(omega_path)?).ok()
+    }
+
+    pub fn save_bytes(&self, omega_path: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        let Some(path) = self.path_for(omega_path) else {
+            return Ok(());
+        };
+        self.counted_write(write_atomic(&path, bytes))
+    }
+
+    /// Append one compact JSON line; used by append-only logs.
+    pub fn append_json_line<T: Serialize>(
+        &self,
+        omega_path: &str,
+        value: &T,
+    ) -> Result<(), StorageError> {
+        use std::io::Write;
+
+        let Some(path) = self.path_for(omega_path) else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        let append = || -> Result<(), StorageError> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?
+                .write_all(&line)?;
+            Ok(())
+        };
+        self.counted_write(append())
+    }
+
+    pub fn load_label_file(&self, file: &LabelFile) -> Option<LabelFile> {
+        let path = self.root.as_ref()?.join("∞").join(file.filename());
+        let reader = std::fs::File::open(&path).ok()?;
+        match LabelFile::read(reader) {
+            Ok(on_disk) => Some(on_disk),
+            Err(err) => {
+                self.counters.read_errors.fetch_add(1, Ordering::Relaxed);
+                warn!("[store] ignoring unreadable {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    pub fn save_label_file(&self, file: &LabelFile) -> Result<(), StorageError> {
+        let Some(root) = &self.root else {
+            return Ok(());
+        };
+        let mut text = Vec::new();
+        file.write(&mut text)?;
+        self.counted_write(write_atomic(&root.join("∞").join(file.filename()), &text))
+    }
+
+    /// Every per-label file under `$OMEGA_ROOT/∞/`; unreadable ones are
+    /// skipped with a warning.
+    pub fn load_label_files(&self) -> Vec<LabelFile> {
+        let Some(root) = &self.root else {
+            return Vec::new();
+        };
+        let Ok(entries) = std::fs::read_dir(root.join("∞")) else {
+            return Vec::new();
+        };
+        let mut files = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !(name.starts_with(';') && name.ends_with(";hash;")) {
+                continue;
+            }
+            let read = std::fs::File::open(entry.path()).and_then(LabelFile::read);
+            match read {
+                Ok(file) if file.filename() == name => files.push(file),
+                Ok(_) => warn!("[store] ignoring {name}: contents name another label"),
+                Err(err) => warn!("[store] ignoring unreadable {name}: {err}"),
+            }
+        }
+        files
+    }
+
+    pub fn remove_label_file(&self, file: &LabelFile) -> Result<(), StorageError> {
+        let Some(root) = &self.root else {
+            return Ok(());
+        };
+        match std::fs::remove_file(root.join("∞").join(file.filename())) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse a stored file, upgrading it first when its path is versioned.
+fn decode<T: DeserializeOwned>(omega_path: &str, bytes: &[u8]) -> Result<T, StorageError> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    if let Some(schema) = crate::schema::for_path(omega_path) {
+        schema
+            .upgrade(&mut value)
+            .map_err(|err| StorageError::Schema(format!("{omega_path}: {err}")))?;
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Write `bytes` beside `path` and rename over it, so a crash mid-write
+/// leaves either the old file or the new one, never a torn mix.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), StorageError> {
+    use std::io::Write;
+
+    let parent = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let tmp = parent.join(format!(
+        "{name};tmp;{};{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })?;
+    Ok(())
+}
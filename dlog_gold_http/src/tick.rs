@@ -0,0 +1,116 @@
+//! The gateway's clock in φ ticks.
+//!
+//! Ticks count at [`PHI_TICK_HZ`] from the gateway's boot, read off the
+//! monotonic clock, so they never run backwards when the wall clock is
+//! adjusted. Client frames are paced in [`FRAME_MS`] frames on the same
+//! grid, or every few frames for sessions on slow links: every ack within
+//! one interval names the same `next_tick_ms`.
+//! [`TickAuthority::tick_at`] only depends on the boot time, so a recorded
+//! millisecond maps to the same tick when it is replayed.
+
+use spec::{MonetarySpec, PHI_TICK_HZ};
+use std::time::{Duration, Instant};
+
+use crate::omega::now_ms;
+
+/// Client frame length.
+pub const FRAME_MS: i64 = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TickAuthority {
+    boot: Instant,
+    boot_ms: i64,
+}
+
+impl TickAuthority {
+    pub fn start() -> Self {
+        Self {
+            boot: Instant::now(),
+            boot_ms: now_ms(),
+        }
+    }
+
+    pub fn boot_ms(&self) -> i64 {
+        self.boot_ms
+    }
+
+    /// Wall time as boot time plus monotonic uptime.
+    pub fn now_ms(&self) -> i64 {
+        self.boot_ms + self.boot.elapsed().as_millis() as i64
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        ticks_in(self.boot.elapsed())
+    }
+
+    /// Tick in progress at wall time `ms`; times before boot are tick 0.
+    pub fn tick_at(&self, ms: i64) -> u64 {
+        let since = u64::try_from(ms - self.boot_ms).unwrap_or_default();
+        ticks_in(Duration::from_millis(since))
+    }
+
+    /// Wall time at which `tick` starts, rounded up to the millisecond.
+    pub fn ms_at(&self, tick: u64) -> i64 {
+        self.boot_ms + (tick as f64 * 1000.0 / PHI_TICK_HZ).ceil() as i64
+    }
+
+    /// Start of the next client frame.
+    pub fn next_frame_ms(&self) -> i64 {
+        self.frame_after(self.now_ms())
+    }
+
+    /// Start of the next tick of a client ticking every `interval_ms`, a
+    /// whole number of frames; on the frame grid all the same.
+    pub fn next_tick_ms(&self, interval_ms: i64) -> i64 {
+        self.tick_after(self.now_ms(), interval_ms)
+    }
+
+    fn frame_after(&self, ms: i64) -> i64 {
+        self.tick_after(ms, FRAME_MS)
+    }
+
+    fn tick_after(&self, ms: i64, interval_ms: i64) -> i64 {
+        let since = (ms - self.boot_ms).max(0);
+        self.boot_ms + (since / interval_ms + 1) * interval_ms
+    }
+}
+
+fn ticks_in(elapsed: Duration) -> u64 {
+    (elapsed.as_secs_f64() * PHI_TICK_HZ) as u64
+}
+
+/// φ ticks one block of `spec` spans; sky shows advance this far per block.
+pub fn ticks_per_block(spec: &MonetarySpec) -> u64 {
+    (PHI_TICK_HZ * spec.target_block_seconds).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_and_frames_sit_on_the_boot_grid() {
+        let clock = TickAuthority::start();
+        let boot = clock.boot_ms();
+        assert_eq!(clock.tick_at(boot - 5_000), 0);
+        assert_eq!(clock.tick_at(boot + 1_000), 8_888);
+        assert_eq!(clock.ms_at(8_888), boot + 1_000);
+        assert_eq!(clock.tick_at(clock.ms_at(12_345)), 12_345);
+
+        assert_eq!(clock.frame_after(boot), boot + 8);
+        assert_eq!(clock.frame_after(boot + 7), boot + 8);
+        assert_eq!(clock.frame_after(boot + 8), boot + 16);
+        assert_eq!(clock.tick_after(boot + 50, 48), boot + 96);
+        assert_eq!(clock.tick_after(boot + 95, 48), boot + 96);
+        let next = clock.next_frame_ms();
+        assert!(next > clock.now_ms() - FRAME_MS && (next - boot) % FRAME_MS == 0);
+
+        let first = clock.current_tick();
+        assert!(clock.current_tick() >= first);
+        let spec = MonetarySpec::default();
+        assert_eq!(
+            ticks_per_block(&spec),
+            (spec.target_block_seconds * 8_888.0) as u64
+        );
+    }
+}
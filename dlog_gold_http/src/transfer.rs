@@ -0,0 +1,429 @@
+//! Transfer history: every settled bank transfer with its optional memo,
+//! category tag and client reference, searchable per label. A transfer
+//! between currency zones is two linked records, one on each planet; a
+//! batch transfer is one record against [`BATCH_LABEL`] listing its entries.
+//!
+//! Metadata follows the canon rules: no `;` (segment separator) and no dots
+//! anywhere, so a memo can be written into a canon file untouched.
+
+use corelib::exchange::Quote;
+use dlog_error::BankError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use spec::PlanetId;
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+
+/// Transfers kept for search (persisted as `;∞;bank;transfers;`).
+pub const TRANSFER_LOG: usize = 4096;
+/// Counterparty of both legs of a cross-zone transfer: the sent amount
+/// leaves the source zone and the converted amount enters the destination.
+pub const EXCHANGE_LABEL: &str = ";∞;exchange;";
+/// Counterparty of a batch transfer's record; the entries name the recipients.
+pub const BATCH_LABEL: &str = ";∞;batch;";
+/// Most entries one `batch_transfer` may pay.
+pub const BATCH_MAX: usize = 64;
+pub const MEMO_MAX: usize = 140;
+pub const TAG_MAX: usize = 32;
+pub const CLIENT_REF_MAX: usize = 64;
+/// Most records one search returns.
+pub const SEARCH_MAX: usize = 256;
+
+/// Optional fields a `transfer` payload may carry.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ref: Option<String>,
+}
+
+impl TransferMeta {
+    /// Read and validate `memo`, `tag` and `client_ref` from a frame payload.
+    /// Blank fields count as absent; tags are lowercased.
+    pub fn from_payload(payload: &Value) -> Result<Self, BankError> {
+        let field = |key: &str| -> Result<Option<String>, BankError> {
+            match payload.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::String(raw)) => {
+                    let trimmed = raw.trim();
+                    Ok((!trimmed.is_empty()).then(|| trimmed.to_string()))
+                }
+                Some(_) => Err(invalid(key, "must be a string")),
+            }
+        };
+
+        let memo = field("memo")?;
+        if let Some(memo) = &memo {
+            if memo.chars().count() > MEMO_MAX {
+                return Err(invalid("memo", &format!("exceeds {MEMO_MAX} characters")));
+            }
+            if memo.chars().any(|c| c == ';' || c == '.' || c.is_control()) {
+                return Err(invalid(
+                    "memo",
+                    "may not contain ';', '.' or control characters",
+                ));
+            }
+        }
+
+        let tag = field("tag")?.map(|tag| tag.to_ascii_lowercase());
+        if let Some(tag) = &tag {
+            check_token("tag", tag, TAG_MAX, |c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-'
+            })?;
+        }
+
+        let client_ref = field("client_ref")?;
+        if let Some(client_ref) = &client_ref {
+            check_token("client_ref", client_ref, CLIENT_REF_MAX, |c| {
+                c.is_ascii_alphanumeric() || c == '_' || c == '-'
+            })?;
+        }
+
+        Ok(Self {
+            memo,
+            tag,
+            client_ref,
+        })
+    }
+}
+
+fn invalid(field: &str, reason: &str) -> BankError {
+    BankError::InvalidMetadata(format!("{field} {reason}"))
+}
+
+fn check_token(
+    field: &str,
+    value: &str,
+    max: usize,
+    allowed: impl Fn(char) -> bool,
+) -> Result<(), BankError> {
+    if value.len() > max {
+        return Err(invalid(field, &format!("exceeds {max} characters")));
+    }
+    if !value.chars().all(allowed) {
+        return Err(invalid(field, "may only use letters, digits, '_' and '-'"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferRecord {
+    pub id: u64,
+    pub planet: PlanetId,
+    pub height: u64,
+    pub at_ms: i64,
+    pub from: String,
+    pub to: String,
+    pub amount: u128,
+    #[serde(flatten)]
+    pub meta: TransferMeta,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<ExchangeLeg>,
+    /// Recipients of a batch transfer, in payload order; `amount` is their sum.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub batch: Vec<BatchEntry>,
+}
+
+/// One payout of a batch transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchEntry {
+    pub to: String,
+    pub amount: u128,
+    #[serde(flatten)]
+    pub meta: TransferMeta,
+}
+
+/// Links the two legs of a cross-zone transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExchangeLeg {
+    /// Id of the other leg.
+    pub pair: u64,
+    /// Destination units per source unit, in ppm.
+    pub rate_ppm: u128,
+    /// Kept from the sent amount, in the source unit.
+    pub fee: u128,
+}
+
+impl TransferRecord {
+    fn involves(&self, label: &str) -> bool {
+        self.from == label || self.to == label || self.batch.iter().any(|e| e.to == label)
+    }
+
+    /// The record's own metadata, then each batch entry's.
+    fn metas(&self) -> impl Iterator<Item = &TransferMeta> {
+        std::iter::once(&self.meta).chain(self.batch.iter().map(|e| &e.meta))
+    }
+}
+
+/// What to look for in one label's history.
+#[derive(Debug, Clone)]
+pub struct TransferSearch {
+    /// Ledger key of the label (already privacy-keyed).
+    pub label: String,
+    pub planet: Option<PlanetId>,
+    /// Case-insensitive substring of the memo.
+    pub memo: Option<String>,
+    pub tag: Option<String>,
+    pub heights: RangeInclusive<u64>,
+    pub limit: usize,
+}
+
+impl TransferSearch {
+    fn matches(&self, record: &TransferRecord) -> bool {
+        let memo_hit = match &self.memo {
+            None => true,
+            Some(needle) => record.metas().any(|meta| {
+                meta.memo
+                    .as_ref()
+                    .is_some_and(|memo| memo.to_lowercase().contains(&needle.to_lowercase()))
+            }),
+        };
+        let tag_hit = match &self.tag {
+            None => true,
+            Some(tag) => record.metas().any(|meta| {
+                meta.tag
+                    .as_ref()
+                    .is_some_and(|t| t.eq_ignore_ascii_case(tag))
+            }),
+        };
+        record.involves(&self.label)
+            && self.planet.is_none_or(|planet| planet == record.planet)
+            && self.heights.contains(&record.height)
+            && memo_hit
+            && tag_hit
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TransferLog {
+    recent: Mutex<VecDeque<TransferRecord>>,
+}
+
+impl TransferLog {
+    pub fn from_records(records: Vec<TransferRecord>) -> Self {
+        Self {
+            recent: Mutex::new(records.into()),
+        }
+    }
+
+    pub fn records(&self) -> Vec<TransferRecord> {
+        let recent = self.recent.lock().expect("transfer log mutex poisoned");
+        recent.iter().cloned().collect()
+    }
+
+    pub fn append(
+        &self,
+        planet: PlanetId,
+        height: u64,
+        from: &str,
+        to: &str,
+        amount: u128,
+        meta: TransferMeta,
+    ) -> TransferRecord {
+        let mut recent = self.recent.lock().expect("transfer log mutex poisoned");
+        let record = TransferRecord {
+            id: recent.back().map(|r| r.id + 1).unwrap_or(1),
+            planet,
+            height,
+            at_ms: crate::omega::now_ms(),
+            from: from.into(),
+            to: to.into(),
+            amount,
+            meta,
+            exchange: None,
+            batch: Vec::new(),
+        };
+        push(&mut recent, record.clone());
+        record
+    }
+
+    /// Record a batch transfer as one record from `from` to [`BATCH_LABEL`]
+    /// for the entries' total.
+    pub fn append_batch(
+        &self,
+        planet: PlanetId,
+        height: u64,
+        from: &str,
+        meta: TransferMeta,
+        batch: Vec<BatchEntry>,
+    ) -> TransferRecord {
+        let mut recent = self.recent.lock().expect("transfer log mutex poisoned");
+        let record = TransferRecord {
+            id: recent.back().map(|r| r.id + 1).unwrap_or(1),
+            planet,
+            height,
+            at_ms: crate::omega::now_ms(),
+            from: from.into(),
+            to: BATCH_LABEL.into(),
+            amount: batch.iter().map(|e| e.amount).sum(),
+            meta,
+            exchange: None,
+            batch,
+        };
+        push(&mut recent, record.clone());
+        record
+    }
+
+    /// Record both legs of a cross-zone transfer: `from` pays the sent
+    /// amount out of `planets.0`, `to` receives the converted amount on
+    /// `planets.1`. Returns the source leg.
+    pub fn append_exchange(
+        &self,
+        planets: (PlanetId, PlanetId),
+        height: u64,
+        from: &str,
+        to: &str,
+        quote: &Quote,
+        meta: TransferMeta,
+    ) -> TransferRecord {
+        let mut recent = self.recent.lock().expect("transfer log mutex poisoned");
+        let id = recent.back().map(|r| r.id + 1).unwrap_or(1);
+        let leg = |pair| ExchangeLeg {
+            pair,
+            rate_ppm: quote.rate_ppm,
+            fee: quote.fee,
+        };
+        let sent = TransferRecord {
+            id,
+            planet: planets.0,
+            height,
+            at_ms: crate::omega::now_ms(),
+            from: from.into(),
+            to: EXCHANGE_LABEL.into(),
+            amount: quote.sent,
+            meta: meta.clone(),
+            exchange: Some(leg(id + 1)),
+            batch: Vec::new(),
+        };
+        let received = TransferRecord {
+            id: id + 1,
+            planet: planets.1,
+            from: EXCHANGE_LABEL.into(),
+            to: to.into(),
+            amount: quote.received,
+            meta,
+            exchange: Some(leg(id)),
+            ..sent.clone()
+        };
+        push(&mut recent, sent.clone());
+        push(&mut recent, received);
+        sent
+    }
+
+    pub fn last_id(&self) -> u64 {
+        let recent = self.recent.lock().expect("transfer log mutex poisoned");
+        recent.back().map(|r| r.id).unwrap_or_default()
+    }
+
+    /// Records with an id above `id`, oldest first.
+    pub fn after(&self, id: u64) -> Vec<TransferRecord> {
+        let recent = self.recent.lock().expect("transfer log mutex poisoned");
+        recent.iter().filter(|r| r.id > id).cloned().collect()
+    }
+
+    /// Append another log's records past our newest (read replicas).
+    pub fn ingest(&self, records: Vec<TransferRecord>) {
+        let mut recent = self.recent.lock().expect("transfer log mutex poisoned");
+        let last = recent.back().map(|r| r.id).unwrap_or_default();
+        for record in records.into_iter().filter(|r| r.id > last) {
+            push(&mut recent, record);
+        }
+    }
+
+    /// Matching records, newest first.
+    pub fn search(&self, search: &TransferSearch) -> Vec<TransferRecord> {
+        let recent = self.recent.lock().expect("transfer log mutex poisoned");
+        recent
+            .iter()
+            .rev()
+            .filter(|record| search.matches(record))
+            .take(search.limit.min(SEARCH_MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Ids of retained records touching `label`, oldest first.
+    pub fn history(&self, label: &str) -> Vec<u64> {
+        let recent = self.recent.lock().expect("transfer log mutex poisoned");
+        recent
+            .iter()
+            .filter(|record| record.involves(label))
+            .map(|record| record.id)
+            .collect()
+    }
+
+    /// Point every record touching `is_owned` labels at `tombstone` and drop
+    /// their free-text fields. Returns whether anything changed.
+    pub fn tombstone(&self, is_owned: impl Fn(&str) -> bool, tombstone: &str) -> bool {
+        let mut recent = self.recent.lock().expect("transfer log mutex poisoned");
+        let mut changed = false;
+        for record in recent.iter_mut() {
+            let mut hit = false;
+            for label in [&mut record.from, &mut record.to] {
+                if is_owned(label) {
+                    *label = tombstone.to_string();
+                    hit = true;
+                }
+            }
+            for entry in &mut record.batch {
+                if is_owned(&entry.to) {
+                    entry.to = tombstone.to_string();
+                    entry.meta.memo = None;
+                    entry.meta.client_ref = None;
+                    changed = true;
+                }
+            }
+            if hit {
+                record.meta.memo = None;
+                record.meta.client_ref = None;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn push(recent: &mut VecDeque<TransferRecord>, record: TransferRecord) {
+    if recent.len() >= TRANSFER_LOG {
+        recent.pop_front();
+    }
+    recent.push_back(record);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_follows_canon_rules() {
+        let meta = TransferMeta::from_payload(&serde_json::json!({
+            "memo": "  rent for march  ",
+            "tag": "Rent",
+            "client_ref": "inv-0042",
+        }))
+        .unwrap();
+        assert_eq!(meta.memo.as_deref(), Some("rent for march"));
+        assert_eq!(meta.tag.as_deref(), Some("rent"));
+
+        for bad in [
+            serde_json::json!({ "memo": "see example.com" }),
+            serde_json::json!({ "memo": "a;b" }),
+            serde_json::json!({ "memo": "x".repeat(MEMO_MAX + 1) }),
+            serde_json::json!({ "tag": "two words" }),
+            serde_json::json!({ "client_ref": 42 }),
+        ] {
+            assert!(matches!(
+                TransferMeta::from_payload(&bad),
+                Err(BankError::InvalidMetadata(_))
+            ));
+        }
+        assert_eq!(
+            TransferMeta::from_payload(&serde_json::json!({ "memo": " " })).unwrap(),
+            TransferMeta::default()
+        );
+    }
+}
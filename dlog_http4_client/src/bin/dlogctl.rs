@@ -0,0 +1,335 @@
+//! `dlogctl`: operator CLI over the gateway's public and admin APIs.
+//!
+//! ```text
+//! dlogctl [--json] status
+//! dlogctl [--json] sessions list | sessions kick <session_id>
+//! dlogctl [--json] bank balance <label> | bank transfer <from> <to> <amount>
+//! dlogctl [--json] blocks show [height]
+//! dlogctl [--json] chunks show <cx> <cz>
+//! dlogctl [--json] locks list
+//! dlogctl [--json] config reload
+//! ```
+//!
+//! The gateway is `OMEGA_EDGE` and the sim API `OMEGA_SIM_URL` (both default
+//! to `http://127.0.0.1:8080`); admin calls send `OMEGA_ADMIN_TOKEN`. Bank
+//! commands log in as `DLOG_PHONE` the way the demo client does, and take
+//! bare label names (`fun`) or full `;phone;label;` keys. Output is aligned
+//! tables, or the raw responses with `--json`.
+
+use dlog_http4_client::{balance_frame, omega_label, transfer_frame, FrameAck, OmegaClient};
+use serde_json::{json, Value};
+
+const USAGE: &str = "usage: dlogctl [--json] <status | sessions list | sessions kick <id> | \
+bank balance <label> | bank transfer <from> <to> <amount> | blocks show [height] | \
+chunks show <cx> <cz> | locks list | config reload>";
+
+/// Columns for list responses: header and the field it shows.
+const SESSION_COLUMNS: &[(&str, &str)] = &[
+    ("SESSION", "session_id"),
+    ("PLANET", "planet"),
+    ("PHONE", "phone"),
+    ("CAPABILITIES", "capabilities"),
+    ("ESTABLISHED_MS", "established_ms"),
+    ("LAST_INPUT_MS", "last_input_ms"),
+];
+const LOCK_COLUMNS: &[(&str, &str)] = &[
+    ("ESCROW", "escrow_id"),
+    ("PLANET", "planet"),
+    ("LABEL", "label"),
+    ("AMOUNT", "amount"),
+    ("ITEM", "item"),
+    ("DEADLINE", "deadline_height"),
+];
+const PLANET_COLUMNS: &[(&str, &str)] = &[
+    ("PLANET", "planet"),
+    ("UNIT", "unit"),
+    ("SESSIONS", "sessions"),
+    ("LABELS", "bank_labels"),
+    ("GAME_TICKS", "game_ticks"),
+];
+const CHUNK_COLUMNS: &[(&str, &str)] = &[
+    ("X", "x"),
+    ("Y", "y"),
+    ("Z", "z"),
+    ("BLOCK", "block"),
+    ("LAST_TICK", "last_tick"),
+];
+
+/// What a command printed: the response as received, and how to tabulate it.
+struct Output {
+    json: Value,
+    tables: Vec<Table>,
+}
+
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn list(items: &Value, columns: &[(&str, &str)]) -> Self {
+        let rows = items
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|item| {
+                        columns
+                            .iter()
+                            .map(|(_, field)| cell(&item[field]))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            headers: columns
+                .iter()
+                .map(|(header, _)| header.to_string())
+                .collect(),
+            rows,
+        }
+    }
+
+    /// One `KEY VALUE` row per scalar in `value`, keys sorted; nested objects
+    /// get dotted keys.
+    fn fields(value: &Value) -> Self {
+        fn walk(prefix: &str, value: &Value, rows: &mut Vec<Vec<String>>) {
+            match value.as_object() {
+                Some(object) => {
+                    for (key, value) in object {
+                        let key = match prefix {
+                            "" => key.clone(),
+                            _ => format!("{prefix}.{key}"),
+                        };
+                        walk(&key, value, rows);
+                    }
+                }
+                None => rows.push(vec![prefix.to_string(), cell(value)]),
+            }
+        }
+        let mut rows = Vec::new();
+        walk("", value, &mut rows);
+        Self {
+            headers: vec!["KEY".into(), "VALUE".into()],
+            rows,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        std::iter::once(&self.headers)
+            .chain(&self.rows)
+            .map(|row| {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| {
+                        let pad = width - cell.chars().count();
+                        format!("{cell}{}", " ".repeat(pad))
+                    })
+                    .collect();
+                line.join("  ").trim_end().to_string() + "\n"
+            })
+            .collect()
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".into(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let json_mode = args.iter().any(|arg| arg == "--json");
+    args.retain(|arg| arg != "--json");
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let edge = std::env::var("OMEGA_EDGE").unwrap_or_else(|_| "http://127.0.0.1:8080".into());
+    let gateway = OmegaClient::new(edge)?.with_admin_token(
+        std::env::var("OMEGA_ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty()),
+    );
+
+    let output = match args.as_slice() {
+        ["status"] => status(&gateway).await?,
+        ["sessions", "list"] => {
+            let sessions: Value = gateway.admin_get("/admin/sessions").await?;
+            listed(sessions, SESSION_COLUMNS)
+        }
+        ["sessions", "kick", session_id] => {
+            let kicked: Value = gateway
+                .admin_delete(&format!("/admin/sessions/{session_id}"))
+                .await?;
+            listed(json!([kicked]), SESSION_COLUMNS)
+        }
+        ["bank", "balance", label] => {
+            bank(&gateway, |session, phone| {
+                balance_frame(session, &omega_label(phone, label))
+            })
+            .await?
+        }
+        ["bank", "transfer", from, to, amount] => {
+            let amount: u64 = amount
+                .parse()
+                .map_err(|_| anyhow::anyhow!("amount must be a whole number, got {amount:?}"))?;
+            bank(&gateway, |session, phone| {
+                transfer_frame(
+                    session,
+                    &omega_label(phone, from),
+                    &omega_label(phone, to),
+                    amount,
+                )
+            })
+            .await?
+        }
+        ["blocks", "show"] => {
+            let tip: Value = gateway.get("/explorer/tip").await?;
+            fields(tip)
+        }
+        ["blocks", "show", height] => {
+            let header: Value = gateway.get(&format!("/explorer/blocks/{height}")).await?;
+            fields(header)
+        }
+        ["chunks", "show", cx, cz] => {
+            let sim_url =
+                std::env::var("OMEGA_SIM_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".into());
+            let chunk: Value = OmegaClient::new(sim_url)?
+                .get(&format!("/explorer/chunks/{cx}/{cz}"))
+                .await?;
+            let mut summary = chunk.clone();
+            let blocks = summary
+                .as_object_mut()
+                .and_then(|chunk| chunk.remove("blocks"))
+                .unwrap_or_default();
+            Output {
+                tables: vec![Table::fields(&summary), Table::list(&blocks, CHUNK_COLUMNS)],
+                json: chunk,
+            }
+        }
+        ["locks", "list"] => {
+            let locks: Value = gateway.admin_get("/admin/locks").await?;
+            listed(locks, LOCK_COLUMNS)
+        }
+        ["config", "reload"] => {
+            let reloaded: Value = gateway.admin_post("/admin/config/reload").await?;
+            fields(reloaded)
+        }
+        _ => anyhow::bail!("{USAGE}"),
+    };
+
+    if json_mode {
+        println!("{}", serde_json::to_string_pretty(&output.json)?);
+    } else {
+        let tables: Vec<String> = output.tables.iter().map(Table::render).collect();
+        print!("{}", tables.join("\n"));
+    }
+    Ok(())
+}
+
+fn listed(items: Value, columns: &[(&str, &str)]) -> Output {
+    Output {
+        tables: vec![Table::list(&items, columns)],
+        json: items,
+    }
+}
+
+fn fields(value: Value) -> Output {
+    Output {
+        tables: vec![Table::fields(&value)],
+        json: value,
+    }
+}
+
+async fn status(gateway: &OmegaClient) -> anyhow::Result<Output> {
+    let status: Value = gateway.get("/omega/status").await?;
+    let gateway_status = &status["gateway"];
+    let summary = json!({
+        "gateway_id": gateway_status["gateway_id"],
+        "boot_ms": gateway_status["boot_ms"],
+        "block_height": gateway_status["block_height"],
+        "session_count": gateway_status["session_count"],
+        "scheduled_tasks": gateway_status["scheduled_tasks"],
+        "consensus": gateway_status["consensus"]["policy"],
+        "producing": gateway_status["consensus"]["producing"],
+        "sync_lag": status["sync_lag"],
+    });
+    Ok(Output {
+        tables: vec![
+            Table::fields(&summary),
+            Table::list(&gateway_status["planets"], PLANET_COLUMNS),
+        ],
+        json: status,
+    })
+}
+
+/// Log in as `DLOG_PHONE`, handshake a banking session and send one frame.
+async fn bank(
+    gateway: &OmegaClient,
+    frame: impl FnOnce(&str, &str) -> dlog_http4_client::FrameEnvelope,
+) -> anyhow::Result<Output> {
+    let phone = std::env::var("DLOG_PHONE").unwrap_or_else(|_| "9132077554".into());
+    let label = std::env::var("DLOG_LABEL").unwrap_or_else(|_| "comet".into());
+    let identity = gateway.login_via_phone(&phone, &label, "dlogctl").await?;
+    let session = gateway
+        .handshake(&identity.handshake_request(std::env::var("OMEGA_PLANET").ok()))
+        .await?;
+    let frame = frame(&session.session_id, &phone).signed(session.frame_key.as_deref());
+    let ack: FrameAck = gateway.send_frame(&frame).await?;
+    let json = json!({
+        "seq": ack.seq,
+        "accepted": ack.accepted,
+        "payload": frame.payload,
+        "notes": ack.notes,
+    });
+    let rows = ack.notes.iter().map(|note| vec![note.clone()]).collect();
+    Ok(Output {
+        tables: vec![
+            Table::fields(&json!({ "accepted": ack.accepted, "payload": frame.payload })),
+            Table {
+                headers: vec!["NOTES".into()],
+                rows,
+            },
+        ],
+        json,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_align_columns_and_flatten_nested_fields() {
+        let sessions = json!([
+            { "session_id": "a1", "planet": "earth", "phone": "********54",
+              "capabilities": ["banking", "render"] },
+            { "session_id": "b", "planet": "moon", "phone": null, "capabilities": [] },
+        ]);
+        let table = Table::list(&sessions, &SESSION_COLUMNS[..4]);
+        assert_eq!(
+            table.render(),
+            "SESSION  PLANET  PHONE       CAPABILITIES\n\
+             a1       earth   ********54  banking,render\n\
+             b        moon    -\n"
+        );
+
+        let reloaded = Table::fields(&json!({ "dns": { "version": 3, "records": 8 } }));
+        assert_eq!(
+            reloaded.render(),
+            "KEY          VALUE\ndns.records  8\ndns.version  3\n"
+        );
+    }
+}
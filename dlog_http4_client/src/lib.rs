@@ -0,0 +1,604 @@
+//! Client SDK for the HTTP-4 bridge (`dlog_gold_http`).
+//!
+//! Wire types for handshakes and frames, plus [`OmegaClient`], which logs in
+//! by phone, handshakes, sends bank frames and calls the admin API. The
+//! `dlog_http4_client` demo and the `dlogctl` operator CLI are built on it.
+//!
+//! [`OmegaClient::reconnect`] is the reconnect loop: it waits as long as the
+//! gateway's backoff hints say (a deferred handshake's `retry_after_ms`, or
+//! the one the dropped session's handshake carried), and otherwise backs off
+//! exponentially with jitter seeded by the client id, so a wave of clients
+//! dropped together doesn't come back together.
+//!
+//! Bank frames are signed with the session's handshake `frame_key` (see
+//! [`FrameEnvelope::signed`]); gateways may reject unsigned ones.
+//! [`ResponseVerifier`] checks signed sim tick responses against pinned
+//! keys.
+
+pub mod response_signature;
+
+pub use response_signature::{ResponseVerifier, SignatureError};
+
+use corelib::consensus::{frame_signing_bytes, BlockSigner, KeyedSigner};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub struct HandshakeRequest {
+    pub client_id: String,
+    pub capabilities: Vec<String>,
+    pub requested_routes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub planet: Option<String>,
+    /// `player` (the gateway's default) or `spectator`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandshakeResponse {
+    pub session_id: String,
+    pub kernel_version: String,
+    pub motd: String,
+    pub granted_routes: Vec<RouteHint>,
+    pub identity: Option<IdentityDescriptor>,
+    #[serde(default)]
+    pub planet: Option<String>,
+    /// Frame kinds the gateway handles (`TICK_FRAME`, `QUERY`, …); empty
+    /// from gateways that predate the list.
+    #[serde(default)]
+    pub frame_kinds: Vec<String>,
+    #[serde(default)]
+    pub session_kind: Option<String>,
+    #[serde(default)]
+    pub gateway_id: Option<String>,
+    /// Boot count of `gateway_id`; 0 from gateways that predate it.
+    #[serde(default)]
+    pub incarnation: u64,
+    /// Set while the gateway is deferring handshakes: how long to wait
+    /// before re-handshaking should this session drop.
+    #[serde(default)]
+    pub retry_after_ms: Option<u64>,
+    /// Key to sign this session's bank frames with; `None` from gateways
+    /// that predate frame keys.
+    #[serde(default)]
+    pub frame_key: Option<String>,
+}
+
+impl HandshakeResponse {
+    /// Whether the gateway will accept frames of `kind`. Gateways that don't
+    /// list their kinds are assumed to take the original ones.
+    pub fn supports(&self, kind: FrameKind) -> bool {
+        let name = serde_json::to_value(kind).unwrap_or_default();
+        self.frame_kinds.is_empty() || self.frame_kinds.iter().any(|k| name == k.as_str())
+    }
+}
+
+/// How [`OmegaClient::reconnect`] backs off when the gateway gives no hint.
+#[derive(Debug, Clone, Copy)]
+pub struct Reconnect {
+    /// First wait; each failed attempt doubles it.
+    pub base: Duration,
+    pub max: Duration,
+    pub attempts: u32,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(250),
+            max: Duration::from_secs(30),
+            attempts: 10,
+        }
+    }
+}
+
+impl Reconnect {
+    /// Wait before attempt `attempt + 1`: `base·2^attempt` up to `max`,
+    /// then somewhere in its upper half, seeded by `client_id` so clients
+    /// that failed together spread out.
+    pub fn backoff(&self, attempt: u32, client_id: &str) -> Duration {
+        let ceiling = self
+            .base
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max)
+            .as_millis() as u64;
+        let mut hasher = DefaultHasher::new();
+        (client_id, attempt).hash(&mut hasher);
+        let half = ceiling / 2;
+        Duration::from_millis(ceiling - half + hasher.finish() % (half + 1))
+    }
+}
+
+/// One handshake attempt.
+enum Attempt {
+    Open(Box<HandshakeResponse>),
+    /// Deferred or unreachable; wait the gateway's hint if it gave one.
+    Retry(Option<Duration>),
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct PhoneStartResponse {
+    session_token: String,
+    expires_in_ms: i64,
+    biometric_required: bool,
+    instructions: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct PhoneConfirmResponse {
+    status: String,
+    verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RouteHint {
+    pub omega_path: String,
+    pub target: String,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdentityDescriptor {
+    pub phone: String,
+    pub label: String,
+    pub display_name: String,
+    pub presence_state: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrameEnvelope {
+    pub session_id: String,
+    pub seq: u64,
+    pub namespace: String,
+    pub kind: FrameKind,
+    pub payload: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl FrameEnvelope {
+    /// Sign with the session's `frame_key`: a MAC over the session id, seq,
+    /// namespace and payload digest. Without a key the frame goes unsigned.
+    pub fn signed(mut self, frame_key: Option<&str>) -> Self {
+        self.signature = frame_key.map(|key| {
+            KeyedSigner::from_secret(key).sign(&frame_signing_bytes(
+                &self.session_id,
+                self.seq,
+                &self.namespace,
+                &self.payload,
+            ))
+        });
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FrameAck {
+    pub session_id: String,
+    pub seq: u64,
+    pub accepted: bool,
+    /// When to send the next tick, and the interval the gateway paces this
+    /// session at (longer on slow links).
+    #[serde(default)]
+    pub next_tick_ms: i64,
+    #[serde(default)]
+    pub tick_interval_ms: i64,
+    pub routed: Vec<RouteHint>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FrameKind {
+    TickFrame,
+    Query,
+    Event,
+    MineJob,
+    MineResult,
+    Dns,
+    Audio,
+    Game,
+}
+
+/// A phone whose `/auth/phone` session was confirmed.
+#[derive(Debug)]
+pub struct ClientIdentity {
+    pub phone: String,
+    pub label: String,
+    pub display_name: String,
+    pub session_token: String,
+}
+
+impl ClientIdentity {
+    /// Handshake for a `render` + `banking` session on `planet` (Earth if `None`).
+    pub fn handshake_request(&self, planet: Option<String>) -> HandshakeRequest {
+        HandshakeRequest {
+            client_id: Uuid::new_v4().to_string(),
+            capabilities: vec!["render".into(), "banking".into()],
+            requested_routes: vec![";∞;bank;infinity;".into()],
+            phone: Some(self.phone.clone()),
+            session_token: Some(self.session_token.clone()),
+            planet,
+            session_kind: None,
+        }
+    }
+
+    /// Handshake for a read-only spectator session on `planet`: it watches
+    /// and listens, and the gateway refuses its input and world frames.
+    pub fn spectator_request(&self, planet: Option<String>) -> HandshakeRequest {
+        HandshakeRequest {
+            client_id: Uuid::new_v4().to_string(),
+            capabilities: Vec::new(),
+            requested_routes: Vec::new(),
+            phone: Some(self.phone.clone()),
+            session_token: Some(self.session_token.clone()),
+            planet,
+            session_kind: Some("spectator".into()),
+        }
+    }
+}
+
+/// `POST /omega/sessions/:id/migration`: a one-time code for another device.
+#[derive(Debug, Deserialize)]
+pub struct MigrationCode {
+    pub code: String,
+    pub expires_ms: i64,
+}
+
+/// A session taken over from another device with a [`MigrationCode`].
+#[derive(Debug, Deserialize)]
+pub struct MigratedSession {
+    #[serde(flatten)]
+    pub handshake: HandshakeResponse,
+    /// Phone-auth token for this device; the old device's is revoked.
+    pub session_token: Option<String>,
+}
+
+/// One message on the `/omega/ws` frame socket. Acks carry their frame's
+/// namespace; pushes come on `;∞;events;`, `;∞;notifications;` and
+/// `;∞;tick;`, and the socket's own errors on `;∞;socket;`.
+#[derive(Debug, Deserialize)]
+pub struct SocketMessage {
+    pub namespace: String,
+    #[serde(flatten)]
+    pub body: SocketBody,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SocketBody {
+    Ack {
+        ack: FrameAck,
+    },
+    Event {
+        event: Value,
+    },
+    Notification {
+        notification: Value,
+    },
+    Tick {
+        height: u64,
+        tick: u64,
+        next_tick_ms: i64,
+    },
+    Error {
+        message: String,
+    },
+    Closed {
+        reason: String,
+    },
+}
+
+pub struct OmegaClient {
+    http: Client,
+    endpoint: String,
+    admin_token: Option<String>,
+}
+
+impl OmegaClient {
+    pub fn new(endpoint: impl Into<String>) -> anyhow::Result<Self> {
+        Ok(Self {
+            http: Client::builder().timeout(Duration::from_secs(5)).build()?,
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            admin_token: None,
+        })
+    }
+
+    /// Sent as `X-Admin-Token` on admin calls.
+    pub fn with_admin_token(mut self, token: Option<String>) -> Self {
+        self.admin_token = token;
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.endpoint)
+    }
+
+    fn admin(&self, request: RequestBuilder) -> anyhow::Result<RequestBuilder> {
+        let token = self
+            .admin_token
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("OMEGA_ADMIN_TOKEN is required"))?;
+        Ok(request.header("x-admin-token", token))
+    }
+
+    /// Start and confirm a phone session (`DLOG_BIOMETRIC` signs the confirm).
+    pub async fn login_via_phone(
+        &self,
+        phone: &str,
+        label: &str,
+        display_name: &str,
+    ) -> anyhow::Result<ClientIdentity> {
+        let started: PhoneStartResponse = read(self.http.post(self.url("/auth/phone/start")).json(
+            &serde_json::json!({
+                "phone": phone,
+                "label": label,
+                "display_name": display_name,
+            }),
+        ))
+        .await?;
+        let biometric_signature =
+            std::env::var("DLOG_BIOMETRIC").unwrap_or_else(|_| "biometric-ok".into());
+        let confirmed: PhoneConfirmResponse = read(
+            self.http
+                .post(self.url("/auth/phone/confirm"))
+                .json(&serde_json::json!({
+                    "session_token": started.session_token,
+                    "biometric_signature": biometric_signature,
+                })),
+        )
+        .await?;
+        if !confirmed.verified {
+            anyhow::bail!("biometric confirmation failed");
+        }
+        Ok(ClientIdentity {
+            phone: phone.into(),
+            label: label.into(),
+            display_name: display_name.into(),
+            session_token: started.session_token,
+        })
+    }
+
+    pub async fn handshake(&self, request: &HandshakeRequest) -> anyhow::Result<HandshakeResponse> {
+        read(self.http.post(self.url("/omega/handshake")).json(request)).await
+    }
+
+    /// Handshake again after `dropped` (the session that was lost, if any),
+    /// first waiting out the hint its handshake carried. Deferrals and
+    /// unreachable or failing gateways are retried up to `policy.attempts`
+    /// times, each after the gateway's `retry_after_ms` when given, else
+    /// after [`Reconnect::backoff`]. Refusals (bad credentials, say) are not.
+    pub async fn reconnect(
+        &self,
+        dropped: Option<&HandshakeResponse>,
+        request: &HandshakeRequest,
+        policy: &Reconnect,
+    ) -> anyhow::Result<HandshakeResponse> {
+        if let Some(hint) = dropped.and_then(|session| session.retry_after_ms) {
+            tokio::time::sleep(Duration::from_millis(hint)).await;
+        }
+        for attempt in 0..policy.attempts {
+            let wait = match self.try_handshake(request).await? {
+                Attempt::Open(response) => return Ok(*response),
+                Attempt::Retry(hint) => {
+                    hint.unwrap_or_else(|| policy.backoff(attempt, &request.client_id))
+                }
+            };
+            warn!(
+                "[Ω] handshake attempt {} not admitted; retrying in {} ms",
+                attempt + 1,
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+        anyhow::bail!(
+            "gateway at {} still deferring after {} handshakes",
+            self.endpoint,
+            policy.attempts
+        )
+    }
+
+    async fn try_handshake(&self, request: &HandshakeRequest) -> anyhow::Result<Attempt> {
+        let sent = self
+            .http
+            .post(self.url("/omega/handshake"))
+            .json(request)
+            .send()
+            .await;
+        let resp = match sent {
+            Ok(resp) => resp,
+            Err(err) if err.is_connect() || err.is_timeout() => return Ok(Attempt::Retry(None)),
+            Err(err) => return Err(err.into()),
+        };
+        if !resp.status().is_server_error() {
+            return Ok(Attempt::Open(Box::new(decode(resp).await?)));
+        }
+        let header_secs = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|secs| secs.trim().parse::<u64>().ok());
+        let body: Value = resp.json().await.unwrap_or_default();
+        let hint = body
+            .get("retry_after_ms")
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis)
+            .or_else(|| header_secs.map(Duration::from_secs));
+        Ok(Attempt::Retry(hint))
+    }
+
+    /// Mint a code that moves `session_id` to another device.
+    pub async fn mint_migration_code(
+        &self,
+        identity: &ClientIdentity,
+        session_id: &str,
+    ) -> anyhow::Result<MigrationCode> {
+        let path = format!("/omega/sessions/{session_id}/migration");
+        read(self.http.post(self.url(&path)).json(&serde_json::json!({
+            "phone": identity.phone,
+            "session_token": identity.session_token,
+        })))
+        .await
+    }
+
+    /// Take over the session a migration `code` was minted for.
+    pub async fn redeem_migration_code(&self, code: &str) -> anyhow::Result<MigratedSession> {
+        read(
+            self.http
+                .post(self.url("/omega/sessions/migrate"))
+                .json(&serde_json::json!({
+                    "code": code,
+                    "client_id": Uuid::new_v4().to_string(),
+                })),
+        )
+        .await
+    }
+
+    pub async fn send_frame(&self, frame: &FrameEnvelope) -> anyhow::Result<FrameAck> {
+        read(self.http.post(self.url("/omega/frame")).json(frame)).await
+    }
+
+    /// Where to open `session_id`'s frame socket: send frames there as JSON
+    /// text and read [`SocketMessage`]s back.
+    pub fn frame_socket_url(&self, session_id: &str) -> String {
+        let endpoint = self
+            .endpoint
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+        format!("{endpoint}/omega/ws?session={session_id}")
+    }
+
+    /// Public GET, e.g. `/omega/status` or `/explorer/tip`.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        read(self.http.get(self.url(path))).await
+    }
+
+    pub async fn admin_get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        read(self.admin(self.http.get(self.url(path)))?).await
+    }
+
+    pub async fn admin_post<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        read(self.admin(self.http.post(self.url(path)))?).await
+    }
+
+    pub async fn admin_delete<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        read(self.admin(self.http.delete(self.url(path)))?).await
+    }
+
+    /// Current block height from `/omega/status`.
+    pub async fn block_height(&self) -> anyhow::Result<u64> {
+        #[derive(Deserialize)]
+        struct Status {
+            block_height: u64,
+        }
+        Ok(self.get::<Status>("/omega/status").await?.block_height)
+    }
+
+    /// One signup frame and the cursor of the next.
+    pub async fn signup_frame(&self, cursor: usize) -> anyhow::Result<(String, usize)> {
+        let resp = self
+            .http
+            .get(self.url(&format!("/signup/frame?cursor={cursor}")))
+            .send()
+            .await?
+            .error_for_status()?;
+        let next_cursor = resp
+            .headers()
+            .get("x-next-cursor")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(cursor.wrapping_add(1));
+        Ok((resp.text().await?, next_cursor))
+    }
+}
+
+/// Send `request` and decode its JSON; an error status fails with the body
+/// the server gave, so gateway refusals read as themselves.
+async fn read<T: DeserializeOwned>(request: RequestBuilder) -> anyhow::Result<T> {
+    decode(request.send().await?).await
+}
+
+async fn decode<T: DeserializeOwned>(resp: Response) -> anyhow::Result<T> {
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        anyhow::bail!("{status}: {}", body.trim());
+    }
+    Ok(resp.json().await?)
+}
+
+pub fn balance_frame(session_id: &str, label: &str) -> FrameEnvelope {
+    FrameEnvelope {
+        session_id: session_id.into(),
+        seq: frame_seq(),
+        namespace: ";∞;bank;infinity;balances;".into(),
+        kind: FrameKind::Query,
+        payload: serde_json::json!({
+            "kind": "balance_query",
+            "label": label
+        }),
+        signature: None,
+    }
+}
+
+pub fn transfer_frame(session_id: &str, from: &str, to: &str, amount: u64) -> FrameEnvelope {
+    FrameEnvelope {
+        session_id: session_id.into(),
+        seq: frame_seq(),
+        namespace: ";∞;bank;infinity;transfer;".into(),
+        kind: FrameKind::Event,
+        payload: serde_json::json!({
+            "kind": "transfer",
+            "from": from,
+            "to": to,
+            "amount": amount,
+        }),
+        signature: None,
+    }
+}
+
+/// A `TICK_FRAME` reporting the round trip the client measured, so the
+/// gateway can pace it (see `FrameAck::tick_interval_ms`).
+pub fn heartbeat_frame(session_id: &str, rtt_ms: u64) -> FrameEnvelope {
+    FrameEnvelope {
+        session_id: session_id.into(),
+        seq: frame_seq(),
+        namespace: ";game;".into(),
+        kind: FrameKind::TickFrame,
+        payload: serde_json::json!({ "rtt_ms": rtt_ms }),
+        signature: None,
+    }
+}
+
+fn frame_seq() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// `;phone;label;`; a label already in that form is returned as is.
+pub fn omega_label(phone: &str, label: &str) -> String {
+    if label.starts_with(';') {
+        return label.to_string();
+    }
+    format!(";{phone};{label};")
+}
@@ -0,0 +1,157 @@
+//! Checking signed `/v1/sim/tick` responses.
+//!
+//! With signing on, the sim API sends `X-Omega-Signature: <kid>.<sig>`: an
+//! Ed25519 signature of the body's SHA-256 by the key `kid` names. A
+//! [`ResponseVerifier`] only trusts keys its caller pinned, e.g. copied
+//! from `GET /v1/sim/keys` when the deployment was set up. It never fetches
+//! keys from the server it checks, since whoever can rewrite a response can
+//! rewrite the key set too; an unknown `kid` fails until the pins change.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use spec::ResponseKeySet;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Why a response didn't verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// No `X-Omega-Signature`, or not `<kid>.<sig>`.
+    Unsigned,
+    /// Signed with a key that isn't pinned.
+    UnknownKey(String),
+    /// The signature doesn't match the body.
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::Unsigned => write!(f, "response is not signed"),
+            SignatureError::UnknownKey(kid) => write!(f, "response signed with unpinned key {kid}"),
+            SignatureError::Mismatch => write!(f, "response signature does not match its body"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Pinned Ed25519 response keys by kid.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseVerifier {
+    keys: BTreeMap<String, Vec<u8>>,
+}
+
+/// Id of an Ed25519 public key: hex of the first 8 bytes of its SHA-256.
+pub fn key_id(public_key: &[u8]) -> String {
+    digest(&SHA256, public_key).as_ref()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+impl ResponseVerifier {
+    /// Trust exactly `keys`. Each must be a 32-byte base64url public key
+    /// under its own kid, so a mistyped pin fails here rather than on
+    /// every response.
+    pub fn pinned(keys: &ResponseKeySet) -> Result<Self, String> {
+        let mut pinned = BTreeMap::new();
+        for key in &keys.keys {
+            let public = URL_SAFE_NO_PAD
+                .decode(key.public_key.trim().trim_end_matches('='))
+                .map_err(|err| format!("key {} is not base64url: {err}", key.kid))?;
+            if public.len() != 32 {
+                return Err(format!("key {} is not a 32-byte Ed25519 key", key.kid));
+            }
+            if key_id(&public) != key.kid {
+                return Err(format!("key {} has another key's id", key.kid));
+            }
+            pinned.insert(key.kid.clone(), public);
+        }
+        Ok(Self { keys: pinned })
+    }
+
+    /// Check a response's `X-Omega-Signature` value against its `body`,
+    /// exactly as received.
+    pub fn verify(&self, header: Option<&str>, body: &[u8]) -> Result<(), SignatureError> {
+        let (kid, sig) = header
+            .and_then(|h| h.trim().split_once('.'))
+            .ok_or(SignatureError::Unsigned)?;
+        let public = self
+            .keys
+            .get(kid)
+            .ok_or_else(|| SignatureError::UnknownKey(kid.to_string()))?;
+        let sig = URL_SAFE_NO_PAD
+            .decode(sig)
+            .map_err(|_| SignatureError::Mismatch)?;
+        UnparsedPublicKey::new(&ED25519, public)
+            .verify(digest(&SHA256, body).as_ref(), &sig)
+            .map_err(|_| SignatureError::Mismatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use spec::ResponseKey;
+
+    fn signer(seed: u8) -> (Ed25519KeyPair, ResponseKey) {
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap();
+        let public = pair.public_key().as_ref();
+        let key = ResponseKey {
+            kid: key_id(public),
+            public_key: URL_SAFE_NO_PAD.encode(public),
+            active: true,
+        };
+        (pair, key)
+    }
+
+    fn sign(pair: &Ed25519KeyPair, key: &ResponseKey, body: &[u8]) -> String {
+        let sig = pair.sign(digest(&SHA256, body).as_ref());
+        format!("{}.{}", key.kid, URL_SAFE_NO_PAD.encode(sig.as_ref()))
+    }
+
+    #[test]
+    fn only_pinned_keys_verify() {
+        let (pair, key) = signer(1);
+        let (proxy, proxy_key) = signer(2);
+        let verifier = ResponseVerifier::pinned(&ResponseKeySet {
+            keys: vec![key.clone()],
+        })
+        .unwrap();
+        let body = br#"{"tick":1}"#;
+
+        assert_eq!(
+            verifier.verify(Some(&sign(&pair, &key, body)), body),
+            Ok(())
+        );
+        assert_eq!(
+            verifier.verify(Some(&sign(&pair, &key, body)), br#"{"tick":2}"#),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(verifier.verify(None, body), Err(SignatureError::Unsigned));
+        // A proxy's own key isn't trusted just because it signed.
+        assert_eq!(
+            verifier.verify(Some(&sign(&proxy, &proxy_key, body)), body),
+            Err(SignatureError::UnknownKey(proxy_key.kid.clone()))
+        );
+        // Nor can it borrow a pinned kid for its key.
+        let borrowed = sign(&proxy, &key, body);
+        assert_eq!(
+            verifier.verify(Some(&borrowed), body),
+            Err(SignatureError::Mismatch)
+        );
+
+        let mislabelled = ResponseKey {
+            kid: key.kid,
+            ..proxy_key
+        };
+        assert!(ResponseVerifier::pinned(&ResponseKeySet {
+            keys: vec![mislabelled]
+        })
+        .is_err());
+    }
+}
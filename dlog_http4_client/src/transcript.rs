@@ -0,0 +1,111 @@
+//! Records a live session as a golden transcript for the gateway's fixture
+//! runner (`dlog_gold_http/fixtures/`). The session id in notes is replaced
+//! by `{session}` so the transcript replays against any handshake. Interest
+//! accrues per block, so the transcript keeps the height it was recorded at
+//! and the runner seals up to it before replaying.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+use dlog_http4_client::{FrameAck, FrameEnvelope, FrameKind, HandshakeRequest};
+
+const SESSION_PLACEHOLDER: &str = "{session}";
+
+#[derive(Debug, Serialize)]
+struct Transcript {
+    name: String,
+    height: u64,
+    handshake: Value,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Serialize)]
+struct Step {
+    frame: RecordedFrame,
+    ack: RecordedAck,
+}
+
+#[derive(Debug, Serialize)]
+struct RecordedFrame {
+    seq: u64,
+    namespace: String,
+    kind: FrameKind,
+    payload: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RecordedAck {
+    accepted: bool,
+    routed: Vec<String>,
+    notes: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    session_id: String,
+    transcript: Transcript,
+}
+
+impl Recorder {
+    /// Start a transcript for the session `handshake` opened. The session
+    /// token is left out; the replay runner trusts the recorded phone so bank
+    /// frames still spend from the phone's own labels. `height` is the
+    /// gateway's block height when the session started.
+    pub fn new(name: &str, handshake: &HandshakeRequest, session_id: &str, height: u64) -> Self {
+        let mut recorded = serde_json::json!({
+            "client_id": handshake.client_id,
+            "capabilities": handshake.capabilities,
+            "requested_routes": handshake.requested_routes,
+        });
+        if let Some(phone) = &handshake.phone {
+            recorded["phone"] = phone.as_str().into();
+        }
+        if let Some(planet) = &handshake.planet {
+            recorded["planet"] = planet.as_str().into();
+        }
+        Self {
+            session_id: session_id.into(),
+            transcript: Transcript {
+                name: name.into(),
+                height,
+                handshake: recorded,
+                steps: Vec::new(),
+            },
+        }
+    }
+
+    pub fn record(&mut self, frame: &FrameEnvelope, ack: &FrameAck) {
+        self.transcript.steps.push(Step {
+            frame: RecordedFrame {
+                seq: frame.seq,
+                namespace: frame.namespace.clone(),
+                kind: frame.kind,
+                payload: frame.payload.clone(),
+            },
+            ack: RecordedAck {
+                accepted: ack.accepted,
+                routed: ack.routed.iter().map(|h| h.omega_path.clone()).collect(),
+                notes: ack
+                    .notes
+                    .iter()
+                    .map(|note| note.replace(&self.session_id, SESSION_PLACEHOLDER))
+                    .collect(),
+            },
+        });
+    }
+
+    /// Write the transcript, unless a block sealed while recording: balances
+    /// would then mix two heights and no replay could match them.
+    pub fn write(&self, path: &Path, height_now: u64) -> anyhow::Result<()> {
+        if height_now != self.transcript.height {
+            anyhow::bail!(
+                "block height moved from {} to {height_now} while recording; record again",
+                self.transcript.height
+            );
+        }
+        let json = serde_json::to_string_pretty(&self.transcript)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! Cache headers for CDN-facing routes in the gateway and the sim.
+//!
+//! Handlers tag their JSON with an ETag through [`Tagged`]; wrapping a route
+//! in [`cached`] adds the route's `Cache-Control` and answers a matching
+//! `If-None-Match` with `304 Not Modified`, so revalidation costs no body.
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+    Json,
+};
+use serde::Serialize;
+
+/// How long shared caches may serve a route before revalidating.
+#[derive(Debug, Clone, Copy)]
+pub struct CachePolicy {
+    pub max_age: u32,
+    /// Extra window in which a stale copy may be served while refetching.
+    pub stale_while_revalidate: u32,
+}
+
+impl CachePolicy {
+    /// Moves with every seal (~8 s).
+    pub const TIP: Self = Self {
+        max_age: 4,
+        stale_while_revalidate: 8,
+    };
+    /// Sealed history below the tip only changes on a reorg.
+    pub const HISTORY: Self = Self {
+        max_age: 300,
+        stale_while_revalidate: 3600,
+    };
+
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!(
+            "public, max-age={}, stale-while-revalidate={}",
+            self.max_age, self.stale_while_revalidate
+        ))
+        .expect("cache-control is ascii")
+    }
+}
+
+/// JSON body plus the strong ETag it was derived from.
+pub struct Tagged<T>(pub String, pub T);
+
+impl<T: Serialize> IntoResponse for Tagged<T> {
+    fn into_response(self) -> Response {
+        let mut response = Json(self.1).into_response();
+        if let Ok(value) = HeaderValue::from_str(&self.0) {
+            response.headers_mut().insert(header::ETAG, value);
+        }
+        response
+    }
+}
+
+fn matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(candidates) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    candidates.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == etag.trim_start_matches("W/")
+    })
+}
+
+/// Apply `policy` to `route` and turn ETag hits into `304 Not Modified`.
+pub fn cached<S>(route: MethodRouter<S>, policy: CachePolicy) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    route.layer(middleware::from_fn(
+        move |req: Request, next: Next| async move {
+            let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+            let mut response = next.run(req).await;
+            if !response.status().is_success() {
+                return response;
+            }
+            // Handlers may tighten the route policy for a single response.
+            response
+                .headers_mut()
+                .entry(header::CACHE_CONTROL)
+                .or_insert_with(|| policy.header_value());
+            let hit = match (&if_none_match, response.headers().get(header::ETAG)) {
+                (Some(wanted), Some(etag)) => matches(wanted, etag),
+                _ => false,
+            };
+            if hit {
+                let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+                for name in [header::ETAG, header::CACHE_CONTROL] {
+                    if let Some(value) = response.headers_mut().remove(&name) {
+                        not_modified.headers_mut().insert(name, value);
+                    }
+                }
+                return not_modified;
+            }
+            response
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_and_listed_tags_match() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        for wanted in ["\"abc\"", "W/\"abc\"", "\"x\", \"abc\"", "*"] {
+            assert!(
+                matches(&HeaderValue::from_static(wanted), &etag),
+                "{wanted}"
+            );
+        }
+        assert!(!matches(&HeaderValue::from_static("\"abcd\""), &etag));
+    }
+}
@@ -0,0 +1,247 @@
+//! On-demand CPU profiling and allocation counters for the gateway and sim.
+//!
+//! [`cpu_profile`] samples every thread for a bounded window and encodes the
+//! result as pprof protobuf, which `go tool pprof`, speedscope and inferno all
+//! turn into flamegraphs. [`CountingAlloc`] wraps the system allocator with
+//! a handful of atomic counters; a binary opts in with `#[global_allocator]`
+//! and [`alloc_stats`] reads them back. Each binary mounts the handlers behind
+//! its own admin check.
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+pub const DEFAULT_SECONDS: u64 = 10;
+pub const MAX_SECONDS: u64 = 60;
+/// Samples per second; odd so sampling doesn't lock step with 8 s ticks.
+pub const DEFAULT_FREQUENCY: i32 = 99;
+pub const MAX_FREQUENCY: i32 = 1000;
+
+/// Query of `/debug/pprof/profile`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProfileParams {
+    #[serde(default)]
+    pub seconds: Option<u64>,
+    #[serde(default)]
+    pub frequency: Option<i32>,
+}
+
+impl ProfileParams {
+    fn window(&self) -> Duration {
+        Duration::from_secs(
+            self.seconds
+                .unwrap_or(DEFAULT_SECONDS)
+                .clamp(1, MAX_SECONDS),
+        )
+    }
+
+    fn frequency(&self) -> i32 {
+        self.frequency
+            .unwrap_or(DEFAULT_FREQUENCY)
+            .clamp(1, MAX_FREQUENCY)
+    }
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// The single profiling slot; released on drop, however the profile ends.
+struct Running;
+
+impl Running {
+    fn acquire() -> Option<Self> {
+        (!RUNNING.swap(true, Ordering::AcqRel)).then_some(Running)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::Release);
+    }
+}
+
+/// Sample the process for `params.seconds` and return pprof protobuf bytes.
+/// Only one profile runs at a time; a concurrent request gets `409`. The slot
+/// is held by the sampling thread, so a dropped request frees it once the
+/// window ends rather than never.
+pub async fn cpu_profile(params: ProfileParams) -> Result<Vec<u8>, (StatusCode, String)> {
+    let running =
+        Running::acquire().ok_or((StatusCode::CONFLICT, "a profile is already running".into()))?;
+    let (window, frequency) = (params.window(), params.frequency());
+    info!("[pprof] profiling for {window:?} at {frequency} Hz");
+    // The profiler guard isn't Send, so the whole window runs on a blocking thread.
+    let result = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, String> {
+        use pprof::protos::Message;
+        let _running = running;
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(frequency)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(|e| e.to_string())?;
+        std::thread::sleep(window);
+        let profile = guard
+            .report()
+            .build()
+            .and_then(|report| report.pprof())
+            .map_err(|e| e.to_string())?;
+        let mut body = Vec::new();
+        profile.encode(&mut body).map_err(|e| e.to_string())?;
+        Ok(body)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    result.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// [`cpu_profile`] as a download (`profile.pb`).
+pub async fn profile_response(params: ProfileParams) -> Response {
+    match cpu_profile(params).await {
+        Ok(body) => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"profile.pb\"",
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static FREED_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// System allocator plus counters. Install with
+/// `#[global_allocator] static ALLOC: CountingAlloc = CountingAlloc;`.
+pub struct CountingAlloc;
+
+impl CountingAlloc {
+    fn allocated(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let total = ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+        let live = total.saturating_sub(FREED_BYTES.load(Ordering::Relaxed));
+        PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn freed(size: usize) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        FREED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::freed(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            Self::freed(layout.size());
+            Self::allocated(new_size);
+        }
+        new
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocStats {
+    /// False when the binary didn't install [`CountingAlloc`].
+    pub tracking: bool,
+    pub allocations: u64,
+    pub deallocations: u64,
+    pub allocated_bytes: u64,
+    pub freed_bytes: u64,
+    pub live_bytes: u64,
+    pub peak_live_bytes: u64,
+}
+
+pub fn alloc_stats() -> AllocStats {
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let freed_bytes = FREED_BYTES.load(Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    AllocStats {
+        tracking: allocations > 0,
+        allocations,
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        allocated_bytes,
+        freed_bytes,
+        live_bytes: allocated_bytes.saturating_sub(freed_bytes),
+        peak_live_bytes: PEAK_LIVE_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn profiles_encode_and_allocations_count() {
+        let before = alloc_stats();
+        let buf = vec![0u8; 1 << 20];
+        let during = alloc_stats();
+        assert!(during.tracking);
+        assert!(during.allocated_bytes >= before.allocated_bytes + buf.len() as u64);
+        assert!(during.peak_live_bytes >= buf.len() as u64);
+        drop(buf);
+
+        let params = ProfileParams {
+            seconds: Some(1),
+            frequency: Some(250),
+        };
+        let busy = std::thread::spawn(|| {
+            let deadline = std::time::Instant::now() + Duration::from_millis(900);
+            let mut x = 0u64;
+            while std::time::Instant::now() < deadline {
+                x = x.wrapping_mul(6364136223846793005).wrapping_add(1);
+            }
+            x
+        });
+        let (first, second) = tokio::join!(cpu_profile(params.clone()), async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cpu_profile(params.clone()).await
+        });
+        busy.join().unwrap();
+        assert!(!first.unwrap().is_empty());
+        assert_eq!(second.unwrap_err().0, StatusCode::CONFLICT);
+
+        // A request dropped mid-window frees the slot when sampling ends.
+        let dropped =
+            tokio::time::timeout(Duration::from_millis(100), cpu_profile(params.clone())).await;
+        assert!(dropped.is_err());
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert!(cpu_profile(params).await.is_ok());
+    }
+}
@@ -0,0 +1,154 @@
+//! Fault injection for chaos tests (`chaos` feature).
+//!
+//! A [`FaultPlan`] per upstream name makes guarded calls fail or stall with
+//! some probability before the upstream is contacted. Injected failures go
+//! through the same retry and breaker path as real ones, so a chaos run
+//! shows what callers and `/health` do under a flaky presence service or
+//! bucket. Plans live in one process-wide table, set from `OMEGA_CHAOS` at
+//! startup ([`load_env`]) or by the services' admin endpoints ([`set`]).
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+static PLANS: Mutex<BTreeMap<String, FaultPlan>> = Mutex::new(BTreeMap::new());
+
+/// Faults for one upstream. Rates are probabilities per attempt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultPlan {
+    #[serde(default)]
+    pub fail_rate: f64,
+    /// Stall before the attempt; counts against the call's budget.
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default)]
+    pub delay_rate: f64,
+}
+
+impl FaultPlan {
+    pub fn is_noop(&self) -> bool {
+        self.fail_rate <= 0.0 && (self.delay_ms == 0 || self.delay_rate <= 0.0)
+    }
+
+    /// `fail:0.3,delay:200@0.5` (a delay without `@` always applies).
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut plan = Self::default();
+        let rate = |raw: &str| -> Result<f64, String> {
+            raw.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| (0.0..=1.0).contains(rate))
+                .ok_or_else(|| format!("`{raw}` is not a rate in 0..=1"))
+        };
+        for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once(':') {
+                Some(("fail", value)) => plan.fail_rate = rate(value)?,
+                Some(("delay", value)) => {
+                    let (ms, odds) = value.split_once('@').unwrap_or((value, "1"));
+                    plan.delay_ms = ms
+                        .trim()
+                        .trim_end_matches("ms")
+                        .parse()
+                        .map_err(|_| format!("`{ms}` is not a delay in ms"))?;
+                    plan.delay_rate = rate(odds)?;
+                }
+                _ => return Err(format!("unknown fault `{part}`")),
+            }
+        }
+        Ok(plan)
+    }
+}
+
+/// What one attempt should suffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Fault {
+    pub delay: Option<Duration>,
+    pub fail: bool,
+}
+
+/// Replace `upstream`'s plan; a no-op plan clears it.
+pub fn set(upstream: &str, plan: FaultPlan) {
+    let mut plans = PLANS.lock().expect("chaos mutex poisoned");
+    if plan.is_noop() {
+        plans.remove(upstream);
+    } else {
+        plans.insert(upstream.to_string(), plan);
+    }
+}
+
+pub fn clear() {
+    PLANS.lock().expect("chaos mutex poisoned").clear();
+}
+
+pub fn plans() -> BTreeMap<String, FaultPlan> {
+    PLANS.lock().expect("chaos mutex poisoned").clone()
+}
+
+/// Parse `presence=fail:0.3;gcs=delay:250@0.5,fail:0.1`.
+pub fn parse_plans(raw: &str) -> Result<BTreeMap<String, FaultPlan>, String> {
+    raw.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, plan) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("`{entry}` is not upstream=faults"))?;
+            Ok((name.trim().to_string(), FaultPlan::parse(plan)?))
+        })
+        .collect()
+}
+
+/// Install the plans in `OMEGA_CHAOS`, returning how many upstreams they
+/// cover.
+pub fn load_env() -> Result<usize, String> {
+    let Ok(raw) = std::env::var("OMEGA_CHAOS") else {
+        return Ok(0);
+    };
+    let plans = parse_plans(&raw)?;
+    for (name, plan) in &plans {
+        set(name, *plan);
+    }
+    Ok(plans.len())
+}
+
+/// Roll the dice for one attempt at `upstream`.
+pub(crate) fn draw(upstream: &str) -> Fault {
+    let Some(plan) = PLANS
+        .lock()
+        .expect("chaos mutex poisoned")
+        .get(upstream)
+        .copied()
+    else {
+        return Fault::default();
+    };
+    let mut rng = rand::thread_rng();
+    let mut hit = |rate: f64| rate > 0.0 && rand::Rng::gen_bool(&mut rng, rate.min(1.0));
+    Fault {
+        delay: hit(plan.delay_rate).then(|| Duration::from_millis(plan.delay_ms)),
+        fail: hit(plan.fail_rate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_parse_from_the_env_format() {
+        let plans = parse_plans("presence=fail:0.3; gcs=delay:250ms@0.5,fail:1").unwrap();
+        assert_eq!(plans["presence"].fail_rate, 0.3);
+        assert_eq!(
+            plans["gcs"],
+            FaultPlan {
+                fail_rate: 1.0,
+                delay_ms: 250,
+                delay_rate: 0.5
+            }
+        );
+        assert_eq!(FaultPlan::parse("delay:40").unwrap().delay_rate, 1.0);
+        assert!(parse_plans("gcs=fail:2").is_err());
+        assert!(parse_plans("gcs=explode:1").is_err());
+        assert!(FaultPlan::default().is_noop());
+    }
+}
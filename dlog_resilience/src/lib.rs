@@ -0,0 +1,474 @@
+//! Retry + circuit breaker for calls to upstream services (presence, GCS).
+//!
+//! An [`Upstream`] owns one breaker and one [`RetryPolicy`]. Every call gets
+//! a total time budget; transient failures are retried with full-jitter
+//! exponential backoff until attempts or budget run out. Enough consecutive
+//! failures open the breaker, which then fails fast until a cooldown lets a
+//! single probe through (half-open). [`Upstream::snapshot`] feeds `/health`.
+//! With the `chaos` feature, [`chaos`] plans inject failures and stalls
+//! into guarded calls.
+
+#[cfg(any(test, feature = "chaos"))]
+pub mod chaos;
+
+use rand::Rng;
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total tries, including the first.
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Wall-clock cap across every attempt and backoff sleep.
+    pub budget: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            budget: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Full jitter: uniform in `[0, min(max_delay, base_delay · 2^attempt)]`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1u32 << attempt.min(16))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open the breaker.
+    pub failure_threshold: u32,
+    /// How long an open breaker fails fast before allowing a probe.
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerSnapshot {
+    pub upstream: String,
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    /// Calls refused without reaching the upstream.
+    pub rejected: u64,
+    /// Time until an open breaker admits a probe.
+    pub retry_in_ms: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    total_failures: u64,
+    rejected: u64,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// Why a guarded call failed.
+#[derive(Debug)]
+pub enum CallError<E> {
+    /// Breaker open; the upstream was not contacted.
+    Open { upstream: String },
+    /// The budget ran out before the upstream answered.
+    TimedOut { upstream: String, budget: Duration },
+    /// The upstream's own error (last attempt, or a non-transient one).
+    Failed(E),
+    /// A chaos plan failed the last attempt; the upstream was not contacted.
+    Injected { upstream: String },
+}
+
+impl<E: fmt::Display> fmt::Display for CallError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Open { upstream } => write!(f, "{upstream} circuit open"),
+            CallError::TimedOut { upstream, budget } => {
+                write!(f, "{upstream} timed out after {}ms", budget.as_millis())
+            }
+            CallError::Failed(err) => err.fmt(f),
+            CallError::Injected { upstream } => write!(f, "{upstream} failure injected"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for CallError<E> {}
+
+#[derive(Debug)]
+pub struct Upstream {
+    name: String,
+    policy: RetryPolicy,
+    config: BreakerConfig,
+    breaker: Mutex<Breaker>,
+}
+
+impl Upstream {
+    pub fn new(name: impl Into<String>, policy: RetryPolicy, config: BreakerConfig) -> Self {
+        Self {
+            name: name.into(),
+            policy,
+            config,
+            breaker: Mutex::new(Breaker {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                total_failures: 0,
+                rejected: 0,
+                opened_at: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        let breaker = self.breaker.lock().expect("breaker mutex poisoned");
+        let retry_in_ms = match (breaker.state, breaker.opened_at) {
+            (BreakerState::Open, Some(at)) => Some(
+                self.config
+                    .cooldown
+                    .saturating_sub(at.elapsed())
+                    .as_millis() as u64,
+            ),
+            _ => None,
+        };
+        BreakerSnapshot {
+            upstream: self.name.clone(),
+            state: breaker.state,
+            consecutive_failures: breaker.consecutive_failures,
+            total_failures: breaker.total_failures,
+            rejected: breaker.rejected,
+            retry_in_ms,
+        }
+    }
+
+    /// Retry every error as transient.
+    pub async fn call<T, E, F, Fut>(&self, op: F) -> Result<T, CallError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.call_if(op, |_| true).await
+    }
+
+    /// Run `op` under the breaker. Errors for which `transient` is false are
+    /// returned at once and don't count against the upstream.
+    pub async fn call_if<T, E, F, Fut>(
+        &self,
+        mut op: F,
+        transient: impl Fn(&E) -> bool,
+    ) -> Result<T, CallError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let deadline = Instant::now() + self.policy.budget;
+        let timed_out = || CallError::TimedOut {
+            upstream: self.name.clone(),
+            budget: self.policy.budget,
+        };
+        let attempts = self.policy.attempts.max(1);
+        let mut last = None;
+
+        for attempt in 0..attempts {
+            // Dropped at the end of the attempt, or with the future if the
+            // caller gives up mid-call, so a half-open probe slot can't leak.
+            let Some(_admission) = self.admit() else {
+                return Err(last.unwrap_or(CallError::Open {
+                    upstream: self.name.clone(),
+                }));
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(timed_out());
+            }
+
+            match tokio::time::timeout(remaining, self.attempt(&mut op)).await {
+                Ok(Ok(Ok(value))) => {
+                    self.on_success();
+                    return Ok(value);
+                }
+                Ok(Ok(Err(err))) if !transient(&err) => {
+                    return Err(CallError::Failed(err));
+                }
+                Ok(Ok(Err(err))) => {
+                    self.on_failure();
+                    last = Some(CallError::Failed(err));
+                }
+                Ok(Err(injected)) => {
+                    self.on_failure();
+                    last = Some(injected);
+                }
+                Err(_) => {
+                    self.on_failure();
+                    return Err(timed_out());
+                }
+            }
+
+            if attempt + 1 < attempts {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                tokio::time::sleep(self.policy.backoff(attempt).min(remaining)).await;
+            }
+        }
+        Err(last.unwrap_or_else(timed_out))
+    }
+
+    /// One try at `op`, after whatever a chaos plan injects.
+    async fn attempt<T, E, Fut>(
+        &self,
+        op: impl FnOnce() -> Fut,
+    ) -> Result<Result<T, E>, CallError<E>>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        #[cfg(any(test, feature = "chaos"))]
+        {
+            let fault = chaos::draw(&self.name);
+            if let Some(delay) = fault.delay {
+                tokio::time::sleep(delay).await;
+            }
+            if fault.fail {
+                return Err(CallError::Injected {
+                    upstream: self.name.clone(),
+                });
+            }
+        }
+        Ok(op().await)
+    }
+
+    /// Let a call through, or `None` if the breaker refuses it. The returned
+    /// admission holds the half-open probe slot (if it took one) until dropped.
+    fn admit(&self) -> Option<Admission<'_>> {
+        let mut breaker = self.breaker.lock().expect("breaker mutex poisoned");
+        let probe = match breaker.state {
+            BreakerState::Closed => false,
+            BreakerState::Open => {
+                let cooled = breaker
+                    .opened_at
+                    .is_some_and(|at| at.elapsed() >= self.config.cooldown);
+                if !cooled {
+                    breaker.rejected += 1;
+                    return None;
+                }
+                breaker.state = BreakerState::HalfOpen;
+                info!("[resilience] {} half-open, probing", self.name);
+                true
+            }
+            BreakerState::HalfOpen if breaker.probe_in_flight => {
+                breaker.rejected += 1;
+                return None;
+            }
+            BreakerState::HalfOpen => true,
+        };
+        breaker.probe_in_flight |= probe;
+        Some(Admission {
+            upstream: self,
+            probe,
+        })
+    }
+
+    fn release_probe(&self) {
+        self.breaker
+            .lock()
+            .expect("breaker mutex poisoned")
+            .probe_in_flight = false;
+    }
+
+    fn on_success(&self) {
+        let mut breaker = self.breaker.lock().expect("breaker mutex poisoned");
+        if breaker.state != BreakerState::Closed {
+            info!("[resilience] {} recovered, closing breaker", self.name);
+        }
+        breaker.state = BreakerState::Closed;
+        breaker.consecutive_failures = 0;
+        breaker.opened_at = None;
+        breaker.probe_in_flight = false;
+    }
+
+    fn on_failure(&self) {
+        let mut breaker = self.breaker.lock().expect("breaker mutex poisoned");
+        breaker.consecutive_failures += 1;
+        breaker.total_failures += 1;
+        breaker.probe_in_flight = false;
+        let trip = breaker.state == BreakerState::HalfOpen
+            || breaker.consecutive_failures >= self.config.failure_threshold;
+        if trip && breaker.state != BreakerState::Open {
+            warn!(
+                "[resilience] {} opened after {} consecutive failures",
+                self.name, breaker.consecutive_failures
+            );
+            breaker.state = BreakerState::Open;
+            breaker.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// One admitted attempt; frees the probe slot it holds when dropped.
+struct Admission<'a> {
+    upstream: &'a Upstream,
+    probe: bool,
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.upstream.release_probe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn quick(attempts: u32, threshold: u32) -> Upstream {
+        Upstream::new(
+            "test",
+            RetryPolicy {
+                attempts,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(2),
+                budget: Duration::from_secs(1),
+            },
+            BreakerConfig {
+                failure_threshold: threshold,
+                cooldown: Duration::from_millis(20),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_until_success() {
+        let upstream = quick(3, 10);
+        let calls = AtomicU32::new(0);
+        let result = upstream
+            .call(|| async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err("flaky"),
+                    _ => Ok(7),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let fatal = upstream
+            .call_if(|| async { Err::<(), _>("bad request") }, |_| false)
+            .await;
+        assert!(matches!(fatal, Err(CallError::Failed("bad request"))));
+        assert_eq!(upstream.snapshot().state, BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn breaker_opens_fails_fast_and_recovers_after_cooldown() {
+        let upstream = quick(1, 2);
+        for _ in 0..2 {
+            let _ = upstream.call(|| async { Err::<(), _>("down") }).await;
+        }
+        assert_eq!(upstream.snapshot().state, BreakerState::Open);
+
+        let calls = AtomicU32::new(0);
+        let rejected = upstream
+            .call(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, &str>(())
+            })
+            .await;
+        assert!(matches!(rejected, Err(CallError::Open { .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(upstream.snapshot().rejected, 1);
+
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        upstream.call(|| async { Ok::<_, &str>(()) }).await.unwrap();
+        let snapshot = upstream.snapshot();
+        assert_eq!(snapshot.state, BreakerState::Closed);
+        assert_eq!(snapshot.total_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn chaos_plans_fail_and_stall_attempts() {
+        let upstream = Upstream::new(
+            "chaos-test",
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(2),
+                budget: Duration::from_millis(200),
+            },
+            BreakerConfig::default(),
+        );
+        let calls = AtomicU32::new(0);
+        let op = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, &str>(())
+        };
+        chaos::set("chaos-test", chaos::FaultPlan::parse("fail:1").unwrap());
+        let failed = upstream.call(op).await;
+        assert!(matches!(failed, Err(CallError::Injected { .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(upstream.snapshot().consecutive_failures, 3);
+
+        chaos::set("chaos-test", chaos::FaultPlan::parse("delay:500").unwrap());
+        let stalled = upstream.call(op).await;
+        assert!(matches!(stalled, Err(CallError::TimedOut { .. })));
+
+        chaos::set("chaos-test", chaos::FaultPlan::default());
+        assert!(!chaos::plans().contains_key("chaos-test"));
+        upstream.call(op).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dropping_a_probe_frees_the_half_open_slot() {
+        let upstream = quick(1, 1);
+        let _ = upstream.call(|| async { Err::<(), _>("down") }).await;
+        tokio::time::sleep(Duration::from_millis(25)).await;
+
+        // The caller gives up while the probe is still waiting on the upstream.
+        let abandoned = tokio::time::timeout(
+            Duration::from_millis(5),
+            upstream.call(std::future::pending::<Result<(), &str>>),
+        )
+        .await;
+        assert!(abandoned.is_err());
+        assert_eq!(upstream.snapshot().state, BreakerState::HalfOpen);
+
+        upstream.call(|| async { Ok::<_, &str>(()) }).await.unwrap();
+        assert_eq!(upstream.snapshot().state, BreakerState::Closed);
+    }
+}
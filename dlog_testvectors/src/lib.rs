@@ -0,0 +1,340 @@
+//! Canonical test vectors for implementations outside this workspace.
+//!
+//! The Paper plugin and the web app hash, root, print and key the same
+//! things the Rust crates do, and have to agree byte for byte. [`generate`]
+//! runs fixed inputs through the real `corelib` functions:
+//!
+//! - `shaless`: sha-less digests of raw bytes (given as hex)
+//! - `canonical_json`: values, their canonical JSON and its digest
+//! - `master_roots`: `{height, balances}` snapshots and their master roots
+//! - `chained_roots`: block links over a state root
+//! - `octal`: `octal_int`, `octal_ratio` and `octal_f64` renderings (big
+//!   integers as decimal strings)
+//! - `labels`: raw label names and their canonical form, `null` if refused
+//! - `label_keys`: the `;phone;label;` key, file name and redaction of a label
+//!
+//! The same vectors are committed as `vectors.json` and embedded
+//! ([`EMBEDDED`]), so a change to any of those functions fails this crate's
+//! tests until the file is regenerated on purpose
+//! (`cargo run -p dlog_testvectors -- dump > dlog_testvectors/vectors.json`)
+//! and the other implementations are updated to match.
+
+use corelib::consensus::BlockHeader;
+use corelib::label_file::{self, LabelFile};
+use corelib::octal::{octal_f64, octal_int, octal_ratio};
+use corelib::privacy::redact_phones;
+use corelib::{canonical, master_root_for, shaless_digest, shaless_json_digest, UniverseSnapshot};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// `vectors.json` as committed.
+pub const EMBEDDED: &str = include_str!("../vectors.json");
+
+/// Bumped when a vector changes what it means rather than its value.
+pub const FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vectors {
+    pub format: u32,
+    pub shaless: Vec<DigestVector>,
+    pub canonical_json: Vec<JsonVector>,
+    pub master_roots: Vec<RootVector>,
+    pub chained_roots: Vec<ChainVector>,
+    pub octal: Vec<OctalVector>,
+    pub labels: Vec<LabelVector>,
+    pub label_keys: Vec<LabelKeyVector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestVector {
+    pub name: String,
+    pub input_hex: String,
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonVector {
+    pub name: String,
+    pub value: Value,
+    /// The bytes that are hashed, as UTF-8.
+    pub canonical: String,
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootVector {
+    pub name: String,
+    pub height: u64,
+    pub balances: Value,
+    pub root: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainVector {
+    pub prev_root: String,
+    pub height: u64,
+    pub producer: String,
+    pub sealed_ms: i64,
+    pub state_root: String,
+    pub root: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "fn", rename_all = "snake_case")]
+pub enum OctalInput {
+    Int { n: String },
+    Ratio { num: String, den: String },
+    Float { x: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OctalVector {
+    #[serde(flatten)]
+    pub input: OctalInput,
+    pub octal: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelVector {
+    pub raw: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelKeyVector {
+    pub phone: String,
+    pub label: String,
+    pub key: String,
+    pub file_name: String,
+    pub redacted: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// `len` bytes counting up modulo 251, so no block of input repeats.
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn shaless() -> Vec<DigestVector> {
+    let inputs: [(&str, Vec<u8>); 6] = [
+        ("empty", Vec::new()),
+        ("abc", b"abc".to_vec()),
+        ("omega", "Ω".as_bytes().to_vec()),
+        ("label_key", b";9132077554;comet;".to_vec()),
+        ("pattern_1024", pattern(1024)),
+        ("pattern_3000", pattern(3000)),
+    ];
+    inputs
+        .into_iter()
+        .map(|(name, input)| DigestVector {
+            name: name.to_string(),
+            input_hex: hex(&input),
+            digest: shaless_digest(&input),
+        })
+        .collect()
+}
+
+fn canonical_json() -> Vec<JsonVector> {
+    let values = [
+        (
+            "sorted_keys",
+            json!({ "b": 1, "a": { "d": [3, 2], "c": null } }),
+        ),
+        ("floats", json!([1.5, 0.1, 2.0, -0.0, 100.0])),
+        (
+            "unicode",
+            json!({ "Ω": ";9132077554;comet;", "planet": "moon" }),
+        ),
+        (
+            "integers",
+            json!({ "max_u64": u64::MAX, "negative": -8, "zero": 0 }),
+        ),
+        ("nesting", json!([[], {}, [true, false, null], "a\"b\\c"])),
+    ];
+    values
+        .into_iter()
+        .map(|(name, value)| {
+            let bytes = canonical::to_vec(&value).expect("JSON values encode");
+            JsonVector {
+                name: name.to_string(),
+                canonical: String::from_utf8(bytes).expect("canonical JSON is UTF-8"),
+                digest: shaless_json_digest(&value),
+                value,
+            }
+        })
+        .collect()
+}
+
+fn master_roots() -> Vec<RootVector> {
+    let empty = UniverseSnapshot::empty();
+    let mut roots = vec![RootVector {
+        name: "empty_universe".into(),
+        height: empty.height,
+        balances: json!({}),
+        root: empty.master_root_infinity,
+    }];
+    let snapshots = [
+        (
+            "planet_ledger",
+            7,
+            json!({
+                "earth": { ";9132077554;comet;": 1_000_000, ";9132077554;fun;": 50_000 },
+                "moon": { ";5550001111;main;": 500 },
+            }),
+        ),
+        (
+            "float_balances",
+            3,
+            json!({ ";1;a;": 1.5, ";2;b;": 0.1, ";3;c;": 2.0 }),
+        ),
+        (
+            "label_balances",
+            42,
+            json!({ ";5550001111;fun;": 0.25, ";9132077554;comet;": 1000.5 }),
+        ),
+    ];
+    roots.extend(
+        snapshots
+            .into_iter()
+            .map(|(name, height, balances)| RootVector {
+                name: name.to_string(),
+                height,
+                root: master_root_for(height, &balances),
+                balances,
+            }),
+    );
+    roots
+}
+
+fn chained_roots(state_root: &str) -> Vec<ChainVector> {
+    let mut parent: Option<BlockHeader> = None;
+    let mut chain = Vec::new();
+    for (producer, sealed_ms) in [("node-a", 1_700_000_000_000), ("node-b", 1_700_000_008_000)] {
+        let header = BlockHeader::next(parent.as_ref(), producer, sealed_ms, state_root);
+        chain.push(ChainVector {
+            prev_root: header.prev_root.clone(),
+            height: header.height,
+            producer: header.producer.clone(),
+            sealed_ms,
+            state_root: state_root.to_string(),
+            root: header.root.clone(),
+        });
+        parent = Some(header);
+    }
+    chain
+}
+
+fn octal() -> Vec<OctalVector> {
+    let ints = [0, 7, 8, 3_900_000, u128::from(u64::MAX), u128::MAX];
+    let ratios = [(0, 5), (7, 1), (1, 8), (3, 2), (1, 3), (1_000_000, 999_999)];
+    let floats = [
+        0.0,
+        -0.0,
+        0.5,
+        0.1,
+        -2.25,
+        1.618_033_988_749_895,
+        12_345.678_9,
+    ];
+    let ints = ints.into_iter().map(|n| OctalVector {
+        input: OctalInput::Int { n: n.to_string() },
+        octal: octal_int(n),
+    });
+    let ratios = ratios.into_iter().map(|(num, den)| OctalVector {
+        input: OctalInput::Ratio {
+            num: num.to_string(),
+            den: den.to_string(),
+        },
+        octal: octal_ratio(num, den),
+    });
+    let floats = floats.into_iter().map(|x| OctalVector {
+        input: OctalInput::Float { x },
+        octal: octal_f64(x),
+    });
+    ints.chain(ratios).chain(floats).collect()
+}
+
+fn labels() -> Vec<LabelVector> {
+    let long = "a".repeat(label_file::LABEL_MAX);
+    let too_long = "a".repeat(label_file::LABEL_MAX + 1);
+    let raws = [
+        "comet",
+        " ;Comet; ",
+        "gift_123",
+        "savings-2",
+        long.as_str(),
+        "",
+        ";;",
+        "has space",
+        "ünicode",
+        "dot.ted",
+        too_long.as_str(),
+    ];
+    raws.into_iter()
+        .map(|raw| LabelVector {
+            raw: raw.to_string(),
+            name: label_file::canonical_label(raw).ok(),
+        })
+        .collect()
+}
+
+fn label_keys() -> Vec<LabelKeyVector> {
+    [("9132077554", "comet"), ("5550001111", "fun")]
+        .into_iter()
+        .map(|(phone, label)| {
+            let file = LabelFile::new(phone, label);
+            let key = file.key();
+            LabelKeyVector {
+                phone: phone.to_string(),
+                label: label.to_string(),
+                redacted: redact_phones(&key),
+                file_name: file.filename(),
+                key,
+            }
+        })
+        .collect()
+}
+
+/// Every vector, computed by this build's `corelib`.
+pub fn generate() -> Vectors {
+    let master_roots = master_roots();
+    let chained_roots = chained_roots(&master_roots[1].root);
+    Vectors {
+        format: FORMAT,
+        shaless: shaless(),
+        canonical_json: canonical_json(),
+        master_roots,
+        chained_roots,
+        octal: octal(),
+        labels: labels(),
+        label_keys: label_keys(),
+    }
+}
+
+/// The committed vectors.
+pub fn embedded() -> Result<Vectors, serde_json::Error> {
+    serde_json::from_str(EMBEDDED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_vectors_match_this_build() {
+        let embedded = embedded().unwrap();
+        let generated = generate();
+        // Section by section, so a failure names what drifted.
+        assert_eq!(embedded.shaless, generated.shaless);
+        assert_eq!(embedded.canonical_json, generated.canonical_json);
+        assert_eq!(embedded.master_roots, generated.master_roots);
+        assert_eq!(embedded.chained_roots, generated.chained_roots);
+        assert_eq!(embedded.octal, generated.octal);
+        assert_eq!(embedded.labels, generated.labels);
+        assert_eq!(embedded.label_keys, generated.label_keys);
+        assert_eq!(embedded, generated);
+    }
+}
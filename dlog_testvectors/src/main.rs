@@ -0,0 +1,78 @@
+//! `dlog_testvectors`: dump the canonical vectors for other languages.
+//!
+//! ```text
+//! dlog_testvectors dump [section]
+//! dlog_testvectors check
+//! ```
+//!
+//! `dump` prints every vector this build computes as JSON (the shape of
+//! `vectors.json`), or only one section of it (`shaless`, `octal`, …).
+//! `check` compares them with the committed vectors and names the sections
+//! that differ. Exits 1 when they do, 2 on bad arguments.
+
+use dlog_testvectors::{embedded, generate};
+use serde_json::Value;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: dlog_testvectors dump [section] | check";
+
+fn dump(section: Option<&str>) -> Result<(), String> {
+    let vectors = serde_json::to_value(generate()).map_err(|err| err.to_string())?;
+    let out = match section {
+        Some(name) => vectors
+            .get(name)
+            .ok_or_else(|| format!("no section `{name}`"))?,
+        None => &vectors,
+    };
+    let json = serde_json::to_string_pretty(out).map_err(|err| err.to_string())?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Sections whose committed vectors differ from this build's.
+fn drifted() -> Result<Vec<String>, String> {
+    let committed = embedded().map_err(|err| format!("vectors.json: {err}"))?;
+    let as_value = |vectors| serde_json::to_value(vectors).map_err(|err| err.to_string());
+    let (Value::Object(generated), Value::Object(committed)) =
+        (as_value(generate())?, as_value(committed)?)
+    else {
+        return Err("vectors are not a JSON object".into());
+    };
+    Ok(generated
+        .into_iter()
+        .filter(|(name, section)| committed.get(name) != Some(section))
+        .map(|(name, _)| name)
+        .collect())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["dump"] => dump(None),
+        ["dump", section] => dump(Some(section)),
+        ["check"] => match drifted() {
+            Ok(drifted) if drifted.is_empty() => {
+                println!("ok");
+                Ok(())
+            }
+            Ok(drifted) => {
+                for name in drifted {
+                    println!("drifted: {name}");
+                }
+                return ExitCode::from(1);
+            }
+            Err(why) => Err(why),
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(why) => {
+            eprintln!("{why}");
+            ExitCode::from(2)
+        }
+    }
+}
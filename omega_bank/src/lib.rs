@@ -0,0 +1,659 @@
+//! The Ω bank: per-planet label ledgers, φ holder interest and transfers.
+//!
+//! A [`Bank`] is plain data behind `&mut self`; callers bring their own lock
+//! and persistence (the gateway wraps one in a mutex and refolds label
+//! files from what moved). Balances are integer base units and interest is
+//! worked out in integer ppm, so every node that applies the same calls at
+//! the same heights holds the same ledgers.
+//!
+//! A zone's rates can change at a scheduled height ([`MonetaryChange`]).
+//! Each period pays at the rates in effect at its last block, so a change
+//! never reaches back into interest already paid.
+
+use dlog_error::BankError;
+use serde::Serialize;
+use spec::{CompoundingMode, MonetaryChange, MonetarySpec, PlanetId};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Labels → balances, one namespace per planet.
+pub type Ledgers = HashMap<PlanetId, HashMap<String, u128>>;
+
+/// Client frame length; interest compounds once per frame.
+const FRAME_MS: f64 = 8.0;
+/// Highest APY a scheduled change may set (1000%).
+const APY_MAX: f64 = 10.0;
+const REASON_MAX: usize = 280;
+
+/// Client frames one block of `spec` spans.
+pub fn frames_per_block(spec: &MonetarySpec) -> u32 {
+    (spec.target_block_seconds * 1000.0 / FRAME_MS).round() as u32
+}
+
+/// One block's worth of the φ tick, compounded in integer ppm so every
+/// node derives the same factor. Earth's 61.8% holder APY ticks 1.00002
+/// per 8 ms; other zones scale the tick by their own APY.
+pub fn phi_block_factor_ppm(spec: &MonetarySpec) -> u64 {
+    let ticks = frames_per_block(spec);
+    let earth_apy = PlanetId::Earth.zone().monetary.holder_interest_apy;
+    let tick_ppm = (20.0 * spec.holder_interest_apy / earth_apy).round() as u64;
+    (0..ticks).fold(1_000_000, |factor, _| {
+        factor * (1_000_000 + tick_ppm) / 1_000_000
+    })
+}
+
+/// e^(rate_ppm / 10⁶) in ppm, from its series in 10¹² fixed point so every
+/// node gets the same factor without relying on the platform's `exp`.
+pub fn exp_ppm(rate_ppm: u64) -> u64 {
+    const ONE: u128 = 1_000_000_000_000;
+    let x = rate_ppm as u128 * 1_000_000;
+    let (mut sum, mut term) = (ONE, ONE);
+    for k in 1..=32 {
+        term = term * x / ONE / k;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    ((sum + 500_000) / 1_000_000) as u64
+}
+
+/// Where one zone's supply came from since the ledgers were loaded; the
+/// ledger should always sum to `genesis + minted - burned`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SupplyAccount {
+    /// Supply when the ledgers were created or restored.
+    pub genesis: u128,
+    /// Credits and interest, plus exchanges into the zone.
+    pub minted: u128,
+    /// Debits, plus exchanges out of the zone (fee included).
+    pub burned: u128,
+}
+
+impl SupplyAccount {
+    pub fn expected(&self) -> u128 {
+        (self.genesis + self.minted).saturating_sub(self.burned)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bank {
+    ledgers: Ledgers,
+    /// Height interest has been paid up to; interest follows blocks, not the
+    /// wall clock, so a ledger is a function of the chain it sealed on.
+    accrued_height: u64,
+    /// Each zone's interest per block, from its MonetarySpec.
+    per_block_factor_ppm: BTreeMap<PlanetId, u64>,
+    compounding: CompoundingMode,
+    /// Scheduled rate changes, by effective height.
+    schedule: Vec<MonetaryChange>,
+    /// Labels that hold funds without earning on them (escrow locks).
+    exempt: BTreeSet<String>,
+    supply: BTreeMap<PlanetId, SupplyAccount>,
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new(Ledgers::new())
+    }
+}
+
+impl Bank {
+    /// A bank over `ledgers` with nothing accrued yet, compounding per block.
+    pub fn new(ledgers: Ledgers) -> Self {
+        let mut bank = Self {
+            ledgers: Ledgers::new(),
+            accrued_height: 0,
+            per_block_factor_ppm: PlanetId::ALL
+                .into_iter()
+                .map(|planet| (planet, phi_block_factor_ppm(&planet.zone().monetary)))
+                .collect(),
+            compounding: CompoundingMode::default(),
+            schedule: Vec::new(),
+            exempt: BTreeSet::new(),
+            supply: BTreeMap::new(),
+        };
+        bank.restore(ledgers, 0);
+        bank
+    }
+
+    /// Never pay interest on `label`; a refund then returns exactly what
+    /// was put in.
+    pub fn with_exempt(mut self, label: &str) -> Self {
+        self.exempt.insert(label.to_string());
+        self
+    }
+
+    pub fn compounding(&self) -> CompoundingMode {
+        self.compounding
+    }
+
+    /// Every node of a chain must run the same mode.
+    pub fn set_compounding(&mut self, mode: CompoundingMode) {
+        self.compounding = mode;
+    }
+
+    /// `planet`'s growth over the next block to be paid.
+    pub fn per_block_factor_ppm(&self, planet: PlanetId) -> u64 {
+        self.per_block_factor_at(planet, self.next_height())
+    }
+
+    /// What the next compounding period pays on `planet` under the bank's
+    /// mode: the per-block rate compounded every block, eight blocks of it
+    /// at once, or e^rate per block, worked out in integers like the
+    /// per-block factor.
+    pub fn period_factor_ppm(&self, planet: PlanetId) -> u64 {
+        self.period_factor_at(planet, self.next_height())
+    }
+
+    fn next_height(&self) -> u64 {
+        self.accrued_height.saturating_add(1)
+    }
+
+    fn per_block_factor_at(&self, planet: PlanetId, height: u64) -> u64 {
+        match self.change_at(planet, height) {
+            Some(change) => phi_block_factor_ppm(&change.monetary),
+            None => self.per_block_factor_ppm[&planet],
+        }
+    }
+
+    fn period_factor_at(&self, planet: PlanetId, height: u64) -> u64 {
+        let per_block = self.per_block_factor_at(planet, height);
+        let rate_ppm = per_block - 1_000_000;
+        match self.compounding {
+            CompoundingMode::PerBlock => per_block,
+            CompoundingMode::PerEightBlocks => {
+                1_000_000 + rate_ppm * self.compounding.period_blocks()
+            }
+            CompoundingMode::Continuous => exp_ppm(rate_ppm),
+        }
+    }
+
+    /// The latest change to `planet` in effect at `height`.
+    fn change_at(&self, planet: PlanetId, height: u64) -> Option<&MonetaryChange> {
+        self.schedule
+            .iter()
+            .rev()
+            .find(|change| change.planet == planet && change.effective_height <= height)
+    }
+
+    /// `planet`'s monetary parameters at `height`: the latest change in
+    /// effect by then, else the zone's own.
+    pub fn monetary_at(&self, planet: PlanetId, height: u64) -> MonetarySpec {
+        let monetary = match self.change_at(planet, height) {
+            Some(change) => change.monetary.clone(),
+            None => planet.zone().monetary,
+        };
+        MonetarySpec {
+            compounding: self.compounding,
+            ..monetary
+        }
+    }
+
+    /// Every scheduled change, paid or not, by effective height.
+    pub fn schedule(&self) -> &[MonetaryChange] {
+        &self.schedule
+    }
+
+    /// Schedule `change`. It has to take effect past the height interest is
+    /// paid up to, set rates between 0 and 1000%, keep the block interval
+    /// (blocks are shared by every zone), and carry a reason and approvers.
+    pub fn schedule_change(&mut self, change: MonetaryChange) -> Result<(), BankError> {
+        let invalid = |why: String| Err(BankError::InvalidSchedule(why));
+        if change.effective_height <= self.accrued_height {
+            return invalid(format!(
+                "interest is paid up to height {}",
+                self.accrued_height
+            ));
+        }
+        let monetary = &change.monetary;
+        for (name, apy) in [
+            ("holder_interest_apy", monetary.holder_interest_apy),
+            ("miner_inflation_apy", monetary.miner_inflation_apy),
+        ] {
+            if !(0.0..=APY_MAX).contains(&apy) {
+                return invalid(format!("{name} must be between 0 and {APY_MAX}"));
+            }
+        }
+        let current = self.monetary_at(change.planet, change.effective_height);
+        if monetary.target_block_seconds != current.target_block_seconds {
+            return invalid("target_block_seconds is shared by every zone".into());
+        }
+        let reason = change.reason.trim();
+        if reason.is_empty() || reason.chars().count() > REASON_MAX {
+            return invalid(format!("reason must be 1-{REASON_MAX} characters"));
+        }
+        if change.approvers.is_empty() {
+            return invalid("at least one approver is required".into());
+        }
+        if self.schedule.iter().any(|scheduled| {
+            scheduled.planet == change.planet
+                && scheduled.effective_height == change.effective_height
+        }) {
+            return invalid(format!(
+                "{} already changes at height {}",
+                change.planet, change.effective_height
+            ));
+        }
+        let at = self
+            .schedule
+            .partition_point(|scheduled| scheduled.effective_height <= change.effective_height);
+        self.schedule.insert(at, change);
+        Ok(())
+    }
+
+    /// Take a stored (or the leader's) schedule as it is.
+    pub fn restore_schedule(&mut self, mut changes: Vec<MonetaryChange>) {
+        changes.sort_by_key(|change| change.effective_height);
+        self.schedule = changes;
+    }
+
+    pub fn accrued_height(&self) -> u64 {
+        self.accrued_height
+    }
+
+    /// Take `ledgers` as they stood at `height`, interest included. Their
+    /// supply becomes the new genesis.
+    pub fn restore(&mut self, ledgers: Ledgers, height: u64) {
+        self.ledgers = ledgers;
+        self.accrued_height = height;
+        self.supply = PlanetId::ALL
+            .into_iter()
+            .map(|planet| {
+                let genesis = self.supply_of(planet);
+                (
+                    planet,
+                    SupplyAccount {
+                        genesis,
+                        ..SupplyAccount::default()
+                    },
+                )
+            })
+            .collect();
+    }
+
+    /// What `planet`'s ledger holds in total.
+    pub fn supply_of(&self, planet: PlanetId) -> u128 {
+        self.ledgers
+            .get(&planet)
+            .map(|ledger| ledger.values().sum())
+            .unwrap_or_default()
+    }
+
+    /// Supply accounting per zone since the ledgers were loaded.
+    pub fn supply_accounts(&self) -> &BTreeMap<PlanetId, SupplyAccount> {
+        &self.supply
+    }
+
+    /// Stop interest so balances stay exact (tests).
+    pub fn freeze_interest(&mut self) {
+        self.accrued_height = u64::MAX;
+    }
+
+    pub fn ledgers(&self) -> &Ledgers {
+        &self.ledgers
+    }
+
+    /// Fold every label `owned` matches into `tombstone`, planet by planet.
+    /// Balances move rather than vanish, so supply is unchanged. Returns the
+    /// emptied labels with what each held, sorted.
+    pub fn tombstone_labels(
+        &mut self,
+        owned: impl Fn(&str) -> bool,
+        tombstone: &str,
+    ) -> Vec<(PlanetId, String, u128)> {
+        let mut emptied = Vec::new();
+        for (planet, ledger) in &mut self.ledgers {
+            let labels: Vec<String> = ledger
+                .keys()
+                .filter(|label| label.as_str() != tombstone && owned(label))
+                .cloned()
+                .collect();
+            let mut moved = 0u128;
+            for label in labels {
+                let balance = ledger.remove(&label).unwrap_or_default();
+                moved += balance;
+                emptied.push((*planet, label, balance));
+            }
+            if moved > 0 {
+                *ledger.entry(tombstone.to_string()).or_default() += moved;
+            }
+        }
+        emptied.sort();
+        emptied
+    }
+
+    /// Put `amount` into `label` without booking it against supply, so
+    /// invariant checks have a broken ledger to find.
+    #[cfg(feature = "chaos")]
+    pub fn inject_unbooked(&mut self, planet: PlanetId, label: &str, amount: u128) {
+        self.put(planet, label, amount);
+    }
+
+    pub fn snapshot(&self) -> Ledgers {
+        self.ledgers.clone()
+    }
+
+    pub fn balance_of(&self, planet: PlanetId, label: &str) -> u128 {
+        self.ledgers
+            .get(&planet)
+            .and_then(|ledger| ledger.get(label))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Mint `amount` into `label`.
+    pub fn credit(&mut self, planet: PlanetId, label: &str, amount: u128) {
+        self.put(planet, label, amount);
+        self.supply.entry(planet).or_default().minted += amount;
+    }
+
+    /// Take `amount` out of `label` and out of supply.
+    pub fn debit(&mut self, planet: PlanetId, label: &str, amount: u128) -> Result<(), BankError> {
+        self.take(planet, label, amount)?;
+        self.supply.entry(planet).or_default().burned += amount;
+        Ok(())
+    }
+
+    /// Move `amount` from `from` to `to` on `planet`.
+    pub fn transfer(
+        &mut self,
+        planet: PlanetId,
+        from: &str,
+        to: &str,
+        amount: u128,
+    ) -> Result<(), BankError> {
+        self.exchange((planet, planet), from, to, amount, amount)
+    }
+
+    /// Take `sent` from `from` on the first planet and pay `received` to `to`
+    /// on the second; the caller quotes the rate and fee between them.
+    pub fn exchange(
+        &mut self,
+        (from_planet, to_planet): (PlanetId, PlanetId),
+        from: &str,
+        to: &str,
+        sent: u128,
+        received: u128,
+    ) -> Result<(), BankError> {
+        self.take(from_planet, from, sent)?;
+        self.put(to_planet, to, received);
+        if from_planet != to_planet {
+            self.supply.entry(from_planet).or_default().burned += sent;
+            self.supply.entry(to_planet).or_default().minted += received;
+        }
+        Ok(())
+    }
+
+    /// Pay every `(to, amount)` from `from` on `planet`, or none of them when
+    /// the total doesn't fit `from`'s balance. Returns the total.
+    pub fn transfer_many(
+        &mut self,
+        planet: PlanetId,
+        from: &str,
+        payouts: &[(&str, u128)],
+    ) -> Result<u128, BankError> {
+        let total = payouts.iter().map(|(_, amount)| amount).sum();
+        self.take(planet, from, total)?;
+        for (to, amount) in payouts {
+            self.put(planet, to, *amount);
+        }
+        Ok(total)
+    }
+
+    /// Pay interest for every period ending between the last accrued height
+    /// and `height`, each at the rates in effect at its last block.
+    /// Re-applied heights (fork switches) don't pay twice. Returns the
+    /// labels whose balance moved.
+    pub fn accrue(&mut self, height: u64) -> Vec<String> {
+        if height <= self.accrued_height {
+            return Vec::new();
+        }
+        // Stretches of unchanged rates, each ending just before a change.
+        let mut ends: Vec<u64> = self
+            .schedule
+            .iter()
+            .map(|change| change.effective_height.saturating_sub(1))
+            .filter(|end| (self.accrued_height + 1..height).contains(end))
+            .collect();
+        ends.push(height);
+        ends.dedup();
+
+        let period = self.compounding.period_blocks();
+        let mut earned = BTreeSet::new();
+        let mut from = self.accrued_height;
+        for to in ends {
+            let periods = to / period - from / period;
+            if periods > 0 {
+                let factors: BTreeMap<_, _> = PlanetId::ALL
+                    .into_iter()
+                    .map(|planet| (planet, self.period_factor_at(planet, to) as u128))
+                    .collect();
+                self.pay(periods, &factors, &mut earned);
+            }
+            from = to;
+        }
+        self.accrued_height = height;
+        earned.into_iter().collect()
+    }
+
+    /// Grow every balance by its zone's factor, `periods` times over.
+    fn pay(
+        &mut self,
+        periods: u64,
+        factors: &BTreeMap<PlanetId, u128>,
+        earned: &mut BTreeSet<String>,
+    ) {
+        let exempt = &self.exempt;
+        for (planet, ledger) in &mut self.ledgers {
+            let factor = factors[planet];
+            let mut interest = 0;
+            for (label, balance) in ledger.iter_mut() {
+                if exempt.contains(label) {
+                    continue;
+                }
+                let before = *balance;
+                for _ in 0..periods {
+                    *balance = (*balance * factor) / 1_000_000;
+                }
+                if *balance != before {
+                    interest += *balance - before;
+                    earned.insert(label.clone());
+                }
+            }
+            self.supply.entry(*planet).or_default().minted += interest;
+        }
+    }
+
+    fn put(&mut self, planet: PlanetId, label: &str, amount: u128) {
+        *self
+            .ledgers
+            .entry(planet)
+            .or_default()
+            .entry(label.to_string())
+            .or_default() += amount;
+    }
+
+    /// Take `amount` out of `label`, if its balance covers it.
+    fn take(&mut self, planet: PlanetId, label: &str, amount: u128) -> Result<(), BankError> {
+        if amount == 0 {
+            return Err(BankError::ZeroAmount);
+        }
+        let balance = self.balance_of(planet, label);
+        if balance < amount {
+            return Err(BankError::InsufficientFunds {
+                label: label.to_string(),
+                balance,
+                amount,
+            });
+        }
+        self.ledgers
+            .entry(planet)
+            .or_default()
+            .insert(label.to_string(), balance - amount);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMET: &str = ";9132077554;comet;";
+    const FUN: &str = ";9132077554;fun;";
+    const ESCROW: &str = ";∞;escrow;";
+
+    fn bank() -> Bank {
+        let mut bank = Bank::default().with_exempt(ESCROW);
+        bank.credit(PlanetId::Earth, COMET, 1_000_000);
+        bank
+    }
+
+    #[test]
+    fn transfers_move_funds_or_nothing() {
+        let mut bank = bank();
+        bank.transfer(PlanetId::Earth, COMET, FUN, 400_000).unwrap();
+        assert_eq!(bank.balance_of(PlanetId::Earth, FUN), 400_000);
+        assert!(matches!(
+            bank.transfer(PlanetId::Earth, FUN, COMET, 400_001),
+            Err(BankError::InsufficientFunds { .. })
+        ));
+        assert!(matches!(
+            bank.transfer(PlanetId::Earth, FUN, COMET, 0),
+            Err(BankError::ZeroAmount)
+        ));
+
+        let payouts = [(FUN, 300_000), (ESCROW, 400_000)];
+        assert!(bank
+            .transfer_many(PlanetId::Earth, COMET, &payouts)
+            .is_err());
+        assert_eq!(bank.balance_of(PlanetId::Earth, COMET), 600_000);
+        assert_eq!(
+            bank.transfer_many(PlanetId::Earth, COMET, &payouts[..1]),
+            Ok(300_000)
+        );
+
+        bank.exchange((PlanetId::Earth, PlanetId::Moon), FUN, FUN, 100, 90)
+            .unwrap();
+        assert_eq!(bank.balance_of(PlanetId::Moon, FUN), 90);
+        assert_eq!(bank.supply_of(PlanetId::Earth), 1_000_000 - 100);
+        for (planet, account) in bank.supply_accounts() {
+            assert_eq!(account.expected(), bank.supply_of(*planet));
+        }
+        assert_eq!(bank.supply_accounts()[&PlanetId::Earth].minted, 1_000_000);
+        assert_eq!(bank.supply_accounts()[&PlanetId::Earth].burned, 100);
+    }
+
+    #[test]
+    fn tombstoning_moves_owned_balances_without_touching_supply() {
+        let mut bank = bank();
+        bank.transfer(PlanetId::Earth, COMET, FUN, 250_000).unwrap();
+        bank.credit(PlanetId::Mars, FUN, 40);
+        bank.credit(PlanetId::Mars, ESCROW, 7);
+        let tombstone = ";∞;forgotten;";
+        let emptied = bank.tombstone_labels(|label| label.starts_with(";9132077554;"), tombstone);
+        assert_eq!(
+            emptied,
+            vec![
+                (PlanetId::Earth, COMET.to_string(), 750_000),
+                (PlanetId::Earth, FUN.to_string(), 250_000),
+                (PlanetId::Mars, FUN.to_string(), 40),
+            ]
+        );
+        assert_eq!(bank.balance_of(PlanetId::Earth, tombstone), 1_000_000);
+        assert_eq!(bank.balance_of(PlanetId::Mars, tombstone), 40);
+        assert_eq!(bank.balance_of(PlanetId::Mars, ESCROW), 7);
+        assert!(!bank.ledgers()[&PlanetId::Earth].contains_key(COMET));
+        for (planet, account) in bank.supply_accounts() {
+            assert_eq!(account.expected(), bank.supply_of(*planet));
+        }
+    }
+
+    #[test]
+    fn interest_follows_heights_and_skips_exempt_labels() {
+        let mut bank = bank();
+        bank.credit(PlanetId::Earth, ESCROW, 500);
+        assert_eq!(bank.per_block_factor_ppm(PlanetId::Earth), 1_020_000);
+        assert_eq!(bank.accrue(1), vec![COMET.to_string()]);
+        assert_eq!(bank.balance_of(PlanetId::Earth, COMET), 1_020_000);
+        assert_eq!(bank.balance_of(PlanetId::Earth, ESCROW), 500);
+        // A height paid once isn't paid again.
+        assert!(bank.accrue(1).is_empty());
+
+        bank.set_compounding(CompoundingMode::PerEightBlocks);
+        assert_eq!(bank.period_factor_ppm(PlanetId::Earth), 1_160_000);
+        assert!(bank.accrue(7).is_empty());
+        bank.accrue(8);
+        assert_eq!(bank.balance_of(PlanetId::Earth, COMET), 1_183_200);
+
+        let earth = bank.supply_accounts()[&PlanetId::Earth];
+        assert_eq!(earth.expected(), bank.supply_of(PlanetId::Earth));
+
+        bank.freeze_interest();
+        assert!(bank.accrue(1_000).is_empty());
+        assert_eq!(exp_ppm(0), 1_000_000);
+        assert_eq!(exp_ppm(1_000_000), 2_718_282);
+    }
+
+    fn change(planet: PlanetId, effective_height: u64, holder_interest_apy: f64) -> MonetaryChange {
+        MonetaryChange {
+            effective_height,
+            planet,
+            monetary: MonetarySpec {
+                holder_interest_apy,
+                ..MonetarySpec::default()
+            },
+            reason: "halve the rate".into(),
+            approvers: vec![COMET.into()],
+            scheduled_ms: 0,
+        }
+    }
+
+    #[test]
+    fn scheduled_rates_apply_from_their_height() {
+        let mut bank = bank();
+        bank.accrue(2);
+        assert_eq!(bank.balance_of(PlanetId::Earth, COMET), 1_040_400);
+
+        // Earth pays half its rate from height 5; blocks 3 and 4 still pay 2%.
+        bank.schedule_change(change(PlanetId::Earth, 5, 0.309))
+            .unwrap();
+        assert_eq!(bank.per_block_factor_ppm(PlanetId::Earth), 1_020_000);
+        bank.accrue(6);
+        assert_eq!(bank.balance_of(PlanetId::Earth, COMET), 1_104_188);
+        assert_eq!(bank.per_block_factor_ppm(PlanetId::Earth), 1_010_000);
+        assert_eq!(
+            bank.monetary_at(PlanetId::Earth, 4).holder_interest_apy,
+            0.618
+        );
+        assert_eq!(
+            bank.monetary_at(PlanetId::Earth, 5).holder_interest_apy,
+            0.309
+        );
+        assert_eq!(
+            bank.monetary_at(PlanetId::Mars, 9).holder_interest_apy,
+            0.382
+        );
+        let earth = bank.supply_accounts()[&PlanetId::Earth];
+        assert_eq!(earth.expected(), bank.supply_of(PlanetId::Earth));
+
+        // Paid heights, repeats, wild rates and unapproved changes are refused.
+        let mut unapproved = change(PlanetId::Moon, 9, 0.5);
+        unapproved.approvers.clear();
+        let mut faster = change(PlanetId::Moon, 9, 0.5);
+        faster.monetary.target_block_seconds = 4.0;
+        for refused in [
+            change(PlanetId::Earth, 6, 0.5),
+            change(PlanetId::Earth, 5, 0.5),
+            change(PlanetId::Moon, 9, f64::NAN),
+            change(PlanetId::Moon, 9, 11.0),
+            unapproved,
+            faster,
+        ] {
+            assert!(matches!(
+                bank.schedule_change(refused),
+                Err(BankError::InvalidSchedule(_))
+            ));
+        }
+        assert_eq!(bank.schedule().len(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+//! Gateway event feed: polls `/omega/events` and turns events into cues.
+
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Something the audio side can react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OmegaCue {
+    BlockSealed { height: u64 },
+    Event { kind: String, height: u64 },
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsResponse {
+    last_seq: u64,
+    events: Vec<GatewayEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayEvent {
+    height: u64,
+    kind: String,
+}
+
+impl From<GatewayEvent> for OmegaCue {
+    fn from(event: GatewayEvent) -> Self {
+        match event.kind.as_str() {
+            "block_sealed" => OmegaCue::BlockSealed {
+                height: event.height,
+            },
+            _ => OmegaCue::Event {
+                kind: event.kind,
+                height: event.height,
+            },
+        }
+    }
+}
+
+/// Poll `{edge}/omega/events` forever, forwarding new events as cues to every
+/// listener. The first poll only primes the cursor (plus the latest height) so
+/// a restart doesn't replay the backlog as a burst of stingers.
+pub fn spawn_event_feed(edge: String, mut listeners: Vec<Sender<OmegaCue>>) {
+    thread::spawn(move || {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(2))
+            .build();
+        let mut since: Option<u64> = None;
+        let mut offline = false;
+
+        loop {
+            let url = format!("{edge}/omega/events?since={}", since.unwrap_or_default());
+            match agent
+                .get(&url)
+                .call()
+                .map_err(|err| err.to_string())
+                .and_then(|resp| {
+                    resp.into_json::<EventsResponse>()
+                        .map_err(|err| err.to_string())
+                }) {
+                Ok(body) => {
+                    if offline {
+                        println!("[Ω] event feed reconnected: {edge}");
+                        offline = false;
+                    }
+                    let priming = since.is_none();
+                    since = Some(body.last_seq);
+                    let cues: Vec<OmegaCue> = body.events.into_iter().map(OmegaCue::from).collect();
+                    let forwarded = if priming {
+                        cues.into_iter()
+                            .rfind(|c| matches!(c, OmegaCue::BlockSealed { .. }))
+                            .into_iter()
+                            .collect()
+                    } else {
+                        cues
+                    };
+                    for cue in forwarded {
+                        listeners.retain(|tx| tx.send(cue.clone()).is_ok());
+                    }
+                    if listeners.is_empty() {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    if !offline {
+                        println!("[Ω] event feed offline ({err}); retrying");
+                        offline = true;
+                    }
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
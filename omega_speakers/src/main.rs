@@ -1,13 +1,26 @@
+mod events;
+mod midi;
+mod mixer;
+mod stream;
+mod telemetry;
+
 use std::collections::HashMap;
 use std::env;
-use std::f32::consts::PI;
 use std::fs;
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
 
+use rand::SeedableRng;
 use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
 use rodio::{OutputStream, Sink, Source};
 
+use events::{OmegaCue, spawn_event_feed};
+use midi::{MidiMapping, MidiMode, spawn_midi_output};
+use mixer::{Envelope, Mixer, PhiArpeggio, RailBed, StingerPatch, Voice, VoiceKind};
+use stream::{STREAM_PATH, spawn_stream_server};
+use telemetry::{AudioMeter, BLOCK_FRAMES, spawn_telemetry_reporter};
+
 #[derive(Debug, Clone)]
 struct OmegaConfig {
     omega_root: String,
@@ -22,6 +35,54 @@ struct OmegaConfig {
     mode: String,
     height: f32,
     alpha_scale: f32,
+    voices: VoiceConfig,
+    edge: Option<String>,
+    midi_mode: MidiMode,
+    midi_map_path: String,
+    metrics_path: String,
+    /// Stream over HTTP on this address instead of playing locally.
+    serve: Option<String>,
+}
+
+/// Per-voice gain/envelope controls (speaker profile keys in parentheses).
+#[derive(Debug, Clone)]
+struct VoiceConfig {
+    /// Rail bed gain (`rail_gain`).
+    rail_gain: f32,
+    /// Melody gain, base pitch, note length, fade (`melody_gain`, `melody_hz`,
+    /// `melody_note_s`, `melody_attack_s`).
+    melody_gain: f32,
+    melody_hz: f32,
+    melody_note_s: f32,
+    melody_attack_s: f32,
+    /// Stinger gain, base pitch, attack, release (`stinger_gain`,
+    /// `stinger_hz`, `stinger_attack_s`, `stinger_release_s`).
+    stinger_gain: f32,
+    stinger_hz: f32,
+    stinger_attack_s: f32,
+    stinger_release_s: f32,
+}
+
+impl VoiceConfig {
+    fn from_profile(speaker: &HashMap<String, String>) -> Self {
+        let f = |key: &str, default: f32| {
+            speaker
+                .get(key)
+                .and_then(|s| s.parse::<f32>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            rail_gain: f("rail_gain", 1.0),
+            melody_gain: f("melody_gain", 0.35),
+            melody_hz: f("melody_hz", 432.0),
+            melody_note_s: f("melody_note_s", 0.618),
+            melody_attack_s: f("melody_attack_s", 2.0),
+            stinger_gain: f("stinger_gain", 0.6),
+            stinger_hz: f("stinger_hz", 528.0),
+            stinger_attack_s: f("stinger_attack_s", 0.01),
+            stinger_release_s: f("stinger_release_s", 0.8),
+        }
+    }
 }
 
 impl OmegaConfig {
@@ -75,6 +136,37 @@ impl OmegaConfig {
 
         let alpha_scale = friction_alpha(&friction);
         let sky_stream_path = format!("{}/sky/sky;stream", omega_root);
+        let voices = VoiceConfig::from_profile(&speaker);
+
+        // Event-driven voices follow the gateway; OMEGA_EDGE=off disables the feed.
+        let edge = match env::var("OMEGA_EDGE") {
+            Ok(v) if v.trim().eq_ignore_ascii_case("off") => None,
+            Ok(v) if !v.trim().is_empty() => Some(v.trim().trim_end_matches('/').to_string()),
+            _ => Some("http://127.0.0.1:8080".to_string()),
+        };
+
+        // OMEGA_MIDI=off|dry|live; the mapping file can be overridden per show.
+        let midi_mode = env::var("OMEGA_MIDI")
+            .ok()
+            .map(|raw| {
+                MidiMode::parse(&raw).unwrap_or_else(|err| {
+                    eprintln!("[midi] {err}; MIDI disabled");
+                    MidiMode::Off
+                })
+            })
+            .unwrap_or(MidiMode::Off);
+        let midi_map_path = env::var("OMEGA_MIDI_MAP")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| format!("{omega_root}/flames/midi;map"));
+
+        let metrics_path = format!("{omega_root}/flames/speaker;metrics");
+
+        // OMEGA_SPEAKER_SERVE=0.0.0.0:8899 streams the bed instead of playing it.
+        let serve = env::var("OMEGA_SPEAKER_SERVE")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty() && !s.eq_ignore_ascii_case("off"));
 
         Self {
             omega_root,
@@ -89,6 +181,12 @@ impl OmegaConfig {
             mode,
             height,
             alpha_scale,
+            voices,
+            edge,
+            midi_mode,
+            midi_map_path,
+            metrics_path,
+            serve,
         }
     }
 }
@@ -116,6 +214,17 @@ fn parse_kv_file(path: &str) -> HashMap<String, String> {
     out
 }
 
+/// Mapping file when present and valid, otherwise the built-in defaults.
+fn load_midi_mapping(path: &str) -> MidiMapping {
+    if fs::metadata(path).is_err() {
+        return MidiMapping::fallback();
+    }
+    MidiMapping::from_kv(&parse_kv_file(path)).unwrap_or_else(|err| {
+        eprintln!("[midi] {path}: {err}; using built-in mapping");
+        MidiMapping::fallback()
+    })
+}
+
 fn speaker_profile_path(omega_root: &str, friction: &str) -> String {
     let specific = format!("{omega_root}/flames/speaker;{friction}");
     if fs::metadata(&specific).is_ok() {
@@ -151,51 +260,47 @@ fn friction_alpha(friction: &str) -> f32 {
     }
 }
 
-struct OmegaSource {
-    sample_rate: u32,
-    rail_hz: f32,
-    whoosh_min_hz: f32,
-    whoosh_max_hz: f32,
-    gain: f32,
-    alpha_scale: f32,
-    t: f32,
-    rng: StdRng,
-    whoosh_state: f32,
+/// Stereo rodio source driven by the mixer; gateway cues are drained between frames.
+struct MixerSource {
+    mixer: Mixer,
+    cues: Receiver<OmegaCue>,
+    meter: Arc<AudioMeter>,
+    /// Render time and frame count of the block in progress.
+    block_render: Duration,
+    block_frames: u64,
+    current: f32,
     channel: u8,
 }
 
-impl Iterator for OmegaSource {
+impl Iterator for MixerSource {
     type Item = f32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let dt = 1.0 / self.sample_rate as f32;
-
-        // Update state once per stereo frame (on left channel)
+        // Render once per stereo frame (on left channel)
         if self.channel == 0 {
-            self.t += dt;
-
-            let noise: f32 = self.rng.gen_range(-1.0..1.0);
-
-            // Rail drives an LFO between whoosh_min and whoosh_max
-            let rail_phase = (self.t * self.rail_hz * 2.0 * PI).sin() * 0.5 + 0.5;
-            let center_hz =
-                self.whoosh_min_hz + (self.whoosh_max_hz - self.whoosh_min_hz) * rail_phase;
-
-            let alpha = ((2.0 * PI * center_hz * dt) * self.alpha_scale)
-                .clamp(0.001, 0.99);
-            self.whoosh_state = self.whoosh_state * (1.0 - alpha) + noise * alpha;
+            let started = Instant::now();
+            while let Ok(cue) = self.cues.try_recv() {
+                self.mixer.on_cue(&cue);
+            }
+            self.current = self.mixer.next_sample();
+            self.block_render += started.elapsed();
+            self.block_frames += 1;
+            if self.block_frames == BLOCK_FRAMES {
+                self.meter
+                    .record_block(self.block_render, self.mixer.active_voices());
+                self.block_render = Duration::ZERO;
+                self.block_frames = 0;
+            }
         }
 
-        let sample = self.whoosh_state * self.gain;
-
         // Flip channel 0 ↔ 1 (L/R interleave)
         self.channel ^= 1;
 
-        Some(sample)
+        Some(self.current)
     }
 }
 
-impl Source for OmegaSource {
+impl Source for MixerSource {
     fn current_frame_len(&self) -> Option<usize> {
         None
     }
@@ -205,7 +310,7 @@ impl Source for OmegaSource {
     }
 
     fn sample_rate(&self) -> u32 {
-        self.sample_rate
+        self.mixer.sample_rate()
     }
 
     fn total_duration(&self) -> Option<Duration> {
@@ -250,16 +355,88 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let rng = StdRng::from_seed(seed_bytes);
 
-    let source = OmegaSource {
-        sample_rate: 44_100,
-        rail_hz: config.rail_hz,
-        whoosh_min_hz: config.whoosh_min_hz,
-        whoosh_max_hz: config.whoosh_max_hz,
-        gain: config.gain,
-        alpha_scale: config.alpha_scale,
-        t: 0.0,
-        rng,
-        whoosh_state: 0.0,
+    let voices = &config.voices;
+    let mut mixer = Mixer::new(
+        44_100,
+        config.gain,
+        StingerPatch {
+            gain: voices.stinger_gain,
+            envelope: Envelope::pluck(voices.stinger_attack_s, voices.stinger_release_s),
+            base_hz: voices.stinger_hz,
+        },
+    );
+    mixer.add_voice(Voice::new(
+        voices.rail_gain,
+        Envelope::pad(0.5),
+        VoiceKind::Rail(Box::new(RailBed::new(
+            config.rail_hz,
+            config.whoosh_min_hz,
+            config.whoosh_max_hz,
+            config.alpha_scale,
+            rng,
+        ))),
+    ));
+    mixer.add_voice(Voice::new(
+        voices.melody_gain,
+        Envelope::pad(voices.melody_attack_s),
+        VoiceKind::Melody(PhiArpeggio::new(voices.melody_hz, voices.melody_note_s, 0)),
+    ));
+    println!(
+        "[+] Voices         : rail x{:.2}, melody x{:.2} @ {:.1} Hz, stingers x{:.2} @ {:.1} Hz",
+        voices.rail_gain,
+        voices.melody_gain,
+        voices.melody_hz,
+        voices.stinger_gain,
+        voices.stinger_hz
+    );
+
+    let (cue_tx, cue_rx) = mpsc::channel();
+    let mut listeners = vec![cue_tx];
+    if config.midi_mode != MidiMode::Off {
+        let mapping = load_midi_mapping(&config.midi_map_path);
+        println!(
+            "[+] MIDI           : {:?} ch{} port={} ({} cues) ← {}",
+            config.midi_mode,
+            mapping.channel,
+            mapping.port.as_deref().unwrap_or("<first>"),
+            mapping.actions.len(),
+            config.midi_map_path
+        );
+        let (midi_tx, midi_rx) = mpsc::channel();
+        listeners.push(midi_tx);
+        spawn_midi_output(config.midi_mode, mapping, midi_rx);
+    }
+    match &config.edge {
+        Some(edge) => {
+            println!("[+] Event feed     : {edge}/omega/events");
+            spawn_event_feed(edge.clone(), listeners);
+        }
+        None => println!("[+] Event feed     : off"),
+    }
+
+    let meter = AudioMeter::new(mixer.sample_rate());
+    println!("[+] Telemetry      : every 8s → gateway, else {}", config.metrics_path);
+    spawn_telemetry_reporter(
+        config.edge.clone(),
+        Arc::clone(&meter),
+        config.metrics_path.clone(),
+    );
+
+    if let Some(addr) = &config.serve {
+        let bound = spawn_stream_server(addr, mixer, cue_rx, meter)?;
+        println!("[Ω] Vortex bed streaming on http://{bound}{STREAM_PATH} (Ctrl+C to stop)");
+        loop {
+            std::thread::sleep(Duration::from_secs(3600));
+        }
+    }
+
+    let source = MixerSource {
+        mixer,
+        cues: cue_rx,
+        meter,
+        block_render: Duration::ZERO,
+        block_frames: 0,
+        current: 0.0,
         channel: 0,
     };
 
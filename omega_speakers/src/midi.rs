@@ -0,0 +1,329 @@
+//! MIDI output for live sets: Ω cues mapped to notes / CC on one channel.
+//!
+//! The mapping lives in a kv file (default `$OMEGA_ROOT/flames/midi;map`):
+//!
+//! ```text
+//! channel=1
+//! port=IAC
+//! block_sealed=note:60:100:250
+//! transfer=note:67:90:120
+//! sky_transition=cc:74:64
+//! ```
+//!
+//! `note:<key>:<velocity>:<hold_ms>` and `cc:<controller>:<value>`. Keys are
+//! gateway event kinds (`block_sealed` is the seal cue). Live output needs the
+//! `midi` feature; dry-run only logs what would be sent.
+
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::events::OmegaCue;
+
+const NOTE_ON: u8 = 0x90;
+const NOTE_OFF: u8 = 0x80;
+const CONTROL_CHANGE: u8 = 0xB0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiMode {
+    Off,
+    /// Log mapped messages instead of opening a port.
+    DryRun,
+    Live,
+}
+
+impl MidiMode {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "off" | "0" => Ok(MidiMode::Off),
+            "dry" | "dry-run" | "dry_run" => Ok(MidiMode::DryRun),
+            "on" | "live" | "1" => Ok(MidiMode::Live),
+            other => Err(format!("unknown MIDI mode {other:?} (off|dry|live)")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiAction {
+    Note { key: u8, velocity: u8, hold_ms: u64 },
+    Cc { controller: u8, value: u8 },
+}
+
+impl MidiAction {
+    fn parse(raw: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = raw.split(':').map(str::trim).collect();
+        let byte = |s: &str| {
+            s.parse::<u8>()
+                .ok()
+                .filter(|v| *v < 0x80)
+                .ok_or_else(|| format!("{s:?} is not a 7-bit MIDI value in {raw:?}"))
+        };
+        match parts.as_slice() {
+            ["note", key, velocity, hold_ms] => Ok(MidiAction::Note {
+                key: byte(key)?,
+                velocity: byte(velocity)?,
+                hold_ms: hold_ms
+                    .parse()
+                    .map_err(|_| format!("bad hold_ms in {raw:?}"))?,
+            }),
+            ["cc", controller, value] => Ok(MidiAction::Cc {
+                controller: byte(controller)?,
+                value: byte(value)?,
+            }),
+            _ => Err(format!(
+                "expected note:<key>:<velocity>:<hold_ms> or cc:<controller>:<value>, got {raw:?}"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MidiMapping {
+    /// 1-based channel as shown on hardware.
+    pub channel: u8,
+    /// Substring of the output port name; first port when unset.
+    pub port: Option<String>,
+    pub actions: HashMap<String, MidiAction>,
+}
+
+impl MidiMapping {
+    /// Built-in mapping used when no mapping file exists.
+    pub fn fallback() -> Self {
+        let actions = [
+            (
+                "block_sealed",
+                MidiAction::Note {
+                    key: 60,
+                    velocity: 100,
+                    hold_ms: 250,
+                },
+            ),
+            (
+                "transfer",
+                MidiAction::Note {
+                    key: 67,
+                    velocity: 90,
+                    hold_ms: 120,
+                },
+            ),
+            (
+                "sky_transition",
+                MidiAction::Cc {
+                    controller: 74,
+                    value: 64,
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(kind, action)| (kind.to_string(), action))
+        .collect();
+        Self {
+            channel: 1,
+            port: None,
+            actions,
+        }
+    }
+
+    /// Build from parsed kv pairs; `channel` and `port` are reserved keys.
+    pub fn from_kv(kv: &HashMap<String, String>) -> Result<Self, String> {
+        let channel = match kv.get("channel") {
+            Some(raw) => raw
+                .parse::<u8>()
+                .ok()
+                .filter(|c| (1..=16).contains(c))
+                .ok_or_else(|| format!("channel must be 1-16, got {raw:?}"))?,
+            None => 1,
+        };
+        let port = kv.get("port").filter(|p| !p.is_empty()).cloned();
+        let mut actions = HashMap::new();
+        for (kind, raw) in kv {
+            if kind == "channel" || kind == "port" {
+                continue;
+            }
+            actions.insert(kind.clone(), MidiAction::parse(raw)?);
+        }
+        Ok(Self {
+            channel,
+            port,
+            actions,
+        })
+    }
+
+    fn kind_of(cue: &OmegaCue) -> &str {
+        match cue {
+            OmegaCue::BlockSealed { .. } => "block_sealed",
+            OmegaCue::Event { kind, .. } => kind,
+        }
+    }
+
+    pub fn action_for(&self, cue: &OmegaCue) -> Option<MidiAction> {
+        self.actions.get(Self::kind_of(cue)).copied()
+    }
+
+    fn status(&self, kind: u8) -> u8 {
+        kind | (self.channel - 1)
+    }
+}
+
+/// Where raw MIDI bytes end up.
+pub trait MidiSink: Send {
+    fn send(&mut self, message: &[u8]);
+}
+
+/// Logs every message with a readable decode.
+pub struct DryRunSink;
+
+impl MidiSink for DryRunSink {
+    fn send(&mut self, message: &[u8]) {
+        let decoded = match message {
+            [status, a, b] => match status & 0xF0 {
+                NOTE_ON => format!("note_on key={a} vel={b}"),
+                NOTE_OFF => format!("note_off key={a}"),
+                CONTROL_CHANGE => format!("cc {a}={b}"),
+                _ => "unknown".to_string(),
+            },
+            _ => "unknown".to_string(),
+        };
+        let channel = message.first().map(|s| (s & 0x0F) + 1).unwrap_or(0);
+        println!("[midi] dry-run ch{channel} {decoded} {message:02X?}");
+    }
+}
+
+#[cfg(feature = "midi")]
+struct PortSink(midir::MidiOutputConnection);
+
+#[cfg(feature = "midi")]
+impl MidiSink for PortSink {
+    fn send(&mut self, message: &[u8]) {
+        if let Err(err) = self.0.send(message) {
+            eprintln!("[midi] send failed: {err}");
+        }
+    }
+}
+
+/// Open the configured output port (first port when none is named).
+#[cfg(feature = "midi")]
+fn open_port(mapping: &MidiMapping) -> Result<Box<dyn MidiSink>, String> {
+    let output = midir::MidiOutput::new("omega_speakers").map_err(|e| e.to_string())?;
+    let ports = output.ports();
+    let port = ports
+        .iter()
+        .find(|p| match (&mapping.port, output.port_name(p)) {
+            (Some(wanted), Ok(name)) => name.contains(wanted.as_str()),
+            (None, Ok(_)) => true,
+            (_, Err(_)) => false,
+        })
+        .ok_or_else(|| match &mapping.port {
+            Some(wanted) => format!("no MIDI output port matching {wanted:?}"),
+            None => "no MIDI output ports".to_string(),
+        })?;
+    let name = output.port_name(port).unwrap_or_default();
+    let conn = output
+        .connect(port, "omega-cues")
+        .map_err(|e| e.to_string())?;
+    println!("[midi] connected to {name}");
+    Ok(Box::new(PortSink(conn)))
+}
+
+#[cfg(not(feature = "midi"))]
+fn open_port(_mapping: &MidiMapping) -> Result<Box<dyn MidiSink>, String> {
+    Err("built without the `midi` feature".to_string())
+}
+
+/// Translate cues into MIDI on a background thread. Live mode falls back to
+/// dry-run when the port can't be opened so a show never silently drops cues.
+pub fn spawn_midi_output(mode: MidiMode, mapping: MidiMapping, cues: Receiver<OmegaCue>) {
+    let sink: Box<dyn MidiSink> = match mode {
+        MidiMode::Off => return,
+        MidiMode::DryRun => Box::new(DryRunSink),
+        MidiMode::Live => open_port(&mapping).unwrap_or_else(|err| {
+            eprintln!("[midi] live output unavailable ({err}); logging instead");
+            Box::new(DryRunSink)
+        }),
+    };
+    let sink = Arc::new(Mutex::new(sink));
+
+    thread::spawn(move || {
+        for cue in cues {
+            let Some(action) = mapping.action_for(&cue) else {
+                continue;
+            };
+            match action {
+                MidiAction::Note {
+                    key,
+                    velocity,
+                    hold_ms,
+                } => {
+                    let on = [mapping.status(NOTE_ON), key, velocity];
+                    let off = [mapping.status(NOTE_OFF), key, 0];
+                    sink.lock().expect("midi sink mutex poisoned").send(&on);
+                    // Note-off on its own timer so overlapping cues don't queue up.
+                    let sink = Arc::clone(&sink);
+                    thread::spawn(move || {
+                        thread::sleep(Duration::from_millis(hold_ms));
+                        sink.lock().expect("midi sink mutex poisoned").send(&off);
+                    });
+                }
+                MidiAction::Cc { controller, value } => {
+                    let cc = [mapping.status(CONTROL_CHANGE), controller, value];
+                    sink.lock().expect("midi sink mutex poisoned").send(&cc);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_parse_and_reject_out_of_range_values() {
+        assert_eq!(
+            MidiAction::parse(" note : 60 : 100 : 250 "),
+            Ok(MidiAction::Note {
+                key: 60,
+                velocity: 100,
+                hold_ms: 250,
+            })
+        );
+        assert_eq!(
+            MidiAction::parse("cc:74:64"),
+            Ok(MidiAction::Cc {
+                controller: 74,
+                value: 64,
+            })
+        );
+        assert!(MidiAction::parse("note:128:100:250").is_err());
+        assert!(MidiAction::parse("note:60:100:soon").is_err());
+        assert!(MidiAction::parse("cc:74").is_err());
+        assert!(MidiAction::parse("pitch:1:2").is_err());
+    }
+
+    #[test]
+    fn mapping_reserves_channel_and_port() {
+        let kv = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        let mapping = MidiMapping::from_kv(&kv(&[
+            ("channel", "10"),
+            ("port", "IAC"),
+            ("sky_transition", "cc:74:64"),
+        ]))
+        .unwrap();
+        assert_eq!(mapping.channel, 10);
+        assert_eq!(mapping.port.as_deref(), Some("IAC"));
+        assert_eq!(mapping.actions.len(), 1);
+        assert_eq!(mapping.status(NOTE_ON), 0x99);
+
+        let defaults = MidiMapping::from_kv(&kv(&[("port", "")])).unwrap();
+        assert_eq!((defaults.channel, defaults.port), (1, None));
+        assert!(MidiMapping::from_kv(&kv(&[("channel", "17")])).is_err());
+        assert!(MidiMapping::from_kv(&kv(&[("block_sealed", "note:60")])).is_err());
+    }
+}
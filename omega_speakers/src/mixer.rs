@@ -0,0 +1,389 @@
+//! Multi-voice mixer: whoosh rail bed, φ arpeggio melody, event stingers.
+//!
+//! Every voice has its own gain and ADSR envelope. The mixer renders one mono
+//! sample per frame; the output source duplicates it across channels.
+//!
+//! Voices never read absolute time: LFOs, oscillators and note steps advance
+//! wrapped phase accumulators by `dt`, so a speaker left running for days
+//! sounds the same as one that just started (an `f32` clock stops advancing
+//! after ~9 minutes at 44.1 kHz).
+
+use std::f32::consts::PI;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::events::OmegaCue;
+
+const PHI: f32 = 1.618_034;
+/// Stingers beyond this many concurrent voices are dropped.
+const MAX_STINGERS: usize = 8;
+/// Shortest melody note; a zero length would divide by zero.
+const MIN_NOTE_S: f32 = 0.01;
+
+/// Attack/decay/sustain/release envelope, times in seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct Envelope {
+    pub attack_s: f32,
+    pub decay_s: f32,
+    pub sustain: f32,
+    pub release_s: f32,
+}
+
+impl Envelope {
+    /// Held voices (rail bed, melody) sit at full level after a short fade in.
+    pub fn pad(attack_s: f32) -> Self {
+        Self {
+            attack_s,
+            decay_s: 0.0,
+            sustain: 1.0,
+            release_s: attack_s,
+        }
+    }
+
+    /// One-shot shape: fast attack, decays into a short sustain then releases.
+    pub fn pluck(attack_s: f32, release_s: f32) -> Self {
+        Self {
+            attack_s,
+            decay_s: release_s * 0.5,
+            sustain: 0.4,
+            release_s,
+        }
+    }
+
+    /// Level at `age` seconds, given when (if ever) the voice was released.
+    pub fn level(&self, age: f32, released_at: Option<f32>) -> f32 {
+        let held = |t: f32| {
+            if t < self.attack_s {
+                t / self.attack_s.max(1e-4)
+            } else if t < self.attack_s + self.decay_s {
+                let p = (t - self.attack_s) / self.decay_s.max(1e-4);
+                1.0 + (self.sustain - 1.0) * p
+            } else {
+                self.sustain
+            }
+        };
+        match released_at {
+            Some(at) if age >= at => {
+                let p = (age - at) / self.release_s.max(1e-4);
+                held(at) * (1.0 - p).max(0.0)
+            }
+            _ => held(age),
+        }
+    }
+
+    fn finished(&self, age: f32, released_at: Option<f32>) -> bool {
+        released_at.is_some_and(|at| age >= at + self.release_s)
+    }
+}
+
+/// Filtered noise swept between two band edges by the rail LFO.
+pub struct RailBed {
+    pub rail_hz: f32,
+    pub whoosh_min_hz: f32,
+    pub whoosh_max_hz: f32,
+    pub alpha_scale: f32,
+    pub rng: StdRng,
+    state: f32,
+    lfo_phase: f32,
+}
+
+impl RailBed {
+    pub fn new(
+        rail_hz: f32,
+        whoosh_min_hz: f32,
+        whoosh_max_hz: f32,
+        alpha_scale: f32,
+        rng: StdRng,
+    ) -> Self {
+        Self {
+            rail_hz,
+            whoosh_min_hz,
+            whoosh_max_hz,
+            alpha_scale,
+            rng,
+            state: 0.0,
+            lfo_phase: 0.0,
+        }
+    }
+
+    fn sample(&mut self, dt: f32) -> f32 {
+        let noise: f32 = self.rng.gen_range(-1.0..1.0);
+
+        // Rail drives an LFO between whoosh_min and whoosh_max
+        let rail_phase = (self.lfo_phase * 2.0 * PI).sin() * 0.5 + 0.5;
+        self.lfo_phase = (self.lfo_phase + self.rail_hz * dt).fract();
+        let center_hz = self.whoosh_min_hz + (self.whoosh_max_hz - self.whoosh_min_hz) * rail_phase;
+
+        let alpha = ((2.0 * PI * center_hz * dt) * self.alpha_scale).clamp(0.001, 0.99);
+        self.state = self.state * (1.0 - alpha) + noise * alpha;
+        self.state
+    }
+}
+
+/// Sine arpeggio whose notes come from the octal digits of the block height.
+pub struct PhiArpeggio {
+    pub base_hz: f32,
+    pub note_s: f32,
+    notes: Vec<f32>,
+    phase: f32,
+    /// Index of the current note and how far through it we are (0..1).
+    step: usize,
+    note_pos: f32,
+}
+
+impl PhiArpeggio {
+    pub fn new(base_hz: f32, note_s: f32, height: u64) -> Self {
+        let mut arp = Self {
+            base_hz,
+            note_s: note_s.max(MIN_NOTE_S),
+            notes: Vec::new(),
+            phase: 0.0,
+            step: 0,
+            note_pos: 0.0,
+        };
+        arp.set_height(height);
+        arp
+    }
+
+    /// Octal digit `d` of the height becomes `base · φ^(d/8)`.
+    pub fn set_height(&mut self, height: u64) {
+        self.notes = format!("{height:o}")
+            .bytes()
+            .map(|d| self.base_hz * PHI.powf((d - b'0') as f32 / 8.0))
+            .collect();
+    }
+
+    fn sample(&mut self, dt: f32) -> f32 {
+        let hz = self.notes[self.step % self.notes.len()];
+        // Half-sine gate per note so steps don't click.
+        let gate = (PI * self.note_pos).sin();
+        self.phase = (self.phase + hz * dt).fract();
+        self.note_pos += dt / self.note_s.max(MIN_NOTE_S);
+        if self.note_pos >= 1.0 {
+            self.note_pos = self.note_pos.fract();
+            self.step = (self.step + 1) % self.notes.len();
+        }
+        (self.phase * 2.0 * PI).sin() * gate
+    }
+}
+
+/// One-shot chirp gliding up by φ over its lifetime.
+pub struct Stinger {
+    pub hz: f32,
+    pub glide_s: f32,
+    phase: f32,
+}
+
+impl Stinger {
+    pub fn new(hz: f32, glide_s: f32) -> Self {
+        Self {
+            hz,
+            glide_s,
+            phase: 0.0,
+        }
+    }
+
+    fn sample(&mut self, age: f32, dt: f32) -> f32 {
+        let glide = (age / self.glide_s.max(1e-4)).min(1.0);
+        let hz = self.hz * PHI.powf(glide);
+        self.phase = (self.phase + hz * dt).fract();
+        (self.phase * 2.0 * PI).sin()
+    }
+}
+
+pub enum VoiceKind {
+    Rail(Box<RailBed>),
+    Melody(PhiArpeggio),
+    Stinger(Stinger),
+}
+
+pub struct Voice {
+    pub gain: f32,
+    pub envelope: Envelope,
+    kind: VoiceKind,
+    /// Seconds since the voice started. `f64` so held voices keep aging.
+    age: f64,
+    released_at: Option<f32>,
+}
+
+impl Voice {
+    pub fn new(gain: f32, envelope: Envelope, kind: VoiceKind) -> Self {
+        Self {
+            gain,
+            envelope,
+            kind,
+            age: 0.0,
+            released_at: None,
+        }
+    }
+
+    fn sample(&mut self, dt: f32) -> f32 {
+        let age = self.age as f32;
+        let raw = match &mut self.kind {
+            VoiceKind::Rail(rail) => rail.sample(dt),
+            VoiceKind::Melody(arp) => arp.sample(dt),
+            VoiceKind::Stinger(stinger) => stinger.sample(age, dt),
+        };
+        let level = self.envelope.level(age, self.released_at);
+        self.age += f64::from(dt);
+        raw * level * self.gain
+    }
+
+    fn finished(&self) -> bool {
+        self.envelope.finished(self.age as f32, self.released_at)
+    }
+}
+
+/// Gain + envelope template applied to every new stinger.
+#[derive(Debug, Clone, Copy)]
+pub struct StingerPatch {
+    pub gain: f32,
+    pub envelope: Envelope,
+    pub base_hz: f32,
+}
+
+pub struct Mixer {
+    sample_rate: u32,
+    pub master_gain: f32,
+    pub stinger: StingerPatch,
+    voices: Vec<Voice>,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32, master_gain: f32, stinger: StingerPatch) -> Self {
+        Self {
+            sample_rate,
+            master_gain,
+            stinger,
+            voices: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn add_voice(&mut self, voice: Voice) {
+        self.voices.push(voice);
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Re-seed every melody voice from a new block height.
+    pub fn set_height(&mut self, height: u64) {
+        for voice in &mut self.voices {
+            if let VoiceKind::Melody(arp) = &mut voice.kind {
+                arp.set_height(height);
+            }
+        }
+    }
+
+    /// Fire a one-shot stinger; `slot` picks one of eight φ-spaced pitches.
+    pub fn trigger_stinger(&mut self, slot: u8) {
+        let live = self
+            .voices
+            .iter()
+            .filter(|v| matches!(v.kind, VoiceKind::Stinger(_)))
+            .count();
+        if live >= MAX_STINGERS {
+            return;
+        }
+        let patch = self.stinger;
+        let hz = patch.base_hz * PHI.powf((slot % 8) as f32 / 8.0);
+        let mut voice = Voice::new(
+            patch.gain,
+            patch.envelope,
+            VoiceKind::Stinger(Stinger::new(
+                hz,
+                patch.envelope.attack_s + patch.envelope.decay_s,
+            )),
+        );
+        voice.released_at = Some(patch.envelope.attack_s + patch.envelope.decay_s);
+        self.voices.push(voice);
+    }
+
+    /// React to a gateway cue: heights retune the melody, events fire stingers.
+    pub fn on_cue(&mut self, cue: &OmegaCue) {
+        match cue {
+            OmegaCue::BlockSealed { height } => self.set_height(*height),
+            OmegaCue::Event { kind, .. } => {
+                let slot = kind.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+                self.trigger_stinger(slot);
+            }
+        }
+    }
+
+    /// Render one mono sample and advance time by one frame.
+    pub fn next_sample(&mut self) -> f32 {
+        let dt = 1.0 / self.sample_rate as f32;
+        let mix: f32 = self.voices.iter_mut().map(|v| v.sample(dt)).sum();
+        self.voices.retain(|v| !v.finished());
+        // Soft clip so stacked stingers can't hard-clip the output.
+        (mix * self.master_gain).tanh()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn melody_keeps_stepping_long_after_start() {
+        let dt = 1.0 / 44_100.0;
+        let mut arp = PhiArpeggio::new(220.0, 0.5, 0o1234);
+        // Start at the tail of the last note; the step wraps to the first.
+        arp.step = 3;
+        arp.note_pos = 0.999;
+        for _ in 0..100 {
+            arp.sample(dt);
+        }
+        assert_eq!(arp.step, 0);
+        assert!(arp.note_pos > 0.0 && arp.note_pos < 0.01);
+    }
+
+    #[test]
+    fn zero_note_length_and_long_runs_stay_finite() {
+        let patch = StingerPatch {
+            gain: 0.5,
+            envelope: Envelope::pluck(0.01, 0.2),
+            base_hz: 440.0,
+        };
+        let mut mixer = Mixer::new(8_000, 0.8, patch);
+        mixer.add_voice(Voice::new(
+            0.5,
+            Envelope::pad(0.1),
+            VoiceKind::Melody(PhiArpeggio::new(220.0, 0.0, 8)),
+        ));
+        mixer.add_voice(Voice::new(
+            0.5,
+            Envelope::pad(0.1),
+            VoiceKind::Rail(Box::new(RailBed::new(
+                0.5,
+                200.0,
+                800.0,
+                1.0,
+                StdRng::seed_from_u64(7),
+            ))),
+        ));
+        mixer.trigger_stinger(3);
+        for _ in 0..80_000 {
+            assert!(mixer.next_sample().is_finite());
+        }
+        // The stinger released and was dropped; the held voices remain.
+        assert_eq!(mixer.active_voices(), 2);
+    }
+
+    #[test]
+    fn envelope_releases_to_silence() {
+        let env = Envelope::pluck(0.01, 0.1);
+        assert_eq!(env.level(0.0, None), 0.0);
+        assert!((env.level(0.01, None) - 1.0).abs() < 1e-3);
+        assert!((env.level(1.0, None) - env.sustain).abs() < 1e-6);
+        assert_eq!(env.level(0.5, Some(0.2)), 0.0);
+        assert!(env.finished(0.31, Some(0.2)));
+    }
+}
@@ -0,0 +1,258 @@
+//! Server mode: the Ω bed as a WAV stream over HTTP.
+//!
+//! With `OMEGA_SPEAKER_SERVE=<addr>` the engine opens no output device.
+//! One render thread drives the same mixer, voices and gateway cues as the
+//! local speakers, paced to the wall clock, and every `GET /stream.wav`
+//! gets the blocks rendered from the moment it connected: a WAV header
+//! with open-ended sizes, then 16-bit stereo PCM in chunked encoding, so a
+//! browser `<audio>` element can play it. A listener more than
+//! [`CLIENT_BACKLOG`] blocks behind skips blocks until it catches up.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::events::OmegaCue;
+use crate::mixer::Mixer;
+use crate::telemetry::{AudioMeter, BLOCK_FRAMES};
+
+pub const STREAM_PATH: &str = "/stream.wav";
+/// Rendered blocks a listener may fall behind by.
+pub const CLIENT_BACKLOG: usize = 64;
+const CHANNELS: u16 = 2;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_LINES: usize = 64;
+
+type Listeners = Arc<Mutex<Vec<SyncSender<Arc<[u8]>>>>>;
+
+/// Header for a stream of unknown length: the RIFF and data sizes are left
+/// at their maximum, which players read as "until the connection ends".
+pub fn wav_header(sample_rate: u32) -> [u8; 44] {
+    let block_align = CHANNELS * 2;
+    let mut header = [0u8; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    header[8..16].copy_from_slice(b"WAVEfmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&CHANNELS.to_le_bytes());
+    header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    header[28..32].copy_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+    header
+}
+
+/// One block of mono samples as interleaved 16-bit little-endian stereo.
+pub fn pcm_block(samples: &[f32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(samples.len() * usize::from(CHANNELS) * 2);
+    for sample in samples {
+        let pcm = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+        for _ in 0..CHANNELS {
+            out.extend_from_slice(&pcm.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// Bind `addr` and start rendering; returns the bound address.
+pub fn spawn_stream_server(
+    addr: &str,
+    mixer: Mixer,
+    cues: Receiver<OmegaCue>,
+    meter: Arc<AudioMeter>,
+) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    let listeners: Listeners = Arc::default();
+    let sample_rate = mixer.sample_rate();
+
+    let fan_out = Arc::clone(&listeners);
+    thread::spawn(move || render_loop(mixer, cues, meter, fan_out));
+    thread::spawn(move || {
+        for conn in listener.incoming() {
+            let Ok(conn) = conn else { continue };
+            let listeners = Arc::clone(&listeners);
+            thread::spawn(move || {
+                if let Err(err) = serve(conn, sample_rate, &listeners) {
+                    eprintln!("[stream] listener dropped: {err}");
+                }
+            });
+        }
+    });
+    Ok(local)
+}
+
+/// Render a block per block period, whether or not anyone listens, so
+/// cues and block heights keep the bed where the local speakers would be.
+fn render_loop(
+    mut mixer: Mixer,
+    cues: Receiver<OmegaCue>,
+    meter: Arc<AudioMeter>,
+    listeners: Listeners,
+) {
+    let period = Duration::from_secs_f64(BLOCK_FRAMES as f64 / f64::from(mixer.sample_rate()));
+    let mut samples = vec![0.0f32; BLOCK_FRAMES as usize];
+    let mut due = Instant::now();
+    loop {
+        let started = Instant::now();
+        while let Ok(cue) = cues.try_recv() {
+            mixer.on_cue(&cue);
+        }
+        for sample in &mut samples {
+            *sample = mixer.next_sample();
+        }
+        meter.record_block(started.elapsed(), mixer.active_voices());
+
+        let block: Arc<[u8]> = pcm_block(&samples).into();
+        listeners
+            .lock()
+            .expect("stream listeners mutex poisoned")
+            .retain(|tx| match tx.try_send(Arc::clone(&block)) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+
+        due += period;
+        match due.checked_duration_since(Instant::now()) {
+            Some(wait) => thread::sleep(wait),
+            // Fell behind (suspended host, say): restart the schedule
+            // rather than bursting to catch up.
+            None => due = Instant::now(),
+        }
+    }
+}
+
+fn serve(conn: TcpStream, sample_rate: u32, listeners: &Listeners) -> io::Result<()> {
+    conn.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(conn.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; nothing in them changes the reply.
+    for _ in 0..MAX_REQUEST_LINES {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut conn = conn;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = path.split('?').next().unwrap_or(path);
+    match (method, path) {
+        ("OPTIONS", _) => return reply(&mut conn, "204 No Content", ""),
+        ("GET", STREAM_PATH) => {}
+        ("GET", _) => return reply(&mut conn, "404 Not Found", "not found\n"),
+        _ => return reply(&mut conn, "405 Method Not Allowed", "GET only\n"),
+    }
+
+    let (tx, rx) = mpsc::sync_channel(CLIENT_BACKLOG);
+    listeners
+        .lock()
+        .expect("stream listeners mutex poisoned")
+        .push(tx);
+    write!(
+        conn,
+        "HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nTransfer-Encoding: chunked\r\n\
+         Cache-Control: no-store\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n"
+    )?;
+    write_chunk(&mut conn, &wav_header(sample_rate))?;
+    for block in rx {
+        write_chunk(&mut conn, &block)?;
+    }
+    Ok(())
+}
+
+fn write_chunk(conn: &mut TcpStream, bytes: &[u8]) -> io::Result<()> {
+    write!(conn, "{:x}\r\n", bytes.len())?;
+    conn.write_all(bytes)?;
+    conn.write_all(b"\r\n")?;
+    conn.flush()
+}
+
+fn reply(conn: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        conn,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, OPTIONS\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mixer::{Envelope, PhiArpeggio, StingerPatch, Voice, VoiceKind};
+    use std::io::Read;
+
+    #[test]
+    fn listeners_get_a_wav_header_then_live_pcm_chunks() {
+        let header = wav_header(44_100);
+        assert_eq!(&header[..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(header[24..28].try_into().unwrap()),
+            44_100
+        );
+        assert_eq!(
+            u32::from_le_bytes(header[28..32].try_into().unwrap()),
+            176_400
+        );
+        assert_eq!(
+            pcm_block(&[1.0, -2.0]),
+            [255, 127, 255, 127, 1, 128, 1, 128]
+        );
+
+        let mut mixer = Mixer::new(
+            8_000,
+            0.5,
+            StingerPatch {
+                gain: 0.5,
+                envelope: Envelope::pluck(0.01, 0.1),
+                base_hz: 440.0,
+            },
+        );
+        mixer.add_voice(Voice::new(
+            1.0,
+            Envelope::pad(0.0),
+            VoiceKind::Melody(PhiArpeggio::new(220.0, 0.5, 8)),
+        ));
+        let (_cue_tx, cue_rx) = mpsc::channel();
+        let addr =
+            spawn_stream_server("127.0.0.1:0", mixer, cue_rx, AudioMeter::new(8_000)).unwrap();
+
+        let mut missing = TcpStream::connect(addr).unwrap();
+        missing.write_all(b"GET /nope HTTP/1.1\r\n\r\n").unwrap();
+        let mut reply = String::new();
+        missing.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("HTTP/1.1 404"));
+
+        let mut conn = TcpStream::connect(addr).unwrap();
+        conn.write_all(b"GET /stream.wav HTTP/1.1\r\nHost: x\r\n\r\n")
+            .unwrap();
+        let mut reader = BufReader::new(conn);
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            reader.read_line(&mut head).unwrap();
+        }
+        assert!(head.contains("audio/wav") && head.contains("chunked"));
+        let chunk = |reader: &mut BufReader<TcpStream>| {
+            let mut size = String::new();
+            reader.read_line(&mut size).unwrap();
+            let mut bytes = vec![0; usize::from_str_radix(size.trim(), 16).unwrap() + 2];
+            reader.read_exact(&mut bytes).unwrap();
+            bytes.truncate(bytes.len() - 2);
+            bytes
+        };
+        assert_eq!(chunk(&mut reader), wav_header(8_000));
+        let block = chunk(&mut reader);
+        assert_eq!(block.len(), BLOCK_FRAMES as usize * 4);
+        assert!(block.iter().any(|&b| b != 0));
+    }
+}
@@ -0,0 +1,159 @@
+//! Audio health: underruns, render time and voice count.
+//!
+//! The mixer source stamps an [`AudioMeter`] as it renders; a reporter thread
+//! ships snapshots to the gateway as `AUDIO` telemetry frames, or writes them
+//! to a local kv metrics file while the gateway is unreachable.
+
+use std::fs;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::json;
+
+/// Frames per render block; roughly one device callback at 44.1 kHz.
+pub const BLOCK_FRAMES: u64 = 512;
+const REPORT_INTERVAL: Duration = Duration::from_secs(8);
+/// Telemetry frames ride an unregistered session; the gateway accepts those
+/// (handshakes need a verified phone, which the speaker engine doesn't have).
+const SESSION_ID: &str = "omega_speakers";
+
+#[derive(Debug)]
+pub struct AudioMeter {
+    /// Set on the first rendered block so device start-up isn't an underrun.
+    started: OnceLock<Instant>,
+    sample_rate: u32,
+    frames: AtomicU64,
+    underruns: AtomicU64,
+    render_us_total: AtomicU64,
+    render_blocks: AtomicU64,
+    render_us_max: AtomicU64,
+    active_voices: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioSnapshot {
+    pub underruns: u64,
+    pub render_us_avg: u64,
+    pub render_us_max: u64,
+    pub buffered_ms: i64,
+    pub active_voices: usize,
+    pub sample_rate: u32,
+}
+
+impl AudioMeter {
+    pub fn new(sample_rate: u32) -> Arc<Self> {
+        Arc::new(Self {
+            started: OnceLock::new(),
+            sample_rate,
+            frames: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+            render_us_total: AtomicU64::new(0),
+            render_blocks: AtomicU64::new(0),
+            render_us_max: AtomicU64::new(0),
+            active_voices: AtomicUsize::new(0),
+        })
+    }
+
+    fn wall_ms(&self) -> u64 {
+        self.started
+            .get()
+            .map_or(0, |started| started.elapsed().as_millis() as u64)
+    }
+
+    /// Audio time rendered so far minus wall time, in milliseconds.
+    fn ahead_ms(&self, frames: u64) -> i64 {
+        let audio_ms = frames * 1000 / self.sample_rate as u64;
+        audio_ms as i64 - self.wall_ms() as i64
+    }
+
+    /// Called once per rendered block with the time spent rendering it.
+    pub fn record_block(&self, render: Duration, active_voices: usize) {
+        self.started.get_or_init(Instant::now);
+        let frames = self.frames.fetch_add(BLOCK_FRAMES, Ordering::Relaxed) + BLOCK_FRAMES;
+        let us = render.as_micros() as u64;
+        self.render_us_total.fetch_add(us, Ordering::Relaxed);
+        self.render_blocks.fetch_add(1, Ordering::Relaxed);
+        self.render_us_max.fetch_max(us, Ordering::Relaxed);
+        self.active_voices.store(active_voices, Ordering::Relaxed);
+
+        // Wall clock overtook the audio we had queued: the device ran dry.
+        // Count it once and rebase so a single stall isn't counted forever.
+        let block_ms = (BLOCK_FRAMES * 1000 / self.sample_rate as u64) as i64;
+        if self.ahead_ms(frames) < -block_ms {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+            let wall_frames = self.wall_ms() * self.sample_rate as u64 / 1000;
+            self.frames.store(wall_frames, Ordering::Relaxed);
+        }
+    }
+
+    /// Snapshot and reset the render window (underruns stay cumulative).
+    pub fn snapshot(&self) -> AudioSnapshot {
+        let blocks = self.render_blocks.swap(0, Ordering::Relaxed);
+        let total = self.render_us_total.swap(0, Ordering::Relaxed);
+        AudioSnapshot {
+            underruns: self.underruns.load(Ordering::Relaxed),
+            render_us_avg: total.checked_div(blocks).unwrap_or(0),
+            render_us_max: self.render_us_max.swap(0, Ordering::Relaxed),
+            buffered_ms: self.ahead_ms(self.frames.load(Ordering::Relaxed)).max(0),
+            active_voices: self.active_voices.load(Ordering::Relaxed),
+            sample_rate: self.sample_rate,
+        }
+    }
+}
+
+impl AudioSnapshot {
+    fn to_kv(&self) -> String {
+        format!(
+            "underruns={}\nrender_us_avg={}\nrender_us_max={}\nbuffered_ms={}\nactive_voices={}\nsample_rate={}\n",
+            self.underruns,
+            self.render_us_avg,
+            self.render_us_max,
+            self.buffered_ms,
+            self.active_voices,
+            self.sample_rate
+        )
+    }
+}
+
+/// Report every [`REPORT_INTERVAL`]: gateway first, `metrics_path` as fallback.
+pub fn spawn_telemetry_reporter(
+    edge: Option<String>,
+    meter: Arc<AudioMeter>,
+    metrics_path: String,
+) {
+    thread::spawn(move || {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(2))
+            .build();
+        let mut seq = 0u64;
+
+        loop {
+            thread::sleep(REPORT_INTERVAL);
+            let snapshot = meter.snapshot();
+            seq += 1;
+
+            let sent = edge.as_deref().is_some_and(|edge| {
+                let mut payload = json!(snapshot);
+                payload["kind"] = json!("telemetry");
+                let frame = json!({
+                    "session_id": SESSION_ID,
+                    "seq": seq,
+                    "namespace": ";∞;speaker;engine;",
+                    "kind": "AUDIO",
+                    "payload": payload,
+                });
+                agent
+                    .post(&format!("{edge}/omega/frame"))
+                    .send_json(frame)
+                    .is_ok()
+            });
+
+            if !sent && let Err(err) = fs::write(&metrics_path, snapshot.to_kv()) {
+                eprintln!("[telemetry] {metrics_path}: {err}");
+            }
+        }
+    });
+}
@@ -10,6 +10,7 @@ import org.bukkit.Material;
 import org.bukkit.World;
 import org.bukkit.command.Command;
 import org.bukkit.command.CommandSender;
+import org.bukkit.configuration.ConfigurationSection;
 import org.bukkit.entity.ArmorStand;
 import org.bukkit.entity.Entity;
 import org.bukkit.entity.Player;
@@ -20,19 +21,28 @@ import net.md_5.bungee.api.ChatMessageType;
 import net.md_5.bungee.api.chat.TextComponent;
 
 import javax.net.ssl.HttpsURLConnection;
-import java.io.BufferedReader;
+import java.io.ByteArrayOutputStream;
 import java.io.IOException;
 import java.io.InputStream;
-import java.io.InputStreamReader;
 import java.io.OutputStream;
 import java.net.HttpURLConnection;
 import java.net.URL;
 import java.nio.charset.StandardCharsets;
+import java.security.GeneralSecurityException;
+import java.security.KeyFactory;
+import java.security.MessageDigest;
+import java.security.PublicKey;
+import java.security.Signature;
+import java.security.spec.X509EncodedKeySpec;
+import java.util.ArrayDeque;
+import java.util.Base64;
+import java.util.Deque;
 import java.util.HashMap;
 import java.util.HashSet;
 import java.util.Map;
 import java.util.Set;
 import java.util.UUID;
+import java.util.concurrent.ConcurrentHashMap;
 
 public class OmegaSimClientPlugin extends JavaPlugin {
     private final Gson gson = new Gson();
@@ -42,6 +52,9 @@ public class OmegaSimClientPlugin extends JavaPlugin {
     private String authToken;
     private long lastUiMs = 0L;
     private int intervalTicks;
+    private final ClockSync clock = new ClockSync();
+    private boolean verifySignatures;
+    private final ResponseKeys responseKeys = new ResponseKeys();
 
     @Override
     public void onEnable() {
@@ -49,6 +62,10 @@ public class OmegaSimClientPlugin extends JavaPlugin {
         this.apiBase = getConfig().getString("api_base", "http://localhost:8888");
         this.authToken = getConfig().getString("auth_token", "");
         this.intervalTicks = getConfig().getInt("tick_interval_ticks", 10);
+        this.verifySignatures = getConfig().getBoolean("verify_signatures", false);
+        if (verifySignatures) {
+            loadPinnedKeys();
+        }
 
         new BukkitRunnable() {
             @Override
@@ -57,6 +74,15 @@ public class OmegaSimClientPlugin extends JavaPlugin {
             }
         }.runTaskTimerAsynchronously(this, 40L, intervalTicks);
 
+        // Re-measure the clock offset every 5 s; the first exchange runs
+        // before the first tick.
+        new BukkitRunnable() {
+            @Override
+            public void run() {
+                syncClock();
+            }
+        }.runTaskTimerAsynchronously(this, 20L, 100L);
+
         getLogger().info("[omega-sim] client enabled; posting to " + apiBase);
     }
 
@@ -105,38 +131,116 @@ public class OmegaSimClientPlugin extends JavaPlugin {
         }
     }
 
+    private void syncClock() {
+        try {
+            JsonObject req = new JsonObject();
+            req.addProperty("client_send_ms", System.currentTimeMillis());
+            String body = post("/v1/sim/clock", gson.toJson(req));
+            long receivedMs = System.currentTimeMillis();
+            clock.record(gson.fromJson(body, ClockSyncResponse.class), receivedMs);
+        } catch (IOException e) {
+            getLogger().warning("[omega-sim] clock sync failed: " + e.getMessage());
+        }
+    }
+
     private SimTickResponse postTick(Player player) throws IOException {
-        URL url = new URL(apiBase + "/v1/sim/tick");
+        Reply reply = send("POST", "/v1/sim/tick", buildPayload(player));
+        if (verifySignatures) {
+            verifySignature(reply);
+        }
+        return gson.fromJson(reply.text(), SimTickResponse.class);
+    }
+
+    private String post(String path, String payload) throws IOException {
+        return send("POST", path, payload).text();
+    }
+
+    /** `payload` is null for a GET. */
+    private Reply send(String method, String path, String payload) throws IOException {
+        URL url = new URL(apiBase + path);
         HttpURLConnection conn = (HttpURLConnection) url.openConnection();
         if (conn instanceof HttpsURLConnection) {
             ((HttpsURLConnection) conn).setSSLSocketFactory((HttpsURLConnection.getDefaultSSLSocketFactory()));
         }
-        conn.setRequestMethod("POST");
+        conn.setRequestMethod(method);
         conn.setConnectTimeout(3000);
         conn.setReadTimeout(4000);
-        conn.setRequestProperty("Content-Type", "application/json");
         if (authToken != null && !authToken.isEmpty()) {
             conn.setRequestProperty("X-Auth-Token", authToken);
         }
-        conn.setDoOutput(true);
 
-        String payload = buildPayload(player);
-        byte[] bytes = payload.getBytes(StandardCharsets.UTF_8);
-        conn.setFixedLengthStreamingMode(bytes.length);
-        try (OutputStream os = conn.getOutputStream()) {
-            os.write(bytes);
+        if (payload != null) {
+            conn.setRequestProperty("Content-Type", "application/json");
+            conn.setDoOutput(true);
+            byte[] bytes = payload.getBytes(StandardCharsets.UTF_8);
+            conn.setFixedLengthStreamingMode(bytes.length);
+            try (OutputStream os = conn.getOutputStream()) {
+                os.write(bytes);
+            }
         }
 
         int code = conn.getResponseCode();
         InputStream body = (code >= 200 && code < 300) ? conn.getInputStream() : conn.getErrorStream();
-        String responseBody = readAll(body);
+        Reply reply = new Reply(readAll(body), conn.getHeaderField("X-Omega-Signature"));
         conn.disconnect();
 
         if (code < 200 || code >= 300) {
-            throw new IOException("HTTP " + code + " body=" + responseBody);
+            throw new IOException("HTTP " + code + " body=" + reply.text());
         }
 
-        return gson.fromJson(responseBody, SimTickResponse.class);
+        return reply;
+    }
+
+    /**
+     * Pin the response keys from `response_keys` (kid: base64url public key,
+     * as `GET /v1/sim/keys` lists them). They are never fetched from the
+     * server being checked: whoever can rewrite its responses can rewrite
+     * its key set too.
+     */
+    private void loadPinnedKeys() {
+        Map<String, String> pins = new HashMap<>();
+        ConfigurationSection section = getConfig().getConfigurationSection("response_keys");
+        if (section != null) {
+            for (String kid : section.getKeys(false)) {
+                pins.put(kid, section.getString(kid, ""));
+            }
+        }
+        try {
+            responseKeys.pin(pins);
+        } catch (GeneralSecurityException | IllegalArgumentException e) {
+            getLogger().severe("[omega-sim] response_keys: " + e.getMessage() + "; every tick response will be dropped");
+            return;
+        }
+        if (pins.isEmpty()) {
+            getLogger().severe("[omega-sim] verify_signatures is on but response_keys pins no key; every tick response will be dropped");
+        }
+    }
+
+    /**
+     * Check `X-Omega-Signature` (`<kid>.<sig>`): an Ed25519 signature of the
+     * body's SHA-256 by a pinned key. Any other kid fails.
+     */
+    private void verifySignature(Reply reply) throws IOException {
+        String header = reply.signature;
+        int dot = header == null ? -1 : header.indexOf('.');
+        if (dot < 0) {
+            throw new IOException("tick response is not signed");
+        }
+        String kid = header.substring(0, dot);
+        PublicKey key = responseKeys.get(kid);
+        if (key == null) {
+            throw new IOException("tick response signed with unpinned key " + kid);
+        }
+        try {
+            Signature verifier = Signature.getInstance("Ed25519");
+            verifier.initVerify(key);
+            verifier.update(MessageDigest.getInstance("SHA-256").digest(reply.body));
+            if (!verifier.verify(Base64.getUrlDecoder().decode(header.substring(dot + 1)))) {
+                throw new IOException("tick response signature does not match");
+            }
+        } catch (GeneralSecurityException | IllegalArgumentException e) {
+            throw new IOException("tick response signature: " + e.getMessage());
+        }
     }
 
     private String buildPayload(Player player) {
@@ -166,6 +270,10 @@ public class OmegaSimClientPlugin extends JavaPlugin {
         root.add("inputs", inputs);
 
         root.addProperty("client_time_ms", System.currentTimeMillis());
+        Long offset = clock.offsetMs();
+        if (offset != null) {
+            root.addProperty("clock_offset_ms", offset);
+        }
 
         return gson.toJson(root);
     }
@@ -287,25 +395,113 @@ public class OmegaSimClientPlugin extends JavaPlugin {
         }
     }
 
-    private String readAll(InputStream is) throws IOException {
+    private byte[] readAll(InputStream is) throws IOException {
         if (is == null) {
-            return "";
+            return new byte[0];
+        }
+        try (InputStream in = is) {
+            ByteArrayOutputStream out = new ByteArrayOutputStream();
+            byte[] buf = new byte[8192];
+            int n;
+            while ((n = in.read(buf)) != -1) {
+                out.write(buf, 0, n);
+            }
+            return out.toByteArray();
+        }
+    }
+
+    /** A response body as received, so its signature can be checked. */
+    private static class Reply {
+        final byte[] body;
+        final String signature;
+
+        Reply(byte[] body, String signature) {
+            this.body = body;
+            this.signature = signature;
+        }
+
+        String text() {
+            return new String(body, StandardCharsets.UTF_8);
+        }
+    }
+
+    /** Pinned Ed25519 keys by kid. */
+    private static class ResponseKeys {
+        /** DER prefix that makes a raw 32-byte Ed25519 key an X.509 one. */
+        private static final byte[] X509_PREFIX = {
+            0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00
+        };
+        private final Map<String, PublicKey> keys = new ConcurrentHashMap<>();
+
+        PublicKey get(String kid) {
+            return keys.get(kid);
         }
-        try (BufferedReader br = new BufferedReader(new InputStreamReader(is, StandardCharsets.UTF_8))) {
-            StringBuilder sb = new StringBuilder();
-            String line;
-            while ((line = br.readLine()) != null) {
-                sb.append(line);
+
+        /** Trust exactly `pins` (kid to base64url key); all or none of them. */
+        synchronized void pin(Map<String, String> pins) throws GeneralSecurityException {
+            Map<String, PublicKey> next = new HashMap<>();
+            KeyFactory factory = KeyFactory.getInstance("Ed25519");
+            for (Map.Entry<String, String> pin : pins.entrySet()) {
+                byte[] raw = Base64.getUrlDecoder().decode(pin.getValue().trim());
+                if (raw.length != 32) {
+                    throw new IllegalArgumentException("key " + pin.getKey() + " is not a 32-byte Ed25519 key");
+                }
+                byte[] der = new byte[X509_PREFIX.length + raw.length];
+                System.arraycopy(X509_PREFIX, 0, der, 0, X509_PREFIX.length);
+                System.arraycopy(raw, 0, der, X509_PREFIX.length, raw.length);
+                next.put(pin.getKey(), factory.generatePublic(new X509EncodedKeySpec(der)));
+            }
+            keys.clear();
+            keys.putAll(next);
+        }
+    }
+
+    /**
+     * phi-NTP offset estimate, as spec::ClockSync: the lowest-RTT sample of the
+     * last 8, approached by 1/phi of the gap per exchange.
+     */
+    private static class ClockSync {
+        private static final double PHI = 1.618033988749894;
+        private static final int WINDOW = 8;
+        private final Deque<long[]> samples = new ArrayDeque<>();
+        private Double offsetMs;
+
+        synchronized void record(ClockSyncResponse resp, long clientReceiveMs) {
+            long t0 = resp.client_send_ms;
+            long t1 = resp.server_receive_ms;
+            long t2 = resp.server_transmit_ms;
+            long offset = ((t1 - t0) + (t2 - clientReceiveMs)) / 2;
+            long rtt = Math.max(0L, (clientReceiveMs - t0) - (t2 - t1));
+            if (samples.size() == WINDOW) {
+                samples.removeFirst();
+            }
+            samples.addLast(new long[] {offset, rtt});
+            long[] best = samples.getFirst();
+            for (long[] s : samples) {
+                if (s[1] < best[1]) {
+                    best = s;
+                }
             }
-            return sb.toString();
+            offsetMs = offsetMs == null ? best[0] : offsetMs + (best[0] - offsetMs) / PHI;
+        }
+
+        synchronized Long offsetMs() {
+            return offsetMs == null ? null : Math.round(offsetMs);
         }
     }
 
     // DTOs for parsing the API response.
+    private static class ClockSyncResponse {
+        long client_send_ms;
+        long server_receive_ms;
+        long server_transmit_ms;
+    }
+
     private static class SimTickResponse {
         long tick;
         String state_version;
         long server_time_ms;
+        long input_time_ms;
         SimView view;
     }
 
@@ -1,9 +1,10 @@
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     routing::{get, post},
     Json, Router,
 };
+use corelib::privacy::PhonePrivacy;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -20,6 +21,9 @@ struct PresenceRecord {
     display_name: String,
     source: PresenceSource,
     session_id: String,
+    /// Last verified phone session seen for this phone; required to delete.
+    #[serde(skip)]
+    session_token: Option<String>,
     state: PresenceState,
 }
 
@@ -68,22 +72,33 @@ struct PresenceResponse {
     record: Option<PresenceRecord>,
 }
 
+/// Records are keyed (and store `phone`) by the privacy phone key, so with
+/// `OMEGA_PHONE_SALT` set no plaintext phone is held at rest.
 #[derive(Clone)]
 struct AppState {
     records: Arc<Mutex<HashMap<String, PresenceRecord>>>,
+    privacy: PhonePrivacy,
+    /// `OMEGA_ADMIN_TOKEN`; as `X-Admin-Token` it deletes any record.
+    admin_token: Option<String>,
+    /// `PRESENCE_SERVICE_TOKEN`, shared with the gateway; as
+    /// `X-Service-Token` it deletes records with no phone session.
+    service_token: Option<String>,
 }
 
 #[tokio::main]
 async fn main() {
     let state = AppState {
         records: Arc::new(Mutex::new(HashMap::new())),
+        privacy: PhonePrivacy::from_env(),
+        admin_token: secret_from_env("OMEGA_ADMIN_TOKEN"),
+        service_token: secret_from_env("PRESENCE_SERVICE_TOKEN"),
     };
 
     let app = Router::new()
         .route("/presence/mojang", post(register_mojang))
         .route("/presence/web", post(register_web))
         .route("/presence/heartbeat", post(heartbeat))
-        .route("/presence/:phone", get(get_presence))
+        .route("/presence/:phone", get(get_presence).delete(delete_presence))
         .with_state(state);
 
     let addr: SocketAddr = "0.0.0.0:4000".parse().expect("invalid bind address");
@@ -107,18 +122,23 @@ async fn register_mojang(
     State(state): State<AppState>,
     Json(payload): Json<MojangPresenceRequest>,
 ) -> StatusCode {
+    let phone_key = state.privacy.phone_key(&payload.phone);
     let mut records = state.records.lock().expect("records mutex poisoned");
     let session_id = Uuid::new_v4().to_string();
+    let session_token = records
+        .get(&phone_key)
+        .and_then(|r| r.session_token.clone());
     records.insert(
-        payload.phone.clone(),
+        phone_key.clone(),
         PresenceRecord {
-            phone: payload.phone,
+            phone: phone_key,
             label: payload.label,
             display_name: payload
                 .display_name
                 .unwrap_or_else(|| payload.gamer_tag.clone()),
             source: PresenceSource::Mojang,
             session_id,
+            session_token,
             state: PresenceState::Online,
         },
     );
@@ -129,15 +149,17 @@ async fn register_web(
     State(state): State<AppState>,
     Json(payload): Json<WebPresenceRequest>,
 ) -> StatusCode {
+    let phone_key = state.privacy.phone_key(&payload.phone);
     let mut records = state.records.lock().expect("records mutex poisoned");
     records.insert(
-        payload.phone.clone(),
+        phone_key.clone(),
         PresenceRecord {
-            phone: payload.phone,
+            phone: phone_key,
             label: payload.label,
             display_name: payload.display_name,
             source: PresenceSource::Web,
-            session_id: payload.session_token,
+            session_id: Uuid::new_v4().to_string(),
+            session_token: Some(payload.session_token),
             state: PresenceState::Online,
         },
     );
@@ -149,10 +171,11 @@ async fn heartbeat(
     Json(payload): Json<HeartbeatRequest>,
 ) -> StatusCode {
     let mut records = state.records.lock().expect("records mutex poisoned");
-    if let Some(record) = records
-        .values_mut()
-        .find(|r| r.session_id == payload.session_id)
-    {
+    // Web clients heartbeat with their phone session token.
+    if let Some(record) = records.values_mut().find(|r| {
+        r.session_id == payload.session_id
+            || r.session_token.as_deref() == Some(payload.session_id.as_str())
+    }) {
         record.state = payload.state;
         StatusCode::NO_CONTENT
     } else {
@@ -165,9 +188,194 @@ async fn get_presence(
     axum::extract::Path(phone): axum::extract::Path<String>,
 ) -> Json<PresenceResponse> {
     let records = state.records.lock().expect("records mutex poisoned");
-    let record = records.get(&phone).cloned();
+    let record = records.get(&state.privacy.phone_key(&phone)).cloned();
     Json(PresenceResponse {
         status: if record.is_some() { "ok" } else { "not_found" },
         record,
     })
 }
+
+/// Erase a phone's presence record (gateway account deletion). The caller
+/// must present the phone's verified session as `X-Session-Token`. A record
+/// with no phone session (Mojang logins only) takes the gateway's
+/// `X-Service-Token` instead; the gateway has verified the phone itself.
+/// `X-Admin-Token` deletes any record.
+async fn delete_presence(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(phone): axum::extract::Path<String>,
+) -> StatusCode {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+    };
+    let (admin_token, token, service_token) = (
+        header("x-admin-token"),
+        header("x-session-token"),
+        header("x-service-token"),
+    );
+    if admin_token.is_none() && token.is_none() && service_token.is_none() {
+        return StatusCode::UNAUTHORIZED;
+    }
+    let admin = presented(admin_token, state.admin_token.as_deref());
+    let service = presented(service_token, state.service_token.as_deref());
+    let phone_key = state.privacy.phone_key(&phone);
+    let mut records = state.records.lock().expect("records mutex poisoned");
+    let Some(record) = records.get(&phone_key) else {
+        return StatusCode::NOT_FOUND;
+    };
+    let allowed = admin
+        || match &record.session_token {
+            Some(session) => presented(token, Some(session)),
+            None => service,
+        };
+    if !allowed {
+        return StatusCode::FORBIDDEN;
+    }
+    records.remove(&phone_key);
+    StatusCode::NO_CONTENT
+}
+
+fn secret_from_env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+}
+
+/// Whether `presented` matches the configured `expected` secret, compared
+/// without an early exit so timing doesn't leak how much of it was right.
+fn presented(presented: Option<&str>, expected: Option<&str>) -> bool {
+    let (Some(presented), Some(expected)) = (presented, expected) else {
+        return false;
+    };
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+
+    fn state() -> AppState {
+        AppState {
+            records: Arc::new(Mutex::new(HashMap::new())),
+            privacy: PhonePrivacy::default(),
+            admin_token: Some("root".into()),
+            service_token: Some("gateway".into()),
+        }
+    }
+
+    async fn mojang_login(state: &AppState, phone: &str) {
+        let login = MojangPresenceRequest {
+            gamer_tag: "vortex".into(),
+            _mojang_uuid: "069a79f444e94726a5befca90e38aaf5".into(),
+            phone: phone.into(),
+            label: "vortex1".into(),
+            display_name: None,
+        };
+        register_mojang(State(state.clone()), Json(login)).await;
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    async fn delete(state: &AppState, phone: &str, pairs: &[(&'static str, &str)]) -> StatusCode {
+        delete_presence(State(state.clone()), headers(pairs), Path(phone.into())).await
+    }
+
+    #[tokio::test]
+    async fn mojang_only_records_delete_with_the_service_or_admin_token() {
+        let state = state();
+        mojang_login(&state, "9132077554").await;
+        assert_eq!(
+            delete(&state, "9132077554", &[]).await,
+            StatusCode::UNAUTHORIZED
+        );
+        for refused in [
+            ("x-session-token", "guess"),
+            ("x-service-token", "gatewax"),
+            ("x-admin-token", "roo"),
+        ] {
+            assert_eq!(
+                delete(&state, "9132077554", &[refused]).await,
+                StatusCode::FORBIDDEN
+            );
+        }
+        // Mojang uuids are public, so one proves nothing.
+        assert_eq!(
+            delete(
+                &state,
+                "9132077554",
+                &[("x-mojang-uuid", "069a79f444e94726a5befca90e38aaf5")]
+            )
+            .await,
+            StatusCode::UNAUTHORIZED
+        );
+        let service = ("x-service-token", "gateway");
+        assert_eq!(
+            delete(&state, "9132077554", &[service]).await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            delete(&state, "9132077554", &[service]).await,
+            StatusCode::NOT_FOUND
+        );
+
+        mojang_login(&state, "9132077554").await;
+        assert_eq!(
+            delete(&state, "9132077554", &[("x-admin-token", "root")]).await,
+            StatusCode::NO_CONTENT
+        );
+    }
+
+    #[tokio::test]
+    async fn web_sessions_stay_private_and_gate_deletes() {
+        let state = state();
+        let web = WebPresenceRequest {
+            phone: "9132077554".into(),
+            label: "vortex1".into(),
+            session_token: "phone-session".into(),
+            display_name: "Vortex".into(),
+        };
+        register_web(State(state.clone()), Json(web)).await;
+        let beat = HeartbeatRequest {
+            session_id: "phone-session".into(),
+            state: PresenceState::Idle,
+        };
+        assert_eq!(
+            heartbeat(State(state.clone()), Json(beat)).await,
+            StatusCode::NO_CONTENT
+        );
+
+        let Json(found) = get_presence(State(state.clone()), Path("9132077554".into())).await;
+        let body = serde_json::to_value(&found).unwrap();
+        assert_eq!(body["record"]["state"], "idle");
+        assert!(body["record"]["session_id"].is_string());
+        assert!(!body.to_string().contains("phone-session"));
+
+        // A Mojang login on top keeps the phone session as the key; the
+        // service token alone doesn't stand in for it.
+        mojang_login(&state, "9132077554").await;
+        assert_eq!(
+            delete(&state, "9132077554", &[("x-service-token", "gateway")]).await,
+            StatusCode::FORBIDDEN
+        );
+        let session = ("x-session-token", "phone-session");
+        assert_eq!(
+            delete(&state, "9132077554", &[session]).await,
+            StatusCode::NO_CONTENT
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Validation of show definitions before they are uploaded.
+//!
+//! [`check_show`] runs the rules the gateway's registry applies, then plays
+//! the show through one full loop: how much of the loop each slide is on
+//! screen (holding and fading), and every sampled step where the sky or fog
+//! colour jumps by more than [`MAX_COLOR_STEP`] on a channel. A jump is
+//! usually a slide too short to crossfade, or neighbouring gradients too far
+//! apart for their fade.
+
+use spec::SkyShowConfig;
+use std::collections::HashSet;
+
+use crate::SkyTimeline;
+
+/// Largest slide texture the registry accepts.
+pub const SKY_ASSET_MAX_BYTES: usize = 4 * 1024 * 1024;
+/// Largest change of one colour channel between two samples before it is
+/// reported.
+pub const MAX_COLOR_STEP: u8 = 24;
+/// Samples one check takes at most; longer loops are sampled with a stride.
+pub const MAX_CHECK_SAMPLES: u64 = 1 << 20;
+
+/// The registry's rule for a slide texture.
+pub fn check_asset(mime: &str, size: usize) -> Result<(), String> {
+    if !mime.starts_with("image/") {
+        return Err(format!("slide assets must be image/*, got {mime:?}"));
+    }
+    if size == 0 || size > SKY_ASSET_MAX_BYTES {
+        return Err(format!(
+            "slide assets must be 1..={SKY_ASSET_MAX_BYTES} bytes, got {size}"
+        ));
+    }
+    Ok(())
+}
+
+/// What the show would look like over one loop, and what is wrong with it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ShowReport {
+    pub total_duration_ticks: u64,
+    /// Ticks between samples of the simulated loop.
+    pub stride: u64,
+    /// Registry rules the show breaks; a show with any is refused.
+    pub problems: Vec<String>,
+    pub coverage: Vec<SlideCoverage>,
+    pub discontinuities: Vec<Discontinuity>,
+}
+
+impl ShowReport {
+    pub fn ok(&self) -> bool {
+        self.problems.is_empty() && self.discontinuities.is_empty()
+    }
+}
+
+/// How much of the loop one slide is showing.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SlideCoverage {
+    pub slide: String,
+    /// Sampled ticks the slide was current, holding or fading out.
+    pub ticks: u64,
+    /// Of those, ticks spent crossfading into the next slide.
+    pub fade_ticks: u64,
+    /// `ticks` over the loop, `[0, 1]`.
+    pub share: f32,
+}
+
+/// A colour jump between two consecutive samples.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Discontinuity {
+    /// The sample after the jump.
+    pub tick: u64,
+    pub slide: String,
+    pub next_slide: String,
+    /// Largest channel change of the sky and the fog colour.
+    pub sky_step: u8,
+    pub fog_step: u8,
+}
+
+/// Registry rules for a whole show.
+fn registry_problems(show: &SkyShowConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    if show.slides.is_empty() {
+        problems.push("a show needs at least one slide".to_string());
+    }
+    let mut seen = HashSet::new();
+    for slide in &show.slides {
+        let id = &slide.id;
+        if id.trim().is_empty() {
+            problems.push("slide ids must not be empty".to_string());
+        } else if !seen.insert(id.as_str()) {
+            problems.push(format!("slide {id} appears twice"));
+        }
+        if slide.duration_ticks == 0 {
+            problems.push(format!("slide {id} lasts 0 ticks"));
+        }
+        if let Some(stop) = slide
+            .gradient
+            .iter()
+            .find(|stop| !(0.0..=1.0).contains(&stop.at))
+        {
+            problems.push(format!(
+                "slide {id} has a gradient stop at {} outside 0..=1",
+                stop.at
+            ));
+        }
+        if let Some(asset) = &slide.asset {
+            let size = usize::try_from(asset.size).unwrap_or(usize::MAX);
+            if let Err(why) = check_asset(&asset.mime, size) {
+                problems.push(format!("slide {id}: {why}"));
+            }
+        }
+    }
+    problems
+}
+
+/// Validate `show` and simulate one loop of it.
+pub fn check_show(show: &SkyShowConfig) -> ShowReport {
+    let problems = registry_problems(show);
+    let timeline = SkyTimeline::new(show.clone());
+    let total = timeline.total_duration_ticks();
+    let stride = total.div_ceil(MAX_CHECK_SAMPLES).max(1);
+
+    let mut coverage: Vec<SlideCoverage> = show
+        .slides
+        .iter()
+        .map(|slide| SlideCoverage {
+            slide: slide.id.clone(),
+            ticks: 0,
+            fade_ticks: 0,
+            share: 0.0,
+        })
+        .collect();
+    let mut discontinuities = Vec::new();
+    let mut previous = None;
+    // One sample past the loop, so the wrap back to the first slide is
+    // checked too.
+    for tick in (0..=total).step_by(stride as usize) {
+        let render = timeline.render(tick);
+        if tick < total {
+            if let Some(slide) = coverage.iter_mut().find(|c| c.slide == render.slide) {
+                slide.ticks += stride;
+                if render.blend > 0.0 {
+                    slide.fade_ticks += stride;
+                }
+            }
+        }
+        if let Some((sky, fog)) = previous {
+            let sky_step = channel_step(sky, render.sky.rgb);
+            let fog_step = channel_step(fog, render.fog.rgb);
+            if sky_step > MAX_COLOR_STEP || fog_step > MAX_COLOR_STEP {
+                discontinuities.push(Discontinuity {
+                    tick,
+                    slide: render.slide.clone(),
+                    next_slide: render.next_slide.clone(),
+                    sky_step,
+                    fog_step,
+                });
+            }
+        }
+        previous = Some((render.sky.rgb, render.fog.rgb));
+    }
+    for slide in &mut coverage {
+        slide.share = slide.ticks as f32 / total as f32;
+    }
+
+    ShowReport {
+        total_duration_ticks: total,
+        stride,
+        problems,
+        coverage,
+        discontinuities,
+    }
+}
+
+fn channel_step(a: [u8; 3], b: [u8; 3]) -> u8 {
+    (0..3).map(|i| a[i].abs_diff(b[i])).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec::{SkyAssetRef, SkyGradientStop};
+
+    #[test]
+    fn the_default_show_passes_and_a_hard_cut_is_reported() {
+        let report = check_show(&SkyShowConfig::default_eight());
+        assert!(report.ok(), "{report:?}");
+        assert_eq!(report.coverage.len(), 8);
+        assert!(report
+            .coverage
+            .iter()
+            .all(|c| c.ticks == 888 && c.fade_ticks == 111));
+        assert_eq!(report.coverage[0].share, 0.125);
+
+        let mut show = SkyShowConfig::default_eight();
+        // A white slide of 4 ticks gets a one-tick fade: a hard cut.
+        show.slides[2].duration_ticks = 4;
+        show.slides[2].gradient = vec![SkyGradientStop {
+            at: 0.0,
+            rgb: [255, 255, 255],
+        }];
+        show.slides[5].id = "slide-1".into();
+        show.slides[6].gradient = vec![SkyGradientStop {
+            at: 1.5,
+            rgb: [0, 0, 0],
+        }];
+        show.slides[7].asset = Some(SkyAssetRef {
+            hash: ";∞;sha-less;1;".into(),
+            mime: "text/html".into(),
+            size: 10,
+            url: "/sky/assets/1".into(),
+        });
+        let report = check_show(&show);
+        assert!(!report.ok());
+        assert_eq!(
+            report.problems,
+            [
+                "slide slide-1 appears twice",
+                "slide slide-7 has a gradient stop at 1.5 outside 0..=1",
+                "slide slide-8: slide assets must be image/*, got \"text/html\"",
+            ]
+        );
+        let cut = &report.discontinuities[0];
+        assert_eq!(cut.tick, 2 * 888 + 3);
+        assert_eq!(
+            (cut.slide.as_str(), cut.next_slide.as_str()),
+            ("slide-3", "slide-4")
+        );
+        assert_eq!(report.coverage[2].ticks, 4);
+    }
+}
@@ -0,0 +1,188 @@
+//! Sky and fog colours a Minecraft client can apply as they are.
+//!
+//! A slide's `gradient` runs from zenith to horizon: the zenith end becomes
+//! the sky colour and the horizon end the fog colour, dimmed by how much
+//! daylight the slide's point in the loop gets. Slides without a gradient
+//! take the palette of the time of day instead. Colours crossfade into the
+//! next slide along with the sample's `blend`.
+
+use spec::{SkyGradientStop, SkySlideRef};
+
+use crate::{CelestialPhase, SkyTimeline};
+
+/// Minecraft ticks in a day; time 0 is sunrise, 6000 noon, 18000 midnight.
+pub const MC_DAY_TICKS: u32 = 24_000;
+
+/// Zenith and horizon colours at the middle of night, dawn, day and dusk.
+const PALETTE: [([f32; 3], [f32; 3]); 4] = [
+    ([8.0, 10.0, 32.0], [24.0, 28.0, 64.0]),
+    ([84.0, 110.0, 190.0], [255.0, 160.0, 110.0]),
+    ([100.0, 150.0, 255.0], [192.0, 216.0, 255.0]),
+    ([60.0, 60.0, 140.0], [250.0, 120.0, 80.0]),
+];
+
+/// Darkest a slide's own gradient gets at midnight.
+const NIGHT_BRIGHTNESS: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct McColor {
+    pub rgb: [u8; 3],
+    /// `0xRRGGBB`, as Minecraft packets and `Color.fromRGB` take it.
+    pub packed: u32,
+}
+
+impl McColor {
+    pub fn new(rgb: [u8; 3]) -> Self {
+        let [r, g, b] = rgb.map(u32::from);
+        Self {
+            rgb,
+            packed: r << 16 | g << 8 | b,
+        }
+    }
+
+    fn from_linear(rgb: [f32; 3]) -> Self {
+        Self::new(rgb.map(|c| c.round().clamp(0.0, 255.0) as u8))
+    }
+}
+
+/// What the Paper plugin applies to a world at one tick of its show.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkyRender {
+    pub tick: u64,
+    pub slide: String,
+    pub next_slide: String,
+    pub blend: f32,
+    pub celestial: CelestialPhase,
+    /// 0 at midnight, 1 at noon.
+    pub daylight: f32,
+    /// World time in `[0, 24000)`.
+    pub time_of_day: u32,
+    pub sky: McColor,
+    pub fog: McColor,
+}
+
+impl SkyTimeline {
+    /// Minecraft sky state at `tick`.
+    pub fn render(&self, tick: u64) -> SkyRender {
+        let sample = self.sample(tick);
+        SkyRender {
+            tick,
+            blend: sample.blend,
+            celestial: sample.celestial,
+            daylight: daylight(sample.phase),
+            time_of_day: time_of_day(sample.phase),
+            sky: sample.sky,
+            fog: sample.fog,
+            slide: sample.slide,
+            next_slide: sample.next_slide,
+        }
+    }
+}
+
+/// Sky and fog colours of `slide` at `phase`, crossfaded into `next`'s.
+pub(crate) fn blended_colors(
+    slide: Option<&SkySlideRef>,
+    next: Option<&SkySlideRef>,
+    phase: f32,
+    blend: f32,
+) -> (McColor, McColor) {
+    let daylight = daylight(phase);
+    let (sky, fog) = slide_colors(slide, phase, daylight);
+    let (next_sky, next_fog) = slide_colors(next, phase, daylight);
+    (
+        McColor::from_linear(mix(sky, next_sky, blend)),
+        McColor::from_linear(mix(fog, next_fog, blend)),
+    )
+}
+
+/// Loop position (0 = midnight) as Minecraft world time.
+pub fn time_of_day(phase: f32) -> u32 {
+    let ticks = (phase.rem_euclid(1.0) * MC_DAY_TICKS as f32) as u32;
+    (ticks + MC_DAY_TICKS * 3 / 4) % MC_DAY_TICKS
+}
+
+fn daylight(phase: f32) -> f32 {
+    0.5 - 0.5 * (std::f32::consts::TAU * phase).cos()
+}
+
+fn slide_colors(slide: Option<&SkySlideRef>, phase: f32, daylight: f32) -> ([f32; 3], [f32; 3]) {
+    match slide.map(|slide| slide.gradient.as_slice()) {
+        Some(stops) if !stops.is_empty() => {
+            let brightness = NIGHT_BRIGHTNESS + (1.0 - NIGHT_BRIGHTNESS) * daylight;
+            let dim = |rgb: [f32; 3]| rgb.map(|c| c * brightness);
+            (dim(gradient_at(stops, 0.0)), dim(gradient_at(stops, 1.0)))
+        }
+        _ => palette_at(phase),
+    }
+}
+
+/// Palette colours for `phase`, eased between the middles of the quarters.
+fn palette_at(phase: f32) -> ([f32; 3], [f32; 3]) {
+    let x = (phase * 4.0 - 0.5).rem_euclid(4.0);
+    let i = x as usize % 4;
+    let (from, to) = (PALETTE[i], PALETTE[(i + 1) % 4]);
+    let t = x.fract();
+    (mix(from.0, to.0, t), mix(from.1, to.1, t))
+}
+
+fn gradient_at(stops: &[SkyGradientStop], at: f32) -> [f32; 3] {
+    let rgb = |stop: &SkyGradientStop| stop.rgb.map(f32::from);
+    let mut stops: Vec<&SkyGradientStop> = stops.iter().collect();
+    stops.sort_by(|a, b| a.at.total_cmp(&b.at));
+    let after = stops.iter().position(|stop| stop.at >= at);
+    match after {
+        None => rgb(stops[stops.len() - 1]),
+        Some(0) => rgb(stops[0]),
+        Some(i) => {
+            let (a, b) = (stops[i - 1], stops[i]);
+            let span = (b.at - a.at).max(f32::EPSILON);
+            mix(rgb(a), rgb(b), (at - a.at) / span)
+        }
+    }
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    let t = t.clamp(0.0, 1.0);
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec::SkyShowConfig;
+
+    #[test]
+    fn renders_world_time_and_crossfades_gradients() {
+        let timeline = SkyTimeline::default_eight();
+        let total = timeline.total_duration_ticks();
+        let midnight = timeline.render(0);
+        let noon = timeline.render(total / 2);
+        assert_eq!((midnight.time_of_day, noon.time_of_day), (18_000, 6_000));
+        assert_eq!(midnight.celestial, CelestialPhase::Night);
+        assert!(noon.daylight > 0.99 && midnight.daylight < 0.01);
+        assert!(noon.sky.rgb[2] > midnight.sky.rgb[2]);
+        assert_eq!(McColor::new([0x12, 0x34, 0x56]).packed, 0x123456);
+
+        let mut show = SkyShowConfig::default_eight();
+        show.slides[3].gradient = vec![
+            SkyGradientStop {
+                at: 1.0,
+                rgb: [0, 0, 200],
+            },
+            SkyGradientStop {
+                at: 0.0,
+                rgb: [200, 0, 0],
+            },
+        ];
+        let timeline = SkyTimeline::new(show);
+        // Slide 4 holds just before noon; its last tick has faded into slide 5.
+        let holding = timeline.render(3 * 888);
+        assert_eq!(holding.slide, "slide-4");
+        assert!(holding.sky.rgb[0] > 100 && holding.sky.rgb[2] == 0);
+        assert!(holding.fog.rgb[2] > 100 && holding.fog.rgb[0] == 0);
+        let faded = timeline.render(4 * 888 - 1);
+        assert_eq!(faded.blend, 1.0);
+        let next = timeline.render(4 * 888);
+        assert!((0..3).all(|i| faded.sky.rgb[i].abs_diff(next.sky.rgb[i]) <= 1));
+    }
+}
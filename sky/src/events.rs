@@ -0,0 +1,195 @@
+//! Procedural sky events: auroras and comets drawn over the show.
+//!
+//! Each block height of each planet seeds an [`OmegaRng`]; its first draw
+//! decides whether an event starts during that block, the next ones where in
+//! the block it starts and how long it lasts. Nothing is stored, so every
+//! node and every client that knows the height sees the same events.
+
+use spec::PlanetId;
+
+use crate::AudioBand;
+
+/// Blocks ahead the gateway lists in timeline and status responses.
+pub const SKY_EVENT_LOOKAHEAD: u64 = 64;
+
+/// SplitMix64 over a fixed seed: small, fast and identical on every node.
+#[derive(Debug, Clone)]
+pub struct OmegaRng {
+    state: u64,
+}
+
+impl OmegaRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The stream for `planet` at block `height`.
+    pub fn for_block(planet: PlanetId, height: u64) -> Self {
+        let salt = planet
+            .key()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        Self::new(height.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ salt)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n` (0 when `n` is 0).
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        ((u128::from(self.next_u64()) * u128::from(n)) >> 64) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkyEventKind {
+    Aurora,
+    Comet,
+}
+
+impl SkyEventKind {
+    /// Overlay slide the client draws over the show while the event runs.
+    pub fn overlay_slide(self) -> &'static str {
+        match self {
+            Self::Aurora => "overlay-aurora",
+            Self::Comet => "overlay-comet",
+        }
+    }
+
+    /// Speaker cue played when the event starts (same mode names as
+    /// `omega_speakers`).
+    pub fn stinger(self) -> AudioBand {
+        match self {
+            Self::Aurora => AudioBand {
+                mode: "hum",
+                min_hz: 110.0,
+                max_hz: 440.0,
+            },
+            Self::Comet => AudioBand {
+                mode: "whoosh",
+                min_hz: 520.0,
+                max_hz: 1320.0,
+            },
+        }
+    }
+
+    /// Blocks out of [`EVENT_ROLL`] that start an event of this kind.
+    fn chances(self) -> u64 {
+        match self {
+            Self::Aurora => 3,
+            Self::Comet => 1,
+        }
+    }
+}
+
+/// Each block rolls `0..EVENT_ROLL`: 1 in 48 starts an aurora, 1 in 144 a comet.
+const EVENT_ROLL: u64 = 144;
+/// Aurora length in blocks, `min..=max`.
+const AURORA_BLOCKS: (u64, u64) = (2, 6);
+
+/// An overlay scheduled at a block height. Ticks count the planet's show
+/// from height 0 without wrapping at the end of its loop.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkyEvent {
+    pub kind: SkyEventKind,
+    pub planet: PlanetId,
+    pub height: u64,
+    pub start_tick: u64,
+    pub end_tick: u64,
+    pub overlay_slide: &'static str,
+    pub stinger: AudioBand,
+}
+
+/// The event starting during block `height` on `planet`, if any. The Sun has
+/// no magnetosphere to light, so it only gets comets.
+pub fn sky_event_at(planet: PlanetId, height: u64, ticks_per_block: u64) -> Option<SkyEvent> {
+    let mut rng = OmegaRng::for_block(planet, height);
+    let roll = rng.below(EVENT_ROLL);
+    let mut floor = 0;
+    let kind = [SkyEventKind::Aurora, SkyEventKind::Comet]
+        .into_iter()
+        .filter(|kind| planet != PlanetId::Sun || *kind != SkyEventKind::Aurora)
+        .find(|kind| {
+            floor += kind.chances();
+            roll < floor
+        })?;
+    let block_start = height.saturating_mul(ticks_per_block);
+    let start_tick = block_start.saturating_add(rng.below(ticks_per_block));
+    let length = match kind {
+        SkyEventKind::Aurora => {
+            let (min, max) = AURORA_BLOCKS;
+            (min + rng.below(max - min + 1)).saturating_mul(ticks_per_block)
+        }
+        // A comet crosses in a fraction of a block.
+        SkyEventKind::Comet => (ticks_per_block / 4).max(1) + rng.below(ticks_per_block / 2),
+    };
+    Some(SkyEvent {
+        kind,
+        planet,
+        height,
+        start_tick,
+        end_tick: start_tick.saturating_add(length.max(1)),
+        overlay_slide: kind.overlay_slide(),
+        stinger: kind.stinger(),
+    })
+}
+
+/// Events starting in blocks `from_height..from_height + blocks`, oldest first.
+pub fn upcoming_sky_events(
+    planet: PlanetId,
+    from_height: u64,
+    blocks: u64,
+    ticks_per_block: u64,
+) -> Vec<SkyEvent> {
+    (0..blocks)
+        .map_while(|offset| from_height.checked_add(offset))
+        .filter_map(|height| sky_event_at(planet, height, ticks_per_block))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_rare_deterministic_and_inside_their_block() {
+        let tpb = 8 * 89;
+        let earth = upcoming_sky_events(PlanetId::Earth, 0, 4096, tpb);
+        assert_eq!(earth, upcoming_sky_events(PlanetId::Earth, 0, 4096, tpb));
+        assert_ne!(earth, upcoming_sky_events(PlanetId::Moon, 0, 4096, tpb));
+
+        let count = |kind| earth.iter().filter(|e| e.kind == kind).count();
+        // Expected 4096/48 ≈ 85 auroras and fewer comets.
+        assert!((40..140).contains(&count(SkyEventKind::Aurora)));
+        assert!(count(SkyEventKind::Comet) < count(SkyEventKind::Aurora));
+        for event in &earth {
+            let block = event.height * tpb;
+            assert!((block..block + tpb).contains(&event.start_tick));
+            assert!(event.end_tick > event.start_tick);
+            assert_eq!(event.overlay_slide, event.kind.overlay_slide());
+        }
+
+        let sun = upcoming_sky_events(PlanetId::Sun, 0, 4096, tpb);
+        assert!(sun.iter().all(|e| e.kind == SkyEventKind::Comet));
+        assert!(upcoming_sky_events(PlanetId::Earth, u64::MAX, 8, tpb).len() <= 1);
+    }
+
+    #[test]
+    fn rng_below_stays_in_range() {
+        let mut rng = OmegaRng::new(7);
+        assert!((0..1000).all(|_| rng.below(10) < 10));
+        assert_eq!(rng.below(0), 0);
+        assert_eq!(OmegaRng::new(7).next_u64(), OmegaRng::new(7).next_u64());
+    }
+}
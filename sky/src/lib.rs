@@ -1,6 +1,20 @@
 //! SkyLighting logic for the Ω universe.
 
-use spec::{SkyShowConfig, SkySlideRef};
+mod check;
+mod color;
+mod events;
+
+pub use check::{
+    check_asset, check_show, Discontinuity, ShowReport, SlideCoverage, MAX_COLOR_STEP,
+    SKY_ASSET_MAX_BYTES,
+};
+use color::blended_colors;
+pub use color::{time_of_day, McColor, SkyRender, MC_DAY_TICKS};
+pub use events::{
+    sky_event_at, upcoming_sky_events, OmegaRng, SkyEvent, SkyEventKind, SKY_EVENT_LOOKAHEAD,
+};
+
+use spec::{PlanetId, SkyShowConfig, SkySlideRef};
 
 /// Runtime representation of a looping sky timeline.
 #[derive(Debug, Clone)]
@@ -27,6 +41,10 @@ impl SkyTimeline {
         Self::new(SkyShowConfig::default_eight())
     }
 
+    pub fn for_planet(planet: PlanetId) -> Self {
+        Self::new(SkyShowConfig::for_planet(planet))
+    }
+
     pub fn show(&self) -> &SkyShowConfig {
         &self.show
     }
@@ -45,6 +63,191 @@ impl SkyTimeline {
     pub fn total_duration_ticks(&self) -> u64 {
         self.total_duration_ticks
     }
+
+    /// Where the show stands at `tick`: current slide and how far it has
+    /// played, crossfade toward the next one with the blended colours,
+    /// position in the loop, where the sun and moon are, and the audio band
+    /// that fits it.
+    pub fn sample(&self, tick: u64) -> SkySample {
+        let slides = &self.show.slides;
+        let total = self.total_duration_ticks.max(1);
+        let mut t = tick % total;
+        let mut index = slides.len().saturating_sub(1);
+        for (i, slide) in slides.iter().enumerate() {
+            if t < slide.duration_ticks {
+                index = i;
+                break;
+            }
+            t -= slide.duration_ticks;
+        }
+        let id = |i: usize| slides.get(i).map(|s| s.id.clone()).unwrap_or_default();
+
+        // Crossfade over the last eighth of each slide.
+        let duration = slides
+            .get(index)
+            .map(|s| s.duration_ticks)
+            .unwrap_or(1)
+            .max(1);
+        let fade = (duration / 8).max(1);
+        let fade_start = duration - fade;
+        let blend = if t >= fade_start {
+            (t - fade_start + 1) as f32 / fade as f32
+        } else {
+            0.0
+        };
+
+        let phase = (tick % total) as f32 / total as f32;
+        let celestial = CelestialPhase::at(phase);
+        let next = (index + 1) % slides.len().max(1);
+        let (sky, fog) = blended_colors(slides.get(index), slides.get(next), phase, blend);
+        let sun = sun_direction(phase);
+        SkySample {
+            tick,
+            slide: id(index),
+            next_slide: id(next),
+            progress: t.min(duration - 1) as f32 / duration as f32,
+            blend,
+            phase,
+            celestial,
+            sky,
+            fog,
+            sun,
+            moon: sun.map(|c| -c),
+            audio: celestial.audio_band(blend),
+        }
+    }
+
+    /// `sample` every `stride` ticks across `[from_tick, from_tick + ticks)`.
+    /// Ticks past `u64::MAX` carry on from the same point of the loop.
+    pub fn preview(&self, from_tick: u64, ticks: u64, stride: u64) -> Vec<SkySample> {
+        let stride = stride.max(1);
+        let total = self.total_duration_ticks.max(1);
+        (0..ticks.div_ceil(stride))
+            .map(|i| {
+                // `i * stride` stays below `ticks`; only the offset can overflow.
+                let offset = i * stride;
+                let tick = from_tick.checked_add(offset).unwrap_or_else(|| {
+                    let wrapped = (from_tick % total) as u128 + (offset % total) as u128;
+                    (wrapped % total as u128) as u64
+                });
+                self.sample(tick)
+            })
+            .collect()
+    }
+}
+
+/// Most samples one preview returns; larger spans get a wider stride.
+pub const MAX_PREVIEW_SAMPLES: u64 = 2048;
+
+/// A sampled timeline segment, as `/sky/shows/:id/preview` serves it to the
+/// dashboard.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkyPreview {
+    pub show: String,
+    pub from_tick: u64,
+    pub ticks: u64,
+    pub stride: u64,
+    pub total_duration_ticks: u64,
+    pub samples: Vec<SkySample>,
+}
+
+impl SkyPreview {
+    /// `ticks` defaults to one loop; `stride` is widened so the preview
+    /// never exceeds [`MAX_PREVIEW_SAMPLES`].
+    pub fn new(
+        show: String,
+        timeline: &SkyTimeline,
+        from_tick: u64,
+        ticks: Option<u64>,
+        stride: u64,
+    ) -> Self {
+        let ticks = ticks.unwrap_or(timeline.total_duration_ticks());
+        let stride = stride.max(ticks.div_ceil(MAX_PREVIEW_SAMPLES)).max(1);
+        Self {
+            show,
+            from_tick,
+            ticks,
+            stride,
+            total_duration_ticks: timeline.total_duration_ticks(),
+            samples: timeline.preview(from_tick, ticks, stride),
+        }
+    }
+}
+
+/// Unit vector toward the sun at `phase` (+X east, +Y up): below the
+/// horizon at midnight, rising in the east at dawn, overhead at noon. The
+/// moon is always opposite.
+fn sun_direction(phase: f32) -> [f32; 3] {
+    let angle = std::f32::consts::TAU * phase.rem_euclid(1.0);
+    [angle.sin(), -angle.cos(), 0.0]
+}
+
+/// Quarter of the show loop, read as time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CelestialPhase {
+    Night,
+    Dawn,
+    Day,
+    Dusk,
+}
+
+impl CelestialPhase {
+    /// `phase` in `[0, 1)`, starting at midnight.
+    pub fn at(phase: f32) -> Self {
+        match (phase.rem_euclid(1.0) * 4.0) as u8 {
+            0 => Self::Night,
+            1 => Self::Dawn,
+            2 => Self::Day,
+            _ => Self::Dusk,
+        }
+    }
+
+    /// Speaker mode and band for this phase, nudged upward while a slide
+    /// crossfades (same mode names as `omega_speakers`).
+    pub fn audio_band(self, blend: f32) -> AudioBand {
+        let (mode, min_hz, span_hz) = match self {
+            Self::Night => ("hum", 90.0, 240.0),
+            Self::Dawn => ("ring", 360.0, 520.0),
+            Self::Day => ("whoosh_rail", 180.0, 420.0),
+            Self::Dusk => ("whoosh", 240.0, 360.0),
+        };
+        let min_hz = min_hz * (1.0 + 0.25 * blend.clamp(0.0, 1.0));
+        AudioBand {
+            mode,
+            min_hz,
+            max_hz: min_hz + span_hz,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AudioBand {
+    pub mode: &'static str,
+    pub min_hz: f32,
+    pub max_hz: f32,
+}
+
+/// One point of a timeline preview.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkySample {
+    pub tick: u64,
+    pub slide: String,
+    pub next_slide: String,
+    /// How far `slide` has played, `[0, 1)`.
+    pub progress: f32,
+    /// 0 while a slide holds, rising to 1 as it fades into `next_slide`.
+    pub blend: f32,
+    /// Position in the show loop, `[0, 1)`.
+    pub phase: f32,
+    pub celestial: CelestialPhase,
+    /// Sky and fog colours, crossfaded into `next_slide`'s by `blend`.
+    pub sky: McColor,
+    pub fog: McColor,
+    /// Unit vectors toward the sun and the moon, +X east and +Y up.
+    pub sun: [f32; 3],
+    pub moon: [f32; 3],
+    pub audio: AudioBand,
 }
 
 /// A tiny "ray" placeholder, matching the mental model from RayTraceEngine.
@@ -64,3 +267,59 @@ impl SkyRay {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_crossfades_into_the_next_slide() {
+        let timeline = SkyTimeline::default_eight();
+        let samples = timeline.preview(770, 130, 8);
+        assert_eq!(samples.len(), 17);
+        assert_eq!(samples[0].slide, "slide-1");
+        assert_eq!(samples[0].blend, 0.0);
+
+        let fading = &samples[13]; // tick 874, 97 ticks into the 111-tick fade
+        assert_eq!(fading.next_slide, "slide-2");
+        assert!(fading.blend > 0.8 && fading.blend < 1.0);
+        assert!(fading.audio.min_hz > timeline.sample(0).audio.min_hz);
+        assert_eq!(samples[15].slide, "slide-2"); // tick 890
+
+        let last = timeline.sample(timeline.total_duration_ticks() - 1);
+        assert_eq!((last.next_slide.as_str(), last.blend), ("slide-1", 1.0));
+        assert_eq!(last.celestial, CelestialPhase::Dusk);
+    }
+
+    #[test]
+    fn samples_move_the_sun_and_blend_colours_across_slides() {
+        let timeline = SkyTimeline::default_eight();
+        let total = timeline.total_duration_ticks();
+        let near = |a: [f32; 3], b: [f32; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-3);
+        assert!(near(timeline.sample(0).sun, [0.0, -1.0, 0.0]));
+        assert!(near(timeline.sample(total / 4).sun, [1.0, 0.0, 0.0]));
+        let noon = timeline.sample(total / 2);
+        assert!(near(noon.sun, [0.0, 1.0, 0.0]));
+        assert!(near(noon.moon, [0.0, -1.0, 0.0]));
+
+        let start = timeline.sample(888);
+        assert_eq!((start.slide.as_str(), start.progress), ("slide-2", 0.0));
+        assert_eq!(timeline.sample(888 + 444).progress, 0.5);
+        let faded = timeline.sample(887);
+        assert_eq!(faded.blend, 1.0);
+        assert!((0..3).all(|i| faded.sky.rgb[i].abs_diff(start.sky.rgb[i]) <= 1));
+        assert_eq!(timeline.render(887).sky, faded.sky);
+    }
+
+    #[test]
+    fn preview_near_the_end_of_time_wraps_within_the_loop() {
+        let timeline = SkyTimeline::default_eight();
+        let total = timeline.total_duration_ticks();
+        let samples = timeline.preview(u64::MAX - 3, 16, 4);
+        assert_eq!(samples.len(), 4);
+        assert_eq!(samples[0].tick, u64::MAX - 3);
+        let expected = (u64::MAX % total + 1) % total;
+        assert_eq!(samples[1].slide, timeline.sample(expected).slide);
+        assert!(samples.iter().all(|s| s.phase < 1.0));
+    }
+}
@@ -0,0 +1,138 @@
+//! `dlog-sky`: check a sky show before it is uploaded.
+//!
+//! ```text
+//! dlog-sky check <show.json> [--preview <out.json>] [--stride <ticks>]
+//! ```
+//!
+//! The file is a show definition as the timeline routes serve it
+//! (`{"slides": [...]}`). The report lists the registry rules it breaks,
+//! each slide's share of the loop and the colour jumps found playing it
+//! through once. `--preview` also writes the dashboard's preview JSON for one
+//! loop (`-` for stdout), sampled every `--stride` ticks (default 8). Exits 1
+//! when the show has problems or discontinuities, 2 when it can't be read.
+
+use dlog_sky::{check_show, ShowReport, SkyPreview, SkyTimeline, MAX_COLOR_STEP};
+use spec::SkyShowConfig;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: dlog-sky check <show.json> [--preview <out.json>] [--stride <ticks>]";
+
+struct CheckArgs {
+    file: String,
+    preview: Option<String>,
+    stride: u64,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<CheckArgs, String> {
+    if args.next().as_deref() != Some("check") {
+        return Err(USAGE.to_string());
+    }
+    let file = args.next().ok_or(USAGE)?;
+    let mut check = CheckArgs {
+        file,
+        preview: None,
+        stride: 8,
+    };
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        match flag.as_str() {
+            "--preview" => check.preview = Some(value),
+            "--stride" => match value.parse() {
+                Ok(stride) if stride > 0 => check.stride = stride,
+                _ => {
+                    return Err(format!(
+                        "--stride must be a positive tick count, got {value}"
+                    ))
+                }
+            },
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    Ok(check)
+}
+
+fn print_report(report: &ShowReport) {
+    println!(
+        "loop: {} ticks, sampled every {}",
+        report.total_duration_ticks, report.stride
+    );
+    println!(
+        "{:<24} {:>10} {:>10} {:>7}",
+        "SLIDE", "TICKS", "FADE", "SHARE"
+    );
+    for slide in &report.coverage {
+        println!(
+            "{:<24} {:>10} {:>10} {:>6.2}%",
+            slide.slide,
+            slide.ticks,
+            slide.fade_ticks,
+            slide.share * 100.0
+        );
+    }
+    for problem in &report.problems {
+        println!("problem: {problem}");
+    }
+    for jump in &report.discontinuities {
+        println!(
+            "discontinuity at tick {} ({} -> {}): sky {}, fog {} (max {MAX_COLOR_STEP})",
+            jump.tick, jump.slide, jump.next_slide, jump.sky_step, jump.fog_step
+        );
+    }
+    if report.ok() {
+        println!("ok");
+    }
+}
+
+fn write_preview(show: SkyShowConfig, check: &CheckArgs, out: &str) -> Result<(), String> {
+    let name = Path::new(&check.file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let timeline = SkyTimeline::new(show);
+    let preview = SkyPreview::new(name, &timeline, 0, None, check.stride);
+    let json = serde_json::to_string_pretty(&preview).map_err(|err| err.to_string())?;
+    if out == "-" {
+        println!("{json}");
+        return Ok(());
+    }
+    std::fs::write(out, json).map_err(|err| format!("writing {out}: {err}"))
+}
+
+fn main() -> ExitCode {
+    let check = match parse_args(std::env::args().skip(1)) {
+        Ok(check) => check,
+        Err(why) => {
+            eprintln!("{why}");
+            return ExitCode::from(2);
+        }
+    };
+    let show: SkyShowConfig = match std::fs::read_to_string(&check.file)
+        .map_err(|err| err.to_string())
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|err| err.to_string()))
+    {
+        Ok(show) => show,
+        Err(why) => {
+            eprintln!("{}: {why}", check.file);
+            return ExitCode::from(2);
+        }
+    };
+
+    let report = check_show(&show);
+    // A preview on stdout would be corrupted by the report, so only the
+    // exit code tells.
+    if check.preview.as_deref() != Some("-") {
+        print_report(&report);
+    }
+    if let Some(out) = &check.preview {
+        if let Err(why) = write_preview(show, &check, out) {
+            eprintln!("{why}");
+            return ExitCode::from(2);
+        }
+    }
+    if report.ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
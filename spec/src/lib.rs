@@ -1,5 +1,152 @@
-// Ω: identifier for which planet/realm this monetary binding is attached.
-pub type PlanetId = String;
+// Ω: identifier for which planet/realm a session, ledger, or sim is attached to.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Hash,
+    Ord,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanetId {
+    #[default]
+    Earth,
+    Moon,
+    Mars,
+    Sun,
+}
+
+impl PlanetId {
+    pub const ALL: [PlanetId; 4] = [
+        PlanetId::Earth,
+        PlanetId::Moon,
+        PlanetId::Mars,
+        PlanetId::Sun,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            PlanetId::Earth => "earth",
+            PlanetId::Moon => "moon",
+            PlanetId::Mars => "mars",
+            PlanetId::Sun => "sun",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.key().eq_ignore_ascii_case(raw.trim()))
+    }
+
+    /// Planet owning a world name such as `moon_shell` or `earth_core`.
+    pub fn from_world(world: &str) -> Option<Self> {
+        Self::parse(world.split('_').next().unwrap_or(world))
+    }
+
+    pub fn profile(self) -> &'static PlanetGravityProfile {
+        PlanetGravityProfile::by_key(self.key()).expect("every PlanetId has a profile")
+    }
+
+    /// The planet's currency zone: its unit, worth and monetary policy.
+    pub fn zone(self) -> CurrencyZone {
+        let (unit, phi_rank, holder_interest_apy) = match self {
+            PlanetId::Earth => ("DLOG", 0, 0.618),
+            PlanetId::Moon => ("LUNA", -1, 1.0),
+            PlanetId::Mars => ("ARES", 1, 0.382),
+            PlanetId::Sun => ("SOL", 2, 0.236),
+        };
+        CurrencyZone {
+            planet: self,
+            unit,
+            phi_rank,
+            monetary: MonetarySpec {
+                holder_interest_apy,
+                ..MonetarySpec::default()
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for PlanetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+// Ω: what a session may do once handshaken. Clients request these by key;
+// services check them before touching ledgers, mining, or admin surfaces.
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Render,
+    Input,
+    Audio,
+    Game,
+    Dns,
+    Banking,
+    Mining,
+    Admin,
+    /// Gateway-to-gateway consensus traffic (`BLOCK` frames).
+    Peer,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [
+        Capability::Render,
+        Capability::Input,
+        Capability::Audio,
+        Capability::Game,
+        Capability::Dns,
+        Capability::Banking,
+        Capability::Mining,
+        Capability::Admin,
+        Capability::Peer,
+    ];
+
+    /// Granted when a handshake asks for nothing: everything but ledgers,
+    /// mining, admin, and peer traffic.
+    pub const DEFAULT: [Capability; 5] = [
+        Capability::Render,
+        Capability::Input,
+        Capability::Audio,
+        Capability::Game,
+        Capability::Dns,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Capability::Render => "render",
+            Capability::Input => "input",
+            Capability::Audio => "audio",
+            Capability::Game => "game",
+            Capability::Dns => "dns",
+            Capability::Banking => "banking",
+            Capability::Mining => "mining",
+            Capability::Admin => "admin",
+            Capability::Peer => "peer",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.key().eq_ignore_ascii_case(raw.trim()))
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.key())
+    }
+}
 
 //
 // === Ω auto: LabelId + MonetarySpec (do not edit by hand) ===================
@@ -27,6 +174,9 @@ pub struct MonetarySpec {
     pub holder_interest_apy: f64,
     /// Target block interval in NPC seconds (~8s, but Ω-side it's "one tick").
     pub target_block_seconds: f64,
+    /// How often holder interest folds into balances.
+    #[serde(default)]
+    pub compounding: CompoundingMode,
 }
 
 impl Default for MonetarySpec {
@@ -35,12 +185,88 @@ impl Default for MonetarySpec {
             miner_inflation_apy: 0.088248,
             holder_interest_apy: 0.618,
             target_block_seconds: 8.0,
+            compounding: CompoundingMode::PerBlock,
+        }
+    }
+}
+
+/// When holder interest lands. Every mode pays the same nominal rate per
+/// block; they differ in how often it compounds, so per-8-block pays a little
+/// less than per-block and continuous a little more.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompoundingMode {
+    /// Each block's interest compounds into the next.
+    #[default]
+    PerBlock,
+    /// Eight blocks of simple interest land at heights divisible by 8.
+    #[serde(rename = "per_8_blocks")]
+    PerEightBlocks,
+    /// The per-block rate compounds continuously within each block.
+    Continuous,
+}
+
+impl CompoundingMode {
+    pub const ALL: [CompoundingMode; 3] = [
+        CompoundingMode::PerBlock,
+        CompoundingMode::PerEightBlocks,
+        CompoundingMode::Continuous,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            CompoundingMode::PerBlock => "per_block",
+            CompoundingMode::PerEightBlocks => "per_8_blocks",
+            CompoundingMode::Continuous => "continuous",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.key().eq_ignore_ascii_case(raw.trim()))
+    }
+
+    /// Blocks between interest payments.
+    pub fn period_blocks(self) -> u64 {
+        match self {
+            CompoundingMode::PerEightBlocks => 8,
+            CompoundingMode::PerBlock | CompoundingMode::Continuous => 1,
         }
     }
 }
 
 // === Ω auto end: LabelId + MonetarySpec =====================================
 
+/// A zone's monetary parameters from one block height on, with the
+/// governance record behind the change (`/v1/spec/monetary/history`).
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct MonetaryChange {
+    /// First height whose interest is paid at the new rates.
+    pub effective_height: u64,
+    pub planet: PlanetId,
+    /// `compounding` is chain-wide and not scheduled per zone.
+    pub monetary: MonetarySpec,
+    pub reason: String,
+    /// Label keys (`;phone;label;`) of whoever approved it.
+    pub approvers: Vec<String>,
+    /// Wall clock when it was scheduled.
+    #[serde(default)]
+    pub scheduled_ms: i64,
+}
+
+// Ω: each planet runs its own economy. Ledgers count in the zone's unit, and
+// one unit is worth φ^phi_rank Earth units, so rates between zones are powers
+// of φ. Blocks are shared, so every zone keeps the same block interval.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct CurrencyZone {
+    pub planet: PlanetId,
+    /// Ticker shown next to balances, e.g. "DLOG" on Earth.
+    pub unit: &'static str,
+    pub phi_rank: i32,
+    pub monetary: MonetarySpec,
+}
+
 //
 // Ω-Physics planetary gravity profile + φ constant.
 // This is deliberately minimal and can be extended later without
@@ -62,6 +288,32 @@ pub struct PlanetGravityProfile {
     pub shell_radius_m: f64,
     /// Approx core radius in meters for the inner hollow-sphere boundary.
     pub core_radius_m: f64,
+    /// φ exponent shaping movement feel (Earth 1.0 baseline, Moon lighter, Sun heavier).
+    pub phi_exp: f64,
+}
+
+impl PlanetGravityProfile {
+    pub fn by_key(key: &str) -> Option<&'static PlanetGravityProfile> {
+        PLANET_PROFILES
+            .iter()
+            .find(|p| p.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Gravity magnitude (m/s²) at `radius_m` from the center, treating the body
+    /// as a uniform hollow shell between core and shell radius: zero inside the
+    /// cavity, enclosed-mass fraction within the shell, inverse-square outside.
+    pub fn gravity_at(&self, radius_m: f64) -> f64 {
+        let (inner, outer) = (self.core_radius_m, self.shell_radius_m);
+        if radius_m <= inner || radius_m <= 0.0 {
+            return 0.0;
+        }
+        let surface_gm = self.surface_gravity_mps2 * outer * outer;
+        if radius_m >= outer {
+            return surface_gm / (radius_m * radius_m);
+        }
+        let enclosed = (radius_m.powi(3) - inner.powi(3)) / (outer.powi(3) - inner.powi(3));
+        surface_gm * enclosed / (radius_m * radius_m)
+    }
 }
 
 /// Minimal Ω planet table for the API layer.
@@ -72,24 +324,28 @@ pub const PLANET_PROFILES: &[PlanetGravityProfile] = &[
         surface_gravity_mps2: 274.0,
         shell_radius_m: 695_700_000.0,
         core_radius_m: 0.2 * 695_700_000.0,
+        phi_exp: 1.3,
     },
     PlanetGravityProfile {
         key: "earth",
         surface_gravity_mps2: 9.806_65,
         shell_radius_m: 6_371_000.0,
         core_radius_m: 3_500_000.0,
+        phi_exp: 1.0,
     },
     PlanetGravityProfile {
         key: "moon",
         surface_gravity_mps2: 1.62,
         shell_radius_m: 1_737_100.0,
         core_radius_m: 1_000_000.0,
+        phi_exp: 0.5,
     },
     PlanetGravityProfile {
         key: "mars",
         surface_gravity_mps2: 3.711,
         shell_radius_m: 3_389_500.0,
         core_radius_m: 1_800_000.0,
+        phi_exp: 0.8,
     },
 ];
 
@@ -101,6 +357,30 @@ pub const PLANET_PROFILES: &[PlanetGravityProfile] = &[
 pub struct SkySlideRef {
     pub id: String,
     pub duration_ticks: u64,
+    /// Registered texture for the slide, once there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<SkyAssetRef>,
+    /// Sky colours from zenith (`at` 0) to horizon (`at` 1). Slides without
+    /// one are coloured by the time of day they fall on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gradient: Vec<SkyGradientStop>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SkyGradientStop {
+    pub at: f32,
+    pub rgb: [u8; 3],
+}
+
+/// Content-addressed slide texture. `hash` is the sha-less digest of the
+/// bytes, so a client can check that what it fetched from `url` is what
+/// every other client is showing.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SkyAssetRef {
+    pub hash: String,
+    pub mime: String,
+    pub size: u64,
+    pub url: String,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -116,10 +396,24 @@ impl SkyShowConfig {
             slides.push(SkySlideRef {
                 id: format!("slide-{}", i + 1),
                 duration_ticks: 888,
+                asset: None,
+                gradient: Vec::new(),
             });
         }
         SkyShowConfig { slides }
     }
+
+    /// Per-planet show: Earth keeps the canonical eight slides, other planets
+    /// get their own slide ids with the same rhythm.
+    pub fn for_planet(planet: PlanetId) -> Self {
+        let mut show = Self::default_eight();
+        if planet != PlanetId::Earth {
+            for slide in &mut show.slides {
+                slide.id = format!("{planet}-{}", slide.id);
+            }
+        }
+        show
+    }
 }
 
 //
@@ -143,7 +437,7 @@ pub struct Pose {
 }
 
 /// Raw input flags from the client.
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, Default)]
 pub struct InputState {
     pub forward: bool,
     pub back: bool,
@@ -153,6 +447,38 @@ pub struct InputState {
     pub sneak: bool,
 }
 
+/// Sections of a [`SimView`] a client wants, as bits: anchors 1,
+/// entities 2, barriers 4, ui 8. Requests without a mask get every section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ViewMask(pub u8);
+
+impl ViewMask {
+    pub const ANCHORS: Self = Self(1);
+    pub const ENTITIES: Self = Self(1 << 1);
+    pub const BARRIERS: Self = Self(1 << 2);
+    pub const UI: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    pub fn contains(self, section: Self) -> bool {
+        self.0 & section.0 == section.0
+    }
+}
+
+impl Default for ViewMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::ops::BitOr for ViewMask {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
 /// Request from Paper client to the Ω sim endpoint.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
 pub struct SimTickRequest {
@@ -163,6 +489,16 @@ pub struct SimTickRequest {
     pub inputs: InputState,
     #[serde(default)]
     pub client_time_ms: Option<u64>,
+    /// Client's estimate of server clock minus its own, from `/v1/sim/clock`.
+    /// With `client_time_ms` it places the inputs on the server clock.
+    #[serde(default)]
+    pub clock_offset_ms: Option<i64>,
+    /// Planet whose sim state this tick belongs to.
+    #[serde(default)]
+    pub planet: PlanetId,
+    /// View sections to send back; the rest come back empty.
+    #[serde(default)]
+    pub view_mask: ViewMask,
 }
 
 /// One logical render anchor (e.g., origin, planets).
@@ -197,6 +533,16 @@ pub struct UiOverlay {
     pub title: String,
     #[serde(default)]
     pub hotbar: Vec<String>,
+    /// Counters to pin on the HUD (e.g. unread notifications).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub badges: Vec<UiBadge>,
+}
+
+/// A counter badge on the HUD.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Default, PartialEq, Eq)]
+pub struct UiBadge {
+    pub kind: String,
+    pub count: u32,
 }
 
 /// View slice returned to the client.
@@ -218,5 +564,122 @@ pub struct SimTickResponse {
     pub tick: u64,
     pub state_version: String,
     pub server_time_ms: u64,
+    /// Server time the tick's inputs were taken to have happened at.
+    #[serde(default)]
+    pub input_time_ms: u64,
     pub view: SimView,
 }
+
+/// Header carrying the signature of a signed response: `<kid>.<sig>`, the
+/// id of the signing key and the unpadded base64url Ed25519 signature of the
+/// body's SHA-256 digest.
+pub const SIGNATURE_HEADER: &str = "x-omega-signature";
+
+/// One public key responses may be signed with.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct ResponseKey {
+    /// Hex of the first 8 bytes of the key's SHA-256.
+    pub kid: String,
+    /// The 32-byte Ed25519 public key, unpadded base64url.
+    pub public_key: String,
+    /// Signs new responses; the rest are retired but still accepted.
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// Published keys (`GET /v1/sim/keys`); empty while responses are unsigned.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Default)]
+pub struct ResponseKeySet {
+    #[serde(default)]
+    pub keys: Vec<ResponseKey>,
+}
+
+impl ResponseKeySet {
+    pub fn get(&self, kid: &str) -> Option<&ResponseKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+/// Client half of a φ-NTP clock exchange (`POST /v1/sim/clock`).
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, Default)]
+pub struct ClockSyncRequest {
+    /// Client clock when the request was sent (t0).
+    pub client_send_ms: u64,
+}
+
+/// Server half of a clock exchange: t0 echoed, plus when the server got the
+/// request (t1) and sent the reply (t2), on the server clock.
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, Default)]
+pub struct ClockSyncResponse {
+    pub client_send_ms: u64,
+    pub server_receive_ms: u64,
+    pub server_transmit_ms: u64,
+}
+
+/// Offset and round trip measured by one clock exchange.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ClockSample {
+    /// Server clock minus client clock.
+    pub offset_ms: i64,
+    /// Round trip, less the time the server held the request.
+    pub rtt_ms: u64,
+}
+
+impl ClockSyncResponse {
+    /// NTP arithmetic, given the client clock when the reply arrived (t3).
+    pub fn sample(&self, client_receive_ms: u64) -> ClockSample {
+        let (t0, t1, t2, t3) = (
+            self.client_send_ms as i64,
+            self.server_receive_ms as i64,
+            self.server_transmit_ms as i64,
+            client_receive_ms as i64,
+        );
+        ClockSample {
+            offset_ms: ((t1 - t0) + (t2 - t3)) / 2,
+            rtt_ms: ((t3 - t0) - (t2 - t1)).max(0) as u64,
+        }
+    }
+}
+
+/// Samples a client keeps when estimating its clock offset.
+pub const CLOCK_SYNC_WINDOW: usize = 8;
+
+/// Client-side φ-NTP estimator. The lowest-RTT sample in the window is the
+/// least skewed by queueing; the estimate moves 1/φ of the way towards it on
+/// each exchange, so one lucky or unlucky sample can't jerk the sim clock.
+#[derive(Clone, Debug, Default)]
+pub struct ClockSync {
+    samples: std::collections::VecDeque<ClockSample>,
+    offset_ms: Option<f64>,
+}
+
+impl ClockSync {
+    /// Fold in a reply that arrived at `client_receive_ms`; returns its sample.
+    pub fn record(&mut self, resp: &ClockSyncResponse, client_receive_ms: u64) -> ClockSample {
+        let sample = resp.sample(client_receive_ms);
+        if self.samples.len() == CLOCK_SYNC_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        let best = self
+            .samples
+            .iter()
+            .min_by_key(|s| s.rtt_ms)
+            .map_or(sample.offset_ms, |s| s.offset_ms) as f64;
+        self.offset_ms = Some(match self.offset_ms {
+            Some(current) => current + (best - current) / PHI,
+            None => best,
+        });
+        sample
+    }
+
+    /// Server clock minus client clock, once at least one exchange is in.
+    pub fn offset_ms(&self) -> Option<i64> {
+        self.offset_ms.map(|o| o.round() as i64)
+    }
+
+    /// Lowest round trip in the window.
+    pub fn rtt_ms(&self) -> Option<u64> {
+        self.samples.iter().map(|s| s.rtt_ms).min()
+    }
+}